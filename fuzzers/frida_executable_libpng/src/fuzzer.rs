@@ -39,7 +39,7 @@ use libafl_bolts::{
 #[cfg(unix)]
 use libafl_frida::asan::{
     asan_rt::AsanRuntime,
-    errors::{AsanErrorsFeedback, AsanErrorsObserver, ASAN_ERRORS},
+    errors::{AsanErrorsFeedback, AsanErrorsObserver},
 };
 use libafl_frida::{
     cmplog_rt::CmpLogRuntime,
@@ -186,7 +186,7 @@ unsafe fn fuzz(
                 let observers = tuple_list!(
                     edges_observer,
                     time_observer,
-                    AsanErrorsObserver::new(&ASAN_ERRORS)
+                    AsanErrorsObserver::thread_local()
                 );
                 #[cfg(windows)]
                 let observers = tuple_list!(edges_observer, time_observer);
@@ -301,7 +301,7 @@ unsafe fn fuzz(
                 let observers = tuple_list!(
                     edges_observer,
                     time_observer,
-                    AsanErrorsObserver::new(&ASAN_ERRORS)
+                    AsanErrorsObserver::thread_local()
                 );
                 #[cfg(windows)]
                 let observers = tuple_list!(edges_observer, time_observer,);
@@ -431,7 +431,7 @@ unsafe fn fuzz(
                 let observers = tuple_list!(
                     edges_observer,
                     time_observer,
-                    AsanErrorsObserver::new(&ASAN_ERRORS)
+                    AsanErrorsObserver::thread_local()
                 );
                 #[cfg(windows)]
                 let observers = tuple_list!(edges_observer, time_observer,);
@@ -45,7 +45,7 @@ use libafl_bolts::{
 #[cfg(unix)]
 use libafl_frida::asan::asan_rt::AsanRuntime;
 #[cfg(unix)]
-use libafl_frida::asan::errors::{AsanErrorsFeedback, AsanErrorsObserver, ASAN_ERRORS};
+use libafl_frida::asan::errors::{AsanErrorsFeedback, AsanErrorsObserver};
 use libafl_frida::{
     cmplog_rt::CmpLogRuntime,
     coverage_rt::{CoverageRuntime, MAP_SIZE},
@@ -180,7 +180,7 @@ unsafe fn fuzz(options: &FuzzerOptions) -> Result<(), Error> {
                 let observers = tuple_list!(
                     edges_observer,
                     time_observer,
-                    AsanErrorsObserver::new(&ASAN_ERRORS)
+                    AsanErrorsObserver::thread_local()
                 );
                 #[cfg(windows)]
                 let observers = tuple_list!(edges_observer, time_observer);
@@ -295,7 +295,7 @@ unsafe fn fuzz(options: &FuzzerOptions) -> Result<(), Error> {
                 let observers = tuple_list!(
                     edges_observer,
                     time_observer,
-                    AsanErrorsObserver::new(&ASAN_ERRORS)
+                    AsanErrorsObserver::thread_local()
                 );
                 #[cfg(windows)]
                 let observers = tuple_list!(edges_observer, time_observer,);
@@ -426,7 +426,7 @@ unsafe fn fuzz(options: &FuzzerOptions) -> Result<(), Error> {
                 let observers = tuple_list!(
                     edges_observer,
                     time_observer,
-                    AsanErrorsObserver::new(&ASAN_ERRORS)
+                    AsanErrorsObserver::thread_local()
                 );
                 #[cfg(windows)]
                 let observers = tuple_list!(edges_observer, time_observer,);
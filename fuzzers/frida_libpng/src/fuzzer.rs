@@ -23,14 +23,13 @@ use libafl::{
     observers::{HitcountsMapObserver, StdMapObserver, TimeObserver},
     schedulers::{IndexesLenTimeMinimizerScheduler, QueueScheduler},
     stages::{ShadowTracingStage, StdMutationalStage},
-    state::{HasCorpus, HasMetadata, StdState},
+    state::{HasCorpus, HasMetadata, HasRngSeed, StdState},
     Error,
 };
 #[cfg(unix)]
 use libafl::{feedback_and_fast, feedbacks::ConstFeedback};
 use libafl_bolts::{
     cli::{parse_args, FuzzerOptions},
-    current_nanos,
     rands::StdRand,
     shmem::{ShMemProvider, StdShMemProvider},
     tuples::{tuple_list, Merge},
@@ -39,7 +38,7 @@ use libafl_bolts::{
 #[cfg(unix)]
 use libafl_frida::asan::{
     asan_rt::AsanRuntime,
-    errors::{AsanErrorsFeedback, AsanErrorsObserver, ASAN_ERRORS},
+    errors::{AsanErrorsFeedback, AsanErrorsObserver},
 };
 use libafl_frida::{
     cmplog_rt::CmpLogRuntime,
@@ -135,9 +134,10 @@ unsafe fn fuzz(options: &FuzzerOptions) -> Result<(), Error> {
 
                 // If not restarting, create a State from scratch
                 let mut state = state.unwrap_or_else(|| {
-                    StdState::new(
-                        // RNG
-                        StdRand::with_seed(current_nanos()),
+                    let seed = options.rng_seed();
+                    let mut state = StdState::new(
+                        // RNG, seeded with the `--replay-seed`, if given
+                        StdRand::with_seed(seed),
                         // Corpus that will be evolved, we keep it in memory for performance
                         CachedOnDiskCorpus::no_meta(PathBuf::from("./corpus_discovered"), 64)
                             .unwrap(),
@@ -147,7 +147,10 @@ unsafe fn fuzz(options: &FuzzerOptions) -> Result<(), Error> {
                         &mut feedback,
                         &mut objective,
                     )
-                    .unwrap()
+                    .unwrap();
+                    // Keep the seed, to replay this run with `--replay-seed`
+                    state.set_rng_seed(seed);
+                    state
                 });
 
                 println!("We're a client, let's fuzz :)");
@@ -176,7 +179,7 @@ unsafe fn fuzz(options: &FuzzerOptions) -> Result<(), Error> {
                 let observers = tuple_list!(
                     edges_observer,
                     time_observer,
-                    AsanErrorsObserver::new(&ASAN_ERRORS)
+                    AsanErrorsObserver::thread_local()
                 );
                 #[cfg(windows)]
                 let observers = tuple_list!(edges_observer, time_observer);
@@ -251,9 +254,10 @@ unsafe fn fuzz(options: &FuzzerOptions) -> Result<(), Error> {
 
                 // If not restarting, create a State from scratch
                 let mut state = state.unwrap_or_else(|| {
-                    StdState::new(
-                        // RNG
-                        StdRand::with_seed(current_nanos()),
+                    let seed = options.rng_seed();
+                    let mut state = StdState::new(
+                        // RNG, seeded with the `--replay-seed`, if given
+                        StdRand::with_seed(seed),
                         // Corpus that will be evolved, we keep it in memory for performance
                         CachedOnDiskCorpus::no_meta(PathBuf::from("./corpus_discovered"), 64)
                             .unwrap(),
@@ -263,7 +267,10 @@ unsafe fn fuzz(options: &FuzzerOptions) -> Result<(), Error> {
                         &mut feedback,
                         &mut objective,
                     )
-                    .unwrap()
+                    .unwrap();
+                    // Keep the seed, to replay this run with `--replay-seed`
+                    state.set_rng_seed(seed);
+                    state
                 });
 
                 println!("We're a client, let's fuzz :)");
@@ -292,7 +299,7 @@ unsafe fn fuzz(options: &FuzzerOptions) -> Result<(), Error> {
                 let observers = tuple_list!(
                     edges_observer,
                     time_observer,
-                    AsanErrorsObserver::new(&ASAN_ERRORS)
+                    AsanErrorsObserver::thread_local()
                 );
                 #[cfg(windows)]
                 let observers = tuple_list!(edges_observer, time_observer,);
@@ -381,9 +388,10 @@ unsafe fn fuzz(options: &FuzzerOptions) -> Result<(), Error> {
 
                 // If not restarting, create a State from scratch
                 let mut state = state.unwrap_or_else(|| {
-                    StdState::new(
-                        // RNG
-                        StdRand::with_seed(current_nanos()),
+                    let seed = options.rng_seed();
+                    let mut state = StdState::new(
+                        // RNG, seeded with the `--replay-seed`, if given
+                        StdRand::with_seed(seed),
                         // Corpus that will be evolved, we keep it in memory for performance
                         CachedOnDiskCorpus::no_meta(PathBuf::from("./corpus_discovered"), 64)
                             .unwrap(),
@@ -393,7 +401,10 @@ unsafe fn fuzz(options: &FuzzerOptions) -> Result<(), Error> {
                         &mut feedback,
                         &mut objective,
                     )
-                    .unwrap()
+                    .unwrap();
+                    // Keep the seed, to replay this run with `--replay-seed`
+                    state.set_rng_seed(seed);
+                    state
                 });
 
                 println!("We're a client, let's fuzz :)");
@@ -422,7 +433,7 @@ unsafe fn fuzz(options: &FuzzerOptions) -> Result<(), Error> {
                 let observers = tuple_list!(
                     edges_observer,
                     time_observer,
-                    AsanErrorsObserver::new(&ASAN_ERRORS)
+                    AsanErrorsObserver::thread_local()
                 );
                 #[cfg(windows)]
                 let observers = tuple_list!(edges_observer, time_observer,);
@@ -0,0 +1,120 @@
+//! The [`ZstdCorpusArchive`] stores the inputs of a campaign in a single Zstandard-compressed tar archive.
+//!
+//! Large campaigns produce millions of small files with an [`crate::corpus::OnDiskCorpus`].
+//! The archive instead appends each input as one tar entry, named after its [`CorpusId`],
+//! optionally followed by the hash of its bytes, to `<output_dir>/corpus.tar.zst`, or to `<output_dir>/corpus_<client>.tar.zst` with one archive per client.
+//! Each entry is written as its own Zstandard frame, so that the archive stays readable if the fuzzer crashes,
+//! and can be extracted again with [`ZstdCorpusArchive::extract_to`] or `tar --zstd -xf`.
+//!
+//! An archive must only be written by one process at a time, concurrent writers would interleave their frames.
+
+use alloc::format;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use libafl_bolts::{hash_std, AsSlice};
+
+use crate::{corpus::CorpusId, inputs::HasTargetBytes, Error};
+
+/// The name of the archive in the output directory
+pub const CORPUS_ARCHIVE_NAME: &str = "corpus.tar.zst";
+
+/// The default Zstandard compression level of the archive
+pub const DEFAULT_CORPUS_ARCHIVE_LEVEL: i32 = 3;
+
+/// The size of tar headers and of the blocks the entries are padded to
+const TAR_BLOCK_SIZE: usize = 512;
+
+/// A Zstandard-compressed tar archive the inputs of the corpus are appended to, see the [module documentation](self).
+#[derive(Debug)]
+pub struct ZstdCorpusArchive {
+    path: PathBuf,
+    file: File,
+    level: i32,
+    hash_suffix: bool,
+}
+
+impl ZstdCorpusArchive {
+    /// Opens `<output_dir>/corpus.tar.zst`, appending to the entries of earlier runs.
+    pub fn new<P: AsRef<Path>>(output_dir: P) -> Result<Self, Error> {
+        Self::with_file_name(output_dir, CORPUS_ARCHIVE_NAME)
+    }
+
+    /// Opens the archive of the client `client_id`, `<output_dir>/corpus_<client_id>.tar.zst`,
+    /// appending to the entries of its earlier runs.
+    pub fn for_client<P: AsRef<Path>>(output_dir: P, client_id: usize) -> Result<Self, Error> {
+        Self::with_file_name(output_dir, &format!("corpus_{client_id}.tar.zst"))
+    }
+
+    fn with_file_name<P: AsRef<Path>>(output_dir: P, file_name: &str) -> Result<Self, Error> {
+        fs::create_dir_all(output_dir.as_ref())?;
+        let path = output_dir.as_ref().join(file_name);
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(Self {
+            path,
+            file,
+            level: DEFAULT_CORPUS_ARCHIVE_LEVEL,
+            hash_suffix: false,
+        })
+    }
+
+    /// Sets the Zstandard compression level of the entries appended from now on
+    #[must_use]
+    pub fn with_level(mut self, level: i32) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Sets whether the entries appended from now on are named `<id>_<hash>` instead of `<id>`,
+    /// with the hash of the bytes of their input in hex
+    #[must_use]
+    pub fn with_hash_suffix(mut self, hash_suffix: bool) -> Self {
+        self.hash_suffix = hash_suffix;
+        self
+    }
+
+    /// The path of the archive
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends the bytes of `input`, the testcase `id` of the corpus, to the archive, as an entry named after `id`.
+    pub fn append<I>(&mut self, id: CorpusId, input: &I) -> Result<(), Error>
+    where
+        I: HasTargetBytes,
+    {
+        let bytes = input.target_bytes();
+        let bytes = bytes.as_slice();
+
+        let name = if self.hash_suffix {
+            format!("{id}_{:016x}", hash_std(bytes))
+        } else {
+            format!("{id}")
+        };
+        let mut header = tar::Header::new_ustar();
+        header.set_path(name)?;
+        header.set_size(bytes.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+
+        let mut encoder = zstd::Encoder::new(&mut self.file, self.level)?;
+        encoder.write_all(header.as_bytes())?;
+        encoder.write_all(bytes)?;
+        let padding = (TAR_BLOCK_SIZE - bytes.len() % TAR_BLOCK_SIZE) % TAR_BLOCK_SIZE;
+        encoder.write_all(&[0; TAR_BLOCK_SIZE][..padding])?;
+        encoder.finish()?.flush()?;
+        Ok(())
+    }
+
+    /// Extracts all entries of the archive into `dir`, one file per input
+    pub fn extract_to<P: AsRef<Path>>(&self, dir: P) -> Result<(), Error> {
+        fs::create_dir_all(dir.as_ref())?;
+        let decoder = zstd::Decoder::new(File::open(&self.path)?)?;
+        tar::Archive::new(decoder).unpack(dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,321 @@
+//! The [`DeduplicatingCorpus`] wraps another [`Corpus`], rejecting [`Testcase`]s whose input is already in it.
+
+use alloc::{format, vec::Vec};
+use core::cell::RefCell;
+
+use hashbrown::HashMap;
+use libafl_bolts::{hash_std, AsSlice};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{Corpus, CorpusId, HasTestcase, Testcase},
+    inputs::{HasTargetBytes, UsesInput},
+    Error,
+};
+
+/// A [`Corpus`] wrapper that refuses to add a [`Testcase`] if another one with the same target bytes is in the corpus.
+///
+/// Restarts and the synchronization between fuzzer instances can otherwise bring in the same input many times.
+/// Adding a duplicate returns [`Error::Duplicate`], so that it is not reported as a new entry;
+/// [`DeduplicatingCorpus::contains`] checks for duplicates beforehand.
+#[derive(Default, Serialize, Deserialize, Clone, Debug)]
+#[serde(bound = "C: Corpus")]
+pub struct DeduplicatingCorpus<C> {
+    inner: C,
+    /// The entry for each hash of target bytes
+    hashes: HashMap<u64, CorpusId>,
+    /// The hash of each entry, to forget it on removal
+    ids: HashMap<CorpusId, u64>,
+}
+
+impl<C> DeduplicatingCorpus<C>
+where
+    C: Corpus,
+    C::Input: HasTargetBytes,
+{
+    /// Creates a new [`DeduplicatingCorpus`] wrapping `inner`.
+    ///
+    /// Duplicates among the entries `inner` already has are kept, new entries are compared to the first of them.
+    pub fn new(inner: C) -> Result<Self, Error> {
+        let mut corpus = Self {
+            inner,
+            hashes: HashMap::new(),
+            ids: HashMap::new(),
+        };
+        let ids: Vec<CorpusId> = corpus.inner.ids().collect();
+        for id in ids {
+            let hash = corpus.hash_entry(id)?;
+            corpus.hashes.entry(hash).or_insert(id);
+            corpus.ids.insert(id, hash);
+        }
+        Ok(corpus)
+    }
+
+    /// The wrapped corpus
+    #[must_use]
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// Returns `true` if an entry with the same target bytes as `input` is in the corpus
+    #[must_use]
+    pub fn contains(&self, input: &C::Input) -> bool {
+        self.hashes
+            .contains_key(&hash_std(input.target_bytes().as_slice()))
+    }
+
+    /// Hashes the target bytes of the entry at `id`
+    fn hash_entry(&self, id: CorpusId) -> Result<u64, Error> {
+        let input = self.inner.cloned_input_for_id(id)?;
+        Ok(hash_std(input.target_bytes().as_slice()))
+    }
+
+    /// Hashes the target bytes of `testcase`, loading its input if needed
+    fn hash_testcase(&self, testcase: &mut Testcase<C::Input>) -> Result<u64, Error> {
+        if testcase.input().is_none() {
+            self.inner.load_input_into(testcase)?;
+        }
+        let input = testcase.input().as_ref().unwrap();
+        Ok(hash_std(input.target_bytes().as_slice()))
+    }
+
+    /// Forgets the hash of the entry at `id`
+    fn forget(&mut self, id: CorpusId) {
+        if let Some(hash) = self.ids.remove(&id) {
+            if self.hashes.get(&hash) == Some(&id) {
+                self.hashes.remove(&hash);
+            }
+        }
+    }
+}
+
+impl<C> UsesInput for DeduplicatingCorpus<C>
+where
+    C: Corpus,
+{
+    type Input = C::Input;
+}
+
+impl<C> Corpus for DeduplicatingCorpus<C>
+where
+    C: Corpus,
+    C::Input: HasTargetBytes,
+{
+    #[inline]
+    fn count(&self) -> usize {
+        self.inner.count()
+    }
+
+    /// Adds an entry to the corpus, unless its input is a duplicate
+    fn add(&mut self, mut testcase: Testcase<Self::Input>) -> Result<CorpusId, Error> {
+        let hash = self.hash_testcase(&mut testcase)?;
+        if let Some(id) = self.hashes.get(&hash) {
+            return Err(Error::duplicate(format!(
+                "Testcase with hash {hash:#x} is already in the corpus as {id}"
+            )));
+        }
+        let id = self.inner.add(testcase)?;
+        self.hashes.insert(hash, id);
+        self.ids.insert(id, hash);
+        Ok(id)
+    }
+
+    /// Replaces the testcase at the given idx, returning the existing.
+    fn replace(
+        &mut self,
+        idx: CorpusId,
+        mut testcase: Testcase<Self::Input>,
+    ) -> Result<Testcase<Self::Input>, Error> {
+        let hash = self.hash_testcase(&mut testcase)?;
+        let old = self.inner.replace(idx, testcase)?;
+        self.forget(idx);
+        self.hashes.entry(hash).or_insert(idx);
+        self.ids.insert(idx, hash);
+        Ok(old)
+    }
+
+    /// Removes an entry from the corpus, returning it if it was present.
+    fn remove(&mut self, id: CorpusId) -> Result<Testcase<Self::Input>, Error> {
+        let testcase = self.inner.remove(id)?;
+        self.forget(id);
+        Ok(testcase)
+    }
+
+    #[inline]
+    fn get(&self, id: CorpusId) -> Result<&RefCell<Testcase<Self::Input>>, Error> {
+        self.inner.get(id)
+    }
+
+    #[inline]
+    fn current(&self) -> &Option<CorpusId> {
+        self.inner.current()
+    }
+
+    #[inline]
+    fn current_mut(&mut self) -> &mut Option<CorpusId> {
+        self.inner.current_mut()
+    }
+
+    #[inline]
+    fn next(&self, id: CorpusId) -> Option<CorpusId> {
+        self.inner.next(id)
+    }
+
+    #[inline]
+    fn prev(&self, id: CorpusId) -> Option<CorpusId> {
+        self.inner.prev(id)
+    }
+
+    #[inline]
+    fn first(&self) -> Option<CorpusId> {
+        self.inner.first()
+    }
+
+    #[inline]
+    fn last(&self) -> Option<CorpusId> {
+        self.inner.last()
+    }
+
+    #[inline]
+    fn nth(&self, nth: usize) -> CorpusId {
+        self.inner.nth(nth)
+    }
+
+    #[inline]
+    fn load_input_into(&self, testcase: &mut Testcase<Self::Input>) -> Result<(), Error> {
+        self.inner.load_input_into(testcase)
+    }
+
+    #[inline]
+    fn store_input_from(&self, testcase: &Testcase<Self::Input>) -> Result<(), Error> {
+        self.inner.store_input_from(testcase)
+    }
+}
+
+impl<C> HasTestcase for DeduplicatingCorpus<C>
+where
+    C: Corpus,
+    C::Input: HasTargetBytes,
+{
+    fn testcase(&self, id: CorpusId) -> Result<core::cell::Ref<Testcase<Self::Input>>, Error> {
+        Ok(self.get(id)?.borrow())
+    }
+
+    fn testcase_mut(
+        &self,
+        id: CorpusId,
+    ) -> Result<core::cell::RefMut<Testcase<Self::Input>>, Error> {
+        Ok(self.get(id)?.borrow_mut())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::rands::StdRand;
+
+    use super::DeduplicatingCorpus;
+    use crate::{
+        corpus::{Corpus, InMemoryCorpus, Testcase},
+        events::NopEventManager,
+        executors::{test::NopExecutor, WithObservers},
+        feedbacks::ConstFeedback,
+        fuzzer::{Evaluator, ExecuteInputResult, StdFuzzer},
+        inputs::BytesInput,
+        schedulers::QueueScheduler,
+        state::{HasCorpus, HasExecutions, StdState},
+        Error,
+    };
+
+    #[test]
+    fn test_dedup_corpus() {
+        let mut corpus = DeduplicatingCorpus::new(InMemoryCorpus::<BytesInput>::new()).unwrap();
+        let id = corpus
+            .add(Testcase::new(BytesInput::new(b"abc".to_vec())))
+            .unwrap();
+        assert!(matches!(
+            corpus.add(Testcase::new(BytesInput::new(b"abc".to_vec()))),
+            Err(Error::Duplicate(..))
+        ));
+        assert_eq!(corpus.count(), 1);
+
+        corpus.remove(id).unwrap();
+        corpus
+            .add(Testcase::new(BytesInput::new(b"abc".to_vec())))
+            .unwrap();
+        assert_eq!(corpus.count(), 1);
+    }
+
+    #[test]
+    fn test_dedup_corpus_keeps_existing_entries() {
+        let mut inner = InMemoryCorpus::<BytesInput>::new();
+        inner
+            .add(Testcase::new(BytesInput::new(b"abc".to_vec())))
+            .unwrap();
+        inner
+            .add(Testcase::new(BytesInput::new(b"abc".to_vec())))
+            .unwrap();
+
+        let mut corpus = DeduplicatingCorpus::new(inner).unwrap();
+        assert_eq!(corpus.count(), 2);
+        assert!(corpus.contains(&BytesInput::new(b"abc".to_vec())));
+        assert!(matches!(
+            corpus.add(Testcase::new(BytesInput::new(b"abc".to_vec()))),
+            Err(Error::Duplicate(..))
+        ));
+        assert_eq!(corpus.count(), 2);
+    }
+
+    #[test]
+    fn test_fuzz_through_duplicate() {
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            DeduplicatingCorpus::new(InMemoryCorpus::<BytesInput>::new()).unwrap(),
+            InMemoryCorpus::new(),
+            &mut (),
+            &mut (),
+        )
+        .unwrap();
+        let mut fuzzer = StdFuzzer::new(
+            QueueScheduler::new(),
+            ConstFeedback::new(true),
+            ConstFeedback::new(false),
+        );
+        let mut executor = WithObservers::new(NopExecutor::new(), ());
+        let mut mgr = NopEventManager::new();
+
+        let (res, id) = fuzzer
+            .evaluate_input(
+                &mut state,
+                &mut executor,
+                &mut mgr,
+                BytesInput::new(vec![1]),
+            )
+            .unwrap();
+        assert_eq!(res, ExecuteInputResult::Corpus);
+        assert!(id.is_some());
+
+        // the duplicate is skipped, not reported as a new entry, and the fuzzer keeps going
+        let (res, id) = fuzzer
+            .evaluate_input(
+                &mut state,
+                &mut executor,
+                &mut mgr,
+                BytesInput::new(vec![1]),
+            )
+            .unwrap();
+        assert_eq!(res, ExecuteInputResult::None);
+        assert_eq!(id, None);
+        assert_eq!(state.corpus().count(), 1);
+
+        fuzzer
+            .evaluate_input(
+                &mut state,
+                &mut executor,
+                &mut mgr,
+                BytesInput::new(vec![2]),
+            )
+            .unwrap();
+        assert_eq!(state.corpus().count(), 2);
+        assert_eq!(*state.executions(), 3);
+    }
+}
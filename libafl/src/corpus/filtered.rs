@@ -0,0 +1,313 @@
+//! The [`FilteredCorpus`] wraps another [`Corpus`], rejecting [`Testcase`]s whose input fails an [`InputFilter`].
+
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+use libafl_bolts::{AsSlice, HasLen};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    corpus::{Corpus, CorpusId, HasTestcase, Testcase},
+    inputs::{HasTargetBytes, Input, UsesInput},
+    Error,
+};
+
+/// A static validity predicate for inputs
+pub trait InputFilter<I: Input> {
+    /// Returns `true` if `input` is valid, and may be added to the corpus
+    fn accept(&self, input: &I) -> bool;
+}
+
+/// Accepts the inputs with a length between `min` and `max`, inclusive
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct LengthFilter {
+    /// The minimum length of the accepted inputs
+    pub min: usize,
+    /// The maximum length of the accepted inputs
+    pub max: usize,
+}
+
+impl LengthFilter {
+    /// Creates a new [`LengthFilter`], accepting inputs of `min` to `max` bytes
+    #[must_use]
+    pub fn new(min: usize, max: usize) -> Self {
+        Self { min, max }
+    }
+}
+
+impl<I> InputFilter<I> for LengthFilter
+where
+    I: Input + HasLen,
+{
+    fn accept(&self, input: &I) -> bool {
+        (self.min..=self.max).contains(&input.len())
+    }
+}
+
+/// Accepts the inputs whose target bytes start with `prefix`, e.g. the magic bytes of a file format
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MagicBytesFilter {
+    /// The bytes the accepted inputs start with
+    pub prefix: Vec<u8>,
+}
+
+impl MagicBytesFilter {
+    /// Creates a new [`MagicBytesFilter`], accepting inputs starting with `prefix`
+    #[must_use]
+    pub fn new(prefix: Vec<u8>) -> Self {
+        Self { prefix }
+    }
+}
+
+impl<I> InputFilter<I> for MagicBytesFilter
+where
+    I: Input + HasTargetBytes,
+{
+    fn accept(&self, input: &I) -> bool {
+        input.target_bytes().as_slice().starts_with(&self.prefix)
+    }
+}
+
+/// A [`Corpus`] wrapper that refuses to add a [`Testcase`] if its input is rejected by the [`InputFilter`].
+///
+/// Adding or replacing with a rejected input returns [`Error::Refused`], so the fuzzer skips the input.
+/// The entries already in the wrapped corpus are kept, whether they pass the filter or not.
+#[derive(Default, Serialize, Deserialize, Clone, Debug)]
+#[serde(bound = "C: Corpus, F: Serialize + DeserializeOwned")]
+pub struct FilteredCorpus<C, F> {
+    inner: C,
+    filter: F,
+}
+
+impl<C, F> FilteredCorpus<C, F>
+where
+    C: Corpus,
+    F: InputFilter<C::Input>,
+{
+    /// Creates a new [`FilteredCorpus`] wrapping `inner`, adding only the inputs accepted by `filter`
+    pub fn new(inner: C, filter: F) -> Self {
+        Self { inner, filter }
+    }
+
+    /// The wrapped corpus
+    #[must_use]
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// The filter of this corpus
+    #[must_use]
+    pub fn filter(&self) -> &F {
+        &self.filter
+    }
+
+    /// Fails if the input of `testcase` is rejected by the filter, loading it if needed
+    fn check(&self, testcase: &mut Testcase<C::Input>) -> Result<(), Error> {
+        if testcase.input().is_none() {
+            self.inner.load_input_into(testcase)?;
+        }
+        if self.filter.accept(testcase.input().as_ref().unwrap()) {
+            Ok(())
+        } else {
+            Err(Error::refused(
+                "The input of the testcase is rejected by the filter of the corpus",
+            ))
+        }
+    }
+}
+
+impl<C, F> UsesInput for FilteredCorpus<C, F>
+where
+    C: Corpus,
+{
+    type Input = C::Input;
+}
+
+impl<C, F> Corpus for FilteredCorpus<C, F>
+where
+    C: Corpus,
+    F: InputFilter<C::Input> + Serialize + DeserializeOwned,
+{
+    #[inline]
+    fn count(&self) -> usize {
+        self.inner.count()
+    }
+
+    /// Adds an entry to the corpus, unless its input is rejected by the filter
+    fn add(&mut self, mut testcase: Testcase<Self::Input>) -> Result<CorpusId, Error> {
+        self.check(&mut testcase)?;
+        self.inner.add(testcase)
+    }
+
+    /// Replaces the testcase at the given idx, unless the new input is rejected by the filter
+    fn replace(
+        &mut self,
+        idx: CorpusId,
+        mut testcase: Testcase<Self::Input>,
+    ) -> Result<Testcase<Self::Input>, Error> {
+        self.check(&mut testcase)?;
+        self.inner.replace(idx, testcase)
+    }
+
+    /// Removes an entry from the corpus, returning it if it was present.
+    #[inline]
+    fn remove(&mut self, id: CorpusId) -> Result<Testcase<Self::Input>, Error> {
+        self.inner.remove(id)
+    }
+
+    #[inline]
+    fn get(&self, id: CorpusId) -> Result<&RefCell<Testcase<Self::Input>>, Error> {
+        self.inner.get(id)
+    }
+
+    #[inline]
+    fn current(&self) -> &Option<CorpusId> {
+        self.inner.current()
+    }
+
+    #[inline]
+    fn current_mut(&mut self) -> &mut Option<CorpusId> {
+        self.inner.current_mut()
+    }
+
+    #[inline]
+    fn next(&self, id: CorpusId) -> Option<CorpusId> {
+        self.inner.next(id)
+    }
+
+    #[inline]
+    fn prev(&self, id: CorpusId) -> Option<CorpusId> {
+        self.inner.prev(id)
+    }
+
+    #[inline]
+    fn first(&self) -> Option<CorpusId> {
+        self.inner.first()
+    }
+
+    #[inline]
+    fn last(&self) -> Option<CorpusId> {
+        self.inner.last()
+    }
+
+    #[inline]
+    fn nth(&self, nth: usize) -> CorpusId {
+        self.inner.nth(nth)
+    }
+
+    #[inline]
+    fn load_input_into(&self, testcase: &mut Testcase<Self::Input>) -> Result<(), Error> {
+        self.inner.load_input_into(testcase)
+    }
+
+    #[inline]
+    fn store_input_from(&self, testcase: &Testcase<Self::Input>) -> Result<(), Error> {
+        self.inner.store_input_from(testcase)
+    }
+}
+
+impl<C, F> HasTestcase for FilteredCorpus<C, F>
+where
+    C: Corpus,
+    F: InputFilter<C::Input> + Serialize + DeserializeOwned,
+{
+    fn testcase(&self, id: CorpusId) -> Result<core::cell::Ref<Testcase<Self::Input>>, Error> {
+        Ok(self.get(id)?.borrow())
+    }
+
+    fn testcase_mut(
+        &self,
+        id: CorpusId,
+    ) -> Result<core::cell::RefMut<Testcase<Self::Input>>, Error> {
+        Ok(self.get(id)?.borrow_mut())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::rands::StdRand;
+
+    use super::{FilteredCorpus, LengthFilter, MagicBytesFilter};
+    use crate::{
+        corpus::{Corpus, InMemoryCorpus, Testcase},
+        events::NopEventManager,
+        executors::{test::NopExecutor, WithObservers},
+        feedbacks::ConstFeedback,
+        fuzzer::{Evaluator, ExecuteInputResult, StdFuzzer},
+        inputs::BytesInput,
+        schedulers::QueueScheduler,
+        state::{HasCorpus, StdState},
+        Error,
+    };
+
+    #[test]
+    fn test_filtered_corpus() {
+        let mut corpus =
+            FilteredCorpus::new(InMemoryCorpus::<BytesInput>::new(), LengthFilter::new(2, 4));
+        assert!(matches!(
+            corpus.add(Testcase::new(BytesInput::new(b"a".to_vec()))),
+            Err(Error::Refused(..))
+        ));
+        let id = corpus
+            .add(Testcase::new(BytesInput::new(b"abc".to_vec())))
+            .unwrap();
+        assert!(corpus
+            .replace(id, Testcase::new(BytesInput::new(b"abcde".to_vec())))
+            .is_err());
+        assert_eq!(corpus.count(), 1);
+
+        let mut corpus = FilteredCorpus::new(
+            InMemoryCorpus::<BytesInput>::new(),
+            MagicBytesFilter::new(b"GIF".to_vec()),
+        );
+        assert!(corpus
+            .add(Testcase::new(BytesInput::new(b"PNG".to_vec())))
+            .is_err());
+        corpus
+            .add(Testcase::new(BytesInput::new(b"GIF89a".to_vec())))
+            .unwrap();
+        assert_eq!(corpus.count(), 1);
+    }
+
+    #[test]
+    fn test_fuzz_through_filtered_input() {
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            FilteredCorpus::new(InMemoryCorpus::<BytesInput>::new(), LengthFilter::new(2, 4)),
+            InMemoryCorpus::new(),
+            &mut (),
+            &mut (),
+        )
+        .unwrap();
+        let mut fuzzer = StdFuzzer::new(
+            QueueScheduler::new(),
+            ConstFeedback::new(true),
+            ConstFeedback::new(false),
+        );
+        let mut executor = WithObservers::new(NopExecutor::new(), ());
+        let mut mgr = NopEventManager::new();
+
+        // the filtered input is skipped, without failing the evaluation
+        let (res, id) = fuzzer
+            .evaluate_input(
+                &mut state,
+                &mut executor,
+                &mut mgr,
+                BytesInput::new(vec![1]),
+            )
+            .unwrap();
+        assert_eq!(res, ExecuteInputResult::None);
+        assert_eq!(id, None);
+
+        let (res, _) = fuzzer
+            .evaluate_input(
+                &mut state,
+                &mut executor,
+                &mut mgr,
+                BytesInput::new(vec![1, 2]),
+            )
+            .unwrap();
+        assert_eq!(res, ExecuteInputResult::Corpus);
+        assert_eq!(state.corpus().count(), 1);
+    }
+}
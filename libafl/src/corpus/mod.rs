@@ -21,6 +21,17 @@ pub mod cached;
 #[cfg(feature = "std")]
 pub use cached::CachedOnDiskCorpus;
 
+#[cfg(feature = "corpus_archive")]
+pub mod archive;
+#[cfg(feature = "corpus_archive")]
+pub use archive::ZstdCorpusArchive;
+
+pub mod dedup;
+pub use dedup::DeduplicatingCorpus;
+
+pub mod filtered;
+pub use filtered::{FilteredCorpus, InputFilter, LengthFilter, MagicBytesFilter};
+
 #[cfg(feature = "cmin")]
 pub mod minimizer;
 use core::{cell::RefCell, fmt};
@@ -83,7 +94,8 @@ pub trait Corpus: UsesInput + Serialize + for<'de> Deserialize<'de> {
         self.count() == 0
     }
 
-    /// Add an entry to the corpus and return its index
+    /// Add an entry to the corpus and return its index.
+    /// A corpus may refuse the entry, see [`is_refused_add`].
     fn add(&mut self, testcase: Testcase<Self::Input>) -> Result<CorpusId, Error>;
 
     /// Replaces the [`Testcase`] at the given idx, returning the existing.
@@ -148,6 +160,14 @@ pub trait Corpus: UsesInput + Serialize + for<'de> Deserialize<'de> {
     }
 }
 
+/// Returns `true` if `err` is a [`Corpus`] refusing to add a [`Testcase`],
+/// like a [`DeduplicatingCorpus`] refusing a duplicate, or a [`FilteredCorpus`] an input its filter rejects.
+/// The fuzzer skips such a testcase, instead of failing.
+#[must_use]
+pub fn is_refused_add(err: &Error) -> bool {
+    matches!(err, Error::Duplicate(..) | Error::Refused(..))
+}
+
 /// Trait for types which track the current corpus index
 pub trait HasCurrentCorpusIdx {
     /// Set the current corpus index; we have started processing this corpus entry
@@ -0,0 +1,295 @@
+//! The [`ArchiveEventManager`] wraps another event manager, appending every new testcase it fires
+//! to a [`ZstdCorpusArchive`] of its client.
+
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::time::Duration;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::{
+    corpus::{archive::ZstdCorpusArchive, Corpus, CorpusId},
+    events::{
+        CustomBufEventResult, Event, EventConfig, EventFirer, EventManager, EventManagerId,
+        EventProcessor, EventRestarter, HasCustomBufHandlers, HasEventManagerId, LogSeverity,
+        ProgressReporter,
+    },
+    inputs::{HasTargetBytes, UsesInput},
+    observers::ObserversTuple,
+    state::{HasCorpus, HasExecutions, HasLastReportTime, HasMetadata, UsesState},
+    Error,
+};
+
+/// An [`EventManager`] that wraps another manager, and archives the input of each fired [`Event::NewTestcase`]
+/// in `<output_dir>/corpus_<client>.tar.zst`.
+///
+/// The entries are named after the [`CorpusId`] of the new testcase, the last one in the corpus when the event fires.
+/// Each client writes its own archive, reopened in append mode when it restarts,
+/// so the ids, restored with the corpus, never collide across clients or restarts.
+#[derive(Debug)]
+pub struct ArchiveEventManager<EM> {
+    inner: EM,
+    archive: ZstdCorpusArchive,
+}
+
+impl<EM> ArchiveEventManager<EM>
+where
+    EM: HasEventManagerId,
+{
+    /// Creates a new [`ArchiveEventManager`] wrapping `inner`,
+    /// archiving to `<output_dir>/corpus_<client>.tar.zst` for the [`EventManagerId`] of `inner`.
+    pub fn new<P: AsRef<Path>>(inner: EM, output_dir: P) -> Result<Self, Error> {
+        let archive = ZstdCorpusArchive::for_client(output_dir, inner.mgr_id().0)?;
+        Ok(Self::with_archive(inner, archive))
+    }
+}
+
+impl<EM> ArchiveEventManager<EM> {
+    /// Creates a new [`ArchiveEventManager`] wrapping `inner`, archiving to the given archive.
+    /// No other process may write to it at the same time.
+    #[must_use]
+    pub fn with_archive(inner: EM, archive: ZstdCorpusArchive) -> Self {
+        Self { inner, archive }
+    }
+
+    /// The archive the new testcases are appended to
+    #[must_use]
+    pub fn archive(&self) -> &ZstdCorpusArchive {
+        &self.archive
+    }
+
+    /// The wrapped event manager
+    #[must_use]
+    pub fn inner(&self) -> &EM {
+        &self.inner
+    }
+}
+
+impl<EM> UsesState for ArchiveEventManager<EM>
+where
+    EM: UsesState,
+{
+    type State = EM::State;
+}
+
+impl<EM> EventFirer for ArchiveEventManager<EM>
+where
+    EM: EventFirer,
+    EM::State: HasCorpus,
+    <EM::State as UsesInput>::Input: HasTargetBytes,
+{
+    fn fire(
+        &mut self,
+        state: &mut Self::State,
+        event: Event<<Self::State as UsesInput>::Input>,
+    ) -> Result<(), Error> {
+        if let Event::NewTestcase { input, .. } = &event {
+            let id: CorpusId = state.corpus().last().ok_or_else(|| {
+                Error::illegal_state("A new testcase was fired, but the corpus is empty")
+            })?;
+            self.archive.append(id, input)?;
+        }
+        self.inner.fire(state, event)
+    }
+
+    #[inline]
+    fn log(
+        &mut self,
+        state: &mut Self::State,
+        severity_level: LogSeverity,
+        message: String,
+    ) -> Result<(), Error> {
+        self.inner.log(state, severity_level, message)
+    }
+
+    #[inline]
+    fn serialize_observers<OT>(&mut self, observers: &OT) -> Result<Option<Vec<u8>>, Error>
+    where
+        OT: ObserversTuple<Self::State> + Serialize,
+    {
+        self.inner.serialize_observers(observers)
+    }
+
+    #[inline]
+    fn configuration(&self) -> EventConfig {
+        self.inner.configuration()
+    }
+}
+
+impl<EM> EventRestarter for ArchiveEventManager<EM>
+where
+    EM: EventRestarter,
+{
+    #[inline]
+    fn on_restart(&mut self, state: &mut Self::State) -> Result<(), Error> {
+        self.inner.on_restart(state)
+    }
+
+    #[inline]
+    fn send_exiting(&mut self) -> Result<(), Error> {
+        self.inner.send_exiting()
+    }
+
+    #[inline]
+    fn await_restart_safe(&mut self) {
+        self.inner.await_restart_safe();
+    }
+}
+
+impl<E, EM, Z> EventProcessor<E, Z> for ArchiveEventManager<EM>
+where
+    EM: EventProcessor<E, Z>,
+{
+    #[inline]
+    fn process(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut Self::State,
+        executor: &mut E,
+    ) -> Result<usize, Error> {
+        self.inner.process(fuzzer, state, executor)
+    }
+}
+
+impl<E, EM, Z> EventManager<E, Z> for ArchiveEventManager<EM>
+where
+    EM: EventManager<E, Z>,
+    EM::State: HasLastReportTime + HasExecutions + HasMetadata + HasCorpus,
+    <EM::State as UsesInput>::Input: HasTargetBytes,
+{
+}
+
+impl<EM> HasCustomBufHandlers for ArchiveEventManager<EM>
+where
+    Self: UsesState,
+    EM: HasCustomBufHandlers<State = Self::State>,
+{
+    #[inline]
+    fn add_custom_buf_handler(
+        &mut self,
+        handler: Box<
+            dyn FnMut(&mut Self::State, &str, &[u8]) -> Result<CustomBufEventResult, Error>,
+        >,
+    ) {
+        self.inner.add_custom_buf_handler(handler);
+    }
+}
+
+impl<EM> ProgressReporter for ArchiveEventManager<EM>
+where
+    Self: UsesState,
+    EM: ProgressReporter<State = Self::State>,
+    EM::State: HasLastReportTime + HasExecutions + HasMetadata + HasCorpus,
+    <EM::State as UsesInput>::Input: HasTargetBytes,
+{
+    #[inline]
+    fn maybe_report_progress(
+        &mut self,
+        state: &mut Self::State,
+        monitor_timeout: Duration,
+    ) -> Result<(), Error> {
+        self.inner.maybe_report_progress(state, monitor_timeout)
+    }
+
+    #[inline]
+    fn report_progress(&mut self, state: &mut Self::State) -> Result<(), Error> {
+        self.inner.report_progress(state)
+    }
+}
+
+impl<EM> HasEventManagerId for ArchiveEventManager<EM>
+where
+    EM: HasEventManagerId,
+{
+    #[inline]
+    fn mgr_id(&self) -> EventManagerId {
+        self.inner.mgr_id()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+    use std::{env, fs, process};
+
+    use libafl_bolts::hash_std;
+
+    use super::ArchiveEventManager;
+    use crate::{
+        corpus::{archive::ZstdCorpusArchive, Corpus, Testcase},
+        events::{Event, EventConfig, EventFirer, NopEventManager},
+        executors::ExitKind,
+        inputs::BytesInput,
+        state::{test::test_std_state, HasCorpus},
+    };
+
+    fn new_testcase(bytes: &[u8], corpus_size: usize) -> Event<BytesInput> {
+        Event::NewTestcase {
+            input: BytesInput::new(bytes.to_vec()),
+            observers_buf: None,
+            exit_kind: ExitKind::Ok,
+            corpus_size,
+            client_config: EventConfig::AlwaysUnique,
+            time: Duration::ZERO,
+            executions: 0,
+            forward_id: None,
+        }
+    }
+
+    #[test]
+    fn test_archive_event_manager() {
+        let mut state = test_std_state::<BytesInput>();
+        let output_dir = env::temp_dir().join(format!("libafl_archive_test_{}", process::id()));
+        let _res = fs::remove_dir_all(&output_dir);
+        let new_mgr = || ArchiveEventManager::new(NopEventManager::new(), &output_dir).unwrap();
+
+        // a restart reopens the archive of the same client and appends to it
+        for bytes in [&b"first"[..], &b"second"[..]] {
+            let mut mgr = new_mgr();
+            let input = BytesInput::new(bytes.to_vec());
+            state.corpus_mut().add(Testcase::new(input)).unwrap();
+            let corpus_size = state.corpus().count();
+            mgr.fire(&mut state, new_testcase(bytes, corpus_size))
+                .unwrap();
+        }
+
+        let mgr = new_mgr();
+        assert_eq!(mgr.archive().path(), output_dir.join("corpus_0.tar.zst"));
+        let extracted = output_dir.join("extracted");
+        mgr.archive().extract_to(&extracted).unwrap();
+        // the entries are named after their corpus ids
+        for (id, bytes) in [&b"first"[..], &b"second"[..]].into_iter().enumerate() {
+            assert_eq!(fs::read(extracted.join(format!("{id}"))).unwrap(), bytes);
+        }
+
+        fs::remove_dir_all(&output_dir).unwrap();
+    }
+
+    #[test]
+    fn test_archive_hash_suffix() {
+        let mut state = test_std_state::<BytesInput>();
+        let output_dir =
+            env::temp_dir().join(format!("libafl_archive_hash_test_{}", process::id()));
+        let _res = fs::remove_dir_all(&output_dir);
+        let archive = ZstdCorpusArchive::new(&output_dir)
+            .unwrap()
+            .with_hash_suffix(true);
+        let mut mgr = ArchiveEventManager::with_archive(NopEventManager::new(), archive);
+
+        // without a testcase in the corpus, there is no id to name the entry after
+        assert!(mgr.fire(&mut state, new_testcase(b"input", 0)).is_err());
+
+        let id = state
+            .corpus_mut()
+            .add(Testcase::new(BytesInput::new(b"input".to_vec())))
+            .unwrap();
+        mgr.fire(&mut state, new_testcase(b"input", 1)).unwrap();
+
+        let extracted = output_dir.join("extracted");
+        mgr.archive().extract_to(&extracted).unwrap();
+        let entry = extracted.join(format!("{id}_{:016x}", hash_std(b"input")));
+        assert_eq!(fs::read(entry).unwrap(), b"input");
+
+        fs::remove_dir_all(&output_dir).unwrap();
+    }
+}
@@ -0,0 +1,471 @@
+//! The [`FileEventManager`] wraps another event manager, appending each fired [`Event`] to a log on disk.
+//! LLMP events are gone once they were handled, while the log can be analyzed, or replayed with [`replay_event_log`],
+//! after the campaign.
+
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::{marker::PhantomData, time::Duration};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{Corpus, Testcase},
+    events::{
+        CustomBufEventResult, Event, EventConfig, EventFirer, EventManager, EventManagerId,
+        EventProcessor, EventRestarter, HasCustomBufHandlers, HasEventManagerId, ProgressReporter,
+    },
+    inputs::{Input, UsesInput},
+    observers::ObserversTuple,
+    state::{HasCorpus, HasExecutions, HasLastReportTime, HasMetadata, UsesState},
+    Error,
+};
+
+/// The default size after which the [`FileEventManager`] starts a new log file
+pub const DEFAULT_EVENT_LOG_FILE_SIZE: u64 = 64 * 1024 * 1024;
+
+/// The name of the directory in the output directory holding the event logs
+pub const EVENT_LOG_DIR: &str = "events";
+
+/// An [`Event`] as written to the log by the [`FileEventManager`]
+///
+/// In the log, each record is prefixed with its length as little endian `u32`, and serialized with `postcard`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(bound = "I: serde::de::DeserializeOwned")]
+pub struct EventLogRecord<I>
+where
+    I: Input,
+{
+    /// The time since the [`FileEventManager`] was created, from a monotonic clock
+    pub time: Duration,
+    /// The type of the event, e.g. `Testcase` or `Stats`
+    pub event_type: String,
+    /// The event itself
+    pub event: Event<I>,
+}
+
+/// An [`EventManager`] that wraps another manager, and appends all fired events to a log in `<output_dir>/events/`.
+///
+/// The log is split into files `events_<n>.log`, a new one is started once the current one exceeds the maximum size.
+/// Read it back with [`EventLogReader`].
+///
+/// Log messages and progress reports are fired through this manager, so they are logged as well;
+/// the progress reports of the wrapped manager are not used.
+#[derive(Debug)]
+pub struct FileEventManager<EM> {
+    inner: EM,
+    dir: PathBuf,
+    writer: Option<BufWriter<File>>,
+    file_idx: usize,
+    file_size: u64,
+    max_file_size: u64,
+    start: Instant,
+}
+
+impl<EM> FileEventManager<EM> {
+    /// Creates a new [`FileEventManager`] wrapping `inner`, logging to `<output_dir>/events/`.
+    pub fn new<P: AsRef<Path>>(inner: EM, output_dir: P) -> Result<Self, Error> {
+        let dir = output_dir.as_ref().join(EVENT_LOG_DIR);
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            inner,
+            dir,
+            writer: None,
+            file_idx: 0,
+            file_size: 0,
+            max_file_size: DEFAULT_EVENT_LOG_FILE_SIZE,
+            start: Instant::now(),
+        })
+    }
+
+    /// Sets the size after which a new log file is started
+    #[must_use]
+    pub fn with_max_file_size(mut self, max_file_size: u64) -> Self {
+        self.max_file_size = max_file_size;
+        self
+    }
+
+    /// The directory holding the log files
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The wrapped event manager
+    #[must_use]
+    pub fn inner(&self) -> &EM {
+        &self.inner
+    }
+
+    /// Appends an encoded record to the log, rotating the log file if needed
+    fn append(&mut self, record: &[u8]) -> Result<(), Error> {
+        if self.writer.is_none() || self.file_size >= self.max_file_size {
+            if let Some(mut writer) = self.writer.take() {
+                writer.flush()?;
+                self.file_idx += 1;
+            }
+            // skip the files of earlier runs in the same output directory
+            while event_log_file(&self.dir, self.file_idx).exists() {
+                self.file_idx += 1;
+            }
+            let file = OpenOptions::new()
+                .create_new(true)
+                .write(true)
+                .open(event_log_file(&self.dir, self.file_idx))?;
+            self.writer = Some(BufWriter::new(file));
+            self.file_size = 0;
+        }
+
+        let len = u32::try_from(record.len())
+            .map_err(|_| Error::illegal_argument("Event too large for the event log"))?;
+        let writer = self.writer.as_mut().unwrap();
+        writer.write_all(&len.to_le_bytes())?;
+        writer.write_all(record)?;
+        self.file_size += 4 + u64::from(len);
+        Ok(())
+    }
+
+    /// Flushes the current log file
+    pub fn flush(&mut self) -> Result<(), Error> {
+        if let Some(writer) = self.writer.as_mut() {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// The path of the `idx`th log file in `dir`
+fn event_log_file(dir: &Path, idx: usize) -> PathBuf {
+    dir.join(format!("events_{idx}.log"))
+}
+
+impl<EM> Drop for FileEventManager<EM> {
+    fn drop(&mut self) {
+        drop(self.flush());
+    }
+}
+
+impl<EM> UsesState for FileEventManager<EM>
+where
+    EM: UsesState,
+{
+    type State = EM::State;
+}
+
+impl<EM> EventFirer for FileEventManager<EM>
+where
+    EM: EventFirer,
+{
+    fn fire(
+        &mut self,
+        state: &mut Self::State,
+        event: Event<<Self::State as UsesInput>::Input>,
+    ) -> Result<(), Error> {
+        let record = EventLogRecord {
+            time: self.start.elapsed(),
+            event_type: event.name().into(),
+            event,
+        };
+        self.append(&postcard::to_allocvec(&record)?)?;
+        self.inner.fire(state, record.event)
+    }
+
+    #[inline]
+    fn serialize_observers<OT>(&mut self, observers: &OT) -> Result<Option<Vec<u8>>, Error>
+    where
+        OT: ObserversTuple<Self::State> + Serialize,
+    {
+        self.inner.serialize_observers(observers)
+    }
+
+    #[inline]
+    fn configuration(&self) -> EventConfig {
+        self.inner.configuration()
+    }
+}
+
+impl<EM> EventRestarter for FileEventManager<EM>
+where
+    EM: EventRestarter,
+{
+    #[inline]
+    fn on_restart(&mut self, state: &mut Self::State) -> Result<(), Error> {
+        self.flush()?;
+        self.inner.on_restart(state)
+    }
+
+    #[inline]
+    fn send_exiting(&mut self) -> Result<(), Error> {
+        self.flush()?;
+        self.inner.send_exiting()
+    }
+
+    #[inline]
+    fn await_restart_safe(&mut self) {
+        self.inner.await_restart_safe();
+    }
+}
+
+impl<E, EM, Z> EventProcessor<E, Z> for FileEventManager<EM>
+where
+    EM: EventProcessor<E, Z>,
+{
+    #[inline]
+    fn process(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut Self::State,
+        executor: &mut E,
+    ) -> Result<usize, Error> {
+        self.inner.process(fuzzer, state, executor)
+    }
+}
+
+impl<E, EM, Z> EventManager<E, Z> for FileEventManager<EM>
+where
+    EM: EventManager<E, Z>,
+    EM::State: HasLastReportTime + HasExecutions + HasMetadata,
+{
+}
+
+impl<EM> HasCustomBufHandlers for FileEventManager<EM>
+where
+    Self: UsesState,
+    EM: HasCustomBufHandlers<State = Self::State>,
+{
+    #[inline]
+    fn add_custom_buf_handler(
+        &mut self,
+        handler: Box<
+            dyn FnMut(&mut Self::State, &str, &[u8]) -> Result<CustomBufEventResult, Error>,
+        >,
+    ) {
+        self.inner.add_custom_buf_handler(handler);
+    }
+}
+
+/// Uses the default progress reports, fired through [`FileEventManager::fire`] so that they reach the log
+impl<EM> ProgressReporter for FileEventManager<EM>
+where
+    Self: UsesState,
+    EM: ProgressReporter<State = Self::State>,
+    EM::State: HasLastReportTime + HasExecutions + HasMetadata,
+{
+}
+
+impl<EM> HasEventManagerId for FileEventManager<EM>
+where
+    EM: HasEventManagerId,
+{
+    #[inline]
+    fn mgr_id(&self) -> EventManagerId {
+        self.inner.mgr_id()
+    }
+}
+
+/// Reads back the [`EventLogRecord`]s written by a [`FileEventManager`], in the order they were fired.
+#[derive(Debug)]
+pub struct EventLogReader<I> {
+    files: Vec<PathBuf>,
+    reader: Option<BufReader<File>>,
+    phantom: PhantomData<I>,
+}
+
+impl<I> EventLogReader<I>
+where
+    I: Input,
+{
+    /// Opens the log in `dir`, the `events` directory in the output directory of the [`FileEventManager`]
+    pub fn new<P: AsRef<Path>>(dir: P) -> Result<Self, Error> {
+        let mut files: Vec<(usize, PathBuf)> = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let idx = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix("events_"))
+                .and_then(|name| name.strip_suffix(".log"))
+                .and_then(|idx| idx.parse().ok());
+            if let Some(idx) = idx {
+                files.push((idx, path));
+            }
+        }
+        // the reader pops the files from the back
+        files.sort_unstable_by(|a, b| b.0.cmp(&a.0));
+        Ok(Self {
+            files: files.into_iter().map(|(_, path)| path).collect(),
+            reader: None,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Reads the next record, `None` at the end of the log
+    pub fn next_record(&mut self) -> Result<Option<EventLogRecord<I>>, Error> {
+        loop {
+            if self.reader.is_none() {
+                let Some(path) = self.files.pop() else {
+                    return Ok(None);
+                };
+                self.reader = Some(BufReader::new(File::open(path)?));
+            }
+            let reader = self.reader.as_mut().unwrap();
+
+            let mut len = [0_u8; 4];
+            match reader.read_exact(&mut len) {
+                Ok(()) => (),
+                Err(err) if err.kind() == ErrorKind::UnexpectedEof => {
+                    // end of this file, a partially written record is dropped
+                    self.reader = None;
+                    continue;
+                }
+                Err(err) => return Err(err.into()),
+            }
+            let mut record = vec![0_u8; u32::from_le_bytes(len) as usize];
+            match reader.read_exact(&mut record) {
+                Ok(()) => return Ok(Some(postcard::from_bytes(&record)?)),
+                Err(err) if err.kind() == ErrorKind::UnexpectedEof => self.reader = None,
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
+
+impl<I> Iterator for EventLogReader<I>
+where
+    I: Input,
+{
+    type Item = Result<EventLogRecord<I>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+/// Replays all events logged in `dir` by a [`FileEventManager`], in order, reconstructing the campaign in a fresh `state`:
+/// the input of each [`Event::NewTestcase`] is added to its corpus, and its executions follow the reported ones.
+/// Each event is then fired again to `mgr`, e.g. to rebuild the stats in its monitor.
+/// Returns the number of replayed events.
+pub fn replay_event_log<EM, P>(dir: P, mgr: &mut EM, state: &mut EM::State) -> Result<usize, Error>
+where
+    EM: EventFirer,
+    EM::State: HasCorpus + HasExecutions,
+    P: AsRef<Path>,
+{
+    let mut count = 0;
+    for record in EventLogReader::<<EM::State as UsesInput>::Input>::new(dir)? {
+        let event = record?.event;
+        match &event {
+            Event::NewTestcase {
+                input, executions, ..
+            } => {
+                state
+                    .corpus_mut()
+                    .add(Testcase::with_executions(input.clone(), *executions))?;
+                *state.executions_mut() = *executions;
+            }
+            Event::UpdateExecStats { executions, .. } => *state.executions_mut() = *executions,
+            #[cfg(feature = "introspection")]
+            Event::UpdatePerfMonitor { executions, .. } => *state.executions_mut() = *executions,
+            _ => (),
+        }
+        mgr.fire(state, event)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::{marker::PhantomData, time::Duration};
+    use std::{env, fs, process};
+
+    use super::{replay_event_log, EventLogReader, FileEventManager};
+    use crate::{
+        corpus::Corpus,
+        events::{Event, EventConfig, EventFirer, LogSeverity, NopEventManager, ProgressReporter},
+        executors::ExitKind,
+        inputs::BytesInput,
+        state::{test::test_std_state, HasCorpus, HasExecutions, NopState},
+    };
+
+    #[test]
+    fn test_event_log_roundtrip() {
+        let dir = env::temp_dir().join(format!("libafl_test_event_log_{}", process::id()));
+        drop(fs::remove_dir_all(&dir));
+
+        let mut state = NopState::<BytesInput>::new();
+        let mut mgr = FileEventManager::new(NopEventManager::new(), &dir)
+            .unwrap()
+            .with_max_file_size(1);
+        for executions in 0..3 {
+            mgr.fire(
+                &mut state,
+                Event::UpdateExecStats {
+                    time: Duration::ZERO,
+                    executions,
+                    phantom: PhantomData,
+                },
+            )
+            .unwrap();
+        }
+        mgr.flush().unwrap();
+
+        let executions: Vec<usize> = EventLogReader::<BytesInput>::new(mgr.dir())
+            .unwrap()
+            .map(|record| match record.unwrap().event {
+                Event::UpdateExecStats { executions, .. } => executions,
+                _ => panic!("unexpected event"),
+            })
+            .collect();
+        assert_eq!(executions, [0, 1, 2]);
+
+        drop(mgr);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_event_log_replay() {
+        let dir = env::temp_dir().join(format!("libafl_test_event_replay_{}", process::id()));
+        drop(fs::remove_dir_all(&dir));
+        let mut state = test_std_state::<BytesInput>();
+        let mut mgr = FileEventManager::new(NopEventManager::new(), &dir).unwrap();
+        for (idx, bytes) in [&b"first"[..], &b"second"[..]].into_iter().enumerate() {
+            mgr.fire(
+                &mut state,
+                Event::NewTestcase {
+                    input: BytesInput::new(bytes.to_vec()),
+                    observers_buf: None,
+                    exit_kind: ExitKind::Ok,
+                    corpus_size: idx + 1,
+                    client_config: EventConfig::AlwaysUnique,
+                    time: Duration::ZERO,
+                    executions: 10 * (idx + 1),
+                    forward_id: None,
+                },
+            )
+            .unwrap();
+        }
+        // log messages and progress reports go through the log as well
+        mgr.log(&mut state, LogSeverity::Info, "hello".into())
+            .unwrap();
+        *state.executions_mut() = 42;
+        mgr.report_progress(&mut state).unwrap();
+        mgr.flush().unwrap();
+
+        let mut replayed = test_std_state::<BytesInput>();
+        let count =
+            replay_event_log(mgr.dir(), &mut NopEventManager::new(), &mut replayed).unwrap();
+        // two testcases, the log message, and at least one progress report
+        assert!(count >= 4);
+        assert_eq!(replayed.corpus().count(), 2);
+        assert_eq!(*replayed.executions(), 42);
+
+        drop(mgr);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,515 @@
+//! The [`InfluxDB2EventManager`] wraps another event manager, pushing the metrics of the fuzzer
+//! to an `InfluxDB` v2 instance, for time-series dashboards of distributed campaigns.
+
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::time::Duration;
+use std::{
+    env,
+    sync::mpsc::{self, SyncSender, TrySendError},
+    thread,
+};
+
+#[cfg(feature = "cli")]
+use libafl_bolts::cli::FuzzerOptions;
+use libafl_bolts::current_time;
+use reqwest_client::blocking::Client;
+use serde::Serialize;
+
+use crate::{
+    corpus::Corpus,
+    events::{
+        CustomBufEventResult, Event, EventConfig, EventFirer, EventManager, EventManagerId,
+        EventProcessor, EventRestarter, HasCustomBufHandlers, HasEventManagerId, LogSeverity,
+        ProgressReporter,
+    },
+    inputs::UsesInput,
+    observers::ObserversTuple,
+    state::{HasCorpus, HasExecutions, HasLastReportTime, HasMetadata, HasSolutions, UsesState},
+    Error,
+};
+
+/// The measurement the metrics are written to
+pub const INFLUXDB2_MEASUREMENT: &str = "libafl";
+
+/// The default minimum time between two pushes
+pub const DEFAULT_PUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The number of pushes waiting for the writer thread, before new ones are dropped
+pub const PUSH_QUEUE_LEN: usize = 16;
+
+/// Where and how to write to an `InfluxDB` v2 instance
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InfluxDB2Config {
+    /// The base URL of the instance, e.g. `http://localhost:8086`
+    pub url: String,
+    /// The API token, with write access to the bucket
+    pub token: String,
+    /// The bucket the metrics are written to
+    pub bucket: String,
+    /// The organization owning the bucket
+    pub org: String,
+}
+
+impl InfluxDB2Config {
+    /// Creates a new [`InfluxDB2Config`]
+    #[must_use]
+    pub fn new(url: &str, token: &str, bucket: &str, org: &str) -> Self {
+        Self {
+            url: url.into(),
+            token: token.into(),
+            bucket: bucket.into(),
+            org: org.into(),
+        }
+    }
+
+    /// Reads the config from the `INFLUXDB2_URL`, `INFLUXDB2_TOKEN`, `INFLUXDB2_BUCKET`, and `INFLUXDB2_ORG`
+    /// environment variables
+    pub fn from_env() -> Result<Self, Error> {
+        Ok(Self {
+            url: env_var("INFLUXDB2_URL")?,
+            token: env_var("INFLUXDB2_TOKEN")?,
+            bucket: env_var("INFLUXDB2_BUCKET")?,
+            org: env_var("INFLUXDB2_ORG")?,
+        })
+    }
+
+    /// Takes the config from the `--influxdb2-*` commandline options,
+    /// falling back to the environment variables of [`InfluxDB2Config::from_env`] for those not given.
+    #[cfg(feature = "cli")]
+    pub fn from_options(options: &FuzzerOptions) -> Result<Self, Error> {
+        let or_env = |value: &Option<String>, key: &str| match value {
+            Some(value) => Ok(value.clone()),
+            None => env_var(key),
+        };
+        Ok(Self {
+            url: or_env(&options.influxdb2_url, "INFLUXDB2_URL")?,
+            token: or_env(&options.influxdb2_token, "INFLUXDB2_TOKEN")?,
+            bucket: or_env(&options.influxdb2_bucket, "INFLUXDB2_BUCKET")?,
+            org: or_env(&options.influxdb2_org, "INFLUXDB2_ORG")?,
+        })
+    }
+
+    /// The URL of the write endpoint
+    fn write_url(&self) -> String {
+        format!("{}/api/v2/write", self.url.trim_end_matches('/'))
+    }
+}
+
+fn env_var(key: &str) -> Result<String, Error> {
+    env::var(key).map_err(|_| Error::illegal_argument(format!("{key} is not set")))
+}
+
+/// The metrics of a fuzzer instance at one point in time
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Metrics {
+    executions: usize,
+    corpus_size: usize,
+    crash_count: usize,
+    exec_per_sec: f64,
+}
+
+impl Metrics {
+    /// Formats the metrics as a line of the `InfluxDB` line protocol, with a timestamp in seconds
+    fn to_line(self, client: EventManagerId, time: Duration) -> String {
+        format!(
+            "{INFLUXDB2_MEASUREMENT},client={} executions={}i,corpus_size={}i,crash_count={}i,exec_per_sec={} {}",
+            client.0,
+            self.executions,
+            self.corpus_size,
+            self.crash_count,
+            self.exec_per_sec,
+            time.as_secs()
+        )
+    }
+}
+
+/// Writes lines of the line protocol to the bucket of an `InfluxDB` v2 instance, blocking
+#[derive(Debug)]
+struct InfluxDB2Writer {
+    config: InfluxDB2Config,
+    client: Client,
+}
+
+impl InfluxDB2Writer {
+    fn new(config: InfluxDB2Config) -> Result<Self, Error> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(5))
+            .build()
+            .map_err(|err| Error::unknown(format!("Cannot create the HTTP client: {err}")))?;
+        Ok(Self { config, client })
+    }
+
+    /// Writes a line of the line protocol to the bucket
+    fn write(&self, line: String) -> Result<(), Error> {
+        let response = self
+            .client
+            .post(self.config.write_url())
+            .query(&[
+                ("org", self.config.org.as_str()),
+                ("bucket", self.config.bucket.as_str()),
+                ("precision", "s"),
+            ])
+            .header("Authorization", format!("Token {}", self.config.token))
+            .header("Content-Type", "text/plain; charset=utf-8")
+            .body(line)
+            .send()
+            .map_err(|err| Error::unknown(format!("Cannot write to InfluxDB: {err}")))?;
+        if !response.status().is_success() {
+            return Err(Error::unknown(format!(
+                "InfluxDB rejected the write: {}",
+                response.status()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Spawns a thread writing the lines sent to the returned channel, until it is dropped
+    fn spawn(self) -> Result<SyncSender<String>, Error> {
+        let (sender, receiver) = mpsc::sync_channel::<String>(PUSH_QUEUE_LEN);
+        thread::Builder::new()
+            .name("influxdb2".into())
+            .spawn(move || {
+                for line in receiver {
+                    if let Err(err) = self.write(line) {
+                        log::warn!("Skipping the metrics push: {err}");
+                    }
+                }
+            })?;
+        Ok(sender)
+    }
+}
+
+/// An [`EventManager`] that wraps another manager, and pushes the executions, corpus size, crash count,
+/// and executions per second to `InfluxDB` on each fired [`Event::UpdateUserStats`].
+///
+/// The fuzzer fires user stats when a feedback updates them, e.g. on new coverage.
+/// For a push at a fixed interval, wrap this manager in a [`crate::events::StatsEventManager`].
+/// Pushes are at least [`DEFAULT_PUSH_INTERVAL`] apart. They are written by a background thread, started
+/// on the first push, so a slow or unreachable instance never stalls the fuzzer: failed pushes are logged
+/// and skipped, and pushes beyond [`PUSH_QUEUE_LEN`] waiting ones are dropped.
+#[derive(Debug)]
+pub struct InfluxDB2EventManager<EM> {
+    inner: EM,
+    config: InfluxDB2Config,
+    /// The channel to the writer thread, once it was started
+    sender: Option<SyncSender<String>>,
+    push_interval: Duration,
+    /// The time and executions of the last push, or of the creation before the first push
+    last_push: (Duration, usize),
+}
+
+impl<EM> InfluxDB2EventManager<EM> {
+    /// Creates a new [`InfluxDB2EventManager`] wrapping `inner`, pushing to the instance of `config`
+    pub fn new(inner: EM, config: InfluxDB2Config) -> Result<Self, Error> {
+        Ok(Self {
+            inner,
+            config,
+            sender: None,
+            push_interval: DEFAULT_PUSH_INTERVAL,
+            last_push: (current_time(), 0),
+        })
+    }
+
+    /// Sets the minimum time between two pushes
+    #[must_use]
+    pub fn push_interval(mut self, push_interval: Duration) -> Self {
+        self.push_interval = push_interval;
+        self
+    }
+
+    /// The config of the instance the metrics are pushed to
+    #[must_use]
+    pub fn config(&self) -> &InfluxDB2Config {
+        &self.config
+    }
+
+    /// The wrapped event manager
+    #[must_use]
+    pub fn inner(&self) -> &EM {
+        &self.inner
+    }
+
+    /// Hands a line of the line protocol to the writer thread, starting it if needed, without blocking
+    fn enqueue(&mut self, line: String) -> Result<(), Error> {
+        let sender = match &self.sender {
+            Some(sender) => sender,
+            None => self
+                .sender
+                .insert(InfluxDB2Writer::new(self.config.clone())?.spawn()?),
+        };
+        match sender.try_send(line) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => Err(Error::unknown(
+                "InfluxDB is too slow, dropping the metrics push",
+            )),
+            Err(TrySendError::Disconnected(_)) => {
+                self.sender = None;
+                Err(Error::unknown("The InfluxDB writer thread is gone"))
+            }
+        }
+    }
+
+    /// Pushes the metrics of `state`, unless the last push was less than `push_interval` ago
+    #[allow(clippy::cast_precision_loss)]
+    fn push<S>(&mut self, state: &S, client: EventManagerId)
+    where
+        S: HasCorpus + HasSolutions + HasExecutions,
+    {
+        let now = current_time();
+        let (last_time, last_executions) = self.last_push;
+        let elapsed = now.saturating_sub(last_time);
+        if elapsed < self.push_interval {
+            return;
+        }
+
+        let executions = *state.executions();
+        let metrics = Metrics {
+            executions,
+            corpus_size: state.corpus().count(),
+            crash_count: state.solutions().count(),
+            exec_per_sec: executions.saturating_sub(last_executions) as f64 / elapsed.as_secs_f64(),
+        };
+        self.last_push = (now, executions);
+        if let Err(err) = self.enqueue(metrics.to_line(client, now)) {
+            log::warn!("Skipping the metrics push: {err}");
+        }
+    }
+}
+
+impl<EM> UsesState for InfluxDB2EventManager<EM>
+where
+    EM: UsesState,
+{
+    type State = EM::State;
+}
+
+impl<EM> EventFirer for InfluxDB2EventManager<EM>
+where
+    EM: EventFirer + HasEventManagerId,
+    EM::State: HasCorpus + HasSolutions + HasExecutions,
+{
+    fn fire(
+        &mut self,
+        state: &mut Self::State,
+        event: Event<<Self::State as UsesInput>::Input>,
+    ) -> Result<(), Error> {
+        if matches!(event, Event::UpdateUserStats { .. }) {
+            let client = self.inner.mgr_id();
+            self.push(state, client);
+        }
+        self.inner.fire(state, event)
+    }
+
+    #[inline]
+    fn log(
+        &mut self,
+        state: &mut Self::State,
+        severity_level: LogSeverity,
+        message: String,
+    ) -> Result<(), Error> {
+        self.inner.log(state, severity_level, message)
+    }
+
+    #[inline]
+    fn serialize_observers<OT>(&mut self, observers: &OT) -> Result<Option<Vec<u8>>, Error>
+    where
+        OT: ObserversTuple<Self::State> + Serialize,
+    {
+        self.inner.serialize_observers(observers)
+    }
+
+    #[inline]
+    fn configuration(&self) -> EventConfig {
+        self.inner.configuration()
+    }
+}
+
+impl<EM> EventRestarter for InfluxDB2EventManager<EM>
+where
+    EM: EventRestarter,
+{
+    #[inline]
+    fn on_restart(&mut self, state: &mut Self::State) -> Result<(), Error> {
+        self.inner.on_restart(state)
+    }
+
+    #[inline]
+    fn send_exiting(&mut self) -> Result<(), Error> {
+        self.inner.send_exiting()
+    }
+
+    #[inline]
+    fn await_restart_safe(&mut self) {
+        self.inner.await_restart_safe();
+    }
+}
+
+impl<E, EM, Z> EventProcessor<E, Z> for InfluxDB2EventManager<EM>
+where
+    EM: EventProcessor<E, Z>,
+{
+    #[inline]
+    fn process(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut Self::State,
+        executor: &mut E,
+    ) -> Result<usize, Error> {
+        self.inner.process(fuzzer, state, executor)
+    }
+}
+
+impl<E, EM, Z> EventManager<E, Z> for InfluxDB2EventManager<EM>
+where
+    EM: EventManager<E, Z>,
+    EM::State: HasLastReportTime + HasExecutions + HasMetadata + HasCorpus + HasSolutions,
+{
+}
+
+impl<EM> HasCustomBufHandlers for InfluxDB2EventManager<EM>
+where
+    Self: UsesState,
+    EM: HasCustomBufHandlers<State = Self::State>,
+{
+    #[inline]
+    fn add_custom_buf_handler(
+        &mut self,
+        handler: Box<
+            dyn FnMut(&mut Self::State, &str, &[u8]) -> Result<CustomBufEventResult, Error>,
+        >,
+    ) {
+        self.inner.add_custom_buf_handler(handler);
+    }
+}
+
+impl<EM> ProgressReporter for InfluxDB2EventManager<EM>
+where
+    Self: UsesState,
+    EM: ProgressReporter<State = Self::State> + HasEventManagerId,
+    EM::State: HasLastReportTime + HasExecutions + HasMetadata + HasCorpus + HasSolutions,
+{
+    #[inline]
+    fn maybe_report_progress(
+        &mut self,
+        state: &mut Self::State,
+        monitor_timeout: Duration,
+    ) -> Result<(), Error> {
+        self.inner.maybe_report_progress(state, monitor_timeout)
+    }
+
+    #[inline]
+    fn report_progress(&mut self, state: &mut Self::State) -> Result<(), Error> {
+        self.inner.report_progress(state)
+    }
+}
+
+impl<EM> HasEventManagerId for InfluxDB2EventManager<EM>
+where
+    EM: HasEventManagerId,
+{
+    #[inline]
+    fn mgr_id(&self) -> EventManagerId {
+        self.inner.mgr_id()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{
+        string::{String, ToString},
+        vec::Vec,
+    };
+    use core::time::Duration;
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use super::{InfluxDB2Config, InfluxDB2EventManager, InfluxDB2Writer, Metrics, PUSH_QUEUE_LEN};
+    use crate::events::{EventManagerId, NopEventManager};
+
+    /// Accepts a single request, answers it with `204 No Content`, and returns the request line, headers, and body
+    fn mock_influxdb(listener: &TcpListener) -> (String, Vec<String>, String) {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+        let mut headers = vec![];
+        let mut content_length = 0;
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header).unwrap();
+            let header = header.trim_end().to_string();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((key, value)) = header.split_once(':') {
+                if key.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+            headers.push(header);
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).unwrap();
+
+        let mut stream = stream;
+        stream
+            .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+            .unwrap();
+        (request_line, headers, String::from_utf8(body).unwrap())
+    }
+
+    #[test]
+    fn test_influxdb2_write() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let server = thread::spawn(move || mock_influxdb(&listener));
+
+        let writer =
+            InfluxDB2Writer::new(InfluxDB2Config::new(&url, "secret", "fuzzing", "lab")).unwrap();
+        let metrics = Metrics {
+            executions: 1000,
+            corpus_size: 12,
+            crash_count: 1,
+            exec_per_sec: 250.5,
+        };
+        writer
+            .write(metrics.to_line(EventManagerId(3), Duration::from_secs(1_700_000_000)))
+            .unwrap();
+
+        let (request_line, headers, body) = server.join().unwrap();
+        assert!(request_line.starts_with("POST /api/v2/write?org=lab&bucket=fuzzing&precision=s "));
+        assert!(headers
+            .iter()
+            .any(|header| header.eq_ignore_ascii_case("authorization: Token secret")));
+        assert_eq!(
+            body,
+            "libafl,client=3 executions=1000i,corpus_size=12i,crash_count=1i,exec_per_sec=250.5 1700000000"
+        );
+    }
+    #[test]
+    fn test_influxdb2_push_does_not_block() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+
+        let mut mgr = InfluxDB2EventManager::new(
+            NopEventManager::<()>::new(),
+            InfluxDB2Config::new(&url, "secret", "fuzzing", "lab"),
+        )
+        .unwrap();
+        // nobody answers yet, so the writer thread blocks on the first line, and the queue fills up
+        let queued = (0..2 * PUSH_QUEUE_LEN)
+            .take_while(|i| {
+                mgr.enqueue(format!("libafl,client=0 executions={i}i 1"))
+                    .is_ok()
+            })
+            .count();
+        assert!((PUSH_QUEUE_LEN..=PUSH_QUEUE_LEN + 1).contains(&queued));
+
+        let (_, _, body) = mock_influxdb(&listener);
+        assert_eq!(body, "libafl,client=0 executions=0i 1");
+    }
+}
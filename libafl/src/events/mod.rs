@@ -3,8 +3,20 @@
 
 pub mod simple;
 pub use simple::*;
+pub mod stats;
+pub use stats::StatsEventManager;
+#[cfg(feature = "corpus_archive")]
+pub mod archive;
+#[cfg(feature = "corpus_archive")]
+pub use archive::ArchiveEventManager;
+#[cfg(feature = "influxdb2")]
+pub mod influxdb2;
+#[cfg(feature = "influxdb2")]
+pub use influxdb2::{InfluxDB2Config, InfluxDB2EventManager};
 #[cfg(all(unix, feature = "std"))]
 pub mod centralized;
+#[cfg(feature = "std")]
+pub mod file;
 #[cfg(all(unix, feature = "std"))]
 pub use centralized::*;
 #[cfg(feature = "std")]
@@ -15,6 +27,8 @@ pub mod llmp;
 #[cfg(feature = "tcp_manager")]
 #[allow(clippy::ignored_unit_patterns)]
 pub mod tcp;
+#[cfg(all(unix, feature = "std"))]
+pub mod unix_socket;
 #[cfg(feature = "scalability_introspection")]
 use alloc::string::ToString;
 use alloc::{boxed::Box, string::String, vec::Vec};
@@ -27,6 +41,8 @@ use core::{
 
 use ahash::RandomState;
 #[cfg(feature = "std")]
+pub use file::*;
+#[cfg(feature = "std")]
 pub use launcher::*;
 #[cfg(all(unix, feature = "std"))]
 use libafl_bolts::os::unix_signals::{siginfo_t, ucontext_t, Handler, Signal};
@@ -352,6 +368,17 @@ where
         /// Tag of this buffer
         tag: String,
     },
+    // New variants go last, to keep the serialized variant indices of the existing ones
+    /// The corpus of a client grew past its configured limit and should be compacted,
+    /// for example by culling the entries a [`crate::schedulers::MinimizerScheduler`] does not favor
+    CorpusCompact {
+        /// The current corpus size of this client
+        corpus_size: usize,
+        /// The maximum number of corpus entries
+        max_entries: usize,
+        /// [`PhantomData`]
+        phantom: PhantomData<I>,
+    },
     /*/// A custom type
     Custom {
         // TODO: Allow custom events
@@ -398,6 +425,7 @@ where
                 message: _,
                 phantom: _,
             } => "Log",
+            Event::CorpusCompact { .. } => "CorpusCompact",
             Event::CustomBuf { .. } => "CustomBuf",
             /*Event::Custom {
                 sender_id: _, /*custom_event} => custom_event.name()*/
@@ -848,7 +876,7 @@ where
 #[cfg(test)]
 mod tests {
 
-    use core::ptr::addr_of_mut;
+    use core::{marker::PhantomData, ptr::addr_of_mut};
 
     use libafl_bolts::{current_time, tuples::tuple_list, Named};
     use tuple_list::tuple_list_type;
@@ -903,6 +931,37 @@ mod tests {
             _ => panic!("mistmatch"),
         };
     }
+    #[test]
+    fn test_event_variant_indices() {
+        // the variant index is the first byte of a postcard-serialized enum
+        let custom_buf_idx = if cfg!(feature = "introspection") {
+            6
+        } else {
+            5
+        };
+
+        let e = Event::<BytesInput>::CustomBuf {
+            buf: vec![1, 2, 3],
+            tag: "tag".into(),
+        };
+        assert_eq!(postcard::to_allocvec(&e).unwrap()[0], custom_buf_idx);
+
+        let e = Event::<BytesInput>::CorpusCompact {
+            corpus_size: 1001,
+            max_entries: 1000,
+            phantom: PhantomData,
+        };
+        let serialized = postcard::to_allocvec(&e).unwrap();
+        assert_eq!(serialized[0], custom_buf_idx + 1);
+        match postcard::from_bytes::<Event<BytesInput>>(&serialized).unwrap() {
+            Event::CorpusCompact {
+                corpus_size,
+                max_entries,
+                ..
+            } => assert_eq!((corpus_size, max_entries), (1001, 1000)),
+            _ => panic!("mismatch"),
+        }
+    }
 }
 
 /// `EventManager` Python bindings
@@ -279,6 +279,14 @@ where
                 log::log!((*severity_level).into(), "{message}");
                 Ok(BrokerEventResult::Handled)
             }
+            Event::CorpusCompact {
+                corpus_size,
+                max_entries,
+                phantom: _,
+            } => {
+                log::info!("Corpus size {corpus_size} exceeds the limit of {max_entries} entries");
+                Ok(BrokerEventResult::Handled)
+            }
             Event::CustomBuf { .. } => Ok(BrokerEventResult::Forward),
             //_ => Ok(BrokerEventResult::Forward),
         }
@@ -0,0 +1,323 @@
+//! The [`StatsEventManager`] wraps another [`EventManager`], periodically firing the performance counters
+//! of the fuzzer as [`Event::UpdateUserStats`], independently of how often the fuzzer reports its progress.
+
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::{marker::PhantomData, time::Duration};
+
+use libafl_bolts::current_time;
+use serde::Serialize;
+
+use crate::{
+    corpus::Corpus,
+    events::{
+        CustomBufEventResult, Event, EventConfig, EventFirer, EventManager, EventManagerId,
+        EventProcessor, EventRestarter, HasCustomBufHandlers, HasEventManagerId, LogSeverity,
+        ProgressReporter,
+    },
+    inputs::UsesInput,
+    monitors::{AggregatorOps, UserStats, UserStatsValue},
+    observers::ObserversTuple,
+    state::{
+        HasCorpus, HasExecutions, HasLastReportTime, HasMetadata, HasSolutions, State, UsesState,
+    },
+    Error,
+};
+
+/// The default interval between two stats events, 15 seconds
+pub const DEFAULT_STATS_INTERVAL: Duration = Duration::from_secs(15);
+
+/// An [`EventManager`] wrapping another manager, firing the executions per second, the corpus size,
+/// and the number of crashes as [`Event::UpdateUserStats`] every `stats_interval`.
+///
+/// The stats are fired from [`ProgressReporter::maybe_report_progress`], which the fuzz loop calls once per iteration.
+#[derive(Debug)]
+pub struct StatsEventManager<EM> {
+    inner: EM,
+    stats_interval: Duration,
+    /// The time the last stats were fired at, `None` before the first report
+    last_stats_time: Option<Duration>,
+    /// The number of executions when the last stats were fired
+    last_executions: usize,
+}
+
+impl<EM> StatsEventManager<EM> {
+    /// Creates a new [`StatsEventManager`] wrapping `inner`, firing stats every [`DEFAULT_STATS_INTERVAL`]
+    #[must_use]
+    pub fn new(inner: EM) -> Self {
+        Self {
+            inner,
+            stats_interval: DEFAULT_STATS_INTERVAL,
+            last_stats_time: None,
+            last_executions: 0,
+        }
+    }
+
+    /// Sets the interval between two stats events
+    #[must_use]
+    pub fn stats_interval(mut self, stats_interval: Duration) -> Self {
+        self.stats_interval = stats_interval;
+        self
+    }
+
+    /// Sets the interval between two stats events, in seconds
+    #[must_use]
+    pub fn stats_interval_secs(self, secs: u64) -> Self {
+        self.stats_interval(Duration::from_secs(secs))
+    }
+
+    /// Retrieve the wrapped manager
+    pub fn inner(&mut self) -> &mut EM {
+        &mut self.inner
+    }
+}
+
+impl<EM> StatsEventManager<EM>
+where
+    EM: EventFirer,
+    EM::State: HasExecutions + HasCorpus + HasSolutions,
+{
+    /// Fires the stats if `stats_interval` passed since the last ones
+    #[allow(clippy::cast_precision_loss)]
+    fn maybe_fire_stats(&mut self, state: &mut EM::State) -> Result<(), Error> {
+        let cur = current_time();
+        let executions = *state.executions();
+        let Some(last_stats_time) = self.last_stats_time else {
+            self.last_stats_time = Some(cur);
+            self.last_executions = executions;
+            return Ok(());
+        };
+        // default to 0 here to avoid crashes on clock skew
+        let elapsed = cur.checked_sub(last_stats_time).unwrap_or_default();
+        if elapsed <= self.stats_interval {
+            return Ok(());
+        }
+
+        let execs_per_sec =
+            executions.saturating_sub(self.last_executions) as f64 / elapsed.as_secs_f64();
+        let stats = [
+            (
+                "execs/sec",
+                UserStatsValue::Float(execs_per_sec),
+                AggregatorOps::Sum,
+            ),
+            (
+                "corpus size",
+                UserStatsValue::Number(state.corpus().count() as u64),
+                AggregatorOps::Sum,
+            ),
+            (
+                "crashes",
+                UserStatsValue::Number(state.solutions().count() as u64),
+                AggregatorOps::Sum,
+            ),
+        ];
+        for (name, value, aggregator) in stats {
+            self.inner.fire(
+                state,
+                Event::UpdateUserStats {
+                    name: String::from(name),
+                    value: UserStats::new(value, aggregator),
+                    phantom: PhantomData,
+                },
+            )?;
+        }
+
+        self.last_stats_time = Some(cur);
+        self.last_executions = executions;
+        Ok(())
+    }
+}
+
+impl<EM> UsesState for StatsEventManager<EM>
+where
+    EM: UsesState,
+{
+    type State = EM::State;
+}
+
+impl<EM> EventFirer for StatsEventManager<EM>
+where
+    EM: EventFirer,
+{
+    #[inline]
+    fn fire(
+        &mut self,
+        state: &mut Self::State,
+        event: Event<<Self::State as UsesInput>::Input>,
+    ) -> Result<(), Error> {
+        self.inner.fire(state, event)
+    }
+
+    #[inline]
+    fn log(
+        &mut self,
+        state: &mut Self::State,
+        severity_level: LogSeverity,
+        message: String,
+    ) -> Result<(), Error> {
+        self.inner.log(state, severity_level, message)
+    }
+
+    #[inline]
+    fn serialize_observers<OT>(&mut self, observers: &OT) -> Result<Option<Vec<u8>>, Error>
+    where
+        OT: ObserversTuple<Self::State> + Serialize,
+    {
+        self.inner.serialize_observers(observers)
+    }
+
+    #[inline]
+    fn configuration(&self) -> EventConfig {
+        self.inner.configuration()
+    }
+}
+
+impl<EM> EventRestarter for StatsEventManager<EM>
+where
+    EM: EventRestarter,
+{
+    #[inline]
+    fn on_restart(&mut self, state: &mut Self::State) -> Result<(), Error> {
+        self.inner.on_restart(state)
+    }
+
+    #[inline]
+    fn send_exiting(&mut self) -> Result<(), Error> {
+        self.inner.send_exiting()
+    }
+
+    #[inline]
+    fn await_restart_safe(&mut self) {
+        self.inner.await_restart_safe();
+    }
+}
+
+impl<E, EM, Z> EventProcessor<E, Z> for StatsEventManager<EM>
+where
+    EM: EventProcessor<E, Z>,
+{
+    #[inline]
+    fn process(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut Self::State,
+        executor: &mut E,
+    ) -> Result<usize, Error> {
+        self.inner.process(fuzzer, state, executor)
+    }
+}
+
+impl<E, EM, Z> EventManager<E, Z> for StatsEventManager<EM>
+where
+    EM: EventManager<E, Z>,
+    EM::State: HasLastReportTime + HasExecutions + HasMetadata + HasCorpus + HasSolutions + State,
+{
+}
+
+impl<EM> HasCustomBufHandlers for StatsEventManager<EM>
+where
+    EM: HasCustomBufHandlers,
+{
+    #[inline]
+    fn add_custom_buf_handler(
+        &mut self,
+        handler: Box<
+            dyn FnMut(&mut Self::State, &str, &[u8]) -> Result<CustomBufEventResult, Error>,
+        >,
+    ) {
+        self.inner.add_custom_buf_handler(handler);
+    }
+}
+
+impl<EM> ProgressReporter for StatsEventManager<EM>
+where
+    EM: ProgressReporter,
+    EM::State: HasLastReportTime + HasExecutions + HasMetadata + HasCorpus + HasSolutions,
+{
+    #[inline]
+    fn maybe_report_progress(
+        &mut self,
+        state: &mut Self::State,
+        monitor_timeout: Duration,
+    ) -> Result<(), Error> {
+        self.inner.maybe_report_progress(state, monitor_timeout)?;
+        self.maybe_fire_stats(state)
+    }
+
+    #[inline]
+    fn report_progress(&mut self, state: &mut Self::State) -> Result<(), Error> {
+        self.inner.report_progress(state)
+    }
+}
+
+impl<EM> HasEventManagerId for StatsEventManager<EM>
+where
+    EM: HasEventManagerId,
+{
+    #[inline]
+    fn mgr_id(&self) -> EventManagerId {
+        self.inner.mgr_id()
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use alloc::vec::Vec;
+    use core::time::Duration;
+    use std::{env, fs, process, thread};
+
+    use super::StatsEventManager;
+    use crate::{
+        corpus::{Corpus, Testcase},
+        events::{
+            file::{EventLogReader, FileEventManager},
+            Event, NopEventManager, ProgressReporter,
+        },
+        inputs::BytesInput,
+        monitors::UserStatsValue,
+        state::{test::test_std_state, HasCorpus, HasExecutions},
+    };
+
+    #[test]
+    fn test_stats_event_manager() {
+        let dir = env::temp_dir().join(format!("libafl_test_stats_{}", process::id()));
+        drop(fs::remove_dir_all(&dir));
+        let mut state = test_std_state::<BytesInput>();
+        state
+            .corpus_mut()
+            .add(Testcase::new(BytesInput::new(vec![0])))
+            .unwrap();
+        let mut mgr =
+            StatsEventManager::new(FileEventManager::new(NopEventManager::new(), &dir).unwrap())
+                .stats_interval(Duration::from_millis(100));
+        let monitor_timeout = Duration::from_secs(3600);
+
+        // the first report starts the interval, the second one is within it
+        mgr.maybe_report_progress(&mut state, monitor_timeout)
+            .unwrap();
+        mgr.maybe_report_progress(&mut state, monitor_timeout)
+            .unwrap();
+        thread::sleep(Duration::from_millis(200));
+        *state.executions_mut() = 100;
+        mgr.maybe_report_progress(&mut state, monitor_timeout)
+            .unwrap();
+        mgr.inner().flush().unwrap();
+
+        let stats: Vec<_> = EventLogReader::<BytesInput>::new(mgr.inner().dir())
+            .unwrap()
+            .filter_map(|record| match record.unwrap().event {
+                Event::UpdateUserStats { name, value, .. } => Some((name, value.value().clone())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(stats.len(), 3);
+        assert!(
+            matches!(stats[0], (ref name, UserStatsValue::Float(execs)) if name == "execs/sec" && execs > 0.0)
+        );
+        assert!(matches!(stats[1], (ref name, UserStatsValue::Number(1)) if name == "corpus size"));
+        assert!(matches!(stats[2], (ref name, UserStatsValue::Number(0)) if name == "crashes"));
+
+        drop(mgr);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
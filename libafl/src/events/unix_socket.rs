@@ -0,0 +1,775 @@
+//! Unix domain socket-backed event manager, for running a handful of fuzzer instances on a single machine.
+//!
+//! Compared to the [`LlmpEventManager`](crate::events::LlmpEventManager), this does not use any shared memory,
+//! which makes it simpler to set up and debug, at the cost of throughput.
+//!
+//! Protocol: when connecting, a client sends its old [`ClientId`] (or `u32::MAX` if it's new),
+//! and the broker answers with the [`ClientId`] assigned to it.
+//! Afterwards, each message is framed as `[payload len: u32][sender: u32][postcard-serialized Event]`,
+//! all integers in little endian.
+//! The broker disconnects clients announcing payloads longer than 64 MiB, or falling too far behind
+//! reading the events forwarded to them.
+
+use alloc::{boxed::Box, vec::Vec};
+use core::{marker::PhantomData, num::NonZeroUsize, time::Duration};
+use std::{
+    env, fs,
+    io::{self, ErrorKind, Read, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::{Path, PathBuf},
+    thread,
+};
+
+use libafl_bolts::ClientId;
+use serde::Deserialize;
+
+use super::{CustomBufEventResult, CustomBufHandlerFn};
+use crate::{
+    events::{
+        BrokerEventResult, Event, EventConfig, EventFirer, EventManager, EventManagerId,
+        EventProcessor, EventRestarter, HasCustomBufHandlers, HasEventManagerId, ProgressReporter,
+    },
+    executors::{Executor, HasObservers},
+    feedbacks::transferred::TransferringMetadata,
+    fuzzer::{EvaluatorObservers, ExecutionProcessor},
+    inputs::{Input, UsesInput},
+    monitors::Monitor,
+    state::{HasExecutions, HasLastReportTime, HasMetadata, State, UsesState},
+    Error,
+};
+
+const UNDEFINED_CLIENT_ID: ClientId = ClientId(u32::MAX);
+
+/// The size of the header in front of each message: the payload length and the sender's [`ClientId`]
+const FRAME_HEADER_LEN: usize = 8;
+
+/// The largest payload a frame may announce. Longer frames are refused, instead of buffering them.
+const MAX_FRAME_LEN: usize = 64 * 1024 * 1024;
+
+/// The most bytes the broker queues for a single client. A client falling further behind is disconnected.
+const MAX_OUTBOX_LEN: usize = 4 * MAX_FRAME_LEN;
+
+/// How long the broker waits for a new client to introduce itself
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The socket path used for the given fuzzer `name`, `/tmp/libafl_<name>.sock` on most systems
+#[must_use]
+pub fn default_socket_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("libafl_{name}.sock"))
+}
+
+/// Builds a message frame for the given payload
+fn frame(client_id: ClientId, payload: &[u8]) -> Vec<u8> {
+    let len = u32::try_from(payload.len()).unwrap();
+    let mut frame = Vec::with_capacity(FRAME_HEADER_LEN + payload.len());
+    frame.extend_from_slice(&len.to_le_bytes());
+    frame.extend_from_slice(&client_id.0.to_le_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// Removes the first complete frame from `buf`, returning it whole (including its header) with its sender.
+/// Fails for frames announcing a payload longer than [`MAX_FRAME_LEN`], the stream is unusable afterwards.
+fn take_frame(buf: &mut Vec<u8>) -> Result<Option<(ClientId, Vec<u8>)>, Error> {
+    if buf.len() < FRAME_HEADER_LEN {
+        return Ok(None);
+    }
+    let len = u32::from_le_bytes(buf[..4].try_into().unwrap()) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(Error::illegal_state(format!(
+            "Frame of {len} bytes exceeds the maximum of {MAX_FRAME_LEN} bytes"
+        )));
+    }
+    if buf.len() < FRAME_HEADER_LEN + len {
+        return Ok(None);
+    }
+    let client_id = ClientId(u32::from_le_bytes(buf[4..8].try_into().unwrap()));
+    let frame = buf.drain(..FRAME_HEADER_LEN + len).collect();
+    Ok(Some((client_id, frame)))
+}
+
+/// Decodes a frame received by the broker on the connection of `client_id`.
+/// The sender in the frame header is replaced with `client_id`, as clients may not speak for each other.
+/// Returns `None`, after logging, for frames that do not decode.
+fn receive_frame<I>(client_id: ClientId, mut frame: Vec<u8>) -> Option<(Event<I>, Vec<u8>)>
+where
+    I: Input,
+{
+    frame[4..FRAME_HEADER_LEN].copy_from_slice(&client_id.0.to_le_bytes());
+    match postcard::from_bytes(&frame[FRAME_HEADER_LEN..]) {
+        Ok(event) => Some((event, frame)),
+        Err(e) => {
+            log::warn!("Dropping a malformed event from {client_id:?}: {e}");
+            None
+        }
+    }
+}
+
+/// Reads everything currently available on the `nonblocking` `stream` into `buf`.
+/// Returns `false` if the other side closed the connection.
+fn read_available(stream: &mut UnixStream, buf: &mut Vec<u8>) -> io::Result<bool> {
+    let mut chunk = [0_u8; 4096];
+    loop {
+        match stream.read(&mut chunk) {
+            Ok(0) => return Ok(false),
+            Ok(read) => buf.extend_from_slice(&chunk[..read]),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(true),
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Writes as much of `buf` as possible to the `nonblocking` `stream`, removing the written bytes from it.
+fn write_available(stream: &mut UnixStream, buf: &mut Vec<u8>) -> io::Result<()> {
+    let mut written = 0;
+    while written < buf.len() {
+        match stream.write(&buf[written..]) {
+            Ok(0) => return Err(ErrorKind::WriteZero.into()),
+            Ok(len) => written += len,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    buf.drain(..written);
+    Ok(())
+}
+
+/// A client, as seen from the broker
+#[derive(Debug)]
+struct BrokerClient {
+    client_id: ClientId,
+    stream: UnixStream,
+    /// Received bytes that do not form a complete frame yet
+    inbox: Vec<u8>,
+    /// Frames waiting to be forwarded to this client
+    outbox: Vec<u8>,
+}
+
+impl BrokerClient {
+    /// Writes as much of the outbox as the client accepts.
+    /// Fails if the client fell more than [`MAX_OUTBOX_LEN`] bytes behind.
+    fn flush(&mut self) -> Result<(), Error> {
+        write_available(&mut self.stream, &mut self.outbox)?;
+        if self.outbox.len() > MAX_OUTBOX_LEN {
+            return Err(Error::illegal_state(format!(
+                "{} bytes are waiting to be forwarded, more than the maximum of {MAX_OUTBOX_LEN} bytes",
+                self.outbox.len()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// A broker forwarding events between [`UnixSocketEventManager`]s over a Unix domain socket
+#[derive(Debug)]
+pub struct UnixSocketEventBroker<I, MT>
+where
+    I: Input,
+    MT: Monitor,
+{
+    monitor: MT,
+    listener: UnixListener,
+    path: PathBuf,
+    clients: Vec<BrokerClient>,
+    /// The number of clients that ever connected
+    clients_seen: usize,
+    /// Amount of all clients ever, after which (when all are disconnected) this broker should quit.
+    exit_cleanly_after: Option<NonZeroUsize>,
+    phantom: PhantomData<I>,
+}
+
+impl<I, MT> UnixSocketEventBroker<I, MT>
+where
+    I: Input,
+    MT: Monitor,
+{
+    /// Create a broker listening on [`default_socket_path`] for the given `name`.
+    pub fn new(name: &str, monitor: MT) -> Result<Self, Error> {
+        Self::with_path(default_socket_path(name), monitor)
+    }
+
+    /// Create a broker listening on the socket at `path`.
+    ///
+    /// A leftover socket file from a previous run is replaced,
+    /// but this fails if another broker is still listening on it.
+    pub fn with_path<P: AsRef<Path>>(path: P, monitor: MT) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        if path.exists() {
+            if UnixStream::connect(&path).is_ok() {
+                return Err(Error::illegal_state(format!(
+                    "Another broker is already listening on {}",
+                    path.display()
+                )));
+            }
+            fs::remove_file(&path)?;
+        }
+        let listener = UnixListener::bind(&path)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            monitor,
+            listener,
+            path,
+            clients: vec![],
+            clients_seen: 0,
+            exit_cleanly_after: None,
+            phantom: PhantomData,
+        })
+    }
+
+    /// The path of the socket this broker listens on
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Exit the broker cleanly after at least `n` clients attached and all of them disconnected again
+    pub fn set_exit_cleanly_after(&mut self, n_clients: NonZeroUsize) {
+        self.exit_cleanly_after = Some(n_clients);
+    }
+
+    /// Run in the broker until all clients exit (or forever, if [`Self::set_exit_cleanly_after`] was not called)
+    pub fn broker_loop(&mut self) -> Result<(), Error> {
+        loop {
+            let mut idle = !self.accept_clients()?;
+
+            let mut forwards = vec![];
+            let mut client_idx = 0;
+            while client_idx < self.clients.len() {
+                let client = &mut self.clients[client_idx];
+                let mut connected = match read_available(&mut client.stream, &mut client.inbox) {
+                    Ok(connected) => connected,
+                    Err(e) => {
+                        log::info!("Could not read from {:?}: {e}", client.client_id);
+                        false
+                    }
+                };
+                let sender = client.client_id;
+                loop {
+                    let frame = match take_frame(&mut client.inbox) {
+                        Ok(Some((_, frame))) => frame,
+                        Ok(None) => break,
+                        Err(e) => {
+                            log::warn!("Disconnecting {sender:?}: {e}");
+                            connected = false;
+                            break;
+                        }
+                    };
+                    idle = false;
+                    let Some((event, frame)) = receive_frame::<I>(sender, frame) else {
+                        continue;
+                    };
+                    match Self::handle_in_broker(&mut self.monitor, sender, &event)? {
+                        BrokerEventResult::Forward => forwards.push((sender, frame)),
+                        BrokerEventResult::Handled => (),
+                    }
+                }
+                let client = &mut self.clients[client_idx];
+                if connected {
+                    client_idx += 1;
+                } else {
+                    log::info!("{:?} disconnected", client.client_id);
+                    self.clients.remove(client_idx);
+                }
+            }
+
+            for (sender, frame) in &forwards {
+                for client in &mut self.clients {
+                    if client.client_id != *sender {
+                        client.outbox.extend_from_slice(frame);
+                    }
+                }
+            }
+            self.clients.retain_mut(|client| {
+                if let Err(e) = client.flush() {
+                    log::warn!("Disconnecting {:?}: {e}", client.client_id);
+                    return false;
+                }
+                true
+            });
+
+            if let Some(max_clients) = self.exit_cleanly_after {
+                if self.clients_seen >= max_clients.get() && self.clients.is_empty() {
+                    log::info!("The last client quit. Exiting.");
+                    return Ok(());
+                }
+            }
+
+            if idle {
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+    }
+
+    /// Accepts all pending connections, returns `true` if there were any.
+    fn accept_clients(&mut self) -> Result<bool, Error> {
+        let mut accepted = false;
+        loop {
+            let mut stream = match self.listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(accepted),
+                Err(e) => return Err(e.into()),
+            };
+            accepted = true;
+
+            let client_id = match self.handshake(&mut stream) {
+                Ok(client_id) => client_id,
+                Err(e) => {
+                    log::warn!("Dropping a client that failed to introduce itself: {e}");
+                    continue;
+                }
+            };
+            log::info!("{client_id:?} connected");
+
+            // a restarted client replaces its old connection
+            self.clients.retain(|client| client.client_id != client_id);
+            self.clients.push(BrokerClient {
+                client_id,
+                stream,
+                inbox: vec![],
+                outbox: vec![],
+            });
+        }
+    }
+
+    /// Exchanges the [`ClientId`] with a freshly connected client
+    fn handshake(&mut self, stream: &mut UnixStream) -> Result<ClientId, Error> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+
+        let mut client_id_buf = [0_u8; 4];
+        stream.read_exact(&mut client_id_buf)?;
+        let mut client_id = ClientId(u32::from_le_bytes(client_id_buf));
+        if client_id == UNDEFINED_CLIENT_ID {
+            // ClientIds for this broker start at 0.
+            client_id = ClientId(self.clients_seen.try_into().unwrap());
+            self.clients_seen += 1;
+        }
+        stream.write_all(&client_id.0.to_le_bytes())?;
+
+        stream.set_read_timeout(None)?;
+        stream.set_nonblocking(true)?;
+        Ok(client_id)
+    }
+
+    /// Handle arriving events in the broker
+    #[allow(clippy::unnecessary_wraps)]
+    fn handle_in_broker(
+        monitor: &mut MT,
+        client_id: ClientId,
+        event: &Event<I>,
+    ) -> Result<BrokerEventResult, Error> {
+        match &event {
+            Event::NewTestcase {
+                corpus_size,
+                time,
+                executions,
+                forward_id,
+                ..
+            } => {
+                let id = forward_id.unwrap_or(client_id);
+                monitor.client_stats_insert(id);
+                let client = monitor.client_stats_mut_for(id);
+                client.update_corpus_size(*corpus_size as u64);
+                client.update_executions(*executions as u64, *time);
+                monitor.display(event.name(), id);
+                Ok(BrokerEventResult::Forward)
+            }
+            Event::UpdateExecStats {
+                time, executions, ..
+            } => {
+                monitor.client_stats_insert(client_id);
+                let client = monitor.client_stats_mut_for(client_id);
+                client.update_executions(*executions as u64, *time);
+                monitor.display(event.name(), client_id);
+                Ok(BrokerEventResult::Handled)
+            }
+            Event::UpdateUserStats { name, value, .. } => {
+                monitor.client_stats_insert(client_id);
+                let client = monitor.client_stats_mut_for(client_id);
+                client.update_user_stats(name.clone(), value.clone());
+                monitor.aggregate(name);
+                monitor.display(event.name(), client_id);
+                Ok(BrokerEventResult::Handled)
+            }
+            #[cfg(feature = "introspection")]
+            Event::UpdatePerfMonitor {
+                time,
+                executions,
+                introspection_monitor,
+                ..
+            } => {
+                monitor.client_stats_insert(client_id);
+                let client = monitor.client_stats_mut_for(client_id);
+                client.update_executions(*executions as u64, *time);
+                client.update_introspection_monitor((**introspection_monitor).clone());
+                monitor.display(event.name(), client_id);
+                Ok(BrokerEventResult::Handled)
+            }
+            Event::Objective { objective_size } => {
+                monitor.client_stats_insert(client_id);
+                let client = monitor.client_stats_mut_for(client_id);
+                client.update_objective_size(*objective_size as u64);
+                monitor.display(event.name(), client_id);
+                Ok(BrokerEventResult::Handled)
+            }
+            Event::Log {
+                severity_level,
+                message,
+                ..
+            } => {
+                log::log!((*severity_level).into(), "{client_id:?}: {message}");
+                Ok(BrokerEventResult::Handled)
+            }
+            Event::CorpusCompact {
+                corpus_size,
+                max_entries,
+                phantom: _,
+            } => {
+                log::info!(
+                    "{client_id:?}: corpus size {corpus_size} exceeds the limit of {max_entries} entries"
+                );
+                Ok(BrokerEventResult::Handled)
+            }
+            Event::CustomBuf { .. } => Ok(BrokerEventResult::Forward),
+        }
+    }
+}
+
+impl<I, MT> Drop for UnixSocketEventBroker<I, MT>
+where
+    I: Input,
+    MT: Monitor,
+{
+    fn drop(&mut self) {
+        // the socket file is not removed automatically
+        drop(fs::remove_file(&self.path));
+    }
+}
+
+/// An [`EventManager`] that exchanges events with other fuzzer instances through a [`UnixSocketEventBroker`]
+pub struct UnixSocketEventManager<S>
+where
+    S: State,
+{
+    /// The `nonblocking` connection to the broker
+    stream: UnixStream,
+    /// Our `ClientId`
+    client_id: ClientId,
+    /// Received bytes that do not form a complete frame yet
+    inbox: Vec<u8>,
+    /// The custom buf handler
+    custom_buf_handlers: Vec<Box<CustomBufHandlerFn<S>>>,
+    /// The configuration defines this specific fuzzer.
+    /// A node will not re-use the observer values sent over the socket
+    /// from nodes with other configurations.
+    configuration: EventConfig,
+    phantom: PhantomData<S>,
+}
+
+impl<S> core::fmt::Debug for UnixSocketEventManager<S>
+where
+    S: State,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("UnixSocketEventManager")
+            .field("stream", &self.stream)
+            .field("client_id", &self.client_id)
+            .field("configuration", &self.configuration)
+            .field("phantom", &self.phantom)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<S> UnixSocketEventManager<S>
+where
+    S: State + HasExecutions + HasMetadata,
+{
+    /// Connect to the broker for the given `name`, listening on [`default_socket_path`]
+    pub fn new(name: &str, configuration: EventConfig) -> Result<Self, Error> {
+        Self::with_path(default_socket_path(name), configuration)
+    }
+
+    /// Connect to the broker listening on the socket at `path`
+    pub fn with_path<P: AsRef<Path>>(path: P, configuration: EventConfig) -> Result<Self, Error> {
+        Self::existing_with_path(path, UNDEFINED_CLIENT_ID, configuration)
+    }
+
+    /// Reconnect to the broker listening on the socket at `path`, specifying our old client id
+    pub fn existing_with_path<P: AsRef<Path>>(
+        path: P,
+        client_id: ClientId,
+        configuration: EventConfig,
+    ) -> Result<Self, Error> {
+        let mut stream = UnixStream::connect(path)?;
+
+        let mut client_id_buf = client_id.0.to_le_bytes();
+        stream.write_all(&client_id_buf)?;
+        stream.read_exact(&mut client_id_buf)?;
+        let client_id = ClientId(u32::from_le_bytes(client_id_buf));
+        log::info!("Our client id: {client_id:?}");
+
+        stream.set_nonblocking(true)?;
+        Ok(Self {
+            stream,
+            client_id,
+            inbox: vec![],
+            custom_buf_handlers: vec![],
+            configuration,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Our `ClientId`, as assigned by the broker
+    #[must_use]
+    pub fn client_id(&self) -> ClientId {
+        self.client_id
+    }
+
+    // Handle arriving events in the client
+    fn handle_in_client<E, Z>(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut S,
+        client_id: ClientId,
+        event: Event<S::Input>,
+    ) -> Result<(), Error>
+    where
+        E: Executor<Self, Z> + HasObservers<State = S>,
+        for<'a> E::Observers: Deserialize<'a>,
+        Z: ExecutionProcessor<E::Observers, State = S> + EvaluatorObservers<E::Observers>,
+    {
+        match event {
+            Event::NewTestcase {
+                input,
+                client_config,
+                exit_kind,
+                observers_buf,
+                ..
+            } => {
+                log::info!("Received new Testcase from {client_id:?} ({client_config:?})");
+
+                if let Ok(meta) = state.metadata_mut::<TransferringMetadata>() {
+                    meta.set_transferring(true);
+                }
+                let res = if client_config.match_with(&self.configuration)
+                    && observers_buf.is_some()
+                {
+                    let observers: E::Observers =
+                        postcard::from_bytes(observers_buf.as_ref().unwrap())?;
+                    fuzzer.process_execution(state, self, input, &observers, &exit_kind, false)?
+                } else {
+                    fuzzer.evaluate_input_with_observers::<E, Self>(
+                        state, executor, self, input, false,
+                    )?
+                };
+                if let Ok(meta) = state.metadata_mut::<TransferringMetadata>() {
+                    meta.set_transferring(false);
+                }
+                if let Some(item) = res.1 {
+                    log::info!("Added received Testcase as item #{item}");
+                }
+                Ok(())
+            }
+            Event::CustomBuf { tag, buf } => {
+                for handler in &mut self.custom_buf_handlers {
+                    if handler(state, &tag, &buf)? == CustomBufEventResult::Handled {
+                        break;
+                    }
+                }
+                Ok(())
+            }
+            _ => Err(Error::unknown(format!(
+                "Received illegal message that message should not have arrived: {:?}.",
+                event.name()
+            ))),
+        }
+    }
+}
+
+impl<S> UsesState for UnixSocketEventManager<S>
+where
+    S: State,
+{
+    type State = S;
+}
+
+impl<S> EventFirer for UnixSocketEventManager<S>
+where
+    S: State,
+{
+    fn fire(
+        &mut self,
+        _state: &mut Self::State,
+        event: Event<<Self::State as UsesInput>::Input>,
+    ) -> Result<(), Error> {
+        let serialized = postcard::to_allocvec(&event)?;
+        if serialized.len() > MAX_FRAME_LEN {
+            return Err(Error::illegal_argument(format!(
+                "Event of {} bytes exceeds the maximum frame length of {MAX_FRAME_LEN} bytes",
+                serialized.len()
+            )));
+        }
+        let mut outbox = frame(self.client_id, &serialized);
+        // the broker never blocks on us, so it will eventually make room in the socket buffer
+        loop {
+            write_available(&mut self.stream, &mut outbox)?;
+            if outbox.is_empty() {
+                return Ok(());
+            }
+            thread::yield_now();
+        }
+    }
+
+    fn configuration(&self) -> EventConfig {
+        self.configuration
+    }
+}
+
+impl<S> EventRestarter for UnixSocketEventManager<S> where S: State {}
+
+impl<E, S, Z> EventProcessor<E, Z> for UnixSocketEventManager<S>
+where
+    S: State + HasExecutions + HasMetadata,
+    E: HasObservers<State = S> + Executor<Self, Z>,
+    for<'a> E::Observers: Deserialize<'a>,
+    Z: EvaluatorObservers<E::Observers, State = S> + ExecutionProcessor<E::Observers, State = S>,
+{
+    fn process(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut Self::State,
+        executor: &mut E,
+    ) -> Result<usize, Error> {
+        if !read_available(&mut self.stream, &mut self.inbox)? {
+            return Err(Error::illegal_state("The broker closed the connection"));
+        }
+
+        let mut count = 0;
+        while let Some((other_client_id, frame)) = take_frame(&mut self.inbox)? {
+            if other_client_id == self.client_id {
+                log::warn!("Own ID should never have been sent by the broker");
+                continue;
+            }
+            let event = postcard::from_bytes(&frame[FRAME_HEADER_LEN..])?;
+            self.handle_in_client(fuzzer, executor, state, other_client_id, event)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+impl<E, S, Z> EventManager<E, Z> for UnixSocketEventManager<S>
+where
+    E: HasObservers<State = S> + Executor<Self, Z>,
+    for<'a> E::Observers: Deserialize<'a>,
+    S: State + HasExecutions + HasMetadata + HasLastReportTime,
+    Z: EvaluatorObservers<E::Observers, State = S> + ExecutionProcessor<E::Observers, State = S>,
+{
+}
+
+impl<S> HasCustomBufHandlers for UnixSocketEventManager<S>
+where
+    S: State,
+{
+    fn add_custom_buf_handler(
+        &mut self,
+        handler: Box<dyn FnMut(&mut S, &str, &[u8]) -> Result<CustomBufEventResult, Error>>,
+    ) {
+        self.custom_buf_handlers.push(handler);
+    }
+}
+
+impl<S> ProgressReporter for UnixSocketEventManager<S> where
+    S: State + HasExecutions + HasMetadata + HasLastReportTime
+{
+}
+
+impl<S> HasEventManagerId for UnixSocketEventManager<S>
+where
+    S: State,
+{
+    /// Gets the id assigned to this client by the broker.
+    fn mgr_id(&self) -> EventManagerId {
+        EventManagerId(self.client_id.0 as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::net::UnixStream;
+
+    use libafl_bolts::ClientId;
+
+    use super::{frame, receive_frame, take_frame, BrokerClient, MAX_FRAME_LEN, MAX_OUTBOX_LEN};
+    use crate::{events::Event, inputs::BytesInput};
+
+    #[test]
+    fn test_frames() {
+        let mut buf = frame(ClientId(1), b"first");
+        buf.extend(frame(ClientId(2), b"second"));
+        let first_len = buf.len() - frame(ClientId(2), b"second").len();
+        let mut partial = buf[..first_len + 3].to_vec();
+
+        let (client_id, first) = take_frame(&mut partial).unwrap().unwrap();
+        assert_eq!(client_id, ClientId(1));
+        assert_eq!(&first[8..], b"first");
+        assert!(take_frame(&mut partial).unwrap().is_none());
+
+        partial.extend_from_slice(&buf[first_len + 3..]);
+        let (client_id, second) = take_frame(&mut partial).unwrap().unwrap();
+        assert_eq!(client_id, ClientId(2));
+        assert_eq!(&second[8..], b"second");
+        assert!(partial.is_empty());
+    }
+    #[test]
+    fn test_receive_frame() {
+        let event = Event::<BytesInput>::CustomBuf {
+            buf: vec![1, 2, 3],
+            tag: "tag".into(),
+        };
+        let payload = postcard::to_allocvec(&event).unwrap();
+
+        // the sender claimed in the frame is replaced with the one of the connection
+        let mut buf = frame(ClientId(7), &payload);
+        let (_, received) = take_frame(&mut buf).unwrap().unwrap();
+        let (event, mut forwarded) = receive_frame::<BytesInput>(ClientId(2), received).unwrap();
+        assert!(matches!(event, Event::CustomBuf { tag, .. } if tag == "tag"));
+        assert_eq!(take_frame(&mut forwarded).unwrap().unwrap().0, ClientId(2));
+
+        // malformed frames are dropped
+        let mut buf = frame(ClientId(2), &[0xff; 4]);
+        let (_, received) = take_frame(&mut buf).unwrap().unwrap();
+        assert!(receive_frame::<BytesInput>(ClientId(2), received).is_none());
+    }
+
+    #[test]
+    fn test_frame_too_long() {
+        let mut buf = u32::try_from(MAX_FRAME_LEN + 1)
+            .unwrap()
+            .to_le_bytes()
+            .to_vec();
+        buf.extend_from_slice(&2_u32.to_le_bytes());
+        assert!(take_frame(&mut buf).is_err());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_slow_client_disconnected() {
+        let (stream, _other) = UnixStream::pair().unwrap();
+        stream.set_nonblocking(true).unwrap();
+        let mut client = BrokerClient {
+            client_id: ClientId(1),
+            stream,
+            inbox: vec![],
+            outbox: vec![],
+        };
+        // the other end never reads, the outbox only shrinks by what fits into the socket buffer
+        client.outbox = vec![0; MAX_OUTBOX_LEN];
+        client.flush().unwrap();
+        client.outbox.extend_from_slice(&vec![0; MAX_OUTBOX_LEN]);
+        assert!(client.flush().is_err());
+    }
+}
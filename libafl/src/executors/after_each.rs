@@ -0,0 +1,150 @@
+//! An [`AfterEachHookExecutor`] runs a closure after each execution of the executor it wraps.
+
+use core::fmt::{self, Debug, Formatter};
+
+use crate::{
+    executors::{Executor, ExitKind, HasObservers},
+    inputs::UsesInput,
+    observers::UsesObservers,
+    state::UsesState,
+    Error,
+};
+
+/// An [`AfterEachHookExecutor`] wraps an executor, and calls a hook after each of its executions.
+///
+/// The hook gets the input, the [`ExitKind`] of the execution, and the state, and returns the [`ExitKind`]
+/// reported for the execution, e.g. to count certain executions, or to turn them into crashes,
+/// without implementing an executor or a feedback.
+pub struct AfterEachHookExecutor<E, F> {
+    inner: E,
+    hook: F,
+}
+
+impl<E, F> Debug for AfterEachHookExecutor<E, F>
+where
+    E: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AfterEachHookExecutor")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<E, F> AfterEachHookExecutor<E, F>
+where
+    E: UsesState,
+    F: FnMut(&<E::State as UsesInput>::Input, ExitKind, &mut E::State) -> ExitKind,
+{
+    /// Create a new `AfterEachHookExecutor`, calling `hook` after each execution of `inner`
+    pub fn new(inner: E, hook: F) -> Self {
+        Self { inner, hook }
+    }
+
+    /// Retrieve the wrapped `Executor`
+    pub fn inner(&mut self) -> &mut E {
+        &mut self.inner
+    }
+}
+
+impl<E, EM, F, Z> Executor<EM, Z> for AfterEachHookExecutor<E, F>
+where
+    E: Executor<EM, Z>,
+    EM: UsesState<State = E::State>,
+    F: FnMut(&<E::State as UsesInput>::Input, ExitKind, &mut E::State) -> ExitKind,
+    Z: UsesState<State = E::State>,
+{
+    fn run_target(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut Self::State,
+        mgr: &mut EM,
+        input: &Self::Input,
+    ) -> Result<ExitKind, Error> {
+        let exit_kind = self.inner.run_target(fuzzer, state, mgr, input)?;
+        Ok((self.hook)(input, exit_kind, state))
+    }
+}
+
+impl<E, F> UsesState for AfterEachHookExecutor<E, F>
+where
+    E: UsesState,
+{
+    type State = E::State;
+}
+
+impl<E, F> UsesObservers for AfterEachHookExecutor<E, F>
+where
+    E: UsesObservers,
+{
+    type Observers = E::Observers;
+}
+
+impl<E, F> HasObservers for AfterEachHookExecutor<E, F>
+where
+    E: HasObservers,
+{
+    #[inline]
+    fn observers(&self) -> &Self::Observers {
+        self.inner.observers()
+    }
+
+    #[inline]
+    fn observers_mut(&mut self) -> &mut Self::Observers {
+        self.inner.observers_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AfterEachHookExecutor;
+    use crate::{
+        events::NopEventManager,
+        executors::{test::NopExecutor, Executor, ExitKind},
+        fuzzer::test::NopFuzzer,
+        inputs::{BytesInput, HasBytesVec},
+        state::{HasExecutions, NopState},
+    };
+
+    #[test]
+    fn test_after_each_hook_executor() {
+        let mut state = NopState::<BytesInput>::new();
+        let mut fuzzer = NopFuzzer::new();
+        let mut mgr = NopEventManager::new();
+
+        let mut calls = 0;
+        {
+            let mut executor = AfterEachHookExecutor::new(
+                NopExecutor::new(),
+                |input: &BytesInput, exit_kind, state: &mut NopState<BytesInput>| {
+                    calls += 1;
+                    assert_eq!(exit_kind, ExitKind::Ok);
+                    // the hook runs after the inner executor counted the execution
+                    assert_eq!(*state.executions(), calls);
+                    if input.bytes()[0] == b'X' {
+                        ExitKind::Crash
+                    } else {
+                        exit_kind
+                    }
+                },
+            );
+
+            for (bytes, expected) in [(b"a", ExitKind::Ok), (b"X", ExitKind::Crash)] {
+                let exit_kind = executor
+                    .run_target(
+                        &mut fuzzer,
+                        &mut state,
+                        &mut mgr,
+                        &BytesInput::new(bytes.to_vec()),
+                    )
+                    .unwrap();
+                assert_eq!(exit_kind, expected);
+            }
+            // errors of the inner executor are passed on, without calling the hook
+            assert!(executor
+                .run_target(&mut fuzzer, &mut state, &mut mgr, &BytesInput::new(vec![]))
+                .is_err());
+        }
+        assert_eq!(calls, 2);
+    }
+}
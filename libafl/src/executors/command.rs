@@ -7,7 +7,7 @@ use core::{
 #[cfg(unix)]
 use std::os::unix::ffi::OsStrExt;
 #[cfg(feature = "std")]
-use std::process::Child;
+use std::process::{Child, ExitStatus};
 use std::{
     ffi::{OsStr, OsString},
     io::{Read, Write},
@@ -87,6 +87,18 @@ pub struct StdCommandConfigurator {
     command: Command,
 }
 
+impl StdCommandConfigurator {
+    /// The command spawned for each execution
+    pub(crate) fn command_mut(&mut self) -> &mut Command {
+        &mut self.command
+    }
+
+    /// How the input is delivered to the command
+    pub(crate) fn input_location(&self) -> &InputLocation {
+        &self.input_location
+    }
+}
+
 impl CommandConfigurator for StdCommandConfigurator {
     fn spawn_child<I>(&mut self, input: &I) -> Result<Child, Error>
     where
@@ -308,50 +320,31 @@ where
     }
 }
 
-// this only works on unix because of the reliance on checking the process signal for detecting OOM
 #[cfg(all(feature = "std", unix))]
-impl<EM, OT, S, T, Z> Executor<EM, Z> for CommandExecutor<OT, S, T>
+impl<OT, S, T> CommandExecutor<OT, S, T>
 where
-    EM: UsesState<State = S>,
-    S: State + HasExecutions,
+    S: State,
     S::Input: HasTargetBytes,
     T: CommandConfigurator,
-    OT: Debug + MatchName + ObserversTuple<S>,
-    Z: UsesState<State = S>,
+    OT: MatchName + ObserversTuple<S>,
 {
-    fn run_target(
-        &mut self,
-        _fuzzer: &mut Z,
-        state: &mut Self::State,
-        _mgr: &mut EM,
-        input: &Self::Input,
-    ) -> Result<ExitKind, Error> {
-        use std::os::unix::prelude::ExitStatusExt;
-
+    /// Spawns the child for `input`, waits for it, and hands its output to the observers.
+    /// Returns the exit status of the child, or `None` if it was killed after the timeout.
+    pub(crate) fn run_child(&mut self, input: &S::Input) -> Result<Option<ExitStatus>, Error> {
         use wait_timeout::ChildExt;
 
-        *state.executions_mut() += 1;
-
         let mut child = self.configurer.spawn_child(input)?;
 
-        let res = match child
+        let status = child
             .wait_timeout(self.configurer.exec_timeout())
-            .expect("waiting on child failed")
-            .map(|status| status.signal())
-        {
-            // for reference: https://www.man7.org/linux/man-pages/man7/signal.7.html
-            Some(Some(9)) => Ok(ExitKind::Oom),
-            Some(Some(_)) => Ok(ExitKind::Crash),
-            Some(None) => Ok(ExitKind::Ok),
-            None => {
-                // if this fails, there is not much we can do. let's hope it failed because the process finished
-                // in the meantime.
-                drop(child.kill());
-                // finally, try to wait to properly clean up system resources.
-                drop(child.wait());
-                Ok(ExitKind::Timeout)
-            }
-        };
+            .expect("waiting on child failed");
+        if status.is_none() {
+            // if this fails, there is not much we can do. let's hope it failed because the process finished
+            // in the meantime.
+            drop(child.kill());
+            // finally, try to wait to properly clean up system resources.
+            drop(child.wait());
+        }
 
         if self.observers.observes_stderr() {
             let mut stderr = Vec::new();
@@ -372,7 +365,39 @@ where
             self.observers.observe_stdout(&stdout);
         }
 
-        res
+        Ok(status)
+    }
+}
+
+// this only works on unix because of the reliance on checking the process signal for detecting OOM
+#[cfg(all(feature = "std", unix))]
+impl<EM, OT, S, T, Z> Executor<EM, Z> for CommandExecutor<OT, S, T>
+where
+    EM: UsesState<State = S>,
+    S: State + HasExecutions,
+    S::Input: HasTargetBytes,
+    T: CommandConfigurator,
+    OT: Debug + MatchName + ObserversTuple<S>,
+    Z: UsesState<State = S>,
+{
+    fn run_target(
+        &mut self,
+        _fuzzer: &mut Z,
+        state: &mut Self::State,
+        _mgr: &mut EM,
+        input: &Self::Input,
+    ) -> Result<ExitKind, Error> {
+        use std::os::unix::prelude::ExitStatusExt;
+
+        *state.executions_mut() += 1;
+
+        Ok(match self.run_child(input)?.map(|status| status.signal()) {
+            // for reference: https://www.man7.org/linux/man-pages/man7/signal.7.html
+            Some(Some(9)) => ExitKind::Oom,
+            Some(Some(_)) => ExitKind::Crash,
+            Some(None) => ExitKind::Ok,
+            None => ExitKind::Timeout,
+        })
     }
 }
 
@@ -67,6 +67,29 @@ const fn fs_opt_get_mapsize(x: i32) -> i32 {
     } else { 0 }
 } */
 
+/// The signature AFL++ embeds into binaries built for persistent mode
+const PERSIST_SIG: &[u8] = b"##SIG_AFL_PERSISTENT##";
+
+/// Checks whether `binary` carries the persistent mode signature, like `afl-fuzz` does
+fn has_persistent_signature(binary: &[u8]) -> bool {
+    binary
+        .windows(PERSIST_SIG.len())
+        .any(|window| window == PERSIST_SIG)
+}
+
+/// Reads the target `program`, looking it up in `PATH` if needed, and checks it for the persistent mode signature
+fn is_persistent_program(program: &OsStr) -> bool {
+    let path = Path::new(program);
+    let binary = if path.components().count() > 1 {
+        std::fs::read(path).ok()
+    } else {
+        env::var_os("PATH").and_then(|paths| {
+            env::split_paths(&paths).find_map(|dir| std::fs::read(dir.join(path)).ok())
+        })
+    };
+    binary.map_or(false, |binary| has_persistent_signature(&binary))
+}
+
 /// The length of header bytes which tells shmem size
 const SHMEM_FUZZ_HDR_SIZE: usize = 4;
 const MAX_INPUT_SIZE_DEFAULT: usize = 1024 * 1024;
@@ -498,6 +521,10 @@ where
     phantom: PhantomData<S>,
     map_size: Option<usize>,
     timeout: TimeSpec,
+    /// The maximum number of inputs run by a persistent child, before it is replaced
+    persistent_count: Option<u32>,
+    /// The number of inputs run by the current persistent child
+    persistent_runs: u32,
 }
 
 impl<OT, S, SP> Debug for ForkserverExecutor<OT, S, SP>
@@ -573,7 +600,8 @@ pub struct ForkserverExecutorBuilder<'a, SP> {
     debug_child: bool,
     use_stdin: bool,
     uses_shmem_testcase: bool,
-    is_persistent: bool,
+    is_persistent: Option<bool>,
+    persistent_count: Option<u32>,
     is_deferred_frksrv: bool,
     autotokens: Option<&'a mut Tokens>,
     input_filename: Option<OsString>,
@@ -631,6 +659,8 @@ impl<'a, SP> ForkserverExecutorBuilder<'a, SP> {
             phantom: PhantomData,
             map_size: self.map_size,
             timeout,
+            persistent_count: self.persistent_count,
+            persistent_runs: 0,
         })
     }
 
@@ -687,9 +717,48 @@ impl<'a, SP> ForkserverExecutorBuilder<'a, SP> {
             phantom: PhantomData,
             map_size: self.map_size,
             timeout,
+            persistent_count: self.persistent_count,
+            persistent_runs: 0,
         })
     }
 
+    /// Whether the target runs in persistent mode, as set by [`ForkserverExecutorBuilder::is_persistent`].
+    /// If it was left unset, a [`ForkserverExecutorBuilder::persistent_count`] enables persistent mode
+    /// for targets with the persistent mode signature.
+    fn resolve_persistent(&self) -> bool {
+        // the target is only probed for the persistent mode signature if `is_persistent` was left unset,
+        // an explicit setting always wins
+        let program_is_persistent = || self.program.as_deref().map_or(true, is_persistent_program);
+        match self.is_persistent {
+            Some(true) => {
+                if self.persistent_count.is_some() && !program_is_persistent() {
+                    log::warn!(
+                        "{:?} lacks the persistent mode signature, using persistent mode anyway",
+                        self.program
+                    );
+                }
+                true
+            }
+            Some(false) => {
+                if self.persistent_count.is_some() {
+                    log::warn!("Persistent mode is disabled, ignoring the persistent count");
+                }
+                false
+            }
+            None if self.persistent_count.is_some() => {
+                let is_persistent = program_is_persistent();
+                if !is_persistent {
+                    log::warn!(
+                        "{:?} was not built for persistent mode, ignoring the persistent count",
+                        self.program
+                    );
+                }
+                is_persistent
+            }
+            None => false,
+        }
+    }
+
     #[allow(clippy::pedantic)]
     fn build_helper(&mut self) -> Result<(Forkserver, InputFile, Option<SP::ShMem>), Error>
     where
@@ -718,6 +787,11 @@ impl<'a, SP> ForkserverExecutorBuilder<'a, SP> {
             }
         };
 
+        let is_persistent = self.resolve_persistent();
+        if !is_persistent {
+            self.persistent_count = None;
+        }
+
         let mut forkserver = match &self.program {
             Some(t) => Forkserver::with_kill_signal(
                 t.clone(),
@@ -726,7 +800,7 @@ impl<'a, SP> ForkserverExecutorBuilder<'a, SP> {
                 input_file.as_raw_fd(),
                 self.use_stdin,
                 0,
-                self.is_persistent,
+                is_persistent,
                 self.is_deferred_frksrv,
                 self.debug_child,
                 self.kill_signal.unwrap_or(KILL_SIGNAL_DEFAULT),
@@ -994,7 +1068,22 @@ impl<'a, SP> ForkserverExecutorBuilder<'a, SP> {
     /// Call this if you want to run it under persistent mode; default is false
     #[must_use]
     pub fn is_persistent(mut self, is_persistent: bool) -> Self {
-        self.is_persistent = is_persistent;
+        self.is_persistent = Some(is_persistent);
+        self
+    }
+
+    /// Runs the harness in persistent mode, replacing the child after it ran `count` inputs.
+    ///
+    /// In persistent mode, the child stops itself after each input, and the forkserver resumes it
+    /// for the next one, until the `__AFL_LOOP` count compiled into the target is exhausted.
+    /// State leaking between the runs accumulates over that many inputs; with a lower `count`,
+    /// the executor kills the stopped child earlier, and the forkserver forks a fresh one.
+    /// Unless persistent mode is set explicitly with [`ForkserverExecutorBuilder::is_persistent`],
+    /// it is enabled if the target has the persistent mode signature, otherwise the count is ignored with a warning.
+    #[must_use]
+    pub fn persistent_count(mut self, count: u32) -> Self {
+        assert!(count > 0, "The persistent count needs to be positive");
+        self.persistent_count = Some(count);
         self
     }
 
@@ -1036,7 +1125,8 @@ impl<'a> ForkserverExecutorBuilder<'a, UnixShMemProvider> {
             debug_child: false,
             use_stdin: false,
             uses_shmem_testcase: false,
-            is_persistent: false,
+            is_persistent: None,
+            persistent_count: None,
             is_deferred_frksrv: false,
             autotokens: None,
             input_filename: None,
@@ -1062,6 +1152,7 @@ impl<'a> ForkserverExecutorBuilder<'a, UnixShMemProvider> {
             use_stdin: self.use_stdin,
             uses_shmem_testcase: self.uses_shmem_testcase,
             is_persistent: self.is_persistent,
+            persistent_count: self.persistent_count,
             is_deferred_frksrv: self.is_deferred_frksrv,
             autotokens: self.autotokens,
             input_filename: self.input_filename,
@@ -1102,7 +1193,19 @@ where
 
         let mut exit_kind = ExitKind::Ok;
 
-        let last_run_timed_out = self.forkserver.last_run_timed_out_raw();
+        let mut last_run_timed_out = self.forkserver.last_run_timed_out_raw();
+
+        if self
+            .persistent_count
+            .map_or(false, |count| self.persistent_runs >= count)
+        {
+            if let Some(child_pid) = self.forkserver.child_pid {
+                // The forkserver resumes a stopped child, unless it is told that the child was killed
+                let _ = kill(child_pid, Signal::SIGKILL);
+                self.forkserver.reset_child_pid();
+                last_run_timed_out = 1;
+            }
+        }
 
         if self.uses_shmem_testcase {
             debug_assert!(
@@ -1152,7 +1255,13 @@ where
             ));
         }
 
-        self.forkserver.set_child_pid(Pid::from_raw(pid));
+        let pid = Pid::from_raw(pid);
+        if self.forkserver.child_pid == Some(pid) {
+            self.persistent_runs += 1;
+        } else {
+            self.persistent_runs = 1;
+        }
+        self.forkserver.set_child_pid(pid);
 
         if let Some(status) = self.forkserver.read_st_timed(&self.timeout)? {
             self.forkserver.set_status(status);
@@ -1163,7 +1272,7 @@ where
                     .observers_mut()
                     .match_name_mut::<AsanBacktraceObserver>("AsanBacktraceObserver")
                 {
-                    asan_observer.parse_asan_output_from_asan_log_file(pid)?;
+                    asan_observer.parse_asan_output_from_asan_log_file(pid.as_raw())?;
                 }
             }
         } else {
@@ -1232,7 +1341,9 @@ mod tests {
     use serial_test::serial;
 
     use crate::{
-        executors::forkserver::ForkserverExecutorBuilder,
+        executors::forkserver::{
+            has_persistent_signature, is_persistent_program, ForkserverExecutorBuilder, PERSIST_SIG,
+        },
         observers::{ConstMapObserver, HitcountsMapObserver},
         Error,
     };
@@ -1273,4 +1384,42 @@ mod tests {
         };
         assert!(result);
     }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_persistent_signature() {
+        let mut binary = b"\x7fELF some code".to_vec();
+        assert!(!has_persistent_signature(&binary));
+        binary.extend_from_slice(PERSIST_SIG);
+        binary.extend_from_slice(b"more code");
+        assert!(has_persistent_signature(&binary));
+
+        // Neither an uninstrumented binary nor a missing one is persistent
+        assert!(!is_persistent_program(&OsString::from("echo")));
+        assert!(!is_persistent_program(&OsString::from(
+            "/nonexistent/persistent_target"
+        )));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_persistent_count_keeps_explicit_mode() {
+        let builder = || ForkserverExecutorBuilder::new().program("echo");
+        // the count alone only enables persistent mode for targets built for it
+        assert!(!builder().persistent_count(10).resolve_persistent());
+        assert!(!builder().resolve_persistent());
+        // an explicit setting is never overridden by the signature probe
+        assert!(builder()
+            .is_persistent(true)
+            .persistent_count(10)
+            .resolve_persistent());
+        assert!(builder()
+            .persistent_count(10)
+            .is_persistent(true)
+            .resolve_persistent());
+        assert!(!builder()
+            .is_persistent(false)
+            .persistent_count(10)
+            .resolve_persistent());
+    }
 }
@@ -0,0 +1,329 @@
+//! The [`GuaranteedTimeoutExecutor`] forks for each execution, and kills the child with `SIGKILL` on timeout.
+//!
+//! The timers of the other executors deliver `SIGALRM` (or `SIGUSR2`) to the target, which can mask or ignore them.
+//! `SIGKILL` cannot be masked, so untrusted targets cannot hang the fuzzer.
+
+use core::{
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+    time::Duration,
+};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Instant,
+};
+
+use libafl_bolts::shmem::ShMemProvider;
+use nix::{
+    sys::{
+        signal::{killpg, Signal},
+        wait::{waitpid, WaitStatus},
+    },
+    unistd::{fork, setpgid, ForkResult, Pid},
+};
+
+use crate::{
+    executors::{Executor, ExitKind, HasObservers},
+    inputs::UsesInput,
+    observers::{ObserversTuple, UsesObservers},
+    state::{HasExecutions, State, UsesState},
+    Error,
+};
+
+/// An executor running the harness in a forked child, in a process group of its own.
+///
+/// For each execution, a watchdog thread sleeps for the timeout, unless the child exits before,
+/// and then sends `SIGKILL` to the process group of the child, including the processes it spawned.
+/// A child killed by `SIGKILL` is reported as [`ExitKind::Timeout`], any other signal as [`ExitKind::Crash`].
+/// For a harness returning [`ExitKind::Crash`], the child aborts.
+pub struct GuaranteedTimeoutExecutor<'a, H, OT, S, SP>
+where
+    H: FnMut(&S::Input) -> ExitKind + ?Sized,
+    OT: ObserversTuple<S>,
+    S: UsesInput,
+    SP: ShMemProvider,
+{
+    harness_fn: &'a mut H,
+    observers: OT,
+    shmem_provider: SP,
+    timeout: Duration,
+    phantom: PhantomData<S>,
+}
+
+impl<'a, H, OT, S, SP> Debug for GuaranteedTimeoutExecutor<'a, H, OT, S, SP>
+where
+    H: FnMut(&S::Input) -> ExitKind + ?Sized,
+    OT: ObserversTuple<S> + Debug,
+    S: UsesInput,
+    SP: ShMemProvider,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GuaranteedTimeoutExecutor")
+            .field("observers", &self.observers)
+            .field("shmem_provider", &self.shmem_provider)
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a, H, OT, S, SP> GuaranteedTimeoutExecutor<'a, H, OT, S, SP>
+where
+    H: FnMut(&S::Input) -> ExitKind + ?Sized,
+    OT: ObserversTuple<S>,
+    S: State,
+    SP: ShMemProvider,
+{
+    /// Creates a new [`GuaranteedTimeoutExecutor`], killing the child running `harness_fn` after `timeout`.
+    /// The observers that the child writes to need to be backed by shared memory of `shmem_provider`.
+    pub fn new(
+        harness_fn: &'a mut H,
+        observers: OT,
+        timeout: Duration,
+        shmem_provider: SP,
+    ) -> Self {
+        Self {
+            harness_fn,
+            observers,
+            shmem_provider,
+            timeout,
+            phantom: PhantomData,
+        }
+    }
+
+    /// The timeout of each execution
+    #[must_use]
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Sets the timeout of each execution
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Waits for the `child` to exit, killing its process group once the timeout elapsed
+    fn wait_with_watchdog(&self, child: Pid) -> Result<ExitKind, Error> {
+        let done = Arc::new(AtomicBool::new(false));
+        let watchdog = {
+            let done = done.clone();
+            let timeout = self.timeout;
+            thread::spawn(move || {
+                let start = Instant::now();
+                while !done.load(Ordering::Acquire) {
+                    let elapsed = start.elapsed();
+                    if elapsed >= timeout {
+                        // the child may have exited in the meantime, then there is nothing to kill
+                        let _ = killpg(child, Signal::SIGKILL);
+                        return;
+                    }
+                    // spurious wakeups just go around the loop
+                    thread::park_timeout(timeout - elapsed);
+                }
+            })
+        };
+
+        let res = waitpid(child, None);
+        done.store(true, Ordering::Release);
+        watchdog.thread().unpark();
+        watchdog
+            .join()
+            .map_err(|_| Error::unknown("The watchdog thread panicked"))?;
+
+        log::trace!("{res:#?}");
+        match res? {
+            WaitStatus::Signaled(_, Signal::SIGKILL, _) => Ok(ExitKind::Timeout),
+            WaitStatus::Signaled(_, _, _) => Ok(ExitKind::Crash),
+            WaitStatus::Exited(_, code) if code > 128 && code < 160 => {
+                // Signal exit codes
+                if code - 128 == Signal::SIGKILL as libc::c_int {
+                    Ok(ExitKind::Timeout)
+                } else {
+                    Ok(ExitKind::Crash)
+                }
+            }
+            _ => Ok(ExitKind::Ok),
+        }
+    }
+}
+
+impl<'a, H, OT, S, SP> UsesState for GuaranteedTimeoutExecutor<'a, H, OT, S, SP>
+where
+    H: FnMut(&S::Input) -> ExitKind + ?Sized,
+    OT: ObserversTuple<S>,
+    S: State,
+    SP: ShMemProvider,
+{
+    type State = S;
+}
+
+impl<'a, H, OT, S, SP> UsesObservers for GuaranteedTimeoutExecutor<'a, H, OT, S, SP>
+where
+    H: FnMut(&S::Input) -> ExitKind + ?Sized,
+    OT: ObserversTuple<S>,
+    S: State,
+    SP: ShMemProvider,
+{
+    type Observers = OT;
+}
+
+impl<'a, H, OT, S, SP> HasObservers for GuaranteedTimeoutExecutor<'a, H, OT, S, SP>
+where
+    H: FnMut(&S::Input) -> ExitKind + ?Sized,
+    OT: ObserversTuple<S>,
+    S: State,
+    SP: ShMemProvider,
+{
+    #[inline]
+    fn observers(&self) -> &OT {
+        &self.observers
+    }
+
+    #[inline]
+    fn observers_mut(&mut self) -> &mut OT {
+        &mut self.observers
+    }
+}
+
+impl<'a, EM, H, OT, S, SP, Z> Executor<EM, Z> for GuaranteedTimeoutExecutor<'a, H, OT, S, SP>
+where
+    EM: UsesState<State = S>,
+    H: FnMut(&S::Input) -> ExitKind + ?Sized,
+    OT: ObserversTuple<S>,
+    S: State + HasExecutions,
+    SP: ShMemProvider,
+    Z: UsesState<State = S>,
+{
+    fn run_target(
+        &mut self,
+        _fuzzer: &mut Z,
+        state: &mut Self::State,
+        _mgr: &mut EM,
+        input: &Self::Input,
+    ) -> Result<ExitKind, Error> {
+        *state.executions_mut() += 1;
+
+        self.shmem_provider.pre_fork()?;
+        // # Safety
+        // The child only runs the harness and exits, without returning to the fuzzer.
+        match unsafe { fork() }? {
+            ForkResult::Child => {
+                self.shmem_provider.post_fork(true)?;
+                // a process group of its own, so that the watchdog does not kill the fuzzer
+                setpgid(Pid::from_raw(0), Pid::from_raw(0))
+                    .expect("Failed to create a process group");
+
+                self.observers
+                    .pre_exec_child_all(state, input)
+                    .expect("Failed to run pre_exec on observers");
+                let exit_kind = (self.harness_fn)(input);
+                self.observers
+                    .post_exec_child_all(state, input, &exit_kind)
+                    .expect("Failed to run post_exec on observers");
+
+                unsafe {
+                    if exit_kind == ExitKind::Crash {
+                        // reported as a crash by the signal
+                        libc::abort();
+                    }
+                    libc::_exit(0);
+                }
+            }
+            ForkResult::Parent { child } => {
+                self.shmem_provider.post_fork(false)?;
+                // also set it from the parent, in case the watchdog fires before the child got to it.
+                // This fails if the child already did, which is fine.
+                let _ = setpgid(child, child);
+                self.wait_with_watchdog(child)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use libafl_bolts::shmem::{ShMemProvider, StdShMemProvider};
+
+    use super::GuaranteedTimeoutExecutor;
+    use crate::{
+        events::NopEventManager,
+        executors::{Executor, ExitKind},
+        fuzzer::test::NopFuzzer,
+        inputs::NopInput,
+        state::{HasExecutions, NopState},
+    };
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_guaranteed_timeout_exec() {
+        let mut state = NopState::<NopInput>::new();
+        let mut fuzzer = NopFuzzer::new();
+        let mut mgr = NopEventManager::new();
+
+        let mut harness = |_input: &NopInput| ExitKind::Ok;
+        let mut executor = GuaranteedTimeoutExecutor::new(
+            &mut harness,
+            (),
+            Duration::from_secs(5),
+            StdShMemProvider::new().unwrap(),
+        );
+        assert_eq!(
+            executor
+                .run_target(&mut fuzzer, &mut state, &mut mgr, &NopInput {})
+                .unwrap(),
+            ExitKind::Ok
+        );
+
+        let mut harness = |_input: &NopInput| ExitKind::Crash;
+        let mut executor = GuaranteedTimeoutExecutor::new(
+            &mut harness,
+            (),
+            Duration::from_secs(5),
+            StdShMemProvider::new().unwrap(),
+        );
+        assert_eq!(
+            executor
+                .run_target(&mut fuzzer, &mut state, &mut mgr, &NopInput {})
+                .unwrap(),
+            ExitKind::Crash
+        );
+        assert_eq!(*state.executions(), 2);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_guaranteed_timeout_masked_signals() {
+        let mut state = NopState::<NopInput>::new();
+        let mut fuzzer = NopFuzzer::new();
+        let mut mgr = NopEventManager::new();
+
+        // a target blocking all the signals it can, and hanging
+        let mut harness = |_input: &NopInput| {
+            unsafe {
+                let mut set = core::mem::zeroed();
+                libc::sigfillset(&mut set);
+                libc::sigprocmask(libc::SIG_BLOCK, &set, core::ptr::null_mut());
+            }
+            loop {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        };
+        let mut executor = GuaranteedTimeoutExecutor::new(
+            &mut harness,
+            (),
+            Duration::from_millis(200),
+            StdShMemProvider::new().unwrap(),
+        );
+        assert_eq!(
+            executor
+                .run_target(&mut fuzzer, &mut state, &mut mgr, &NopInput {})
+                .unwrap(),
+            ExitKind::Timeout
+        );
+    }
+}
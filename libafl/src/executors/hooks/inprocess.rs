@@ -1,11 +1,9 @@
 //! The hook for `InProcessExecutor`
-#[cfg(any(unix, feature = "std"))]
-use core::ptr::addr_of_mut;
 #[cfg(any(unix, all(windows, feature = "std")))]
 use core::sync::atomic::{compiler_fence, Ordering};
 use core::{
     ffi::c_void,
-    ptr::{self, null_mut},
+    ptr::{self, addr_of_mut, null_mut},
     time::Duration,
 };
 #[cfg(all(target_os = "linux", feature = "std"))]
@@ -28,7 +26,9 @@ use crate::executors::hooks::unix::unix_signal_handler;
 use crate::state::State;
 use crate::{
     events::{EventFirer, EventRestarter},
-    executors::{hooks::ExecutorHook, inprocess::HasInProcessHooks, Executor, HasObservers},
+    executors::{
+        hooks::ExecutorHook, inprocess::HasInProcessHooks, Executor, ExitKind, HasObservers,
+    },
     feedbacks::Feedback,
     state::{HasCorpus, HasExecutions, HasSolutions},
     Error, HasObjective,
@@ -45,8 +45,16 @@ pub struct InProcessHooks {
     /// `TImer` struct
     #[cfg(feature = "std")]
     pub timer: TimerStruct,
+    /// Decides the [`ExitKind`] of an execution running out of memory, see [`inprocess_report_oom`]
+    pub oom_handler: Option<OomHandler>,
 }
 
+/// Decides the [`ExitKind`] of an execution in which the allocator failed to allocate `size` bytes.
+///
+/// Returning [`ExitKind::Ok`] ignores the failure and lets the target go on.
+/// It runs inside the allocator, so it must not allocate itself.
+pub type OomHandler = fn(size: usize) -> ExitKind;
+
 /// Any hooks that is about timeout
 pub trait HasTimeout {
     /// Return ref to timer
@@ -197,6 +205,11 @@ impl ExecutorHook for InProcessHooks {
             (*data).crash_handler = self.crash_handler;
             (*data).timeout_handler = self.timeout_handler;
         }
+        unsafe {
+            let data = addr_of_mut!(GLOBAL_STATE);
+            (*data).oom_handler = self.oom_handler;
+            (*data).oom_exit_kind = None;
+        }
 
         #[cfg(feature = "std")]
         self.timer_mut().set_timer();
@@ -246,6 +259,7 @@ impl InProcessHooks {
                     as *const _,
                 #[cfg(feature = "std")]
                 timer: TimerStruct::new(exec_tmout),
+                oom_handler: None,
             })
         }
     }
@@ -292,11 +306,12 @@ impl InProcessHooks {
                 crash_handler,
                 timeout_handler,
                 timer,
+                oom_handler: None,
             });
         }
         #[cfg(not(feature = "std"))]
         {
-            ret = Ok(Self {});
+            ret = Ok(Self { oom_handler: None });
         }
 
         ret
@@ -314,7 +329,7 @@ impl InProcessHooks {
         Z: HasObjective<Objective = OF, State = E::State>,
     {
         #[cfg_attr(miri, allow(unused_variables))]
-        let ret = Self {};
+        let ret = Self { oom_handler: None };
         Ok(ret)
     }
 
@@ -329,6 +344,7 @@ impl InProcessHooks {
             timeout_handler: ptr::null(),
             #[cfg(feature = "std")]
             timer: TimerStruct::new(Duration::from_millis(5000)),
+            oom_handler: None,
         }
     }
 }
@@ -347,6 +363,11 @@ pub struct InProcessExecutorHandlerData {
     pub(crate) current_input_ptr: *const c_void,
     pub(crate) in_handler: bool,
 
+    /// The OOM handler of the running executor
+    pub(crate) oom_handler: Option<OomHandler>,
+    /// The [`ExitKind`] of the last OOM reported in this execution, if not ignored
+    pub(crate) oom_exit_kind: Option<ExitKind>,
+
     /// The timeout handler
     #[cfg(feature = "std")]
     pub(crate) crash_handler: *const c_void,
@@ -398,6 +419,12 @@ impl InProcessExecutorHandlerData {
         !self.current_input_ptr.is_null()
     }
 
+    /// Takes the [`ExitKind`] decided for an OOM in this execution, if any
+    #[cfg(any(unix, feature = "std"))]
+    pub(crate) fn take_oom_exit_kind(&mut self) -> Option<ExitKind> {
+        self.oom_exit_kind.take()
+    }
+
     #[cfg(any(unix, feature = "std"))]
     pub(crate) fn set_in_handler(&mut self, v: bool) -> bool {
         let old = self.in_handler;
@@ -421,6 +448,11 @@ pub(crate) static mut GLOBAL_STATE: InProcessExecutorHandlerData = InProcessExec
 
     in_handler: false,
 
+    // The OOM handler fn
+    oom_handler: None,
+    // The exit kind of a reported OOM
+    oom_exit_kind: None,
+
     // The crash handler fn
     #[cfg(feature = "std")]
     crash_handler: ptr::null(),
@@ -470,3 +502,24 @@ pub fn inprocess_get_input<'a, I>() -> Option<&'a I> {
 pub fn inprocess_in_handler() -> bool {
     unsafe { GLOBAL_STATE.in_handler }
 }
+
+/// Reports that the allocator failed to allocate `size` bytes in the running target, e.g. from a `malloc` hook.
+///
+/// Returns the [`ExitKind`] decided by the [`OomHandler`] of the running executor, or [`ExitKind::Crash`] without one,
+/// as an OOM has always been reported as a crash. Unless it is [`ExitKind::Ok`], the caller should abort the execution,
+/// for example by raising `SIGABRT`: the crash handler then reports the execution with the returned [`ExitKind`].
+///
+/// Rust's own allocation failures can not be reported like this, as the alloc error hook is not stable yet.
+#[must_use]
+pub fn inprocess_report_oom(size: usize) -> ExitKind {
+    unsafe {
+        let data = addr_of_mut!(GLOBAL_STATE);
+        let exit_kind = (*data)
+            .oom_handler
+            .map_or(ExitKind::Crash, |handler| handler(size));
+        if exit_kind != ExitKind::Ok {
+            (*data).oom_exit_kind = Some(exit_kind);
+        }
+        exit_kind
+    }
+}
@@ -217,13 +217,10 @@ pub mod unix_signal_handler {
                 log::error!("{}", std::str::from_utf8(&bsod).unwrap());
             }
 
+            // an OOM reported by the allocator may have been turned into another exit kind
+            let exit_kind = data.take_oom_exit_kind().unwrap_or(ExitKind::Crash);
             run_observers_and_save_state::<E, EM, OF, Z>(
-                executor,
-                state,
-                input,
-                fuzzer,
-                event_mgr,
-                ExitKind::Crash,
+                executor, state, input, fuzzer, event_mgr, exit_kind,
             );
         } else {
             {
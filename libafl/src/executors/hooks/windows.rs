@@ -395,14 +395,10 @@ pub mod windows_exception_handler {
             // Make sure we don't crash in the crash handler forever.
             if is_crash {
                 let input = data.take_current_input::<<E::State as UsesInput>::Input>();
+                let exit_kind = data.take_oom_exit_kind().unwrap_or(ExitKind::Crash);
 
                 run_observers_and_save_state::<E, EM, OF, Z>(
-                    executor,
-                    state,
-                    input,
-                    fuzzer,
-                    event_mgr,
-                    ExitKind::Crash,
+                    executor, state, input, fuzzer, event_mgr, exit_kind,
                 );
             } else {
                 // This is not worth saving
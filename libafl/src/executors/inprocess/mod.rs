@@ -24,7 +24,10 @@ use crate::{
     corpus::{Corpus, Testcase},
     events::{Event, EventFirer, EventRestarter},
     executors::{
-        hooks::{inprocess::InProcessHooks, ExecutorHooksTuple},
+        hooks::{
+            inprocess::{InProcessHooks, OomHandler},
+            ExecutorHooksTuple,
+        },
         inprocess::inner::GenericInProcessExecutorInner,
         Executor, ExitKind, HasObservers,
     },
@@ -38,6 +41,8 @@ use crate::{
 
 /// The inner structure of `InProcessExecutor`.
 pub mod inner;
+/// A version of `InProcessExecutor` running the harness in a loop, like the persistent mode of AFL++.
+pub mod persistent;
 /// A version of `InProcessExecutor` with a state accessible from the harness.
 pub mod stateful;
 
@@ -386,6 +391,16 @@ where
     pub fn hooks_mut(&mut self) -> &mut (InProcessHooks, HT) {
         self.inner.hooks_mut()
     }
+
+    /// Sets the [`OomHandler`] deciding the [`ExitKind`] of executions running out of memory.
+    ///
+    /// It is called whenever an allocator hook calls [`inprocess_report_oom`](crate::executors::hooks::inprocess::inprocess_report_oom),
+    /// like the `OomObserver` of `libafl_targets` does. Without one, such executions are reported as [`ExitKind::Crash`].
+    #[must_use]
+    pub fn with_oom_handler(mut self, oom_handler: OomHandler) -> Self {
+        self.hooks_mut().0.oom_handler = Some(oom_handler);
+        self
+    }
 }
 
 /// The struct has [`InProcessHooks`].
@@ -518,7 +533,10 @@ mod tests {
     use crate::{
         corpus::InMemoryCorpus,
         events::NopEventManager,
-        executors::{Executor, ExitKind, InProcessExecutor},
+        executors::{
+            hooks::inprocess::{inprocess_report_oom, OomHandler},
+            Executor, ExitKind, InProcessExecutor,
+        },
         feedbacks::CrashFeedback,
         inputs::{NopInput, UsesInput},
         schedulers::RandScheduler,
@@ -558,6 +576,48 @@ mod tests {
             .run_target(&mut fuzzer, &mut state, &mut mgr, &input)
             .unwrap();
     }
+
+    #[test]
+    fn test_inmem_exec_oom_handler() {
+        // the harness reports an OOM and returns the exit kind decided for it,
+        // instead of aborting and going through the crash handler
+        let mut harness = |_buf: &NopInput| inprocess_report_oom(1 << 40);
+        let mut feedback = tuple_list!();
+        let mut objective = CrashFeedback::new();
+        let mut state = StdState::new(
+            libafl_bolts::rands::XkcdRand::new(),
+            InMemoryCorpus::<NopInput>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        let mut fuzzer = StdFuzzer::<_, _, _, ()>::new(RandScheduler::new(), feedback, objective);
+        let mut mgr = NopEventManager::new();
+
+        let handlers: [(Option<OomHandler>, ExitKind); 3] = [
+            (None, ExitKind::Crash),
+            (Some(|_size| ExitKind::Ok), ExitKind::Ok),
+            (Some(|_size| ExitKind::Oom), ExitKind::Oom),
+        ];
+        for (handler, expected) in handlers {
+            let mut executor = InProcessExecutor::new(
+                &mut harness,
+                tuple_list!(),
+                &mut fuzzer,
+                &mut state,
+                &mut mgr,
+            )
+            .unwrap();
+            if let Some(handler) = handler {
+                executor = executor.with_oom_handler(handler);
+            }
+            let exit_kind = executor
+                .run_target(&mut fuzzer, &mut state, &mut mgr, &NopInput {})
+                .unwrap();
+            assert_eq!(exit_kind, expected);
+        }
+    }
 }
 
 #[cfg(feature = "python")]
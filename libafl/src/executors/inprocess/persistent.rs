@@ -0,0 +1,229 @@
+//! The [`PersistentModeInProcessExecutor`] calls the harness in a loop, like the persistent mode of AFL++.
+//!
+//! Each call to `run_target` runs the harness on the same input up to `max_iters` times,
+//! calling the `reset` function in between to clear the global state left behind by the previous iteration.
+//! Bugs depending on state that piles up over several calls, e.g. in caches or allocators, then show up in a single execution.
+
+use alloc::boxed::Box;
+use core::time::Duration;
+
+use libafl_bolts::tuples::tuple_list;
+
+use crate::{
+    events::{EventFirer, EventRestarter},
+    executors::{inprocess::OwnedInProcessExecutor, Executor, ExitKind, HasObservers},
+    feedbacks::Feedback,
+    fuzzer::HasObjective,
+    observers::{ObserversTuple, UsesObservers},
+    state::{HasCorpus, HasExecutions, HasSolutions, State, UsesState},
+    Error,
+};
+
+/// An in-process executor running the harness up to `max_iters` times per execution.
+///
+/// The loop stops at the first iteration not returning [`ExitKind::Ok`], and this [`ExitKind`] is reported.
+/// As all iterations run on the same input, a crash in any of them is reported with the input of the whole sequence.
+/// Crashes and timeouts caught by the signal handlers of the wrapped [`OwnedInProcessExecutor`] are reported as usual.
+#[derive(Debug)]
+pub struct PersistentModeInProcessExecutor<OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: State,
+{
+    inner: OwnedInProcessExecutor<OT, S>,
+    max_iters: usize,
+}
+
+/// Runs `harness` up to `max_iters` times, calling `reset` between the iterations
+fn run_persistent_loop<I, H>(harness: &mut H, reset: fn(), max_iters: usize, input: &I) -> ExitKind
+where
+    H: FnMut(&I) -> ExitKind + ?Sized,
+{
+    for iter in 0..max_iters {
+        if iter > 0 {
+            reset();
+        }
+        let exit_kind = harness(input);
+        if exit_kind != ExitKind::Ok {
+            return exit_kind;
+        }
+    }
+    ExitKind::Ok
+}
+
+impl<OT, S> PersistentModeInProcessExecutor<OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: HasExecutions + HasSolutions + HasCorpus + State,
+{
+    /// Create a new [`PersistentModeInProcessExecutor`] with the default timeout (5 sec) for the whole loop
+    pub fn new<EM, H, OF, Z>(
+        harness_fn: H,
+        reset: fn(),
+        max_iters: usize,
+        observers: OT,
+        fuzzer: &mut Z,
+        state: &mut S,
+        event_mgr: &mut EM,
+    ) -> Result<Self, Error>
+    where
+        H: FnMut(&S::Input) -> ExitKind + 'static,
+        OwnedInProcessExecutor<OT, S>: Executor<EM, Z, State = S> + HasObservers,
+        EM: EventFirer<State = S> + EventRestarter,
+        OF: Feedback<S>,
+        Z: HasObjective<Objective = OF, State = S>,
+    {
+        Self::with_timeout(
+            harness_fn,
+            reset,
+            max_iters,
+            observers,
+            fuzzer,
+            state,
+            event_mgr,
+            Duration::from_millis(5000),
+        )
+    }
+
+    /// Create a new [`PersistentModeInProcessExecutor`].
+    /// * `harness_fn` - the harness, called up to `max_iters` times per execution
+    /// * `reset` - resets the global state of the target, called between two iterations
+    /// * `timeout` - the timeout of all iterations together
+    ///
+    /// # Panics
+    /// If `max_iters` is `0`
+    #[allow(clippy::too_many_arguments)]
+    pub fn with_timeout<EM, H, OF, Z>(
+        mut harness_fn: H,
+        reset: fn(),
+        max_iters: usize,
+        observers: OT,
+        fuzzer: &mut Z,
+        state: &mut S,
+        event_mgr: &mut EM,
+        timeout: Duration,
+    ) -> Result<Self, Error>
+    where
+        H: FnMut(&S::Input) -> ExitKind + 'static,
+        OwnedInProcessExecutor<OT, S>: Executor<EM, Z, State = S> + HasObservers,
+        EM: EventFirer<State = S> + EventRestarter,
+        OF: Feedback<S>,
+        Z: HasObjective<Objective = OF, State = S>,
+    {
+        assert!(
+            max_iters > 0,
+            "A PersistentModeInProcessExecutor needs to run the harness at least once"
+        );
+        let looped_harness: Box<dyn FnMut(&S::Input) -> ExitKind> =
+            Box::new(move |input: &S::Input| {
+                run_persistent_loop(&mut harness_fn, reset, max_iters, input)
+            });
+        let inner = OwnedInProcessExecutor::with_timeout_generic(
+            tuple_list!(),
+            looped_harness,
+            observers,
+            fuzzer,
+            state,
+            event_mgr,
+            timeout,
+        )?;
+        Ok(Self { inner, max_iters })
+    }
+
+    /// The maximum number of iterations per execution
+    #[must_use]
+    pub fn max_iters(&self) -> usize {
+        self.max_iters
+    }
+
+    /// Retrieve the wrapped [`OwnedInProcessExecutor`]
+    pub fn inner(&mut self) -> &mut OwnedInProcessExecutor<OT, S> {
+        &mut self.inner
+    }
+}
+
+impl<EM, OT, S, Z> Executor<EM, Z> for PersistentModeInProcessExecutor<OT, S>
+where
+    OwnedInProcessExecutor<OT, S>: Executor<EM, Z, State = S>,
+    EM: UsesState<State = S>,
+    OT: ObserversTuple<S>,
+    S: State,
+    Z: UsesState<State = S>,
+{
+    #[inline]
+    fn run_target(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut Self::State,
+        mgr: &mut EM,
+        input: &Self::Input,
+    ) -> Result<ExitKind, Error> {
+        self.inner.run_target(fuzzer, state, mgr, input)
+    }
+}
+
+impl<OT, S> UsesState for PersistentModeInProcessExecutor<OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: State,
+{
+    type State = S;
+}
+
+impl<OT, S> UsesObservers for PersistentModeInProcessExecutor<OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: State,
+{
+    type Observers = OT;
+}
+
+impl<OT, S> HasObservers for PersistentModeInProcessExecutor<OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: State,
+{
+    #[inline]
+    fn observers(&self) -> &OT {
+        self.inner.observers()
+    }
+
+    #[inline]
+    fn observers_mut(&mut self) -> &mut OT {
+        self.inner.observers_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::run_persistent_loop;
+    use crate::{executors::ExitKind, inputs::BytesInput};
+
+    static RESETS: AtomicUsize = AtomicUsize::new(0);
+
+    fn count_reset() {
+        RESETS.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[test]
+    fn test_persistent_loop() {
+        let input = BytesInput::new(b"a".to_vec());
+        let mut calls = 0;
+        let mut harness = |_input: &BytesInput| {
+            calls += 1;
+            if calls == 3 {
+                ExitKind::Crash
+            } else {
+                ExitKind::Ok
+            }
+        };
+        assert_eq!(
+            run_persistent_loop(&mut harness, count_reset, 10, &input),
+            ExitKind::Crash
+        );
+        assert_eq!(calls, 3);
+        assert_eq!(RESETS.load(Ordering::Relaxed), 2);
+    }
+}
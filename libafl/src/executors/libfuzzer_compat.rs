@@ -0,0 +1,298 @@
+//! The [`LibFuzzerCompatExecutor`] runs existing libFuzzer harnesses from a shared library, without changes to the harness.
+
+use alloc::{boxed::Box, ffi::CString, vec::Vec};
+use core::{
+    ffi::{c_char, c_int},
+    fmt::{self, Debug, Formatter},
+    time::Duration,
+};
+use std::{env, ffi::OsStr, path::PathBuf};
+
+use libafl_bolts::{tuples::tuple_list, AsSlice};
+use libloading::Library;
+
+use crate::{
+    events::{EventFirer, EventRestarter},
+    executors::{
+        inprocess::{GenericInProcessExecutor, OwnedInProcessExecutor},
+        Executor, ExitKind, HasObservers,
+    },
+    feedbacks::Feedback,
+    fuzzer::HasObjective,
+    inputs::HasTargetBytes,
+    observers::{ObserversTuple, UsesObservers},
+    state::{HasCorpus, HasExecutions, HasSolutions, State, UsesState},
+    Error,
+};
+
+/// The signature of `LLVMFuzzerTestOneInput`
+type TestOneInputFn = unsafe extern "C" fn(*const u8, usize) -> c_int;
+
+/// The signature of `LLVMFuzzerInitialize`
+type InitializeFn = unsafe extern "C" fn(*mut c_int, *mut *mut *mut c_char) -> c_int;
+
+/// The signature of `__sanitizer_set_death_callback`
+type SetDeathCallbackFn = unsafe extern "C" fn(Option<extern "C" fn()>);
+
+/// The sanitizer death callback, aborting so that sanitizer reports end in a signal the crash handler catches,
+/// instead of an `exit`, like libFuzzer does
+extern "C" fn abort_on_sanitizer_death() {
+    std::process::abort();
+}
+
+/// Registers [`abort_on_sanitizer_death`] with the sanitizer runtime of the library, or of this process.
+///
+/// Returns `false` if there is no sanitizer runtime.
+fn set_sanitizer_death_callback(library: &Library) -> bool {
+    const SYMBOL: &[u8] = b"__sanitizer_set_death_callback\0";
+    unsafe {
+        if let Ok(set_death_callback) = library.get::<SetDeathCallbackFn>(SYMBOL) {
+            set_death_callback(Some(abort_on_sanitizer_death));
+            return true;
+        }
+        // the runtime may be linked into the executable instead
+        #[cfg(unix)]
+        if let Ok(set_death_callback) =
+            libloading::os::unix::Library::this().get::<SetDeathCallbackFn>(SYMBOL)
+        {
+            set_death_callback(Some(abort_on_sanitizer_death));
+            return true;
+        }
+    }
+    false
+}
+
+/// Calls `LLVMFuzzerInitialize` with the arguments of this process, like libFuzzer does
+fn call_initialize(initialize: InitializeFn) {
+    // The harness may keep pointers to the arguments, so they are leaked.
+    let args: Vec<*mut c_char> = env::args()
+        .filter_map(|arg| CString::new(arg).ok())
+        .map(CString::into_raw)
+        .chain([core::ptr::null_mut()])
+        .collect();
+    let mut argc = c_int::try_from(args.len() - 1).unwrap();
+    let mut argv = Box::leak(args.into_boxed_slice()).as_mut_ptr();
+    unsafe {
+        initialize(&mut argc, &mut argv);
+    }
+}
+
+/// An [`Executor`] calling `LLVMFuzzerTestOneInput` of a shared library, for existing libFuzzer harnesses.
+///
+/// The library is loaded with `libloading`, and `LLVMFuzzerInitialize` is called once, if the library has it.
+/// The harness runs in an [`OwnedInProcessExecutor`], so crashes, including sanitizer reports, end up as [`ExitKind::Crash`]:
+/// the executor registers a sanitizer death callback aborting the process, and leaves the environment untouched.
+/// Sanitizers which do not halt on errors by default, like UBSan, still need it in their options for this process,
+/// e.g. `UBSAN_OPTIONS=halt_on_error=1:abort_on_error=1`.
+pub struct LibFuzzerCompatExecutor<OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: State,
+{
+    // Declared before `library`, so the harness is dropped before the library gets unloaded.
+    inner: OwnedInProcessExecutor<OT, S>,
+    library: Library,
+    path: PathBuf,
+}
+
+impl<OT, S> Debug for LibFuzzerCompatExecutor<OT, S>
+where
+    OT: ObserversTuple<S> + Debug,
+    S: State,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LibFuzzerCompatExecutor")
+            .field("path", &self.path)
+            .field("library", &self.library)
+            .field("inner", &self.inner)
+            .finish()
+    }
+}
+
+impl<OT, S> LibFuzzerCompatExecutor<OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: State + HasExecutions + HasSolutions + HasCorpus,
+    S::Input: HasTargetBytes,
+{
+    /// Loads the shared library at `path`, and creates a new [`LibFuzzerCompatExecutor`] calling its `LLVMFuzzerTestOneInput`.
+    ///
+    /// # Safety
+    /// Loading the library runs its initializers, and the harness is called with the signature libFuzzer expects,
+    /// so `path` has to be a trusted libFuzzer harness.
+    pub unsafe fn new<EM, OF, P, Z>(
+        path: P,
+        observers: OT,
+        fuzzer: &mut Z,
+        state: &mut S,
+        event_mgr: &mut EM,
+        timeout: Duration,
+    ) -> Result<Self, Error>
+    where
+        P: AsRef<OsStr>,
+        EM: EventFirer<State = S> + EventRestarter,
+        OF: Feedback<S>,
+        Z: HasObjective<Objective = OF, State = S>,
+    {
+        let path = PathBuf::from(path.as_ref());
+        let library = Library::new(&path)
+            .map_err(|err| Error::illegal_argument(format!("Failed to load {path:?}: {err}")))?;
+        let test_one_input: TestOneInputFn = *library
+            .get::<TestOneInputFn>(b"LLVMFuzzerTestOneInput\0")
+            .map_err(|err| {
+                Error::illegal_argument(format!(
+                    "{path:?} has no LLVMFuzzerTestOneInput, is it a libFuzzer harness? {err}"
+                ))
+            })?;
+        if !set_sanitizer_death_callback(&library) {
+            log::info!("{path:?} has no sanitizer runtime, sanitizer reports will not be caught");
+        }
+        if let Ok(initialize) = library.get::<InitializeFn>(b"LLVMFuzzerInitialize\0") {
+            call_initialize(*initialize);
+        }
+
+        let harness: Box<dyn FnMut(&S::Input) -> ExitKind> = Box::new(move |input: &S::Input| {
+            let target = input.target_bytes();
+            let buf = target.as_slice();
+            // libFuzzer ignores the return value, too (apart from -1, rejecting the input)
+            unsafe {
+                test_one_input(buf.as_ptr(), buf.len());
+            }
+            ExitKind::Ok
+        });
+        let inner = GenericInProcessExecutor::with_timeout_generic(
+            tuple_list!(),
+            harness,
+            observers,
+            fuzzer,
+            state,
+            event_mgr,
+            timeout,
+        )?;
+
+        Ok(Self {
+            inner,
+            library,
+            path,
+        })
+    }
+
+    /// The path of the loaded library
+    #[must_use]
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    /// The [`OwnedInProcessExecutor`] running the harness
+    #[must_use]
+    pub fn inner(&self) -> &OwnedInProcessExecutor<OT, S> {
+        &self.inner
+    }
+
+    /// The [`OwnedInProcessExecutor`] running the harness (mutable)
+    pub fn inner_mut(&mut self) -> &mut OwnedInProcessExecutor<OT, S> {
+        &mut self.inner
+    }
+}
+
+impl<EM, OT, S, Z> Executor<EM, Z> for LibFuzzerCompatExecutor<OT, S>
+where
+    EM: UsesState<State = S>,
+    OT: ObserversTuple<S>,
+    S: State + HasExecutions,
+    Z: UsesState<State = S>,
+{
+    #[inline]
+    fn run_target(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut Self::State,
+        mgr: &mut EM,
+        input: &Self::Input,
+    ) -> Result<ExitKind, Error> {
+        self.inner.run_target(fuzzer, state, mgr, input)
+    }
+}
+
+impl<OT, S> UsesState for LibFuzzerCompatExecutor<OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: State,
+{
+    type State = S;
+}
+
+impl<OT, S> UsesObservers for LibFuzzerCompatExecutor<OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: State,
+{
+    type Observers = OT;
+}
+
+impl<OT, S> HasObservers for LibFuzzerCompatExecutor<OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: State,
+{
+    #[inline]
+    fn observers(&self) -> &OT {
+        self.inner.observers()
+    }
+
+    #[inline]
+    fn observers_mut(&mut self) -> &mut OT {
+        self.inner.observers_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+    use std::env;
+
+    use libloading::Library;
+
+    use super::{set_sanitizer_death_callback, LibFuzzerCompatExecutor};
+    use crate::{
+        events::NopEventManager, feedbacks::ConstFeedback, inputs::BytesInput,
+        schedulers::QueueScheduler, state::test::test_std_state, StdFuzzer,
+    };
+
+    #[test]
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    fn test_libfuzzer_compat_keeps_env() {
+        let asan_options = env::var_os("ASAN_OPTIONS");
+        let ubsan_options = env::var_os("UBSAN_OPTIONS");
+
+        // not a sanitized library, and not a libFuzzer harness
+        assert!(!set_sanitizer_death_callback(
+            &unsafe { Library::new("libc.so.6") }.unwrap()
+        ));
+
+        let mut state = test_std_state::<BytesInput>();
+        let mut fuzzer = StdFuzzer::<_, _, _, ()>::new(
+            QueueScheduler::new(),
+            ConstFeedback::new(false),
+            ConstFeedback::new(false),
+        );
+        let mut mgr = NopEventManager::new();
+        for path in ["libc.so.6", "/nonexistent/libharness.so"] {
+            let executor = unsafe {
+                LibFuzzerCompatExecutor::new(
+                    path,
+                    (),
+                    &mut fuzzer,
+                    &mut state,
+                    &mut mgr,
+                    Duration::from_secs(1),
+                )
+            };
+            assert!(executor.is_err());
+        }
+
+        // the sanitizer options of the process are left alone
+        assert_eq!(env::var_os("ASAN_OPTIONS"), asan_options);
+        assert_eq!(env::var_os("UBSAN_OPTIONS"), ubsan_options);
+    }
+}
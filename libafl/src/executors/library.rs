@@ -0,0 +1,323 @@
+//! The [`LibraryExecutor`] loads the target library anew for each execution, resetting its global state.
+//!
+//! Targets keeping state in global variables are hard to fuzz in-process: a run depends on all runs before.
+//! Unloading the library with `dlclose` and loading it again with `dlopen` starts each run from the initial
+//! values of the globals. This is slower than a persistent in-process executor, but for small libraries
+//! faster than forking a child for each input.
+//!
+//! Beware of coverage instrumentation initialized by the library itself: each `dlopen` runs the constructors
+//! of the library again, so `SanitizerCoverage` calls `__sanitizer_cov_trace_pc_guard_init` with fresh,
+//! zeroed guards. The `libafl_targets` runtime numbers them on from the last guard, so each load moves the edges
+//! of the library to new map entries, until they wrap around or overflow the map. Reset the numbering before each load
+//! with [`LibraryExecutor::before_load`]:
+//!
+//! ```ignore
+//! let edges_before = unsafe { MAX_EDGES_NUM };
+//! let executor = unsafe { LibraryExecutor::new("./libtarget.so", DEFAULT_LIBRARY_ENTRY, observers)? }
+//!     .before_load(move || unsafe { MAX_EDGES_NUM = edges_before });
+//! ```
+//!
+//! The guards then get the same numbers on each load, and the coverage stays comparable between executions.
+
+use alloc::{
+    borrow::{Cow, ToOwned},
+    boxed::Box,
+    string::String,
+    vec::Vec,
+};
+use core::{
+    ffi::c_int,
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use libafl_bolts::AsSlice;
+use libloading::os::unix::{Library, RTLD_LOCAL, RTLD_NOW};
+
+use crate::{
+    executors::{Executor, ExitKind, HasObservers},
+    inputs::HasTargetBytes,
+    observers::{ObserversTuple, UsesObservers},
+    state::{HasExecutions, State, UsesState},
+    Error,
+};
+
+/// The signature of the entry point, the same as `LLVMFuzzerTestOneInput`
+type EntryFn = unsafe extern "C" fn(*const u8, usize) -> c_int;
+
+/// The default entry point of a [`LibraryExecutor`]
+pub const DEFAULT_LIBRARY_ENTRY: &str = "LLVMFuzzerTestOneInput";
+
+/// Calls the entry point of a shared library for each input, see the [module documentation](self).
+///
+/// The library is opened with `RTLD_LOCAL | RTLD_NOW`, so its symbols do not leak into later loads,
+/// and missing symbols fail the load instead of the execution.
+/// By default, the library is closed after each execution. With [`LibraryExecutor::keep_loaded`],
+/// the handle is cached instead, and the library only reloaded when the file changes on disk,
+/// e.g. after the target was rebuilt; the globals are then kept until the reload.
+///
+/// Crashes of the target are not caught, so this should run in a restarting event manager.
+/// A library that cannot be unloaded, e.g. one with thread-local destructors or `RTLD_NODELETE`,
+/// keeps its globals even if closed after each execution.
+pub struct LibraryExecutor<OT, S> {
+    path: PathBuf,
+    /// The entry point symbol, nul-terminated
+    entry: Vec<u8>,
+    keep_loaded: bool,
+    /// The cached handle, and the modification time of the file it was loaded from
+    library: Option<(Library, Option<SystemTime>)>,
+    /// Called before each load of the library, see [`LibraryExecutor::before_load`]
+    before_load: Option<Box<dyn FnMut()>>,
+    observers: OT,
+    phantom: PhantomData<S>,
+}
+
+impl<OT, S> Debug for LibraryExecutor<OT, S>
+where
+    OT: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LibraryExecutor")
+            .field("path", &self.path)
+            .field(
+                "entry",
+                &String::from_utf8_lossy(&self.entry[..self.entry.len() - 1]),
+            )
+            .field("keep_loaded", &self.keep_loaded)
+            .field("before_load", &self.before_load.is_some())
+            .field("observers", &self.observers)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<OT, S> LibraryExecutor<OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: State,
+{
+    /// Creates a new [`LibraryExecutor`], calling the `entry` symbol of the library at `path` for each input.
+    ///
+    /// The library is loaded once to check that it has the entry point.
+    ///
+    /// # Safety
+    /// Loading the library runs its initializers, and the entry point is called with the signature of
+    /// `LLVMFuzzerTestOneInput`, so `path` has to be a trusted library exporting `entry` with that signature.
+    pub unsafe fn new<P>(path: P, entry: &str, observers: OT) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut entry = entry.as_bytes().to_owned();
+        entry.push(0);
+        let executor = Self {
+            path: path.as_ref().to_path_buf(),
+            entry,
+            keep_loaded: false,
+            library: None,
+            before_load: None,
+            observers,
+            phantom: PhantomData,
+        };
+        let library = executor.open()?;
+        executor.entry_point(&library)?;
+        Ok(executor)
+    }
+
+    /// Keeps the library loaded between executions, only reloading it when the file changes on disk.
+    /// Faster, but the globals of the target are no longer reset for each execution.
+    #[must_use]
+    pub fn keep_loaded(mut self, keep_loaded: bool) -> Self {
+        self.keep_loaded = keep_loaded;
+        self
+    }
+
+    /// Calls `hook` before each load of the library, e.g. to reset the numbering of the coverage guards
+    /// initialized by the library, see the [module documentation](self).
+    #[must_use]
+    pub fn before_load<F>(mut self, hook: F) -> Self
+    where
+        F: FnMut() + 'static,
+    {
+        self.before_load = Some(Box::new(hook));
+        self
+    }
+
+    /// The path of the library
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The name of the entry point
+    fn entry_name(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.entry[..self.entry.len() - 1])
+    }
+
+    /// The modification time of the library file, `None` if it cannot be read
+    fn modified(&self) -> Option<SystemTime> {
+        fs::metadata(&self.path)
+            .and_then(|meta| meta.modified())
+            .ok()
+    }
+
+    /// Runs the [`LibraryExecutor::before_load`] hook, and opens the library
+    fn load(&mut self) -> Result<Library, Error> {
+        if let Some(hook) = &mut self.before_load {
+            hook();
+        }
+        self.open()
+    }
+
+    /// Opens the library
+    fn open(&self) -> Result<Library, Error> {
+        unsafe { Library::open(Some(&self.path), RTLD_LOCAL | RTLD_NOW) }.map_err(|err| {
+            Error::illegal_argument(format!("Failed to load {:?}: {err}", self.path))
+        })
+    }
+
+    /// Resolves the entry point in `library`
+    fn entry_point(&self, library: &Library) -> Result<EntryFn, Error> {
+        unsafe { library.get::<EntryFn>(&self.entry) }
+            .map(|entry| *entry)
+            .map_err(|err| {
+                Error::illegal_argument(format!(
+                    "{:?} has no entry point {}: {err}",
+                    self.path,
+                    self.entry_name()
+                ))
+            })
+    }
+
+    /// Loads the cached library, or reloads it if the file changed since it was loaded
+    fn reload_if_changed(&mut self) -> Result<(), Error> {
+        let modified = self.modified();
+        if let Some((_, loaded_at)) = &self.library {
+            if *loaded_at != modified {
+                log::info!("{:?} changed on disk, reloading it", self.path);
+                // close the old library first, or `dlopen` returns its handle again
+                self.library = None;
+            }
+        }
+        if self.library.is_none() {
+            self.library = Some((self.load()?, modified));
+        }
+        Ok(())
+    }
+}
+
+impl<EM, OT, S, Z> Executor<EM, Z> for LibraryExecutor<OT, S>
+where
+    EM: UsesState<State = S>,
+    S: State + HasExecutions,
+    S::Input: HasTargetBytes,
+    OT: ObserversTuple<S>,
+    Z: UsesState<State = S>,
+{
+    fn run_target(
+        &mut self,
+        _fuzzer: &mut Z,
+        state: &mut Self::State,
+        _mgr: &mut EM,
+        input: &Self::Input,
+    ) -> Result<ExitKind, Error> {
+        *state.executions_mut() += 1;
+
+        let target = input.target_bytes();
+        let buf = target.as_slice();
+        if self.keep_loaded {
+            self.reload_if_changed()?;
+            let (library, _) = self.library.as_ref().unwrap();
+            let entry = self.entry_point(library)?;
+            unsafe {
+                entry(buf.as_ptr(), buf.len());
+            }
+        } else {
+            let library = self.load()?;
+            let entry = self.entry_point(&library)?;
+            unsafe {
+                entry(buf.as_ptr(), buf.len());
+            }
+            library.close().map_err(|err| {
+                Error::unknown(format!("Failed to unload {:?}: {err}", self.path))
+            })?;
+        }
+        Ok(ExitKind::Ok)
+    }
+}
+
+impl<OT, S> UsesState for LibraryExecutor<OT, S>
+where
+    S: State,
+{
+    type State = S;
+}
+
+impl<OT, S> UsesObservers for LibraryExecutor<OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: State,
+{
+    type Observers = OT;
+}
+
+impl<OT, S> HasObservers for LibraryExecutor<OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: State,
+{
+    #[inline]
+    fn observers(&self) -> &OT {
+        &self.observers
+    }
+
+    #[inline]
+    fn observers_mut(&mut self) -> &mut OT {
+        &mut self.observers
+    }
+}
+
+#[cfg(test)]
+#[cfg(target_os = "linux")]
+mod tests {
+    use alloc::rc::Rc;
+    use core::cell::Cell;
+
+    use super::LibraryExecutor;
+    use crate::{inputs::BytesInput, state::NopState};
+
+    #[test]
+    fn test_library_before_load() {
+        // any library with an exported function does, the entry point is never called
+        let Ok(executor) =
+            (unsafe { LibraryExecutor::<(), NopState<BytesInput>>::new("libm.so.6", "cos", ()) })
+        else {
+            return;
+        };
+        let loads = Rc::new(Cell::new(0));
+        let hook_loads = loads.clone();
+        let mut executor = executor.before_load(move || hook_loads.set(hook_loads.get() + 1));
+
+        // checking the entry point in `new` does not count
+        assert_eq!(loads.get(), 0);
+        for _ in 0..3 {
+            let library = executor.load().unwrap();
+            executor.entry_point(&library).unwrap();
+            library.close().unwrap();
+        }
+        assert_eq!(loads.get(), 3);
+
+        executor = executor.keep_loaded(true);
+        executor.reload_if_changed().unwrap();
+        executor.reload_if_changed().unwrap();
+        assert_eq!(loads.get(), 4);
+
+        assert!(unsafe {
+            LibraryExecutor::<(), NopState<BytesInput>>::new("libm.so.6", "no_such_entry", ())
+        }
+        .is_err());
+    }
+}
@@ -4,19 +4,41 @@
 use alloc::vec::Vec;
 use core::fmt::Debug;
 
+pub use after_each::AfterEachHookExecutor;
 pub use combined::CombinedExecutor;
 #[cfg(all(feature = "std", any(unix, doc)))]
 pub use command::CommandExecutor;
 pub use differential::DiffExecutor;
 #[cfg(all(feature = "std", feature = "fork", unix))]
 pub use forkserver::{Forkserver, ForkserverExecutor};
-pub use inprocess::InProcessExecutor;
+#[cfg(all(feature = "std", feature = "fork", unix))]
+pub use guaranteed_timeout::GuaranteedTimeoutExecutor;
+pub use inprocess::{persistent::PersistentModeInProcessExecutor, InProcessExecutor};
 #[cfg(all(feature = "std", feature = "fork", unix))]
 pub use inprocess_fork::InProcessForkExecutor;
 #[cfg(unix)]
 use libafl_bolts::os::unix_signals::Signal;
+#[cfg(feature = "libfuzzer_compat")]
+pub use libfuzzer_compat::LibFuzzerCompatExecutor;
+#[cfg(all(feature = "library_executor", unix))]
+pub use library::LibraryExecutor;
+#[cfg(feature = "std")]
+pub use network::NetworkExecutor;
+#[cfg(all(feature = "std", unix))]
+pub use pipe::ChildPipeExecutor;
+#[cfg(feature = "evaluator_pool")]
+pub use pool::EvaluatorPool;
+#[cfg(all(feature = "std", unix))]
+pub use restart::CoverageGuidedRestartExecutor;
+pub use retry::RetryExecutor;
+#[cfg(all(feature = "std", target_os = "linux"))]
+pub use sandbox::SandboxedExecutor;
 use serde::{Deserialize, Serialize};
 pub use shadow::ShadowExecutor;
+#[cfg(feature = "wasm")]
+pub use wasm::WasmExecutor;
+#[cfg(all(feature = "std", windows))]
+pub use windows_process::WindowsProcessExecutor;
 pub use with_observers::WithObservers;
 
 use crate::{
@@ -25,20 +47,62 @@ use crate::{
     Error,
 };
 
+pub mod after_each;
 pub mod combined;
 #[cfg(all(feature = "std", any(unix, doc)))]
 pub mod command;
 pub mod differential;
 #[cfg(all(feature = "std", feature = "fork", unix))]
 pub mod forkserver;
+/// The module for the forking executor killing timed out children with `SIGKILL`
+#[cfg(all(feature = "std", feature = "fork", unix))]
+pub mod guaranteed_timeout;
 pub mod inprocess;
 
 /// The module for inproc fork executor
 #[cfg(all(feature = "std", unix))]
 pub mod inprocess_fork;
 
+/// The module for the executor running libFuzzer harnesses from a shared library
+#[cfg(feature = "libfuzzer_compat")]
+pub mod libfuzzer_compat;
+
+/// The module for the executor loading the target library anew for each input
+#[cfg(all(feature = "library_executor", unix))]
+pub mod library;
+
+/// The module for the TCP network executor
+#[cfg(feature = "std")]
+pub mod network;
+
+/// The module for the stdin pipe executor
+#[cfg(all(feature = "std", unix))]
+pub mod pipe;
+
+/// The module for the pool of worker threads running inputs
+#[cfg(feature = "evaluator_pool")]
+pub mod pool;
+
+/// The module for the executor restarting a long-running child when the coverage stagnates
+#[cfg(all(feature = "std", unix))]
+pub mod restart;
+
+pub mod retry;
+
+/// The module for the executor running commands in their own Linux namespaces
+#[cfg(all(feature = "std", target_os = "linux"))]
+pub mod sandbox;
+
 pub mod shadow;
 
+/// The module for the WebAssembly executor
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+/// The module for the executor running a new Windows process confined to a job object for each input
+#[cfg(all(feature = "std", windows))]
+pub mod windows_process;
+
 pub mod with_observers;
 
 /// The module for all the hooks
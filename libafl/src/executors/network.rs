@@ -0,0 +1,345 @@
+//! The [`NetworkExecutor`] sends each input to a target server over TCP, for targets running as network services.
+
+use alloc::vec::Vec;
+use core::{
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+    time::Duration,
+};
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::{SocketAddr, TcpStream},
+    thread,
+    time::Instant,
+};
+
+use libafl_bolts::AsSlice;
+
+use crate::{
+    executors::{Executor, ExitKind, HasObservers},
+    inputs::HasTargetBytes,
+    observers::{ObserversTuple, UsesObservers},
+    state::{HasExecutions, State, UsesState},
+    Error,
+};
+
+/// How long to wait between attempts to reach the server, while it is not accepting connections or in the health check
+const HEALTH_CHECK_RETRY_DELAY: Duration = Duration::from_millis(10);
+
+/// Prefixes `bytes` with their length, as a little endian `u32`
+fn packet(bytes: &[u8]) -> Result<Vec<u8>, Error> {
+    let len = u32::try_from(bytes.len())
+        .map_err(|_| Error::illegal_argument("Packet is too large to be sent"))?;
+    let mut packet = Vec::with_capacity(4 + bytes.len());
+    packet.extend_from_slice(&len.to_le_bytes());
+    packet.extend_from_slice(bytes);
+    Ok(packet)
+}
+
+/// This [`Executor`] connects to a server at a given [`SocketAddr`] for each execution,
+/// sends the input as a packet prefixed with its length (a little endian `u32`), and waits for a response.
+/// Servers reading raw bytes get the input without the prefix, see [`NetworkExecutor::set_length_prefix`].
+///
+/// If no response arrives within the timeout, the run is a [`ExitKind::Timeout`];
+/// if the server drops the connection instead, it's a [`ExitKind::Crash`].
+/// While the server refuses connections, e.g. during a restart, connecting is retried until the timeout,
+/// after which the run is a [`ExitKind::Timeout`] as well.
+///
+/// Whatever supervises the server has to restart it after a crash. To not blame the next input for the restart,
+/// set a health check with [`NetworkExecutor::set_health_check`]: before each execution, a known-good packet
+/// is sent until the server responds to it again.
+pub struct NetworkExecutor<OT, S> {
+    addr: SocketAddr,
+    timeout: Duration,
+    health_check: Option<Vec<u8>>,
+    length_prefix: bool,
+    response: Vec<u8>,
+    observers: OT,
+    phantom: PhantomData<S>,
+}
+
+impl<OT, S> Debug for NetworkExecutor<OT, S>
+where
+    OT: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NetworkExecutor")
+            .field("addr", &self.addr)
+            .field("timeout", &self.timeout)
+            .field("health_check", &self.health_check)
+            .field("length_prefix", &self.length_prefix)
+            .field("observers", &self.observers)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<OT, S> NetworkExecutor<OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: State,
+{
+    /// Creates a new [`NetworkExecutor`], sending inputs to the server at `addr`.
+    /// The `timeout` applies to connecting, and to waiting for the response.
+    pub fn new(addr: SocketAddr, timeout: Duration, observers: OT) -> Self {
+        Self {
+            addr,
+            timeout,
+            health_check: None,
+            length_prefix: true,
+            response: Vec::new(),
+            observers,
+            phantom: PhantomData,
+        }
+    }
+
+    /// The address of the server
+    #[must_use]
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// The timeout for a single execution
+    #[must_use]
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Sets a known-good packet, the server has to respond to before each execution.
+    /// `None` disables the health check.
+    pub fn set_health_check(&mut self, ping: Option<Vec<u8>>) {
+        self.health_check = ping;
+    }
+
+    /// Sets whether the inputs and the health check packet are prefixed with their length, the default.
+    /// Without the prefix, the raw bytes are sent, and the server has to tell the end of the input by itself.
+    pub fn set_length_prefix(&mut self, length_prefix: bool) {
+        self.length_prefix = length_prefix;
+    }
+
+    /// The response of the server to the last input, empty if there was none
+    #[must_use]
+    pub fn response(&self) -> &[u8] {
+        &self.response
+    }
+
+    /// Connects to the server, retrying while it refuses connections, until the timeout expires
+    fn connect(&self) -> Result<TcpStream, Error> {
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            match TcpStream::connect_timeout(&self.addr, self.timeout) {
+                Err(err)
+                    if err.kind() == ErrorKind::ConnectionRefused && Instant::now() < deadline =>
+                {
+                    thread::sleep(HEALTH_CHECK_RETRY_DELAY);
+                }
+                ret => return Ok(ret?),
+            }
+        }
+    }
+
+    /// Connects to the server, and sends `bytes`, as a packet if [`NetworkExecutor::set_length_prefix`] is set
+    fn send(&self, bytes: &[u8]) -> Result<TcpStream, Error> {
+        let mut stream = self.connect()?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+        stream.set_nodelay(true)?;
+        if self.length_prefix {
+            stream.write_all(&packet(bytes)?)?;
+        } else {
+            stream.write_all(bytes)?;
+        }
+        Ok(stream)
+    }
+
+    /// Sends the health check packet until the server responds to it, or the timeout expires
+    fn wait_for_server(&self, ping: &[u8]) -> Result<(), Error> {
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            let mut buf = [0_u8; 1];
+            let responded = self
+                .send(ping)
+                .and_then(|mut stream| Ok(stream.read(&mut buf)?))
+                .is_ok_and(|read| read > 0);
+            if responded {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                return Err(Error::illegal_state(format!(
+                    "The server at {} does not respond to the health check",
+                    self.addr
+                )));
+            }
+            thread::sleep(HEALTH_CHECK_RETRY_DELAY);
+        }
+    }
+
+    /// Sends the input to the server and waits for the response, returning the resulting [`ExitKind`]
+    fn send_input(&mut self, input: &[u8]) -> Result<ExitKind, Error> {
+        let mut stream = self.send(input)?;
+
+        let mut buf = [0_u8; 4096];
+        loop {
+            match stream.read(&mut buf) {
+                // the server closed the connection without responding
+                Ok(0) if self.response.is_empty() => return Ok(ExitKind::Crash),
+                Ok(0) => return Ok(ExitKind::Ok),
+                Ok(read) => {
+                    self.response.extend_from_slice(&buf[..read]);
+                    // more may follow, but the server responded.
+                    stream.set_nonblocking(true)?;
+                }
+                Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    if self.response.is_empty() {
+                        return Ok(ExitKind::Timeout);
+                    }
+                    return Ok(ExitKind::Ok);
+                }
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted
+                    ) =>
+                {
+                    return Ok(ExitKind::Crash);
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+}
+
+impl<EM, OT, S, Z> Executor<EM, Z> for NetworkExecutor<OT, S>
+where
+    EM: UsesState<State = S>,
+    S: State + HasExecutions,
+    S::Input: HasTargetBytes,
+    OT: Debug + ObserversTuple<S>,
+    Z: UsesState<State = S>,
+{
+    fn run_target(
+        &mut self,
+        _fuzzer: &mut Z,
+        state: &mut Self::State,
+        _mgr: &mut EM,
+        input: &Self::Input,
+    ) -> Result<ExitKind, Error> {
+        *state.executions_mut() += 1;
+
+        if let Some(ping) = &self.health_check {
+            self.wait_for_server(ping)?;
+        }
+
+        self.response.clear();
+        match self.send_input(input.target_bytes().as_slice()) {
+            Err(Error::File(err, _))
+                if matches!(
+                    err.kind(),
+                    ErrorKind::BrokenPipe
+                        | ErrorKind::ConnectionReset
+                        | ErrorKind::ConnectionAborted
+                ) =>
+            {
+                // the server went away while we were sending the input
+                Ok(ExitKind::Crash)
+            }
+            // the server did not come back, or accept the connection, within the timeout
+            Err(Error::File(err, _))
+                if matches!(
+                    err.kind(),
+                    ErrorKind::ConnectionRefused | ErrorKind::TimedOut | ErrorKind::WouldBlock
+                ) =>
+            {
+                Ok(ExitKind::Timeout)
+            }
+            ret => ret,
+        }
+    }
+}
+
+impl<OT, S> UsesState for NetworkExecutor<OT, S>
+where
+    S: State,
+{
+    type State = S;
+}
+
+impl<OT, S> UsesObservers for NetworkExecutor<OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: State,
+{
+    type Observers = OT;
+}
+
+impl<OT, S> HasObservers for NetworkExecutor<OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: State,
+{
+    fn observers(&self) -> &OT {
+        &self.observers
+    }
+
+    fn observers_mut(&mut self) -> &mut OT {
+        &mut self.observers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use super::{packet, NetworkExecutor};
+    use crate::{
+        events::NopEventManager,
+        executors::{Executor, ExitKind},
+        fuzzer::test::NopFuzzer,
+        inputs::BytesInput,
+        state::NopState,
+    };
+
+    #[test]
+    fn test_packet() {
+        assert_eq!(packet(b"ab").unwrap(), [2, 0, 0, 0, b'a', b'b']);
+        assert_eq!(packet(b"").unwrap(), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_network_executor_raw() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        // echoes the raw bytes of a single connection, then refuses all others
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 3];
+            stream.read_exact(&mut buf).unwrap();
+            stream.write_all(&buf).unwrap();
+        });
+
+        let mut executor =
+            NetworkExecutor::<(), NopState<BytesInput>>::new(addr, Duration::from_millis(200), ());
+        executor.set_length_prefix(false);
+        let run = |executor: &mut NetworkExecutor<_, _>| {
+            executor
+                .run_target(
+                    &mut NopFuzzer::new(),
+                    &mut NopState::new(),
+                    &mut NopEventManager::new(),
+                    &BytesInput::new(b"abc".to_vec()),
+                )
+                .unwrap()
+        };
+        assert_eq!(run(&mut executor), ExitKind::Ok);
+        assert_eq!(executor.response(), b"abc");
+        server.join().unwrap();
+
+        // the server is gone, and does not come back within the timeout
+        assert_eq!(run(&mut executor), ExitKind::Timeout);
+    }
+}
@@ -0,0 +1,365 @@
+//! The [`ChildPipeExecutor`] spawns the target once and feeds each input through its stdin pipe,
+//! for targets that read from stdin in a loop but do not implement the forkserver protocol.
+
+use alloc::vec::Vec;
+use core::{
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+    time::Duration,
+};
+use std::{
+    io::{ErrorKind, Read, Write},
+    os::fd::{AsRawFd, BorrowedFd},
+    process::{Child, Command, Stdio},
+    time::Instant,
+};
+
+use libafl_bolts::AsSlice;
+use nix::sys::{
+    select::{pselect, FdSet},
+    signal::SigSet,
+    time::TimeSpec,
+};
+
+use crate::{
+    executors::{Executor, ExitKind, HasObservers},
+    inputs::HasTargetBytes,
+    observers::{ObserversTuple, UsesObservers},
+    state::{HasExecutions, State, UsesState},
+    Error,
+};
+
+/// This [`Executor`] keeps a single child process alive across executions.
+///
+/// Each input is written to the child's stdin, then the child's stdout is read until the
+/// `sentinel` byte sequence shows up, which marks the end of the processing of that input.
+/// If the sentinel does not appear within the timeout, the run is a [`ExitKind::Timeout`];
+/// if the child dies, the run is a [`ExitKind::Crash`]. In both cases the child is respawned
+/// for the next execution.
+///
+/// Unlike the [`crate::executors::ForkserverExecutor`], the target does not need to be recompiled,
+/// but any state the target keeps between inputs will leak into subsequent runs.
+pub struct ChildPipeExecutor<OT, S> {
+    command: Command,
+    child: Option<Child>,
+    sentinel: Vec<u8>,
+    timeout: Duration,
+    observers: OT,
+    phantom: PhantomData<S>,
+}
+
+impl<OT, S> Debug for ChildPipeExecutor<OT, S>
+where
+    OT: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChildPipeExecutor")
+            .field("command", &self.command)
+            .field("child", &self.child)
+            .field("sentinel", &self.sentinel)
+            .field("timeout", &self.timeout)
+            .field("observers", &self.observers)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<OT, S> ChildPipeExecutor<OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: State,
+{
+    /// Creates a new [`ChildPipeExecutor`].
+    /// The child is spawned lazily, on the first execution.
+    /// Its stdin and stdout will be replaced by pipes, stderr is discarded.
+    /// With an empty `sentinel`, the first chunk of output ends the execution.
+    pub fn new(mut command: Command, sentinel: Vec<u8>, timeout: Duration, observers: OT) -> Self {
+        command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null());
+        Self {
+            command,
+            child: None,
+            sentinel,
+            timeout,
+            observers,
+            phantom: PhantomData,
+        }
+    }
+
+    /// The sentinel marking the end of an execution in the child's output
+    #[must_use]
+    pub fn sentinel(&self) -> &[u8] {
+        &self.sentinel
+    }
+
+    /// The timeout for a single execution
+    #[must_use]
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Kills the current child, if any. A new one will be spawned on the next execution.
+    pub fn kill_child(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            // if this fails, the child most likely exited on its own in the meantime.
+            drop(child.kill());
+            drop(child.wait());
+        }
+    }
+
+    /// Spawns the child, with non-blocking pipes, so that neither side can stall an execution past its timeout
+    fn spawn_child(&mut self) -> Result<(), Error> {
+        let child = self.command.spawn()?;
+        for fd in [
+            child.stdin.as_ref().unwrap().as_raw_fd(),
+            child.stdout.as_ref().unwrap().as_raw_fd(),
+        ] {
+            // # Safety
+            // The pipes of the child are open
+            unsafe {
+                let flags = libc::fcntl(fd, libc::F_GETFL);
+                if flags == -1 || libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) == -1 {
+                    return Err(std::io::Error::last_os_error().into());
+                }
+            }
+        }
+        self.child = Some(child);
+        Ok(())
+    }
+
+    /// Writes the input to the child and waits for the sentinel, returning the resulting [`ExitKind`].
+    ///
+    /// Writing the input and reading the output are interleaved, within the timeout, so that a child
+    /// that stops reading, or writes more than the pipe holds before reading on, times out instead of blocking us.
+    fn feed_child(&mut self, input: &[u8], output: &mut Vec<u8>) -> Result<ExitKind, Error> {
+        let mut buf = [0_u8; 4096];
+        if let Some(child) = self.child.as_mut() {
+            // output left over from the last execution, e.g. following its sentinel, must not end this one
+            let stdout = child.stdout.as_mut().unwrap();
+            let exited = loop {
+                match stdout.read(&mut buf) {
+                    Ok(0) => break true,
+                    Ok(_) => {}
+                    Err(err) if err.kind() == ErrorKind::WouldBlock => break false,
+                    Err(err) => return Err(err.into()),
+                }
+            };
+            // the child exited after the last execution, not during this one
+            if exited {
+                self.kill_child();
+            }
+        }
+        if self.child.is_none() {
+            self.spawn_child()?;
+        }
+        let child = self.child.as_mut().unwrap();
+        let stdin = child.stdin.as_mut().unwrap();
+        let stdout = child.stdout.as_mut().unwrap();
+
+        let deadline = Instant::now() + self.timeout;
+        let mut written = 0;
+        loop {
+            let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+                return Ok(ExitKind::Timeout);
+            };
+
+            // # Safety
+            // The child's stdin and stdout stay open for the duration of this call.
+            let stdout_fd = unsafe { BorrowedFd::borrow_raw(stdout.as_raw_fd()) };
+            let stdin_fd = unsafe { BorrowedFd::borrow_raw(stdin.as_raw_fd()) };
+            let mut readfds = FdSet::new();
+            readfds.insert(&stdout_fd);
+            let mut writefds = FdSet::new();
+            if written < input.len() {
+                writefds.insert(&stdin_fd);
+            }
+            let sret = pselect(
+                Some(stdout_fd.as_raw_fd().max(stdin_fd.as_raw_fd()) + 1),
+                &mut readfds,
+                &mut writefds,
+                None,
+                Some(&TimeSpec::from_duration(remaining)),
+                Some(&SigSet::empty()),
+            )?;
+            if sret == 0 {
+                return Ok(ExitKind::Timeout);
+            }
+
+            if writefds.contains(&stdin_fd) {
+                match stdin.write(&input[written..]) {
+                    Ok(count) => written += count,
+                    Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+                    Err(err) if err.kind() == ErrorKind::BrokenPipe => return Ok(ExitKind::Crash),
+                    Err(err) => return Err(err.into()),
+                }
+            }
+
+            if !readfds.contains(&stdout_fd) {
+                continue;
+            }
+            let read = match stdout.read(&mut buf) {
+                Ok(read) => read,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => continue,
+                Err(err) => return Err(err.into()),
+            };
+            if read == 0 {
+                // EOF, the child is gone.
+                return Ok(ExitKind::Crash);
+            }
+            output.extend_from_slice(&buf[..read]);
+
+            if self.sentinel.is_empty()
+                || output
+                    .windows(self.sentinel.len())
+                    .any(|window| window == self.sentinel.as_slice())
+            {
+                return Ok(ExitKind::Ok);
+            }
+        }
+    }
+}
+
+impl<EM, OT, S, Z> Executor<EM, Z> for ChildPipeExecutor<OT, S>
+where
+    EM: UsesState<State = S>,
+    S: State + HasExecutions,
+    S::Input: HasTargetBytes,
+    OT: Debug + ObserversTuple<S>,
+    Z: UsesState<State = S>,
+{
+    fn run_target(
+        &mut self,
+        _fuzzer: &mut Z,
+        state: &mut Self::State,
+        _mgr: &mut EM,
+        input: &Self::Input,
+    ) -> Result<ExitKind, Error> {
+        *state.executions_mut() += 1;
+
+        let mut output = Vec::new();
+        let exit_kind = self.feed_child(input.target_bytes().as_slice(), &mut output)?;
+        if exit_kind != ExitKind::Ok {
+            self.kill_child();
+        }
+
+        if self.observers.observes_stdout() {
+            self.observers.observe_stdout(&output);
+        }
+
+        Ok(exit_kind)
+    }
+}
+
+impl<OT, S> Drop for ChildPipeExecutor<OT, S> {
+    fn drop(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            drop(child.kill());
+            drop(child.wait());
+        }
+    }
+}
+
+impl<OT, S> UsesState for ChildPipeExecutor<OT, S>
+where
+    S: State,
+{
+    type State = S;
+}
+
+impl<OT, S> UsesObservers for ChildPipeExecutor<OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: State,
+{
+    type Observers = OT;
+}
+
+impl<OT, S> HasObservers for ChildPipeExecutor<OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: State,
+{
+    fn observers(&self) -> &OT {
+        &self.observers
+    }
+
+    fn observers_mut(&mut self) -> &mut OT {
+        &mut self.observers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+    use std::process::Command;
+
+    use libafl_bolts::tuples::tuple_list;
+
+    use super::ChildPipeExecutor;
+    use crate::{
+        events::NopEventManager,
+        executors::{Executor, ExitKind, HasObservers},
+        fuzzer::test::NopFuzzer,
+        inputs::BytesInput,
+        observers::StdOutObserver,
+        state::{HasExecutions, NopState},
+    };
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_child_pipe_exec() {
+        let mut state = NopState::<BytesInput>::new();
+        let mut fuzzer = NopFuzzer::new();
+        let mut mgr = NopEventManager::new();
+
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(
+            r#"while read line; do
+                case "$line" in crash) exit 1;; hang) sleep 10;; esac
+                echo "out:$line"; echo DONE
+            done"#,
+        );
+        let mut executor = ChildPipeExecutor::new(
+            command,
+            b"DONE\n".to_vec(),
+            Duration::from_millis(500),
+            tuple_list!(StdOutObserver::new("stdout".into())),
+        );
+
+        let mut pids = vec![];
+        // the child is kept across executions, and respawned after a crash or a timeout
+        for (input, expected, respawned) in [
+            (&b"a\n"[..], ExitKind::Ok, true),
+            (b"b\n", ExitKind::Ok, false),
+            (b"crash\n", ExitKind::Crash, false),
+            (b"c\n", ExitKind::Ok, true),
+            (b"hang\n", ExitKind::Timeout, false),
+            (b"d\n", ExitKind::Ok, true),
+        ] {
+            let exit_kind = executor
+                .run_target(
+                    &mut fuzzer,
+                    &mut state,
+                    &mut mgr,
+                    &BytesInput::new(input.to_vec()),
+                )
+                .unwrap();
+            assert_eq!(exit_kind, expected);
+            if exit_kind == ExitKind::Ok {
+                let pid = executor.child.as_ref().unwrap().id();
+                assert_eq!(pids.last() != Some(&pid), respawned);
+                pids.push(pid);
+
+                let stdout = executor.observers().0.stdout.as_ref().unwrap();
+                let mut expected_stdout = b"out:".to_vec();
+                expected_stdout.extend_from_slice(input);
+                expected_stdout.extend_from_slice(b"DONE\n");
+                assert_eq!(stdout, &expected_stdout);
+            } else {
+                assert!(executor.child.is_none());
+            }
+        }
+        assert_eq!(*state.executions(), 6);
+    }
+}
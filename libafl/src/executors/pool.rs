@@ -0,0 +1,264 @@
+//! The [`EvaluatorPool`] runs inputs on a pool of worker threads, for multi-core in-process fuzzing without LLMP.
+//!
+//! Each worker owns a clone of the executor, the state, and the fuzzer it hands to the executor.
+//! The main thread distributes the inputs over a work queue, and collects the exit kind and the observers
+//! of each execution. The feedbacks, and thereby the corpus, are only evaluated on the main thread,
+//! in the order the inputs were given, so they need no synchronization.
+//!
+//! The observers are sent back serialized, so that each result carries its own copy of the observed data.
+//! Observers of global memory, such as a static coverage map, would see the executions of all workers at once:
+//! the harness of each worker has to write to memory owned by its observers, e.g. thread-local maps.
+
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use std::thread::{self, JoinHandle};
+
+use crossbeam_channel::{Receiver, Sender};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    corpus::CorpusId,
+    events::{EventFirer, NopEventManager},
+    executors::{Executor, ExitKind, HasObservers},
+    fuzzer::{ExecuteInputResult, ExecutionProcessor},
+    inputs::UsesInput,
+    observers::ObserversTuple,
+    state::{HasExecutions, State, UsesState},
+    Error,
+};
+
+/// The result of an execution on a worker: the exit kind and the serialized observers
+type WorkerResult = Result<(ExitKind, Vec<u8>), Error>;
+
+/// A pool of worker threads running inputs, see the [module documentation](self).
+#[derive(Debug)]
+pub struct EvaluatorPool<E, S>
+where
+    S: UsesInput,
+{
+    /// The work queue, dropped to stop the workers
+    work: Option<Sender<(usize, S::Input)>>,
+    results: Receiver<(usize, WorkerResult)>,
+    workers: Vec<JoinHandle<()>>,
+    phantom: PhantomData<E>,
+}
+
+impl<E, S> EvaluatorPool<E, S>
+where
+    E: HasObservers<State = S>,
+    E::Observers: Serialize + DeserializeOwned,
+    S: State + HasExecutions,
+{
+    /// Spawns `num_workers` worker threads, each with its own clone of `executor`, `state`, and `fuzzer`
+    pub fn new<Z>(num_workers: usize, executor: &E, state: &S, fuzzer: &Z) -> Result<Self, Error>
+    where
+        E: Executor<NopEventManager<S>, Z> + Clone + Send + 'static,
+        S: Clone + Send + 'static,
+        S::Input: Send,
+        Z: UsesState<State = S> + Clone + Send + 'static,
+    {
+        if num_workers == 0 {
+            return Err(Error::illegal_argument(
+                "An EvaluatorPool needs at least one worker",
+            ));
+        }
+        let (work_sender, work_receiver) = crossbeam_channel::unbounded();
+        let (result_sender, result_receiver) = crossbeam_channel::unbounded();
+
+        let mut workers = Vec::with_capacity(num_workers);
+        for id in 0..num_workers {
+            let executor = executor.clone();
+            let state = state.clone();
+            let fuzzer = fuzzer.clone();
+            let work = work_receiver.clone();
+            let results = result_sender.clone();
+            workers.push(
+                thread::Builder::new()
+                    .name(format!("libafl-worker-{id}"))
+                    .spawn(move || run_worker(executor, state, fuzzer, &work, &results))?,
+            );
+        }
+
+        Ok(Self {
+            work: Some(work_sender),
+            results: result_receiver,
+            workers,
+            phantom: PhantomData,
+        })
+    }
+
+    /// The number of worker threads
+    #[must_use]
+    pub fn num_workers(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Runs all `inputs` on the workers, and returns their exit kinds and observers, in the order of `inputs`
+    pub fn run_inputs(
+        &mut self,
+        state: &mut S,
+        inputs: &[S::Input],
+    ) -> Result<Vec<(ExitKind, E::Observers)>, Error>
+    where
+        S::Input: Clone,
+    {
+        let work = self.work.as_ref().unwrap();
+        for (idx, input) in inputs.iter().enumerate() {
+            work.send((idx, input.clone()))
+                .map_err(|_| Error::shutting_down())?;
+        }
+
+        let results = collect_results(&self.results, inputs.len());
+        // the executions happened in the states of the workers
+        *state.executions_mut() += inputs.len();
+        results
+    }
+
+    /// Runs all `inputs` on the workers, then evaluates the feedbacks of `fuzzer` for each of them on this thread,
+    /// adding the interesting ones to the corpus, in the order of `inputs`.
+    pub fn evaluate_inputs<Z, EM>(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut S,
+        manager: &mut EM,
+        inputs: Vec<S::Input>,
+    ) -> Result<Vec<(ExecuteInputResult, Option<CorpusId>)>, Error>
+    where
+        Z: ExecutionProcessor<E::Observers, State = S>,
+        EM: EventFirer<State = S>,
+        S::Input: Clone,
+    {
+        let results = self.run_inputs(state, &inputs)?;
+        inputs
+            .into_iter()
+            .zip(results)
+            .map(|(input, (exit_kind, observers))| {
+                fuzzer.process_execution(state, manager, input, &observers, &exit_kind, true)
+            })
+            .collect()
+    }
+}
+
+impl<E, S> Drop for EvaluatorPool<E, S>
+where
+    S: UsesInput,
+{
+    fn drop(&mut self) {
+        // closing the work queue stops the workers
+        drop(self.work.take());
+        for worker in self.workers.drain(..) {
+            drop(worker.join());
+        }
+    }
+}
+
+/// Receives the results of `count` inputs, and orders them by the index of their input.
+///
+/// All `count` results are received, even after an error, so that none of them is left in the queue
+/// to be mistaken for a result of the next batch. The first error is returned.
+fn collect_results<OT>(
+    results: &Receiver<(usize, WorkerResult)>,
+    count: usize,
+) -> Result<Vec<(ExitKind, OT)>, Error>
+where
+    OT: DeserializeOwned,
+{
+    let mut ordered: Vec<Option<(ExitKind, OT)>> = (0..count).map(|_| None).collect();
+    let mut first_error = None;
+    for _ in 0..count {
+        let (idx, result) = results
+            .recv()
+            .map_err(|_| Error::illegal_state("All workers of the EvaluatorPool stopped"))?;
+        let observers = result
+            .and_then(|(exit_kind, observers)| Ok((exit_kind, postcard::from_bytes(&observers)?)));
+        match observers {
+            Ok(observers) => ordered[idx] = Some(observers),
+            Err(err) => {
+                first_error.get_or_insert(err);
+            }
+        }
+    }
+    match first_error {
+        Some(err) => Err(err),
+        None => Ok(ordered.into_iter().map(Option::unwrap).collect()),
+    }
+}
+
+/// The loop of a worker thread, running inputs until the work queue is closed
+fn run_worker<E, S, Z>(
+    mut executor: E,
+    mut state: S,
+    mut fuzzer: Z,
+    work: &Receiver<(usize, S::Input)>,
+    results: &Sender<(usize, WorkerResult)>,
+) where
+    E: Executor<NopEventManager<S>, Z> + HasObservers<State = S>,
+    E::Observers: Serialize,
+    S: State + HasExecutions,
+    Z: UsesState<State = S>,
+{
+    let mut mgr = NopEventManager::new();
+    while let Ok((idx, input)) = work.recv() {
+        let result = run_input(&mut executor, &mut state, &mut fuzzer, &mut mgr, &input);
+        if results.send((idx, result)).is_err() {
+            break;
+        }
+    }
+}
+
+/// Runs a single input on a worker, like [`crate::fuzzer::StdFuzzer::execute_input`]
+fn run_input<E, S, Z>(
+    executor: &mut E,
+    state: &mut S,
+    fuzzer: &mut Z,
+    mgr: &mut NopEventManager<S>,
+    input: &S::Input,
+) -> WorkerResult
+where
+    E: Executor<NopEventManager<S>, Z> + HasObservers<State = S>,
+    E::Observers: Serialize,
+    S: State + HasExecutions,
+    Z: UsesState<State = S>,
+{
+    executor.observers_mut().pre_exec_all(state, input)?;
+    let exit_kind = executor.run_target(fuzzer, state, mgr, input)?;
+    executor
+        .observers_mut()
+        .post_exec_all(state, input, &exit_kind)?;
+    Ok((exit_kind, postcard::to_allocvec(executor.observers())?))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        executors::{pool::collect_results, ExitKind},
+        Error,
+    };
+
+    #[test]
+    fn test_collect_results() {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let unit = postcard::to_allocvec(&()).unwrap();
+
+        // the results arrive out of order
+        sender
+            .send((1, Ok((ExitKind::Crash, unit.clone()))))
+            .unwrap();
+        sender.send((0, Ok((ExitKind::Ok, unit.clone())))).unwrap();
+        let results = collect_results::<()>(&receiver, 2).unwrap();
+        assert_eq!(results, [(ExitKind::Ok, ()), (ExitKind::Crash, ())]);
+
+        // an error does not leave the other results of its batch behind
+        sender.send((1, Err(Error::empty("Input Empty")))).unwrap();
+        sender.send((0, Ok((ExitKind::Ok, unit.clone())))).unwrap();
+        sender
+            .send((2, Ok((ExitKind::Timeout, unit.clone()))))
+            .unwrap();
+        assert!(collect_results::<()>(&receiver, 3).is_err());
+        assert!(receiver.try_recv().is_err());
+
+        sender.send((0, Ok((ExitKind::Oom, unit)))).unwrap();
+        let results = collect_results::<()>(&receiver, 1).unwrap();
+        assert_eq!(results, [(ExitKind::Oom, ())]);
+    }
+}
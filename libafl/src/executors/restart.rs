@@ -0,0 +1,276 @@
+//! The [`CoverageGuidedRestartExecutor`] keeps the child of a [`ChildPipeExecutor`] alive for as long as it finds new coverage,
+//! for targets that are expensive to initialize, and restarts it once the coverage stagnates.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+};
+
+use crate::{
+    events::{Event, EventFirer},
+    executors::{pipe::ChildPipeExecutor, Executor, ExitKind, HasObservers},
+    inputs::HasTargetBytes,
+    monitors::{AggregatorOps, UserStats, UserStatsValue},
+    observers::{MapObserver, ObserversTuple, UsesObservers},
+    state::{HasExecutions, State, UsesState},
+    Error,
+};
+
+/// The name of the user stats holding the number of restarts
+pub const RESTARTS_STATS_NAME: &str = "restarts";
+
+/// This [`Executor`] wraps a [`ChildPipeExecutor`], restarting its child when the coverage stagnates.
+///
+/// After each run, the map observer named `map_observer_name` is checked for edges never hit before.
+/// If none were hit for `stagnation_limit` consecutive executions, the child is killed and a fresh one
+/// is spawned on the next execution, dropping any state accumulated in the long-running process.
+/// The number of restarts is reported as [`RESTARTS_STATS_NAME`] user stats.
+///
+/// The `reset_fn`, if set, runs before each respawn. It is meant to restore a checkpoint of the state of the target,
+/// e.g. a snapshot of an already initialized data directory, so that the new child skips the expensive initialization.
+pub struct CoverageGuidedRestartExecutor<M, OT, S> {
+    inner: ChildPipeExecutor<OT, S>,
+    map_observer_name: String,
+    stagnation_limit: usize,
+    reset_fn: Option<fn()>,
+    /// If each entry of the map was hit in any execution so far
+    seen: Vec<bool>,
+    runs_without_new: usize,
+    restarts: u64,
+    phantom: PhantomData<M>,
+}
+
+impl<M, OT, S> Debug for CoverageGuidedRestartExecutor<M, OT, S>
+where
+    OT: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CoverageGuidedRestartExecutor")
+            .field("inner", &self.inner)
+            .field("map_observer_name", &self.map_observer_name)
+            .field("stagnation_limit", &self.stagnation_limit)
+            .field("runs_without_new", &self.runs_without_new)
+            .field("restarts", &self.restarts)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<M, OT, S> CoverageGuidedRestartExecutor<M, OT, S>
+where
+    M: MapObserver,
+    OT: ObserversTuple<S>,
+    S: State,
+{
+    /// Creates a new [`CoverageGuidedRestartExecutor`], restarting the child of `inner`
+    /// after `stagnation_limit` consecutive executions without new coverage in `map_observer`.
+    pub fn new(inner: ChildPipeExecutor<OT, S>, map_observer: &M, stagnation_limit: usize) -> Self {
+        Self {
+            inner,
+            map_observer_name: map_observer.name().to_string(),
+            stagnation_limit,
+            reset_fn: None,
+            seen: Vec::new(),
+            runs_without_new: 0,
+            restarts: 0,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Sets the function restoring the checkpointed state of the target before each restart
+    #[must_use]
+    pub fn with_reset_fn(mut self, reset_fn: fn()) -> Self {
+        self.reset_fn = Some(reset_fn);
+        self
+    }
+
+    /// The number of times the child was restarted because of stagnating coverage
+    #[must_use]
+    pub fn restarts(&self) -> u64 {
+        self.restarts
+    }
+
+    /// The wrapped [`ChildPipeExecutor`]
+    #[must_use]
+    pub fn inner(&self) -> &ChildPipeExecutor<OT, S> {
+        &self.inner
+    }
+
+    /// Marks the entries hit in the last execution as seen, returns `true` if any of them was new
+    fn update_seen(&mut self) -> Result<bool, Error> {
+        let map = self
+            .inner
+            .observers()
+            .match_name::<M>(&self.map_observer_name)
+            .ok_or_else(|| Error::key_not_found("MapObserver not found".to_string()))?;
+        let initial = map.initial();
+        let len = map.usable_count();
+        if self.seen.len() < len {
+            self.seen.resize(len, false);
+        }
+
+        let mut new_coverage = false;
+        for (idx, seen) in self.seen.iter_mut().enumerate().take(len) {
+            if !*seen && *map.get(idx) != initial {
+                *seen = true;
+                new_coverage = true;
+            }
+        }
+        Ok(new_coverage)
+    }
+}
+
+impl<EM, M, OT, S, Z> Executor<EM, Z> for CoverageGuidedRestartExecutor<M, OT, S>
+where
+    EM: EventFirer<State = S>,
+    M: MapObserver,
+    S: State + HasExecutions,
+    S::Input: HasTargetBytes,
+    OT: Debug + ObserversTuple<S>,
+    Z: UsesState<State = S>,
+{
+    fn run_target(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut Self::State,
+        mgr: &mut EM,
+        input: &Self::Input,
+    ) -> Result<ExitKind, Error> {
+        let exit_kind = self.inner.run_target(fuzzer, state, mgr, input)?;
+
+        if self.update_seen()? {
+            self.runs_without_new = 0;
+        } else {
+            self.runs_without_new += 1;
+        }
+
+        if self.runs_without_new >= self.stagnation_limit {
+            log::info!(
+                "No new coverage for {} executions, restarting the child",
+                self.runs_without_new
+            );
+            self.inner.kill_child();
+            if let Some(reset_fn) = self.reset_fn {
+                reset_fn();
+            }
+            self.runs_without_new = 0;
+            self.restarts += 1;
+
+            mgr.fire(
+                state,
+                Event::UpdateUserStats {
+                    name: RESTARTS_STATS_NAME.to_string(),
+                    value: UserStats::new(
+                        UserStatsValue::Number(self.restarts),
+                        AggregatorOps::Sum,
+                    ),
+                    phantom: PhantomData,
+                },
+            )?;
+        }
+
+        Ok(exit_kind)
+    }
+}
+
+impl<M, OT, S> UsesState for CoverageGuidedRestartExecutor<M, OT, S>
+where
+    S: State,
+{
+    type State = S;
+}
+
+impl<M, OT, S> UsesObservers for CoverageGuidedRestartExecutor<M, OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: State,
+{
+    type Observers = OT;
+}
+
+impl<M, OT, S> HasObservers for CoverageGuidedRestartExecutor<M, OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: State,
+{
+    fn observers(&self) -> &OT {
+        self.inner.observers()
+    }
+
+    fn observers_mut(&mut self) -> &mut OT {
+        self.inner.observers_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+    use std::process::Command;
+
+    use libafl_bolts::tuples::tuple_list;
+
+    use super::CoverageGuidedRestartExecutor;
+    use crate::{
+        events::NopEventManager,
+        executors::{pipe::ChildPipeExecutor, Executor, ExitKind, HasObservers},
+        fuzzer::test::NopFuzzer,
+        inputs::BytesInput,
+        observers::{MapObserver, StdMapObserver},
+        state::NopState,
+    };
+
+    static RESETS: AtomicUsize = AtomicUsize::new(0);
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_restart_on_stagnation() {
+        let mut state = NopState::<BytesInput>::new();
+        let mut fuzzer = NopFuzzer::new();
+        let mut mgr = NopEventManager::new();
+
+        let observer = StdMapObserver::owned("map", vec![0_u8; 4]);
+        let inner = ChildPipeExecutor::new(
+            Command::new("cat"),
+            b"\n".to_vec(),
+            Duration::from_secs(5),
+            tuple_list!(observer.clone()),
+        );
+        let mut executor =
+            CoverageGuidedRestartExecutor::new(inner, &observer, 2).with_reset_fn(|| {
+                RESETS.fetch_add(1, Ordering::Relaxed);
+            });
+
+        // the entry hit in each run, if any, and the restarts after it
+        for (hit, restarts) in [
+            (Some(0), 0),
+            (Some(0), 0),
+            (Some(1), 0),
+            (None, 0),
+            (Some(1), 1),
+            (Some(2), 1),
+        ] {
+            let map = &mut executor.observers_mut().0;
+            map.reset_map().unwrap();
+            if let Some(idx) = hit {
+                *map.get_mut(idx) = 1;
+            }
+            let exit_kind = executor
+                .run_target(
+                    &mut fuzzer,
+                    &mut state,
+                    &mut mgr,
+                    &BytesInput::new(b"x\n".to_vec()),
+                )
+                .unwrap();
+            assert_eq!(exit_kind, ExitKind::Ok);
+            assert_eq!(executor.restarts(), restarts);
+        }
+        assert_eq!(RESETS.load(Ordering::Relaxed), 1);
+    }
+}
@@ -0,0 +1,293 @@
+//! A `RetryExecutor` runs each input multiple times in a flaky target, and decides on the most common [`ExitKind`].
+
+use alloc::vec::Vec;
+use core::fmt::Debug;
+
+use crate::{
+    executors::{Executor, ExitKind, HasObservers},
+    observers::{ObserversTuple, UsesObservers},
+    state::UsesState,
+    Error,
+};
+
+/// A [`RetryExecutor`] wraps an executor of a non-deterministic target, running each input up to `max_retries` times.
+///
+/// The [`ExitKind`] seen most often wins, the executions stop as soon as one of them has the majority.
+/// If all executions finished differently, the result is an [`ExitKind::Diff`] of the first two.
+/// The observers are reset between the executions, as the fuzzer does before and after each input,
+/// so they hold the values of the last execution.
+#[derive(Debug)]
+pub struct RetryExecutor<E> {
+    inner: E,
+    max_retries: usize,
+}
+
+impl<E> RetryExecutor<E> {
+    /// Create a new `RetryExecutor`, running the inputs up to `max_retries` times in `inner`.
+    ///
+    /// # Panics
+    /// If `max_retries` is `0`
+    pub fn new<EM, Z>(inner: E, max_retries: usize) -> Self
+    where
+        E: Executor<EM, Z>,
+        EM: UsesState<State = E::State>,
+        Z: UsesState<State = E::State>,
+    {
+        assert!(
+            max_retries > 0,
+            "A RetryExecutor needs to run at least once"
+        );
+        Self { inner, max_retries }
+    }
+
+    /// The maximum number of executions of an input
+    #[must_use]
+    pub fn max_retries(&self) -> usize {
+        self.max_retries
+    }
+
+    /// Retrieve the wrapped `Executor`
+    pub fn inner(&mut self) -> &mut E {
+        &mut self.inner
+    }
+}
+
+impl<E, EM, Z> Executor<EM, Z> for RetryExecutor<E>
+where
+    E: Executor<EM, Z> + HasObservers,
+    EM: UsesState<State = E::State>,
+    Z: UsesState<State = E::State>,
+{
+    fn run_target(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut Self::State,
+        mgr: &mut EM,
+        input: &Self::Input,
+    ) -> Result<ExitKind, Error> {
+        // the distinct exit kinds, in the order they were first seen, and how often they occurred
+        let mut votes: Vec<(ExitKind, usize)> = Vec::new();
+        let mut last_exit_kind = None;
+        for _ in 0..self.max_retries {
+            // the fuzzer runs the hooks of the observers around the first and the last execution
+            if let Some(last_exit_kind) = &last_exit_kind {
+                self.inner
+                    .observers_mut()
+                    .post_exec_all(state, input, last_exit_kind)?;
+                self.inner.observers_mut().pre_exec_all(state, input)?;
+            }
+            let exit_kind = self.inner.run_target(fuzzer, state, mgr, input)?;
+            last_exit_kind = Some(exit_kind);
+            let count = if let Some(vote) = votes.iter_mut().find(|(kind, _)| *kind == exit_kind) {
+                vote.1 += 1;
+                vote.1
+            } else {
+                votes.push((exit_kind, 1));
+                1
+            };
+            if count * 2 > self.max_retries {
+                return Ok(exit_kind);
+            }
+        }
+
+        if votes.len() > 1 && votes.iter().all(|(_, count)| *count == 1) {
+            return Ok(ExitKind::Diff {
+                primary: votes[0].0.into(),
+                secondary: votes[1].0.into(),
+            });
+        }
+        // on ties, the exit kind seen first wins
+        let mut winner = votes[0];
+        for vote in &votes[1..] {
+            if vote.1 > winner.1 {
+                winner = *vote;
+            }
+        }
+        Ok(winner.0)
+    }
+}
+
+impl<E> UsesState for RetryExecutor<E>
+where
+    E: UsesState,
+{
+    type State = E::State;
+}
+
+impl<E> UsesObservers for RetryExecutor<E>
+where
+    E: UsesObservers,
+{
+    type Observers = E::Observers;
+}
+
+impl<E> HasObservers for RetryExecutor<E>
+where
+    E: HasObservers,
+{
+    #[inline]
+    fn observers(&self) -> &Self::Observers {
+        self.inner.observers()
+    }
+
+    #[inline]
+    fn observers_mut(&mut self) -> &mut Self::Observers {
+        self.inner.observers_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::marker::PhantomData;
+
+    use libafl_bolts::{
+        tuples::{tuple_list, tuple_list_type},
+        Error, Named,
+    };
+
+    use super::RetryExecutor;
+    use crate::{
+        events::NopEventManager,
+        executors::{DiffExitKind, Executor, ExitKind, HasObservers},
+        fuzzer::test::NopFuzzer,
+        inputs::{BytesInput, UsesInput},
+        observers::{Observer, UsesObservers},
+        state::{NopState, State, UsesState},
+    };
+
+    /// Counts the resets between the executions
+    #[derive(Debug, Default)]
+    struct ResetObserver {
+        pre_execs: usize,
+        post_execs: usize,
+    }
+
+    impl Named for ResetObserver {
+        fn name(&self) -> &str {
+            "ResetObserver"
+        }
+    }
+
+    impl<S> Observer<S> for ResetObserver
+    where
+        S: UsesInput,
+    {
+        fn pre_exec(&mut self, _state: &mut S, _input: &S::Input) -> Result<(), Error> {
+            self.pre_execs += 1;
+            Ok(())
+        }
+
+        fn post_exec(
+            &mut self,
+            _state: &mut S,
+            _input: &S::Input,
+            _exit_kind: &ExitKind,
+        ) -> Result<(), Error> {
+            self.post_execs += 1;
+            Ok(())
+        }
+    }
+
+    /// Returns the given exit kinds, one per execution
+    struct FlakyExecutor<S> {
+        exit_kinds: Vec<ExitKind>,
+        runs: usize,
+        observers: tuple_list_type!(ResetObserver),
+        phantom: PhantomData<S>,
+    }
+
+    impl<S> UsesObservers for FlakyExecutor<S>
+    where
+        S: State,
+    {
+        type Observers = tuple_list_type!(ResetObserver);
+    }
+
+    impl<S> HasObservers for FlakyExecutor<S>
+    where
+        S: State,
+    {
+        fn observers(&self) -> &Self::Observers {
+            &self.observers
+        }
+
+        fn observers_mut(&mut self) -> &mut Self::Observers {
+            &mut self.observers
+        }
+    }
+
+    impl<S> UsesState for FlakyExecutor<S>
+    where
+        S: State,
+    {
+        type State = S;
+    }
+
+    impl<EM, S, Z> Executor<EM, Z> for FlakyExecutor<S>
+    where
+        EM: UsesState<State = S>,
+        S: State,
+        Z: UsesState<State = S>,
+    {
+        fn run_target(
+            &mut self,
+            _fuzzer: &mut Z,
+            _state: &mut Self::State,
+            _mgr: &mut EM,
+            _input: &Self::Input,
+        ) -> Result<ExitKind, Error> {
+            let exit_kind = self.exit_kinds[self.runs % self.exit_kinds.len()];
+            self.runs += 1;
+            Ok(exit_kind)
+        }
+    }
+
+    fn run(exit_kinds: Vec<ExitKind>, max_retries: usize) -> (ExitKind, usize) {
+        let flaky = FlakyExecutor::<NopState<BytesInput>> {
+            exit_kinds,
+            runs: 0,
+            observers: tuple_list!(ResetObserver::default()),
+            phantom: PhantomData,
+        };
+        let mut executor =
+            RetryExecutor::new::<NopEventManager<_>, NopFuzzer<_>>(flaky, max_retries);
+        let exit_kind = executor
+            .run_target(
+                &mut NopFuzzer::new(),
+                &mut NopState::new(),
+                &mut NopEventManager::new(),
+                &BytesInput::new(vec![1]),
+            )
+            .unwrap();
+        let runs = executor.inner().runs;
+        // the fuzzer resets the observers around the first and the last execution
+        let observer = &executor.observers().0;
+        assert_eq!(observer.pre_execs, runs - 1);
+        assert_eq!(observer.post_execs, runs - 1);
+        (exit_kind, runs)
+    }
+
+    #[test]
+    fn test_retry_executor() {
+        // the majority is known after two runs
+        assert_eq!(
+            run(vec![ExitKind::Crash, ExitKind::Crash, ExitKind::Ok], 3),
+            (ExitKind::Crash, 2)
+        );
+        assert_eq!(
+            run(vec![ExitKind::Ok, ExitKind::Crash, ExitKind::Crash], 3),
+            (ExitKind::Crash, 3)
+        );
+        assert_eq!(
+            run(vec![ExitKind::Ok, ExitKind::Crash, ExitKind::Timeout], 3),
+            (
+                ExitKind::Diff {
+                    primary: DiffExitKind::Ok,
+                    secondary: DiffExitKind::Crash
+                },
+                3
+            )
+        );
+    }
+}
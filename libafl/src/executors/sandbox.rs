@@ -0,0 +1,258 @@
+//! The [`SandboxedExecutor`] runs the commands of a [`CommandExecutor`] in fresh Linux namespaces,
+//! for targets that open network connections or write to arbitrary files.
+//!
+//! Before the target is executed, the spawned child unshares
+//! * the network namespace, leaving it without any network interface, so no connection can leave the sandbox,
+//! * the mount namespace, with the target binary bind-mounted read-only, so the target cannot modify itself,
+//! * the PID namespace, so all processes the target spawns are killed once its first child exits.
+//!
+//! `unshare` does not move the calling process into the new PID namespace, only its children:
+//! the target itself keeps its PID, so the [`CommandExecutor`] waits for, and kills on timeouts, the target directly.
+//! The target is also killed if the fuzzer dies.
+//!
+//! Creating the namespaces needs `CAP_SYS_ADMIN`, a failure is reported when the command is spawned.
+
+use core::fmt::Debug;
+use std::{
+    ffi::{CStr, CString},
+    fs, io,
+    os::unix::{ffi::OsStrExt, process::CommandExt},
+    ptr,
+};
+
+use libafl_bolts::tuples::MatchName;
+
+use crate::{
+    executors::{
+        command::{CommandExecutor, InputLocation, StdCommandConfigurator},
+        Executor, ExitKind, HasObservers,
+    },
+    inputs::HasTargetBytes,
+    observers::{ObserversTuple, UsesObservers},
+    state::{HasExecutions, State, UsesState},
+    Error,
+};
+
+/// Checks the return value of a libc call, returning the `errno` on failure
+fn check(ret: libc::c_int) -> io::Result<()> {
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Moves the spawned child into new namespaces, called right before the target is executed.
+/// Only async-signal-safe calls are allowed here, `program` is prepared by the parent.
+#[allow(clippy::cast_sign_loss)]
+fn enter_sandbox(program: Option<&CStr>) -> io::Result<()> {
+    unsafe {
+        // don't outlive the fuzzer
+        check(libc::prctl(
+            libc::PR_SET_PDEATHSIG,
+            libc::SIGKILL as libc::c_ulong,
+        ))?;
+        check(libc::unshare(
+            libc::CLONE_NEWPID | libc::CLONE_NEWNET | libc::CLONE_NEWNS,
+        ))?;
+        // keep the mounts below from propagating back to the namespace of the fuzzer
+        check(libc::mount(
+            ptr::null(),
+            b"/\0".as_ptr().cast(),
+            ptr::null(),
+            libc::MS_REC | libc::MS_PRIVATE,
+            ptr::null(),
+        ))?;
+        if let Some(program) = program {
+            check(libc::mount(
+                program.as_ptr(),
+                program.as_ptr(),
+                ptr::null(),
+                libc::MS_BIND,
+                ptr::null(),
+            ))?;
+            check(libc::mount(
+                ptr::null(),
+                program.as_ptr(),
+                ptr::null(),
+                libc::MS_BIND | libc::MS_REMOUNT | libc::MS_RDONLY,
+                ptr::null(),
+            ))?;
+        }
+        Ok(())
+    }
+}
+
+/// A [`CommandExecutor`] running each execution in new network, mount, and PID namespaces,
+/// see the [module documentation](self).
+///
+/// Unlike the [`CommandExecutor`], a non-zero exit code of the target is reported as [`ExitKind::Crash`].
+#[derive(Debug)]
+pub struct SandboxedExecutor<OT, S> {
+    inner: CommandExecutor<OT, S, StdCommandConfigurator>,
+}
+
+impl<OT, S> SandboxedExecutor<OT, S>
+where
+    OT: Debug,
+{
+    /// Sandboxes the executions of `inner`.
+    ///
+    /// The input must be delivered via stdin or a file: commands taking the input as argument
+    /// are rebuilt for each execution, outside of the sandbox.
+    /// The program is only mounted read-only if it is given as a path, not looked up in `PATH`.
+    pub fn new(mut inner: CommandExecutor<OT, S, StdCommandConfigurator>) -> Result<Self, Error> {
+        if matches!(inner.inner().input_location(), InputLocation::Arg { .. }) {
+            return Err(Error::illegal_argument(
+                "SandboxedExecutor cannot sandbox commands taking the input as argument",
+            ));
+        }
+
+        let command = inner.inner().command_mut();
+        let program = fs::canonicalize(command.get_program())
+            .ok()
+            .map(|path| CString::new(path.as_os_str().as_bytes()))
+            .transpose()
+            .map_err(|_| Error::illegal_argument("The program path contains a nul byte"))?;
+        unsafe {
+            command.pre_exec(move || enter_sandbox(program.as_deref()));
+        }
+        Ok(Self { inner })
+    }
+
+    /// The wrapped [`CommandExecutor`]
+    pub fn inner(&mut self) -> &mut CommandExecutor<OT, S, StdCommandConfigurator> {
+        &mut self.inner
+    }
+}
+
+impl<EM, OT, S, Z> Executor<EM, Z> for SandboxedExecutor<OT, S>
+where
+    EM: UsesState<State = S>,
+    S: State + HasExecutions,
+    S::Input: HasTargetBytes,
+    OT: Debug + MatchName + ObserversTuple<S>,
+    Z: UsesState<State = S>,
+{
+    fn run_target(
+        &mut self,
+        _fuzzer: &mut Z,
+        state: &mut Self::State,
+        _mgr: &mut EM,
+        input: &Self::Input,
+    ) -> Result<ExitKind, Error> {
+        use std::os::unix::prelude::ExitStatusExt;
+
+        *state.executions_mut() += 1;
+
+        Ok(match self.inner.run_child(input)? {
+            Some(status) => match status.signal() {
+                Some(9) => ExitKind::Oom,
+                Some(_) => ExitKind::Crash,
+                None if status.success() => ExitKind::Ok,
+                None => ExitKind::Crash,
+            },
+            None => ExitKind::Timeout,
+        })
+    }
+}
+
+impl<OT, S> UsesState for SandboxedExecutor<OT, S>
+where
+    S: State,
+{
+    type State = S;
+}
+
+impl<OT, S> UsesObservers for SandboxedExecutor<OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: State,
+{
+    type Observers = OT;
+}
+
+impl<OT, S> HasObservers for SandboxedExecutor<OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: State,
+{
+    #[inline]
+    fn observers(&self) -> &OT {
+        self.inner.observers()
+    }
+
+    #[inline]
+    fn observers_mut(&mut self) -> &mut OT {
+        self.inner.observers_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+    use std::{fs, io};
+
+    use crate::{
+        events::NopEventManager,
+        executors::{command::CommandExecutor, Executor, ExitKind, SandboxedExecutor},
+        fuzzer::test::NopFuzzer,
+        inputs::BytesInput,
+        state::NopState,
+        Error,
+    };
+
+    /// Runs `script` in the sandbox, or returns `None` if creating the namespaces is not permitted
+    fn run_sandboxed(script: &str, input: &[u8]) -> Option<ExitKind> {
+        let mut builder = CommandExecutor::builder();
+        builder
+            .program("sh")
+            .arg("-c")
+            .arg(script)
+            .timeout(Duration::from_secs(2));
+        let mut executor = SandboxedExecutor::new(builder.build(()).unwrap()).unwrap();
+
+        match executor.run_target(
+            &mut NopFuzzer::new(),
+            &mut NopState::new(),
+            &mut NopEventManager::new(),
+            &BytesInput::new(input.to_vec()),
+        ) {
+            Ok(exit_kind) => Some(exit_kind),
+            // creating namespaces needs `CAP_SYS_ADMIN`, missing in most test environments
+            Err(Error::File(err, _)) if err.kind() == io::ErrorKind::PermissionDenied => None,
+            Err(err) => panic!("Running the sandboxed command failed: {err:?}"),
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_sandbox() {
+        let net = fs::read_link("/proc/self/ns/net").unwrap();
+        let script = format!(
+            "test \"$(readlink /proc/self/ns/net)\" != '{}' && read line && test \"$line\" = input",
+            net.display()
+        );
+        let Some(exit_kind) = run_sandboxed(&script, b"input\n") else {
+            return;
+        };
+        // the target got its input and runs in a network namespace of its own
+        assert_eq!(exit_kind, ExitKind::Ok);
+
+        assert_eq!(run_sandboxed("exit 1", b""), Some(ExitKind::Crash));
+        // children of the target are in the new PID namespace, the first one is its PID 1
+        assert_eq!(
+            run_sandboxed("sh -c 'test $$ -eq 1'", b""),
+            Some(ExitKind::Ok)
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_sandbox_timeout() {
+        let Some(exit_kind) = run_sandboxed("sleep 10", b"") else {
+            return;
+        };
+        assert_eq!(exit_kind, ExitKind::Timeout);
+    }
+}
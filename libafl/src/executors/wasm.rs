@@ -0,0 +1,423 @@
+//! The [`WasmExecutor`] runs a WebAssembly harness in-process, using `wasmtime`.
+//!
+//! `wasmtime` has no hooks to instrument the module at load time, so coverage has to be compiled in:
+//! build the harness with `-fsanitize-coverage=trace-pc-guard` (e.g. `clang --target=wasm32-wasi`),
+//! and the executor provides the `__sanitizer_cov_trace_pc_guard` and `__sanitizer_cov_trace_pc_guard_init`
+//! imports, which write to the coverage map handed to [`WasmExecutor::new`].
+
+use alloc::string::{String, ToString};
+use core::{
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+};
+
+use libafl_bolts::AsSlice;
+use wasmtime::{Caller, Config, Engine, Instance, Linker, Memory, Module, Store, Trap, TypedFunc};
+
+use crate::{
+    executors::{Executor, ExitKind, HasObservers},
+    inputs::HasTargetBytes,
+    observers::{ObserversTuple, UsesObservers},
+    state::{HasExecutions, State, UsesState},
+    Error,
+};
+
+/// The size of a WebAssembly page
+const WASM_PAGE_SIZE: usize = 0x10000;
+
+/// The default amount of fuel (roughly, the number of executed WebAssembly instructions) per execution
+pub const DEFAULT_WASM_FUEL: u64 = 1 << 32;
+
+/// The state of the coverage callbacks, stored inside the `wasmtime` [`Store`]
+struct WasmCoverage {
+    map: *mut u8,
+    map_len: usize,
+    /// The next id to hand out in `__sanitizer_cov_trace_pc_guard_init`
+    next_guard: u32,
+}
+
+/// Converts a `wasmtime` error to an [`Error`]
+fn wasm_error(err: impl fmt::Display) -> Error {
+    Error::unknown(format!("wasmtime: {err}"))
+}
+
+/// Reads the little endian `u32` at `offset` of the linear memory
+fn read_u32(memory: &Memory, caller: &Caller<'_, WasmCoverage>, offset: usize) -> Option<u32> {
+    let bytes = memory.data(caller).get(offset..offset + 4)?;
+    Some(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+/// An [`Executor`] for harnesses compiled to WebAssembly.
+///
+/// The harness is called like `LLVMFuzzerTestOneInput`, with a pointer to the input in linear memory and its length.
+/// A trap results in [`ExitKind::Crash`], running out of fuel in [`ExitKind::Timeout`].
+/// After a crash or timeout, the module is instantiated again, to start from a clean state.
+/// Imports the executor does not provide trap when called.
+pub struct WasmExecutor<OT, S> {
+    module: Module,
+    linker: Linker<WasmCoverage>,
+    store: Store<WasmCoverage>,
+    harness_name: String,
+    harness: TypedFunc<(i32, i32), i32>,
+    memory: Memory,
+    /// Where the inputs are written in the linear memory
+    input_offset: usize,
+    max_input_size: usize,
+    fuel: u64,
+    observers: OT,
+    phantom: PhantomData<S>,
+}
+
+impl<OT, S> Debug for WasmExecutor<OT, S>
+where
+    OT: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WasmExecutor")
+            .field("harness_name", &self.harness_name)
+            .field("input_offset", &self.input_offset)
+            .field("max_input_size", &self.max_input_size)
+            .field("fuel", &self.fuel)
+            .field("observers", &self.observers)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<OT, S> WasmExecutor<OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: State,
+{
+    /// Compiles the module in `wasm` (binary or text format), and prepares the harness
+    /// exported as `harness_name`, taking inputs up to `max_input_size` bytes (longer ones are truncated).
+    ///
+    /// # Safety
+    /// The coverage map at `coverage_map` must stay valid for `coverage_map_len` bytes, for the lifetime
+    /// of the executor. Usually, it's the map of a `MapObserver` in `observers`.
+    #[allow(clippy::cast_sign_loss)]
+    pub unsafe fn new(
+        wasm: &[u8],
+        harness_name: &str,
+        max_input_size: usize,
+        coverage_map: *mut u8,
+        coverage_map_len: usize,
+        observers: OT,
+    ) -> Result<Self, Error> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config).map_err(wasm_error)?;
+        let module = Module::new(&engine, wasm).map_err(wasm_error)?;
+
+        let mut linker = Linker::new(&engine);
+        linker
+            .func_wrap(
+                "env",
+                "__sanitizer_cov_trace_pc_guard_init",
+                |mut caller: Caller<'_, WasmCoverage>, start: i32, stop: i32| {
+                    let Some(memory) = caller.get_export("memory").and_then(|e| e.into_memory())
+                    else {
+                        return;
+                    };
+                    let (start, stop) = (start as u32 as usize, stop as u32 as usize);
+                    if start == stop || read_u32(&memory, &caller, start) != Some(0) {
+                        // already initialized
+                        return;
+                    }
+                    for guard in (start..stop).step_by(4) {
+                        let coverage = caller.data_mut();
+                        coverage.next_guard = coverage.next_guard.wrapping_add(1);
+                        let id = coverage.next_guard;
+                        let Some(bytes) = memory.data_mut(&mut caller).get_mut(guard..guard + 4)
+                        else {
+                            return;
+                        };
+                        bytes.copy_from_slice(&id.to_le_bytes());
+                    }
+                },
+            )
+            .map_err(wasm_error)?;
+        linker
+            .func_wrap(
+                "env",
+                "__sanitizer_cov_trace_pc_guard",
+                |mut caller: Caller<'_, WasmCoverage>, guard: i32| {
+                    let Some(memory) = caller.get_export("memory").and_then(|e| e.into_memory())
+                    else {
+                        return;
+                    };
+                    let Some(id) = read_u32(&memory, &caller, guard as u32 as usize) else {
+                        return;
+                    };
+                    let coverage = caller.data();
+                    if coverage.map_len > 0 {
+                        unsafe {
+                            let entry = coverage.map.add(id as usize % coverage.map_len);
+                            *entry = (*entry).wrapping_add(1);
+                        }
+                    }
+                },
+            )
+            .map_err(wasm_error)?;
+        linker
+            .define_unknown_imports_as_traps(&module)
+            .map_err(wasm_error)?;
+
+        let store = Store::new(
+            &engine,
+            WasmCoverage {
+                map: coverage_map,
+                map_len: coverage_map_len,
+                next_guard: 0,
+            },
+        );
+        let (mut store, instance) = Self::instantiate(&linker, &module, store)?;
+        let (harness, memory, input_offset) =
+            Self::prepare(&mut store, &instance, harness_name, max_input_size)?;
+
+        Ok(Self {
+            module,
+            linker,
+            store,
+            harness_name: harness_name.to_string(),
+            harness,
+            memory,
+            input_offset,
+            max_input_size,
+            fuel: DEFAULT_WASM_FUEL,
+            observers,
+            phantom: PhantomData,
+        })
+    }
+
+    /// The fuel available to each execution, see [`Config::consume_fuel`]
+    #[must_use]
+    pub fn fuel(&self) -> u64 {
+        self.fuel
+    }
+
+    /// Sets the fuel available to each execution, which bounds its runtime
+    pub fn set_fuel(&mut self, fuel: u64) {
+        self.fuel = fuel;
+    }
+
+    /// The maximum size of an input, longer ones are truncated
+    #[must_use]
+    pub fn max_input_size(&self) -> usize {
+        self.max_input_size
+    }
+
+    fn instantiate(
+        linker: &Linker<WasmCoverage>,
+        module: &Module,
+        mut store: Store<WasmCoverage>,
+    ) -> Result<(Store<WasmCoverage>, Instance), Error> {
+        let instance = linker.instantiate(&mut store, module).map_err(wasm_error)?;
+        Ok((store, instance))
+    }
+
+    /// Looks up the harness and memory, and reserves the pages for the inputs at the end of the linear memory
+    fn prepare(
+        store: &mut Store<WasmCoverage>,
+        instance: &Instance,
+        harness_name: &str,
+        max_input_size: usize,
+    ) -> Result<(TypedFunc<(i32, i32), i32>, Memory, usize), Error> {
+        let harness = instance
+            .get_typed_func::<(i32, i32), i32>(&mut *store, harness_name)
+            .map_err(wasm_error)?;
+        let memory = instance
+            .get_memory(&mut *store, "memory")
+            .ok_or_else(|| Error::key_not_found("The module does not export its memory"))?;
+        let pages = max_input_size.div_ceil(WASM_PAGE_SIZE).max(1);
+        let old_pages = memory.grow(&mut *store, pages as u64).map_err(wasm_error)?;
+        Ok((harness, memory, old_pages as usize * WASM_PAGE_SIZE))
+    }
+
+    /// Instantiates the module again, dropping all state of the previous instance
+    pub fn reset_instance(&mut self) -> Result<(), Error> {
+        let WasmCoverage { map, map_len, .. } = *self.store.data();
+        let coverage = WasmCoverage {
+            map,
+            map_len,
+            next_guard: 0,
+        };
+        let store = Store::new(self.store.engine(), coverage);
+        let (mut store, instance) = Self::instantiate(&self.linker, &self.module, store)?;
+        let (harness, memory, input_offset) = Self::prepare(
+            &mut store,
+            &instance,
+            &self.harness_name,
+            self.max_input_size,
+        )?;
+        self.store = store;
+        self.harness = harness;
+        self.memory = memory;
+        self.input_offset = input_offset;
+        Ok(())
+    }
+}
+
+impl<EM, OT, S, Z> Executor<EM, Z> for WasmExecutor<OT, S>
+where
+    EM: UsesState<State = S>,
+    S: State + HasExecutions,
+    S::Input: HasTargetBytes,
+    OT: Debug + ObserversTuple<S>,
+    Z: UsesState<State = S>,
+{
+    fn run_target(
+        &mut self,
+        _fuzzer: &mut Z,
+        state: &mut Self::State,
+        _mgr: &mut EM,
+        input: &Self::Input,
+    ) -> Result<ExitKind, Error> {
+        *state.executions_mut() += 1;
+
+        let target_bytes = input.target_bytes();
+        let mut bytes = target_bytes.as_slice();
+        if bytes.len() > self.max_input_size {
+            bytes = &bytes[..self.max_input_size];
+        }
+        self.memory
+            .write(&mut self.store, self.input_offset, bytes)
+            .map_err(wasm_error)?;
+        self.store.set_fuel(self.fuel).map_err(wasm_error)?;
+
+        #[allow(clippy::cast_possible_wrap, clippy::cast_possible_truncation)]
+        let exit_kind = match self.harness.call(
+            &mut self.store,
+            (self.input_offset as i32, bytes.len() as i32),
+        ) {
+            Ok(_) => ExitKind::Ok,
+            Err(err) => match err.downcast_ref::<Trap>() {
+                Some(Trap::OutOfFuel) => ExitKind::Timeout,
+                Some(trap) => {
+                    log::info!("WebAssembly trap: {trap}");
+                    ExitKind::Crash
+                }
+                None => {
+                    log::info!("WebAssembly harness failed: {err}");
+                    ExitKind::Crash
+                }
+            },
+        };
+
+        if exit_kind != ExitKind::Ok {
+            self.reset_instance()?;
+        }
+        Ok(exit_kind)
+    }
+}
+
+impl<OT, S> UsesState for WasmExecutor<OT, S>
+where
+    S: State,
+{
+    type State = S;
+}
+
+impl<OT, S> UsesObservers for WasmExecutor<OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: State,
+{
+    type Observers = OT;
+}
+
+impl<OT, S> HasObservers for WasmExecutor<OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: State,
+{
+    fn observers(&self) -> &OT {
+        &self.observers
+    }
+
+    fn observers_mut(&mut self) -> &mut OT {
+        &mut self.observers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::WasmExecutor;
+    use crate::{
+        events::NopEventManager,
+        executors::{Executor, ExitKind},
+        fuzzer::test::NopFuzzer,
+        inputs::BytesInput,
+        state::NopState,
+    };
+
+    /// Hits guard 1 for each input, and guard 2 for non-empty inputs not starting with `c` (trapping) or `l` (looping)
+    const HARNESS: &str = r#"
+        (module
+            (import "env" "__sanitizer_cov_trace_pc_guard_init" (func $init (param i32 i32)))
+            (import "env" "__sanitizer_cov_trace_pc_guard" (func $guard (param i32)))
+            (memory (export "memory") 1)
+            (func (export "LLVMFuzzerTestOneInput") (param $ptr i32) (param $len i32) (result i32)
+                (call $init (i32.const 16) (i32.const 24))
+                (call $guard (i32.const 16))
+                (if (i32.eqz (local.get $len)) (then (return (i32.const 0))))
+                (if (i32.eq (i32.load8_u (local.get $ptr)) (i32.const 99)) (then unreachable))
+                (if (i32.eq (i32.load8_u (local.get $ptr)) (i32.const 108)) (then (loop $l (br $l))))
+                (call $guard (i32.const 20))
+                (i32.const 0)))
+    "#;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_wasm_executor() {
+        let mut state = NopState::<BytesInput>::new();
+        let mut fuzzer = NopFuzzer::new();
+        let mut mgr = NopEventManager::new();
+
+        let mut map = vec![0_u8; 4];
+        let mut executor = unsafe {
+            WasmExecutor::new(
+                HARNESS.as_bytes(),
+                "LLVMFuzzerTestOneInput",
+                64,
+                map.as_mut_ptr(),
+                map.len(),
+                (),
+            )
+        }
+        .unwrap();
+        executor.set_fuel(100_000);
+
+        let mut run = |executor: &mut WasmExecutor<(), NopState<BytesInput>>, bytes: &[u8]| {
+            executor
+                .run_target(
+                    &mut fuzzer,
+                    &mut state,
+                    &mut mgr,
+                    &BytesInput::new(bytes.to_vec()),
+                )
+                .unwrap()
+        };
+        let maps: Vec<(ExitKind, [u8; 4])> = [&b""[..], b"a", b"c", b"l", b"a"]
+            .into_iter()
+            .map(|bytes| {
+                let exit_kind = run(&mut executor, bytes);
+                let hits = [map[0], map[1], map[2], map[3]];
+                map.fill(0);
+                (exit_kind, hits)
+            })
+            .collect();
+        assert_eq!(
+            maps,
+            [
+                (ExitKind::Ok, [0, 1, 0, 0]),
+                (ExitKind::Ok, [0, 1, 1, 0]),
+                (ExitKind::Crash, [0, 1, 0, 0]),
+                (ExitKind::Timeout, [0, 1, 0, 0]),
+                // the module was instantiated again, with the same guards
+                (ExitKind::Ok, [0, 1, 1, 0]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,327 @@
+//! The [`WindowsProcessExecutor`] runs a target program as a new Windows process for each execution.
+//!
+//! Windows has no `fork`, and a timed out process may leave children behind that keep files locked.
+//! Each child is therefore started suspended, and assigned to a job object before it runs:
+//! the job allows a single active process, so the target cannot spawn others, and on a timeout
+//! the whole job is terminated at once.
+
+use alloc::{string::String, vec::Vec};
+use core::{
+    ffi::c_void,
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+    mem::size_of,
+    time::Duration,
+};
+use std::{
+    fs,
+    os::windows::ffi::OsStrExt,
+    path::{Path, PathBuf},
+};
+
+use libafl_bolts::AsSlice;
+use windows::{
+    core::{PCWSTR, PWSTR},
+    Win32::{
+        Foundation::{CloseHandle, HANDLE, WAIT_OBJECT_0, WAIT_TIMEOUT},
+        System::{
+            JobObjects::{
+                AssignProcessToJobObject, CreateJobObjectW, JobObjectBasicLimitInformation,
+                SetInformationJobObject, TerminateJobObject, JOBOBJECT_BASIC_LIMIT_INFORMATION,
+                JOB_OBJECT_LIMIT_ACTIVE_PROCESS, JOB_OBJECT_LIMIT_DIE_ON_UNHANDLED_EXCEPTION,
+            },
+            Threading::{
+                CreateProcessW, GetExitCodeProcess, ResumeThread, WaitForSingleObject,
+                CREATE_SUSPENDED, PROCESS_INFORMATION, STARTUPINFOW,
+            },
+        },
+    },
+};
+
+use crate::{
+    executors::{Executor, ExitKind, HasObservers},
+    inputs::HasTargetBytes,
+    observers::{ObserversTuple, UsesObservers},
+    state::{HasExecutions, State, UsesState},
+    Error,
+};
+
+/// The argument replaced by the path of the input file
+pub const INPUT_FILE_PLACEHOLDER: &str = "@@";
+
+/// The exit code [`TerminateJobObject`] gives the processes of a timed out job
+const TIMEOUT_EXIT_CODE: u32 = 0xdead_beef;
+
+/// The exit code of a process terminated by a `__debugbreak`
+const STATUS_BREAKPOINT: u32 = 0x8000_0003;
+
+/// Returns `true` if the exit code is an `NTSTATUS` of error severity, such as `STATUS_ACCESS_VIOLATION` (`0xc0000005`),
+/// the exit code of a process terminated by an unhandled exception.
+fn is_crash_exit_code(code: u32) -> bool {
+    code & 0xc000_0000 == 0xc000_0000 || code == STATUS_BREAKPOINT
+}
+
+/// Appends `arg` to a command line, quoted as expected by `CommandLineToArgvW`
+fn append_quoted(cmdline: &mut String, arg: &str) {
+    if !cmdline.is_empty() {
+        cmdline.push(' ');
+    }
+    if !arg.is_empty() && !arg.contains([' ', '\t', '"']) {
+        cmdline.push_str(arg);
+        return;
+    }
+    cmdline.push('"');
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                // backslashes before a quote, and the quote itself, are escaped
+                cmdline.extend(core::iter::repeat('\\').take(2 * backslashes + 1));
+                backslashes = 0;
+            }
+            _ => backslashes = 0,
+        }
+        cmdline.push(c);
+    }
+    // backslashes before the closing quote are escaped
+    cmdline.extend(core::iter::repeat('\\').take(backslashes));
+    cmdline.push('"');
+}
+
+/// Encodes `s` as nul-terminated UTF-16
+fn to_wide<S: AsRef<std::ffi::OsStr>>(s: S) -> Vec<u16> {
+    s.as_ref().encode_wide().chain(Some(0)).collect()
+}
+
+/// Runs a program for each input, confined to a job object, see the [module documentation](self).
+///
+/// The input is written to a file, and passed to the program in place of the [`INPUT_FILE_PLACEHOLDER`] argument.
+/// A process terminated by an unhandled exception, such as an access violation, is a [`ExitKind::Crash`],
+/// any other exit is [`ExitKind::Ok`].
+pub struct WindowsProcessExecutor<OT, S> {
+    program: PathBuf,
+    args: Vec<String>,
+    input_file: PathBuf,
+    timeout: Duration,
+    job: HANDLE,
+    observers: OT,
+    phantom: PhantomData<S>,
+}
+
+impl<OT, S> Debug for WindowsProcessExecutor<OT, S>
+where
+    OT: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WindowsProcessExecutor")
+            .field("program", &self.program)
+            .field("args", &self.args)
+            .field("input_file", &self.input_file)
+            .field("timeout", &self.timeout)
+            .field("observers", &self.observers)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<OT, S> WindowsProcessExecutor<OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: State,
+{
+    /// Creates a new [`WindowsProcessExecutor`], running `program` with `args` for each input.
+    /// The input is written to `input_file`, which replaces each [`INPUT_FILE_PLACEHOLDER`] in `args`.
+    pub fn new<P, Q>(
+        program: P,
+        args: Vec<String>,
+        input_file: Q,
+        timeout: Duration,
+        observers: OT,
+    ) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let job = unsafe { CreateJobObjectW(None, PCWSTR::null())? };
+        let mut limits = JOBOBJECT_BASIC_LIMIT_INFORMATION {
+            LimitFlags: JOB_OBJECT_LIMIT_ACTIVE_PROCESS
+                | JOB_OBJECT_LIMIT_DIE_ON_UNHANDLED_EXCEPTION,
+            ActiveProcessLimit: 1,
+            ..Default::default()
+        };
+        let limited = unsafe {
+            SetInformationJobObject(
+                job,
+                JobObjectBasicLimitInformation,
+                (&mut limits as *mut JOBOBJECT_BASIC_LIMIT_INFORMATION).cast::<c_void>(),
+                size_of::<JOBOBJECT_BASIC_LIMIT_INFORMATION>() as u32,
+            )
+        };
+        if let Err(err) = limited {
+            unsafe {
+                CloseHandle(job);
+            }
+            return Err(err.into());
+        }
+
+        Ok(Self {
+            program: program.as_ref().to_path_buf(),
+            args,
+            input_file: input_file.as_ref().to_path_buf(),
+            timeout,
+            job,
+            observers,
+            phantom: PhantomData,
+        })
+    }
+
+    /// The timeout of an execution
+    #[must_use]
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// The command line of an execution
+    fn command_line(&self) -> String {
+        let input_file = self.input_file.to_string_lossy();
+        let mut cmdline = String::new();
+        append_quoted(&mut cmdline, &self.program.to_string_lossy());
+        for arg in &self.args {
+            if arg == INPUT_FILE_PLACEHOLDER {
+                append_quoted(&mut cmdline, &input_file);
+            } else {
+                append_quoted(&mut cmdline, arg);
+            }
+        }
+        cmdline
+    }
+
+    /// Starts the program suspended, assigns it to the job, and lets it run.
+    /// Returns the exit code, or `None` on a timeout.
+    #[allow(clippy::cast_possible_truncation)]
+    fn run_child(&mut self) -> Result<Option<u32>, Error> {
+        let program = to_wide(&self.program);
+        let mut cmdline = to_wide(self.command_line());
+        let startup_info = STARTUPINFOW {
+            cb: size_of::<STARTUPINFOW>() as u32,
+            ..Default::default()
+        };
+        let mut process_info = PROCESS_INFORMATION::default();
+        unsafe {
+            CreateProcessW(
+                PCWSTR(program.as_ptr()),
+                PWSTR(cmdline.as_mut_ptr()),
+                None,
+                None,
+                false,
+                CREATE_SUSPENDED,
+                None,
+                PCWSTR::null(),
+                &startup_info,
+                &mut process_info,
+            )?;
+        }
+
+        let result = self.wait_in_job(&process_info);
+        unsafe {
+            CloseHandle(process_info.hThread);
+            CloseHandle(process_info.hProcess);
+        }
+        result
+    }
+
+    /// Runs the suspended process in the job until it exits, or the timeout expires
+    fn wait_in_job(&mut self, process_info: &PROCESS_INFORMATION) -> Result<Option<u32>, Error> {
+        unsafe {
+            if let Err(err) = AssignProcessToJobObject(self.job, process_info.hProcess) {
+                TerminateJobObject(self.job, TIMEOUT_EXIT_CODE)?;
+                return Err(err.into());
+            }
+            ResumeThread(process_info.hThread);
+
+            let millis = u32::try_from(self.timeout.as_millis()).unwrap_or(u32::MAX);
+            let waited = WaitForSingleObject(process_info.hProcess, millis);
+            if waited == WAIT_TIMEOUT {
+                TerminateJobObject(self.job, TIMEOUT_EXIT_CODE)?;
+                // the handles can only be closed once the process is gone
+                WaitForSingleObject(process_info.hProcess, u32::MAX);
+                return Ok(None);
+            }
+            if waited != WAIT_OBJECT_0 {
+                TerminateJobObject(self.job, TIMEOUT_EXIT_CODE)?;
+                return Err(Error::unknown(format!(
+                    "Waiting for the child failed: {waited:?}"
+                )));
+            }
+
+            let mut exit_code = 0;
+            GetExitCodeProcess(process_info.hProcess, &mut exit_code)?;
+            Ok(Some(exit_code))
+        }
+    }
+}
+
+impl<OT, S> Drop for WindowsProcessExecutor<OT, S> {
+    fn drop(&mut self) {
+        unsafe {
+            CloseHandle(self.job);
+        }
+    }
+}
+
+impl<EM, OT, S, Z> Executor<EM, Z> for WindowsProcessExecutor<OT, S>
+where
+    EM: UsesState<State = S>,
+    S: State + HasExecutions,
+    S::Input: HasTargetBytes,
+    OT: ObserversTuple<S>,
+    Z: UsesState<State = S>,
+{
+    fn run_target(
+        &mut self,
+        _fuzzer: &mut Z,
+        state: &mut Self::State,
+        _mgr: &mut EM,
+        input: &Self::Input,
+    ) -> Result<ExitKind, Error> {
+        *state.executions_mut() += 1;
+
+        fs::write(&self.input_file, input.target_bytes().as_slice())?;
+        Ok(match self.run_child()? {
+            Some(code) if is_crash_exit_code(code) => ExitKind::Crash,
+            Some(_) => ExitKind::Ok,
+            None => ExitKind::Timeout,
+        })
+    }
+}
+
+impl<OT, S> UsesState for WindowsProcessExecutor<OT, S>
+where
+    S: State,
+{
+    type State = S;
+}
+
+impl<OT, S> UsesObservers for WindowsProcessExecutor<OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: State,
+{
+    type Observers = OT;
+}
+
+impl<OT, S> HasObservers for WindowsProcessExecutor<OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: State,
+{
+    #[inline]
+    fn observers(&self) -> &OT {
+        &self.observers
+    }
+
+    #[inline]
+    fn observers_mut(&mut self) -> &mut OT {
+        &mut self.observers
+    }
+}
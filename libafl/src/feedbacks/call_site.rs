@@ -0,0 +1,192 @@
+//! The [`NewCallSiteFeedback`] keeps inputs that reach an instrumented point in a new calling context,
+//! as reported by a [`CallSiteObserver`]
+
+use alloc::string::{String, ToString};
+
+use hashbrown::HashSet;
+use libafl_bolts::Named;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    events::EventFirer,
+    executors::ExitKind,
+    feedbacks::{Feedback, HasObserverName},
+    inputs::UsesInput,
+    observers::{CallSiteObserver, ObserversTuple},
+    state::{HasNamedMetadata, State},
+    Error,
+};
+
+/// The prefix of the metadata names
+pub const NEWCALLSITEFEEDBACK_PREFIX: &str = "newcallsitefeedback_metadata_";
+
+/// The state of [`NewCallSiteFeedback`]
+#[derive(Default, Serialize, Deserialize, Clone, Debug)]
+#[allow(clippy::unsafe_derive_deserialize)]
+pub struct NewCallSiteFeedbackMetadata {
+    /// All pairs of instrumented address and call stack hash seen so far
+    pub sites: HashSet<u128>,
+}
+
+libafl_bolts::impl_serdeany!(NewCallSiteFeedbackMetadata);
+
+impl NewCallSiteFeedbackMetadata {
+    /// Create a new [`NewCallSiteFeedbackMetadata`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// A [`NewCallSiteFeedback`] considers an input interesting if its execution reached an instrumented point
+/// in a calling context that no previous execution reached it in.
+///
+/// This gives context-sensitive coverage, without enumerating whole paths.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NewCallSiteFeedback {
+    name: String,
+    observer_name: String,
+}
+
+impl<S> Feedback<S> for NewCallSiteFeedback
+where
+    S: State + HasNamedMetadata,
+{
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error> {
+        state.add_named_metadata(NewCallSiteFeedbackMetadata::new(), &self.name);
+        Ok(())
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    fn is_interesting<EM, OT>(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _input: &<S as UsesInput>::Input,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .match_name::<CallSiteObserver>(&self.observer_name)
+            .ok_or_else(|| {
+                Error::illegal_state(format!(
+                    "A NewCallSiteFeedback needs a CallSiteObserver named {}",
+                    self.observer_name
+                ))
+            })?;
+
+        let meta = state.named_metadata_mut::<NewCallSiteFeedbackMetadata>(&self.name)?;
+
+        let mut interesting = false;
+        for site in observer.sites() {
+            interesting |= meta.sites.insert(*site);
+        }
+        Ok(interesting)
+    }
+}
+
+impl Named for NewCallSiteFeedback {
+    #[inline]
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl HasObserverName for NewCallSiteFeedback {
+    #[inline]
+    fn observer_name(&self) -> &str {
+        &self.observer_name
+    }
+}
+
+impl NewCallSiteFeedback {
+    /// Returns a new [`NewCallSiteFeedback`] for the given [`CallSiteObserver`].
+    #[must_use]
+    pub fn new(observer: &CallSiteObserver) -> Self {
+        Self {
+            name: NEWCALLSITEFEEDBACK_PREFIX.to_string() + observer.name(),
+            observer_name: observer.name().to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::tuples::tuple_list;
+
+    use super::NewCallSiteFeedback;
+    use crate::{
+        events::NopEventManager,
+        executors::ExitKind,
+        feedbacks::Feedback,
+        inputs::BytesInput,
+        observers::{record_call_site, CallSiteObserver, Observer},
+        state::{test::test_std_state, StdState},
+    };
+
+    /// Runs an execution reaching the instrumented points `addrs`, all from the same calling context
+    fn run(observer: &mut CallSiteObserver, addrs: &[usize]) {
+        let mut state = test_std_state::<BytesInput>();
+        let input = BytesInput::new(vec![]);
+        observer.pre_exec(&mut state, &input).unwrap();
+        for addr in addrs {
+            record_call_site(*addr);
+        }
+        observer
+            .post_exec(&mut state, &input, &ExitKind::Ok)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_new_call_site_feedback() {
+        let mut observer = CallSiteObserver::new("call_sites");
+        let mut feedback = NewCallSiteFeedback::new(&observer);
+        let mut state = test_std_state::<BytesInput>();
+        feedback.init_state(&mut state).unwrap();
+        let mut mgr = NopEventManager::new();
+        let input = BytesInput::new(vec![]);
+
+        let mut is_interesting = |observer: &CallSiteObserver, state: &mut StdState<_, _, _, _>| {
+            feedback
+                .is_interesting(
+                    state,
+                    &mut mgr,
+                    &input,
+                    &tuple_list!(observer.clone()),
+                    &ExitKind::Ok,
+                )
+                .unwrap()
+        };
+
+        // the same call site again is not interesting, a new one next to the known one is.
+        // `run` is called from a single site, so that the calling contexts are the same.
+        let executions: [(&[usize], bool); 3] =
+            [(&[0x10], true), (&[0x10], false), (&[0x10, 0x20], true)];
+        for (addrs, interesting) in executions {
+            run(&mut observer, addrs);
+            assert_eq!(is_interesting(&observer, &mut state), interesting);
+        }
+    }
+
+    #[test]
+    fn test_new_call_site_feedback_without_observer() {
+        let observer = CallSiteObserver::new("call_sites");
+        let mut feedback = NewCallSiteFeedback::new(&observer);
+        let mut state = test_std_state::<BytesInput>();
+        feedback.init_state(&mut state).unwrap();
+
+        assert!(feedback
+            .is_interesting(
+                &mut state,
+                &mut NopEventManager::new(),
+                &BytesInput::new(vec![]),
+                &(),
+                &ExitKind::Ok,
+            )
+            .is_err());
+    }
+}
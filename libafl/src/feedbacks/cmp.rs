@@ -0,0 +1,199 @@
+//! The [`CmpLogFeedback`] considers an input interesting if it produced comparison operands that were never seen before.
+//! Paired with a [`CmpObserver`] that stores [`CmpValuesMetadata`], such as the [`crate::observers::CmpOperandsObserver`]
+//! over a [`crate::observers::CmpOperandsMap`], and a redqueen-style mutator such as the
+//! [`crate::mutators::CmpLogMutator`], it keeps the inputs that reach new comparisons in the corpus.
+
+use alloc::string::{String, ToString};
+use core::{
+    hash::{BuildHasher, Hasher},
+    marker::PhantomData,
+};
+
+use ahash::RandomState;
+use hashbrown::HashSet;
+use libafl_bolts::Named;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    events::EventFirer,
+    executors::ExitKind,
+    feedbacks::{Feedback, HasObserverName},
+    inputs::UsesInput,
+    observers::{cmp::CmpValuesMetadata, CmpMap, CmpObserver, CmpValues, ObserversTuple},
+    state::{HasNamedMetadata, State},
+    Error,
+};
+
+/// The prefix of the metadata names
+pub const CMPLOGFEEDBACK_PREFIX: &str = "cmplogfeedback_metadata_";
+
+/// The state of [`CmpLogFeedback`], holding the hashes of all comparison operands seen so far
+#[derive(Default, Serialize, Deserialize, Clone, Debug)]
+#[allow(clippy::unsafe_derive_deserialize)]
+pub struct CmpLogFeedbackMetadata {
+    /// The hashes of the `(cmp index, operands)` pairs seen so far
+    pub seen: HashSet<u64>,
+}
+
+libafl_bolts::impl_serdeany!(CmpLogFeedbackMetadata);
+
+impl CmpLogFeedbackMetadata {
+    /// Create a new [`CmpLogFeedbackMetadata`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reset the internal state
+    pub fn reset(&mut self) -> Result<(), Error> {
+        self.seen.clear();
+        Ok(())
+    }
+}
+
+/// Hashes the operands logged for the cmp at `idx`
+fn hash_cmp_values(idx: usize, values: &CmpValues) -> u64 {
+    let mut hasher = RandomState::with_seeds(0, 0, 0, 0).build_hasher();
+    hasher.write_usize(idx);
+    match values {
+        CmpValues::Bytes((v0, v1)) => {
+            hasher.write(v0);
+            hasher.write(v1);
+        }
+        _ => {
+            let (v0, v1) = values.to_u64_tuple().unwrap();
+            hasher.write_u64(v0);
+            hasher.write_u64(v1);
+        }
+    }
+    hasher.finish()
+}
+
+/// A [`CmpLogFeedback`] reports an input as interesting if its execution logged a pair of
+/// comparison operands that has not been observed before, at any of the cmp sites of the [`CmpMap`].
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CmpLogFeedback<CM, O> {
+    name: String,
+    observer_name: String,
+    phantom: PhantomData<(CM, O)>,
+}
+
+impl<'a, CM, O, S> Feedback<S> for CmpLogFeedback<CM, O>
+where
+    CM: CmpMap,
+    O: CmpObserver<'a, CM, S, CmpValuesMetadata>,
+    S: State + HasNamedMetadata,
+{
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error> {
+        state.add_named_metadata(CmpLogFeedbackMetadata::new(), &self.name);
+        Ok(())
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    fn is_interesting<EM, OT>(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _input: &<S as UsesInput>::Input,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .match_name::<O>(&self.observer_name)
+            .ok_or_else(|| Error::key_not_found("CmpObserver not found"))?;
+        let cmp_map = observer.cmp_map();
+
+        let meta = state
+            .named_metadata_map_mut()
+            .get_mut::<CmpLogFeedbackMetadata>(&self.name)
+            .unwrap();
+
+        let mut interesting = false;
+        for idx in 0..observer.usable_count() {
+            for execution in 0..cmp_map.usable_executions_for(idx) {
+                if let Some(values) = cmp_map.values_of(idx, execution) {
+                    interesting |= meta.seen.insert(hash_cmp_values(idx, &values));
+                }
+            }
+        }
+
+        Ok(interesting)
+    }
+}
+
+impl<CM, O> Named for CmpLogFeedback<CM, O> {
+    #[inline]
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<CM, O> HasObserverName for CmpLogFeedback<CM, O> {
+    #[inline]
+    fn observer_name(&self) -> &str {
+        &self.observer_name
+    }
+}
+
+impl<CM, O> CmpLogFeedback<CM, O>
+where
+    O: Named,
+{
+    /// Creates a new [`CmpLogFeedback`] for the given [`CmpObserver`]
+    #[must_use]
+    pub fn new(observer: &O) -> Self {
+        Self {
+            name: CMPLOGFEEDBACK_PREFIX.to_string() + observer.name(),
+            observer_name: observer.name().to_string(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+
+    use libafl_bolts::{ownedref::OwnedRefMut, tuples::tuple_list};
+
+    use super::CmpLogFeedback;
+    use crate::{
+        events::NopEventManager,
+        executors::ExitKind,
+        feedbacks::Feedback,
+        inputs::BytesInput,
+        observers::{CmpMap, CmpObserver, CmpOperandsMap, CmpOperandsObserver},
+        state::test::test_std_state,
+    };
+
+    #[test]
+    fn test_cmplog_feedback() {
+        let mut state = test_std_state::<BytesInput>();
+        let mut mgr = NopEventManager::new();
+        let input = BytesInput::new(vec![0]);
+
+        let mut observers = tuple_list!(CmpOperandsObserver::new(
+            "cmplog",
+            OwnedRefMut::Owned(Box::new(CmpOperandsMap::new(16))),
+            false
+        ));
+        let mut feedback = CmpLogFeedback::<CmpOperandsMap, _>::new(&observers.0);
+        feedback.init_state(&mut state).unwrap();
+
+        for (v1, interesting) in [(2, true), (2, false), (3, true)] {
+            let map = observers.0.cmp_map_mut();
+            map.reset().unwrap();
+            map.log(5, 1, v1, 4);
+            assert_eq!(
+                feedback
+                    .is_interesting(&mut state, &mut mgr, &input, &observers, &ExitKind::Ok)
+                    .unwrap(),
+                interesting
+            );
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! The [`ComparatorFeedback`] looks for behavior rather than coverage: outputs matching a user-supplied predicate.
+
+use alloc::string::{String, ToString};
+use core::fmt::{self, Debug, Formatter};
+
+use libafl_bolts::Named;
+
+use crate::{
+    events::EventFirer,
+    executors::ExitKind,
+    feedbacks::{Feedback, HasObserverName},
+    observers::{ObserversTuple, StdOutObserver},
+    state::State,
+    Error,
+};
+
+/// Considers an input interesting if the predicate holds for the [`ExitKind`] and the stdout of its execution,
+/// as captured by a [`StdOutObserver`] (so the executor has to support it, like the [`crate::executors::CommandExecutor`]).
+/// If no stdout was captured, the predicate sees an empty output.
+///
+/// Inputs with a certain behavior are usually the goal of the campaign, not a step towards it,
+/// so use this feedback as objective, to put the matching inputs into the solutions:
+///
+/// ```rust,ignore
+/// let stdout_observer = StdOutObserver::new("stdout".to_string());
+/// let mut objective = ComparatorFeedback::new(&stdout_observer, |_exit_kind, stdout| {
+///     stdout.windows(5).any(|w| w == b"PANIC")
+/// });
+/// ```
+pub struct ComparatorFeedback<F> {
+    name: String,
+    observer_name: String,
+    predicate: F,
+}
+
+impl<F> ComparatorFeedback<F>
+where
+    F: FnMut(&ExitKind, &[u8]) -> bool,
+{
+    /// Creates a new [`ComparatorFeedback`], evaluating `predicate` on the output captured by `observer`
+    #[must_use]
+    pub fn new(observer: &StdOutObserver, predicate: F) -> Self {
+        Self {
+            name: format!("ComparatorFeedback({})", observer.name()),
+            observer_name: observer.name().to_string(),
+            predicate,
+        }
+    }
+}
+
+impl<F> Debug for ComparatorFeedback<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ComparatorFeedback")
+            .field("name", &self.name)
+            .field("observer_name", &self.observer_name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F, S> Feedback<S> for ComparatorFeedback<F>
+where
+    F: FnMut(&ExitKind, &[u8]) -> bool,
+    S: State,
+{
+    #[allow(clippy::wrong_self_convention)]
+    fn is_interesting<EM, OT>(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _input: &S::Input,
+        observers: &OT,
+        exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .match_name::<StdOutObserver>(&self.observer_name)
+            .ok_or_else(|| {
+                Error::illegal_argument(format!(
+                    "ComparatorFeedback: observer {} not found",
+                    self.observer_name
+                ))
+            })?;
+        let stdout = observer.stdout.as_deref().unwrap_or_default();
+        Ok((self.predicate)(exit_kind, stdout))
+    }
+}
+
+impl<F> Named for ComparatorFeedback<F> {
+    #[inline]
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<F> HasObserverName for ComparatorFeedback<F> {
+    #[inline]
+    fn observer_name(&self) -> &str {
+        &self.observer_name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::tuples::tuple_list;
+
+    use super::ComparatorFeedback;
+    use crate::{
+        events::NopEventManager, executors::ExitKind, feedbacks::Feedback, inputs::BytesInput,
+        observers::StdOutObserver, state::NopState,
+    };
+
+    #[test]
+    fn test_comparator_feedback() {
+        let mut state = NopState::<BytesInput>::new();
+        let mut mgr = NopEventManager::new();
+        let input = BytesInput::new(vec![0]);
+        let mut observers = tuple_list!(StdOutObserver::new("stdout".into()));
+        let mut feedback = ComparatorFeedback::new(&observers.0, |exit_kind, stdout| {
+            *exit_kind == ExitKind::Ok && stdout.windows(5).any(|w| w == b"PANIC")
+        });
+
+        // (stdout, exit kind, interesting)
+        for (stdout, exit_kind, interesting) in [
+            (None, ExitKind::Ok, false),
+            (Some(&b"all good"[..]), ExitKind::Ok, false),
+            (Some(b"PANIC: at the disco"), ExitKind::Ok, true),
+            (Some(b"PANIC: at the disco"), ExitKind::Timeout, false),
+        ] {
+            observers.0.stdout = stdout.map(<[u8]>::to_vec);
+            assert_eq!(
+                feedback
+                    .is_interesting(&mut state, &mut mgr, &input, &observers, &exit_kind)
+                    .unwrap(),
+                interesting
+            );
+        }
+
+        // the observer is looked up by name
+        let other = tuple_list!(StdOutObserver::new("other".into()));
+        assert!(feedback
+            .is_interesting(&mut state, &mut mgr, &input, &other, &ExitKind::Ok)
+            .is_err());
+    }
+}
@@ -0,0 +1,209 @@
+//! The [`InputComplexityFeedback`] uses the compression ratio of inputs as a proxy for their structural novelty.
+//!
+//! The less an input compresses, the more unique patterns it contains, and the more parser states it likely reaches.
+//! Inputs compressing worse than all inputs before are kept, which helps to escape local minima of the coverage.
+//! This is meant as a lightweight complement to coverage feedbacks, e.g. combined with `feedback_or_fast!`.
+
+use alloc::string::{String, ToString};
+
+use libafl_bolts::{compress::GzipCompressor, AsSlice, Named};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::Testcase,
+    events::EventFirer,
+    executors::ExitKind,
+    feedbacks::Feedback,
+    inputs::HasTargetBytes,
+    observers::ObserversTuple,
+    state::{HasNamedMetadata, State},
+    Error,
+};
+
+/// The default minimum input length for the [`InputComplexityFeedback`].
+/// The compression ratio of shorter inputs is dominated by the header of the compressed stream.
+pub const DEFAULT_COMPLEXITY_MIN_LEN: usize = 64;
+
+/// The lowest compression ratio seen by an [`InputComplexityFeedback`]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[allow(clippy::unsafe_derive_deserialize)]
+pub struct InputComplexityMetadata {
+    /// The lowest ratio of input length to compressed length, `None` before the first input
+    pub min_ratio: Option<f64>,
+}
+
+libafl_bolts::impl_serdeany!(InputComplexityMetadata);
+
+/// Considers an input interesting if its compression ratio is lower than that of all inputs before,
+/// see the [module documentation](self).
+///
+/// The ratio is the length of the target bytes divided by their length after a fast deflate compression.
+/// The lowest ratio only moves once an interesting input is actually added to the corpus.
+#[derive(Debug)]
+pub struct InputComplexityFeedback {
+    name: String,
+    compressor: GzipCompressor,
+    /// The ratio of the last interesting input, until its testcase is added or discarded
+    last_ratio: Option<f64>,
+}
+
+impl InputComplexityFeedback {
+    /// Creates a new [`InputComplexityFeedback`] with the given name for its state metadata,
+    /// ignoring inputs shorter than [`DEFAULT_COMPLEXITY_MIN_LEN`].
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        Self::with_min_len(name, DEFAULT_COMPLEXITY_MIN_LEN)
+    }
+
+    /// Creates a new [`InputComplexityFeedback`], ignoring inputs shorter than `min_len` bytes
+    #[must_use]
+    pub fn with_min_len(name: &str, min_len: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            compressor: GzipCompressor::new(min_len.max(1)),
+            last_ratio: None,
+        }
+    }
+
+    /// The compression ratio of `bytes`, `None` if they are too short
+    #[allow(clippy::cast_precision_loss)]
+    fn ratio(&self, bytes: &[u8]) -> Result<Option<f64>, Error> {
+        Ok(self
+            .compressor
+            .compress(bytes)?
+            .map(|compressed| bytes.len() as f64 / compressed.len() as f64))
+    }
+}
+
+impl<S> Feedback<S> for InputComplexityFeedback
+where
+    S: State + HasNamedMetadata,
+    S::Input: HasTargetBytes,
+{
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error> {
+        state.add_named_metadata(InputComplexityMetadata::default(), &self.name);
+        Ok(())
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    fn is_interesting<EM, OT>(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        input: &S::Input,
+        _observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let Some(ratio) = self.ratio(input.target_bytes().as_slice())? else {
+            return Ok(false);
+        };
+
+        let meta = state
+            .named_metadata_map()
+            .get::<InputComplexityMetadata>(&self.name)
+            .unwrap();
+        if meta.min_ratio.map_or(true, |min_ratio| ratio < min_ratio) {
+            self.last_ratio = Some(ratio);
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn append_metadata<OT>(
+        &mut self,
+        state: &mut S,
+        _observers: &OT,
+        _testcase: &mut Testcase<S::Input>,
+    ) -> Result<(), Error>
+    where
+        OT: ObserversTuple<S>,
+    {
+        if let Some(ratio) = self.last_ratio.take() {
+            state
+                .named_metadata_map_mut()
+                .get_mut::<InputComplexityMetadata>(&self.name)
+                .unwrap()
+                .min_ratio = Some(ratio);
+        }
+        Ok(())
+    }
+
+    fn discard_metadata(&mut self, _state: &mut S, _input: &S::Input) -> Result<(), Error> {
+        self.last_ratio = None;
+        Ok(())
+    }
+}
+
+impl Named for InputComplexityFeedback {
+    #[inline]
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{InputComplexityFeedback, InputComplexityMetadata};
+    use crate::{
+        corpus::Testcase,
+        events::NopEventManager,
+        executors::ExitKind,
+        feedbacks::Feedback,
+        inputs::BytesInput,
+        state::{test::test_std_state, HasNamedMetadata},
+    };
+
+    fn min_ratio<S>(state: &S) -> Option<f64>
+    where
+        S: HasNamedMetadata,
+    {
+        state
+            .named_metadata_map()
+            .get::<InputComplexityMetadata>("complexity")
+            .unwrap()
+            .min_ratio
+    }
+
+    #[test]
+    fn test_input_complexity_feedback_moves_on_append() {
+        let mut state = test_std_state::<BytesInput>();
+        let mut mgr = NopEventManager::new();
+        let mut feedback = InputComplexityFeedback::with_min_len("complexity", 16);
+        feedback.init_state(&mut state).unwrap();
+
+        let repetitive = BytesInput::new(vec![b'a'; 256]);
+        let diverse = BytesInput::new((0..=255).collect());
+
+        // A rejected input does not move the bar
+        assert!(feedback
+            .is_interesting(&mut state, &mut mgr, &repetitive, &(), &ExitKind::Ok)
+            .unwrap());
+        feedback.discard_metadata(&mut state, &repetitive).unwrap();
+        assert_eq!(min_ratio(&state), None);
+
+        assert!(feedback
+            .is_interesting(&mut state, &mut mgr, &repetitive, &(), &ExitKind::Ok)
+            .unwrap());
+        feedback
+            .append_metadata(&mut state, &(), &mut Testcase::new(repetitive.clone()))
+            .unwrap();
+        let repetitive_ratio = min_ratio(&state).unwrap();
+
+        assert!(!feedback
+            .is_interesting(&mut state, &mut mgr, &repetitive, &(), &ExitKind::Ok)
+            .unwrap());
+        assert!(feedback
+            .is_interesting(&mut state, &mut mgr, &diverse, &(), &ExitKind::Ok)
+            .unwrap());
+        feedback
+            .append_metadata(&mut state, &(), &mut Testcase::new(diverse))
+            .unwrap();
+        assert!(min_ratio(&state).unwrap() < repetitive_ratio);
+    }
+}
@@ -0,0 +1,190 @@
+//! The [`CorpusSizeLimitFeedback`] caps the number of entries in the corpus.
+
+use alloc::string::String;
+use core::marker::PhantomData;
+
+use libafl_bolts::Named;
+
+use crate::{
+    corpus::{Corpus, Testcase},
+    events::{Event, EventFirer},
+    executors::ExitKind,
+    feedbacks::Feedback,
+    observers::ObserversTuple,
+    state::{HasCorpus, State},
+    Error,
+};
+
+/// Wraps a [`Feedback`], rejecting all inputs once the corpus holds `max_entries` entries.
+///
+/// When the limit is reached, an [`Event::CorpusCompact`] is fired, signalling that the corpus should be compacted,
+/// e.g. by culling the entries a [`crate::schedulers::MinimizerScheduler`] does not favor.
+/// It is fired again only after the corpus shrank below the limit in the meantime.
+#[derive(Debug, Clone)]
+pub struct CorpusSizeLimitFeedback<F> {
+    /// The wrapped feedback
+    pub inner: F,
+    name: String,
+    max_entries: usize,
+    /// If we already asked for a compaction, since the corpus reached the limit
+    compact_requested: bool,
+}
+
+impl<F> CorpusSizeLimitFeedback<F>
+where
+    F: Named,
+{
+    /// Creates a new [`CorpusSizeLimitFeedback`], accepting the inputs `inner` considers interesting,
+    /// as long as the corpus holds less than `max_entries` entries.
+    pub fn with_limit(inner: F, max_entries: usize) -> Self {
+        let name = format!("CorpusSizeLimit({})", inner.name());
+        Self {
+            inner,
+            name,
+            max_entries,
+            compact_requested: false,
+        }
+    }
+
+    /// The maximum number of corpus entries
+    #[must_use]
+    pub fn max_entries(&self) -> usize {
+        self.max_entries
+    }
+}
+
+impl<F, S> Feedback<S> for CorpusSizeLimitFeedback<F>
+where
+    F: Feedback<S>,
+    S: HasCorpus + State,
+{
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error> {
+        self.inner.init_state(state)
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    fn is_interesting<EM, OT>(
+        &mut self,
+        state: &mut S,
+        manager: &mut EM,
+        input: &S::Input,
+        observers: &OT,
+        exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let corpus_size = state.corpus().count();
+        if corpus_size < self.max_entries {
+            self.compact_requested = false;
+            return self
+                .inner
+                .is_interesting(state, manager, input, observers, exit_kind);
+        }
+
+        if !self.compact_requested {
+            self.compact_requested = true;
+            manager.fire(
+                state,
+                Event::CorpusCompact {
+                    corpus_size,
+                    max_entries: self.max_entries,
+                    phantom: PhantomData,
+                },
+            )?;
+        }
+        Ok(false)
+    }
+
+    #[inline]
+    fn append_metadata<OT>(
+        &mut self,
+        state: &mut S,
+        observers: &OT,
+        testcase: &mut Testcase<S::Input>,
+    ) -> Result<(), Error>
+    where
+        OT: ObserversTuple<S>,
+    {
+        self.inner.append_metadata(state, observers, testcase)
+    }
+
+    #[inline]
+    fn discard_metadata(&mut self, state: &mut S, input: &S::Input) -> Result<(), Error> {
+        self.inner.discard_metadata(state, input)
+    }
+}
+
+impl<F> Named for CorpusSizeLimitFeedback<F> {
+    #[inline]
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use alloc::vec::Vec;
+    use std::{env, fs, process};
+
+    use super::CorpusSizeLimitFeedback;
+    use crate::{
+        corpus::{Corpus, CorpusId, Testcase},
+        events::{
+            file::{EventLogReader, FileEventManager},
+            Event, NopEventManager,
+        },
+        executors::ExitKind,
+        feedbacks::{ConstFeedback, Feedback},
+        inputs::BytesInput,
+        state::{test::test_std_state, HasCorpus},
+    };
+
+    #[test]
+    fn test_corpus_size_limit_feedback() {
+        let dir = env::temp_dir().join(format!("libafl_test_corpus_limit_{}", process::id()));
+        drop(fs::remove_dir_all(&dir));
+        let mut state = test_std_state::<BytesInput>();
+        let mut mgr = FileEventManager::new(NopEventManager::new(), &dir).unwrap();
+        let mut feedback = CorpusSizeLimitFeedback::with_limit(ConstFeedback::new(true), 2);
+        let input = BytesInput::new(vec![0]);
+
+        // (corpus size, interesting)
+        for (size, interesting) in [(0, true), (2, false), (2, false), (1, true), (2, false)] {
+            while state.corpus().count() < size {
+                state
+                    .corpus_mut()
+                    .add(Testcase::new(input.clone()))
+                    .unwrap();
+            }
+            while state.corpus().count() > size {
+                state.corpus_mut().remove(CorpusId::from(0_usize)).unwrap();
+            }
+            assert_eq!(
+                feedback
+                    .is_interesting(&mut state, &mut mgr, &input, &(), &ExitKind::Ok)
+                    .unwrap(),
+                interesting
+            );
+        }
+        mgr.flush().unwrap();
+
+        // a compaction is requested once each time the corpus reaches the limit
+        let compactions: Vec<_> = EventLogReader::<BytesInput>::new(mgr.dir())
+            .unwrap()
+            .filter_map(|record| match record.unwrap().event {
+                Event::CorpusCompact {
+                    corpus_size,
+                    max_entries,
+                    ..
+                } => Some((corpus_size, max_entries)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(compactions, [(2, 2), (2, 2)]);
+
+        drop(mgr);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,234 @@
+//! The [`DistanceFeedback`] keeps inputs getting closer to a target basic block, for directed fuzzing.
+//!
+//! The distances are computed ahead of time on the control flow graph of the target, e.g. by an LLVM pass,
+//! and written to a `.cfg.json` file. The file maps each target basic block to the distances of the blocks
+//! reaching it, keyed by the index of the block in the coverage map, both as decimal strings:
+//!
+//! ```json
+//! {
+//!     "4198964": { "12": 0, "57": 1, "1031": 4 }
+//! }
+//! ```
+
+use alloc::string::{String, ToString};
+use core::marker::PhantomData;
+use std::{fs, path::Path};
+
+use hashbrown::HashMap;
+use libafl_bolts::Named;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    events::EventFirer,
+    executors::ExitKind,
+    feedbacks::{Feedback, HasObserverName},
+    inputs::UsesInput,
+    observers::{MapObserver, ObserversTuple},
+    state::{HasNamedMetadata, State},
+    Error,
+};
+
+/// The prefix of the metadata names
+pub const DISTANCEFEEDBACK_PREFIX: &str = "distancefeedback_metadata_";
+
+/// The state of [`DistanceFeedback`]
+#[derive(Default, Serialize, Deserialize, Clone, Debug)]
+#[allow(clippy::unsafe_derive_deserialize)]
+pub struct DistanceMetadata {
+    /// The target basic block
+    pub target_bb: u64,
+    /// The minimum distance to the target reached so far, `None` before any input reached a block with a distance
+    pub min_distance: Option<u64>,
+}
+
+libafl_bolts::impl_serdeany!(DistanceMetadata);
+
+/// A [`DistanceFeedback`] considers an input interesting if its execution came closer to the target basic block
+/// than all executions before, see the [module documentation](self).
+///
+/// The distance of an execution is the minimum distance of all blocks it covered, as reported by a [`MapObserver`].
+/// Blocks without a distance do not reach the target.
+#[derive(Debug)]
+pub struct DistanceFeedback<O> {
+    name: String,
+    observer_name: String,
+    target_bb: u64,
+    /// The distance to the target of the blocks reaching it, by coverage map index
+    distances: HashMap<usize, u64>,
+    phantom: PhantomData<O>,
+}
+
+impl<O> DistanceFeedback<O>
+where
+    O: MapObserver,
+{
+    /// Creates a new [`DistanceFeedback`] towards `target_bb`, given the distances of the blocks reaching it
+    /// by their index in the map of `observer`.
+    ///
+    /// The metadata is named after both the observer and the target, so feedbacks towards different targets
+    /// may share an observer.
+    #[must_use]
+    pub fn new(observer: &O, target_bb: u64, distances: HashMap<usize, u64>) -> Self {
+        Self {
+            name: format!("{DISTANCEFEEDBACK_PREFIX}{}_{target_bb:x}", observer.name()),
+            observer_name: observer.name().to_string(),
+            target_bb,
+            distances,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Creates a new [`DistanceFeedback`] towards `target_bb`, reading the distances from the `.cfg.json` file at `path`
+    pub fn from_cfg_file<P>(observer: &O, target_bb: u64, path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let mut targets: HashMap<u64, HashMap<usize, u64>> =
+            serde_json::from_str(&fs::read_to_string(path)?)?;
+        let distances = targets.remove(&target_bb).ok_or_else(|| {
+            Error::illegal_argument(format!(
+                "{} has no distances to the basic block {target_bb:#x}",
+                path.display()
+            ))
+        })?;
+        Ok(Self::new(observer, target_bb, distances))
+    }
+
+    /// The target basic block
+    #[must_use]
+    pub fn target_bb(&self) -> u64 {
+        self.target_bb
+    }
+
+    /// The minimum distance of the blocks covered in the map of `observer`, `None` if none of them reaches the target
+    fn min_distance(&self, observer: &O) -> Option<u64> {
+        let initial = observer.initial();
+        let len = observer.usable_count();
+        self.distances
+            .iter()
+            .filter(|(idx, _)| **idx < len && *observer.get(**idx) != initial)
+            .map(|(_, distance)| *distance)
+            .min()
+    }
+}
+
+impl<O, S> Feedback<S> for DistanceFeedback<O>
+where
+    O: MapObserver,
+    S: State + HasNamedMetadata,
+{
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error> {
+        state.add_named_metadata(
+            DistanceMetadata {
+                target_bb: self.target_bb,
+                min_distance: None,
+            },
+            &self.name,
+        );
+        Ok(())
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    fn is_interesting<EM, OT>(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _input: &<S as UsesInput>::Input,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .match_name::<O>(&self.observer_name)
+            .expect("A DistanceFeedback needs a MapObserver");
+        let Some(distance) = self.min_distance(observer) else {
+            return Ok(false);
+        };
+
+        let meta = state
+            .named_metadata_map_mut()
+            .get_mut::<DistanceMetadata>(&self.name)
+            .unwrap();
+        if meta.min_distance.map_or(true, |min| distance < min) {
+            meta.min_distance = Some(distance);
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+}
+
+impl<O> Named for DistanceFeedback<O> {
+    #[inline]
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<O> HasObserverName for DistanceFeedback<O> {
+    #[inline]
+    fn observer_name(&self) -> &str {
+        &self.observer_name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hashbrown::HashMap;
+    use libafl_bolts::{tuples::tuple_list, Named};
+
+    use super::{DistanceFeedback, DistanceMetadata};
+    use crate::{
+        events::NopEventManager,
+        executors::ExitKind,
+        feedbacks::Feedback,
+        inputs::BytesInput,
+        observers::{MapObserver, StdMapObserver},
+        state::{test::test_std_state, HasNamedMetadata},
+    };
+
+    #[test]
+    fn test_distance_feedbacks_on_one_observer() {
+        let mut state = test_std_state::<BytesInput>();
+        let mut mgr = NopEventManager::new();
+        let input = BytesInput::new(vec![0]);
+
+        let mut observers = tuple_list!(StdMapObserver::owned("edges", vec![0_u8; 8]));
+        let mut near = DistanceFeedback::new(&observers.0, 0x10, HashMap::from([(1, 3), (2, 1)]));
+        let mut far = DistanceFeedback::new(&observers.0, 0x20, HashMap::from([(1, 5), (3, 2)]));
+        assert_ne!(near.name(), far.name());
+        near.init_state(&mut state).unwrap();
+        far.init_state(&mut state).unwrap();
+
+        for (hit, near_interesting, far_interesting) in
+            [(1, true, true), (2, true, false), (1, false, false)]
+        {
+            observers.0.reset_map().unwrap();
+            *observers.0.get_mut(hit) = 1;
+            assert_eq!(
+                near.is_interesting(&mut state, &mut mgr, &input, &observers, &ExitKind::Ok)
+                    .unwrap(),
+                near_interesting
+            );
+            assert_eq!(
+                far.is_interesting(&mut state, &mut mgr, &input, &observers, &ExitKind::Ok)
+                    .unwrap(),
+                far_interesting
+            );
+        }
+
+        let min_distance = |name: &str| {
+            state
+                .named_metadata_map()
+                .get::<DistanceMetadata>(name)
+                .unwrap()
+                .min_distance
+        };
+        assert_eq!(min_distance(near.name()), Some(1));
+        assert_eq!(min_distance(far.name()), Some(5));
+    }
+}
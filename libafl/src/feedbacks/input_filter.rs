@@ -0,0 +1,106 @@
+//! The [`InputFilterFeedback`] rejects inputs that fail an [`InputFilter`], before they reach the corpus.
+//!
+//! Unlike the [`crate::corpus::FilteredCorpus`], it does not fail the evaluation of rejected inputs.
+
+use libafl_bolts::Named;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::InputFilter, events::EventFirer, executors::ExitKind, feedbacks::Feedback,
+    observers::ObserversTuple, state::State, Error,
+};
+
+/// A [`Feedback`] that is interesting for the inputs accepted by its [`InputFilter`].
+///
+/// Combine it with the coverage feedback, e.g. with [`crate::feedback_and_fast`],
+/// so that rejected inputs are never added to the corpus, without failing the evaluation.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct InputFilterFeedback<F> {
+    filter: F,
+}
+
+impl<F> InputFilterFeedback<F> {
+    /// Creates a new [`InputFilterFeedback`], interesting for the inputs accepted by `filter`
+    #[must_use]
+    pub fn new(filter: F) -> Self {
+        Self { filter }
+    }
+
+    /// The filter of this feedback
+    #[must_use]
+    pub fn filter(&self) -> &F {
+        &self.filter
+    }
+}
+
+impl<F, S> Feedback<S> for InputFilterFeedback<F>
+where
+    F: InputFilter<S::Input>,
+    S: State,
+{
+    #[inline]
+    #[allow(clippy::wrong_self_convention)]
+    fn is_interesting<EM, OT>(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        input: &S::Input,
+        _observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        Ok(self.filter.accept(input))
+    }
+}
+
+impl<F> Named for InputFilterFeedback<F> {
+    #[inline]
+    fn name(&self) -> &str {
+        "InputFilterFeedback"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InputFilterFeedback;
+    use crate::{
+        corpus::{LengthFilter, MagicBytesFilter},
+        events::NopEventManager,
+        executors::ExitKind,
+        feedbacks::Feedback,
+        inputs::BytesInput,
+        state::test::test_std_state,
+    };
+
+    #[test]
+    fn test_input_filter_feedback() {
+        let mut state = test_std_state::<BytesInput>();
+        let mut mgr = NopEventManager::new();
+
+        let mut length = InputFilterFeedback::new(LengthFilter::new(2, 4));
+        let mut magic = InputFilterFeedback::new(MagicBytesFilter::new(b"GIF".to_vec()));
+        for (bytes, length_ok, magic_ok) in [
+            (&b"G"[..], false, false),
+            (&b"GIF8"[..], true, true),
+            (&b"PNG"[..], true, false),
+            (&b"GIF89a"[..], false, true),
+        ] {
+            let input = BytesInput::new(bytes.to_vec());
+            assert_eq!(
+                length
+                    .is_interesting(&mut state, &mut mgr, &input, &(), &ExitKind::Ok)
+                    .unwrap(),
+                length_ok
+            );
+            assert_eq!(
+                magic
+                    .is_interesting(&mut state, &mut mgr, &input, &(), &ExitKind::Ok)
+                    .unwrap(),
+                magic_ok
+            );
+        }
+    }
+}
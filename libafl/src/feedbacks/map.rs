@@ -35,7 +35,13 @@ pub const MAPFEEDBACK_PREFIX: &str = "mapfeedback_metadata_";
 pub type AflMapFeedback<O, S, T> = MapFeedback<DifferentIsNovel, O, OrReducer, S, T>;
 
 /// A [`MapFeedback`] that strives to maximize the map contents.
+///
+/// If the map entries are counters that wrap around, consider the [`SaturatingMaxMapFeedback`].
 pub type MaxMapFeedback<O, S, T> = MapFeedback<DifferentIsNovel, O, MaxReducer, S, T>;
+/// A [`MapFeedback`] that strives to maximize the map contents, for maps of counters that may wrap around.
+/// Entries that saturated (see [`SaturatingMaxReducer`]) are no source of novelty anymore.
+pub type SaturatingMaxMapFeedback<O, S, T> =
+    MapFeedback<SaturatedIsNotNovel, O, SaturatingMaxReducer, S, T>;
 /// A [`MapFeedback`] that strives to minimize the map contents.
 pub type MinMapFeedback<O, S, T> = MapFeedback<DifferentIsNovel, O, MinReducer, S, T>;
 
@@ -118,6 +124,35 @@ where
     }
 }
 
+/// A [`SaturatingMaxReducer`] reduces int values and returns their maximum, capped at `T::max_value() - 1`,
+/// until an entry saturates: from then on, it stays at `T::max_value()`.
+///
+/// `T::max_value()` is reserved as the saturation sentinel, a history never holds it otherwise.
+/// An entry saturates once a value reaches `T::max_value()`: the counter may wrap around with the next hit,
+/// so its later values do not tell anything about the number of hits.
+/// A value below the history is a run with less hits, it is not taken for a wrap-around.
+#[derive(Clone, Debug)]
+pub struct SaturatingMaxReducer {}
+
+impl<T> Reducer<T> for SaturatingMaxReducer
+where
+    T: PrimInt + Default + Copy + 'static,
+{
+    #[inline]
+    fn reduce(history: T, new: T) -> T {
+        let saturated = T::max_value();
+        if history == saturated || new == saturated {
+            return saturated;
+        }
+        let observed = new.min(saturated - T::one());
+        if observed > history {
+            observed
+        } else {
+            history
+        }
+    }
+}
+
 /// A [`MinReducer`] reduces int values and returns their minimum.
 #[derive(Clone, Debug)]
 pub struct MinReducer {}
@@ -220,6 +255,20 @@ where
     }
 }
 
+/// Consider as novelty if the reduced value is different from the old value,
+/// unless the entry just saturated, as marked by the [`SaturatingMaxReducer`].
+#[derive(Clone, Debug)]
+pub struct SaturatedIsNotNovel {}
+impl<T> IsNovel<T> for SaturatedIsNotNovel
+where
+    T: PrimInt + Default + Copy + 'static,
+{
+    #[inline]
+    fn is_novel(old: T, new: T) -> bool {
+        old != new && new != T::max_value()
+    }
+}
+
 /// A testcase metadata holding a list of indexes of a map
 #[derive(Debug, Serialize, Deserialize)]
 #[cfg_attr(
@@ -952,9 +1001,146 @@ where
     }
 }
 
+/// The prefix of the [`MaxEdgeCountFeedback`] metadata names
+pub const MAXEDGECOUNTFEEDBACK_PREFIX: &str = "maxedgecountfeedback_metadata_";
+
+/// The state of [`MaxEdgeCountFeedback`]
+#[derive(Default, Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct MaxEdgeCountMetadata {
+    /// The most map entries covered by a single execution so far
+    pub max_total_edges: usize,
+}
+
+libafl_bolts::impl_serdeany!(MaxEdgeCountMetadata);
+
+/// A [`MaxEdgeCountFeedback`] considers an input interesting if its execution covered more map entries
+/// than any execution before.
+///
+/// Unlike the [`MaxMapFeedback`], which rewards an increase of any single entry, this rewards the breadth of
+/// the coverage of one execution: the number of entries different from the initial value of the map.
+/// The maximum only moves once an interesting input is actually added to the corpus.
+#[derive(Clone, Debug)]
+pub struct MaxEdgeCountFeedback<O, S> {
+    name: String,
+    observer_name: String,
+    /// The entries covered by the last interesting execution, until its testcase is added or discarded
+    last_total_edges: Option<usize>,
+    phantom: PhantomData<(O, S)>,
+}
+
+impl<O, S> MaxEdgeCountFeedback<O, S>
+where
+    O: MapObserver,
+{
+    /// Creates a new [`MaxEdgeCountFeedback`] for a [`MapObserver`].
+    #[must_use]
+    pub fn new(map_observer: &O) -> Self {
+        Self {
+            name: MAXEDGECOUNTFEEDBACK_PREFIX.to_string() + map_observer.name(),
+            observer_name: map_observer.name().to_string(),
+            last_total_edges: None,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<O, S> Feedback<S> for MaxEdgeCountFeedback<O, S>
+where
+    O: MapObserver,
+    S: State + HasNamedMetadata,
+{
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error> {
+        state.add_named_metadata(MaxEdgeCountMetadata::default(), &self.name);
+        Ok(())
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    fn is_interesting<EM, OT>(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _input: &S::Input,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers.match_name::<O>(&self.observer_name).unwrap();
+        let total_edges = observer.count_bytes() as usize;
+
+        let meta = state
+            .named_metadata_map()
+            .get::<MaxEdgeCountMetadata>(&self.name)
+            .unwrap();
+        if total_edges > meta.max_total_edges {
+            self.last_total_edges = Some(total_edges);
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn append_metadata<OT>(
+        &mut self,
+        state: &mut S,
+        _observers: &OT,
+        _testcase: &mut Testcase<S::Input>,
+    ) -> Result<(), Error>
+    where
+        OT: ObserversTuple<S>,
+    {
+        if let Some(total_edges) = self.last_total_edges.take() {
+            state
+                .named_metadata_map_mut()
+                .get_mut::<MaxEdgeCountMetadata>(&self.name)
+                .unwrap()
+                .max_total_edges = total_edges;
+        }
+        Ok(())
+    }
+
+    fn discard_metadata(&mut self, _state: &mut S, _input: &S::Input) -> Result<(), Error> {
+        self.last_total_edges = None;
+        Ok(())
+    }
+}
+
+impl<O, S> Named for MaxEdgeCountFeedback<O, S> {
+    #[inline]
+    fn name(&self) -> &str {
+        self.name.as_str()
+    }
+}
+
+impl<O, S> HasObserverName for MaxEdgeCountFeedback<O, S> {
+    #[inline]
+    fn observer_name(&self) -> &str {
+        self.observer_name.as_str()
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::feedbacks::{AllIsNovel, IsNovel, NextPow2IsNovel};
+    use libafl_bolts::tuples::tuple_list;
+
+    use crate::{
+        corpus::Testcase,
+        events::NopEventManager,
+        executors::ExitKind,
+        feedbacks::{
+            AllIsNovel, Feedback, IsNovel, MaxEdgeCountFeedback, NextPow2IsNovel, Reducer,
+            SaturatedIsNotNovel, SaturatingMaxReducer,
+        },
+        inputs::BytesInput,
+        observers::{MapObserver, StdMapObserver},
+        state::test::test_std_state,
+    };
 
     #[test]
     fn test_map_is_novel() {
@@ -975,6 +1161,72 @@ mod tests {
         assert!(NextPow2IsNovel::is_novel(254_u8, 255));
         assert!(!NextPow2IsNovel::is_novel(255_u8, 255));
     }
+
+    #[test]
+    fn test_map_saturating() {
+        let reduce = SaturatingMaxReducer::reduce;
+        assert_eq!(reduce(3_u8, 5), 5);
+        assert_eq!(reduce(5_u8, 3), 5);
+        assert_eq!(reduce(200_u8, 254), 254);
+        assert_eq!(reduce(254_u8, 254), 254);
+        // a run with less hits does not saturate the entry
+        assert_eq!(reduce(200_u8, 4), 200);
+        // reaching the maximum does, and saturated entries stay saturated
+        assert_eq!(reduce(200_u8, 255), 255);
+        assert_eq!(reduce(255_u8, 4), 255);
+        assert_eq!(
+            <SaturatingMaxReducer as Reducer<u16>>::reduce(40000, 3),
+            40000
+        );
+        assert_eq!(
+            <SaturatingMaxReducer as Reducer<u16>>::reduce(40000, u16::MAX),
+            u16::MAX
+        );
+
+        assert!(SaturatedIsNotNovel::is_novel(3_u8, reduce(3, 5)));
+        assert!(SaturatedIsNotNovel::is_novel(200_u8, reduce(200, 254)));
+        assert!(!SaturatedIsNotNovel::is_novel(200_u8, reduce(200, 4)));
+        assert!(!SaturatedIsNotNovel::is_novel(200_u8, reduce(200, 255)));
+        assert!(!SaturatedIsNotNovel::is_novel(255_u8, reduce(255, 4)));
+    }
+
+    #[test]
+    fn test_max_edge_count_moves_on_append() {
+        let mut state = test_std_state::<BytesInput>();
+        let mut mgr = NopEventManager::new();
+        let input = BytesInput::new(vec![0]);
+
+        let mut observers = tuple_list!(StdMapObserver::owned("edges", vec![0_u8; 8]));
+        let mut feedback = MaxEdgeCountFeedback::new(&observers.0);
+        feedback.init_state(&mut state).unwrap();
+
+        // (covered entries, interesting, added to the corpus)
+        for (covered, interesting, added) in [
+            (2, true, false),
+            (1, true, true),
+            (1, false, false),
+            (2, true, true),
+            (2, false, false),
+        ] {
+            observers.0.reset_map().unwrap();
+            for idx in 0..covered {
+                *observers.0.get_mut(idx) = 1;
+            }
+            assert_eq!(
+                feedback
+                    .is_interesting(&mut state, &mut mgr, &input, &observers, &ExitKind::Ok)
+                    .unwrap(),
+                interesting
+            );
+            if added {
+                feedback
+                    .append_metadata(&mut state, &observers, &mut Testcase::new(input.clone()))
+                    .unwrap();
+            } else {
+                feedback.discard_metadata(&mut state, &input).unwrap();
+            }
+        }
+    }
 }
 
 /// `MapFeedback` Python bindings
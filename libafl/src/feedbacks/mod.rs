@@ -7,12 +7,56 @@
 pub mod map;
 pub use map::*;
 
+pub mod cmp;
+pub use cmp::{CmpLogFeedback, CmpLogFeedbackMetadata};
+
+pub mod taint;
+pub use taint::{TaintFeedback, TaintOperandsMetadata};
+
+pub mod new_feature;
+pub use new_feature::{NewFeatureFeedback, NewFeatureFeedbackMetadata};
+
+pub mod corpus_size_limit;
+pub use corpus_size_limit::CorpusSizeLimitFeedback;
+
+pub mod input_filter;
+pub use input_filter::InputFilterFeedback;
+
+#[cfg(feature = "std")]
+pub mod comparator;
+#[cfg(feature = "std")]
+pub use comparator::ComparatorFeedback;
+
+#[cfg(feature = "regex")]
+pub mod regex_match;
+#[cfg(feature = "regex")]
+pub use regex_match::{RegexFeedback, RegexMatchMetadata};
+
+#[cfg(feature = "gzip")]
+pub mod complexity;
+#[cfg(feature = "gzip")]
+pub use complexity::{InputComplexityFeedback, InputComplexityMetadata};
+
 pub mod differential;
 pub use differential::DiffFeedback;
 #[cfg(feature = "std")]
 pub mod concolic;
 #[cfg(feature = "std")]
 pub use concolic::ConcolicFeedback;
+#[cfg(all(feature = "std", feature = "concolic_mutation"))]
+pub mod path_constraint;
+#[cfg(all(feature = "std", feature = "concolic_mutation"))]
+pub use path_constraint::{PathConstraintFeedback, PathConstraintMetadata};
+
+#[cfg(feature = "std")]
+pub mod call_site;
+#[cfg(feature = "std")]
+pub use call_site::{NewCallSiteFeedback, NewCallSiteFeedbackMetadata};
+
+#[cfg(feature = "std")]
+pub mod distance;
+#[cfg(feature = "std")]
+pub use distance::{DistanceFeedback, DistanceMetadata};
 
 #[cfg(feature = "std")]
 pub mod new_hash_feedback;
@@ -23,6 +67,18 @@ pub use new_hash_feedback::NewHashFeedbackMetadata;
 
 #[cfg(feature = "nautilus")]
 pub mod nautilus;
+#[cfg(all(
+    feature = "syscall_observer",
+    target_os = "linux",
+    target_arch = "x86_64"
+))]
+pub mod syscall;
+#[cfg(all(
+    feature = "syscall_observer",
+    target_os = "linux",
+    target_arch = "x86_64"
+))]
+pub use syscall::{NewSyscallFeedback, NewSyscallFeedbackMetadata};
 pub mod transferred;
 
 use alloc::string::{String, ToString};
@@ -0,0 +1,214 @@
+//! The [`NewFeatureFeedback`] keeps the inputs producing a feature never seen before,
+//! the feature being computed from an observer by a user-supplied hash function.
+//!
+//! This allows custom novelty signals, e.g. the set of states reached by a protocol implementation,
+//! without writing a dedicated [`Feedback`] for each of them.
+
+use alloc::string::{String, ToString};
+use core::{
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+};
+
+use hashbrown::HashSet;
+use libafl_bolts::Named;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    events::EventFirer,
+    executors::ExitKind,
+    feedbacks::{Feedback, HasObserverName},
+    inputs::UsesInput,
+    observers::{Observer, ObserversTuple},
+    state::{HasNamedMetadata, State},
+    Error,
+};
+
+/// The prefix of the metadata names
+pub const NEWFEATUREFEEDBACK_PREFIX: &str = "newfeaturefeedback_metadata_";
+
+/// The state of [`NewFeatureFeedback`], holding the feature hashes seen so far
+#[derive(Default, Serialize, Deserialize, Clone, Debug)]
+#[allow(clippy::unsafe_derive_deserialize)]
+pub struct NewFeatureFeedbackMetadata {
+    /// The feature hashes seen so far
+    pub seen: HashSet<u64>,
+}
+
+libafl_bolts::impl_serdeany!(NewFeatureFeedbackMetadata);
+
+impl NewFeatureFeedbackMetadata {
+    /// Create a new [`NewFeatureFeedbackMetadata`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reset the internal state
+    pub fn reset(&mut self) -> Result<(), Error> {
+        self.seen.clear();
+        Ok(())
+    }
+}
+
+/// A [`NewFeatureFeedback`] reports an input as interesting if `hash_fn`, applied to the observer
+/// after the execution, returns a hash that was never returned before.
+#[derive(Serialize, Deserialize)]
+pub struct NewFeatureFeedback<F, O>
+where
+    F: Fn(&O) -> u64,
+{
+    name: String,
+    observer_name: String,
+    /// The function computing the feature hash of an execution from the observer
+    hash_fn: F,
+    phantom: PhantomData<O>,
+}
+
+impl<F, O> NewFeatureFeedback<F, O>
+where
+    F: Fn(&O) -> u64,
+{
+    /// Create a new [`NewFeatureFeedback`] for the feature `name`, hashing the observer named `observer_name` with `hash_fn`.
+    ///
+    /// The metadata is named after both the feature and the observer, so several features of one observer
+    /// keep their seen hashes apart.
+    #[must_use]
+    pub fn new(name: &str, observer_name: &str, hash_fn: F) -> Self {
+        Self {
+            name: format!("{NEWFEATUREFEEDBACK_PREFIX}{observer_name}_{name}"),
+            observer_name: observer_name.to_string(),
+            hash_fn,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<F, O> Debug for NewFeatureFeedback<F, O>
+where
+    F: Fn(&O) -> u64,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NewFeatureFeedback")
+            .field("name", &self.name)
+            .field("observer_name", &self.observer_name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F, O, S> Feedback<S> for NewFeatureFeedback<F, O>
+where
+    F: Fn(&O) -> u64,
+    O: Observer<S>,
+    S: State + HasNamedMetadata,
+{
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error> {
+        state.add_named_metadata(NewFeatureFeedbackMetadata::new(), &self.name);
+        Ok(())
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    fn is_interesting<EM, OT>(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _input: &<S as UsesInput>::Input,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .match_name::<O>(&self.observer_name)
+            .ok_or_else(|| {
+                Error::key_not_found(format!(
+                    "NewFeatureFeedback: observer {} not found",
+                    self.observer_name
+                ))
+            })?;
+        let feature = (self.hash_fn)(observer);
+
+        let meta = state
+            .named_metadata_map_mut()
+            .get_mut::<NewFeatureFeedbackMetadata>(&self.name)
+            .unwrap();
+        Ok(meta.seen.insert(feature))
+    }
+}
+
+impl<F, O> Named for NewFeatureFeedback<F, O>
+where
+    F: Fn(&O) -> u64,
+{
+    #[inline]
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<F, O> HasObserverName for NewFeatureFeedback<F, O>
+where
+    F: Fn(&O) -> u64,
+{
+    #[inline]
+    fn observer_name(&self) -> &str {
+        &self.observer_name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::tuples::tuple_list;
+
+    use super::NewFeatureFeedback;
+    use crate::{
+        events::NopEventManager,
+        executors::ExitKind,
+        feedbacks::Feedback,
+        inputs::BytesInput,
+        observers::{MapObserver, StdMapObserver},
+        state::test::test_std_state,
+    };
+
+    #[test]
+    fn test_new_feature_feedbacks_on_one_observer() {
+        let mut state = test_std_state::<BytesInput>();
+        let mut mgr = NopEventManager::new();
+        let input = BytesInput::new(vec![0]);
+
+        let mut observers = tuple_list!(StdMapObserver::owned("states", vec![0_u8; 2]));
+        let mut first =
+            NewFeatureFeedback::new("first", "states", |o: &StdMapObserver<u8, false>| {
+                u64::from(*o.get(0))
+            });
+        let mut second =
+            NewFeatureFeedback::new("second", "states", |o: &StdMapObserver<u8, false>| {
+                u64::from(*o.get(1))
+            });
+        first.init_state(&mut state).unwrap();
+        second.init_state(&mut state).unwrap();
+
+        for (map, first_interesting, second_interesting) in [
+            ([1, 0], true, true),
+            ([0, 1], true, true),
+            ([1, 1], false, false),
+        ] {
+            *observers.0.get_mut(0) = map[0];
+            *observers.0.get_mut(1) = map[1];
+            assert_eq!(
+                first
+                    .is_interesting(&mut state, &mut mgr, &input, &observers, &ExitKind::Ok)
+                    .unwrap(),
+                first_interesting
+            );
+            assert_eq!(
+                second
+                    .is_interesting(&mut state, &mut mgr, &input, &observers, &ExitKind::Ok)
+                    .unwrap(),
+                second_interesting
+            );
+        }
+    }
+}
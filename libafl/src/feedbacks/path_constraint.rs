@@ -0,0 +1,283 @@
+//! The [`PathConstraintFeedback`] solves the path constraints of concolic traces with `z3`,
+//! stashing the inputs taking new branches for the [`crate::stages::PathConstraintSolutionsStage`] to evaluate.
+//! It is a building block for hybrid fuzzers, for which the concolic executions are part of the fuzzing loop.
+//!
+//! The solved inputs are not added to the corpus from within [`Feedback::is_interesting`]: a feedback has no access
+//! to the fuzzer, so such a testcase would never be executed, bypass the scheduler and the other feedbacks,
+//! and never be reported to the other clients. Instead, they are evaluated like any other input by the stage.
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{fmt::Debug, marker::PhantomData};
+
+use hashbrown::HashSet;
+use libafl_bolts::{impl_serdeany, Named};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    events::EventFirer,
+    executors::ExitKind,
+    feedbacks::{Feedback, HasObserverName},
+    inputs::{HasBytesVec, UsesInput},
+    observers::{
+        concolic::{ConcolicObserver, Location, SymExpr},
+        ObserversTuple,
+    },
+    stages::concolic::generate_mutations_negating,
+    state::{HasNamedMetadata, State},
+    Error,
+};
+
+/// The prefix of the metadata names
+pub const PATH_CONSTRAINT_FEEDBACK_PREFIX: &str = "pathconstraint_";
+
+/// The most solved inputs kept in the [`PathConstraintMetadata`] until the stage evaluates them.
+/// Traces taking new branches while it is full are not solved, and their branches are not marked as seen,
+/// so that a later trace solves them again.
+pub const MAX_PENDING_SOLUTIONS: usize = 1024;
+
+/// The branches already seen by a [`PathConstraintFeedback`], and the solved inputs not evaluated yet
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct PathConstraintMetadata {
+    /// The location of each seen branch, and if it was taken
+    pub seen: HashSet<(Location, bool)>,
+    /// The bytes of the inputs solving the negated conditions, for the [`crate::stages::PathConstraintSolutionsStage`]
+    pub pending: Vec<Vec<u8>>,
+}
+
+impl_serdeany!(PathConstraintMetadata);
+
+/// Solves the path constraints recorded by a [`ConcolicObserver`], the symbolic trace of the execution.
+///
+/// For each trace taking a branch direction not seen before, the last such condition is negated and solved with `z3`.
+/// The inputs solving it are stashed in the [`PathConstraintMetadata`], the corpus is not touched during the evaluation.
+/// Add a [`crate::stages::PathConstraintSolutionsStage`] to evaluate them through the fuzzer.
+/// The traced input itself is never considered interesting, combine this with another feedback for that.
+#[derive(Debug)]
+pub struct PathConstraintFeedback<S> {
+    name: String,
+    observer_name: String,
+    phantom: PhantomData<S>,
+}
+
+impl<S> PathConstraintFeedback<S> {
+    /// Creates a new [`PathConstraintFeedback`], solving the traces of the given observer
+    #[must_use]
+    pub fn new(observer: &ConcolicObserver) -> Self {
+        Self {
+            name: PATH_CONSTRAINT_FEEDBACK_PREFIX.to_string() + observer.name(),
+            observer_name: observer.name().to_string(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<S> Feedback<S> for PathConstraintFeedback<S>
+where
+    S: State + HasNamedMetadata,
+    S::Input: HasBytesVec,
+{
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error> {
+        state.add_named_metadata(PathConstraintMetadata::default(), &self.name);
+        Ok(())
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    fn is_interesting<EM, OT>(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        input: &<S as UsesInput>::Input,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .match_name::<ConcolicObserver>(&self.observer_name)
+            .ok_or_else(|| Error::key_not_found("ConcolicObserver not found".to_string()))?;
+        let trace = observer.create_metadata_from_current_map();
+
+        let branches: Vec<(Location, bool)> = trace
+            .iter_messages()
+            .filter_map(|(_, msg)| match msg {
+                SymExpr::PathConstraint {
+                    location, taken, ..
+                } => Some((location, taken)),
+                _ => None,
+            })
+            .collect();
+
+        let metadata = state.named_metadata_mut::<PathConstraintMetadata>(&self.name)?;
+        if metadata.pending.len() >= MAX_PENDING_SOLUTIONS {
+            log::debug!("Too many unevaluated solutions, not solving the path constraints");
+            return Ok(false);
+        }
+        let Some(target) = branches
+            .iter()
+            .rev()
+            .find(|branch| !metadata.seen.contains(*branch))
+            .copied()
+        else {
+            return Ok(false);
+        };
+        metadata.seen.extend(branches);
+
+        let solutions = generate_mutations_negating(trace.iter_messages(), |location, taken| {
+            (location, taken) == target
+        });
+        let room = MAX_PENDING_SOLUTIONS - metadata.pending.len();
+        for solution in solutions.into_iter().take(room) {
+            let mut solved = input.bytes().to_vec();
+            for (index, new_byte) in solution {
+                if let Some(byte) = solved.get_mut(index) {
+                    *byte = new_byte;
+                }
+            }
+            metadata.pending.push(solved);
+        }
+
+        Ok(false)
+    }
+}
+
+impl<S> Named for PathConstraintFeedback<S> {
+    #[inline]
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<S> HasObserverName for PathConstraintFeedback<S> {
+    #[inline]
+    fn observer_name(&self) -> &str {
+        &self.observer_name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::ToString, vec::Vec};
+    use std::io::Cursor;
+
+    use libafl_bolts::{tuples::tuple_list, Named};
+
+    use super::{PathConstraintFeedback, PathConstraintMetadata, MAX_PENDING_SOLUTIONS};
+    use crate::{
+        events::NopEventManager,
+        executors::ExitKind,
+        feedbacks::Feedback,
+        inputs::BytesInput,
+        observers::concolic::{serialization_format::MessageFileWriter, ConcolicObserver, SymExpr},
+        state::{test::test_std_state, HasNamedMetadata, StdState},
+    };
+
+    /// A trace of a branch at `location`, not taken as the first input byte is not `b'b'`
+    fn trace(location: usize) -> Vec<u8> {
+        let mut buf = Vec::new();
+        {
+            let mut cursor = Cursor::new(&mut buf);
+            let mut writer = MessageFileWriter::from_writer(&mut cursor).unwrap();
+            let a = writer
+                .write_message(SymExpr::InputByte {
+                    offset: 0,
+                    value: b'a',
+                })
+                .unwrap();
+            let b = writer
+                .write_message(SymExpr::Integer {
+                    value: u64::from(b'b'),
+                    bits: 8,
+                })
+                .unwrap();
+            let constraint = writer.write_message(SymExpr::Equal { a, b }).unwrap();
+            writer
+                .write_message(SymExpr::PathConstraint {
+                    constraint,
+                    taken: false,
+                    location: location.into(),
+                })
+                .unwrap();
+            writer.update_trace_header().unwrap();
+        }
+        buf
+    }
+
+    #[test]
+    fn test_path_constraint_feedback() {
+        let map = trace(1);
+        let observer = ConcolicObserver::new("concolic".into(), &map);
+        let mut feedback = PathConstraintFeedback::new(&observer);
+        let mut state = test_std_state::<BytesInput>();
+        feedback.init_state(&mut state).unwrap();
+        let observers = tuple_list!(observer);
+        let input = BytesInput::new(b"ab".to_vec());
+
+        for _ in 0..2 {
+            // the traced input itself is never interesting
+            assert!(!feedback
+                .is_interesting(
+                    &mut state,
+                    &mut NopEventManager::new(),
+                    &input,
+                    &observers,
+                    &ExitKind::Ok
+                )
+                .unwrap());
+        }
+        // the branch is solved once, taking it the second time
+        let metadata = state
+            .named_metadata::<PathConstraintMetadata>(feedback.name())
+            .unwrap();
+        assert_eq!(metadata.pending, [b"bb".to_vec()]);
+    }
+
+    #[test]
+    fn test_path_constraint_feedback_bounded() {
+        let map = trace(2);
+        let observer = ConcolicObserver::new("concolic".into(), &map);
+        let mut feedback = PathConstraintFeedback::new(&observer);
+        let mut state = test_std_state::<BytesInput>();
+        feedback.init_state(&mut state).unwrap();
+        let observers = tuple_list!(observer);
+        let input = BytesInput::new(b"a".to_vec());
+        let name = feedback.name().to_string();
+        let mut run = |state: &mut StdState<_, _, _, _>| {
+            feedback
+                .is_interesting(
+                    state,
+                    &mut NopEventManager::new(),
+                    &input,
+                    &observers,
+                    &ExitKind::Ok,
+                )
+                .unwrap();
+        };
+
+        state
+            .named_metadata_mut::<PathConstraintMetadata>(&name)
+            .unwrap()
+            .pending = vec![vec![]; MAX_PENDING_SOLUTIONS];
+        run(&mut state);
+        let metadata = state
+            .named_metadata_mut::<PathConstraintMetadata>(&name)
+            .unwrap();
+        assert_eq!(metadata.pending.len(), MAX_PENDING_SOLUTIONS);
+        assert!(metadata.seen.is_empty());
+
+        // once the stage drained the solutions, the branch is solved
+        metadata.pending.clear();
+        run(&mut state);
+        let metadata = state
+            .named_metadata::<PathConstraintMetadata>(&name)
+            .unwrap();
+        assert_eq!(metadata.pending, [b"b".to_vec()]);
+    }
+}
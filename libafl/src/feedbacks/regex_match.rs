@@ -0,0 +1,251 @@
+//! The [`RegexFeedback`] looks for inputs making the target print a message matching a regular expression,
+//! such as `heap corruption` or `assertion failed`.
+
+use alloc::string::{String, ToString};
+
+use libafl_bolts::Named;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::Testcase,
+    events::EventFirer,
+    executors::ExitKind,
+    feedbacks::{Feedback, HasObserverName},
+    observers::{ObserversTuple, StdErrObserver, StdOutObserver},
+    state::{HasMetadata, State},
+    Error,
+};
+
+/// The output of the target the [`RegexFeedback`] is matched against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStream {
+    /// The stdout, captured by a [`StdOutObserver`]
+    Stdout,
+    /// The stderr, captured by a [`StdErrObserver`]
+    Stderr,
+}
+
+/// The match of a [`RegexFeedback`], attached to the testcases it found for triage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[allow(clippy::unsafe_derive_deserialize)]
+pub struct RegexMatchMetadata {
+    /// The pattern of the feedback
+    pub pattern: String,
+    /// The text that matched the pattern, `None` for a negated feedback
+    pub matched: Option<String>,
+}
+
+libafl_bolts::impl_serdeany!(RegexMatchMetadata);
+
+/// Considers an input interesting if the output of its execution matches a [`Regex`],
+/// or, if negated, if it does not match.
+///
+/// The output is captured by a [`StdOutObserver`] or [`StdErrObserver`], so the executor has to support it,
+/// like the [`crate::executors::CommandExecutor`]. If no output was captured, the pattern is matched
+/// against an empty output. Invalid UTF-8 in the output is replaced before matching.
+#[derive(Debug)]
+pub struct RegexFeedback {
+    name: String,
+    observer_name: String,
+    stream: OutputStream,
+    regex: Regex,
+    negate: bool,
+    /// The match of the last execution, until it is attached to the testcase
+    last_match: Option<RegexMatchMetadata>,
+}
+
+impl RegexFeedback {
+    fn new(observer_name: &str, stream: OutputStream, regex: Regex) -> Self {
+        Self {
+            name: format!("RegexFeedback({observer_name})"),
+            observer_name: observer_name.to_string(),
+            stream,
+            regex,
+            negate: false,
+            last_match: None,
+        }
+    }
+
+    /// Creates a new [`RegexFeedback`], matching `regex` against the stdout captured by `observer`
+    #[must_use]
+    pub fn with_stdout(observer: &StdOutObserver, regex: Regex) -> Self {
+        Self::new(observer.name(), OutputStream::Stdout, regex)
+    }
+
+    /// Creates a new [`RegexFeedback`], matching `regex` against the stderr captured by `observer`
+    #[must_use]
+    pub fn with_stderr(observer: &StdErrObserver, regex: Regex) -> Self {
+        Self::new(observer.name(), OutputStream::Stderr, regex)
+    }
+
+    /// If `negate` is set, inputs are interesting if their output does not match the pattern
+    #[must_use]
+    pub fn negate(mut self, negate: bool) -> Self {
+        self.negate = negate;
+        self
+    }
+
+    /// The output the pattern is matched against
+    #[must_use]
+    pub fn stream(&self) -> OutputStream {
+        self.stream
+    }
+}
+
+impl<S> Feedback<S> for RegexFeedback
+where
+    S: State,
+{
+    #[allow(clippy::wrong_self_convention)]
+    fn is_interesting<EM, OT>(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _input: &S::Input,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let not_found = || {
+            Error::illegal_argument(format!(
+                "RegexFeedback: observer {} not found",
+                self.observer_name
+            ))
+        };
+        let output = match self.stream {
+            OutputStream::Stdout => observers
+                .match_name::<StdOutObserver>(&self.observer_name)
+                .ok_or_else(not_found)?
+                .stdout
+                .as_deref(),
+            OutputStream::Stderr => observers
+                .match_name::<StdErrObserver>(&self.observer_name)
+                .ok_or_else(not_found)?
+                .stderr
+                .as_deref(),
+        };
+        let output = String::from_utf8_lossy(output.unwrap_or_default());
+
+        let matched = self.regex.find(&output).map(|m| m.as_str().to_string());
+        if matched.is_some() == self.negate {
+            self.last_match = None;
+            return Ok(false);
+        }
+        self.last_match = Some(RegexMatchMetadata {
+            pattern: self.regex.as_str().to_string(),
+            matched,
+        });
+        Ok(true)
+    }
+
+    fn append_metadata<OT>(
+        &mut self,
+        _state: &mut S,
+        _observers: &OT,
+        testcase: &mut Testcase<S::Input>,
+    ) -> Result<(), Error>
+    where
+        OT: ObserversTuple<S>,
+    {
+        if let Some(metadata) = self.last_match.take() {
+            testcase.metadata_map_mut().insert(metadata);
+        }
+        Ok(())
+    }
+
+    fn discard_metadata(&mut self, _state: &mut S, _input: &S::Input) -> Result<(), Error> {
+        self.last_match = None;
+        Ok(())
+    }
+}
+
+impl Named for RegexFeedback {
+    #[inline]
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl HasObserverName for RegexFeedback {
+    #[inline]
+    fn observer_name(&self) -> &str {
+        &self.observer_name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::tuples::tuple_list;
+    use regex::Regex;
+
+    use super::{RegexFeedback, RegexMatchMetadata};
+    use crate::{
+        corpus::Testcase,
+        events::NopEventManager,
+        executors::ExitKind,
+        feedbacks::Feedback,
+        inputs::BytesInput,
+        observers::{StdErrObserver, StdOutObserver},
+        state::{HasMetadata, NopState},
+    };
+
+    #[test]
+    fn test_regex_feedback() {
+        let mut state = NopState::<BytesInput>::new();
+        let mut mgr = NopEventManager::new();
+        let input = BytesInput::new(vec![0]);
+        let mut observers = tuple_list!(
+            StdOutObserver::new("stdout".into()),
+            StdErrObserver::new("stderr".into())
+        );
+        let regex = Regex::new("heap corruption at 0x[0-9a-f]+").unwrap();
+        let mut feedback = RegexFeedback::with_stderr(&observers.1 .0, regex.clone());
+        let mut negated = RegexFeedback::with_stderr(&observers.1 .0, regex.clone()).negate(true);
+
+        // (stderr, matches)
+        for (stderr, matches) in [
+            (None, false),
+            (Some(&b"all good\n"[..]), false),
+            (Some(b"\xff error: heap corruption at 0x1337\n"), true),
+        ] {
+            observers.1 .0.stderr = stderr.map(<[u8]>::to_vec);
+            // the stdout is not looked at
+            observers.0.stdout = Some(b"heap corruption at 0x1".to_vec());
+            assert_eq!(
+                feedback
+                    .is_interesting(&mut state, &mut mgr, &input, &observers, &ExitKind::Ok)
+                    .unwrap(),
+                matches
+            );
+            assert_eq!(
+                negated
+                    .is_interesting(&mut state, &mut mgr, &input, &observers, &ExitKind::Ok)
+                    .unwrap(),
+                !matches
+            );
+        }
+
+        // the match of the last execution is attached to the testcase
+        let mut testcase = Testcase::new(input.clone());
+        feedback
+            .append_metadata(&mut state, &observers, &mut testcase)
+            .unwrap();
+        let metadata = testcase.metadata::<RegexMatchMetadata>().unwrap();
+        assert_eq!(metadata.pattern, regex.as_str());
+        assert_eq!(
+            metadata.matched.as_deref(),
+            Some("heap corruption at 0x1337")
+        );
+
+        // an observer of the other stream is not picked up
+        let mut stdout_only = tuple_list!(StdOutObserver::new("stderr".into()));
+        stdout_only.0.stdout = Some(b"heap corruption at 0x1".to_vec());
+        assert!(feedback
+            .is_interesting(&mut state, &mut mgr, &input, &stdout_only, &ExitKind::Ok)
+            .is_err());
+    }
+}
@@ -0,0 +1,110 @@
+//! The [`NewSyscallFeedback`] keeps inputs that issue syscalls never seen before, as reported by a [`SyscallObserver`]
+
+use alloc::string::{String, ToString};
+
+use hashbrown::HashSet;
+use libafl_bolts::Named;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    events::EventFirer,
+    executors::ExitKind,
+    feedbacks::{Feedback, HasObserverName},
+    inputs::UsesInput,
+    observers::{ObserversTuple, SyscallObserver},
+    state::{HasNamedMetadata, State},
+    Error,
+};
+
+/// The prefix of the metadata names
+pub const NEWSYSCALLFEEDBACK_PREFIX: &str = "newsyscallfeedback_metadata_";
+
+/// The state of [`NewSyscallFeedback`]
+#[derive(Default, Serialize, Deserialize, Clone, Debug)]
+#[allow(clippy::unsafe_derive_deserialize)]
+pub struct NewSyscallFeedbackMetadata {
+    /// All syscall numbers seen so far
+    pub syscalls: HashSet<i64>,
+}
+
+libafl_bolts::impl_serdeany!(NewSyscallFeedbackMetadata);
+
+impl NewSyscallFeedbackMetadata {
+    /// Create a new [`NewSyscallFeedbackMetadata`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// A [`NewSyscallFeedback`] considers an input interesting if its execution entered at least one syscall
+/// that no previous execution entered.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NewSyscallFeedback {
+    name: String,
+    observer_name: String,
+}
+
+impl<S> Feedback<S> for NewSyscallFeedback
+where
+    S: State + HasNamedMetadata,
+{
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error> {
+        state.add_named_metadata(NewSyscallFeedbackMetadata::new(), &self.name);
+        Ok(())
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    fn is_interesting<EM, OT>(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _input: &<S as UsesInput>::Input,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .match_name::<SyscallObserver>(&self.observer_name)
+            .expect("A NewSyscallFeedback needs a SyscallObserver");
+
+        let meta = state
+            .named_metadata_map_mut()
+            .get_mut::<NewSyscallFeedbackMetadata>(&self.name)
+            .unwrap();
+
+        let mut interesting = false;
+        for nr in observer.syscalls().keys() {
+            interesting |= meta.syscalls.insert(*nr);
+        }
+        Ok(interesting)
+    }
+}
+
+impl Named for NewSyscallFeedback {
+    #[inline]
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl HasObserverName for NewSyscallFeedback {
+    #[inline]
+    fn observer_name(&self) -> &str {
+        &self.observer_name
+    }
+}
+
+impl NewSyscallFeedback {
+    /// Returns a new [`NewSyscallFeedback`] for the given [`SyscallObserver`].
+    #[must_use]
+    pub fn new(observer: &SyscallObserver) -> Self {
+        Self {
+            name: NEWSYSCALLFEEDBACK_PREFIX.to_string() + observer.name(),
+            observer_name: observer.name().to_string(),
+        }
+    }
+}
@@ -0,0 +1,281 @@
+//! The [`TaintFeedback`] keeps the inputs whose comparison operands changed since their previous execution.
+//!
+//! The operands are read from a [`CmpObserver`], as logged by `CmpLog`.
+//! The feedback remembers the operands of the last execution of each input, by the hash of its target bytes.
+//! If the same input is executed again, e.g. by a stage or another fuzzer instance, and any comparison site
+//! reached by both executions saw different operands, the operands depend on more than the input bytes,
+//! and the input is interesting. Each new corpus entry stores its operands in its [`TaintOperandsMetadata`].
+//!
+//! Combine it with a coverage feedback, e.g. `feedback_or!(MaxMapFeedback::new(..), TaintFeedback::new(..))`.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{
+    hash::{BuildHasher, Hasher},
+    marker::PhantomData,
+};
+
+use ahash::RandomState;
+use hashbrown::HashMap;
+use libafl_bolts::{hash_std, AsSlice, Named};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::Testcase,
+    events::EventFirer,
+    executors::ExitKind,
+    feedbacks::{Feedback, HasObserverName},
+    inputs::{HasTargetBytes, UsesInput},
+    observers::{cmp::CmpValuesMetadata, CmpMap, CmpObserver, CmpValues, ObserversTuple},
+    state::{HasMetadata, State},
+    Error,
+};
+
+/// The number of inputs whose operands a [`TaintFeedback`] remembers, before it forgets all of them
+pub const MAX_REMEMBERED_INPUTS: usize = 1 << 16;
+
+/// The operands of the comparison sites, logged by the execution of a testcase
+#[derive(Default, Serialize, Deserialize, Clone, Debug)]
+#[allow(clippy::unsafe_derive_deserialize)]
+pub struct TaintOperandsMetadata {
+    /// A hash of all operands logged for each cmp site, `0` if the site was not reached
+    pub operands: Vec<u64>,
+}
+
+libafl_bolts::impl_serdeany!(TaintOperandsMetadata);
+
+impl TaintOperandsMetadata {
+    /// The cmp sites reached by both executions, whose operands differ
+    #[must_use]
+    pub fn changed_sites(&self, other: &Self) -> Vec<usize> {
+        self.operands
+            .iter()
+            .zip(&other.operands)
+            .enumerate()
+            .filter(|(_, (a, b))| **a != 0 && **b != 0 && a != b)
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+}
+
+/// Hashes all operands logged for the cmp site at `idx`, in the order of their executions
+fn hash_site_operands<CM: CmpMap>(cmp_map: &CM, idx: usize) -> u64 {
+    let executions = cmp_map.usable_executions_for(idx);
+    if executions == 0 {
+        return 0;
+    }
+    let mut hasher = RandomState::with_seeds(0, 0, 0, 0).build_hasher();
+    for execution in 0..executions {
+        match cmp_map.values_of(idx, execution) {
+            Some(CmpValues::Bytes((v0, v1))) => {
+                hasher.write(&v0);
+                hasher.write(&v1);
+            }
+            Some(values) => {
+                let (v0, v1) = values.to_u64_tuple().unwrap();
+                hasher.write_u64(v0);
+                hasher.write_u64(v1);
+            }
+            None => {}
+        }
+    }
+    // reserve 0 for the sites not reached
+    hasher.finish().max(1)
+}
+
+/// The operands of the first `usable_count` cmp sites of the last execution
+fn taint_operands<CM: CmpMap>(cmp_map: &CM, usable_count: usize) -> TaintOperandsMetadata {
+    TaintOperandsMetadata {
+        operands: (0..usable_count)
+            .map(|idx| hash_site_operands(cmp_map, idx))
+            .collect(),
+    }
+}
+
+/// A [`TaintFeedback`] reports an input as interesting if at least one of the comparisons reached by it
+/// and by the previous execution of the same input logged different operands.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TaintFeedback<CM, O> {
+    observer_name: String,
+    /// The operands of the last execution of each input, by the hash of its target bytes
+    last_operands: HashMap<u64, TaintOperandsMetadata>,
+    phantom: PhantomData<(CM, O)>,
+}
+
+impl<'a, CM, O, S> Feedback<S> for TaintFeedback<CM, O>
+where
+    CM: CmpMap,
+    O: CmpObserver<'a, CM, S, CmpValuesMetadata>,
+    S: State,
+    S::Input: HasTargetBytes,
+{
+    #[allow(clippy::wrong_self_convention)]
+    fn is_interesting<EM, OT>(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        input: &<S as UsesInput>::Input,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .match_name::<O>(&self.observer_name)
+            .ok_or_else(|| Error::key_not_found("CmpObserver not found"))?;
+        let operands = taint_operands(observer.cmp_map(), observer.usable_count());
+
+        let hash = hash_std(input.target_bytes().as_slice());
+        let changed = self
+            .last_operands
+            .get(&hash)
+            .map_or(false, |last| !operands.changed_sites(last).is_empty());
+        if self.last_operands.len() >= MAX_REMEMBERED_INPUTS
+            && !self.last_operands.contains_key(&hash)
+        {
+            self.last_operands.clear();
+        }
+        self.last_operands.insert(hash, operands);
+        Ok(changed)
+    }
+
+    fn append_metadata<OT>(
+        &mut self,
+        _state: &mut S,
+        observers: &OT,
+        testcase: &mut Testcase<S::Input>,
+    ) -> Result<(), Error>
+    where
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .match_name::<O>(&self.observer_name)
+            .ok_or_else(|| Error::key_not_found("CmpObserver not found"))?;
+        testcase.add_metadata(taint_operands(observer.cmp_map(), observer.usable_count()));
+        Ok(())
+    }
+}
+
+impl<CM, O> Named for TaintFeedback<CM, O> {
+    #[inline]
+    fn name(&self) -> &str {
+        "TaintFeedback"
+    }
+}
+
+impl<CM, O> HasObserverName for TaintFeedback<CM, O> {
+    #[inline]
+    fn observer_name(&self) -> &str {
+        &self.observer_name
+    }
+}
+
+impl<CM, O> TaintFeedback<CM, O>
+where
+    O: Named,
+{
+    /// Creates a new [`TaintFeedback`] for the given [`CmpObserver`]
+    #[must_use]
+    pub fn new(observer: &O) -> Self {
+        Self {
+            observer_name: observer.name().to_string(),
+            last_operands: HashMap::new(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{boxed::Box, vec::Vec};
+
+    use libafl_bolts::{ownedref::OwnedRefMut, rands::StdRand, tuples::tuple_list};
+    use serde::{Deserialize, Serialize};
+
+    use super::TaintFeedback;
+    use crate::{
+        corpus::InMemoryCorpus,
+        events::NopEventManager,
+        executors::ExitKind,
+        feedbacks::Feedback,
+        inputs::BytesInput,
+        observers::{cmp::CmpValuesMetadata, CmpMap, CmpObserver, CmpValues, StdCmpObserver},
+        state::{test::test_std_state, StdState},
+        Error,
+    };
+
+    /// A [`CmpMap`] logging one execution of each cmp site
+    #[derive(Debug, Default, Serialize, Deserialize)]
+    struct TestCmpMap {
+        values: Vec<Option<(u64, u64)>>,
+    }
+
+    impl CmpMap for TestCmpMap {
+        fn len(&self) -> usize {
+            self.values.len()
+        }
+
+        fn executions_for(&self, idx: usize) -> usize {
+            usize::from(self.values[idx].is_some())
+        }
+
+        fn usable_executions_for(&self, idx: usize) -> usize {
+            self.executions_for(idx)
+        }
+
+        fn values_of(&self, idx: usize, _execution: usize) -> Option<CmpValues> {
+            self.values[idx].map(CmpValues::U64)
+        }
+
+        fn reset(&mut self) -> Result<(), Error> {
+            self.values.iter_mut().for_each(|value| *value = None);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_taint_feedback() {
+        type TestState =
+            StdState<BytesInput, InMemoryCorpus<BytesInput>, StdRand, InMemoryCorpus<BytesInput>>;
+
+        let mut state: TestState = test_std_state();
+        let mut mgr = NopEventManager::new();
+
+        let observer = StdCmpObserver::<_, TestState, CmpValuesMetadata>::new(
+            "cmp",
+            OwnedRefMut::Owned(Box::new(TestCmpMap {
+                values: vec![Some((1, 2)), None],
+            })),
+            false,
+        );
+        let mut feedback = TaintFeedback::<TestCmpMap, _>::new(&observer);
+        let mut observers = tuple_list!(observer);
+        let input = BytesInput::new(b"abc".to_vec());
+        let other = BytesInput::new(b"abd".to_vec());
+
+        let mut run = |observers: &_, input: &BytesInput| {
+            feedback
+                .is_interesting(&mut state, &mut mgr, input, observers, &ExitKind::Ok)
+                .unwrap()
+        };
+
+        // the first execution of an input has nothing to compare against
+        assert!(!run(&observers, &input));
+        assert!(!run(&observers, &input));
+
+        // the operands only changed for another input
+        observers.0.cmp_map_mut().values[0] = Some((1, 3));
+        assert!(!run(&observers, &other));
+
+        // the same input saw different operands
+        assert!(run(&observers, &input));
+
+        // a site reached by only one execution is no change
+        observers.0.cmp_map_mut().values[1] = Some((5, 6));
+        assert!(!run(&observers, &input));
+    }
+}
@@ -0,0 +1,225 @@
+//! A builder for the [`StdFuzzer`], checking at compile time that all of its components are set.
+//!
+//! Each component is a type parameter of the [`StdFuzzerBuilder`], starting out as a marker type
+//! ([`NoCorpus`], [`NoScheduler`], [`NoFeedback`], [`NoObjective`], [`NoExecutor`]).
+//! A component can only be set while it is unset, and [`StdFuzzerBuilder::build`] only exists once none of them are markers anymore.
+//! Forgetting a component then fails at the call to `build`, instead of deep in the generic bounds of the fuzzer.
+//!
+//! The corpus and the executor are not part of the fuzzer: the corpus lives in the state,
+//! and the executor is passed to the fuzzing loop.
+//! [`StdFuzzerBuilder::build`] checks that they fit the fuzzer, and hands them back next to it.
+
+use crate::{
+    corpus::Corpus,
+    executors::HasObservers,
+    feedbacks::Feedback,
+    fuzzer::StdFuzzer,
+    inputs::UsesInput,
+    schedulers::Scheduler,
+    state::{HasCorpus, HasExecutions},
+};
+
+/// The corpus of a [`StdFuzzerBuilder`] is not set yet
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoCorpus;
+
+/// The scheduler of a [`StdFuzzerBuilder`] is not set yet
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoScheduler;
+
+/// The feedback of a [`StdFuzzerBuilder`] is not set yet
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoFeedback;
+
+/// The objective of a [`StdFuzzerBuilder`] is not set yet
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoObjective;
+
+/// The executor of a [`StdFuzzerBuilder`] is not set yet
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoExecutor;
+
+/// Builds a [`StdFuzzer`] step by step, see the [module docs](self).
+///
+/// ```rust,ignore
+/// let (fuzzer, corpus, executor) = StdFuzzerBuilder::new()
+///     .with_corpus(InMemoryCorpus::new())
+///     .with_scheduler(QueueScheduler::new())
+///     .with_feedback(feedback)
+///     .with_objective(objective)
+///     .with_executor(executor)
+///     .build();
+/// ```
+#[derive(Debug)]
+pub struct StdFuzzerBuilder<C, CS, F, OF, E> {
+    corpus: C,
+    scheduler: CS,
+    feedback: F,
+    objective: OF,
+    executor: E,
+}
+
+impl StdFuzzerBuilder<NoCorpus, NoScheduler, NoFeedback, NoObjective, NoExecutor> {
+    /// Creates a new [`StdFuzzerBuilder`] without any components
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            corpus: NoCorpus,
+            scheduler: NoScheduler,
+            feedback: NoFeedback,
+            objective: NoObjective,
+            executor: NoExecutor,
+        }
+    }
+}
+
+impl Default for StdFuzzerBuilder<NoCorpus, NoScheduler, NoFeedback, NoObjective, NoExecutor> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<CS, F, OF, E> StdFuzzerBuilder<NoCorpus, CS, F, OF, E> {
+    /// Sets the [`Corpus`] the state of the fuzzer will be created with
+    #[must_use]
+    pub fn with_corpus<C>(self, corpus: C) -> StdFuzzerBuilder<C, CS, F, OF, E>
+    where
+        C: Corpus,
+    {
+        StdFuzzerBuilder {
+            corpus,
+            scheduler: self.scheduler,
+            feedback: self.feedback,
+            objective: self.objective,
+            executor: self.executor,
+        }
+    }
+}
+
+impl<C, F, OF, E> StdFuzzerBuilder<C, NoScheduler, F, OF, E> {
+    /// Sets the [`Scheduler`] picking the corpus entries to fuzz
+    #[must_use]
+    pub fn with_scheduler<CS>(self, scheduler: CS) -> StdFuzzerBuilder<C, CS, F, OF, E>
+    where
+        CS: Scheduler,
+        CS::State: HasCorpus,
+    {
+        StdFuzzerBuilder {
+            corpus: self.corpus,
+            scheduler,
+            feedback: self.feedback,
+            objective: self.objective,
+            executor: self.executor,
+        }
+    }
+}
+
+impl<C, CS, OF, E> StdFuzzerBuilder<C, CS, NoFeedback, OF, E> {
+    /// Sets the [`Feedback`] deciding which inputs are added to the corpus.
+    /// Combine multiple feedbacks with `feedback_or!` or `feedback_and!`.
+    #[must_use]
+    pub fn with_feedback<F>(self, feedback: F) -> StdFuzzerBuilder<C, CS, F, OF, E> {
+        StdFuzzerBuilder {
+            corpus: self.corpus,
+            scheduler: self.scheduler,
+            feedback,
+            objective: self.objective,
+            executor: self.executor,
+        }
+    }
+}
+
+impl<C, CS, F, E> StdFuzzerBuilder<C, CS, F, NoObjective, E> {
+    /// Sets the objective [`Feedback`] deciding which inputs are solutions, e.g. crashes
+    #[must_use]
+    pub fn with_objective<OF>(self, objective: OF) -> StdFuzzerBuilder<C, CS, F, OF, E> {
+        StdFuzzerBuilder {
+            corpus: self.corpus,
+            scheduler: self.scheduler,
+            feedback: self.feedback,
+            objective,
+            executor: self.executor,
+        }
+    }
+}
+
+impl<C, CS, F, OF> StdFuzzerBuilder<C, CS, F, OF, NoExecutor> {
+    /// Sets the executor running the target, together with its observers
+    #[must_use]
+    pub fn with_executor<E>(self, executor: E) -> StdFuzzerBuilder<C, CS, F, OF, E>
+    where
+        E: HasObservers,
+    {
+        StdFuzzerBuilder {
+            corpus: self.corpus,
+            scheduler: self.scheduler,
+            feedback: self.feedback,
+            objective: self.objective,
+            executor,
+        }
+    }
+}
+
+impl<C, CS, F, OF, E> StdFuzzerBuilder<C, CS, F, OF, E>
+where
+    C: Corpus<Input = <CS::State as UsesInput>::Input>,
+    CS: Scheduler,
+    F: Feedback<CS::State>,
+    OF: Feedback<CS::State>,
+    E: HasObservers<State = CS::State>,
+    CS::State: UsesInput + HasExecutions + HasCorpus,
+{
+    /// Builds the [`StdFuzzer`], once all components are set.
+    /// Returns the corpus to create the state with and the executor to fuzz with next to it.
+    #[must_use]
+    #[allow(clippy::type_complexity)]
+    pub fn build(self) -> (StdFuzzer<CS, F, OF, E::Observers>, C, E) {
+        (
+            StdFuzzer::new(self.scheduler, self.feedback, self.objective),
+            self.corpus,
+            self.executor,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StdFuzzerBuilder;
+    use crate::{
+        corpus::{Corpus, InMemoryCorpus},
+        events::NopEventManager,
+        executors::{test::NopExecutor, WithObservers},
+        feedbacks::ConstFeedback,
+        fuzzer::Evaluator,
+        inputs::BytesInput,
+        schedulers::QueueScheduler,
+        state::{test::test_std_state, HasCorpus},
+    };
+
+    #[test]
+    fn test_fuzzer_builder() {
+        // the components can be set in any order
+        let (mut fuzzer, corpus, mut executor) = StdFuzzerBuilder::new()
+            .with_executor(WithObservers::new(NopExecutor::new(), ()))
+            .with_objective(ConstFeedback::new(false))
+            .with_corpus(InMemoryCorpus::<BytesInput>::new())
+            .with_scheduler(QueueScheduler::new())
+            .with_feedback(ConstFeedback::new(true))
+            .build();
+
+        // the state holds the corpus built with the fuzzer
+        let mut state = test_std_state::<BytesInput>();
+        *state.corpus_mut() = corpus;
+        let mut mgr = NopEventManager::new();
+
+        fuzzer
+            .evaluate_input(
+                &mut state,
+                &mut executor,
+                &mut mgr,
+                BytesInput::new(vec![1]),
+            )
+            .unwrap();
+        assert_eq!(state.corpus().count(), 1);
+    }
+}
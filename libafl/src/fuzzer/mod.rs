@@ -7,7 +7,7 @@ use libafl_bolts::current_time;
 use serde::{de::DeserializeOwned, Serialize};
 
 use crate::{
-    corpus::{Corpus, CorpusId, HasCurrentCorpusIdx, HasTestcase, Testcase},
+    corpus::{is_refused_add, Corpus, CorpusId, HasCurrentCorpusIdx, HasTestcase, Testcase},
     events::{Event, EventConfig, EventFirer, EventProcessor, ProgressReporter},
     executors::{Executor, ExitKind, HasObservers},
     feedbacks::Feedback,
@@ -26,6 +26,9 @@ use crate::{
 #[cfg(feature = "introspection")]
 use crate::{monitors::PerfFeature, state::HasClientPerfMonitor};
 
+pub mod builder;
+pub use builder::StdFuzzerBuilder;
+
 /// Send a monitor update all 15 (or more) seconds
 const STATS_TIMEOUT_DEFAULT: Duration = Duration::from_secs(15);
 
@@ -132,9 +135,28 @@ where
         send_events: bool,
     ) -> Result<(ExecuteInputResult, Option<CorpusId>), Error>;
 
+    /// Runs the input, and evaluates it on the given `observers` instead of the observers of the executor:
+    /// runs the observers around the execution, and evaluates the execution like [`Evaluator::evaluate_input`].
+    /// Returns if it is interesting and (optionally) the index of the new [`crate::corpus::Testcase`].
+    ///
+    /// Useful for observers that can not be owned by the executor, e.g. observing a map of another process.
+    /// The observers have to be mutable to run them, so the ones of the executor can not be passed here,
+    /// use [`Evaluator::evaluate_input`] for those.
+    fn evaluate_input_with_observer_map(
+        &mut self,
+        state: &mut Self::State,
+        executor: &mut E,
+        manager: &mut EM,
+        input: <Self::State as UsesInput>::Input,
+        observers: &mut E::Observers,
+    ) -> Result<(ExecuteInputResult, Option<CorpusId>), Error>
+    where
+        E: HasObservers;
+
     /// Runs the input and triggers observers and feedback.
     /// Adds an input, to the corpus even if it's not considered `interesting` by the `feedback`.
     /// Returns the `index` of the new testcase in the corpus.
+    /// Fails with the error of the corpus if it refuses the testcase, see [`crate::corpus::is_refused_add`].
     /// Usually, you want to use [`Evaluator::evaluate_input`], unless you know what you are doing.
     fn add_input(
         &mut self,
@@ -382,7 +404,14 @@ where
                 let mut testcase = Testcase::with_executions(input.clone(), *state.executions());
                 self.feedback_mut()
                     .append_metadata(state, observers, &mut testcase)?;
-                let idx = state.corpus_mut().add(testcase)?;
+                let idx = match state.corpus_mut().add(testcase) {
+                    Ok(idx) => idx,
+                    Err(err) if is_refused_add(&err) => {
+                        log::debug!("The corpus refused the testcase: {err}");
+                        return Ok((ExecuteInputResult::None, None));
+                    }
+                    Err(err) => return Err(err),
+                };
                 self.scheduler_mut().on_add(state, idx)?;
 
                 if send_events {
@@ -420,7 +449,14 @@ where
                 testcase.set_parent_id_optional(*state.corpus().current());
                 self.objective_mut()
                     .append_metadata(state, observers, &mut testcase)?;
-                state.solutions_mut().add(testcase)?;
+                match state.solutions_mut().add(testcase) {
+                    Ok(_) => {}
+                    Err(err) if is_refused_add(&err) => {
+                        log::debug!("The solutions refused the testcase: {err}");
+                        return Ok((res, None));
+                    }
+                    Err(err) => return Err(err),
+                }
 
                 if send_events {
                     manager.fire(
@@ -462,9 +498,7 @@ where
         let exit_kind = self.execute_input(state, executor, manager, &input)?;
         let observers = executor.observers();
 
-        self.scheduler.on_evaluation(state, &input, observers)?;
-
-        self.process_execution(state, manager, input, observers, &exit_kind, send_events)
+        self.evaluate_execution(state, manager, input, observers, &exit_kind, send_events)
     }
 }
 
@@ -491,6 +525,30 @@ where
         self.evaluate_input_with_observers(state, executor, manager, input, send_events)
     }
 
+    /// Runs the input on the given observers, and evaluates the execution like [`Evaluator::evaluate_input`]
+    fn evaluate_input_with_observer_map(
+        &mut self,
+        state: &mut Self::State,
+        executor: &mut E,
+        manager: &mut EM,
+        input: <Self::State as UsesInput>::Input,
+        observers: &mut OT,
+    ) -> Result<(ExecuteInputResult, Option<CorpusId>), Error> {
+        start_timer!(state);
+        observers.pre_exec_all(state, &input)?;
+        mark_feature_time!(state, PerfFeature::PreExecObservers);
+
+        start_timer!(state);
+        let exit_kind = executor.run_target(self, state, manager, &input)?;
+        mark_feature_time!(state, PerfFeature::TargetExecution);
+
+        start_timer!(state);
+        observers.post_exec_all(state, &input, &exit_kind)?;
+        mark_feature_time!(state, PerfFeature::PostExecObservers);
+
+        self.evaluate_execution(state, manager, input, observers, &exit_kind, true)
+    }
+
     /// Adds an input, even if it's not considered `interesting` by any of the executors
     fn add_input(
         &mut self,
@@ -662,6 +720,26 @@ where
         }
     }
 
+    /// Notifies the scheduler of an execution, and processes its result
+    fn evaluate_execution<EM, OM>(
+        &mut self,
+        state: &mut <Self as UsesState>::State,
+        manager: &mut EM,
+        input: <<Self as UsesState>::State as UsesInput>::Input,
+        observers: &OM,
+        exit_kind: &ExitKind,
+        send_events: bool,
+    ) -> Result<(ExecuteInputResult, Option<CorpusId>), Error>
+    where
+        Self: ExecutionProcessor<OM, State = CS::State>,
+        EM: EventFirer<State = <Self as UsesState>::State>,
+        OM: ObserversTuple<<Self as UsesState>::State>,
+    {
+        self.scheduler.on_evaluation(state, &input, observers)?;
+
+        self.process_execution(state, manager, input, observers, exit_kind, send_events)
+    }
+
     /// Runs the input and triggers observers and feedback
     pub fn execute_input<E, EM>(
         &mut self,
@@ -751,10 +829,19 @@ pub mod test {
     use libafl_bolts::Error;
 
     use crate::{
-        corpus::CorpusId,
-        events::ProgressReporter,
+        corpus::{Corpus, CorpusId, HasTestcase},
+        events::{NopEventManager, ProgressReporter},
+        executors::{test::NopExecutor, WithObservers},
+        feedbacks::ConstFeedback,
+        fuzzer::{Evaluator, ExecuteInputResult, HasScheduler, StdFuzzer},
+        inputs::{BytesInput, UsesInput},
+        observers::ObserversTuple,
+        schedulers::{QueueScheduler, Scheduler},
         stages::{HasCurrentStage, StagesTuple},
-        state::{HasExecutions, HasLastReportTime, HasMetadata, State, UsesState},
+        state::{
+            test::test_std_state, HasCorpus, HasExecutions, HasLastReportTime, HasMetadata, State,
+            UsesState,
+        },
         Fuzzer,
     };
 
@@ -802,6 +889,100 @@ pub mod test {
             unimplemented!()
         }
     }
+
+    /// A [`QueueScheduler`] counting the evaluations it is notified of
+    #[derive(Debug)]
+    struct CountingScheduler<S> {
+        inner: QueueScheduler<S>,
+        evaluations: usize,
+    }
+
+    impl<S> UsesState for CountingScheduler<S>
+    where
+        S: State,
+    {
+        type State = S;
+    }
+
+    impl<S> Scheduler for CountingScheduler<S>
+    where
+        S: HasCorpus + HasTestcase + State,
+    {
+        fn on_add(&mut self, state: &mut S, idx: CorpusId) -> Result<(), Error> {
+            self.inner.on_add(state, idx)
+        }
+
+        fn on_evaluation<OT>(
+            &mut self,
+            _state: &mut S,
+            _input: &<S as UsesInput>::Input,
+            _observers: &OT,
+        ) -> Result<(), Error>
+        where
+            OT: ObserversTuple<S>,
+        {
+            self.evaluations += 1;
+            Ok(())
+        }
+
+        fn next(&mut self, state: &mut S) -> Result<CorpusId, Error> {
+            self.inner.next(state)
+        }
+    }
+
+    #[test]
+    fn test_evaluate_input_with_observer_map() {
+        let mut state = test_std_state::<BytesInput>();
+        let mut fuzzer = StdFuzzer::new(
+            CountingScheduler {
+                inner: QueueScheduler::new(),
+                evaluations: 0,
+            },
+            ConstFeedback::new(true),
+            ConstFeedback::new(false),
+        );
+        let mut executor = WithObservers::new(NopExecutor::new(), ());
+        let mut mgr = NopEventManager::new();
+
+        let (res, id) = fuzzer
+            .evaluate_input(
+                &mut state,
+                &mut executor,
+                &mut mgr,
+                BytesInput::new(vec![1]),
+            )
+            .unwrap();
+        assert_eq!(res, ExecuteInputResult::Corpus);
+        assert_eq!(id, Some(CorpusId(0)));
+        assert_eq!(fuzzer.scheduler().evaluations, 1);
+
+        // The execution on the observer map is evaluated the same way, including the scheduler
+        let (res, id) = fuzzer
+            .evaluate_input_with_observer_map(
+                &mut state,
+                &mut executor,
+                &mut mgr,
+                BytesInput::new(vec![2]),
+                &mut (),
+            )
+            .unwrap();
+        assert_eq!(res, ExecuteInputResult::Corpus);
+        assert_eq!(id, Some(CorpusId(1)));
+        assert_eq!(state.corpus().count(), 2);
+        assert_eq!(*state.executions(), 2);
+        assert_eq!(fuzzer.scheduler().evaluations, 2);
+
+        assert!(fuzzer
+            .evaluate_input_with_observer_map(
+                &mut state,
+                &mut executor,
+                &mut mgr,
+                BytesInput::new(vec![]),
+                &mut (),
+            )
+            .is_err());
+        assert_eq!(state.corpus().count(), 2);
+    }
 }
 
 #[cfg(feature = "python")]
@@ -0,0 +1,49 @@
+//! Generator for [`GrammarInput`]s
+use core::marker::PhantomData;
+
+use crate::{
+    generators::Generator,
+    inputs::{Grammar, GrammarInput},
+    state::HasRand,
+    Error,
+};
+
+#[derive(Clone, Debug)]
+/// Generates random parse trees from a [`Grammar`]
+pub struct GrammarGenerator<'a, S>
+where
+    S: HasRand,
+{
+    grammar: &'a Grammar,
+    max_depth: usize,
+    phantom: PhantomData<S>,
+}
+
+impl<'a, S> Generator<GrammarInput, S> for GrammarGenerator<'a, S>
+where
+    S: HasRand,
+{
+    fn generate(&mut self, state: &mut S) -> Result<GrammarInput, Error> {
+        Ok(GrammarInput::new(self.grammar.generate(
+            state.rand_mut(),
+            self.grammar.start(),
+            self.max_depth,
+        )))
+    }
+}
+
+impl<'a, S> GrammarGenerator<'a, S>
+where
+    S: HasRand,
+{
+    /// Returns a new [`GrammarGenerator`].
+    /// Below `max_depth`, the trees only grow as much as needed to terminate.
+    #[must_use]
+    pub fn new(grammar: &'a Grammar, max_depth: usize) -> Self {
+        Self {
+            grammar,
+            max_depth,
+            phantom: PhantomData,
+        }
+    }
+}
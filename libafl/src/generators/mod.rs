@@ -14,6 +14,9 @@ use crate::{
 pub mod gramatron;
 pub use gramatron::*;
 
+pub mod grammar;
+pub use grammar::GrammarGenerator;
+
 #[cfg(feature = "nautilus")]
 pub mod nautilus;
 #[cfg(feature = "nautilus")]
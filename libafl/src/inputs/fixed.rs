@@ -0,0 +1,229 @@
+//! The [`FixedSizeInput`] is an input of exactly `N` bytes, for targets that take a fixed-size buffer,
+//! like the fields of network protocol headers, or the registers of a hardware interface.
+//!
+//! The byte-level mutators working on a [`HasBytesSlice`], such as the [`crate::mutators::ByteFlipMutator`],
+//! the [`crate::mutators::ByteRandMutator`], and the [`crate::mutators::SpliceMutator`], mutate it in place,
+//! never changing its size.
+
+use alloc::{string::String, vec::Vec};
+use core::{
+    fmt::{self, Formatter},
+    hash::{BuildHasher, Hasher},
+};
+#[cfg(feature = "std")]
+use std::{fs::File, io::Read, path::Path};
+
+use ahash::RandomState;
+#[cfg(feature = "std")]
+use libafl_bolts::{fs::write_file_atomic, Error};
+use libafl_bolts::{ownedref::OwnedSlice, HasLen};
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::inputs::{BytesInput, HasBytesSlice, HasBytesVec, HasTargetBytes, Input};
+
+/// An input of exactly `N` bytes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FixedSizeInput<const N: usize>(pub [u8; N]);
+
+impl<const N: usize> FixedSizeInput<N> {
+    /// Creates a new [`FixedSizeInput`] with the given bytes
+    #[must_use]
+    pub const fn new(bytes: [u8; N]) -> Self {
+        Self(bytes)
+    }
+
+    /// Creates a new [`FixedSizeInput`] from `bytes`, truncated or zero-padded to `N` bytes
+    #[must_use]
+    pub fn from_slice(bytes: &[u8]) -> Self {
+        let mut array = [0; N];
+        let len = bytes.len().min(N);
+        array[..len].copy_from_slice(&bytes[..len]);
+        Self(array)
+    }
+}
+
+impl<const N: usize> Default for FixedSizeInput<N> {
+    fn default() -> Self {
+        Self([0; N])
+    }
+}
+
+impl<const N: usize> Input for FixedSizeInput<N> {
+    #[cfg(feature = "std")]
+    /// Write this input to the file
+    fn to_file<P>(&self, path: P) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        write_file_atomic(path, &self.0)
+    }
+
+    /// Load the content of this input from a file, truncated or zero-padded to `N` bytes
+    #[cfg(feature = "std")]
+    fn from_file<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut file = File::open(path)?;
+        let mut bytes: Vec<u8> = vec![];
+        file.read_to_end(&mut bytes)?;
+        Ok(Self::from_slice(&bytes))
+    }
+
+    /// Generate a name for this input
+    fn generate_name(&self, _idx: usize) -> String {
+        let mut hasher = RandomState::with_seeds(0, 0, 0, 0).build_hasher();
+        hasher.write(&self.0);
+        format!("{:016x}", hasher.finish())
+    }
+}
+
+impl<const N: usize> HasBytesSlice for FixedSizeInput<N> {
+    #[inline]
+    fn bytes_slice(&self) -> &[u8] {
+        &self.0
+    }
+
+    #[inline]
+    fn bytes_slice_mut(&mut self) -> &mut [u8] {
+        &mut self.0
+    }
+
+    fn splice_bytes(&mut self, at: usize, bytes: &[u8]) {
+        if at >= N {
+            return;
+        }
+        let len = bytes.len().min(N - at);
+        self.0[at..at + len].copy_from_slice(&bytes[..len]);
+    }
+}
+
+impl<const N: usize> HasTargetBytes for FixedSizeInput<N> {
+    #[inline]
+    fn target_bytes(&self) -> OwnedSlice<u8> {
+        OwnedSlice::from(&self.0[..])
+    }
+}
+
+impl<const N: usize> HasLen for FixedSizeInput<N> {
+    #[inline]
+    fn len(&self) -> usize {
+        N
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for FixedSizeInput<N> {
+    fn from(bytes: [u8; N]) -> Self {
+        Self(bytes)
+    }
+}
+
+/// Truncates or zero-pads the bytes to `N` bytes
+impl<const N: usize> From<&[u8]> for FixedSizeInput<N> {
+    fn from(bytes: &[u8]) -> Self {
+        Self::from_slice(bytes)
+    }
+}
+
+/// Truncates or zero-pads the bytes to `N` bytes
+impl<const N: usize> From<BytesInput> for FixedSizeInput<N> {
+    fn from(input: BytesInput) -> Self {
+        Self::from_slice(input.bytes())
+    }
+}
+
+impl<const N: usize> From<FixedSizeInput<N>> for BytesInput {
+    fn from(input: FixedSizeInput<N>) -> Self {
+        BytesInput::new(input.0.to_vec())
+    }
+}
+
+// serde only implements the traits for arrays of up to 32 elements, so go through a byte slice instead
+impl<const N: usize> Serialize for FixedSizeInput<N> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+struct FixedSizeVisitor<const N: usize>;
+
+impl<'de, const N: usize> Visitor<'de> for FixedSizeVisitor<N> {
+    type Value = FixedSizeInput<N>;
+
+    fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+        write!(formatter, "{N} bytes")
+    }
+
+    fn visit_bytes<E>(self, bytes: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        <[u8; N]>::try_from(bytes)
+            .map(FixedSizeInput)
+            .map_err(|_| E::invalid_length(bytes.len(), &self))
+    }
+
+    fn visit_byte_buf<E>(self, bytes: Vec<u8>) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.visit_bytes(&bytes)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        let mut bytes = [0; N];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+        }
+        if seq.next_element::<u8>()?.is_some() {
+            return Err(de::Error::invalid_length(N + 1, &self));
+        }
+        Ok(FixedSizeInput(bytes))
+    }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for FixedSizeInput<N> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_bytes(FixedSizeVisitor::<N>)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FixedSizeInput;
+    use crate::inputs::{BytesInput, HasBytesSlice};
+
+    #[test]
+    fn test_fixed_size_input() {
+        let padded: FixedSizeInput<4> = BytesInput::new(vec![1, 2]).into();
+        assert_eq!(padded.0, [1, 2, 0, 0]);
+        let truncated: FixedSizeInput<4> = BytesInput::new(vec![1, 2, 3, 4, 5]).into();
+        assert_eq!(truncated.0, [1, 2, 3, 4]);
+
+        let mut input = FixedSizeInput::new([0; 4]);
+        input.splice_bytes(2, &[7, 8, 9]);
+        assert_eq!(input.0, [0, 0, 7, 8]);
+        input.splice_bytes(0, &[5]);
+        assert_eq!(input.0, [5, 0, 7, 8]);
+        assert_eq!(input.bytes_slice().len(), 4);
+
+        let serialized = postcard::to_allocvec(&input).unwrap();
+        let deserialized: FixedSizeInput<4> = postcard::from_bytes(&serialized).unwrap();
+        assert_eq!(input, deserialized);
+        assert!(postcard::from_bytes::<FixedSizeInput<5>>(&serialized).is_err());
+    }
+}
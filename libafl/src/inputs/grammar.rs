@@ -0,0 +1,576 @@
+//! An input holding a parse tree of a context-free grammar.
+//!
+//! The [`Grammar`] is loaded from a simple BNF-like DSL, one rule per line:
+//!
+//! ```text
+//! # comments start with a hash
+//! expr ::= term | term "+" expr
+//! term ::= "x" | "(" expr ")"
+//! ```
+//!
+//! Each alternative is a sequence of string literals and rule names, the first rule is the start rule.
+//! The empty literal `""` can be used for empty alternatives.
+//! String literals support the `\\`, `\"`, `\n`, `\r`, `\t`, and `\xHH` escapes.
+
+use alloc::{rc::Rc, string::String, vec::Vec};
+use core::{
+    cell::RefCell,
+    hash::{BuildHasher, Hash, Hasher},
+};
+
+use ahash::RandomState;
+use hashbrown::{HashMap, HashSet};
+use libafl_bolts::{rands::Rand, Error, HasLen};
+use serde::{Deserialize, Serialize};
+
+use crate::inputs::{BytesInput, Input};
+
+/// The index of a rule in a [`Grammar`]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct RuleId(pub usize);
+
+/// A symbol on the right-hand side of a rule
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Symbol {
+    /// A literal, emitted as-is
+    Terminal(Vec<u8>),
+    /// A reference to a rule
+    NonTerminal(RuleId),
+}
+
+/// A node in the parse tree of a [`GrammarInput`].
+///
+/// The `children` are the nodes of the non-terminals in the chosen `alternative`, in order.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Node {
+    /// The rule this node derives
+    pub rule: RuleId,
+    /// The index of the alternative of the rule that was chosen
+    pub alternative: usize,
+    /// The derivations of the non-terminals of the alternative
+    pub children: Vec<Node>,
+}
+
+impl Node {
+    /// The number of nodes in this subtree
+    #[must_use]
+    pub fn size(&self) -> usize {
+        1 + self.children.iter().map(Node::size).sum::<usize>()
+    }
+
+    /// Collects the paths (child indices from this node) to all nodes in this subtree, in pre-order
+    pub fn paths(&self, prefix: &mut Vec<usize>, paths: &mut Vec<Vec<usize>>) {
+        paths.push(prefix.clone());
+        for (i, child) in self.children.iter().enumerate() {
+            prefix.push(i);
+            child.paths(prefix, paths);
+            prefix.pop();
+        }
+    }
+
+    /// The node at the given `path` of child indices
+    #[must_use]
+    pub fn get(&self, path: &[usize]) -> Option<&Node> {
+        path.iter()
+            .try_fold(self, |node, &idx| node.children.get(idx))
+    }
+
+    /// The node at the given `path` of child indices, mutable
+    pub fn get_mut(&mut self, path: &[usize]) -> Option<&mut Node> {
+        path.iter()
+            .try_fold(self, |node, &idx| node.children.get_mut(idx))
+    }
+}
+
+/// A context-free grammar over bytes
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Grammar {
+    /// The name of each rule
+    names: Vec<String>,
+    /// The alternatives of each rule
+    rules: Vec<Vec<Vec<Symbol>>>,
+    /// For each rule, the alternative with the smallest derivation
+    minimal_alternatives: Vec<usize>,
+    /// For each rule, if it can derive the empty string
+    nullable: Vec<bool>,
+    /// The alternatives of each rule with the terminals split into bytes, for the Earley recognizer
+    flattened: Vec<Vec<Vec<FlatSymbol>>>,
+}
+
+impl Grammar {
+    /// Loads a grammar from the BNF-like DSL described in the [module documentation](self)
+    pub fn from_dsl(dsl: &str) -> Result<Self, Error> {
+        let mut ids: HashMap<String, RuleId> = HashMap::new();
+        let mut names: Vec<String> = vec![];
+        let mut definitions: Vec<(RuleId, &str)> = vec![];
+
+        for line in dsl.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, rhs) = line
+                .split_once("::=")
+                .ok_or_else(|| Error::illegal_argument(format!("Missing '::=' in line: {line}")))?;
+            let name = name.trim();
+            if !is_rule_name(name) {
+                return Err(Error::illegal_argument(format!(
+                    "Invalid rule name: {name}"
+                )));
+            }
+            let id = *ids.entry(name.into()).or_insert_with(|| {
+                names.push(name.into());
+                RuleId(names.len() - 1)
+            });
+            definitions.push((id, rhs));
+        }
+        if names.is_empty() {
+            return Err(Error::illegal_argument("The grammar has no rules"));
+        }
+
+        let mut rules = vec![vec![]; names.len()];
+        for (id, rhs) in definitions {
+            rules[id.0].extend(parse_alternatives(rhs, &ids)?);
+        }
+        Self::new(names, rules)
+    }
+
+    /// Creates a grammar from its rules, given as alternatives of [`Symbol`]s, the first rule being the start rule.
+    /// Errors if a rule has no alternatives or can never derive a finite string.
+    pub fn new(names: Vec<String>, rules: Vec<Vec<Vec<Symbol>>>) -> Result<Self, Error> {
+        if names.len() != rules.len() || rules.is_empty() {
+            return Err(Error::illegal_argument(
+                "Expected one name for each of at least one rule",
+            ));
+        }
+        for alternatives in &rules {
+            for symbol in alternatives.iter().flatten() {
+                if let Symbol::NonTerminal(RuleId(id)) = symbol {
+                    if *id >= rules.len() {
+                        return Err(Error::illegal_argument(format!("Unknown rule id: {id}")));
+                    }
+                }
+            }
+        }
+
+        // fixpoint over the minimal number of nodes needed to derive each rule
+        let mut minimal_sizes: Vec<Option<usize>> = vec![None; rules.len()];
+        let mut minimal_alternatives = vec![0; rules.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (rule, alternatives) in rules.iter().enumerate() {
+                for (alternative, symbols) in alternatives.iter().enumerate() {
+                    let size = symbols.iter().try_fold(1, |size, symbol| match symbol {
+                        Symbol::Terminal(_) => Some(size),
+                        Symbol::NonTerminal(RuleId(id)) => minimal_sizes[*id].map(|s| size + s),
+                    });
+                    if let Some(size) = size {
+                        if minimal_sizes[rule].map_or(true, |s| size < s) {
+                            minimal_sizes[rule] = Some(size);
+                            minimal_alternatives[rule] = alternative;
+                            changed = true;
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(rule) = minimal_sizes.iter().position(Option::is_none) {
+            return Err(Error::illegal_argument(format!(
+                "Rule {} can never derive a finite string",
+                names[rule]
+            )));
+        }
+
+        let mut nullable = vec![false; rules.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (rule, alternatives) in rules.iter().enumerate() {
+                if nullable[rule] {
+                    continue;
+                }
+                if alternatives.iter().any(|symbols| {
+                    symbols.iter().all(|symbol| match symbol {
+                        Symbol::Terminal(bytes) => bytes.is_empty(),
+                        Symbol::NonTerminal(RuleId(id)) => nullable[*id],
+                    })
+                }) {
+                    nullable[rule] = true;
+                    changed = true;
+                }
+            }
+        }
+
+        let flattened = rules
+            .iter()
+            .map(|alternatives| {
+                alternatives
+                    .iter()
+                    .map(|symbols| {
+                        symbols
+                            .iter()
+                            .flat_map(|symbol| match symbol {
+                                Symbol::Terminal(literal) => {
+                                    literal.iter().map(|b| FlatSymbol::Byte(*b)).collect()
+                                }
+                                Symbol::NonTerminal(rule) => vec![FlatSymbol::Rule(rule.0)],
+                            })
+                            .collect()
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Ok(Self {
+            names,
+            rules,
+            minimal_alternatives,
+            nullable,
+            flattened,
+        })
+    }
+
+    /// The start rule
+    #[must_use]
+    pub fn start(&self) -> RuleId {
+        RuleId(0)
+    }
+
+    /// The name of the given rule
+    #[must_use]
+    pub fn name(&self, rule: RuleId) -> &str {
+        &self.names[rule.0]
+    }
+
+    /// The alternatives of the given rule
+    #[must_use]
+    pub fn alternatives(&self, rule: RuleId) -> &[Vec<Symbol>] {
+        &self.rules[rule.0]
+    }
+
+    /// Generates a random derivation of `rule`.
+    /// Beyond `max_depth`, the smallest derivations are chosen, so that the tree stays finite.
+    pub fn generate<R: Rand>(&self, rand: &mut R, rule: RuleId, max_depth: usize) -> Node {
+        let alternative = if max_depth == 0 {
+            self.minimal_alternatives[rule.0]
+        } else {
+            rand.below(self.rules[rule.0].len() as u64) as usize
+        };
+        let children = self.rules[rule.0][alternative]
+            .iter()
+            .filter_map(|symbol| match symbol {
+                Symbol::Terminal(_) => None,
+                Symbol::NonTerminal(child) => {
+                    Some(self.generate(rand, *child, max_depth.saturating_sub(1)))
+                }
+            })
+            .collect();
+        Node {
+            rule,
+            alternative,
+            children,
+        }
+    }
+
+    /// The smallest derivation of `rule`
+    #[must_use]
+    pub fn minimal(&self, rule: RuleId) -> Node {
+        let alternative = self.minimal_alternatives[rule.0];
+        let children = self.rules[rule.0][alternative]
+            .iter()
+            .filter_map(|symbol| match symbol {
+                Symbol::Terminal(_) => None,
+                Symbol::NonTerminal(child) => Some(self.minimal(*child)),
+            })
+            .collect();
+        Node {
+            rule,
+            alternative,
+            children,
+        }
+    }
+
+    /// Renders the tree to `bytes`, appending to it.
+    /// Errors if the tree does not match the grammar.
+    pub fn render(&self, node: &Node, bytes: &mut Vec<u8>) -> Result<(), Error> {
+        let symbols = self
+            .rules
+            .get(node.rule.0)
+            .and_then(|alternatives| alternatives.get(node.alternative))
+            .ok_or_else(|| Error::illegal_argument("Node does not belong to this grammar"))?;
+        let mut children = node.children.iter();
+        for symbol in symbols {
+            match symbol {
+                Symbol::Terminal(literal) => bytes.extend_from_slice(literal),
+                Symbol::NonTerminal(rule) => match children.next() {
+                    Some(child) if child.rule == *rule => self.render(child, bytes)?,
+                    _ => {
+                        return Err(Error::illegal_argument(format!(
+                            "Children of a {} node do not match its alternative",
+                            self.names[node.rule.0]
+                        )))
+                    }
+                },
+            }
+        }
+        if children.next().is_some() {
+            return Err(Error::illegal_argument(format!(
+                "Too many children for a {} node",
+                self.names[node.rule.0]
+            )));
+        }
+        Ok(())
+    }
+
+    /// Checks if `bytes` can be derived from the start rule, using an Earley recognizer.
+    #[must_use]
+    pub fn accepts(&self, bytes: &[u8]) -> bool {
+        // Items are (rule, alternative, dot, origin), the dot being a byte offset into the alternative.
+        // Terminals are matched byte by byte, on the alternatives flattened to bytes in `new`.
+        let flattened = &self.flattened;
+
+        let mut chart: Vec<Vec<EarleyItem>> = vec![vec![]; bytes.len() + 1];
+        let mut seen: Vec<HashSet<EarleyItem>> = vec![HashSet::new(); bytes.len() + 1];
+        let mut add = |chart: &mut Vec<Vec<EarleyItem>>, pos: usize, item: EarleyItem| {
+            if seen[pos].insert(item) {
+                chart[pos].push(item);
+            }
+        };
+        for alternative in 0..flattened[0].len() {
+            add(&mut chart, 0, (0, alternative, 0, 0));
+        }
+
+        for pos in 0..=bytes.len() {
+            let mut idx = 0;
+            while idx < chart[pos].len() {
+                let (rule, alternative, dot, origin) = chart[pos][idx];
+                idx += 1;
+                match flattened[rule][alternative].get(dot) {
+                    // complete
+                    None => {
+                        let waiting: Vec<EarleyItem> = chart[origin]
+                            .iter()
+                            .filter(|(r, a, d, _)| {
+                                flattened[*r][*a].get(*d) == Some(&FlatSymbol::Rule(rule))
+                            })
+                            .copied()
+                            .collect();
+                        for (r, a, d, o) in waiting {
+                            add(&mut chart, pos, (r, a, d + 1, o));
+                        }
+                    }
+                    // predict
+                    Some(FlatSymbol::Rule(next)) => {
+                        for next_alternative in 0..flattened[*next].len() {
+                            add(&mut chart, pos, (*next, next_alternative, 0, pos));
+                        }
+                        // nullable rules complete right away (Aycock and Horspool)
+                        if self.nullable[*next] {
+                            add(&mut chart, pos, (rule, alternative, dot + 1, origin));
+                        }
+                    }
+                    // scan
+                    Some(FlatSymbol::Byte(byte)) => {
+                        if bytes.get(pos) == Some(byte) {
+                            add(&mut chart, pos + 1, (rule, alternative, dot + 1, origin));
+                        }
+                    }
+                }
+            }
+        }
+
+        chart[bytes.len()]
+            .iter()
+            .any(|(rule, alternative, dot, origin)| {
+                *rule == 0 && *origin == 0 && *dot == flattened[0][*alternative].len()
+            })
+    }
+}
+
+/// An item of the Earley chart: (rule, alternative, dot, origin)
+type EarleyItem = (usize, usize, usize, usize);
+
+/// A symbol of an alternative, with terminals split into bytes
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+enum FlatSymbol {
+    Byte(u8),
+    Rule(usize),
+}
+
+fn is_rule_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Parses the right-hand side of a rule definition
+fn parse_alternatives(rhs: &str, ids: &HashMap<String, RuleId>) -> Result<Vec<Vec<Symbol>>, Error> {
+    let mut alternatives = vec![];
+    let mut symbols = vec![];
+    let mut chars = rhs.trim().chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '|' => alternatives.push(core::mem::take(&mut symbols)),
+            '"' => {
+                let mut literal = vec![];
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => literal.push(b'\n'),
+                            Some('r') => literal.push(b'\r'),
+                            Some('t') => literal.push(b'\t'),
+                            Some('x') => {
+                                let hex: String = chars.by_ref().take(2).collect();
+                                let byte = u8::from_str_radix(&hex, 16).map_err(|_| {
+                                    Error::illegal_argument(format!("Invalid escape: \\x{hex}"))
+                                })?;
+                                literal.push(byte);
+                            }
+                            Some(c @ ('"' | '\\')) => literal.push(c as u8),
+                            c => {
+                                return Err(Error::illegal_argument(format!(
+                                    "Invalid escape: \\{c:?}"
+                                )))
+                            }
+                        },
+                        Some(c) => {
+                            literal.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                        }
+                        None => {
+                            return Err(Error::illegal_argument(format!(
+                                "Unterminated string literal in: {rhs}"
+                            )))
+                        }
+                    }
+                }
+                symbols.push(Symbol::Terminal(literal));
+            }
+            c => {
+                let mut name = String::from(c);
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '|' || c == '"' {
+                        break;
+                    }
+                    name.push(c);
+                    chars.next();
+                }
+                let id = ids
+                    .get(&name)
+                    .ok_or_else(|| Error::illegal_argument(format!("Undefined rule: {name}")))?;
+                symbols.push(Symbol::NonTerminal(*id));
+            }
+        }
+    }
+    alternatives.push(symbols);
+    Ok(alternatives)
+}
+
+/// An input for grammar fuzzing, holding a parse tree of a [`Grammar`]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct GrammarInput {
+    root: Node,
+}
+
+impl Input for GrammarInput {
+    /// Generate a name for this input
+    fn generate_name(&self, _idx: usize) -> String {
+        let mut hasher = RandomState::with_seeds(0, 0, 0, 0).build_hasher();
+        self.root.hash(&mut hasher);
+        format!("{:016x}", hasher.finish())
+    }
+}
+
+/// Rc Ref-cell from Input
+impl From<GrammarInput> for Rc<RefCell<GrammarInput>> {
+    fn from(input: GrammarInput) -> Self {
+        Rc::new(RefCell::new(input))
+    }
+}
+
+impl HasLen for GrammarInput {
+    /// The number of nodes in the tree
+    #[inline]
+    fn len(&self) -> usize {
+        self.root.size()
+    }
+}
+
+impl GrammarInput {
+    /// Creates a new input from the given parse tree
+    #[must_use]
+    pub fn new(root: Node) -> Self {
+        Self { root }
+    }
+
+    /// The root of the parse tree
+    #[must_use]
+    pub fn root(&self) -> &Node {
+        &self.root
+    }
+
+    /// The root of the parse tree, mutable
+    #[must_use]
+    pub fn root_mut(&mut self) -> &mut Node {
+        &mut self.root
+    }
+
+    /// Create a bytes representation of this input
+    pub fn unparse(&self, grammar: &Grammar, bytes: &mut Vec<u8>) -> Result<(), Error> {
+        bytes.clear();
+        grammar.render(&self.root, bytes)
+    }
+
+    /// Renders this input to a [`BytesInput`]
+    pub fn to_bytes_input(&self, grammar: &Grammar) -> Result<BytesInput, Error> {
+        let mut bytes = vec![];
+        self.unparse(grammar, &mut bytes)?;
+        Ok(BytesInput::new(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::rands::StdRand;
+
+    use crate::inputs::{
+        grammar::{Grammar, GrammarInput},
+        HasBytesVec,
+    };
+
+    const EXPR: &str = r#"
+        # simple arithmetic
+        expr ::= term | term "+" expr
+        term ::= "x" | "(" expr ")" | ""
+    "#;
+
+    #[test]
+    fn test_grammar_dsl() {
+        let grammar = Grammar::from_dsl(EXPR).unwrap();
+        assert_eq!(grammar.name(grammar.start()), "expr");
+        assert_eq!(grammar.alternatives(grammar.start()).len(), 2);
+
+        assert!(grammar.accepts(b"x+(x+x)"));
+        assert!(grammar.accepts(b"+"));
+        assert!(grammar.accepts(b""));
+        assert!(!grammar.accepts(b"x+(x"));
+        assert!(!grammar.accepts(b"xx"));
+
+        assert!(Grammar::from_dsl("a ::= \"a\" b").is_err());
+        assert!(Grammar::from_dsl("a ::= \"a\" a").is_err());
+    }
+
+    #[test]
+    fn test_grammar_generate_render() {
+        let grammar = Grammar::from_dsl(EXPR).unwrap();
+        let mut rand = StdRand::with_seed(0);
+        for _ in 0..100 {
+            let input = GrammarInput::new(grammar.generate(&mut rand, grammar.start(), 8));
+            let bytes = input.to_bytes_input(&grammar).unwrap();
+            assert!(grammar.accepts(bytes.bytes()));
+        }
+    }
+}
@@ -0,0 +1,189 @@
+//! The [`HttpRequestInput`] is a structured HTTP/1.1 request, for fuzzing HTTP server implementations.
+//!
+//! Byte-level mutations of a raw request mostly break the request line or the header syntax,
+//! so that the server rejects the request in its parser. The [`HttpRequestInput`] keeps the method, path,
+//! headers, and body apart, and the harness receives them serialized in the HTTP/1.1 wire format.
+//! Raw requests, e.g. captured ones saved as `.http` files, make good seeds, see [`HttpRequestInput::parse`].
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::hash::{BuildHasher, Hasher};
+#[cfg(feature = "std")]
+use std::{fs, path::Path};
+
+use ahash::RandomState;
+use libafl_bolts::{ownedref::OwnedSlice, AsSlice, HasLen};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    inputs::{HasTargetBytes, Input},
+    Error,
+};
+
+/// The protocol version put into the request line
+pub const HTTP_VERSION: &str = "HTTP/1.1";
+
+/// An HTTP request, see the [module documentation](self).
+///
+/// The request is serialized as it is: a `Content-Length` header is neither added nor fixed up,
+/// so that mismatches with the body can be fuzzed as well.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct HttpRequestInput {
+    /// The method, e.g. `GET`
+    pub method: String,
+    /// The request target, e.g. `/index.html?lang=en`
+    pub path: String,
+    /// The headers as pairs of name and value, in order
+    pub headers: Vec<(String, String)>,
+    /// The body, following the headers
+    pub body: Vec<u8>,
+}
+
+impl HttpRequestInput {
+    /// Creates a new [`HttpRequestInput`]
+    #[must_use]
+    pub fn new(method: &str, path: &str, headers: Vec<(String, String)>, body: Vec<u8>) -> Self {
+        Self {
+            method: method.to_string(),
+            path: path.to_string(),
+            headers,
+            body,
+        }
+    }
+
+    /// Parses a raw HTTP request: the request line, the headers up to the first empty line, and the body.
+    ///
+    /// Lines may end in `\r\n` or `\n`. The protocol version of the request line is optional, and ignored.
+    pub fn parse(bytes: &[u8]) -> Result<Self, Error> {
+        let (head, body) = match find(bytes, b"\r\n\r\n") {
+            Some(pos) => (&bytes[..pos], &bytes[pos + 4..]),
+            None => match find(bytes, b"\n\n") {
+                Some(pos) => (&bytes[..pos], &bytes[pos + 2..]),
+                None => (bytes, &[][..]),
+            },
+        };
+        let head = core::str::from_utf8(head)
+            .map_err(|_| Error::illegal_argument("The HTTP request head is no valid UTF-8"))?;
+        let mut lines = head.lines().map(|line| line.trim_end_matches('\r'));
+
+        let request_line = lines
+            .next()
+            .ok_or_else(|| Error::illegal_argument("The HTTP request has no request line"))?;
+        let mut parts = request_line.split_whitespace();
+        let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+            return Err(Error::illegal_argument(format!(
+                "Invalid HTTP request line: {request_line:?}"
+            )));
+        };
+
+        let headers = lines
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                line.split_once(':')
+                    .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+                    .ok_or_else(|| {
+                        Error::illegal_argument(format!("Invalid HTTP header line: {line:?}"))
+                    })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(Self::new(method, path, headers, body.to_vec()))
+    }
+
+    /// Reads a raw HTTP request from the `.http` file at `path`, see [`HttpRequestInput::parse`]
+    #[cfg(feature = "std")]
+    pub fn from_http_file<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        Self::parse(&fs::read(path)?)
+    }
+
+    /// The length of the bytes returned by [`HasTargetBytes::target_bytes`]
+    #[must_use]
+    pub fn target_len(&self) -> usize {
+        let request_line = self.method.len() + 1 + self.path.len() + 1 + HTTP_VERSION.len() + 2;
+        let headers: usize = self
+            .headers
+            .iter()
+            .map(|(name, value)| name.len() + 2 + value.len() + 2)
+            .sum();
+        request_line + headers + 2 + self.body.len()
+    }
+}
+
+/// The position of the first occurrence of `needle` in `haystack`
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+impl Input for HttpRequestInput {
+    /// Generate a name for this input
+    fn generate_name(&self, _idx: usize) -> String {
+        let mut hasher = RandomState::with_seeds(0, 0, 0, 0).build_hasher();
+        hasher.write(self.target_bytes().as_slice());
+        format!("{:016x}", hasher.finish())
+    }
+}
+
+impl HasLen for HttpRequestInput {
+    /// The number of bytes of the serialized request
+    #[inline]
+    fn len(&self) -> usize {
+        self.target_len()
+    }
+}
+
+impl HasTargetBytes for HttpRequestInput {
+    /// The request in the HTTP/1.1 wire format
+    fn target_bytes(&self) -> OwnedSlice<u8> {
+        let mut bytes = Vec::with_capacity(self.target_len());
+        bytes.extend_from_slice(self.method.as_bytes());
+        bytes.push(b' ');
+        bytes.extend_from_slice(self.path.as_bytes());
+        bytes.push(b' ');
+        bytes.extend_from_slice(HTTP_VERSION.as_bytes());
+        bytes.extend_from_slice(b"\r\n");
+        for (name, value) in &self.headers {
+            bytes.extend_from_slice(name.as_bytes());
+            bytes.extend_from_slice(b": ");
+            bytes.extend_from_slice(value.as_bytes());
+            bytes.extend_from_slice(b"\r\n");
+        }
+        bytes.extend_from_slice(b"\r\n");
+        bytes.extend_from_slice(&self.body);
+        OwnedSlice::from(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::AsSlice;
+
+    use super::HttpRequestInput;
+    use crate::inputs::HasTargetBytes;
+
+    #[test]
+    fn test_http_request_roundtrip() {
+        let raw =
+            b"POST /api/v1/items?x=1 HTTP/1.1\r\nHost: localhost\r\nContent-Length: 2\r\n\r\n{}";
+        let input = HttpRequestInput::parse(raw).unwrap();
+        assert_eq!(input.method, "POST");
+        assert_eq!(input.path, "/api/v1/items?x=1");
+        assert_eq!(input.headers.len(), 2);
+        assert_eq!(input.headers[1], ("Content-Length".into(), "2".into()));
+        assert_eq!(input.body, b"{}");
+
+        assert_eq!(input.target_bytes().as_slice(), raw);
+        assert_eq!(input.target_len(), raw.len());
+
+        let lf_only = HttpRequestInput::parse(b"GET /\nAccept: */*\n").unwrap();
+        assert_eq!(lf_only.path, "/");
+        assert_eq!(lf_only.headers, vec![("Accept".into(), "*/*".into())]);
+        assert!(lf_only.body.is_empty());
+    }
+}
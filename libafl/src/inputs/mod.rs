@@ -9,9 +9,23 @@ pub use encoded::*;
 pub mod gramatron;
 pub use gramatron::*;
 
+pub mod grammar;
+pub use grammar::{Grammar, GrammarInput, Node, RuleId, Symbol};
+
 pub mod generalized;
 pub use generalized::*;
 
+pub mod fixed;
+pub use fixed::FixedSizeInput;
+
+pub mod pcap;
+pub use self::pcap::{Packet, PcapInput};
+
+#[cfg(feature = "http_input")]
+pub mod http;
+#[cfg(feature = "http_input")]
+pub use http::HttpRequestInput;
+
 #[cfg(feature = "multipart_inputs")]
 pub mod multi;
 #[cfg(feature = "multipart_inputs")]
@@ -135,6 +149,38 @@ pub trait HasBytesVec {
     fn bytes_mut(&mut self) -> &mut Vec<u8>;
 }
 
+/// Contains mutable bytes, which are not necessarily resizable, like the array of a [`FixedSizeInput`].
+/// Implemented for all inputs with a [`HasBytesVec`].
+pub trait HasBytesSlice {
+    /// The bytes
+    fn bytes_slice(&self) -> &[u8];
+    /// The bytes (as mutable borrow)
+    fn bytes_slice_mut(&mut self) -> &mut [u8];
+    /// Replaces the bytes from `at` on with `bytes`.
+    /// Inputs that cannot be resized clip `bytes`, or keep their own bytes after a shorter `bytes`.
+    fn splice_bytes(&mut self, at: usize, bytes: &[u8]);
+}
+
+impl<I> HasBytesSlice for I
+where
+    I: HasBytesVec,
+{
+    #[inline]
+    fn bytes_slice(&self) -> &[u8] {
+        self.bytes()
+    }
+
+    #[inline]
+    fn bytes_slice_mut(&mut self) -> &mut [u8] {
+        self.bytes_mut()
+    }
+
+    #[inline]
+    fn splice_bytes(&mut self, at: usize, bytes: &[u8]) {
+        self.bytes_mut().splice(at.., bytes.iter().copied());
+    }
+}
+
 /// Defines the input type shared across traits of the type.
 /// Needed for consistency across HasCorpus/HasSolutions and friends.
 pub trait UsesInput {
@@ -0,0 +1,174 @@
+//! The [`PcapInput`] is a sequence of network packets, such as those of a libpcap `.pcap` capture,
+//! for fuzzing network stacks packet by packet.
+//!
+//! The harness receives the packets concatenated, each prefixed with its length as little-endian `u32`,
+//! and feeds them to the target one at a time. Captured sessions make good seeds,
+//! see [`PcapInput::from_pcap_file`].
+
+use alloc::{string::String, vec::Vec};
+use core::{
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+#[cfg(feature = "pcap")]
+use std::path::Path;
+
+use ahash::RandomState;
+use libafl_bolts::{ownedref::OwnedSlice, AsSlice, HasLen};
+use serde::{Deserialize, Serialize};
+
+use crate::inputs::{HasTargetBytes, Input};
+#[cfg(feature = "pcap")]
+use crate::Error;
+
+/// A single packet of a [`PcapInput`]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Packet {
+    /// The time the packet was captured at, relative to the unix epoch
+    pub timestamp: Duration,
+    /// The content of the packet
+    pub data: Vec<u8>,
+}
+
+impl Packet {
+    /// Creates a new [`Packet`]
+    #[must_use]
+    pub fn new(timestamp: Duration, data: Vec<u8>) -> Self {
+        Self { timestamp, data }
+    }
+}
+
+/// An input made of a sequence of packets, see the [module documentation](self).
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PcapInput {
+    packets: Vec<Packet>,
+}
+
+impl PcapInput {
+    /// Creates a new [`PcapInput`] from the given packets
+    #[must_use]
+    pub fn new(packets: Vec<Packet>) -> Self {
+        Self { packets }
+    }
+
+    /// Reads all packets of the libpcap capture at `path`.
+    /// Packets captured truncated are taken as captured.
+    #[cfg(feature = "pcap")]
+    pub fn from_pcap_file<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let mut capture = ::pcap::Capture::from_file(path).map_err(|err| {
+            Error::illegal_argument(format!("Cannot open {}: {err}", path.display()))
+        })?;
+
+        let mut packets = Vec::new();
+        loop {
+            match capture.next_packet() {
+                Ok(packet) => {
+                    let ts = packet.header.ts;
+                    #[allow(clippy::cast_sign_loss)]
+                    let timestamp = Duration::from_secs(ts.tv_sec as u64)
+                        + Duration::from_micros(ts.tv_usec as u64);
+                    packets.push(Packet::new(timestamp, packet.data.to_vec()));
+                }
+                Err(::pcap::Error::NoMorePackets) => break,
+                Err(err) => {
+                    return Err(Error::illegal_argument(format!(
+                        "Cannot read {}: {err}",
+                        path.display()
+                    )))
+                }
+            }
+        }
+        Ok(Self::new(packets))
+    }
+
+    /// The packets of this input
+    #[must_use]
+    pub fn packets(&self) -> &[Packet] {
+        &self.packets
+    }
+
+    /// The packets of this input, mutably
+    pub fn packets_mut(&mut self) -> &mut Vec<Packet> {
+        &mut self.packets
+    }
+
+    /// The length of the bytes returned by [`HasTargetBytes::target_bytes`]
+    #[must_use]
+    pub fn target_len(&self) -> usize {
+        self.packets
+            .iter()
+            .map(|packet| 4 + packet.data.len())
+            .sum()
+    }
+}
+
+impl Input for PcapInput {
+    /// Generate a name for this input, from the hash of its target bytes,
+    /// so that inputs only differing in their packet boundaries get different names
+    fn generate_name(&self, _idx: usize) -> String {
+        let mut hasher = RandomState::with_seeds(0, 0, 0, 0).build_hasher();
+        hasher.write(self.target_bytes().as_slice());
+        format!("{:016x}", hasher.finish())
+    }
+}
+
+impl HasLen for PcapInput {
+    /// The number of packets
+    #[inline]
+    fn len(&self) -> usize {
+        self.packets.len()
+    }
+}
+
+impl HasTargetBytes for PcapInput {
+    /// The packets, each prefixed with its length as little-endian `u32`
+    #[allow(clippy::cast_possible_truncation)]
+    fn target_bytes(&self) -> OwnedSlice<u8> {
+        let mut bytes = Vec::with_capacity(self.target_len());
+        for packet in &self.packets {
+            bytes.extend_from_slice(&(packet.data.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(&packet.data);
+        }
+        OwnedSlice::from(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use libafl_bolts::AsSlice;
+
+    use super::{Packet, PcapInput};
+    use crate::inputs::{HasTargetBytes, Input};
+
+    #[test]
+    fn test_pcap_target_bytes() {
+        let input = PcapInput::new(vec![
+            Packet::new(Duration::from_secs(1), vec![0xaa, 0xbb]),
+            Packet::new(Duration::from_secs(2), vec![]),
+            Packet::new(Duration::from_secs(3), vec![0xcc]),
+        ]);
+        assert_eq!(input.target_len(), 15);
+        assert_eq!(
+            input.target_bytes().as_slice(),
+            &[2, 0, 0, 0, 0xaa, 0xbb, 0, 0, 0, 0, 1, 0, 0, 0, 0xcc]
+        );
+    }
+    #[test]
+    fn test_pcap_names_differ_by_packet_boundaries() {
+        let split = |at: usize| {
+            let data = b"abc";
+            PcapInput::new(vec![
+                Packet::new(Duration::ZERO, data[..at].to_vec()),
+                Packet::new(Duration::ZERO, data[at..].to_vec()),
+            ])
+        };
+        assert_ne!(split(1).generate_name(0), split(2).generate_name(0));
+        assert_eq!(split(1).generate_name(0), split(1).generate_name(1));
+    }
+}
@@ -0,0 +1,152 @@
+//! The [`Base64Mutator`] mutates the decoded content of base64-encoded inputs, e.g. the payloads of web APIs.
+//!
+//! Byte-level mutations of the encoded text mostly produce invalid base64, or change the decoded bytes at random
+//! bit offsets. The [`Base64Mutator`] instead decodes the input, lets an inner mutator mutate the decoded bytes,
+//! and encodes the result again, in the same alphabet and padding style.
+
+use alloc::vec::Vec;
+
+use base64::{
+    engine::general_purpose::{
+        GeneralPurpose, STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD,
+    },
+    Engine,
+};
+use libafl_bolts::Named;
+
+use crate::{
+    corpus::CorpusId,
+    inputs::{BytesInput, HasBytesVec},
+    mutators::{MutationResult, Mutator},
+    Error,
+};
+
+/// The engines tried to decode an input, the first one succeeding is used to encode it again
+const BASE64_ENGINES: [&GeneralPurpose; 4] =
+    [&STANDARD, &URL_SAFE, &STANDARD_NO_PAD, &URL_SAFE_NO_PAD];
+
+/// Decodes `bytes` as standard or URL-safe base64, with or without padding.
+/// Returns the decoded bytes, and the engine that decoded them.
+#[must_use]
+pub fn decode_base64(bytes: &[u8]) -> Option<(Vec<u8>, &'static GeneralPurpose)> {
+    if bytes.is_empty() {
+        return None;
+    }
+    BASE64_ENGINES
+        .iter()
+        .find_map(|engine| engine.decode(bytes).ok().map(|decoded| (decoded, *engine)))
+}
+
+/// Applies the inner mutator to the decoded bytes of a base64-encoded input, and encodes the result again.
+///
+/// Inputs that are not valid base64 are mutated by the inner mutator as they are.
+#[derive(Debug)]
+pub struct Base64Mutator<M> {
+    inner: M,
+}
+
+impl<M> Base64Mutator<M> {
+    /// Creates a new [`Base64Mutator`], mutating the decoded bytes with `inner`
+    #[must_use]
+    pub fn new(inner: M) -> Self {
+        Self { inner }
+    }
+
+    /// The inner mutator
+    #[must_use]
+    pub fn inner(&self) -> &M {
+        &self.inner
+    }
+}
+
+impl<I, M, S> Mutator<I, S> for Base64Mutator<M>
+where
+    I: HasBytesVec,
+    M: Mutator<BytesInput, S>,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let decoded = decode_base64(input.bytes());
+        let engine = decoded.as_ref().map(|(_, engine)| *engine);
+        let mut bytes_input =
+            BytesInput::new(decoded.map_or_else(|| input.bytes().to_vec(), |(bytes, _)| bytes));
+
+        let result = self.inner.mutate(state, &mut bytes_input, stage_idx)?;
+        if result == MutationResult::Mutated {
+            *input.bytes_mut() = match engine {
+                Some(engine) => engine.encode(bytes_input.bytes()).into_bytes(),
+                None => bytes_input.bytes().to_vec(),
+            };
+        }
+        Ok(result)
+    }
+
+    #[inline]
+    fn post_exec(
+        &mut self,
+        state: &mut S,
+        stage_idx: i32,
+        corpus_idx: Option<CorpusId>,
+    ) -> Result<(), Error> {
+        self.inner.post_exec(state, stage_idx, corpus_idx)
+    }
+}
+
+impl<M> Named for Base64Mutator<M> {
+    fn name(&self) -> &str {
+        "Base64Mutator"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::Named;
+
+    use super::{decode_base64, Base64Mutator};
+    use crate::{
+        inputs::{BytesInput, HasBytesVec},
+        mutators::{MutationResult, Mutator},
+        Error,
+    };
+
+    /// Appends a `!` to the input
+    struct AppendMutator;
+
+    impl Named for AppendMutator {
+        fn name(&self) -> &str {
+            "AppendMutator"
+        }
+    }
+
+    impl<S> Mutator<BytesInput, S> for AppendMutator {
+        fn mutate(
+            &mut self,
+            _state: &mut S,
+            input: &mut BytesInput,
+            _stage_idx: i32,
+        ) -> Result<MutationResult, Error> {
+            input.bytes_mut().push(b'!');
+            Ok(MutationResult::Mutated)
+        }
+    }
+
+    #[test]
+    fn test_base64_mutator() {
+        assert_eq!(decode_base64(b"aGk_").unwrap().0, b"hi?");
+        assert!(decode_base64(b"not base64!").is_none());
+
+        let mut mutator = Base64Mutator::new(AppendMutator);
+        // "hi" in standard base64
+        let mut input = BytesInput::new(b"aGk=".to_vec());
+        mutator.mutate(&mut (), &mut input, 0).unwrap();
+        assert_eq!(input.bytes(), b"aGkh");
+
+        let mut raw = BytesInput::new(b"{}".to_vec());
+        mutator.mutate(&mut (), &mut raw, 0).unwrap();
+        assert_eq!(raw.bytes(), b"{}!");
+    }
+}
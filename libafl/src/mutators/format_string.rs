@@ -0,0 +1,249 @@
+//! Mutators inserting `printf` format specifiers, for targets passing user data as the format string
+//! of a `printf`-family function, such as logging libraries or CLI argument parsers.
+//!
+//! The [`FmtStringMutator`] generates random, but valid, specifiers from a [`FormatStringPayloadSet`].
+//! The same set adds its common specifiers to the [`Tokens`], so that the [`crate::mutators::TokenInsert`]
+//! and the [`crate::mutators::TokenReplace`] mutators insert them as well.
+
+use alloc::{string::ToString, vec::Vec};
+use core::cmp::min;
+
+use libafl_bolts::{rands::Rand, Error, Named};
+
+use crate::{
+    inputs::HasBytesVec,
+    mutators::{rand_range, MutationResult, Mutator, Tokens},
+    state::{HasMaxSize, HasRand},
+};
+
+/// The conversions of `printf`, including `%n` writing to memory and the escaped `%%`
+pub const FORMAT_STRING_CONVERSIONS: &[u8] = b"diouxXeEfFgGaAcspn%";
+/// The flags of `printf` conversions
+pub const FORMAT_STRING_FLAGS: &[u8] = b"-+ #0";
+/// The length modifiers of `printf` conversions
+pub const FORMAT_STRING_LENGTH_MODIFIERS: &[&[u8]] =
+    &[b"hh", b"h", b"l", b"ll", b"j", b"z", b"t", b"L"];
+/// Specifiers known to crash or leak memory of vulnerable targets, added to the [`Tokens`]
+pub const FORMAT_STRING_TOKENS: &[&[u8]] = &[
+    b"%s",
+    b"%d",
+    b"%n",
+    b"%x",
+    b"%p",
+    b"%%",
+    b"%s%s%s%s",
+    b"%n%n%n%n",
+    b"%x%x%x%x",
+    b"%1$s",
+    b"%99999d",
+    b"%.99999f",
+];
+
+/// The maximum number of specifiers inserted by a single [`FmtStringMutator`] mutation
+const MAX_SPECIFIERS_PER_MUTATION: u64 = 4;
+
+/// The vocabulary of the format specifiers generated by the [`FmtStringMutator`]
+#[derive(Debug, Clone)]
+pub struct FormatStringPayloadSet {
+    conversions: Vec<u8>,
+    flags: Vec<u8>,
+    length_modifiers: Vec<Vec<u8>>,
+    max_width: u64,
+    max_position: u64,
+    tokens: Vec<Vec<u8>>,
+}
+
+impl Default for FormatStringPayloadSet {
+    fn default() -> Self {
+        Self {
+            conversions: FORMAT_STRING_CONVERSIONS.to_vec(),
+            flags: FORMAT_STRING_FLAGS.to_vec(),
+            length_modifiers: FORMAT_STRING_LENGTH_MODIFIERS
+                .iter()
+                .map(|modifier| modifier.to_vec())
+                .collect(),
+            max_width: 99_999,
+            max_position: 16,
+            tokens: FORMAT_STRING_TOKENS
+                .iter()
+                .map(|token| token.to_vec())
+                .collect(),
+        }
+    }
+}
+
+impl FormatStringPayloadSet {
+    /// Creates a new [`FormatStringPayloadSet`] with all conversions of `printf`
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the conversions to pick from, e.g. `b"sxp"` for targets that must not write through `%n`
+    #[must_use]
+    pub fn with_conversions(mut self, conversions: &[u8]) -> Self {
+        assert!(!conversions.is_empty(), "No conversion to generate");
+        self.conversions = conversions.to_vec();
+        self
+    }
+
+    /// Sets the maximum width and precision of the generated specifiers
+    #[must_use]
+    pub fn with_max_width(mut self, max_width: u64) -> Self {
+        self.max_width = max_width;
+        self
+    }
+
+    /// Sets the maximum argument position of the generated `%<n>$` specifiers, `0` to not generate any
+    #[must_use]
+    pub fn with_max_position(mut self, max_position: u64) -> Self {
+        self.max_position = max_position;
+        self
+    }
+
+    /// Generates a random specifier, like `%s`, `%-08lx`, or `%3$*.5d`
+    pub fn generate<R: Rand>(&self, rand: &mut R) -> Vec<u8> {
+        let conversion = *rand.choose(&self.conversions);
+        let mut specifier = vec![b'%'];
+        if conversion == b'%' {
+            specifier.push(b'%');
+            return specifier;
+        }
+
+        if self.max_position > 0 && rand.below(4) == 0 {
+            let position = rand.between(1, self.max_position);
+            specifier.extend_from_slice(format!("{position}$").as_bytes());
+        }
+        for _ in 0..rand.below(3) {
+            specifier.push(*rand.choose(&self.flags));
+        }
+        match rand.below(3) {
+            0 => specifier.push(b'*'),
+            1 => {
+                let width = rand.below(self.max_width + 1);
+                specifier.extend_from_slice(width.to_string().as_bytes());
+            }
+            _ => {}
+        }
+        if rand.below(3) == 0 {
+            let precision = rand.below(self.max_width + 1);
+            specifier.push(b'.');
+            specifier.extend_from_slice(precision.to_string().as_bytes());
+        }
+        if !matches!(conversion, b'c' | b's' | b'p') && rand.below(2) == 0 {
+            specifier.extend_from_slice(rand.choose(&self.length_modifiers));
+        }
+        specifier.push(conversion);
+        specifier
+    }
+
+    /// Adds a specifier to the dictionary tokens of this set
+    #[must_use]
+    pub fn with_token(mut self, token: &[u8]) -> Self {
+        self.tokens.push(token.to_vec());
+        self
+    }
+
+    /// The common specifiers, to be used as dictionary tokens
+    #[must_use]
+    pub fn tokens(&self) -> &[Vec<u8>] {
+        &self.tokens
+    }
+
+    /// Adds the common specifiers to `tokens`, so that the token mutators insert them
+    pub fn add_to_tokens(&self, tokens: &mut Tokens) {
+        tokens.add_tokens(&self.tokens);
+    }
+}
+
+/// Appends random `printf` format specifiers to the input, or replaces a part of it with them
+#[derive(Debug, Default)]
+pub struct FmtStringMutator {
+    payloads: FormatStringPayloadSet,
+}
+
+impl FmtStringMutator {
+    /// Creates a new [`FmtStringMutator`], generating specifiers from the default [`FormatStringPayloadSet`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new [`FmtStringMutator`], generating specifiers from the given [`FormatStringPayloadSet`]
+    #[must_use]
+    pub fn with_payloads(payloads: FormatStringPayloadSet) -> Self {
+        Self { payloads }
+    }
+
+    /// The [`FormatStringPayloadSet`] of this mutator
+    #[must_use]
+    pub fn payloads(&self) -> &FormatStringPayloadSet {
+        &self.payloads
+    }
+}
+
+impl<I, S> Mutator<I, S> for FmtStringMutator
+where
+    S: HasRand + HasMaxSize,
+    I: HasBytesVec,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let count = 1 + state.rand_mut().below(MAX_SPECIFIERS_PER_MUTATION);
+        let mut payload = Vec::new();
+        for _ in 0..count {
+            payload.extend(self.payloads.generate(state.rand_mut()));
+        }
+
+        let size = input.bytes().len();
+        let range = if size == 0 || state.rand_mut().below(2) == 0 {
+            size..size
+        } else {
+            rand_range(state, size, min(size, payload.len()))
+        };
+
+        if size - range.len() + payload.len() > state.max_size() {
+            return Ok(MutationResult::Skipped);
+        }
+        input.bytes_mut().splice(range, payload);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for FmtStringMutator {
+    fn name(&self) -> &str {
+        "FmtStringMutator"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::rands::StdRand;
+
+    use super::{FormatStringPayloadSet, FORMAT_STRING_CONVERSIONS};
+    use crate::mutators::Tokens;
+
+    #[test]
+    fn test_format_string_payloads() {
+        let payloads = FormatStringPayloadSet::new();
+        let mut rand = StdRand::with_seed(1337);
+        for _ in 0..1000 {
+            let specifier = payloads.generate(&mut rand);
+            assert_eq!(specifier[0], b'%');
+            assert!(FORMAT_STRING_CONVERSIONS.contains(specifier.last().unwrap()));
+        }
+
+        let only_s = FormatStringPayloadSet::new().with_conversions(b"s");
+        for _ in 0..100 {
+            assert_eq!(*only_s.generate(&mut rand).last().unwrap(), b's');
+        }
+
+        let mut tokens = Tokens::new();
+        payloads.add_to_tokens(&mut tokens);
+        assert!(tokens.tokens().contains(&b"%n".to_vec()));
+    }
+}
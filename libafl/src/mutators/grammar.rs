@@ -0,0 +1,106 @@
+//! Mutators for [`GrammarInput`]s, operating on their parse trees.
+use alloc::vec::Vec;
+
+use libafl_bolts::{rands::Rand, Named};
+
+use crate::{
+    inputs::{Grammar, GrammarInput},
+    mutators::{MutationResult, Mutator},
+    state::HasRand,
+    Error,
+};
+
+/// A [`Mutator`] for [`GrammarInput`]s. Each mutation randomly does one of:
+/// - delete a subtree, replacing it with the smallest derivation of its rule,
+/// - replace a subtree with a randomly generated one,
+/// - swap two subtrees of the same rule.
+///
+/// Subtrees are only ever replaced by derivations of the same rule, so the mutated trees stay valid
+/// derivations of the [`Grammar`] and need no recognizer. Trees that fail to render are reverted
+/// and reported as [`MutationResult::Skipped`].
+#[derive(Debug)]
+pub struct GrammarMutator<'a> {
+    grammar: &'a Grammar,
+    max_depth: usize,
+}
+
+impl<'a, S> Mutator<GrammarInput, S> for GrammarMutator<'a>
+where
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut GrammarInput,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let mut paths = vec![];
+        input.root().paths(&mut vec![], &mut paths);
+        let path = state.rand_mut().choose(&paths).clone();
+        let node = input.root().get(&path).unwrap();
+
+        let backup = input.clone();
+        match state.rand_mut().below(3) {
+            0 => {
+                let minimal = self.grammar.minimal(node.rule);
+                if minimal == *node {
+                    return Ok(MutationResult::Skipped);
+                }
+                *input.root_mut().get_mut(&path).unwrap() = minimal;
+            }
+            1 => {
+                let generated = self
+                    .grammar
+                    .generate(state.rand_mut(), node.rule, self.max_depth);
+                if generated == *node {
+                    return Ok(MutationResult::Skipped);
+                }
+                *input.root_mut().get_mut(&path).unwrap() = generated;
+            }
+            _ => {
+                // only swap subtrees that don't contain each other
+                let candidates: Vec<&Vec<usize>> = paths
+                    .iter()
+                    .filter(|other| {
+                        !other.starts_with(&path)
+                            && !path.starts_with(other)
+                            && input.root().get(other).unwrap().rule == node.rule
+                    })
+                    .collect();
+                if candidates.is_empty() {
+                    return Ok(MutationResult::Skipped);
+                }
+                let other_path = state.rand_mut().choose(candidates).clone();
+                let other = input.root().get(&other_path).unwrap().clone();
+                if other == *node {
+                    return Ok(MutationResult::Skipped);
+                }
+                let node = node.clone();
+                *input.root_mut().get_mut(&other_path).unwrap() = node;
+                *input.root_mut().get_mut(&path).unwrap() = other;
+            }
+        }
+
+        let mut bytes = vec![];
+        if input.unparse(self.grammar, &mut bytes).is_err() {
+            *input = backup;
+            return Ok(MutationResult::Skipped);
+        }
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl<'a> Named for GrammarMutator<'a> {
+    fn name(&self) -> &str {
+        "GrammarMutator"
+    }
+}
+
+impl<'a> GrammarMutator<'a> {
+    /// Creates a new [`GrammarMutator`].
+    /// Generated subtrees only grow as much as needed to terminate below `max_depth`.
+    #[must_use]
+    pub fn new(grammar: &'a Grammar, max_depth: usize) -> Self {
+        Self { grammar, max_depth }
+    }
+}
@@ -0,0 +1,175 @@
+//! The [`HttpMutator`] mutates [`HttpRequestInput`]s part by part, keeping the request syntax intact.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use libafl_bolts::{rands::Rand, Named};
+
+use crate::{
+    inputs::HttpRequestInput,
+    mutators::{MutationResult, Mutator},
+    state::{HasMaxSize, HasRand},
+    Error,
+};
+
+/// The methods an [`HttpMutator`] changes the method to, including some rarely implemented ones
+pub const HTTP_METHODS: [&str; 10] = [
+    "GET", "HEAD", "POST", "PUT", "DELETE", "CONNECT", "OPTIONS", "TRACE", "PATCH", "PROPFIND",
+];
+
+/// The headers an [`HttpMutator`] adds, with values likely to reach special cases of the servers
+pub const HTTP_HEADERS: [(&str, &str); 12] = [
+    ("Host", "localhost"),
+    ("Content-Length", "0"),
+    ("Content-Length", "18446744073709551616"),
+    ("Transfer-Encoding", "chunked"),
+    ("Content-Type", "application/x-www-form-urlencoded"),
+    ("Content-Type", "multipart/form-data; boundary=x"),
+    ("Connection", "keep-alive"),
+    ("Expect", "100-continue"),
+    ("Range", "bytes=0-1,-1"),
+    ("Cookie", "a=b; a=c"),
+    ("Accept-Encoding", "gzip, deflate, br"),
+    ("Authorization", "Basic Og=="),
+];
+
+/// The sequences an [`HttpMutator`] injects into the path, escaping the document root in various encodings
+pub const PATH_TRAVERSALS: [&str; 6] = ["../", "..%2f", "%2e%2e/", "%2e%2e%2f", "..\\", "....//"];
+
+/// Mutates a random part of an [`HttpRequestInput`]: it flips a byte of a header value, adds or removes
+/// a header, mutates the body, changes the method, or injects a path traversal sequence into the path.
+///
+/// Header values stay valid UTF-8 and never get line breaks, so that the mutations do not destroy the header
+/// structure. Mutations growing the request beyond the maximum size of the state are skipped.
+#[derive(Debug, Default)]
+pub struct HttpMutator;
+
+impl HttpMutator {
+    /// Creates a new [`HttpMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Flips bits of a random byte of a random header value, as long as the value stays a valid single line
+#[allow(clippy::cast_possible_truncation)]
+fn flip_header_value<R: Rand>(rand: &mut R, input: &mut HttpRequestInput) -> MutationResult {
+    let candidates: Vec<usize> = (0..input.headers.len())
+        .filter(|idx| !input.headers[*idx].1.is_empty())
+        .collect();
+    if candidates.is_empty() {
+        return MutationResult::Skipped;
+    }
+    let idx = *rand.choose(&candidates);
+    let mut value = input.headers[idx].1.clone().into_bytes();
+    let pos = rand.below(value.len() as u64) as usize;
+    value[pos] ^= 1 + rand.below(255) as u8;
+    match String::from_utf8(value) {
+        Ok(value) if !value.contains(['\r', '\n']) => {
+            input.headers[idx].1 = value;
+            MutationResult::Mutated
+        }
+        _ => MutationResult::Skipped,
+    }
+}
+
+/// Mutates the body: flips a byte, inserts random bytes, or truncates it
+#[allow(clippy::cast_possible_truncation)]
+fn mutate_body<R: Rand>(rand: &mut R, body: &mut Vec<u8>, room: usize) -> MutationResult {
+    match rand.below(3) {
+        0 if !body.is_empty() => {
+            let pos = rand.below(body.len() as u64) as usize;
+            body[pos] ^= 1 + rand.below(255) as u8;
+        }
+        2 if !body.is_empty() => {
+            let len = rand.below(body.len() as u64) as usize;
+            body.truncate(len);
+        }
+        _ => {
+            let count = (1 + rand.below(16) as usize).min(room);
+            if count == 0 {
+                return MutationResult::Skipped;
+            }
+            let pos = rand.below(body.len() as u64 + 1) as usize;
+            let bytes: Vec<u8> = (0..count).map(|_| rand.below(256) as u8).collect();
+            body.splice(pos..pos, bytes);
+        }
+    }
+    MutationResult::Mutated
+}
+
+/// Inserts a path traversal sequence after a random `/` of the path, or at its start
+#[allow(clippy::cast_possible_truncation)]
+fn inject_path_traversal<R: Rand>(rand: &mut R, path: &mut String, room: usize) -> MutationResult {
+    let traversal = *rand.choose(&PATH_TRAVERSALS);
+    let count = 1 + rand.below(4) as usize;
+    if traversal.len() * count > room {
+        return MutationResult::Skipped;
+    }
+    let mut slashes: Vec<usize> = path.match_indices('/').map(|(pos, _)| pos + 1).collect();
+    slashes.push(0);
+    let pos = *rand.choose(&slashes);
+    path.insert_str(pos, &traversal.repeat(count));
+    MutationResult::Mutated
+}
+
+impl<S> Mutator<HttpRequestInput, S> for HttpMutator
+where
+    S: HasRand + HasMaxSize,
+{
+    #[allow(clippy::cast_possible_truncation)]
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut HttpRequestInput,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let room = state.max_size().saturating_sub(input.target_len());
+        let rand = state.rand_mut();
+        let result = match rand.below(6) {
+            0 => flip_header_value(rand, input),
+            1 => {
+                let (name, value) = *rand.choose(&HTTP_HEADERS);
+                if name.len() + value.len() + 4 > room {
+                    MutationResult::Skipped
+                } else {
+                    let pos = rand.below(input.headers.len() as u64 + 1) as usize;
+                    input
+                        .headers
+                        .insert(pos, (name.to_string(), value.to_string()));
+                    MutationResult::Mutated
+                }
+            }
+            2 => {
+                if input.headers.is_empty() {
+                    MutationResult::Skipped
+                } else {
+                    let pos = rand.below(input.headers.len() as u64) as usize;
+                    input.headers.remove(pos);
+                    MutationResult::Mutated
+                }
+            }
+            3 => mutate_body(rand, &mut input.body, room),
+            4 => {
+                let method = *rand.choose(&HTTP_METHODS);
+                if method == input.method || method.len() > input.method.len() + room {
+                    MutationResult::Skipped
+                } else {
+                    input.method = method.to_string();
+                    MutationResult::Mutated
+                }
+            }
+            _ => inject_path_traversal(rand, &mut input.path, room),
+        };
+        Ok(result)
+    }
+}
+
+impl Named for HttpMutator {
+    fn name(&self) -> &str {
+        "HttpMutator"
+    }
+}
@@ -0,0 +1,332 @@
+//! The [`JpegMutator`] mutates JPEG images at the level of their segments.
+//!
+//! Byte-level mutations of a JPEG mostly break its marker structure, so that parsers reject the image
+//! in the first header check. The [`JpegMutator`] instead scans the markers of the image, without decoding it,
+//! and mutates whole segments: it flips bytes inside of one, truncates one, duplicates one,
+//! or swaps two segments of the same type, fixing up the segment lengths where needed.
+
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use libafl_bolts::{rands::Rand, Error, Named};
+
+use crate::{
+    inputs::HasBytesVec,
+    mutators::{MutationResult, Mutator},
+    state::{HasMaxSize, HasRand},
+};
+
+/// The start of image marker
+pub const JPEG_SOI: u8 = 0xd8;
+/// The end of image marker
+pub const JPEG_EOI: u8 = 0xd9;
+/// The start of scan marker, followed by the entropy-coded image data
+pub const JPEG_SOS: u8 = 0xda;
+
+/// A segment of a JPEG image, found by [`parse_jpeg_segments`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JpegSegment {
+    /// The marker byte following the `0xff`
+    pub marker: u8,
+    /// The offset of the segment, including the `0xff` fill bytes that may precede its marker
+    pub start: usize,
+    /// The offset of the `0xff` of the marker itself, after the fill bytes
+    pub marker_start: usize,
+    /// The offset after the data covered by the length field, the same as `marker_start + 2` for markers without a length
+    pub header_end: usize,
+    /// The offset after the segment, including the entropy-coded data of a start of scan
+    pub end: usize,
+}
+
+impl JpegSegment {
+    /// Returns `true` if this segment has a length field, followed by its payload
+    #[must_use]
+    pub fn has_length(&self) -> bool {
+        !is_standalone_marker(self.marker)
+    }
+
+    /// The range of the payload, following the marker and the length field
+    #[must_use]
+    pub fn payload(&self) -> Range<usize> {
+        if self.has_length() {
+            (self.marker_start + 4).min(self.end)..self.end
+        } else {
+            self.end..self.end
+        }
+    }
+
+    /// The range of the whole segment
+    #[must_use]
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+}
+
+/// Markers without a length field: start and end of image, restart markers, and `TEM`
+fn is_standalone_marker(marker: u8) -> bool {
+    matches!(marker, 0x01 | 0xd0..=0xd9)
+}
+
+/// The end of the entropy-coded data starting at `pos`, i.e. the next marker other than a restart marker
+fn entropy_coded_end(bytes: &[u8], mut pos: usize) -> usize {
+    while pos + 1 < bytes.len() {
+        if bytes[pos] == 0xff && bytes[pos + 1] != 0x00 && !(0xd0..=0xd7).contains(&bytes[pos + 1])
+        {
+            return pos;
+        }
+        pos += 1;
+    }
+    bytes.len()
+}
+
+/// Scans the markers of a JPEG image, returning its segments in order, starting with the start of image.
+/// Returns `None` if `bytes` do not start with a start of image marker.
+/// The scan stops at the end of image marker, or at the first byte that is not a marker,
+/// a truncated last segment ends at the end of the input.
+#[must_use]
+pub fn parse_jpeg_segments(bytes: &[u8]) -> Option<Vec<JpegSegment>> {
+    if bytes.len() < 2 || bytes[0] != 0xff || bytes[1] != JPEG_SOI {
+        return None;
+    }
+    let mut segments = Vec::new();
+    let mut pos = 0;
+    while pos + 1 < bytes.len() && bytes[pos] == 0xff {
+        let start = pos;
+        // markers may be preceded by any number of fill bytes
+        while pos + 1 < bytes.len() && bytes[pos + 1] == 0xff {
+            pos += 1;
+        }
+        if pos + 1 >= bytes.len() {
+            break;
+        }
+        let marker_start = pos;
+        let marker = bytes[pos + 1];
+        pos += 2;
+
+        let mut segment = JpegSegment {
+            marker,
+            start,
+            marker_start,
+            header_end: pos,
+            end: pos,
+        };
+        if !is_standalone_marker(marker) {
+            if pos + 2 > bytes.len() {
+                segment.header_end = bytes.len();
+                segment.end = bytes.len();
+                segments.push(segment);
+                break;
+            }
+            let len = usize::from(u16::from_be_bytes([bytes[pos], bytes[pos + 1]]));
+            segment.header_end = (pos + len.max(2)).min(bytes.len());
+            segment.end = if marker == JPEG_SOS {
+                entropy_coded_end(bytes, segment.header_end)
+            } else {
+                segment.header_end
+            };
+        }
+        pos = segment.end;
+        segments.push(segment);
+        if marker == JPEG_EOI {
+            break;
+        }
+    }
+    Some(segments)
+}
+
+/// Mutates JPEG images at the segment level, see the [module documentation](self).
+///
+/// Inputs that are not JPEG images are skipped.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct JpegMutator;
+
+impl JpegMutator {
+    /// Creates a new [`JpegMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Flips the bits of a random byte of the payload of `segment`
+#[allow(clippy::cast_possible_truncation)]
+fn flip_segment_byte<R: Rand>(rand: &mut R, bytes: &mut [u8], segment: &JpegSegment) {
+    let payload = segment.payload();
+    let idx = payload.start + rand.below(payload.len() as u64) as usize;
+    bytes[idx] ^= 1 + rand.below(255) as u8;
+}
+
+/// Cuts the payload of `segment` short, fixing up its length field
+#[allow(clippy::cast_possible_truncation)]
+fn truncate_segment<R: Rand>(rand: &mut R, bytes: &mut Vec<u8>, segment: &JpegSegment) {
+    let payload = segment.payload();
+    let new_end = payload.start + rand.below(payload.len() as u64) as usize;
+    bytes.drain(new_end..segment.end);
+    if new_end < segment.header_end {
+        let len_offset = segment.marker_start + 2;
+        let len = u16::try_from(new_end - len_offset).unwrap_or(u16::MAX);
+        bytes[len_offset..len_offset + 2].copy_from_slice(&len.to_be_bytes());
+    }
+}
+
+/// Swaps the segments `a` and `b`, `a` preceding `b`
+fn swap_segments(bytes: &mut Vec<u8>, a: &JpegSegment, b: &JpegSegment) {
+    let mut swapped = Vec::with_capacity(bytes.len());
+    swapped.extend_from_slice(&bytes[..a.start]);
+    swapped.extend_from_slice(&bytes[b.range()]);
+    swapped.extend_from_slice(&bytes[a.end..b.start]);
+    swapped.extend_from_slice(&bytes[a.range()]);
+    swapped.extend_from_slice(&bytes[b.end..]);
+    *bytes = swapped;
+}
+
+impl<I, S> Mutator<I, S> for JpegMutator
+where
+    S: HasRand + HasMaxSize,
+    I: HasBytesVec,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let Some(segments) = parse_jpeg_segments(input.bytes()) else {
+            return Ok(MutationResult::Skipped);
+        };
+        // the start and end of image stay in place
+        let inner: Vec<JpegSegment> = segments
+            .into_iter()
+            .filter(|segment| segment.marker != JPEG_SOI && segment.marker != JPEG_EOI)
+            .collect();
+        if inner.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        let with_payload: Vec<&JpegSegment> = inner
+            .iter()
+            .filter(|segment| !segment.payload().is_empty())
+            .collect();
+
+        match state.rand_mut().below(4) {
+            0 => {
+                if with_payload.is_empty() {
+                    return Ok(MutationResult::Skipped);
+                }
+                let segment = *state.rand_mut().choose(&with_payload);
+                flip_segment_byte(state.rand_mut(), input.bytes_mut(), segment);
+            }
+            1 => {
+                if with_payload.is_empty() {
+                    return Ok(MutationResult::Skipped);
+                }
+                let segment = *state.rand_mut().choose(&with_payload);
+                truncate_segment(state.rand_mut(), input.bytes_mut(), segment);
+            }
+            2 => {
+                let segment = *state.rand_mut().choose(&inner);
+                if input.bytes().len() + segment.range().len() > state.max_size() {
+                    return Ok(MutationResult::Skipped);
+                }
+                let copy = input.bytes()[segment.range()].to_vec();
+                input.bytes_mut().splice(segment.end..segment.end, copy);
+            }
+            _ => {
+                let first = *state.rand_mut().choose(&inner);
+                let same_type: Vec<&JpegSegment> = inner
+                    .iter()
+                    .filter(|segment| {
+                        segment.marker == first.marker && segment.start != first.start
+                    })
+                    .collect();
+                if same_type.is_empty() {
+                    return Ok(MutationResult::Skipped);
+                }
+                let second = *state.rand_mut().choose(&same_type);
+                if input.bytes()[first.range()] == input.bytes()[second.range()] {
+                    return Ok(MutationResult::Skipped);
+                }
+                let (a, b) = if first.start < second.start {
+                    (&first, second)
+                } else {
+                    (second, &first)
+                };
+                swap_segments(input.bytes_mut(), a, b);
+            }
+        }
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for JpegMutator {
+    fn name(&self) -> &str {
+        "JpegMutator"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::rands::StdRand;
+
+    use super::{parse_jpeg_segments, swap_segments, truncate_segment, JPEG_EOI, JPEG_SOS};
+
+    /// SOI, two DQT segments, SOS with two bytes of entropy-coded data including a stuffed `0xff`, EOI
+    const JPEG: &[u8] = &[
+        0xff, 0xd8, //
+        0xff, 0xdb, 0x00, 0x04, 0x01, 0x02, //
+        0xff, 0xdb, 0x00, 0x03, 0x03, //
+        0xff, 0xda, 0x00, 0x03, 0x00, 0x12, 0xff, 0x00, //
+        0xff, 0xd9,
+    ];
+
+    #[test]
+    fn test_parse_jpeg_segments() {
+        assert!(parse_jpeg_segments(b"GIF89a").is_none());
+
+        let segments = parse_jpeg_segments(JPEG).unwrap();
+        assert_eq!(segments.len(), 5);
+        assert_eq!(segments[1].payload(), 6..8);
+        assert_eq!(segments[3].marker, JPEG_SOS);
+        assert_eq!(segments[3].header_end, 18);
+        assert_eq!(segments[3].end, 21);
+        assert_eq!(segments[4].marker, JPEG_EOI);
+    }
+
+    #[test]
+    fn test_jpeg_segment_mutations() {
+        let segments = parse_jpeg_segments(JPEG).unwrap();
+
+        let mut swapped = JPEG.to_vec();
+        swap_segments(&mut swapped, &segments[1], &segments[2]);
+        let reparsed = parse_jpeg_segments(&swapped).unwrap();
+        assert_eq!(reparsed.len(), 5);
+        assert_eq!(&swapped[2..7], &[0xff, 0xdb, 0x00, 0x03, 0x03]);
+
+        let mut truncated = JPEG.to_vec();
+        truncate_segment(&mut StdRand::with_seed(0), &mut truncated, &segments[1]);
+        let reparsed = parse_jpeg_segments(&truncated).unwrap();
+        assert_eq!(reparsed.len(), 5);
+        assert!(reparsed[1].payload().len() < 2);
+    }
+
+    #[test]
+    fn test_truncate_segment_after_fill_bytes() {
+        // SOI, a DQT segment preceded by two fill bytes, EOI
+        let jpeg = [
+            0xff, 0xd8, 0xff, 0xff, 0xff, 0xdb, 0x00, 0x05, 0x01, 0x02, 0x03, 0xff, 0xd9,
+        ];
+        let segments = parse_jpeg_segments(&jpeg).unwrap();
+        assert_eq!(segments[1].start, 2);
+        assert_eq!(segments[1].marker_start, 4);
+        assert_eq!(segments[1].payload(), 8..11);
+
+        let mut truncated = jpeg.to_vec();
+        truncate_segment(&mut StdRand::with_seed(0), &mut truncated, &segments[1]);
+        // the fill bytes and the marker are left alone, only the length field changes
+        assert_eq!(&truncated[2..6], &[0xff, 0xff, 0xff, 0xdb]);
+        let reparsed = parse_jpeg_segments(&truncated).unwrap();
+        assert_eq!(reparsed.len(), 3);
+        assert_eq!(reparsed[1].end, reparsed[2].start);
+        assert!(reparsed[1].payload().len() < 3);
+        assert_eq!(reparsed[2].marker, JPEG_EOI);
+    }
+}
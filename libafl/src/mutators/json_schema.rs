@@ -0,0 +1,394 @@
+//! A structure-aware mutator for JSON inputs, following a [JSON Schema](https://json-schema.org/) (draft 7).
+//!
+//! Byte-level mutations of JSON documents mostly produce documents rejected by the schema validation of the target,
+//! so the code behind it is rarely reached. The [`SchemaGuidedMutator`] walks the schema instead, and only generates
+//! values allowed by the `type`, `enum`, `const`, `minimum`, `maximum`, `minLength`, `maxLength`, `minItems`,
+//! `maxItems`, `properties` and `required` keywords. Local `$ref`s, `anyOf` and `oneOf` are followed as well.
+//!
+//! No strings are generated from a `pattern`: the mutated document is checked against the full schema with
+//! `jsonschema`, and discarded if invalid, e.g. because a random string does not match the pattern.
+
+use alloc::{string::String, vec::Vec};
+use std::{fs, path::Path};
+
+use jsonschema::{Draft, JSONSchema};
+use libafl_bolts::{rands::Rand, Named};
+use serde_json::{Map, Number, Value};
+
+use crate::{
+    inputs::{BytesInput, HasBytesVec},
+    mutators::{MutationResult, Mutator, INTERESTING_32},
+    state::HasRand,
+    Error,
+};
+
+/// The default depth up to which nested arrays and objects get mutated or generated
+pub const DEFAULT_JSON_SCHEMA_MAX_DEPTH: usize = 8;
+
+/// The maximum length of generated strings and arrays, unless the schema requires longer ones
+const MAX_GENERATED_LEN: u64 = 16;
+
+/// The number of mutations tried before giving up, if they are all invalid
+const MAX_ATTEMPTS: usize = 8;
+
+/// Mutates [`BytesInput`]s holding a JSON document, keeping them valid under a JSON Schema,
+/// see the [module documentation](self).
+///
+/// The input is parsed as JSON (an unparsable input, like an empty seed, is replaced by a generated document),
+/// and one value, possibly nested, is mutated: object properties get added, removed unless `required`, or mutated,
+/// arrays get truncated, extended, or have an element mutated, and other values are replaced with random ones of their schema.
+#[derive(Debug)]
+pub struct SchemaGuidedMutator {
+    schema: Value,
+    validator: JSONSchema,
+    max_depth: usize,
+}
+
+impl SchemaGuidedMutator {
+    /// Creates a new [`SchemaGuidedMutator`] for the given schema
+    pub fn new(schema: Value) -> Result<Self, Error> {
+        let validator = JSONSchema::options()
+            .with_draft(Draft::Draft7)
+            .compile(&schema)
+            .map_err(|err| Error::illegal_argument(format!("Invalid JSON schema: {err}")))?;
+        Ok(Self {
+            schema,
+            validator,
+            max_depth: DEFAULT_JSON_SCHEMA_MAX_DEPTH,
+        })
+    }
+
+    /// Creates a new [`SchemaGuidedMutator`] for the schema in the file at `path`
+    pub fn from_file<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        Self::new(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Descends at most `max_depth` arrays and objects deep
+    #[must_use]
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// The schema the inputs are kept valid under
+    #[must_use]
+    pub fn schema(&self) -> &Value {
+        &self.schema
+    }
+
+    /// Checks `document` against the schema
+    #[must_use]
+    pub fn is_valid(&self, document: &Value) -> bool {
+        self.validator.is_valid(document)
+    }
+}
+
+impl<S> Mutator<BytesInput, S> for SchemaGuidedMutator
+where
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut BytesInput,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let walker = SchemaWalker {
+            root: &self.schema,
+            max_depth: self.max_depth,
+        };
+        let parsed = serde_json::from_slice::<Value>(input.bytes()).ok();
+        for _ in 0..MAX_ATTEMPTS {
+            let document = match &parsed {
+                Some(document) => {
+                    let mut document = document.clone();
+                    walker.mutate(state.rand_mut(), &mut document, &self.schema, 0);
+                    document
+                }
+                None => walker.generate(state.rand_mut(), &self.schema, 0),
+            };
+            if parsed.as_ref() != Some(&document) && self.is_valid(&document) {
+                *input.bytes_mut() = serde_json::to_vec(&document)?;
+                return Ok(MutationResult::Mutated);
+            }
+        }
+        Ok(MutationResult::Skipped)
+    }
+}
+
+impl Named for SchemaGuidedMutator {
+    fn name(&self) -> &str {
+        "SchemaGuidedMutator"
+    }
+}
+
+/// Generates and mutates values of (sub-)schemas of the `root` schema
+struct SchemaWalker<'a> {
+    root: &'a Value,
+    max_depth: usize,
+}
+
+impl<'a> SchemaWalker<'a> {
+    /// Follows `$ref`s to the definitions in the root schema, and picks an alternative of `anyOf` and `oneOf`
+    fn resolve<R: Rand>(&self, rand: &mut R, mut schema: &'a Value) -> &'a Value {
+        // bounded, in case of cyclic references
+        for _ in 0..self.max_depth {
+            if let Some(pointer) = schema.get("$ref").and_then(Value::as_str) {
+                match pointer.strip_prefix('#').and_then(|p| self.root.pointer(p)) {
+                    Some(target) => schema = target,
+                    None => break,
+                }
+            } else if let Some(alternatives) = schema
+                .get("anyOf")
+                .or_else(|| schema.get("oneOf"))
+                .and_then(Value::as_array)
+                .filter(|alternatives| !alternatives.is_empty())
+            {
+                schema = rand.choose(alternatives);
+            } else {
+                break;
+            }
+        }
+        schema
+    }
+
+    /// Mutates `value` in place, following `schema`
+    fn mutate<R: Rand>(&self, rand: &mut R, value: &mut Value, schema: &'a Value, depth: usize) {
+        let schema = self.resolve(rand, schema);
+        if depth >= self.max_depth {
+            *value = self.generate(rand, schema, depth);
+            return;
+        }
+        match value {
+            Value::Object(object) if schema.get("properties").is_some() => {
+                self.mutate_object(rand, object, schema, depth);
+            }
+            Value::Array(array) if schema.get("items").is_some() && rand.below(4) != 0 => {
+                self.mutate_array(rand, array, schema, depth);
+            }
+            _ => *value = self.generate(rand, schema, depth),
+        }
+    }
+
+    /// Adds, removes, or mutates a random property of `object`
+    fn mutate_object<R: Rand>(
+        &self,
+        rand: &mut R,
+        object: &mut Map<String, Value>,
+        schema: &'a Value,
+        depth: usize,
+    ) {
+        let Some(properties) = schema
+            .get("properties")
+            .and_then(Value::as_object)
+            .filter(|properties| !properties.is_empty())
+        else {
+            return;
+        };
+        let (name, property) = rand.choose(properties);
+        let required = is_required(schema, name);
+        match object.get_mut(name) {
+            Some(_) if !required && rand.below(4) == 0 => {
+                object.remove(name);
+            }
+            Some(value) => self.mutate(rand, value, property, depth + 1),
+            None => {
+                object.insert(name.clone(), self.generate(rand, property, depth + 1));
+            }
+        }
+    }
+
+    /// Truncates, extends, or mutates an element of `array`, within `minItems` and `maxItems`
+    #[allow(clippy::cast_possible_truncation)]
+    fn mutate_array<R: Rand>(
+        &self,
+        rand: &mut R,
+        array: &mut Vec<Value>,
+        schema: &'a Value,
+        depth: usize,
+    ) {
+        let items = &schema["items"];
+        let (min_items, max_items) = length_bounds(schema, "minItems", "maxItems");
+        let len = array.len() as u64;
+        match rand.below(3) {
+            0 if len > min_items => array.truncate(rand.between(min_items, len - 1) as usize),
+            1 if len < max_items => array.push(self.generate(rand, items, depth + 1)),
+            _ if !array.is_empty() => {
+                let idx = rand.below(len) as usize;
+                self.mutate(rand, &mut array[idx], items, depth + 1);
+            }
+            _ => array.push(self.generate(rand, items, depth + 1)),
+        }
+    }
+
+    /// Generates a random value of `schema`
+    #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+    fn generate<R: Rand>(&self, rand: &mut R, schema: &'a Value, depth: usize) -> Value {
+        let schema = self.resolve(rand, schema);
+        if let Some(value) = schema.get("const") {
+            return value.clone();
+        }
+        if let Some(values) = schema
+            .get("enum")
+            .and_then(Value::as_array)
+            .filter(|values| !values.is_empty())
+        {
+            return rand.choose(values).clone();
+        }
+
+        let typ = match schema.get("type") {
+            Some(Value::String(typ)) => typ.as_str(),
+            Some(Value::Array(types)) if !types.is_empty() => {
+                rand.choose(types).as_str().unwrap_or("null")
+            }
+            _ if schema.get("properties").is_some() => "object",
+            _ if schema.get("items").is_some() => "array",
+            _ => "null",
+        };
+        match typ {
+            "boolean" => Value::Bool(rand.below(2) == 1),
+            "integer" => Value::from(random_integer(rand, schema)),
+            "number" => {
+                let (min, max) = number_bounds(schema);
+                let min = min.max(-1e9);
+                let max = max.min(1e9);
+                let value = min + (max - min) * (rand.below(1 << 20) as f64 / f64::from(1 << 20));
+                Number::from_f64(value).map_or(Value::Null, Value::Number)
+            }
+            "string" => {
+                let (min_len, max_len) = length_bounds(schema, "minLength", "maxLength");
+                let len = rand.between(
+                    min_len,
+                    max_len.min(min_len.saturating_add(MAX_GENERATED_LEN)),
+                );
+                Value::String(
+                    (0..len)
+                        .map(|_| char::from(rand.between(0x20, 0x7e) as u8))
+                        .collect(),
+                )
+            }
+            "array" if depth < self.max_depth => {
+                let (min_items, max_items) = length_bounds(schema, "minItems", "maxItems");
+                let len = rand.between(
+                    min_items,
+                    max_items.min(min_items.saturating_add(MAX_GENERATED_LEN)),
+                );
+                let items = schema.get("items").unwrap_or(&Value::Null);
+                Value::Array(
+                    (0..len)
+                        .map(|_| self.generate(rand, items, depth + 1))
+                        .collect(),
+                )
+            }
+            "object" if depth < self.max_depth => {
+                let mut object = Map::new();
+                if let Some(properties) = schema.get("properties").and_then(Value::as_object) {
+                    for (name, property) in properties {
+                        if is_required(schema, name) || rand.below(2) == 0 {
+                            object.insert(name.clone(), self.generate(rand, property, depth + 1));
+                        }
+                    }
+                }
+                Value::Object(object)
+            }
+            "array" => Value::Array(Vec::new()),
+            "object" => Value::Object(Map::new()),
+            _ => Value::Null,
+        }
+    }
+}
+
+/// If `name` is a `required` property of the object `schema`
+fn is_required(schema: &Value, name: &str) -> bool {
+    schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map_or(false, |required| {
+            required.iter().any(|r| r.as_str() == Some(name))
+        })
+}
+
+/// The bounds of a length, given by the `min` and `max` keywords of `schema`
+fn length_bounds(schema: &Value, min: &str, max: &str) -> (u64, u64) {
+    let min = schema.get(min).and_then(Value::as_u64).unwrap_or(0);
+    let max = schema.get(max).and_then(Value::as_u64).unwrap_or(u64::MAX);
+    (min, max.max(min))
+}
+
+/// The bounds of a number, given by `minimum` and `maximum`, or the draft 7 `exclusiveMinimum` and `exclusiveMaximum`.
+/// The exclusive bounds themselves may still be generated, and are then rejected by the validation.
+fn number_bounds(schema: &Value) -> (f64, f64) {
+    let bound = |key: &str| schema.get(key).and_then(Value::as_f64);
+    let min = bound("minimum")
+        .or_else(|| bound("exclusiveMinimum"))
+        .unwrap_or(f64::MIN);
+    let max = bound("maximum")
+        .or_else(|| bound("exclusiveMaximum"))
+        .unwrap_or(f64::MAX);
+    (min, max.max(min))
+}
+
+/// A random integer within the bounds of `schema`, preferring the bounds themselves and the interesting integers
+#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+fn random_integer<R: Rand>(rand: &mut R, schema: &Value) -> i64 {
+    let bound = |key: &str| schema.get(key).and_then(Value::as_i64);
+    let min = bound("minimum")
+        .or_else(|| bound("exclusiveMinimum").map(|min| min.saturating_add(1)))
+        .unwrap_or(i64::MIN);
+    let max = bound("maximum")
+        .or_else(|| bound("exclusiveMaximum").map(|max| max.saturating_sub(1)))
+        .unwrap_or(i64::MAX)
+        .max(min);
+    let value = match rand.below(4) {
+        0 => return min,
+        1 => return max,
+        2 => i64::from(rand.choose(INTERESTING_32)),
+        _ => rand.next() as i64,
+    };
+    if (min..=max).contains(&value) {
+        value
+    } else {
+        // the offset of `value` within the range, computed in 128 bit to not overflow
+        let span = i128::from(max) - i128::from(min) + 1;
+        (i128::from(min) + i128::from(value).rem_euclid(span)) as i64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::rands::StdRand;
+    use serde_json::json;
+
+    use super::{SchemaGuidedMutator, SchemaWalker};
+
+    #[test]
+    fn test_schema_guided_generation() {
+        let schema = json!({
+            "type": "object",
+            "required": ["id", "kind"],
+            "properties": {
+                "id": { "type": "integer", "minimum": 1, "maximum": 100 },
+                "kind": { "enum": ["a", "b"] },
+                "name": { "type": "string", "minLength": 2, "maxLength": 4 },
+                "tags": { "type": "array", "maxItems": 3, "items": { "$ref": "#/definitions/tag" } }
+            },
+            "definitions": {
+                "tag": { "type": "string", "maxLength": 8 }
+            }
+        });
+        let mutator = SchemaGuidedMutator::new(schema.clone()).unwrap();
+        let walker = SchemaWalker {
+            root: &schema,
+            max_depth: 8,
+        };
+        let mut rand = StdRand::with_seed(1337);
+        let mut document = walker.generate(&mut rand, &schema, 0);
+        for _ in 0..256 {
+            assert!(mutator.is_valid(&document), "invalid: {document}");
+            walker.mutate(&mut rand, &mut document, &schema, 0);
+        }
+    }
+}
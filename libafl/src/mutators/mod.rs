@@ -15,21 +15,75 @@ pub mod mopt_mutator;
 pub use mopt_mutator::*;
 pub mod gramatron;
 pub use gramatron::*;
+pub mod grammar;
+pub use grammar::GrammarMutator;
 pub mod grimoire;
 pub use grimoire::*;
 pub mod tuneable;
 pub use tuneable::*;
+pub mod scored;
+pub use scored::*;
+pub mod format_string;
+pub use format_string::{FmtStringMutator, FormatStringPayloadSet};
 
 #[cfg(feature = "unicode")]
 pub mod string;
 #[cfg(feature = "unicode")]
 pub use string::*;
 
+#[cfg(feature = "nlp")]
+pub mod nlp;
+#[cfg(feature = "nlp")]
+pub use nlp::{NlpSpliceMutator, NlpTokenMutator, NlpWordFrequencyMetadata};
+
+#[cfg(feature = "base64")]
+pub mod base64;
+#[cfg(feature = "base64")]
+pub use self::base64::Base64Mutator;
+
+#[cfg(feature = "jpeg_mutator")]
+pub mod jpeg;
+#[cfg(feature = "jpeg_mutator")]
+pub use jpeg::JpegMutator;
+
+#[cfg(feature = "radamsa")]
+pub mod radamsa;
+#[cfg(feature = "radamsa")]
+pub use radamsa::RadamsaMutator;
+
+pub mod pcap;
+pub use self::pcap::PacketSpliceMutator;
+
+#[cfg(feature = "http_input")]
+pub mod http;
+#[cfg(feature = "http_input")]
+pub use http::HttpMutator;
+
 #[cfg(feature = "multipart_inputs")]
 pub mod multi;
 #[cfg(feature = "multipart_inputs")]
 pub use multi::*;
 
+#[cfg(feature = "std")]
+pub mod regression;
+#[cfg(feature = "std")]
+pub use regression::RegressionMutator;
+
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
+#[cfg(feature = "protobuf")]
+pub use protobuf::*;
+
+#[cfg(feature = "json_schema")]
+pub mod json_schema;
+#[cfg(feature = "json_schema")]
+pub use json_schema::SchemaGuidedMutator;
+
+#[cfg(feature = "zip_mutator")]
+pub mod zip;
+#[cfg(feature = "zip_mutator")]
+pub use self::zip::ZipMutator;
+
 #[cfg(feature = "nautilus")]
 pub mod nautilus;
 
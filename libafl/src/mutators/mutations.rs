@@ -7,7 +7,7 @@ use libafl_bolts::{rands::Rand, Named};
 
 use crate::{
     corpus::Corpus,
-    inputs::{HasBytesVec, Input},
+    inputs::{HasBytesSlice, HasBytesVec, Input},
     mutators::{MutationResult, Mutator},
     random_corpus_id,
     state::{HasCorpus, HasMaxSize, HasRand},
@@ -153,14 +153,14 @@ impl BitFlipMutator {
     }
 }
 
-/// Byteflip mutation for inputs with a bytes vector
+/// Byteflip mutation for inputs with bytes, also of a fixed size
 #[derive(Default, Debug)]
 pub struct ByteFlipMutator;
 
 impl<I, S> Mutator<I, S> for ByteFlipMutator
 where
     S: HasRand,
-    I: HasBytesVec,
+    I: HasBytesSlice,
 {
     fn mutate(
         &mut self,
@@ -168,10 +168,10 @@ where
         input: &mut I,
         _stage_idx: i32,
     ) -> Result<MutationResult, Error> {
-        if input.bytes().is_empty() {
+        if input.bytes_slice().is_empty() {
             Ok(MutationResult::Skipped)
         } else {
-            *state.rand_mut().choose(input.bytes_mut()) ^= 0xff;
+            *state.rand_mut().choose(input.bytes_slice_mut()) ^= 0xff;
             Ok(MutationResult::Mutated)
         }
     }
@@ -308,14 +308,14 @@ impl ByteNegMutator {
     }
 }
 
-/// Byte random mutation for inputs with a bytes vector
+/// Byte random mutation for inputs with bytes, also of a fixed size
 #[derive(Default, Debug)]
 pub struct ByteRandMutator;
 
 impl<I, S> Mutator<I, S> for ByteRandMutator
 where
     S: HasRand,
-    I: HasBytesVec,
+    I: HasBytesSlice,
 {
     fn mutate(
         &mut self,
@@ -323,10 +323,10 @@ where
         input: &mut I,
         _stage_idx: i32,
     ) -> Result<MutationResult, Error> {
-        if input.bytes().is_empty() {
+        if input.bytes_slice().is_empty() {
             Ok(MutationResult::Skipped)
         } else {
-            let byte = state.rand_mut().choose(input.bytes_mut());
+            let byte = state.rand_mut().choose(input.bytes_slice_mut());
             *byte ^= 1 + state.rand_mut().below(254) as u8;
             Ok(MutationResult::Mutated)
         }
@@ -1285,14 +1285,15 @@ fn locate_diffs(this: &[u8], other: &[u8]) -> (i64, i64) {
     (first_diff, last_diff)
 }
 
-/// Splice mutation for inputs with a bytes vector
+/// Splice mutation for inputs with bytes.
+/// For inputs of a fixed size, the spliced bytes are clipped at the end of the input.
 #[derive(Debug, Default)]
 pub struct SpliceMutator;
 
 impl<S> Mutator<S::Input, S> for SpliceMutator
 where
     S: HasCorpus + HasRand,
-    S::Input: HasBytesVec,
+    S::Input: HasBytesSlice,
 {
     #[allow(clippy::cast_sign_loss)]
     fn mutate(
@@ -1313,7 +1314,7 @@ where
             let mut other_testcase = state.corpus().get(idx)?.borrow_mut();
             let other = other_testcase.load_input(state.corpus())?;
 
-            let (f, l) = locate_diffs(input.bytes(), other.bytes());
+            let (f, l) = locate_diffs(input.bytes_slice(), other.bytes_slice());
 
             if f != l && f >= 0 && l >= 2 {
                 (f as u64, l as u64)
@@ -1328,9 +1329,7 @@ where
         // Input will already be loaded.
         let other = other_testcase.input().as_ref().unwrap();
 
-        input
-            .bytes_mut()
-            .splice(split_at.., other.bytes()[split_at..].iter().copied());
+        input.splice_bytes(split_at, &other.bytes_slice()[split_at..]);
 
         Ok(MutationResult::Mutated)
     }
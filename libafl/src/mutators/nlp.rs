@@ -0,0 +1,321 @@
+//! Word-level mutators for text inputs, such as JSON, XML, or HTML documents.
+//!
+//! Byte-level mutations break the syntax of text formats most of the time. The [`NlpTokenMutator`] and
+//! the [`NlpSpliceMutator`] instead split UTF-8 inputs at the Unicode word boundaries, and replace, insert,
+//! delete, or splice whole words, keeping the punctuation and whitespace between them intact.
+//! Inputs that are not valid UTF-8 are skipped.
+
+use alloc::{
+    borrow::ToOwned,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::ops::Range;
+
+use hashbrown::{HashMap, HashSet};
+use libafl_bolts::{rands::Rand, Error, Named};
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    corpus::{Corpus, CorpusId},
+    inputs::HasBytesVec,
+    mutators::{MutationResult, Mutator},
+    random_corpus_id,
+    state::{HasCorpus, HasMaxSize, HasMetadata, HasRand},
+};
+
+/// The frequency of the words in the corpus, used by the [`NlpTokenMutator`]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[allow(clippy::unsafe_derive_deserialize)]
+pub struct NlpWordFrequencyMetadata {
+    /// The words with the number of their occurrences, in the order they were first seen
+    words: Vec<(String, u64)>,
+    /// The index of each word in `words`
+    index: HashMap<String, usize>,
+    /// The sum of all occurrences
+    total: u64,
+    /// The corpus entries the words were counted in
+    scanned: HashSet<CorpusId>,
+}
+
+libafl_bolts::impl_serdeany!(NlpWordFrequencyMetadata);
+
+impl NlpWordFrequencyMetadata {
+    /// Counts the words of `text`
+    pub fn add_text(&mut self, text: &str) {
+        for word in text.unicode_words() {
+            let idx = *self.index.entry(word.to_string()).or_insert_with(|| {
+                self.words.push((word.to_string(), 0));
+                self.words.len() - 1
+            });
+            self.words[idx].1 += 1;
+            self.total += 1;
+        }
+    }
+
+    /// The number of occurrences of `word`
+    #[must_use]
+    pub fn frequency(&self, word: &str) -> u64 {
+        self.index.get(word).map_or(0, |idx| self.words[*idx].1)
+    }
+
+    /// The number of distinct words
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    /// Returns `true` if no word was counted yet
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// The word covering the occurrence `nth` of all occurrences, so that words are picked by their frequency
+    fn word_at(&self, mut nth: u64) -> &str {
+        for (word, count) in &self.words {
+            if nth < *count {
+                return word;
+            }
+            nth -= count;
+        }
+        unreachable!("The occurrence is out of range")
+    }
+}
+
+/// Counts the words of the corpus entries not seen yet
+fn update_word_frequencies<S>(state: &mut S) -> Result<(), Error>
+where
+    S: HasCorpus + HasMetadata,
+    S::Input: HasBytesVec,
+{
+    if !state.has_metadata::<NlpWordFrequencyMetadata>() {
+        state.add_metadata(NlpWordFrequencyMetadata::default());
+    }
+    if state.metadata::<NlpWordFrequencyMetadata>()?.scanned.len() == state.corpus().count() {
+        return Ok(());
+    }
+
+    let mut texts = Vec::new();
+    {
+        let meta = state.metadata::<NlpWordFrequencyMetadata>()?;
+        for id in state.corpus().ids() {
+            if !meta.scanned.contains(&id) {
+                let input = state.corpus().cloned_input_for_id(id)?;
+                texts.push((id, input));
+            }
+        }
+    }
+    let meta = state.metadata_mut::<NlpWordFrequencyMetadata>()?;
+    for (id, input) in texts {
+        meta.scanned.insert(id);
+        if let Ok(text) = core::str::from_utf8(input.bytes()) {
+            meta.add_text(text);
+        }
+    }
+    Ok(())
+}
+
+/// The byte ranges of the segments of `text` between Unicode word boundaries, and which of them are words
+fn segments(text: &str) -> Vec<(Range<usize>, bool)> {
+    text.split_word_bound_indices()
+        .map(|(start, segment)| {
+            let is_word = segment.chars().any(char::is_alphanumeric);
+            (start..start + segment.len(), is_word)
+        })
+        .collect()
+}
+
+/// Replaces, inserts, or deletes a word of a UTF-8 input.
+/// The new words are picked from the words of the corpus, proportionally to their frequency.
+#[derive(Debug, Default)]
+pub struct NlpTokenMutator;
+
+impl NlpTokenMutator {
+    /// Creates a new [`NlpTokenMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<S> Mutator<S::Input, S> for NlpTokenMutator
+where
+    S: HasCorpus + HasMetadata + HasRand + HasMaxSize,
+    S::Input: HasBytesVec,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut S::Input,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        update_word_frequencies(state)?;
+
+        let Ok(text) = core::str::from_utf8(input.bytes()) else {
+            return Ok(MutationResult::Skipped);
+        };
+        let segments = segments(text);
+        let words: Vec<Range<usize>> = segments
+            .iter()
+            .filter(|(_, is_word)| *is_word)
+            .map(|(range, _)| range.clone())
+            .collect();
+
+        let total = state.metadata::<NlpWordFrequencyMetadata>()?.total;
+        let random_word = |state: &mut S| -> Result<String, Error> {
+            let nth = state.rand_mut().below(total);
+            Ok(state
+                .metadata::<NlpWordFrequencyMetadata>()?
+                .word_at(nth)
+                .to_owned())
+        };
+
+        let (range, replacement) = match state.rand_mut().below(3) {
+            // replace
+            0 => {
+                if words.is_empty() || total == 0 {
+                    return Ok(MutationResult::Skipped);
+                }
+                let range = state.rand_mut().choose(&words).clone();
+                (range, random_word(state)?)
+            }
+            // insert, at the boundary of any segment
+            1 => {
+                if total == 0 {
+                    return Ok(MutationResult::Skipped);
+                }
+                let at = state.rand_mut().below(segments.len() as u64 + 1) as usize;
+                let pos = segments
+                    .get(at)
+                    .map_or(text.len(), |(range, _)| range.start);
+                (pos..pos, random_word(state)?)
+            }
+            // delete
+            _ => {
+                if words.is_empty() {
+                    return Ok(MutationResult::Skipped);
+                }
+                (state.rand_mut().choose(&words).clone(), String::new())
+            }
+        };
+
+        if input.bytes().len() - range.len() + replacement.len() > state.max_size() {
+            return Ok(MutationResult::Skipped);
+        }
+        input.bytes_mut().splice(range, replacement.bytes());
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for NlpTokenMutator {
+    fn name(&self) -> &str {
+        "NlpTokenMutator"
+    }
+}
+
+/// Replaces a span of words of a UTF-8 input with a span of words of another corpus entry
+#[derive(Debug, Default)]
+pub struct NlpSpliceMutator;
+
+impl NlpSpliceMutator {
+    /// Creates a new [`NlpSpliceMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// A random span of whole segments, starting and ending at a word
+fn random_word_span<R>(rand: &mut R, segments: &[(Range<usize>, bool)]) -> Option<Range<usize>>
+where
+    R: Rand,
+{
+    let words: Vec<&Range<usize>> = segments
+        .iter()
+        .filter(|(_, is_word)| *is_word)
+        .map(|(range, _)| range)
+        .collect();
+    if words.is_empty() {
+        return None;
+    }
+    let first = rand.below(words.len() as u64) as usize;
+    let last = rand.between(first as u64, words.len() as u64 - 1) as usize;
+    Some(words[first].start..words[last].end)
+}
+
+impl<S> Mutator<S::Input, S> for NlpSpliceMutator
+where
+    S: HasCorpus + HasRand + HasMaxSize,
+    S::Input: HasBytesVec,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut S::Input,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        // We don't want to use the testcase we're already using for splicing
+        let idx = random_corpus_id!(state.corpus(), state.rand_mut());
+        if let Some(cur) = state.corpus().current() {
+            if idx == *cur {
+                return Ok(MutationResult::Skipped);
+            }
+        }
+
+        let Ok(text) = core::str::from_utf8(input.bytes()) else {
+            return Ok(MutationResult::Skipped);
+        };
+        let Some(range) = random_word_span(state.rand_mut(), &segments(text)) else {
+            return Ok(MutationResult::Skipped);
+        };
+
+        let other = state.corpus().cloned_input_for_id(idx)?;
+        let Ok(other_text) = core::str::from_utf8(other.bytes()) else {
+            return Ok(MutationResult::Skipped);
+        };
+        let Some(other_range) = random_word_span(state.rand_mut(), &segments(other_text)) else {
+            return Ok(MutationResult::Skipped);
+        };
+
+        if input.bytes().len() - range.len() + other_range.len() > state.max_size() {
+            return Ok(MutationResult::Skipped);
+        }
+        input
+            .bytes_mut()
+            .splice(range, other.bytes()[other_range].iter().copied());
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for NlpSpliceMutator {
+    fn name(&self) -> &str {
+        "NlpSpliceMutator"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{segments, NlpWordFrequencyMetadata};
+
+    #[test]
+    fn test_nlp_segments() {
+        let text = r#"{"key": "value value"}"#;
+        let words: Vec<&str> = segments(text)
+            .into_iter()
+            .filter(|(_, is_word)| *is_word)
+            .map(|(range, _)| &text[range])
+            .collect();
+        assert_eq!(words, ["key", "value", "value"]);
+
+        let mut meta = NlpWordFrequencyMetadata::default();
+        meta.add_text(text);
+        assert_eq!(meta.len(), 2);
+        assert_eq!(meta.frequency("value"), 2);
+        assert_eq!(meta.word_at(0), "key");
+        assert_eq!(meta.word_at(2), "value");
+    }
+}
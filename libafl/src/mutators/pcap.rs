@@ -0,0 +1,80 @@
+//! Mutators for the [`PcapInput`], working on whole packets.
+
+use libafl_bolts::{rands::Rand, Named};
+
+use crate::{
+    corpus::Corpus,
+    inputs::PcapInput,
+    mutators::{MutationResult, Mutator},
+    random_corpus_id,
+    state::{HasCorpus, HasMaxSize, HasRand},
+    Error,
+};
+
+/// Replaces a random packet of the input with a random packet of another [`PcapInput`] in the corpus,
+/// splicing packet sequences of different sessions.
+#[derive(Debug, Default)]
+pub struct PacketSpliceMutator;
+
+impl PacketSpliceMutator {
+    /// Creates a new [`PacketSpliceMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<S> Mutator<PcapInput, S> for PacketSpliceMutator
+where
+    S: HasCorpus<Input = PcapInput> + HasRand + HasMaxSize,
+{
+    #[allow(clippy::cast_possible_truncation)]
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut PcapInput,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        if input.packets().is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+
+        // We don't want to use the testcase we're already using for splicing
+        let idx = random_corpus_id!(state.corpus(), state.rand_mut());
+        if let Some(cur) = state.corpus().current() {
+            if idx == *cur {
+                return Ok(MutationResult::Skipped);
+            }
+        }
+
+        let other_count = {
+            let mut other_testcase = state.corpus().get(idx)?.borrow_mut();
+            other_testcase.load_input(state.corpus())?.packets().len()
+        };
+        if other_count == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let target = state.rand_mut().below(input.packets().len() as u64) as usize;
+        let source = state.rand_mut().below(other_count as u64) as usize;
+
+        let other_testcase = state.corpus().get(idx)?.borrow();
+        // No need to load the input again, it'll still be cached.
+        let packet = &other_testcase.input().as_ref().unwrap().packets()[source];
+
+        let replaced = &input.packets()[target];
+        if *packet == *replaced
+            || input.target_len() - replaced.data.len() + packet.data.len() > state.max_size()
+        {
+            return Ok(MutationResult::Skipped);
+        }
+        input.packets_mut()[target] = packet.clone();
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for PacketSpliceMutator {
+    fn name(&self) -> &str {
+        "PacketSpliceMutator"
+    }
+}
@@ -0,0 +1,234 @@
+//! A structure-aware mutator for protobuf-encoded inputs, using `prost-reflect` to walk the fields of the message.
+
+use alloc::{string::String, vec::Vec};
+use core::{
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+};
+
+use libafl_bolts::{rands::Rand, Named};
+use prost::{bytes::Bytes, Message};
+use prost_reflect::{DynamicMessage, FieldDescriptor, Kind, ReflectMessage, Value};
+
+use crate::{
+    inputs::{BytesInput, HasBytesVec},
+    mutators::{MutationResult, Mutator, INTERESTING_32},
+    state::HasRand,
+    Error,
+};
+
+/// The default depth up to which sub-messages get mutated
+pub const DEFAULT_PROTOBUF_MAX_DEPTH: usize = 8;
+
+/// The maximum length of generated strings and bytes fields
+const MAX_GENERATED_LEN: u64 = 32;
+
+/// Mutates [`BytesInput`]s holding an encoded protobuf message `M`, keeping them valid messages.
+///
+/// The input is decoded into `M` (an empty seed is the default message; inputs that fail to decode
+/// are left alone and reported as [`MutationResult::Skipped`]), and one field, possibly of a nested sub-message, is mutated:
+/// scalar fields get random values, repeated fields are truncated, extended or have an element mutated,
+/// and fields with presence (`optional`, sub-messages) are cleared.
+/// The mutated message is encoded back into the input.
+pub struct ProtobufMutator<M> {
+    max_depth: usize,
+    phantom: PhantomData<M>,
+}
+
+impl<M> Debug for ProtobufMutator<M> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProtobufMutator")
+            .field("max_depth", &self.max_depth)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<M> Default for ProtobufMutator<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M> ProtobufMutator<M> {
+    /// Creates a new [`ProtobufMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_max_depth(DEFAULT_PROTOBUF_MAX_DEPTH)
+    }
+
+    /// Creates a new [`ProtobufMutator`], descending at most `max_depth` sub-messages deep
+    #[must_use]
+    pub fn with_max_depth(max_depth: usize) -> Self {
+        Self {
+            max_depth,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<M, S> Mutator<BytesInput, S> for ProtobufMutator<M>
+where
+    M: ReflectMessage + Default,
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut BytesInput,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let Ok(message) = M::decode(input.bytes()) else {
+            return Ok(MutationResult::Skipped);
+        };
+        let mut message = message.transcode_to_dynamic();
+        if !mutate_message(state.rand_mut(), &mut message, self.max_depth) {
+            return Ok(MutationResult::Skipped);
+        }
+        *input.bytes_mut() = message.encode_to_vec();
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl<M> Named for ProtobufMutator<M> {
+    fn name(&self) -> &str {
+        "ProtobufMutator"
+    }
+}
+
+/// Mutates a random field of `message`, returns `false` if it has no fields
+fn mutate_message<R: Rand>(rand: &mut R, message: &mut DynamicMessage, depth: usize) -> bool {
+    let fields: Vec<FieldDescriptor> = message.descriptor().fields().collect();
+    if fields.is_empty() {
+        return false;
+    }
+    let field = rand.choose(fields);
+
+    if field.is_map() {
+        // entries are not generated, but dropping them still explores smaller messages
+        message.clear_field(&field);
+    } else if field.is_list() {
+        let kind = field.kind();
+        let Value::List(list) = message.get_field_mut(&field) else {
+            unreachable!("repeated fields hold lists");
+        };
+        match rand.below(3) {
+            0 if !list.is_empty() => list.truncate(rand.below(list.len() as u64) as usize),
+            1 if !list.is_empty() => {
+                let idx = rand.below(list.len() as u64) as usize;
+                mutate_value(rand, &kind, &mut list[idx], depth);
+            }
+            _ => list.push(random_value(rand, &kind)),
+        }
+    } else if field.supports_presence() && message.has_field(&field) && rand.below(4) == 0 {
+        message.clear_field(&field);
+    } else {
+        // sets the default first, for fields not present yet
+        let value = message.get_field_mut(&field);
+        mutate_value(rand, &field.kind(), value, depth);
+    }
+    true
+}
+
+/// Mutates a single value, recursing into sub-messages up to `depth`
+fn mutate_value<R: Rand>(rand: &mut R, kind: &Kind, value: &mut Value, depth: usize) {
+    match value {
+        Value::Message(sub_message) if depth > 0 => {
+            if !mutate_message(rand, sub_message, depth - 1) {
+                *value = random_value(rand, kind);
+            }
+        }
+        _ => *value = random_value(rand, kind),
+    }
+}
+
+/// A random value of the given kind, sub-messages are empty
+fn random_value<R: Rand>(rand: &mut R, kind: &Kind) -> Value {
+    // prefer the interesting integers, like the havoc mutations do
+    let int = |rand: &mut R| {
+        if rand.below(2) == 0 {
+            i64::from(rand.choose(INTERESTING_32))
+        } else {
+            rand.next() as i64
+        }
+    };
+    match kind {
+        Kind::Bool => Value::Bool(rand.below(2) == 1),
+        Kind::Int32 | Kind::Sint32 | Kind::Sfixed32 => Value::I32(int(rand) as i32),
+        Kind::Int64 | Kind::Sint64 | Kind::Sfixed64 => Value::I64(int(rand)),
+        Kind::Uint32 | Kind::Fixed32 => Value::U32(int(rand) as u32),
+        Kind::Uint64 | Kind::Fixed64 => Value::U64(int(rand) as u64),
+        Kind::Float => Value::F32(f32::from_bits(rand.next() as u32)),
+        Kind::Double => Value::F64(f64::from_bits(rand.next())),
+        Kind::String => {
+            let len = rand.below(MAX_GENERATED_LEN);
+            Value::String(
+                (0..len)
+                    .map(|_| char::from(rand.between(0x20, 0x7e) as u8))
+                    .collect::<String>(),
+            )
+        }
+        Kind::Bytes => {
+            let len = rand.below(MAX_GENERATED_LEN);
+            Value::Bytes(Bytes::from(
+                (0..len).map(|_| rand.next() as u8).collect::<Vec<u8>>(),
+            ))
+        }
+        Kind::Enum(descriptor) => {
+            let numbers: Vec<i32> = descriptor.values().map(|value| value.number()).collect();
+            // unknown enum values are valid on the wire, and often badly handled
+            if numbers.is_empty() || rand.below(8) == 0 {
+                Value::EnumNumber(int(rand) as i32)
+            } else {
+                Value::EnumNumber(rand.choose(numbers))
+            }
+        }
+        Kind::Message(descriptor) => Value::Message(DynamicMessage::new(descriptor.clone())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+    use prost_types::Timestamp;
+
+    use super::ProtobufMutator;
+    use crate::{
+        inputs::{BytesInput, HasBytesVec},
+        mutators::{MutationResult, Mutator},
+        state::test::test_std_state,
+    };
+
+    #[test]
+    fn test_protobuf_mutator() {
+        let mut state = test_std_state::<BytesInput>();
+        let mut mutator = ProtobufMutator::<Timestamp>::new();
+
+        // a field tag without its value does not decode, and must not be replaced by a default message
+        let mut input = BytesInput::new(vec![0x08]);
+        assert_eq!(
+            mutator.mutate(&mut state, &mut input, 0).unwrap(),
+            MutationResult::Skipped
+        );
+        assert_eq!(input.bytes(), &[0x08]);
+
+        let seed = Timestamp {
+            seconds: 42,
+            nanos: 7,
+        };
+        let mut input = BytesInput::new(seed.encode_to_vec());
+        for _ in 0..100 {
+            assert_eq!(
+                mutator.mutate(&mut state, &mut input, 0).unwrap(),
+                MutationResult::Mutated
+            );
+            Timestamp::decode(input.bytes()).unwrap();
+        }
+
+        // the empty seed is the default message
+        let mut input = BytesInput::new(vec![]);
+        assert_eq!(
+            mutator.mutate(&mut state, &mut input, 0).unwrap(),
+            MutationResult::Mutated
+        );
+    }
+}
@@ -0,0 +1,241 @@
+//! The [`RadamsaMutator`] hands the input to the [radamsa](https://gitlab.com/akihe/radamsa) fuzzer,
+//! and replaces it with the mutated output.
+//!
+//! radamsa knows many mutations of text-based formats, such as numbers, lines, and nested structures.
+//! It is not part of the havoc mutations, but can be merged with them:
+//!
+//! ```rust,ignore
+//! let mutator = StdScheduledMutator::new(havoc_mutations().merge(tuple_list!(RadamsaMutator::new())));
+//! ```
+
+use alloc::{string::ToString, vec::Vec};
+use std::{
+    io::{ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+    time::Duration,
+};
+
+use libafl_bolts::{rands::Rand, Named};
+use wait_timeout::ChildExt;
+
+use crate::{
+    inputs::HasBytesVec,
+    mutators::{ByteRandMutator, MutationResult, Mutator},
+    state::{HasMaxSize, HasRand},
+    Error,
+};
+
+/// The default timeout of a radamsa call
+pub const DEFAULT_RADAMSA_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Mutates the input by calling radamsa as a subprocess, see the [module documentation](self).
+///
+/// If radamsa cannot be found, the mutator warns once and falls back to the [`ByteRandMutator`].
+#[derive(Debug)]
+pub struct RadamsaMutator {
+    radamsa: PathBuf,
+    timeout: Duration,
+    /// Set once radamsa was not found
+    missing: bool,
+    fallback: ByteRandMutator,
+}
+
+impl RadamsaMutator {
+    /// Creates a new [`RadamsaMutator`], calling the `radamsa` found in `PATH`
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_path("radamsa")
+    }
+
+    /// Creates a new [`RadamsaMutator`], calling radamsa at the given path
+    #[must_use]
+    pub fn with_path<P: AsRef<Path>>(radamsa: P) -> Self {
+        Self {
+            radamsa: radamsa.as_ref().to_path_buf(),
+            timeout: DEFAULT_RADAMSA_TIMEOUT,
+            missing: false,
+            fallback: ByteRandMutator::new(),
+        }
+    }
+
+    /// Sets the time after which a radamsa call is killed, and the mutation skipped
+    #[must_use]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Runs radamsa on `input` with the given seed, `None` if it was not found
+    fn run_radamsa(&self, input: &[u8], seed: u64) -> Result<Option<Vec<u8>>, Error> {
+        let mut child = match Command::new(&self.radamsa)
+            .arg("--seed")
+            .arg(seed.to_string())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        // read concurrently, radamsa blocks once the pipe is full
+        let mut stdout = child.stdout.take().unwrap();
+        let reader = thread::spawn(move || {
+            let mut output = Vec::new();
+            stdout.read_to_end(&mut output).map(|_| output)
+        });
+
+        let mut stdin = child.stdin.take().unwrap();
+        if let Err(err) = stdin.write_all(input) {
+            if err.kind() != ErrorKind::BrokenPipe {
+                return Err(err.into());
+            }
+        }
+        drop(stdin);
+
+        let timed_out = child.wait_timeout(self.timeout)?.is_none();
+        if timed_out {
+            drop(child.kill());
+            drop(child.wait());
+            log::debug!("radamsa timed out after {:?}", self.timeout);
+        }
+        let output = reader
+            .join()
+            .map_err(|_| Error::unknown("The radamsa reader thread panicked"))??;
+        // the partial output of a killed radamsa is not used, an empty output skips the mutation
+        Ok(Some(if timed_out { Vec::new() } else { output }))
+    }
+}
+
+impl Default for RadamsaMutator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I, S> Mutator<I, S> for RadamsaMutator
+where
+    S: HasRand + HasMaxSize,
+    I: HasBytesVec,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        if !self.missing {
+            let seed = state.rand_mut().next();
+            match self.run_radamsa(input.bytes(), seed)? {
+                Some(mut output) => {
+                    output.truncate(state.max_size());
+                    if output.is_empty() || output == input.bytes() {
+                        return Ok(MutationResult::Skipped);
+                    }
+                    *input.bytes_mut() = output;
+                    return Ok(MutationResult::Mutated);
+                }
+                None => {
+                    log::warn!(
+                        "radamsa not found at {}, falling back to the ByteRandMutator",
+                        self.radamsa.display()
+                    );
+                    self.missing = true;
+                }
+            }
+        }
+        self.fallback.mutate(state, input, stage_idx)
+    }
+}
+
+impl Named for RadamsaMutator {
+    fn name(&self) -> &str {
+        "RadamsaMutator"
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{
+        env, fs,
+        os::unix::fs::PermissionsExt,
+        path::{Path, PathBuf},
+        process,
+        time::Duration,
+    };
+
+    use super::RadamsaMutator;
+    use crate::{
+        inputs::{BytesInput, HasBytesVec},
+        mutators::{MutationResult, Mutator},
+        state::{test::test_std_state, HasMaxSize},
+    };
+
+    /// Writes a shell script standing in for radamsa, it gets the input on stdin
+    fn fake_radamsa(dir: &Path, name: &str, script: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, format!("#!/bin/sh\n{script}\n")).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_radamsa_mutator() {
+        let dir = env::temp_dir().join(format!("libafl_test_radamsa_{}", process::id()));
+        drop(fs::remove_dir_all(&dir));
+        fs::create_dir_all(&dir).unwrap();
+        let mut state = test_std_state::<BytesInput>();
+
+        // (radamsa, the mutation result, the input after the mutation)
+        for (radamsa, result, bytes) in [
+            (
+                fake_radamsa(&dir, "upper", "tr a-z A-Z"),
+                MutationResult::Mutated,
+                &b"HELLO"[..],
+            ),
+            (
+                fake_radamsa(&dir, "same", "cat"),
+                MutationResult::Skipped,
+                b"hello",
+            ),
+            (
+                fake_radamsa(&dir, "hang", "exec sleep 10"),
+                MutationResult::Skipped,
+                b"hello",
+            ),
+        ] {
+            let mut mutator =
+                RadamsaMutator::with_path(radamsa).with_timeout(Duration::from_millis(500));
+            let mut input = BytesInput::new(b"hello".to_vec());
+            assert_eq!(mutator.mutate(&mut state, &mut input, 0).unwrap(), result);
+            assert_eq!(input.bytes(), bytes);
+        }
+
+        // the output is truncated to the maximum size
+        state.set_max_size(3);
+        let mut mutator = RadamsaMutator::with_path(dir.join("upper"));
+        let mut input = BytesInput::new(b"hello".to_vec());
+        assert_eq!(
+            mutator.mutate(&mut state, &mut input, 0).unwrap(),
+            MutationResult::Mutated
+        );
+        assert_eq!(input.bytes(), b"HEL");
+
+        // without radamsa, a random byte is replaced instead
+        let mut mutator = RadamsaMutator::with_path(dir.join("missing"));
+        let mut input = BytesInput::new(b"hello".to_vec());
+        assert_eq!(
+            mutator.mutate(&mut state, &mut input, 0).unwrap(),
+            MutationResult::Mutated
+        );
+        assert!(mutator.missing);
+        assert_eq!(input.bytes().len(), 5);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,274 @@
+//! The [`RegressionMutator`] keeps re-testing the reproducers of known crashes,
+//! by mutating them instead of the scheduled corpus entry every now and then.
+
+use alloc::{string::String, vec::Vec};
+use core::{
+    fmt::{self, Debug},
+    marker::PhantomData,
+};
+use std::{fs, path::Path};
+
+use libafl_bolts::{rands::Rand, Named};
+
+use crate::{
+    corpus::CorpusId,
+    inputs::Input,
+    mutators::{
+        scheduled::{ComposedByMutations, ScheduledMutator},
+        MutationId, MutationResult, Mutator, MutatorsTuple,
+    },
+    state::HasRand,
+    Error,
+};
+
+/// A [`ScheduledMutator`] wrapper that, with a given probability, replaces the input to mutate
+/// with one of the known crashing inputs of a regression suite.
+///
+/// The crashing inputs are loaded from a directory when the mutator is created.
+/// The wrapped mutator then mutates the substituted input as usual, so variants of fixed bugs keep getting tested
+/// no matter how the corpus evolves.
+pub struct RegressionMutator<I, MT, S, SM>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand,
+    SM: ScheduledMutator<I, MT, S>,
+{
+    name: String,
+    scheduled: SM,
+    crashes: Vec<I>,
+    probability: f32,
+    phantom: PhantomData<(MT, S)>,
+}
+
+impl<I, MT, S, SM> Debug for RegressionMutator<I, MT, S, SM>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand,
+    SM: ScheduledMutator<I, MT, S>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "RegressionMutator with {} known crashes and probability {} for Input type {}",
+            self.crashes.len(),
+            self.probability,
+            core::any::type_name::<I>()
+        )
+    }
+}
+
+impl<I, MT, S, SM> Named for RegressionMutator<I, MT, S, SM>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand,
+    SM: ScheduledMutator<I, MT, S>,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<I, MT, S, SM> Mutator<I, S> for RegressionMutator<I, MT, S, SM>
+where
+    I: Clone,
+    MT: MutatorsTuple<I, S>,
+    S: HasRand,
+    SM: ScheduledMutator<I, MT, S>,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let substituted = self.substitute(state, input);
+        let result = self.scheduled_mutate(state, input, stage_idx)?;
+        if substituted {
+            // the input differs from the scheduled one, even if no mutation applied
+            Ok(MutationResult::Mutated)
+        } else {
+            Ok(result)
+        }
+    }
+
+    fn post_exec(
+        &mut self,
+        state: &mut S,
+        stage_idx: i32,
+        corpus_idx: Option<CorpusId>,
+    ) -> Result<(), Error> {
+        self.scheduled.post_exec(state, stage_idx, corpus_idx)
+    }
+}
+
+impl<I, MT, S, SM> ComposedByMutations<I, MT, S> for RegressionMutator<I, MT, S, SM>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand,
+    SM: ScheduledMutator<I, MT, S>,
+{
+    #[inline]
+    fn mutations(&self) -> &MT {
+        self.scheduled.mutations()
+    }
+
+    #[inline]
+    fn mutations_mut(&mut self) -> &mut MT {
+        self.scheduled.mutations_mut()
+    }
+}
+
+impl<I, MT, S, SM> ScheduledMutator<I, MT, S> for RegressionMutator<I, MT, S, SM>
+where
+    I: Clone,
+    MT: MutatorsTuple<I, S>,
+    S: HasRand,
+    SM: ScheduledMutator<I, MT, S>,
+{
+    #[inline]
+    fn iterations(&self, state: &mut S, input: &I) -> u64 {
+        self.scheduled.iterations(state, input)
+    }
+
+    #[inline]
+    fn schedule(&self, state: &mut S, input: &I) -> MutationId {
+        self.scheduled.schedule(state, input)
+    }
+
+    #[inline]
+    fn scheduled_mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        self.scheduled.scheduled_mutate(state, input, stage_idx)
+    }
+}
+
+impl<I, MT, S, SM> RegressionMutator<I, MT, S, SM>
+where
+    I: Input,
+    MT: MutatorsTuple<I, S>,
+    S: HasRand,
+    SM: ScheduledMutator<I, MT, S>,
+{
+    /// Creates a new [`RegressionMutator`], loading the known crashing inputs from the files in `crash_dir`.
+    /// Before each mutation, the input is replaced by one of them with the given `probability`, between `0` and `1`.
+    pub fn new<P: AsRef<Path>>(
+        scheduled: SM,
+        crash_dir: P,
+        probability: f32,
+    ) -> Result<Self, Error> {
+        let mut crashes = Vec::new();
+        for entry in fs::read_dir(crash_dir)? {
+            let path = entry?.path();
+            // skip the metadata and lock files of the on-disk corpora
+            let hidden = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .map_or(true, |name| name.starts_with('.'));
+            if hidden || !path.is_file() {
+                continue;
+            }
+            crashes.push(I::from_file(&path)?);
+        }
+        Ok(Self::with_crashes(scheduled, crashes, probability))
+    }
+}
+
+impl<I, MT, S, SM> RegressionMutator<I, MT, S, SM>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand,
+    SM: ScheduledMutator<I, MT, S>,
+{
+    /// Creates a new [`RegressionMutator`] from already loaded crashing inputs
+    pub fn with_crashes(scheduled: SM, crashes: Vec<I>, probability: f32) -> Self {
+        Self {
+            name: format!("RegressionMutator[{}]", scheduled.name()),
+            scheduled,
+            crashes,
+            probability,
+            phantom: PhantomData,
+        }
+    }
+
+    /// The known crashing inputs
+    #[must_use]
+    pub fn crashes(&self) -> &[I] {
+        &self.crashes
+    }
+
+    /// Replaces `input` with a random known crash with the configured probability, returns if it did
+    fn substitute(&self, state: &mut S, input: &mut I) -> bool
+    where
+        I: Clone,
+    {
+        if self.crashes.is_empty() {
+            return false;
+        }
+        #[allow(clippy::cast_precision_loss)]
+        let coin = state.rand_mut().next() as f32 / u64::MAX as f32;
+        if coin >= self.probability {
+            return false;
+        }
+        let idx = state.rand_mut().below(self.crashes.len() as u64) as usize;
+        input.clone_from(&self.crashes[idx]);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use std::{env, fs, process};
+
+    use libafl_bolts::tuples::tuple_list;
+
+    use super::RegressionMutator;
+    use crate::{
+        inputs::{BytesInput, HasBytesVec},
+        mutators::{BitFlipMutator, MutationResult, Mutator, StdScheduledMutator},
+        state::test::test_std_state,
+    };
+
+    #[test]
+    fn test_regression_mutator() {
+        let dir = env::temp_dir().join(format!("libafl_test_regression_{}", process::id()));
+        drop(fs::remove_dir_all(&dir));
+        fs::create_dir_all(dir.join("subdir")).unwrap();
+        fs::write(dir.join("crash-1"), b"AAAA").unwrap();
+        // the metadata files of the on-disk corpora and directories are skipped
+        fs::write(dir.join(".crash-1.metadata"), b"{}").unwrap();
+
+        let mut state = test_std_state::<BytesInput>();
+        let scheduled = StdScheduledMutator::new(tuple_list!(BitFlipMutator::new()));
+        let mut mutator = RegressionMutator::new(scheduled, &dir, 1.0).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(mutator.crashes().len(), 1);
+
+        // the bit flips keep the length, so the length tells if the known crash was mutated
+        let mut input = BytesInput::new(b"xyz".to_vec());
+        assert_eq!(
+            mutator.mutate(&mut state, &mut input, 0).unwrap(),
+            MutationResult::Mutated
+        );
+        assert_eq!(input.bytes().len(), 4);
+
+        let scheduled = StdScheduledMutator::new(tuple_list!(BitFlipMutator::new()));
+        let mut mutator = RegressionMutator::with_crashes(
+            scheduled,
+            vec![BytesInput::new(b"AAAA".to_vec())],
+            0.0,
+        );
+        let lens: Vec<usize> = (0..16)
+            .map(|_| {
+                let mut input = BytesInput::new(b"xyz".to_vec());
+                mutator.mutate(&mut state, &mut input, 0).unwrap();
+                input.bytes().len()
+            })
+            .collect();
+        assert_eq!(lens, [3; 16]);
+    }
+}
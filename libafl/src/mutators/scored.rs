@@ -0,0 +1,437 @@
+//! A [`ScheduledMutator`] that learns which of its mutations are effective.
+//! Each mutation gets a score computed by a [`MutationScore`] from its statistics,
+//! by default its running success rate (how often it was part of a stack that led to a new corpus entry),
+//! and the mutations are sampled with a softmax over the scores.
+
+use alloc::{string::String, vec::Vec};
+use core::{
+    fmt::{self, Debug},
+    marker::PhantomData,
+};
+
+use libafl_bolts::{impl_serdeany, rands::Rand, Named};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::CorpusId,
+    mutators::{
+        ComposedByMutations, MutationId, MutationResult, Mutator, MutatorsTuple, ScheduledMutator,
+    },
+    state::{HasNamedMetadata, HasRand},
+    Error,
+};
+
+/// The default temperature of the softmax used by the [`ScoredHavocMutator`]
+pub const DEFAULT_SCORED_HAVOC_TEMPERATURE: f64 = 0.5;
+
+/// Named metadata in the state, holding the statistics a [`ScoredHavocMutator`] computes its scores from.
+/// Each [`ScoredHavocMutator`] keeps its own under its name. Being part of the state, the scores survive restarts.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct ScoredHavocMetadata {
+    /// How many stacks each mutation was applied in, by [`MutationId`]
+    pub applications: Vec<u64>,
+    /// How many of those stacks led to a new corpus entry, by [`MutationId`]
+    pub successes: Vec<u64>,
+}
+
+impl_serdeany!(ScoredHavocMetadata);
+
+impl ScoredHavocMetadata {
+    /// Creates the metadata for `len` mutations, none of which was applied yet
+    #[must_use]
+    pub fn new(len: usize) -> Self {
+        Self {
+            applications: vec![0; len],
+            successes: vec![0; len],
+        }
+    }
+
+    /// The cumulative distribution of the softmax over the scores computed by `F`, normalized by the highest score,
+    /// at the given `temperature`. Lower temperatures favour the best mutations more strongly.
+    #[must_use]
+    pub fn cumulative_distribution<F>(&self, temperature: f64) -> Vec<f64>
+    where
+        F: MutationScore,
+    {
+        let scores: Vec<f64> = (0..self.applications.len())
+            .map(|id| F::compute(self, id.into()))
+            .collect();
+        let max = scores.iter().copied().fold(f64::MIN_POSITIVE, f64::max);
+
+        let mut cumulative: Vec<f64> = scores
+            .iter()
+            .map(|score| libm::exp((score / max - 1.0) / temperature))
+            .collect();
+        let mut acc = 0.0;
+        for weight in &mut cumulative {
+            acc += *weight;
+            *weight = acc;
+        }
+        for weight in &mut cumulative {
+            *weight /= acc;
+        }
+        cumulative
+    }
+}
+
+/// Compute the score of a mutation from the [`ScoredHavocMetadata`]. Higher is better.
+pub trait MutationScore {
+    /// Computes the score of the mutation `id`. Higher is better, the score must not be negative.
+    fn compute(meta: &ScoredHavocMetadata, id: MutationId) -> f64;
+}
+
+/// The success rate of a mutation, smoothed so that mutations applied only a few times
+/// are neither written off nor preferred too early.
+#[derive(Debug, Clone)]
+pub struct SuccessRateMutationScore;
+
+impl MutationScore for SuccessRateMutationScore {
+    #[allow(clippy::cast_precision_loss)]
+    fn compute(meta: &ScoredHavocMetadata, id: MutationId) -> f64 {
+        (meta.successes[id.0] + 1) as f64 / (meta.applications[id.0] + 2) as f64
+    }
+}
+
+/// A [`Mutator`] that stacks mutations like the [`super::StdScheduledMutator`],
+/// but samples them according to the scores computed by the [`MutationScore`] `F` instead of uniformly.
+///
+/// Use it with [`super::havoc_mutations`] as the mutations to get an adaptive havoc.
+/// The statistics are kept in the state, under the name of the mutator: only [`ScoredHavocMutator`]s
+/// with the same mutations share them.
+pub struct ScoredHavocMutator<I, MT, S, F>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand + HasNamedMetadata,
+    F: MutationScore,
+{
+    name: String,
+    mutations: MT,
+    max_stack_pow: u64,
+    temperature: f64,
+    mutation_log: Vec<MutationId>,
+    phantom: PhantomData<(I, S, F)>,
+}
+
+/// The standard [`ScoredHavocMutator`], scoring the mutations by their [`SuccessRateMutationScore`]
+pub type StdScoredHavocMutator<I, MT, S> = ScoredHavocMutator<I, MT, S, SuccessRateMutationScore>;
+
+impl<I, MT, S, F> Debug for ScoredHavocMutator<I, MT, S, F>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand + HasNamedMetadata,
+    F: MutationScore,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "ScoredHavocMutator with {} mutations at temperature {} for Input type {}",
+            self.mutations.len(),
+            self.temperature,
+            core::any::type_name::<I>()
+        )
+    }
+}
+
+impl<I, MT, S, F> Named for ScoredHavocMutator<I, MT, S, F>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand + HasNamedMetadata,
+    F: MutationScore,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<I, MT, S, F> Mutator<I, S> for ScoredHavocMutator<I, MT, S, F>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand + HasNamedMetadata,
+    F: MutationScore,
+{
+    #[inline]
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        self.scheduled_mutate(state, input, stage_idx)
+    }
+
+    fn post_exec(
+        &mut self,
+        state: &mut S,
+        _stage_idx: i32,
+        corpus_idx: Option<CorpusId>,
+    ) -> Result<(), Error> {
+        if corpus_idx.is_some() {
+            let meta = state.named_metadata_mut::<ScoredHavocMetadata>(&self.name)?;
+            for id in &self.mutation_log {
+                meta.successes[id.0] += 1;
+            }
+        }
+        // Always reset the log for each run
+        self.mutation_log.clear();
+        Ok(())
+    }
+}
+
+impl<I, MT, S, F> ComposedByMutations<I, MT, S> for ScoredHavocMutator<I, MT, S, F>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand + HasNamedMetadata,
+    F: MutationScore,
+{
+    /// Get the mutations
+    #[inline]
+    fn mutations(&self) -> &MT {
+        &self.mutations
+    }
+
+    // Get the mutations (mutable)
+    #[inline]
+    fn mutations_mut(&mut self) -> &mut MT {
+        &mut self.mutations
+    }
+}
+
+impl<I, MT, S, F> ScheduledMutator<I, MT, S> for ScoredHavocMutator<I, MT, S, F>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand + HasNamedMetadata,
+    F: MutationScore,
+{
+    /// Compute the number of iterations used to apply stacked mutations
+    fn iterations(&self, state: &mut S, _: &I) -> u64 {
+        1 << (1 + state.rand_mut().below(self.max_stack_pow))
+    }
+
+    /// Get the next mutation to apply, uniformly if the statistics are not in the state
+    fn schedule(&self, state: &mut S, _: &I) -> MutationId {
+        debug_assert!(self.mutations.len() != 0);
+        match state.named_metadata::<ScoredHavocMetadata>(&self.name) {
+            Ok(meta) => {
+                let cumulative = meta.cumulative_distribution::<F>(self.temperature);
+                self.sample(state, &cumulative)
+            }
+            Err(_) => state.rand_mut().below(self.mutations.len() as u64).into(),
+        }
+    }
+
+    fn scheduled_mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let mut r = MutationResult::Skipped;
+        let num = self.iterations(state, input);
+        self.mutation_log.clear();
+
+        // The scores only change in `post_exec`, so the distribution is the same for the whole stack.
+        let cumulative = state
+            .named_metadata::<ScoredHavocMetadata>(&self.name)?
+            .cumulative_distribution::<F>(self.temperature);
+
+        for _ in 0..num {
+            let idx = self.sample(state, &cumulative);
+            let outcome = self
+                .mutations_mut()
+                .get_and_mutate(idx, state, input, stage_idx)?;
+            if outcome == MutationResult::Mutated {
+                r = MutationResult::Mutated;
+                if !self.mutation_log.contains(&idx) {
+                    self.mutation_log.push(idx);
+                }
+            }
+        }
+
+        let meta = state.named_metadata_mut::<ScoredHavocMetadata>(&self.name)?;
+        for id in &self.mutation_log {
+            meta.applications[id.0] += 1;
+        }
+        Ok(r)
+    }
+}
+
+impl<I, MT, S, F> ScoredHavocMutator<I, MT, S, F>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand + HasNamedMetadata,
+    F: MutationScore,
+{
+    /// Create a new [`ScoredHavocMutator`] instance specifying mutations,
+    /// using the [`DEFAULT_SCORED_HAVOC_TEMPERATURE`]
+    pub fn new(state: &mut S, mutations: MT) -> Self {
+        Self::with_temperature(state, mutations, DEFAULT_SCORED_HAVOC_TEMPERATURE)
+    }
+
+    /// Create a new [`ScoredHavocMutator`] instance specifying mutations and the temperature of the softmax
+    ///
+    /// # Panics
+    /// Panics if the temperature is not positive
+    pub fn with_temperature(state: &mut S, mutations: MT, temperature: f64) -> Self {
+        assert!(
+            temperature > 0.0,
+            "The temperature of a ScoredHavocMutator has to be positive"
+        );
+        let len = mutations.len();
+        let name = format!("ScoredHavocMutator[{}]", mutations.names().join(", "));
+        if let Ok(meta) = state.named_metadata_mut::<ScoredHavocMetadata>(&name) {
+            // the metadata may come from a previous run, never drop the statistics of a mutation
+            if meta.applications.len() < len {
+                meta.applications.resize(len, 0);
+                meta.successes.resize(len, 0);
+            }
+        } else {
+            state.add_named_metadata(ScoredHavocMetadata::new(len), &name);
+        }
+        Self {
+            name,
+            mutations,
+            max_stack_pow: 7,
+            temperature,
+            mutation_log: vec![],
+            phantom: PhantomData,
+        }
+    }
+
+    /// The temperature of the softmax
+    #[must_use]
+    pub fn temperature(&self) -> f64 {
+        self.temperature
+    }
+
+    /// Sets the temperature of the softmax. Lower temperatures favour the best mutations more strongly.
+    ///
+    /// # Panics
+    /// Panics if the temperature is not positive
+    pub fn set_temperature(&mut self, temperature: f64) {
+        assert!(
+            temperature > 0.0,
+            "The temperature of a ScoredHavocMutator has to be positive"
+        );
+        self.temperature = temperature;
+    }
+
+    /// Samples a [`MutationId`] from a cumulative distribution,
+    /// ignoring the statistics of mutations this mutator does not have
+    fn sample(&self, state: &mut S, cumulative: &[f64]) -> MutationId {
+        let cumulative = &cumulative[..self.mutations.len().min(cumulative.len())];
+        #[allow(clippy::cast_precision_loss)]
+        let coin =
+            state.rand_mut().next() as f64 / u64::MAX as f64 * cumulative[cumulative.len() - 1];
+        cumulative
+            .iter()
+            .position(|p| *p >= coin)
+            .unwrap_or(cumulative.len() - 1)
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::{tuples::tuple_list, HasLen, Named};
+
+    use super::{
+        MutationScore, ScoredHavocMetadata, StdScoredHavocMutator, SuccessRateMutationScore,
+    };
+    use crate::{
+        corpus::CorpusId,
+        inputs::BytesInput,
+        mutators::{
+            mutations::{BitFlipMutator, ByteFlipMutator, ByteIncMutator},
+            ComposedByMutations, MutationId, Mutator,
+        },
+        state::{test::test_std_state, HasNamedMetadata},
+    };
+
+    /// Prefers the mutations applied the least, whatever their successes
+    struct RarelyAppliedScore;
+
+    impl MutationScore for RarelyAppliedScore {
+        #[allow(clippy::cast_precision_loss)]
+        fn compute(meta: &ScoredHavocMetadata, id: MutationId) -> f64 {
+            1.0 / (meta.applications[id.0] + 1) as f64
+        }
+    }
+
+    #[test]
+    fn test_scored_distribution() {
+        let mut meta = ScoredHavocMetadata::new(3);
+        let cumulative = meta.cumulative_distribution::<SuccessRateMutationScore>(0.5);
+        assert!((cumulative[0] - 1.0 / 3.0).abs() < 1e-9);
+        assert!((cumulative[2] - 1.0).abs() < 1e-9);
+
+        // mutation 1 was successful, 0 and 2 were not
+        meta.applications = vec![100, 100, 100];
+        meta.successes = vec![0, 10, 0];
+        let cumulative = meta.cumulative_distribution::<SuccessRateMutationScore>(0.5);
+        let p1 = cumulative[1] - cumulative[0];
+        assert!(p1 > cumulative[0]);
+        assert!(p1 > 1.0 - cumulative[1]);
+
+        // a lower temperature favours it more
+        let colder = meta.cumulative_distribution::<SuccessRateMutationScore>(0.1);
+        assert!(colder[1] - colder[0] > p1);
+    }
+
+    #[test]
+    fn test_custom_mutation_score() {
+        let mut meta = ScoredHavocMetadata::new(3);
+        // mutation 2 was applied the least, but was never successful
+        meta.applications = vec![100, 100, 1];
+        meta.successes = vec![50, 50, 0];
+
+        let success_rate = meta.cumulative_distribution::<SuccessRateMutationScore>(0.5);
+        let rarely_applied = meta.cumulative_distribution::<RarelyAppliedScore>(0.5);
+        assert!(1.0 - success_rate[1] < success_rate[0]);
+        assert!(1.0 - rarely_applied[1] > rarely_applied[0]);
+    }
+
+    #[test]
+    fn test_scored_havoc_mutators_keep_their_statistics() {
+        let mut state = test_std_state::<BytesInput>();
+        let mut havoc = StdScoredHavocMutator::new(
+            &mut state,
+            tuple_list!(
+                BitFlipMutator::new(),
+                ByteFlipMutator::new(),
+                ByteIncMutator::new()
+            ),
+        );
+        let mut bit_flip =
+            StdScoredHavocMutator::new(&mut state, tuple_list!(BitFlipMutator::new()));
+
+        let mut input = BytesInput::new(vec![0; 16]);
+        for _ in 0..10 {
+            havoc.mutate(&mut state, &mut input, 0).unwrap();
+            havoc
+                .post_exec(&mut state, 0, Some(CorpusId::from(0_usize)))
+                .unwrap();
+            bit_flip.mutate(&mut state, &mut input, 0).unwrap();
+            bit_flip.post_exec(&mut state, 0, None).unwrap();
+        }
+
+        let havoc_meta = state
+            .named_metadata::<ScoredHavocMetadata>(havoc.name())
+            .unwrap();
+        assert_eq!(havoc_meta.applications.len(), havoc.mutations().len());
+        assert!(havoc_meta.successes.iter().sum::<u64>() > 0);
+        let bit_flip_meta = state
+            .named_metadata::<ScoredHavocMetadata>(bit_flip.name())
+            .unwrap();
+        assert_eq!(bit_flip_meta.applications, vec![10]);
+        assert_eq!(bit_flip_meta.successes, vec![0]);
+
+        // without its statistics, the mutator fails instead of panicking
+        assert!(bit_flip
+            .mutate(&mut test_std_state(), &mut input, 0)
+            .is_err());
+    }
+}
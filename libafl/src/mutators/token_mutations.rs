@@ -25,7 +25,9 @@ use crate::mutators::str_decode;
 use crate::{
     inputs::{HasBytesVec, UsesInput},
     mutators::{
-        buffer_self_copy, mutations::buffer_copy, MultiMutator, MutationResult, Mutator, Named,
+        buffer_self_copy,
+        mutations::{buffer_copy, rand_range},
+        MultiMutator, MutationResult, Mutator, Named,
     },
     observers::cmp::{AFLppCmpValuesMetadata, CmpValues, CmpValuesMetadata},
     stages::TaintMetadata,
@@ -426,6 +428,72 @@ impl TokenReplace {
     }
 }
 
+/// A `DictionaryMutator` replaces a random span of the input with one of its own tokens.
+/// Unlike [`TokenReplace`], the tokens are owned by the mutator instead of being read from the [`Tokens`] metadata,
+/// so it can be fed directly from an AFL-format dictionary file.
+#[derive(Debug, Default, Clone)]
+pub struct DictionaryMutator {
+    tokens: Vec<Vec<u8>>,
+}
+
+impl<I, S> Mutator<I, S> for DictionaryMutator
+where
+    S: HasRand + HasMaxSize,
+    I: HasBytesVec,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let size = input.bytes().len();
+        if size == 0 || self.tokens.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let token_idx = state.rand_mut().below(self.tokens.len() as u64) as usize;
+        let token = &self.tokens[token_idx];
+        let range = rand_range(state, size, size);
+
+        if size - range.len() + token.len() > state.max_size() {
+            return Ok(MutationResult::Skipped);
+        }
+
+        input.bytes_mut().splice(range, token.iter().copied());
+
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for DictionaryMutator {
+    fn name(&self) -> &str {
+        "DictionaryMutator"
+    }
+}
+
+impl DictionaryMutator {
+    /// Creates a new `DictionaryMutator` using the given tokens.
+    #[must_use]
+    pub fn new(tokens: Vec<Vec<u8>>) -> Self {
+        Self { tokens }
+    }
+
+    /// Creates a new `DictionaryMutator` from an AFL-format dictionary file.
+    /// Both plain `"literal"` and `keyword@level="literal"` entries are supported.
+    #[cfg(feature = "std")]
+    pub fn from_afl_file(path: &Path) -> Result<Self, Error> {
+        let tokens = Tokens::from_file(path)?;
+        Ok(Self::new(tokens.tokens().to_vec()))
+    }
+
+    /// Gets the tokens used by this mutator
+    #[must_use]
+    pub fn tokens(&self) -> &[Vec<u8>] {
+        &self.tokens
+    }
+}
+
 /// A `I2SRandReplace` [`Mutator`] replaces a random matching input-2-state comparison operand with the other.
 /// It needs a valid [`CmpValuesMetadata`] in the state.
 #[derive(Debug, Default)]
@@ -615,6 +683,96 @@ impl I2SRandReplace {
     }
 }
 
+/// Returns the pairs of `(operand, replacement)` bytes to look for in the input for a logged comparison,
+/// in both directions and, for integers, in both byte orders
+fn cmp_operand_replacements(values: &CmpValues) -> Vec<(Vec<u8>, Vec<u8>)> {
+    let (v0, v1) = match values {
+        CmpValues::U8((v0, v1)) => (vec![*v0], vec![*v1]),
+        CmpValues::U16((v0, v1)) => (v0.to_ne_bytes().to_vec(), v1.to_ne_bytes().to_vec()),
+        CmpValues::U32((v0, v1)) => (v0.to_ne_bytes().to_vec(), v1.to_ne_bytes().to_vec()),
+        CmpValues::U64((v0, v1)) => (v0.to_ne_bytes().to_vec(), v1.to_ne_bytes().to_vec()),
+        CmpValues::Bytes((v0, v1)) => {
+            let len = core::cmp::min(v0.len(), v1.len());
+            (v0[..len].to_vec(), v1[..len].to_vec())
+        }
+    };
+    if v0.is_empty() || v0 == v1 {
+        return vec![];
+    }
+
+    let mut replacements = vec![(v0.clone(), v1.clone()), (v1.clone(), v0.clone())];
+    if values.is_numeric() && v0.len() > 1 {
+        let (mut v0, mut v1) = (v0, v1);
+        v0.reverse();
+        v1.reverse();
+        replacements.push((v0.clone(), v1.clone()));
+        replacements.push((v1, v0));
+    }
+    replacements
+}
+
+/// A [`CmpLogMutator`] makes one side of a logged comparison match the input.
+/// It collects every place in the input that holds an operand of the comparisons in the
+/// [`CmpValuesMetadata`], e.g. logged by a [`crate::observers::CmpOperandsObserver`],
+/// and overwrites a random one of them with the other operand.
+#[derive(Debug, Default)]
+pub struct CmpLogMutator;
+
+impl<I, S> Mutator<I, S> for CmpLogMutator
+where
+    S: HasMetadata + HasRand,
+    I: HasBytesVec,
+{
+    #[allow(clippy::cast_possible_truncation)]
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let Some(meta) = state.metadata_map().get::<CmpValuesMetadata>() else {
+            return Ok(MutationResult::Skipped);
+        };
+
+        let bytes = input.bytes();
+        let mut candidates = vec![];
+        for values in &meta.list {
+            for (operand, replacement) in cmp_operand_replacements(values) {
+                if operand.len() > bytes.len() {
+                    continue;
+                }
+                for (off, window) in bytes.windows(operand.len()).enumerate() {
+                    if window == operand.as_slice() {
+                        candidates.push((off, replacement.clone()));
+                    }
+                }
+            }
+        }
+        if candidates.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let (off, replacement) =
+            candidates.swap_remove(state.rand_mut().below(candidates.len() as u64) as usize);
+        input.bytes_mut()[off..off + replacement.len()].copy_from_slice(&replacement);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for CmpLogMutator {
+    fn name(&self) -> &str {
+        "CmpLogMutator"
+    }
+}
+
+impl CmpLogMutator {
+    /// Creates a new [`CmpLogMutator`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
 const CMP_ATTTRIBUTE_IS_EQUAL: u8 = 1;
 const CMP_ATTRIBUTE_IS_GREATER: u8 = 2;
 const CMP_ATTRIBUTE_IS_LESSER: u8 = 4;
@@ -1870,7 +2028,17 @@ mod tests {
     use std::fs;
 
     #[cfg(feature = "std")]
-    use super::{AFLppRedQueen, Tokens};
+    use std::path::Path;
+
+    #[cfg(feature = "std")]
+    use super::{AFLppRedQueen, CmpLogMutator, DictionaryMutator, Tokens};
+    #[cfg(feature = "std")]
+    use crate::{
+        inputs::{BytesInput, HasBytesVec},
+        mutators::{MutationResult, Mutator},
+        observers::{CmpValues, CmpValuesMetadata},
+        state::{test::test_std_state, HasMetadata},
+    };
 
     #[cfg(feature = "std")]
     #[test]
@@ -1890,6 +2058,62 @@ token2="B"
         let _res = fs::remove_file("test.tkns");
     }
 
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_dictionary_mutator() {
+        let _res = fs::remove_file("test_dict.tkns");
+        let data = r#"
+# comment
+kw1@1="AAA"
+kw2="\x42\x42"
+        "#;
+        fs::write("test_dict.tkns", data).expect("Unable to write test_dict.tkns");
+        let mut mutator = DictionaryMutator::from_afl_file(Path::new("test_dict.tkns")).unwrap();
+        let _res = fs::remove_file("test_dict.tkns");
+        assert_eq!(mutator.tokens(), &[b"AAA".to_vec(), b"BB".to_vec()]);
+
+        let mut state = test_std_state::<BytesInput>();
+
+        let mut input = BytesInput::new(vec![0; 16]);
+        let result = mutator.mutate(&mut state, &mut input, 0).unwrap();
+        assert_eq!(result, MutationResult::Mutated);
+        assert!(input.bytes().windows(2).any(|w| w == b"AA" || w == b"BB"));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_cmplog_mutator() {
+        let mut state = test_std_state::<BytesInput>();
+        let mut mutator = CmpLogMutator::new();
+
+        let mut input = BytesInput::new(b"xxABCDxx".to_vec());
+        assert_eq!(
+            mutator.mutate(&mut state, &mut input, 0).unwrap(),
+            MutationResult::Skipped
+        );
+
+        let mut meta = CmpValuesMetadata::new();
+        meta.list.push(CmpValues::U32((
+            u32::from_ne_bytes(*b"ABCD"),
+            u32::from_ne_bytes(*b"WXYZ"),
+        )));
+        meta.list.push(CmpValues::U8((b'q', b'r')));
+        state.add_metadata(meta);
+
+        assert_eq!(
+            mutator.mutate(&mut state, &mut input, 0).unwrap(),
+            MutationResult::Mutated
+        );
+        assert_eq!(input.bytes(), b"xxWXYZxx");
+
+        let mut input = BytesInput::new(b"xxDCBAxx".to_vec());
+        assert_eq!(
+            mutator.mutate(&mut state, &mut input, 0).unwrap(),
+            MutationResult::Mutated
+        );
+        assert_eq!(input.bytes(), b"xxZYXWxx");
+    }
+
     #[cfg(feature = "std")]
     #[test]
     fn test_token_mutations() {
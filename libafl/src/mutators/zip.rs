@@ -0,0 +1,261 @@
+//! The [`ZipMutator`] mutates the files inside of ZIP archives, e.g. office documents, JAR files, or APKs.
+//!
+//! Byte-level mutations of a ZIP archive mostly hit the compressed data, or break the checksums and the
+//! central directory, so that the target rejects the archive before looking at its contents.
+//! The [`ZipMutator`] instead inflates the archive, lets an inner mutator mutate one of the contained files,
+//! and writes the archive again, with a new central directory and checksums.
+
+use alloc::{string::String, vec::Vec};
+use std::io::{Cursor, Read, Write};
+
+use libafl_bolts::{rands::Rand, Named};
+use zip_archive::{
+    result::ZipError, write::FileOptions, CompressionMethod, DateTime, ZipArchive, ZipWriter,
+};
+
+use crate::{
+    corpus::CorpusId,
+    inputs::{BytesInput, HasBytesVec},
+    mutators::{MutationResult, Mutator},
+    state::{HasMaxSize, HasRand},
+    Error,
+};
+
+/// A file or directory of a ZIP archive, read by [`read_zip`]
+#[derive(Debug, Clone)]
+struct ZipEntry {
+    name: String,
+    is_dir: bool,
+    /// The compression method, modification time and permissions of the entry, kept when writing it again
+    compression: CompressionMethod,
+    last_modified: DateTime,
+    unix_mode: Option<u32>,
+    data: Vec<u8>,
+}
+
+impl ZipEntry {
+    /// The options to write the entry with, as it was read
+    fn options(&self) -> FileOptions {
+        let options = FileOptions::default()
+            .compression_method(self.compression)
+            .last_modified_time(self.last_modified);
+        match self.unix_mode {
+            Some(mode) => options.unix_permissions(mode),
+            None => options,
+        }
+    }
+}
+
+/// Inflates all entries of the ZIP archive in `bytes`, returning them in order, and the comment of the archive.
+/// Returns `None` if `bytes` are no ZIP archive, or one of its entries cannot be inflated,
+/// e.g. because it is encrypted or compressed with a method other than deflate.
+fn read_zip(bytes: &[u8]) -> Option<(Vec<ZipEntry>, Vec<u8>)> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes)).ok()?;
+    let mut entries = Vec::with_capacity(archive.len());
+    for idx in 0..archive.len() {
+        let mut file = archive.by_index(idx).ok()?;
+        let mut data = Vec::new();
+        file.read_to_end(&mut data).ok()?;
+        entries.push(ZipEntry {
+            name: file.name().into(),
+            is_dir: file.is_dir(),
+            compression: file.compression(),
+            last_modified: file.last_modified(),
+            unix_mode: file.unix_mode(),
+            data,
+        });
+    }
+    let comment = archive.comment().to_vec();
+    Some((entries, comment))
+}
+
+/// Writes a ZIP archive of `entries`, with the given comment
+fn write_zip(entries: &[ZipEntry], comment: Vec<u8>) -> Result<Vec<u8>, Error> {
+    let zip_err = |err: ZipError| Error::unknown(format!("Failed to write a ZIP archive: {err}"));
+
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    for entry in entries {
+        if entry.is_dir {
+            writer
+                .add_directory(entry.name.as_str(), entry.options())
+                .map_err(zip_err)?;
+        } else {
+            // mutated files may outgrow the 4 GiB of a plain entry
+            let options = entry
+                .options()
+                .large_file(entry.data.len() >= u32::MAX as usize);
+            writer
+                .start_file(entry.name.as_str(), options)
+                .map_err(zip_err)?;
+            writer.write_all(&entry.data)?;
+        }
+    }
+    writer.set_raw_comment(comment);
+    Ok(writer.finish().map_err(zip_err)?.into_inner())
+}
+
+/// Applies the inner mutator to a random file inside of a ZIP archive, and writes the archive again.
+///
+/// All other entries are kept as they are, with their names, order, compression methods, and modification times.
+/// Inputs that are not ZIP archives, or whose entries cannot be inflated, are mutated by the inner mutator as they are.
+/// Mutations whose rewritten archive exceeds the maximum size of the state are skipped.
+#[derive(Debug)]
+pub struct ZipMutator<M> {
+    inner: M,
+}
+
+impl<M> ZipMutator<M> {
+    /// Creates a new [`ZipMutator`], mutating the contained files with `inner`
+    #[must_use]
+    pub fn new(inner: M) -> Self {
+        Self { inner }
+    }
+
+    /// The inner mutator
+    #[must_use]
+    pub fn inner(&self) -> &M {
+        &self.inner
+    }
+}
+
+impl<I, M, S> Mutator<I, S> for ZipMutator<M>
+where
+    I: HasBytesVec,
+    M: Mutator<BytesInput, S>,
+    S: HasRand + HasMaxSize,
+{
+    #[allow(clippy::cast_possible_truncation)]
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let archive = read_zip(input.bytes()).and_then(|(entries, comment)| {
+            let files: Vec<usize> = (0..entries.len())
+                .filter(|idx| !entries[*idx].is_dir)
+                .collect();
+            (!files.is_empty()).then_some((entries, comment, files))
+        });
+        let Some((mut entries, comment, files)) = archive else {
+            let mut bytes_input = BytesInput::new(input.bytes().to_vec());
+            let result = self.inner.mutate(state, &mut bytes_input, stage_idx)?;
+            if result == MutationResult::Mutated {
+                *input.bytes_mut() = bytes_input.bytes().to_vec();
+            }
+            return Ok(result);
+        };
+
+        let idx = files[state.rand_mut().below(files.len() as u64) as usize];
+        let mut bytes_input = BytesInput::new(core::mem::take(&mut entries[idx].data));
+        let result = self.inner.mutate(state, &mut bytes_input, stage_idx)?;
+        if result == MutationResult::Skipped {
+            return Ok(result);
+        }
+        entries[idx].data = bytes_input.bytes().to_vec();
+        let bytes = write_zip(&entries, comment)?;
+        if bytes.len() > state.max_size() {
+            return Ok(MutationResult::Skipped);
+        }
+        *input.bytes_mut() = bytes;
+        Ok(MutationResult::Mutated)
+    }
+
+    #[inline]
+    fn post_exec(
+        &mut self,
+        state: &mut S,
+        stage_idx: i32,
+        corpus_idx: Option<CorpusId>,
+    ) -> Result<(), Error> {
+        self.inner.post_exec(state, stage_idx, corpus_idx)
+    }
+}
+
+impl<M> Named for ZipMutator<M> {
+    fn name(&self) -> &str {
+        "ZipMutator"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use std::io::{Cursor, Write};
+
+    use libafl_bolts::Named;
+    use zip_archive::{write::FileOptions, CompressionMethod, ZipWriter};
+
+    use super::{read_zip, ZipMutator};
+    use crate::{
+        inputs::{BytesInput, HasBytesVec},
+        mutators::{MutationResult, Mutator},
+        state::{test::test_std_state, HasMaxSize},
+        Error,
+    };
+
+    /// Appends a `!` to the input
+    struct AppendMutator;
+
+    impl Named for AppendMutator {
+        fn name(&self) -> &str {
+            "AppendMutator"
+        }
+    }
+
+    impl<S> Mutator<BytesInput, S> for AppendMutator {
+        fn mutate(
+            &mut self,
+            _state: &mut S,
+            input: &mut BytesInput,
+            _stage_idx: i32,
+        ) -> Result<MutationResult, Error> {
+            input.bytes_mut().push(b'!');
+            Ok(MutationResult::Mutated)
+        }
+    }
+
+    #[test]
+    fn test_zip_mutator() {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        writer
+            .add_directory("docs", FileOptions::default())
+            .unwrap();
+        writer
+            .start_file(
+                "docs/hello.txt",
+                FileOptions::default().compression_method(CompressionMethod::Deflated),
+            )
+            .unwrap();
+        writer.write_all(b"hello").unwrap();
+        let archive = writer.finish().unwrap().into_inner();
+
+        let mut state = test_std_state::<BytesInput>();
+        let mut mutator = ZipMutator::new(AppendMutator);
+
+        // the rewritten archive may not exceed the maximum size
+        state.set_max_size(archive.len() - 1);
+        let mut input = BytesInput::new(archive.clone());
+        assert_eq!(
+            mutator.mutate(&mut state, &mut input, 0).unwrap(),
+            MutationResult::Skipped
+        );
+        assert_eq!(input.bytes(), archive.as_slice());
+
+        state.set_max_size(2 * archive.len());
+        assert_eq!(
+            mutator.mutate(&mut state, &mut input, 0).unwrap(),
+            MutationResult::Mutated
+        );
+
+        let (entries, _) = read_zip(input.bytes()).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].is_dir);
+        assert_eq!(entries[1].name, "docs/hello.txt");
+        assert_eq!(entries[1].data, b"hello!");
+
+        let mut raw = BytesInput::new(b"PK no zip".to_vec());
+        mutator.mutate(&mut state, &mut raw, 0).unwrap();
+        assert_eq!(raw.bytes(), b"PK no zip!");
+    }
+}
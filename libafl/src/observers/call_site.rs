@@ -0,0 +1,201 @@
+//! The [`CallSiteObserver`] records the instrumented points reached by the target, together with their calling context.
+//!
+//! The target (or its instrumentation) calls [`record_call_site`] at each point of interest, which hashes
+//! the innermost return addresses of the call stack, as offsets into their modules, so that the hashes
+//! are the same across restarts and clients with ASLR. Each distinct pair of address and call stack hash
+//! is a contextualized edge, reported by the observer after the execution.
+//! As the sites are collected per thread, this works for in-process executors only.
+
+use alloc::string::String;
+use core::{
+    cell::{Cell, RefCell},
+    hash::Hasher,
+};
+
+use hashbrown::HashSet;
+use libafl_bolts::{hasher_std, Named};
+use serde::{Deserialize, Serialize};
+
+use crate::{executors::ExitKind, inputs::UsesInput, observers::Observer, Error};
+
+/// The default number of callers hashed into the calling context
+pub const DEFAULT_CALL_STACK_DEPTH: usize = 4;
+
+/// The maximum number of frames walked to find the caller of [`record_call_site`]
+const MAX_WALKED_FRAMES: usize = 64;
+
+/// The frames of the unwinder and of [`record_call_site`], if they cannot be told apart by their symbol addresses
+const UNWINDER_FRAMES: usize = 3;
+
+thread_local! {
+    /// The contextualized edges reached in the current execution
+    static CALL_SITES: RefCell<HashSet<u128>> = RefCell::new(HashSet::new());
+    /// The number of callers hashed by [`record_call_site`], set by the [`CallSiteObserver`]
+    static CALL_STACK_DEPTH: Cell<usize> = Cell::new(DEFAULT_CALL_STACK_DEPTH);
+}
+
+/// Records that the instrumented point `addr` was reached, in the calling context of the current call stack.
+///
+/// Only the innermost return addresses, up to the depth of the [`CallSiteObserver`], make up the context,
+/// so that the frames of the fuzzer calling the harness do not matter.
+/// `addr` should not depend on the load address either, e.g. be an offset or an id of the point.
+#[inline(never)]
+pub fn record_call_site(addr: usize) {
+    let depth = CALL_STACK_DEPTH.with(Cell::get);
+    let hash = call_stack_hash(depth);
+    CALL_SITES.with(|sites| {
+        sites
+            .borrow_mut()
+            .insert(((addr as u128) << 64) | u128::from(hash));
+    });
+}
+
+/// Hashes the `depth` return addresses above the caller of [`record_call_site`]
+#[inline(always)]
+fn call_stack_hash(depth: usize) -> u64 {
+    let mut ips = [0_usize; MAX_WALKED_FRAMES];
+    let mut own_frame = None;
+    let mut walked = 0;
+    let record_fn = record_call_site as usize;
+    backtrace::trace(|frame| {
+        ips[walked] = module_offset(frame);
+        if own_frame.is_none() && frame.symbol_address() as usize == record_fn {
+            own_frame = Some(walked);
+        }
+        walked += 1;
+        walked < MAX_WALKED_FRAMES && own_frame.map_or(true, |own| walked <= own + depth)
+    });
+
+    // without symbol addresses, the innermost frames of the unwinder are part of the hash instead, they never change
+    let (start, end) = own_frame.map_or((0, walked.min(depth + UNWINDER_FRAMES)), |own| {
+        (own + 1, walked)
+    });
+    let mut hasher = hasher_std();
+    for ip in &ips[start..end] {
+        hasher.write_usize(*ip);
+    }
+    hasher.finish()
+}
+
+/// The offset of the instruction pointer of `frame` in its module, the instruction pointer if the module is unknown
+fn module_offset(frame: &backtrace::Frame) -> usize {
+    let ip = frame.ip() as usize;
+    if let Some(base) = frame.module_base_address() {
+        return ip.wrapping_sub(base as usize);
+    }
+    #[cfg(unix)]
+    {
+        let mut info = core::mem::MaybeUninit::<libc::Dl_info>::uninit();
+        // # Safety
+        // `dladdr` only looks up the address, and fills in `info` if it succeeds.
+        if unsafe { libc::dladdr(ip as *const libc::c_void, info.as_mut_ptr()) } != 0 {
+            let base = unsafe { info.assume_init() }.dli_fbase as usize;
+            return ip.wrapping_sub(base);
+        }
+    }
+    ip
+}
+
+/// An observer collecting the contextualized edges reported by [`record_call_site`] during an execution.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallSiteObserver {
+    name: String,
+    depth: usize,
+    /// The pairs of instrumented address (upper 64 bits) and call stack hash (lower 64 bits) reached
+    sites: HashSet<u128>,
+}
+
+impl CallSiteObserver {
+    /// Creates a new [`CallSiteObserver`] with the given name, hashing [`DEFAULT_CALL_STACK_DEPTH`] callers
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        Self::with_depth(name, DEFAULT_CALL_STACK_DEPTH)
+    }
+
+    /// Creates a new [`CallSiteObserver`] with the given name, hashing `depth` callers into the context
+    #[must_use]
+    pub fn with_depth(name: &str, depth: usize) -> Self {
+        Self {
+            name: name.into(),
+            depth,
+            sites: HashSet::new(),
+        }
+    }
+
+    /// The contextualized edges reached in the last execution,
+    /// as instrumented address in the upper 64 bits, and call stack hash in the lower 64 bits
+    #[must_use]
+    pub fn sites(&self) -> &HashSet<u128> {
+        &self.sites
+    }
+}
+
+impl<S> Observer<S> for CallSiteObserver
+where
+    S: UsesInput,
+{
+    fn pre_exec(&mut self, _state: &mut S, _input: &S::Input) -> Result<(), Error> {
+        self.sites.clear();
+        CALL_STACK_DEPTH.with(|depth| depth.set(self.depth));
+        CALL_SITES.with(|sites| sites.borrow_mut().clear());
+        Ok(())
+    }
+
+    fn post_exec(
+        &mut self,
+        _state: &mut S,
+        _input: &S::Input,
+        _exit_kind: &ExitKind,
+    ) -> Result<(), Error> {
+        CALL_SITES.with(|sites| core::mem::swap(&mut self.sites, &mut sites.borrow_mut()));
+        Ok(())
+    }
+}
+
+impl Named for CallSiteObserver {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::hint::black_box;
+
+    use super::{record_call_site, CallSiteObserver};
+    use crate::{executors::ExitKind, inputs::BytesInput, observers::Observer, state::NopState};
+
+    // the distinct values keep the callers from being merged, and the calls from becoming tail calls
+    #[inline(never)]
+    fn first_caller() {
+        record_call_site(0x1337);
+        black_box(1);
+    }
+
+    #[inline(never)]
+    fn second_caller() {
+        record_call_site(0x1337);
+        black_box(2);
+    }
+
+    #[test]
+    fn test_call_site_observer() {
+        let mut observer = CallSiteObserver::new("call_sites");
+        let mut state = NopState::<BytesInput>::new();
+        let input = BytesInput::new(vec![]);
+
+        observer.pre_exec(&mut state, &input).unwrap();
+        // call from a single site, the return address into this test is part of the context too
+        let callers: [fn(); 3] = [first_caller, first_caller, second_caller];
+        for caller in black_box(&callers[..]) {
+            caller();
+        }
+        observer
+            .post_exec(&mut state, &input, &ExitKind::Ok)
+            .unwrap();
+
+        // the same address, in two calling contexts
+        assert_eq!(observer.sites().len(), 2);
+        assert!(observer.sites().iter().all(|site| site >> 64 == 0x1337));
+    }
+}
@@ -415,6 +415,73 @@ where
 /// A [`StdCmpObserver`] that optionally adds comparisons into a [`CmpValuesMetadata`]
 pub type StdCmpValuesObserver<'a, CM, S> = StdCmpObserver<'a, CM, S, CmpValuesMetadata>;
 
+/// An in-memory [`CmpMap`] holding the operands of the last logged execution of each cmp site,
+/// as a fixed number of `(v0, v1, size)` entries. A `size` of `0` marks a site not hit in this execution.
+///
+/// The comparison hooks of the target, e.g. its `__sanitizer_cov_trace_cmp*` callbacks,
+/// fill the map through [`CmpOperandsMap::log`].
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CmpOperandsMap {
+    entries: Vec<(u64, u64, u8)>,
+}
+
+impl CmpOperandsMap {
+    /// Creates a new [`CmpOperandsMap`] with `len` cmp sites
+    #[must_use]
+    pub fn new(len: usize) -> Self {
+        Self {
+            entries: vec![(0, 0, 0); len],
+        }
+    }
+
+    /// Logs the operands of a comparison of `size` bytes (1, 2, 4 or 8) at the cmp site `idx`.
+    /// The site is taken modulo the length of the map.
+    pub fn log(&mut self, idx: usize, v0: u64, v1: u64, size: u8) {
+        if self.entries.is_empty() {
+            return;
+        }
+        let len = self.entries.len();
+        self.entries[idx % len] = (v0, v1, size);
+    }
+}
+
+impl CmpMap for CmpOperandsMap {
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn executions_for(&self, idx: usize) -> usize {
+        usize::from(self.entries[idx].2 != 0)
+    }
+
+    fn usable_executions_for(&self, idx: usize) -> usize {
+        self.executions_for(idx)
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn values_of(&self, idx: usize, execution: usize) -> Option<CmpValues> {
+        if execution != 0 {
+            return None;
+        }
+        let (v0, v1, size) = self.entries[idx];
+        match size {
+            1 => Some(CmpValues::U8((v0 as u8, v1 as u8))),
+            2 => Some(CmpValues::U16((v0 as u16, v1 as u16))),
+            4 => Some(CmpValues::U32((v0 as u32, v1 as u32))),
+            8 => Some(CmpValues::U64((v0, v1))),
+            _ => None,
+        }
+    }
+
+    fn reset(&mut self) -> Result<(), Error> {
+        self.entries.fill((0, 0, 0));
+        Ok(())
+    }
+}
+
+/// A [`StdCmpObserver`] over a [`CmpOperandsMap`], adding the comparisons into a [`CmpValuesMetadata`]
+pub type CmpOperandsObserver<'a, S> = StdCmpValuesObserver<'a, CmpOperandsMap, S>;
+
 /* From AFL++ cmplog.h
 
 #define CMP_MAP_W 65536
@@ -536,3 +603,28 @@ pub struct AFLppCmpLogHeader {
     #[bitfield(name = "reserved", ty = "u32", bits = "60..=63")]
     pub data: [u8; 8],
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{CmpMap, CmpOperandsMap, CmpValues};
+
+    #[test]
+    fn test_cmp_operands_map() {
+        let mut map = CmpOperandsMap::new(4);
+        map.log(1, 0x1234, 0x5678, 2);
+        map.log(6, 0xdead_beef_cafe, 7, 8);
+
+        assert_eq!(map.len(), 4);
+        assert_eq!(map.usable_executions_for(0), 0);
+        assert_eq!(map.values_of(0, 0), None);
+        assert_eq!(map.values_of(1, 0), Some(CmpValues::U16((0x1234, 0x5678))));
+        assert_eq!(map.values_of(1, 1), None);
+        assert_eq!(
+            map.values_of(2, 0),
+            Some(CmpValues::U64((0xdead_beef_cafe, 7)))
+        );
+
+        map.reset().unwrap();
+        assert!((0..map.len()).all(|idx| map.usable_executions_for(idx) == 0));
+    }
+}
@@ -0,0 +1,318 @@
+//! The [`ExecutionHistory`] keeps the last executions, to see which inputs led up to a crash.
+
+use alloc::{collections::VecDeque, rc::Rc, string::String};
+use core::{cell::Cell, time::Duration};
+#[cfg(feature = "std")]
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use libafl_bolts::{current_time, hash_std, impl_serdeany, AsSlice, Named};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{Corpus, CorpusId},
+    executors::{hooks::ExecutorHook, ExitKind, HasObservers},
+    inputs::{HasTargetBytes, UsesInput},
+    observers::Observer,
+    state::{HasCorpus, HasMetadata},
+    Error,
+};
+
+/// An execution, as recorded in the [`ExecutionHistory`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// The corpus entry scheduled when the input ran, i.e. the input itself or the one it was mutated from
+    pub corpus_id: Option<CorpusId>,
+    /// The hash of the target bytes of the input, to tell apart inputs that are not in the corpus
+    pub input_hash: u64,
+    /// How the execution ended
+    pub exit_kind: ExitKind,
+    /// The wall time the execution started at
+    pub time: Duration,
+    /// How long the execution took
+    pub exec_time: Duration,
+}
+
+/// A ring buffer of the last executions, as metadata of the state.
+///
+/// It is opt-in: the [`ExecutionHistoryObserver`] only records executions if the state has this metadata,
+/// added with `state.add_metadata(ExecutionHistory::new(1000))`.
+/// Add an [`ExecutionHistoryHook`] to an in-process executor to time the harness alone.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct ExecutionHistory {
+    capacity: usize,
+    ring: VecDeque<HistoryEntry>,
+}
+
+impl_serdeany!(ExecutionHistory);
+
+impl ExecutionHistory {
+    /// Creates a new [`ExecutionHistory`], keeping the last `capacity` executions
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ring: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// The number of executions kept
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Records an execution, dropping the oldest one if full
+    pub fn push(&mut self, entry: HistoryEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.ring.len() == self.capacity {
+            self.ring.pop_front();
+        }
+        self.ring.push_back(entry);
+    }
+
+    /// The recorded executions, oldest first
+    pub fn entries(&self) -> impl Iterator<Item = &HistoryEntry> {
+        self.ring.iter()
+    }
+
+    /// The last recorded execution
+    #[must_use]
+    pub fn last(&self) -> Option<&HistoryEntry> {
+        self.ring.back()
+    }
+
+    /// Forgets all recorded executions
+    pub fn clear(&mut self) {
+        self.ring.clear();
+    }
+
+    /// Writes the recorded executions to a file, as JSON
+    #[cfg(feature = "std")]
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        fs::write(path, serde_json::to_vec_pretty(&self.ring)?)?;
+        Ok(())
+    }
+}
+
+/// The start and the duration of the last run of the harness, as taken by an [`ExecutionHistoryHook`]
+type HarnessTiming = Rc<Cell<Option<(Duration, Duration)>>>;
+
+/// Times the harness of an executor taking hooks, e.g. the `InProcessExecutor`, for the [`ExecutionHistory`].
+///
+/// Executor hooks see neither the metadata of the state nor the [`ExitKind`],
+/// so the entries are inserted by the [`ExecutionHistoryObserver`] sharing this hook, see [`ExecutionHistoryObserver::with_hook`].
+/// Without the hook, the execution time also counts the other observers.
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionHistoryHook {
+    start_time: Duration,
+    timing: HarnessTiming,
+}
+
+impl ExecutionHistoryHook {
+    /// Creates a new [`ExecutionHistoryHook`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ExecutorHook for ExecutionHistoryHook {
+    fn init<E: HasObservers, S>(&mut self, _state: &mut S) {}
+
+    fn pre_exec<EM, I, S, Z>(
+        &mut self,
+        _fuzzer: &mut Z,
+        _state: &mut S,
+        _mgr: &mut EM,
+        _input: &I,
+    ) {
+        self.start_time = current_time();
+    }
+
+    fn post_exec<EM, I, S, Z>(
+        &mut self,
+        _fuzzer: &mut Z,
+        _state: &mut S,
+        _mgr: &mut EM,
+        _input: &I,
+    ) {
+        self.timing.set(Some((
+            self.start_time,
+            current_time().saturating_sub(self.start_time),
+        )));
+    }
+}
+
+/// Records each execution into the [`ExecutionHistory`] of the state, if it has one.
+///
+/// It also sees the executions ending in crashes and timeouts, which the crash handlers finish with the observers.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExecutionHistoryObserver {
+    name: String,
+    start_time: Duration,
+    #[serde(skip)]
+    hook_timing: Option<HarnessTiming>,
+    #[cfg(feature = "std")]
+    crash_dump: Option<PathBuf>,
+}
+
+impl ExecutionHistoryObserver {
+    /// Creates a new [`ExecutionHistoryObserver`]
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.into(),
+            start_time: Duration::ZERO,
+            hook_timing: None,
+            #[cfg(feature = "std")]
+            crash_dump: None,
+        }
+    }
+
+    /// Takes the times of the executions from the given [`ExecutionHistoryHook`], added to the executor.
+    /// The executions ending in a crash or a timeout, that the hook does not see the end of, are still timed by the observer.
+    #[must_use]
+    pub fn with_hook(mut self, hook: &ExecutionHistoryHook) -> Self {
+        self.hook_timing = Some(hook.timing.clone());
+        self
+    }
+
+    /// Writes the [`ExecutionHistory`] to `path` after each crash or timeout, see [`ExecutionHistory::to_file`]
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn with_crash_dump<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.crash_dump = Some(path.as_ref().to_path_buf());
+        self
+    }
+}
+
+impl Named for ExecutionHistoryObserver {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<S> Observer<S> for ExecutionHistoryObserver
+where
+    S: UsesInput + HasMetadata + HasCorpus,
+    S::Input: HasTargetBytes,
+{
+    fn pre_exec(&mut self, _state: &mut S, _input: &S::Input) -> Result<(), Error> {
+        self.start_time = current_time();
+        if let Some(timing) = &self.hook_timing {
+            timing.set(None);
+        }
+        Ok(())
+    }
+
+    fn post_exec(
+        &mut self,
+        state: &mut S,
+        input: &S::Input,
+        exit_kind: &ExitKind,
+    ) -> Result<(), Error> {
+        let (time, exec_time) = self
+            .hook_timing
+            .as_ref()
+            .and_then(|timing| timing.take())
+            .unwrap_or_else(|| {
+                (
+                    self.start_time,
+                    current_time().saturating_sub(self.start_time),
+                )
+            });
+        let corpus_id = *state.corpus().current();
+        let Ok(history) = state.metadata_mut::<ExecutionHistory>() else {
+            return Ok(());
+        };
+        history.push(HistoryEntry {
+            corpus_id,
+            input_hash: hash_std(input.target_bytes().as_slice()),
+            exit_kind: *exit_kind,
+            time,
+            exec_time,
+        });
+
+        #[cfg(feature = "std")]
+        if let Some(path) = &self.crash_dump {
+            if matches!(exit_kind, ExitKind::Crash | ExitKind::Timeout) {
+                history.to_file(path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+    use core::time::Duration;
+
+    use super::{ExecutionHistory, ExecutionHistoryHook, ExecutionHistoryObserver, HistoryEntry};
+    use crate::{
+        executors::{hooks::ExecutorHook, ExitKind},
+        inputs::BytesInput,
+        observers::Observer,
+        state::{test::test_std_state, HasMetadata},
+    };
+
+    #[test]
+    fn test_execution_history_ring() {
+        let mut history = ExecutionHistory::new(2);
+        for input_hash in 0..3 {
+            history.push(HistoryEntry {
+                corpus_id: None,
+                input_hash,
+                exit_kind: ExitKind::Ok,
+                time: Duration::ZERO,
+                exec_time: Duration::ZERO,
+            });
+        }
+        let hashes: Vec<u64> = history.entries().map(|entry| entry.input_hash).collect();
+        assert_eq!(hashes, [1, 2]);
+        assert_eq!(history.last().unwrap().input_hash, 2);
+    }
+
+    #[test]
+    fn test_execution_history_hook() {
+        let mut state = test_std_state::<BytesInput>();
+        state.add_metadata(ExecutionHistory::new(4));
+
+        let mut hook = ExecutionHistoryHook::new();
+        let mut observer = ExecutionHistoryObserver::new("history").with_hook(&hook);
+        let input = BytesInput::new(vec![1, 2]);
+
+        // a run finishing normally is timed by the hook
+        observer.pre_exec(&mut state, &input).unwrap();
+        hook.pre_exec(&mut (), &mut state, &mut (), &input);
+        hook.post_exec(&mut (), &mut state, &mut (), &input);
+        let hook_time = hook.start_time;
+        observer
+            .post_exec(&mut state, &input, &ExitKind::Ok)
+            .unwrap();
+
+        // a crash never reaches the post_exec of the hook
+        observer.pre_exec(&mut state, &input).unwrap();
+        hook.pre_exec(&mut (), &mut state, &mut (), &input);
+        observer
+            .post_exec(&mut state, &input, &ExitKind::Crash)
+            .unwrap();
+
+        let history = state.metadata::<ExecutionHistory>().unwrap();
+        let entries: Vec<&HistoryEntry> = history.entries().collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].exit_kind, ExitKind::Ok);
+        assert_eq!(entries[0].time, hook_time);
+        assert_eq!(entries[1].exit_kind, ExitKind::Crash);
+        assert_eq!(entries[1].time, observer.start_time);
+    }
+}
@@ -16,8 +16,34 @@ pub mod stacktrace;
 #[cfg(feature = "regex")]
 pub use stacktrace::*;
 
+#[cfg(feature = "std")]
+pub mod call_site;
+#[cfg(feature = "std")]
+pub use call_site::{record_call_site, CallSiteObserver};
+
+pub mod registry;
+pub use registry::{LocalObserverHandle, LocalObserverRegistry, ObserverAccess};
+#[cfg(feature = "concurrent")]
+pub use registry::{SharedObserverHandle, SharedObserverRegistry};
+
 pub mod concolic;
 
+pub mod history;
+pub use history::*;
+
+#[cfg(all(
+    feature = "syscall_observer",
+    target_os = "linux",
+    target_arch = "x86_64"
+))]
+pub mod syscall;
+#[cfg(all(
+    feature = "syscall_observer",
+    target_os = "linux",
+    target_arch = "x86_64"
+))]
+pub use syscall::SyscallObserver;
+
 pub mod value;
 
 use alloc::{
@@ -0,0 +1,395 @@
+//! Observers shared between multiple components, instead of being owned by the executor alone.
+//!
+//! A [`LocalObserverRegistry`] holds each observer in a [`RefCell`], and hands out cheap [`LocalObserverHandle`]s to it.
+//! A handle can be put into the observers tuple of the executor, as it is an [`Observer`] itself,
+//! while other components, like a mutator, keep another handle to the same observer.
+//! Feedbacks find the handle in the observers tuple by its type, e.g. `LocalObserverHandle<TimeObserver>`.
+//! The handles only give access to the observer through a guard, so they are no [`MapObserver`]s:
+//! the references of [`MapObserver::get`] would outlive the guard. Map feedbacks need the map observer itself.
+//!
+//! [`MapObserver`]: crate::observers::MapObserver
+//! [`MapObserver::get`]: crate::observers::MapObserver::get
+//!
+//! Fuzzers sharing observers between threads enable the `concurrent` feature, which adds the [`SharedObserverRegistry`]
+//! backed by `Arc<Mutex<..>>`. Both kinds of handles give access to the observer through [`ObserverAccess`].
+
+use alloc::{rc::Rc, string::String};
+use core::{
+    any::Any,
+    cell::{Ref, RefCell, RefMut},
+    ops::{Deref, DerefMut},
+};
+#[cfg(feature = "concurrent")]
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use hashbrown::HashMap;
+use libafl_bolts::{HasLen, Named};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{executors::ExitKind, inputs::UsesInput, observers::Observer, Error};
+
+/// Access to an observer shared through a registry.
+///
+/// The accessors panic if the observer is borrowed in a conflicting way, or, for the `Mutex`, if it is poisoned.
+pub trait ObserverAccess<T> {
+    /// A borrow of the observer
+    type Ref<'a>: Deref<Target = T>
+    where
+        Self: 'a;
+    /// A mutable borrow of the observer
+    type RefMut<'a>: DerefMut<Target = T>
+    where
+        Self: 'a;
+
+    /// Borrows the observer
+    fn observer(&self) -> Self::Ref<'_>;
+
+    /// Borrows the observer mutably
+    fn observer_mut(&self) -> Self::RefMut<'_>;
+}
+
+/// A handle to an observer of a [`LocalObserverRegistry`]
+#[derive(Debug)]
+pub struct LocalObserverHandle<T> {
+    name: String,
+    inner: Rc<RefCell<T>>,
+}
+
+impl<T> Clone for LocalObserverHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> ObserverAccess<T> for LocalObserverHandle<T> {
+    type Ref<'a>
+        = Ref<'a, T>
+    where
+        Self: 'a;
+    type RefMut<'a>
+        = RefMut<'a, T>
+    where
+        Self: 'a;
+
+    fn observer(&self) -> Ref<'_, T> {
+        self.inner.borrow()
+    }
+
+    fn observer_mut(&self) -> RefMut<'_, T> {
+        self.inner.borrow_mut()
+    }
+}
+
+impl<T> LocalObserverHandle<T>
+where
+    T: Named,
+{
+    fn new(observer: T) -> Self {
+        Self {
+            name: observer.name().into(),
+            inner: Rc::new(RefCell::new(observer)),
+        }
+    }
+}
+
+/// Holds observers by name, to be borrowed by multiple components of a single-threaded fuzzer
+#[derive(Debug, Default)]
+pub struct LocalObserverRegistry {
+    observers: HashMap<String, Rc<dyn Any>>,
+}
+
+impl LocalObserverRegistry {
+    /// Creates a new, empty [`LocalObserverRegistry`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an observer under its name, replacing any observer of the same name
+    pub fn register<T>(&mut self, observer: T) -> LocalObserverHandle<T>
+    where
+        T: Named + 'static,
+    {
+        let handle = LocalObserverHandle::new(observer);
+        self.observers
+            .insert(handle.name.clone(), handle.inner.clone());
+        handle
+    }
+
+    /// A handle to the observer of the given name, if there is one of type `T`
+    #[must_use]
+    pub fn get<T>(&self, name: &str) -> Option<LocalObserverHandle<T>>
+    where
+        T: 'static,
+    {
+        let inner = self.observers.get(name)?.clone().downcast().ok()?;
+        Some(LocalObserverHandle {
+            name: name.into(),
+            inner,
+        })
+    }
+
+    /// The number of observers
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.observers.len()
+    }
+
+    /// Returns `true` if no observer was registered
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.observers.is_empty()
+    }
+}
+
+/// A handle to an observer of a [`SharedObserverRegistry`]
+#[cfg(feature = "concurrent")]
+#[derive(Debug)]
+pub struct SharedObserverHandle<T> {
+    name: String,
+    inner: Arc<Mutex<T>>,
+}
+
+#[cfg(feature = "concurrent")]
+impl<T> Clone for SharedObserverHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+#[cfg(feature = "concurrent")]
+impl<T> ObserverAccess<T> for SharedObserverHandle<T> {
+    type Ref<'a>
+        = MutexGuard<'a, T>
+    where
+        Self: 'a;
+    type RefMut<'a>
+        = MutexGuard<'a, T>
+    where
+        Self: 'a;
+
+    fn observer(&self) -> MutexGuard<'_, T> {
+        self.inner.lock().unwrap()
+    }
+
+    fn observer_mut(&self) -> MutexGuard<'_, T> {
+        self.inner.lock().unwrap()
+    }
+}
+
+#[cfg(feature = "concurrent")]
+impl<T> SharedObserverHandle<T>
+where
+    T: Named,
+{
+    fn new(observer: T) -> Self {
+        Self {
+            name: observer.name().into(),
+            inner: Arc::new(Mutex::new(observer)),
+        }
+    }
+}
+
+/// Holds observers by name, to be borrowed by multiple components, also on other threads
+#[cfg(feature = "concurrent")]
+#[derive(Debug, Default)]
+pub struct SharedObserverRegistry {
+    observers: HashMap<String, Arc<dyn Any + Send + Sync>>,
+}
+
+#[cfg(feature = "concurrent")]
+impl SharedObserverRegistry {
+    /// Creates a new, empty [`SharedObserverRegistry`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an observer under its name, replacing any observer of the same name
+    pub fn register<T>(&mut self, observer: T) -> SharedObserverHandle<T>
+    where
+        T: Named + Send + 'static,
+    {
+        let handle = SharedObserverHandle::new(observer);
+        self.observers
+            .insert(handle.name.clone(), handle.inner.clone());
+        handle
+    }
+
+    /// A handle to the observer of the given name, if there is one of type `T`
+    #[must_use]
+    pub fn get<T>(&self, name: &str) -> Option<SharedObserverHandle<T>>
+    where
+        T: Send + 'static,
+    {
+        let inner = self.observers.get(name)?.clone().downcast().ok()?;
+        Some(SharedObserverHandle {
+            name: name.into(),
+            inner,
+        })
+    }
+
+    /// The number of observers
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.observers.len()
+    }
+
+    /// Returns `true` if no observer was registered
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.observers.is_empty()
+    }
+}
+
+/// Implements [`Observer`], [`Named`], [`HasLen`], and serde for a handle, delegating to the observer.
+/// A deserialized handle holds an observer of its own, not shared with anything.
+macro_rules! impl_observer_handle {
+    ($handle:ident) => {
+        impl<O, S> Observer<S> for $handle<O>
+        where
+            O: Observer<S>,
+            S: UsesInput,
+        {
+            fn flush(&mut self) -> Result<(), Error> {
+                self.observer_mut().flush()
+            }
+
+            fn pre_exec(&mut self, state: &mut S, input: &S::Input) -> Result<(), Error> {
+                self.observer_mut().pre_exec(state, input)
+            }
+
+            fn post_exec(
+                &mut self,
+                state: &mut S,
+                input: &S::Input,
+                exit_kind: &ExitKind,
+            ) -> Result<(), Error> {
+                self.observer_mut().post_exec(state, input, exit_kind)
+            }
+
+            fn pre_exec_child(&mut self, state: &mut S, input: &S::Input) -> Result<(), Error> {
+                self.observer_mut().pre_exec_child(state, input)
+            }
+
+            fn post_exec_child(
+                &mut self,
+                state: &mut S,
+                input: &S::Input,
+                exit_kind: &ExitKind,
+            ) -> Result<(), Error> {
+                self.observer_mut().post_exec_child(state, input, exit_kind)
+            }
+
+            fn observes_stdout(&self) -> bool {
+                self.observer().observes_stdout()
+            }
+
+            fn observes_stderr(&self) -> bool {
+                self.observer().observes_stderr()
+            }
+
+            fn observe_stdout(&mut self, stdout: &[u8]) {
+                self.observer_mut().observe_stdout(stdout);
+            }
+
+            fn observe_stderr(&mut self, stderr: &[u8]) {
+                self.observer_mut().observe_stderr(stderr);
+            }
+        }
+
+        impl<T> Named for $handle<T> {
+            fn name(&self) -> &str {
+                &self.name
+            }
+        }
+
+        impl<T> HasLen for $handle<T>
+        where
+            T: HasLen,
+        {
+            fn len(&self) -> usize {
+                self.observer().len()
+            }
+        }
+
+        impl<T> Serialize for $handle<T>
+        where
+            T: Serialize,
+        {
+            fn serialize<SE>(&self, serializer: SE) -> Result<SE::Ok, SE::Error>
+            where
+                SE: Serializer,
+            {
+                self.observer().serialize(serializer)
+            }
+        }
+
+        impl<'de, T> Deserialize<'de> for $handle<T>
+        where
+            T: Named + DeserializeOwned,
+        {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                T::deserialize(deserializer).map(Self::new)
+            }
+        }
+    };
+}
+
+impl_observer_handle!(LocalObserverHandle);
+#[cfg(feature = "concurrent")]
+impl_observer_handle!(SharedObserverHandle);
+
+#[cfg(test)]
+mod tests {
+    use super::{LocalObserverRegistry, ObserverAccess};
+    use crate::{
+        executors::ExitKind,
+        inputs::BytesInput,
+        observers::{MapObserver, Observer, StdMapObserver, TimeObserver},
+        state::NopState,
+    };
+
+    #[test]
+    fn test_local_observer_registry() {
+        let mut registry = LocalObserverRegistry::new();
+        let mut executor_handle = registry.register(TimeObserver::new("time"));
+        let other_handle = registry.get::<TimeObserver>("time").unwrap();
+        assert!(registry.get::<TimeObserver>("other").is_none());
+        assert!(registry.get::<LocalObserverRegistry>("time").is_none());
+
+        let mut state = NopState::<BytesInput>::new();
+        let input = BytesInput::new(vec![]);
+        executor_handle.pre_exec(&mut state, &input).unwrap();
+        executor_handle
+            .post_exec(&mut state, &input, &ExitKind::Ok)
+            .unwrap();
+        assert!(other_handle.observer().last_runtime().is_some());
+    }
+
+    #[test]
+    fn test_map_observer_handle() {
+        let mut registry = LocalObserverRegistry::new();
+        let handle = registry.register(StdMapObserver::owned("map", vec![0_u8; 4]));
+        let other_handle = registry
+            .get::<StdMapObserver<'static, u8, false>>("map")
+            .unwrap();
+
+        *handle.observer_mut().get_mut(1) = 3;
+        assert_eq!(*other_handle.observer().get(1), 3);
+        assert_eq!(other_handle.observer().count_bytes(), 1);
+        assert_eq!(other_handle.observer().to_vec(), vec![0, 3, 0, 0]);
+
+        handle.observer_mut().reset_map().unwrap();
+        assert_eq!(other_handle.observer().count_bytes(), 0);
+    }
+}
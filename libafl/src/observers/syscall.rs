@@ -0,0 +1,179 @@
+//! The [`SyscallObserver`] records the syscalls issued by a target, using `ptrace(PTRACE_SYSCALL)`.
+//!
+//! This is a slow-path observer: every syscall of the target results in two stops and context switches
+//! to the fuzzer, which easily slows down syscall-heavy targets by an order of magnitude.
+//! It is meant for OS interface fuzzing, where the exercised syscalls are the coverage metric of choice.
+//! Currently only `x86_64` Linux is supported.
+
+use alloc::string::String;
+
+use hashbrown::HashMap;
+use libafl_bolts::Named;
+use nix::{
+    sys::{
+        ptrace,
+        signal::{raise, Signal},
+        wait::{waitpid, WaitStatus},
+    },
+    unistd::{fork, ForkResult, Pid},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{executors::ExitKind, inputs::UsesInput, observers::Observer, Error};
+
+/// The exit status of a forked child that failed to request tracing with `PTRACE_TRACEME`
+const TRACEME_FAILED_STATUS: i32 = 0x7d;
+/// The exit status of a forked child that failed to stop itself for the tracer
+const STOP_FAILED_STATUS: i32 = 0x7e;
+
+/// An observer that counts the syscalls issued by a traced target during the last execution.
+///
+/// The observer does not run the target by itself: executors (or harnesses) need to call
+/// [`SyscallObserver::trace`] to run a harness in a traced child, or [`SyscallObserver::trace_child`]
+/// for a child that requested tracing with `PTRACE_TRACEME` and stopped itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyscallObserver {
+    name: String,
+    /// The number of times each syscall number was entered during the last execution
+    syscalls: HashMap<i64, u64>,
+}
+
+impl SyscallObserver {
+    /// Creates a new [`SyscallObserver`] with the given name.
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.into(),
+            syscalls: HashMap::new(),
+        }
+    }
+
+    /// The number of times each syscall number was entered during the last execution
+    #[must_use]
+    pub fn syscalls(&self) -> &HashMap<i64, u64> {
+        &self.syscalls
+    }
+
+    /// Forks, runs `harness` in the child under `ptrace`, and records all of its syscalls.
+    ///
+    /// The child exits right after the harness returns.
+    /// Returns [`ExitKind::Crash`] if the child got killed by a signal, [`ExitKind::Ok`] otherwise.
+    pub fn trace<F>(&mut self, harness: F) -> Result<ExitKind, Error>
+    where
+        F: FnOnce(),
+    {
+        // # Safety
+        // The child only runs the harness and exits, without returning to the fuzzer.
+        match unsafe { fork() }? {
+            ForkResult::Child => {
+                // the child must not unwind into the fuzzer, failures are reported by the parent
+                if ptrace::traceme().is_err() {
+                    unsafe { libc::_exit(TRACEME_FAILED_STATUS) };
+                }
+                if raise(Signal::SIGSTOP).is_err() {
+                    unsafe { libc::_exit(STOP_FAILED_STATUS) };
+                }
+                harness();
+                unsafe {
+                    libc::_exit(0);
+                }
+            }
+            ForkResult::Parent { child } => self.trace_child(child),
+        }
+    }
+
+    /// Records all syscalls of the child `pid`, until it exits.
+    ///
+    /// The child must have called `PTRACE_TRACEME` and be stopped (for example, by raising `SIGSTOP`).
+    /// Signals other than the syscall stops are forwarded to the child.
+    pub fn trace_child(&mut self, pid: Pid) -> Result<ExitKind, Error> {
+        // the initial stop of the child
+        match waitpid(pid, None)? {
+            WaitStatus::Stopped(_, _) => {}
+            WaitStatus::Exited(_, TRACEME_FAILED_STATUS) => {
+                return Err(Error::unknown(format!(
+                    "The child {pid} could not request tracing with PTRACE_TRACEME"
+                )));
+            }
+            WaitStatus::Exited(_, STOP_FAILED_STATUS) => {
+                return Err(Error::unknown(format!(
+                    "The child {pid} could not stop itself for the tracer"
+                )));
+            }
+            status => {
+                return Err(Error::unknown(format!(
+                    "The child {pid} did not stop for the tracer: {status:?}"
+                )));
+            }
+        }
+        ptrace::setoptions(
+            pid,
+            ptrace::Options::PTRACE_O_TRACESYSGOOD | ptrace::Options::PTRACE_O_EXITKILL,
+        )?;
+
+        let mut in_syscall = false;
+        let mut pending_signal = None;
+        loop {
+            ptrace::syscall(pid, pending_signal.take())?;
+            match waitpid(pid, None)? {
+                WaitStatus::PtraceSyscall(_) => {
+                    // syscall stops alternate between entry and exit
+                    if !in_syscall {
+                        let regs = ptrace::getregs(pid)?;
+                        #[allow(clippy::cast_possible_wrap)]
+                        let nr = regs.orig_rax as i64;
+                        *self.syscalls.entry(nr).or_insert(0) += 1;
+                    }
+                    in_syscall = !in_syscall;
+                }
+                WaitStatus::Stopped(_, signal) => pending_signal = Some(signal),
+                WaitStatus::Exited(_, _) => return Ok(ExitKind::Ok),
+                WaitStatus::Signaled(_, _, _) => return Ok(ExitKind::Crash),
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<S> Observer<S> for SyscallObserver
+where
+    S: UsesInput,
+{
+    fn pre_exec(&mut self, _state: &mut S, _input: &S::Input) -> Result<(), Error> {
+        self.syscalls.clear();
+        Ok(())
+    }
+}
+
+impl Named for SyscallObserver {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SyscallObserver;
+    use crate::executors::ExitKind;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_trace_syscall() {
+        let mut observer = SyscallObserver::new("syscalls");
+        let exit_kind = observer
+            .trace(|| unsafe {
+                libc::getppid();
+                libc::getppid();
+            })
+            .unwrap();
+        assert_eq!(exit_kind, ExitKind::Ok);
+        assert!(observer.syscalls()[&libc::SYS_getppid] >= 2);
+
+        let exit_kind = observer
+            .trace(|| unsafe {
+                libc::abort();
+            })
+            .unwrap();
+        assert_eq!(exit_kind, ExitKind::Crash);
+    }
+}
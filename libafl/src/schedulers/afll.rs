@@ -0,0 +1,359 @@
+//! The [`AfllScheduler`] assigns each corpus entry an energy following AFL's `calculate_score`
+//! (`https://github.com/google/AFL/blob/61037103ae3722c8060ff7082994836a794f978e/afl-fuzz.c#L4741`),
+//! and samples the next entry proportionally to it.
+//!
+//! AFL's handicap (the number of queue cycles an entry missed) has no equivalent without a queue,
+//! so entries are boosted by how recently they were discovered instead.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{marker::PhantomData, time::Duration};
+
+use libafl_bolts::{current_time, rands::Rand, Named};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{Corpus, CorpusId, HasTestcase, Testcase},
+    inputs::UsesInput,
+    observers::{MapObserver, ObserversTuple, TimeObserver},
+    schedulers::{RemovableScheduler, Scheduler},
+    state::{HasCorpus, HasMetadata, HasRand, State, UsesState},
+    Error,
+};
+
+/// The energy of an average entry
+const BASE_ENERGY: f64 = 100.0;
+/// The maximum energy, relative to [`BASE_ENERGY`] (AFL's `HAVOC_MAX_MULT`)
+const MAX_ENERGY_MULT: f64 = 16.0;
+
+/// The per-entry metadata of the [`AfllScheduler`], stored in the [`Testcase`]
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct AfllMetadata {
+    /// The wall-clock time of the execution that discovered this entry
+    pub exec_time: Duration,
+    /// The number of entries set in the coverage map by this entry
+    pub bitmap_size: u64,
+    /// When this entry was discovered
+    pub discovered: Duration,
+    /// The number of ancestors of this entry, in the corpus
+    pub depth: u64,
+}
+
+libafl_bolts::impl_serdeany!(AfllMetadata);
+
+/// Computes the energy of an entry, as AFL does.
+///
+/// `avg_exec_time` and `avg_bitmap_size` are averaged over the whole corpus,
+/// `newer` is the number of entries discovered after this one, out of `count`.
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn afl_energy(
+    meta: &AfllMetadata,
+    avg_exec_time: Duration,
+    avg_bitmap_size: f64,
+    newer: usize,
+    count: usize,
+) -> f64 {
+    let exec_us = meta.exec_time.as_secs_f64() * 1_000_000.0;
+    let avg_exec_us = avg_exec_time.as_secs_f64() * 1_000_000.0;
+    let bitmap_size = meta.bitmap_size as f64;
+
+    // Fast inputs are less expensive to fuzz, so they get more energy
+    let mut energy = if exec_us * 0.1 > avg_exec_us {
+        10.0
+    } else if exec_us * 0.25 > avg_exec_us {
+        25.0
+    } else if exec_us * 0.5 > avg_exec_us {
+        50.0
+    } else if exec_us * 0.75 > avg_exec_us {
+        75.0
+    } else if exec_us * 4.0 < avg_exec_us {
+        300.0
+    } else if exec_us * 3.0 < avg_exec_us {
+        200.0
+    } else if exec_us * 2.0 < avg_exec_us {
+        150.0
+    } else {
+        BASE_ENERGY
+    };
+
+    // Inputs with more coverage are more likely to lead to new behavior
+    if bitmap_size * 0.3 > avg_bitmap_size {
+        energy *= 3.0;
+    } else if bitmap_size * 0.5 > avg_bitmap_size {
+        energy *= 2.0;
+    } else if bitmap_size * 0.75 > avg_bitmap_size {
+        energy *= 1.5;
+    } else if bitmap_size * 3.0 < avg_bitmap_size {
+        energy *= 0.25;
+    } else if bitmap_size * 2.0 < avg_bitmap_size {
+        energy *= 0.5;
+    } else if bitmap_size * 1.5 < avg_bitmap_size {
+        energy *= 0.75;
+    }
+
+    // Recent discoveries did not get fuzzed as much as the older entries yet
+    if newer * 10 < count {
+        energy *= 4.0;
+    } else if newer * 4 < count {
+        energy *= 2.0;
+    }
+
+    // Deep entries are the result of a long chain of discoveries, and likely to have more potential
+    energy *= match meta.depth {
+        0..=3 => 1.0,
+        4..=7 => 2.0,
+        8..=13 => 3.0,
+        14..=25 => 4.0,
+        _ => 5.0,
+    };
+
+    energy.min(BASE_ENERGY * MAX_ENERGY_MULT)
+}
+
+/// A scheduler picking entries with a probability proportional to their AFL energy, see [`afl_energy`].
+///
+/// The execution time of each entry is taken from a [`TimeObserver`], its bitmap size from a [`MapObserver`].
+/// The energies are recomputed lazily, before the next selection after the corpus changed.
+#[derive(Debug, Clone)]
+pub struct AfllScheduler<O, S> {
+    map_observer_name: String,
+    time_observer_name: String,
+    /// The exec time and bitmap size of the last evaluation, for the entry that may get added next
+    last_exec_time: Option<Duration>,
+    last_bitmap_size: u64,
+    /// The cached energy of each entry, and their sum
+    energies: Vec<(CorpusId, f64)>,
+    total_energy: f64,
+    /// If the corpus changed since the energies were computed
+    dirty: bool,
+    phantom: PhantomData<(O, S)>,
+}
+
+impl<O, S> AfllScheduler<O, S>
+where
+    O: MapObserver,
+    S: HasCorpus + HasTestcase,
+{
+    /// Creates a new [`AfllScheduler`], using the given observers of the executions
+    #[must_use]
+    pub fn new(map_observer: &O, time_observer: &TimeObserver) -> Self {
+        Self {
+            map_observer_name: map_observer.name().to_string(),
+            time_observer_name: time_observer.name().to_string(),
+            last_exec_time: None,
+            last_bitmap_size: 0,
+            energies: Vec::new(),
+            total_energy: 0.0,
+            dirty: true,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Recomputes the energy of all entries in the corpus
+    #[allow(clippy::cast_precision_loss)]
+    fn compute_energies(&mut self, state: &S) -> Result<(), Error> {
+        let mut metas = Vec::with_capacity(state.corpus().count());
+        for idx in state.corpus().ids() {
+            let testcase = state.testcase(idx)?;
+            let meta = testcase
+                .metadata_map()
+                .get::<AfllMetadata>()
+                .copied()
+                .unwrap_or_default();
+            metas.push((idx, meta));
+        }
+
+        self.energies.clear();
+        self.total_energy = 0.0;
+        if metas.is_empty() {
+            self.dirty = false;
+            return Ok(());
+        }
+
+        let count = metas.len();
+        let avg_exec_time = metas
+            .iter()
+            .map(|(_, meta)| meta.exec_time)
+            .sum::<Duration>()
+            / u32::try_from(count).unwrap_or(u32::MAX);
+        let avg_bitmap_size = metas
+            .iter()
+            .map(|(_, meta)| meta.bitmap_size as f64)
+            .sum::<f64>()
+            / count as f64;
+
+        let mut discovered: Vec<Duration> = metas.iter().map(|(_, meta)| meta.discovered).collect();
+        discovered.sort_unstable();
+
+        for (idx, meta) in metas {
+            let newer = count - discovered.partition_point(|time| *time <= meta.discovered);
+            let energy = afl_energy(&meta, avg_exec_time, avg_bitmap_size, newer, count);
+            self.energies.push((idx, energy));
+            self.total_energy += energy;
+        }
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+impl<O, S> UsesState for AfllScheduler<O, S>
+where
+    S: State,
+{
+    type State = S;
+}
+
+impl<O, S> RemovableScheduler for AfllScheduler<O, S>
+where
+    O: MapObserver,
+    S: HasCorpus + HasMetadata + HasRand + HasTestcase + State,
+{
+    fn on_remove(
+        &mut self,
+        _state: &mut Self::State,
+        _idx: CorpusId,
+        _testcase: &Option<Testcase<<Self::State as UsesInput>::Input>>,
+    ) -> Result<(), Error> {
+        self.dirty = true;
+        Ok(())
+    }
+
+    fn on_replace(
+        &mut self,
+        _state: &mut Self::State,
+        _idx: CorpusId,
+        _prev: &Testcase<<Self::State as UsesInput>::Input>,
+    ) -> Result<(), Error> {
+        self.dirty = true;
+        Ok(())
+    }
+}
+
+impl<O, S> Scheduler for AfllScheduler<O, S>
+where
+    O: MapObserver,
+    S: HasCorpus + HasMetadata + HasRand + HasTestcase + State,
+{
+    fn on_add(&mut self, state: &mut Self::State, idx: CorpusId) -> Result<(), Error> {
+        let current_idx = *state.corpus().current();
+        let depth = match current_idx {
+            Some(parent_idx) => state
+                .testcase(parent_idx)?
+                .metadata_map()
+                .get::<AfllMetadata>()
+                .map_or(0, |meta| meta.depth + 1),
+            None => 0,
+        };
+
+        let mut testcase = state.testcase_mut(idx)?;
+        let exec_time = self
+            .last_exec_time
+            .or(*testcase.exec_time())
+            .unwrap_or_default();
+        testcase.add_metadata(AfllMetadata {
+            exec_time,
+            bitmap_size: self.last_bitmap_size,
+            discovered: current_time(),
+            depth,
+        });
+        testcase.set_parent_id_optional(current_idx);
+        self.dirty = true;
+        Ok(())
+    }
+
+    fn on_evaluation<OT>(
+        &mut self,
+        _state: &mut Self::State,
+        _input: &<Self::State as UsesInput>::Input,
+        observers: &OT,
+    ) -> Result<(), Error>
+    where
+        OT: ObserversTuple<Self::State>,
+    {
+        let map_observer = observers
+            .match_name::<O>(&self.map_observer_name)
+            .ok_or_else(|| Error::key_not_found("MapObserver not found".to_string()))?;
+        self.last_bitmap_size = map_observer.count_bytes();
+
+        let time_observer = observers
+            .match_name::<TimeObserver>(&self.time_observer_name)
+            .ok_or_else(|| Error::key_not_found("TimeObserver not found".to_string()))?;
+        self.last_exec_time = *time_observer.last_runtime();
+        Ok(())
+    }
+
+    /// Gets the next entry, sampled proportionally to the energies
+    #[allow(clippy::cast_precision_loss)]
+    fn next(&mut self, state: &mut Self::State) -> Result<CorpusId, Error> {
+        if state.corpus().count() == 0 {
+            return Err(Error::empty("No entries in corpus".to_string()));
+        }
+        if self.dirty {
+            self.compute_energies(state)?;
+        }
+
+        let rand_prob = (state.rand_mut().below(1 << 32) as f64) / ((1_u64 << 32) as f64);
+        let threshold = self.total_energy * rand_prob;
+        let mut k = 0.0;
+        let mut ret = self.energies[self.energies.len() - 1].0;
+        for (idx, energy) in &self.energies {
+            k += energy;
+            if k >= threshold {
+                ret = *idx;
+                break;
+            }
+        }
+
+        self.set_current_scheduled(state, Some(ret))?;
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use super::{afl_energy, AfllMetadata, BASE_ENERGY, MAX_ENERGY_MULT};
+
+    #[test]
+    fn test_afl_energy() {
+        let meta = AfllMetadata {
+            exec_time: Duration::from_millis(10),
+            bitmap_size: 100,
+            discovered: Duration::ZERO,
+            depth: 0,
+        };
+        let avg_time = Duration::from_millis(10);
+
+        // An average, old entry gets the base energy
+        assert!((afl_energy(&meta, avg_time, 100.0, 50, 100) - BASE_ENERGY).abs() < f64::EPSILON);
+
+        // Faster and more coverage is better
+        let fast = AfllMetadata {
+            exec_time: Duration::from_millis(1),
+            ..meta
+        };
+        assert!(afl_energy(&fast, avg_time, 100.0, 50, 100) > BASE_ENERGY);
+        let slow = AfllMetadata {
+            exec_time: Duration::from_millis(200),
+            ..meta
+        };
+        assert!(afl_energy(&slow, avg_time, 100.0, 50, 100) < BASE_ENERGY);
+        assert!(afl_energy(&meta, avg_time, 10.0, 50, 100) > BASE_ENERGY);
+
+        // Recent discoveries get a boost, and the energy is capped
+        assert!(afl_energy(&meta, avg_time, 100.0, 0, 100) > BASE_ENERGY);
+        let best = AfllMetadata { depth: 100, ..fast };
+        assert!(
+            (afl_energy(&best, avg_time, 1.0, 0, 100) - BASE_ENERGY * MAX_ENERGY_MULT).abs()
+                < f64::EPSILON
+        );
+    }
+}
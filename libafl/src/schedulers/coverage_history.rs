@@ -0,0 +1,325 @@
+//! The [`CoverageHistoryScheduler`] ages out corpus entries whose coverage was subsumed by other entries.
+//!
+//! Once all edges of an entry are also covered by other entries, fuzzing it adds little over fuzzing those.
+//! After each addition, the scheduler moves such entries to a dormant set, which is only scheduled
+//! with a small probability. Entries become active again if the active corpus shrinks below a threshold.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::marker::PhantomData;
+
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{Corpus, CorpusId, HasTestcase, Testcase},
+    inputs::UsesInput,
+    observers::{MapObserver, ObserversTuple},
+    schedulers::{two_tier::TwoTierQueue, RemovableScheduler, Scheduler},
+    state::{HasCorpus, HasMetadata, HasRand, State, UsesState},
+    Error,
+};
+
+/// The default probability to schedule a dormant entry, in percent
+pub const DEFAULT_DORMANT_PERCENT: u64 = 1;
+
+/// The default number of schedulings between two checks of the active corpus size
+pub const DEFAULT_REEVALUATION_INTERVAL: usize = 1000;
+
+/// The edges covered by a corpus entry, attached to the [`Testcase`] by the [`CoverageHistoryScheduler`]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct CoverageHistoryMetadata {
+    /// The indexes of the map entries set by the execution of this entry
+    pub edges: Vec<usize>,
+}
+
+libafl_bolts::impl_serdeany!(CoverageHistoryMetadata);
+
+/// Schedules the active corpus entries uniformly at random, and the dormant ones, whose edges are all
+/// covered by active entries, rarely. See the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct CoverageHistoryScheduler<O, S> {
+    map_observer_name: String,
+    /// The edges of the last evaluation, for the entry that may get added next
+    last_edges: Vec<usize>,
+    /// The edges of each known entry
+    edges: HashMap<CorpusId, Vec<usize>>,
+    /// The number of active entries covering each edge
+    edge_counts: HashMap<usize, usize>,
+    /// The active entries in the high tier, the dormant ones in the low tier
+    queue: TwoTierQueue,
+    dormant_percent: u64,
+    min_active: usize,
+    reevaluation_interval: usize,
+    /// The number of schedulings since the last check of the active corpus size
+    since_reevaluation: usize,
+    phantom: PhantomData<(O, S)>,
+}
+
+impl<O, S> CoverageHistoryScheduler<O, S>
+where
+    O: MapObserver,
+    S: HasCorpus + HasTestcase,
+{
+    /// Creates a new [`CoverageHistoryScheduler`], reading the coverage of each execution from `map_observer`.
+    /// Dormant entries are reactivated if less than `min_active` entries are active.
+    #[must_use]
+    pub fn new(map_observer: &O, min_active: usize) -> Self {
+        Self {
+            map_observer_name: map_observer.name().to_string(),
+            last_edges: Vec::new(),
+            edges: HashMap::new(),
+            edge_counts: HashMap::new(),
+            queue: TwoTierQueue::new(),
+            dormant_percent: DEFAULT_DORMANT_PERCENT,
+            min_active,
+            reevaluation_interval: DEFAULT_REEVALUATION_INTERVAL,
+            since_reevaluation: 0,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Sets the probability to schedule a dormant entry, in percent
+    #[must_use]
+    pub fn with_dormant_percent(mut self, dormant_percent: u64) -> Self {
+        self.dormant_percent = dormant_percent.min(100);
+        self
+    }
+
+    /// Sets the number of schedulings between two checks of the active corpus size
+    #[must_use]
+    pub fn with_reevaluation_interval(mut self, reevaluation_interval: usize) -> Self {
+        self.reevaluation_interval = reevaluation_interval;
+        self
+    }
+
+    /// The entries scheduled uniformly
+    #[must_use]
+    pub fn active(&self) -> &[CorpusId] {
+        &self.queue.high
+    }
+
+    /// The entries whose coverage was subsumed
+    #[must_use]
+    pub fn dormant(&self) -> &[CorpusId] {
+        &self.queue.low
+    }
+
+    fn count_edges(&mut self, idx: CorpusId) {
+        for edge in &self.edges[&idx] {
+            *self.edge_counts.entry(*edge).or_default() += 1;
+        }
+    }
+
+    fn uncount_edges(&mut self, idx: CorpusId) {
+        for edge in &self.edges[&idx] {
+            if let Some(count) = self.edge_counts.get_mut(edge) {
+                *count = count.saturating_sub(1);
+            }
+        }
+    }
+
+    /// Adds a new entry as active
+    fn activate(&mut self, idx: CorpusId, edges: Vec<usize>) {
+        self.edges.insert(idx, edges);
+        self.count_edges(idx);
+        self.queue.high.push(idx);
+    }
+
+    /// Forgets an entry, active or dormant
+    fn forget(&mut self, idx: CorpusId) {
+        if self.queue.forget(idx) {
+            self.uncount_edges(idx);
+        }
+        self.edges.remove(&idx);
+    }
+
+    /// Moves the active entries whose edges are all covered by other active entries to the dormant set,
+    /// the oldest entries first.
+    fn retire_subsumed(&mut self) {
+        self.queue.high.sort_unstable();
+        let mut i = 0;
+        while i < self.queue.high.len() && self.queue.high.len() > self.min_active {
+            let idx = self.queue.high[i];
+            // entries without known coverage are kept active
+            let edges = &self.edges[&idx];
+            let subsumed = !edges.is_empty()
+                && edges
+                    .iter()
+                    .all(|edge| self.edge_counts.get(edge).copied().unwrap_or_default() > 1);
+            if subsumed {
+                self.uncount_edges(idx);
+                self.queue.high.remove(i);
+                self.queue.low.push(idx);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    /// Reactivates dormant entries, the newest first, until `min_active` entries are active
+    fn reactivate(&mut self) {
+        self.queue.low.sort_unstable();
+        while self.queue.high.len() < self.min_active {
+            let Some(idx) = self.queue.low.pop() else {
+                break;
+            };
+            self.count_edges(idx);
+            self.queue.high.push(idx);
+        }
+    }
+
+    /// Rebuilds the sets from the corpus, e.g. after a restart, which does not restore the scheduler
+    fn rebuild(&mut self, state: &S) -> Result<(), Error> {
+        self.edges.clear();
+        self.edge_counts.clear();
+        self.queue.clear();
+        for idx in state.corpus().ids() {
+            let edges = state
+                .testcase(idx)?
+                .metadata_map()
+                .get::<CoverageHistoryMetadata>()
+                .map(|meta| meta.edges.clone())
+                .unwrap_or_default();
+            self.activate(idx, edges);
+        }
+        self.retire_subsumed();
+        Ok(())
+    }
+}
+
+impl<O, S> UsesState for CoverageHistoryScheduler<O, S>
+where
+    S: State,
+{
+    type State = S;
+}
+
+impl<O, S> RemovableScheduler for CoverageHistoryScheduler<O, S>
+where
+    O: MapObserver,
+    S: HasCorpus + HasMetadata + HasRand + HasTestcase + State,
+{
+    fn on_remove(
+        &mut self,
+        _state: &mut Self::State,
+        idx: CorpusId,
+        _testcase: &Option<Testcase<<Self::State as UsesInput>::Input>>,
+    ) -> Result<(), Error> {
+        if self.edges.contains_key(&idx) {
+            self.forget(idx);
+        }
+        Ok(())
+    }
+}
+
+impl<O, S> Scheduler for CoverageHistoryScheduler<O, S>
+where
+    O: MapObserver,
+    S: HasCorpus + HasMetadata + HasRand + HasTestcase + State,
+{
+    fn on_add(&mut self, state: &mut Self::State, idx: CorpusId) -> Result<(), Error> {
+        let current_idx = *state.corpus().current();
+        let edges = core::mem::take(&mut self.last_edges);
+        let mut testcase = state.testcase_mut(idx)?;
+        testcase.add_metadata(CoverageHistoryMetadata {
+            edges: edges.clone(),
+        });
+        testcase.set_parent_id_optional(current_idx);
+        drop(testcase);
+
+        if self.edges.contains_key(&idx) {
+            self.forget(idx);
+        }
+        self.activate(idx, edges);
+        self.retire_subsumed();
+        Ok(())
+    }
+
+    fn on_evaluation<OT>(
+        &mut self,
+        _state: &mut Self::State,
+        _input: &<Self::State as UsesInput>::Input,
+        observers: &OT,
+    ) -> Result<(), Error>
+    where
+        OT: ObserversTuple<Self::State>,
+    {
+        let map_observer = observers
+            .match_name::<O>(&self.map_observer_name)
+            .ok_or_else(|| Error::key_not_found("MapObserver not found".to_string()))?;
+        let initial = map_observer.initial();
+        self.last_edges.clear();
+        self.last_edges.extend(
+            (0..map_observer.usable_count()).filter(|idx| *map_observer.get(*idx) != initial),
+        );
+        Ok(())
+    }
+
+    /// Gets an active entry, or with a small probability a dormant one
+    fn next(&mut self, state: &mut Self::State) -> Result<CorpusId, Error> {
+        let count = state.corpus().count();
+        if count == 0 {
+            return Err(Error::empty("No entries in corpus".to_string()));
+        }
+        if self.edges.len() != count {
+            self.rebuild(state)?;
+        }
+
+        self.since_reevaluation += 1;
+        if self.since_reevaluation >= self.reevaluation_interval {
+            self.since_reevaluation = 0;
+            self.reactivate();
+        }
+
+        let id = self.queue.pick(state.rand_mut(), self.dormant_percent);
+        self.set_current_scheduled(state, Some(id))?;
+        Ok(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::rands::StdRand;
+
+    use super::CoverageHistoryScheduler;
+    use crate::{
+        corpus::{CorpusId, InMemoryCorpus},
+        inputs::BytesInput,
+        observers::StdMapObserver,
+        state::StdState,
+    };
+
+    type TestScheduler = CoverageHistoryScheduler<
+        StdMapObserver<'static, u8, false>,
+        StdState<BytesInput, InMemoryCorpus<BytesInput>, StdRand, InMemoryCorpus<BytesInput>>,
+    >;
+
+    #[test]
+    fn test_retire_subsumed() {
+        let observer = StdMapObserver::owned("map", vec![0_u8; 4]);
+        let mut scheduler: TestScheduler = CoverageHistoryScheduler::new(&observer, 1);
+
+        scheduler.activate(CorpusId::from(0_usize), vec![0, 1]);
+        scheduler.activate(CorpusId::from(1_usize), vec![1, 2]);
+        scheduler.retire_subsumed();
+        assert_eq!(scheduler.active().len(), 2);
+
+        // the newer entry covers all edges of both older ones
+        scheduler.activate(CorpusId::from(2_usize), vec![0, 1, 2]);
+        scheduler.retire_subsumed();
+        assert_eq!(scheduler.active(), &[CorpusId::from(2_usize)]);
+        assert_eq!(scheduler.dormant().len(), 2);
+
+        scheduler.min_active = 3;
+        scheduler.reactivate();
+        assert_eq!(scheduler.active().len(), 3);
+        assert!(scheduler.dormant().is_empty());
+    }
+}
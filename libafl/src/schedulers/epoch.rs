@@ -0,0 +1,297 @@
+//! The [`EpochScheduler`] ages out corpus entries that stopped producing new corpus entries.
+//!
+//! Old entries whose mutations have not been interesting for a long time mostly waste scheduling time.
+//! Each entry remembers the executions count at which it was added, or last produced an interesting child.
+//! Once `epoch_length` executions passed since then, the entry is moved to a low-priority queue,
+//! which is only scheduled with a small probability. A low-priority entry producing an interesting child
+//! is promoted back. This ages entries by time, complementing the [`super::MinimizerScheduler`],
+//! which drops entries by their coverage.
+
+use alloc::string::ToString;
+use core::marker::PhantomData;
+
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{Corpus, CorpusId, HasTestcase, Testcase},
+    inputs::UsesInput,
+    schedulers::{two_tier::TwoTierQueue, RemovableScheduler, Scheduler},
+    state::{HasCorpus, HasExecutions, HasMetadata, HasRand, State, UsesState},
+    Error,
+};
+
+/// The default number of executions without an interesting child, after which an entry gets low priority
+pub const DEFAULT_EPOCH_LENGTH: usize = 1_000_000;
+
+/// The default probability to schedule a low-priority entry, in percent
+pub const DEFAULT_LOW_PRIORITY_PERCENT: u64 = 5;
+
+/// The default number of schedulings between two checks for unproductive entries
+pub const DEFAULT_REEVALUATION_INTERVAL: usize = 1000;
+
+/// The age of a corpus entry, attached to the [`Testcase`] by the [`EpochScheduler`]
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct EpochMetadata {
+    /// The epoch the entry was discovered in
+    pub discovery_epoch: usize,
+    /// The executions count when the entry was added, or last produced an interesting child
+    pub last_productive: usize,
+}
+
+libafl_bolts::impl_serdeany!(EpochMetadata);
+
+/// Schedules the entries that recently produced interesting children uniformly at random,
+/// and the others rarely. See the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct EpochScheduler<S> {
+    /// The executions count at which each known entry was last productive
+    last_productive: HashMap<CorpusId, usize>,
+    queue: TwoTierQueue,
+    epoch_length: usize,
+    low_priority_percent: u64,
+    reevaluation_interval: usize,
+    /// The number of schedulings since the last check for unproductive entries
+    since_reevaluation: usize,
+    phantom: PhantomData<S>,
+}
+
+impl<S> EpochScheduler<S>
+where
+    S: HasCorpus + HasTestcase + HasExecutions,
+{
+    /// Creates a new [`EpochScheduler`], demoting entries after `epoch_length` executions without an interesting child
+    #[must_use]
+    pub fn new(epoch_length: usize) -> Self {
+        Self {
+            last_productive: HashMap::new(),
+            queue: TwoTierQueue::new(),
+            epoch_length: epoch_length.max(1),
+            low_priority_percent: DEFAULT_LOW_PRIORITY_PERCENT,
+            reevaluation_interval: DEFAULT_REEVALUATION_INTERVAL,
+            since_reevaluation: 0,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Sets the probability to schedule a low-priority entry, in percent
+    #[must_use]
+    pub fn with_low_priority_percent(mut self, low_priority_percent: u64) -> Self {
+        self.low_priority_percent = low_priority_percent.min(100);
+        self
+    }
+
+    /// Sets the number of schedulings between two checks for unproductive entries
+    #[must_use]
+    pub fn with_reevaluation_interval(mut self, reevaluation_interval: usize) -> Self {
+        self.reevaluation_interval = reevaluation_interval;
+        self
+    }
+
+    /// The epoch of the given executions count
+    #[must_use]
+    pub fn epoch(&self, executions: usize) -> usize {
+        executions / self.epoch_length
+    }
+
+    /// The entries scheduled uniformly
+    #[must_use]
+    pub fn high_priority(&self) -> &[CorpusId] {
+        &self.queue.high
+    }
+
+    /// The entries that did not produce an interesting child for an epoch
+    #[must_use]
+    pub fn low_priority(&self) -> &[CorpusId] {
+        &self.queue.low
+    }
+
+    /// Forgets an entry, high or low priority
+    fn forget(&mut self, idx: CorpusId) {
+        self.queue.forget(idx);
+        self.last_productive.remove(&idx);
+    }
+
+    /// Records that `idx` produced an interesting child, promoting it if it had low priority
+    fn mark_productive(
+        &mut self,
+        state: &S,
+        idx: CorpusId,
+        executions: usize,
+    ) -> Result<(), Error> {
+        self.queue.promote(idx);
+        self.last_productive.insert(idx, executions);
+        if let Some(meta) = state
+            .testcase_mut(idx)?
+            .metadata_map_mut()
+            .get_mut::<EpochMetadata>()
+        {
+            meta.last_productive = executions;
+        }
+        Ok(())
+    }
+
+    /// Moves the high-priority entries unproductive for an epoch to the low-priority queue
+    fn demote_unproductive(&mut self, executions: usize) {
+        let last_productive = &self.last_productive;
+        let epoch_length = self.epoch_length;
+        self.queue
+            .demote_where(|idx| executions.saturating_sub(last_productive[&idx]) >= epoch_length);
+    }
+
+    /// Rebuilds the queues from the corpus, e.g. after a restart, which does not restore the scheduler
+    fn rebuild(&mut self, state: &S) -> Result<(), Error> {
+        self.last_productive.clear();
+        self.queue.clear();
+        for idx in state.corpus().ids() {
+            let last_productive = state
+                .testcase(idx)?
+                .metadata_map()
+                .get::<EpochMetadata>()
+                .map_or(*state.executions(), |meta| meta.last_productive);
+            self.last_productive.insert(idx, last_productive);
+            self.queue.high.push(idx);
+        }
+        self.demote_unproductive(*state.executions());
+        Ok(())
+    }
+}
+
+impl<S> UsesState for EpochScheduler<S>
+where
+    S: State,
+{
+    type State = S;
+}
+
+impl<S> RemovableScheduler for EpochScheduler<S>
+where
+    S: HasCorpus + HasExecutions + HasMetadata + HasRand + HasTestcase + State,
+{
+    fn on_remove(
+        &mut self,
+        _state: &mut Self::State,
+        idx: CorpusId,
+        _testcase: &Option<Testcase<<Self::State as UsesInput>::Input>>,
+    ) -> Result<(), Error> {
+        self.forget(idx);
+        Ok(())
+    }
+}
+
+impl<S> Scheduler for EpochScheduler<S>
+where
+    S: HasCorpus + HasExecutions + HasMetadata + HasRand + HasTestcase + State,
+{
+    fn on_add(&mut self, state: &mut Self::State, idx: CorpusId) -> Result<(), Error> {
+        let current_idx = *state.corpus().current();
+        let executions = *state.executions();
+        let mut testcase = state.testcase_mut(idx)?;
+        testcase.add_metadata(EpochMetadata {
+            discovery_epoch: self.epoch(executions),
+            last_productive: executions,
+        });
+        testcase.set_parent_id_optional(current_idx);
+        drop(testcase);
+
+        if let Some(parent_idx) = current_idx {
+            if self.last_productive.contains_key(&parent_idx) {
+                self.mark_productive(state, parent_idx, executions)?;
+            }
+        }
+
+        self.forget(idx);
+        self.last_productive.insert(idx, executions);
+        self.queue.high.push(idx);
+        Ok(())
+    }
+
+    /// Gets a high-priority entry, or with a small probability a low-priority one
+    fn next(&mut self, state: &mut Self::State) -> Result<CorpusId, Error> {
+        let count = state.corpus().count();
+        if count == 0 {
+            return Err(Error::empty("No entries in corpus".to_string()));
+        }
+        if self.last_productive.len() != count {
+            self.rebuild(state)?;
+        }
+
+        self.since_reevaluation += 1;
+        if self.since_reevaluation >= self.reevaluation_interval {
+            self.since_reevaluation = 0;
+            self.demote_unproductive(*state.executions());
+        }
+
+        let id = self.queue.pick(state.rand_mut(), self.low_priority_percent);
+        self.set_current_scheduled(state, Some(id))?;
+        Ok(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::EpochScheduler;
+    use crate::{
+        corpus::{Corpus, CorpusId, Testcase},
+        inputs::BytesInput,
+        schedulers::Scheduler,
+        state::{test::test_std_state, HasCorpus, HasExecutions},
+    };
+
+    #[test]
+    fn test_epoch_scheduler() {
+        let mut state = test_std_state::<BytesInput>();
+        let mut scheduler = EpochScheduler::new(10).with_reevaluation_interval(2);
+
+        for i in 0..2_u8 {
+            let idx = state
+                .corpus_mut()
+                .add(Testcase::new(BytesInput::new(vec![i])))
+                .unwrap();
+            scheduler.on_add(&mut state, idx).unwrap();
+        }
+        assert_eq!(scheduler.high_priority().len(), 2);
+
+        // the entries are only checked every second scheduling
+        *state.executions_mut() = 10;
+        scheduler.next(&mut state).unwrap();
+        assert_eq!(scheduler.high_priority().len(), 2);
+        scheduler.next(&mut state).unwrap();
+        assert!(scheduler.high_priority().is_empty());
+        assert_eq!(scheduler.low_priority().len(), 2);
+
+        // an interesting child promotes its parent
+        let parent = CorpusId::from(0_usize);
+        *state.corpus_mut().current_mut() = Some(parent);
+        let idx = state
+            .corpus_mut()
+            .add(Testcase::new(BytesInput::new(vec![2])))
+            .unwrap();
+        scheduler.on_add(&mut state, idx).unwrap();
+        assert_eq!(scheduler.high_priority().len(), 2);
+        assert!(scheduler.high_priority().contains(&parent));
+        assert_eq!(scheduler.low_priority(), &[CorpusId::from(1_usize)]);
+    }
+
+    #[test]
+    fn test_epoch_scheduler_rebuild() {
+        let mut state = test_std_state::<BytesInput>();
+        let mut scheduler = EpochScheduler::new(10);
+        let idx = state
+            .corpus_mut()
+            .add(Testcase::new(BytesInput::new(vec![0])))
+            .unwrap();
+        scheduler.on_add(&mut state, idx).unwrap();
+
+        // a fresh scheduler, e.g. after a restart, restores the ages from the metadata
+        let mut restarted = EpochScheduler::new(10).with_reevaluation_interval(1);
+        *state.executions_mut() = 20;
+        assert_eq!(restarted.next(&mut state).unwrap(), idx);
+        assert_eq!(restarted.low_priority(), &[idx]);
+    }
+}
@@ -0,0 +1,178 @@
+//! The [`IndexedCorpusScheduler`] picks corpus entries uniformly at random, in constant time also for huge corpora.
+//!
+//! Corpora such as the `CachedOnDiskCorpus` do not store their ids contiguously, so picking a random entry
+//! means walking the ids up to the random position. This scheduler keeps its own flat array of the ids instead,
+//! updated as entries are added and removed.
+
+use alloc::{borrow::ToOwned, vec::Vec};
+use core::marker::PhantomData;
+
+use hashbrown::HashMap;
+use libafl_bolts::rands::Rand;
+
+use crate::{
+    corpus::{Corpus, CorpusId, HasTestcase, Testcase},
+    inputs::UsesInput,
+    schedulers::{RemovableScheduler, Scheduler},
+    state::{HasCorpus, HasRand, State, UsesState},
+    Error,
+};
+
+/// Schedules the corpus entries uniformly at random, using a flat array of their ids
+#[derive(Debug, Clone)]
+pub struct IndexedCorpusScheduler<S> {
+    /// The ids of all entries of the corpus, in no particular order
+    ids: Vec<CorpusId>,
+    /// The position of each id in `ids`
+    positions: HashMap<CorpusId, usize>,
+    phantom: PhantomData<S>,
+}
+
+impl<S> IndexedCorpusScheduler<S> {
+    /// Creates a new [`IndexedCorpusScheduler`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            ids: Vec::new(),
+            positions: HashMap::new(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// The number of entries in the index
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Returns `true` if the index holds no entries
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    fn insert(&mut self, idx: CorpusId) {
+        if !self.positions.contains_key(&idx) {
+            self.positions.insert(idx, self.ids.len());
+            self.ids.push(idx);
+        }
+    }
+
+    fn remove(&mut self, idx: CorpusId) {
+        if let Some(pos) = self.positions.remove(&idx) {
+            self.ids.swap_remove(pos);
+            if let Some(moved) = self.ids.get(pos) {
+                self.positions.insert(*moved, pos);
+            }
+        }
+    }
+}
+
+impl<S> IndexedCorpusScheduler<S>
+where
+    S: HasCorpus,
+{
+    /// Rebuilds the index from the corpus, e.g. after a restart, which does not restore the scheduler
+    fn rebuild(&mut self, state: &S) {
+        self.ids.clear();
+        self.positions.clear();
+        for idx in state.corpus().ids() {
+            self.insert(idx);
+        }
+    }
+}
+
+impl<S> Default for IndexedCorpusScheduler<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> UsesState for IndexedCorpusScheduler<S>
+where
+    S: State,
+{
+    type State = S;
+}
+
+impl<S> RemovableScheduler for IndexedCorpusScheduler<S>
+where
+    S: HasCorpus + HasTestcase + HasRand + State,
+{
+    fn on_remove(
+        &mut self,
+        _state: &mut Self::State,
+        idx: CorpusId,
+        _testcase: &Option<Testcase<<Self::State as UsesInput>::Input>>,
+    ) -> Result<(), Error> {
+        self.remove(idx);
+        Ok(())
+    }
+}
+
+impl<S> Scheduler for IndexedCorpusScheduler<S>
+where
+    S: HasCorpus + HasTestcase + HasRand + State,
+{
+    fn on_add(&mut self, state: &mut Self::State, idx: CorpusId) -> Result<(), Error> {
+        let current_idx = *state.corpus().current();
+        state
+            .corpus()
+            .get(idx)?
+            .borrow_mut()
+            .set_parent_id_optional(current_idx);
+        self.insert(idx);
+        Ok(())
+    }
+
+    /// Gets a random entry, in constant time
+    #[allow(clippy::cast_possible_truncation)]
+    fn next(&mut self, state: &mut Self::State) -> Result<CorpusId, Error> {
+        let count = state.corpus().count();
+        if count == 0 {
+            return Err(Error::empty("No entries in corpus".to_owned()));
+        }
+        if self.ids.len() != count {
+            self.rebuild(state);
+        }
+        let id = self.ids[state.rand_mut().below(self.ids.len() as u64) as usize];
+        self.set_current_scheduled(state, Some(id))?;
+        Ok(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        corpus::{Corpus, CorpusId, Testcase},
+        inputs::BytesInput,
+        schedulers::{IndexedCorpusScheduler, RemovableScheduler, Scheduler},
+        state::{test::test_std_state, HasCorpus},
+    };
+
+    #[test]
+    fn test_indexed_scheduler() {
+        let mut state = test_std_state::<BytesInput>();
+        for i in 0..4 {
+            state
+                .corpus_mut()
+                .add(Testcase::new(BytesInput::new(vec![i])))
+                .unwrap();
+        }
+
+        let mut scheduler = IndexedCorpusScheduler::new();
+        // the index is rebuilt from the corpus on first use
+        scheduler.next(&mut state).unwrap();
+        assert_eq!(scheduler.len(), 4);
+
+        let removed = CorpusId::from(1_usize);
+        let testcase = state.corpus_mut().remove(removed).unwrap();
+        scheduler
+            .on_remove(&mut state, removed, &Some(testcase))
+            .unwrap();
+        assert_eq!(scheduler.len(), 3);
+        for _ in 0..32 {
+            assert_ne!(scheduler.next(&mut state).unwrap(), removed);
+        }
+    }
+}
@@ -9,6 +9,9 @@ pub use testcase_score::{LenTimeMulTestcaseScore, TestcaseScore};
 pub mod queue;
 pub use queue::QueueScheduler;
 
+pub mod indexed;
+pub use indexed::IndexedCorpusScheduler;
+
 pub mod minimizer;
 pub use minimizer::{
     IndexesLenTimeMinimizerScheduler, LenTimeMinimizerScheduler, MinimizerScheduler,
@@ -30,6 +33,20 @@ pub mod tuneable;
 use libafl_bolts::rands::Rand;
 pub use tuneable::*;
 
+pub mod token_frequency;
+pub use token_frequency::{TokenFrequencyMetadata, TokenFrequencyScheduler};
+
+pub mod afll;
+pub use afll::{AfllMetadata, AfllScheduler};
+
+pub mod coverage_history;
+pub use coverage_history::{CoverageHistoryMetadata, CoverageHistoryScheduler};
+
+pub mod epoch;
+pub use epoch::{EpochMetadata, EpochScheduler};
+
+mod two_tier;
+
 use crate::{
     corpus::{Corpus, CorpusId, HasTestcase, SchedulerTestcaseMetadata, Testcase},
     inputs::UsesInput,
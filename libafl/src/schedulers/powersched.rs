@@ -1,10 +1,11 @@
 //! The queue corpus scheduler for power schedules.
 
 use alloc::{
+    format,
     string::{String, ToString},
     vec::Vec,
 };
-use core::{marker::PhantomData, time::Duration};
+use core::{fmt, marker::PhantomData, str::FromStr, time::Duration};
 
 use serde::{Deserialize, Serialize};
 
@@ -168,6 +169,46 @@ pub enum PowerSchedule {
     QUAD,
 }
 
+impl PowerSchedule {
+    /// The name of this power schedule, as passed to `afl-fuzz -p`
+    #[must_use]
+    pub fn name(&self) -> &'static str {
+        match self {
+            PowerSchedule::EXPLORE => "explore",
+            PowerSchedule::EXPLOIT => "exploit",
+            PowerSchedule::FAST => "fast",
+            PowerSchedule::COE => "coe",
+            PowerSchedule::LIN => "lin",
+            PowerSchedule::QUAD => "quad",
+        }
+    }
+}
+
+impl fmt::Display for PowerSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Parses the power schedule names of `afl-fuzz -p`, ignoring case
+impl FromStr for PowerSchedule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "explore" => Ok(PowerSchedule::EXPLORE),
+            "exploit" => Ok(PowerSchedule::EXPLOIT),
+            "fast" => Ok(PowerSchedule::FAST),
+            "coe" => Ok(PowerSchedule::COE),
+            "lin" => Ok(PowerSchedule::LIN),
+            "quad" => Ok(PowerSchedule::QUAD),
+            _ => Err(Error::illegal_argument(format!(
+                "Unknown power schedule {s}, expected one of explore, exploit, fast, coe, lin or quad"
+            ))),
+        }
+    }
+}
+
 /// A corpus scheduler using power schedules
 /// Note that this corpus is merely holding the metadata necessary for the power calculation
 /// and here we DON'T actually calculate the power (we do it in the stage)
@@ -0,0 +1,304 @@
+//! The [`TokenFrequencyScheduler`] prefers corpus entries containing tokens that are rare across the corpus.
+//! Entries whose tokens already show up in many other entries become (almost) redundant and get scheduled less often.
+
+use alloc::{borrow::ToOwned, vec::Vec};
+use core::marker::PhantomData;
+
+use hashbrown::HashMap;
+use libafl_bolts::rands::Rand;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{Corpus, CorpusId, HasTestcase, Testcase},
+    inputs::{HasBytesVec, UsesInput},
+    mutators::Tokens,
+    schedulers::{RemovableScheduler, Scheduler},
+    state::{HasCorpus, HasMetadata, HasRand, State, UsesState},
+    Error,
+};
+
+/// A state metadata holding the frequency of each [`Tokens`] entry across the corpus
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct TokenFrequencyMetadata {
+    /// token -> number of corpus entries containing it
+    pub token_frequency_map: HashMap<Vec<u8>, usize>,
+    /// corpus index -> tokens contained in that entry
+    pub entry_tokens: HashMap<CorpusId, Vec<Vec<u8>>>,
+}
+
+libafl_bolts::impl_serdeany!(TokenFrequencyMetadata);
+
+impl TokenFrequencyMetadata {
+    /// Creates a new [`struct@TokenFrequencyMetadata`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the tokens contained in the entry `idx`
+    pub fn add_entry(&mut self, idx: CorpusId, tokens: Vec<Vec<u8>>) {
+        for token in &tokens {
+            *self.token_frequency_map.entry(token.clone()).or_insert(0) += 1;
+        }
+        self.entry_tokens.insert(idx, tokens);
+    }
+
+    /// Forgets about the entry `idx`, decreasing the frequency of its tokens
+    pub fn remove_entry(&mut self, idx: CorpusId) {
+        let Some(tokens) = self.entry_tokens.remove(&idx) else {
+            return;
+        };
+        for token in tokens {
+            if let Some(freq) = self.token_frequency_map.get_mut(&token) {
+                *freq -= 1;
+                if *freq == 0 {
+                    self.token_frequency_map.remove(&token);
+                }
+            }
+        }
+    }
+
+    /// The scheduling weight of the entry `idx`: the mean of the inverse frequencies of its tokens.
+    /// Entries without any token get the maximum weight, `1.0`.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn weight(&self, idx: CorpusId) -> f64 {
+        let Some(tokens) = self.entry_tokens.get(&idx) else {
+            return 1.0;
+        };
+        if tokens.is_empty() {
+            return 1.0;
+        }
+        let sum: f64 = tokens
+            .iter()
+            .map(|token| 1.0 / self.token_frequency_map[token] as f64)
+            .sum();
+        sum / tokens.len() as f64
+    }
+}
+
+/// Schedules corpus entries with a probability inversely proportional to the frequency of their tokens.
+///
+/// The tokens are taken from the [`Tokens`] metadata of the state when an entry gets added.
+/// If no [`Tokens`] are available, all entries are equally likely.
+/// The weights are recomputed only after the corpus changed, scheduling an entry is a binary search.
+#[derive(Debug, Clone)]
+pub struct TokenFrequencyScheduler<S> {
+    /// The cumulative weights of the entries, in the order of their ids
+    cumulative_weights: Vec<(CorpusId, f64)>,
+    /// If the corpus changed since the weights were computed
+    dirty: bool,
+    phantom: PhantomData<S>,
+}
+
+impl<S> UsesState for TokenFrequencyScheduler<S>
+where
+    S: State,
+{
+    type State = S;
+}
+
+impl<S> RemovableScheduler for TokenFrequencyScheduler<S>
+where
+    S: HasCorpus + HasMetadata + HasRand + HasTestcase + State,
+    S::Input: HasBytesVec,
+{
+    fn on_remove(
+        &mut self,
+        state: &mut Self::State,
+        idx: CorpusId,
+        _testcase: &Option<Testcase<<Self::State as UsesInput>::Input>>,
+    ) -> Result<(), Error> {
+        state
+            .metadata_mut::<TokenFrequencyMetadata>()?
+            .remove_entry(idx);
+        self.dirty = true;
+        Ok(())
+    }
+
+    fn on_replace(
+        &mut self,
+        state: &mut Self::State,
+        idx: CorpusId,
+        _prev: &Testcase<<Self::State as UsesInput>::Input>,
+    ) -> Result<(), Error> {
+        state
+            .metadata_mut::<TokenFrequencyMetadata>()?
+            .remove_entry(idx);
+        self.dirty = true;
+        self.store_tokens(state, idx)
+    }
+}
+
+impl<S> Scheduler for TokenFrequencyScheduler<S>
+where
+    S: HasCorpus + HasMetadata + HasRand + HasTestcase + State,
+    S::Input: HasBytesVec,
+{
+    fn on_add(&mut self, state: &mut Self::State, idx: CorpusId) -> Result<(), Error> {
+        let current_idx = *state.corpus().current();
+        state
+            .corpus()
+            .get(idx)?
+            .borrow_mut()
+            .set_parent_id_optional(current_idx);
+
+        if !state.has_metadata::<TokenFrequencyMetadata>() {
+            state.add_metadata(TokenFrequencyMetadata::new());
+        }
+        self.dirty = true;
+        self.store_tokens(state, idx)
+    }
+
+    /// Gets the next entry, sampled according to the token frequency weights
+    #[allow(clippy::cast_precision_loss)]
+    fn next(&mut self, state: &mut Self::State) -> Result<CorpusId, Error> {
+        if state.corpus().count() == 0 {
+            return Err(Error::empty("No entries in corpus".to_owned()));
+        }
+
+        if self.dirty {
+            self.compute_weights(state.metadata::<TokenFrequencyMetadata>()?);
+        }
+
+        let rand_prob = (state.rand_mut().below(1 << 32) as f64) / ((1_u64 << 32) as f64);
+        let ret = match self.cumulative_weights.last() {
+            None => state.corpus().first().unwrap(),
+            Some((_, total)) => {
+                let threshold = total * rand_prob;
+                let pos = self
+                    .cumulative_weights
+                    .partition_point(|(_, weight)| *weight < threshold)
+                    .min(self.cumulative_weights.len() - 1);
+                self.cumulative_weights[pos].0
+            }
+        };
+
+        self.set_current_scheduled(state, Some(ret))?;
+        Ok(ret)
+    }
+}
+
+impl<S> TokenFrequencyScheduler<S>
+where
+    S: HasCorpus + HasMetadata + HasTestcase + State,
+    S::Input: HasBytesVec,
+{
+    /// Creates a new [`TokenFrequencyScheduler`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            cumulative_weights: Vec::new(),
+            dirty: true,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Computes the cumulative weights of the entries, in a deterministic order
+    fn compute_weights(&mut self, meta: &TokenFrequencyMetadata) {
+        let mut ids: Vec<CorpusId> = meta.entry_tokens.keys().copied().collect();
+        ids.sort_unstable();
+
+        self.cumulative_weights.clear();
+        let mut total = 0.0;
+        for idx in ids {
+            total += meta.weight(idx);
+            self.cumulative_weights.push((idx, total));
+        }
+        self.dirty = false;
+    }
+
+    /// Finds the [`Tokens`] contained in the entry `idx` and updates the frequencies
+    #[allow(clippy::unused_self)]
+    fn store_tokens(&self, state: &mut S, idx: CorpusId) -> Result<(), Error> {
+        let found: Vec<Vec<u8>> = {
+            let Some(tokens) = state.metadata_map().get::<Tokens>() else {
+                state
+                    .metadata_mut::<TokenFrequencyMetadata>()?
+                    .add_entry(idx, Vec::new());
+                return Ok(());
+            };
+            let mut testcase = state.corpus().get(idx)?.borrow_mut();
+            let bytes = testcase.load_input(state.corpus())?.bytes();
+            tokens
+                .iter()
+                .filter(|token| {
+                    !token.is_empty()
+                        && bytes
+                            .windows(token.len())
+                            .any(|window| window == token.as_slice())
+                })
+                .cloned()
+                .collect()
+        };
+
+        state
+            .metadata_mut::<TokenFrequencyMetadata>()?
+            .add_entry(idx, found);
+        Ok(())
+    }
+}
+
+impl<S> Default for TokenFrequencyScheduler<S>
+where
+    S: HasCorpus + HasMetadata + HasTestcase + State,
+    S::Input: HasBytesVec,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use libafl_bolts::rands::StdRand;
+
+    use super::TokenFrequencyScheduler;
+    use crate::{
+        corpus::{Corpus, CorpusId, InMemoryCorpus, Testcase},
+        inputs::BytesInput,
+        mutators::Tokens,
+        schedulers::Scheduler,
+        state::{test::test_std_state, HasCorpus, HasMetadata, StdState},
+    };
+
+    type TestState =
+        StdState<BytesInput, InMemoryCorpus<BytesInput>, StdRand, InMemoryCorpus<BytesInput>>;
+
+    fn schedule(count: usize) -> Vec<CorpusId> {
+        let mut state: TestState = test_std_state();
+        state.add_metadata(Tokens::from(vec![b"common".to_vec(), b"rare".to_vec()]));
+
+        let mut scheduler = TokenFrequencyScheduler::new();
+        for bytes in [&b"common"[..], b"common", b"common", b"rare"] {
+            let idx = state
+                .corpus_mut()
+                .add(Testcase::new(BytesInput::new(bytes.to_vec())))
+                .unwrap();
+            scheduler.on_add(&mut state, idx).unwrap();
+        }
+        (0..count)
+            .map(|_| scheduler.next(&mut state).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_token_frequency_scheduler() {
+        let scheduled = schedule(1000);
+        // the entries are scheduled the same way for the same seed
+        assert_eq!(scheduled, schedule(1000));
+
+        // the single entry with the rare token weighs as much as the three common ones together
+        let rare = scheduled
+            .iter()
+            .filter(|idx| **idx == CorpusId::from(3_usize))
+            .count();
+        assert!((400..600).contains(&rare), "{rare}");
+    }
+}
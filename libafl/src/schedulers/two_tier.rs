@@ -0,0 +1,110 @@
+//! The [`TwoTierQueue`] splits the corpus entries into a tier scheduled uniformly and a tier scheduled rarely,
+//! shared by the [`super::EpochScheduler`] and the [`super::CoverageHistoryScheduler`].
+
+use alloc::vec::Vec;
+
+use libafl_bolts::rands::Rand;
+
+use crate::corpus::CorpusId;
+
+/// The entries of a scheduler, in a high and a low priority tier
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TwoTierQueue {
+    /// The entries scheduled uniformly
+    pub(crate) high: Vec<CorpusId>,
+    /// The entries scheduled with a small probability
+    pub(crate) low: Vec<CorpusId>,
+}
+
+impl TwoTierQueue {
+    /// Creates a new, empty [`TwoTierQueue`]
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forgets all entries
+    pub(crate) fn clear(&mut self) {
+        self.high.clear();
+        self.low.clear();
+    }
+
+    /// Forgets an entry of either tier, returns `true` if it had high priority
+    pub(crate) fn forget(&mut self, idx: CorpusId) -> bool {
+        if let Some(pos) = self.high.iter().position(|id| *id == idx) {
+            self.high.swap_remove(pos);
+            true
+        } else {
+            if let Some(pos) = self.low.iter().position(|id| *id == idx) {
+                self.low.swap_remove(pos);
+            }
+            false
+        }
+    }
+
+    /// Moves an entry to the high tier, if it has low priority
+    pub(crate) fn promote(&mut self, idx: CorpusId) {
+        if let Some(pos) = self.low.iter().position(|id| *id == idx) {
+            self.low.swap_remove(pos);
+            self.high.push(idx);
+        }
+    }
+
+    /// Moves the entries of the high tier matching `demote` to the low tier, in place
+    pub(crate) fn demote_where<F>(&mut self, mut demote: F)
+    where
+        F: FnMut(CorpusId) -> bool,
+    {
+        let mut i = 0;
+        while i < self.high.len() {
+            let idx = self.high[i];
+            if demote(idx) {
+                self.high.swap_remove(i);
+                self.low.push(idx);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    /// Picks a random entry of the high tier, or with a probability of `low_percent` percent of the low tier.
+    /// The tiers must not be both empty.
+    #[allow(clippy::cast_possible_truncation)]
+    pub(crate) fn pick<R>(&self, rand: &mut R, low_percent: u64) -> CorpusId
+    where
+        R: Rand,
+    {
+        let pick_low =
+            self.high.is_empty() || (!self.low.is_empty() && rand.below(100) < low_percent);
+        let entries = if pick_low { &self.low } else { &self.high };
+        entries[rand.below(entries.len() as u64) as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::rands::StdRand;
+
+    use super::TwoTierQueue;
+    use crate::corpus::CorpusId;
+
+    #[test]
+    fn test_two_tier_queue() {
+        let mut queue = TwoTierQueue::new();
+        queue.high.extend((0..4_usize).map(CorpusId::from));
+        queue.demote_where(|idx| usize::from(idx) % 2 == 0);
+        assert_eq!(queue.high.len(), 2);
+        assert_eq!(queue.low.len(), 2);
+
+        let mut rand = StdRand::with_seed(0);
+        for _ in 0..100 {
+            assert_eq!(usize::from(queue.pick(&mut rand, 0)) % 2, 1);
+            assert_eq!(usize::from(queue.pick(&mut rand, 100)) % 2, 0);
+        }
+
+        queue.promote(CorpusId::from(0_usize));
+        assert!(queue.forget(CorpusId::from(0_usize)));
+        assert!(!queue.forget(CorpusId::from(2_usize)));
+        assert_eq!(queue.high.len(), 2);
+        assert!(queue.low.is_empty());
+    }
+}
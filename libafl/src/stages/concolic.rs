@@ -8,6 +8,8 @@ use alloc::{borrow::ToOwned, string::ToString, vec::Vec};
 use core::marker::PhantomData;
 
 use libafl_bolts::tuples::MatchName;
+#[cfg(feature = "concolic_mutation")]
+use libafl_bolts::Named;
 
 use super::{RetryProgress, RetryingStage, Stage, TracingStage};
 #[cfg(all(feature = "concolic_mutation", feature = "introspection"))]
@@ -25,9 +27,10 @@ use crate::{
 };
 #[cfg(feature = "concolic_mutation")]
 use crate::{
+    feedbacks::{PathConstraintFeedback, PathConstraintMetadata},
     inputs::HasBytesVec,
     mark_feature_time,
-    observers::concolic::{ConcolicMetadata, SymExpr, SymExprRef},
+    observers::concolic::{ConcolicMetadata, Location, SymExpr, SymExprRef},
     start_timer, Evaluator,
 };
 
@@ -111,8 +114,19 @@ impl<EM, TE, Z> ConcolicTracingStage<EM, TE, Z> {
 }
 
 #[cfg(feature = "concolic_mutation")]
-#[allow(clippy::too_many_lines)]
 fn generate_mutations(iter: impl Iterator<Item = (SymExprRef, SymExpr)>) -> Vec<Vec<(usize, u8)>> {
+    generate_mutations_negating(iter, |_, _| true)
+}
+
+/// Solves the path constraints of a concolic trace, negating only those for which `should_negate`
+/// returns `true`, given their location and if the branch was taken.
+/// The others are kept as they are, to stay on the traced path.
+#[cfg(feature = "concolic_mutation")]
+#[allow(clippy::too_many_lines)]
+pub(crate) fn generate_mutations_negating(
+    iter: impl Iterator<Item = (SymExprRef, SymExpr)>,
+    mut should_negate: impl FnMut(Location, bool) -> bool,
+) -> Vec<Vec<(usize, u8)>> {
     use hashbrown::HashMap;
     use z3::{
         ast::{Ast, Bool, Dynamic, BV},
@@ -293,13 +307,18 @@ fn generate_mutations(iter: impl Iterator<Item = (SymExprRef, SymExpr)>) -> Vec<
         if let Some(expr) = z3_expr {
             translation.insert(id, expr);
         } else if let SymExpr::PathConstraint {
-            constraint, taken, ..
+            constraint,
+            taken,
+            location,
         } = msg
         {
             let op = translation[&constraint].as_bool().unwrap();
             let op = if taken { op } else { op.not() }.simplify();
             if op.as_bool().is_some() {
                 // this constraint is useless, as it is always sat or unsat
+            } else if !should_negate(location, taken) {
+                // keep following the traced path
+                solver.assert(&op);
             } else {
                 let negated_constraint = op.not().simplify();
                 solver.push();
@@ -427,3 +446,77 @@ impl<Z> Default for SimpleConcolicMutationalStage<Z> {
         }
     }
 }
+
+/// A stage evaluating the inputs solved by a [`PathConstraintFeedback`] through the fuzzer,
+/// so they go through the feedbacks, the scheduler and the events like any other input.
+#[cfg(feature = "concolic_mutation")]
+#[derive(Clone, Debug)]
+pub struct PathConstraintSolutionsStage<Z> {
+    /// The name of the [`PathConstraintMetadata`] of the feedback
+    name: String,
+    phantom: PhantomData<Z>,
+}
+
+#[cfg(feature = "concolic_mutation")]
+impl<Z> UsesState for PathConstraintSolutionsStage<Z>
+where
+    Z: UsesState,
+{
+    type State = Z::State;
+}
+
+#[cfg(feature = "concolic_mutation")]
+impl<E, EM, Z> Stage<E, EM, Z> for PathConstraintSolutionsStage<Z>
+where
+    E: UsesState<State = Z::State>,
+    EM: UsesState<State = Z::State>,
+    Z: Evaluator<E, EM>,
+    Z::Input: HasBytesVec,
+    Z::State: State + HasCorpus + HasNamedMetadata,
+{
+    type Progress = ();
+
+    #[inline]
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut Z::State,
+        manager: &mut EM,
+    ) -> Result<(), Error> {
+        let pending = core::mem::take(
+            &mut state
+                .named_metadata_mut::<PathConstraintMetadata>(&self.name)?
+                .pending,
+        );
+        if pending.is_empty() {
+            return Ok(());
+        }
+
+        let Some(corpus_idx) = state.current_corpus_idx()? else {
+            return Err(Error::illegal_state(
+                "state is not currently processing a corpus index",
+            ));
+        };
+        // The solutions only carry the bytes, the rest of the input is taken from the current entry
+        let base = state.corpus().cloned_input_for_id(corpus_idx)?;
+        for bytes in pending {
+            let mut input = base.clone();
+            *input.bytes_mut() = bytes;
+            fuzzer.evaluate_input(state, executor, manager, input)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "concolic_mutation")]
+impl<Z> PathConstraintSolutionsStage<Z> {
+    /// Creates a new [`PathConstraintSolutionsStage`], evaluating the inputs solved by `feedback`
+    #[must_use]
+    pub fn new<S>(feedback: &PathConstraintFeedback<S>) -> Self {
+        Self {
+            name: feedback.name().to_string(),
+            phantom: PhantomData,
+        }
+    }
+}
@@ -10,6 +10,8 @@ pub use calibrate::CalibrationStage;
 pub use colorization::*;
 #[cfg(feature = "std")]
 pub use concolic::ConcolicTracingStage;
+#[cfg(all(feature = "std", feature = "concolic_mutation"))]
+pub use concolic::PathConstraintSolutionsStage;
 #[cfg(feature = "std")]
 pub use concolic::SimpleConcolicMutationalStage;
 #[cfg(feature = "std")]
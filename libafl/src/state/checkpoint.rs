@@ -0,0 +1,222 @@
+//! The [`CheckpointManager`] periodically writes the whole fuzzer state to disk, so that a campaign can be resumed
+//! after the fuzzer died, e.g. by the OOM killer or a reboot.
+//!
+//! The state is serialized with `postcard`, including the corpus and the solutions it holds.
+//! Corpora on disk only serialize the paths of their testcases, the files themselves are not part of a checkpoint.
+
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use std::{
+    fs::{self, File, OpenOptions},
+    path::{Path, PathBuf},
+};
+
+use libafl_bolts::fs::write_file_atomic;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{state::HasExecutions, Error};
+
+/// The default number of checkpoints kept in the checkpoint directory
+pub const DEFAULT_MAX_CHECKPOINTS: usize = 3;
+
+/// The name of the lock file in the checkpoint directory
+const CHECKPOINT_LOCK_FILE: &str = ".checkpoint.lafl_lock";
+
+/// Writes the state to `<dir>/checkpoint_<n>.bin` every `checkpoint_interval` executions.
+///
+/// A [`CheckpointManager`] holds an exclusive lock on its directory as long as it lives,
+/// so that multiple instances cannot overwrite each other's checkpoints.
+/// The lock is an advisory `flock` on unix, released by the OS even if the fuzzer crashes.
+/// On other platforms, the directory is not locked.
+#[derive(Debug)]
+pub struct CheckpointManager<S> {
+    dir: PathBuf,
+    checkpoint_interval: usize,
+    max_checkpoints: usize,
+    /// The executions of the state at the last checkpoint
+    last_executions: usize,
+    /// The index of the next checkpoint
+    next_idx: usize,
+    /// The locked lock file, unlocked once closed
+    #[allow(dead_code)]
+    lock: File,
+    phantom: PhantomData<S>,
+}
+
+impl<S> CheckpointManager<S>
+where
+    S: Serialize + DeserializeOwned + HasExecutions,
+{
+    /// Creates a new [`CheckpointManager`], checkpointing to `dir` every `checkpoint_interval` executions.
+    /// Fails if another instance holds the lock on `dir`.
+    pub fn new<P: AsRef<Path>>(dir: P, checkpoint_interval: usize) -> Result<Self, Error> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let lock = lock_dir(&dir)?;
+        let next_idx = checkpoints(&dir)?.last().map_or(0, |(idx, _)| idx + 1);
+        Ok(Self {
+            dir,
+            checkpoint_interval,
+            max_checkpoints: DEFAULT_MAX_CHECKPOINTS,
+            last_executions: 0,
+            next_idx,
+            lock,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Creates a new [`CheckpointManager`] for `dir`, and loads the state of the most recent checkpoint in it, if any.
+    pub fn from_latest_checkpoint<P: AsRef<Path>>(
+        dir: P,
+        checkpoint_interval: usize,
+    ) -> Result<(Self, Option<S>), Error> {
+        let mut manager = Self::new(dir, checkpoint_interval)?;
+        let Some((_, path)) = checkpoints(&manager.dir)?.pop() else {
+            return Ok((manager, None));
+        };
+        log::info!("Resuming from checkpoint {}", path.display());
+        let state: S = postcard::from_bytes(&fs::read(path)?)?;
+        manager.last_executions = *state.executions();
+        Ok((manager, Some(state)))
+    }
+
+    /// Sets the number of checkpoints kept, older ones are deleted
+    #[must_use]
+    pub fn with_max_checkpoints(mut self, max_checkpoints: usize) -> Self {
+        assert!(
+            max_checkpoints > 0,
+            "At least one checkpoint needs to be kept"
+        );
+        self.max_checkpoints = max_checkpoints;
+        self
+    }
+
+    /// The directory holding the checkpoints
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Writes a checkpoint if the state ran at least `checkpoint_interval` executions since the last one.
+    /// Returns `true` if a checkpoint was written.
+    pub fn maybe_checkpoint(&mut self, state: &S) -> Result<bool, Error> {
+        if state.executions().saturating_sub(self.last_executions) < self.checkpoint_interval {
+            return Ok(false);
+        }
+        self.checkpoint(state)?;
+        Ok(true)
+    }
+
+    /// Writes a checkpoint of `state`, returning its path
+    pub fn checkpoint(&mut self, state: &S) -> Result<PathBuf, Error> {
+        let path = checkpoint_file(&self.dir, self.next_idx);
+        // a crash while writing leaves the previous checkpoints intact, but the temporary file behind.
+        // As this instance holds the lock, nobody else is writing it.
+        let _ = fs::remove_file(
+            self.dir
+                .join(format!(".checkpoint_{}.bin.tmp", self.next_idx)),
+        );
+        write_file_atomic(&path, &postcard::to_allocvec(state)?)?;
+        self.next_idx += 1;
+        self.last_executions = *state.executions();
+
+        let checkpoints = checkpoints(&self.dir)?;
+        if checkpoints.len() > self.max_checkpoints {
+            for (_, old) in &checkpoints[..checkpoints.len() - self.max_checkpoints] {
+                fs::remove_file(old)?;
+            }
+        }
+        Ok(path)
+    }
+}
+
+/// The path of the `idx`th checkpoint in `dir`
+fn checkpoint_file(dir: &Path, idx: usize) -> PathBuf {
+    dir.join(format!("checkpoint_{idx}.bin"))
+}
+
+/// The checkpoints in `dir`, sorted by their index
+fn checkpoints(dir: &Path) -> Result<Vec<(usize, PathBuf)>, Error> {
+    let mut checkpoints = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let idx = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix("checkpoint_"))
+            .and_then(|name| name.strip_suffix(".bin"))
+            .and_then(|idx| idx.parse::<usize>().ok());
+        if let Some(idx) = idx {
+            checkpoints.push((idx, path));
+        }
+    }
+    checkpoints.sort_unstable();
+    Ok(checkpoints)
+}
+
+/// Opens the lock file of `dir`, and locks it exclusively
+fn lock_dir(dir: &Path) -> Result<File, Error> {
+    let lock = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .open(dir.join(CHECKPOINT_LOCK_FILE))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::io::AsRawFd;
+        // # Safety
+        // The fd is valid as long as the file is open
+        if unsafe { libc::flock(lock.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+            return Err(Error::illegal_state(format!(
+                "Another instance holds the lock on the checkpoint directory {}",
+                dir.display()
+            )));
+        }
+    }
+    Ok(lock)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use libafl_bolts::rands::StdRand;
+
+    use super::CheckpointManager;
+    use crate::{
+        corpus::InMemoryCorpus,
+        inputs::BytesInput,
+        state::{test::test_std_state, HasExecutions, StdState},
+    };
+
+    type TestState =
+        StdState<BytesInput, InMemoryCorpus<BytesInput>, StdRand, InMemoryCorpus<BytesInput>>;
+
+    #[test]
+    fn test_checkpoint_manager() {
+        let dir = env::temp_dir().join(format!("libafl_checkpoints_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut state: TestState = test_std_state();
+
+        {
+            let mut manager = CheckpointManager::<TestState>::new(&dir, 10)
+                .unwrap()
+                .with_max_checkpoints(2);
+            // only one instance at a time
+            #[cfg(unix)]
+            assert!(CheckpointManager::<TestState>::new(&dir, 10).is_err());
+
+            for executions in [5, 10, 15, 20, 30] {
+                *state.executions_mut() = executions;
+                manager.maybe_checkpoint(&state).unwrap();
+            }
+            assert_eq!(fs::read_dir(&dir).unwrap().count(), 2 + 1);
+        }
+
+        let (_manager, resumed) =
+            CheckpointManager::<TestState>::from_latest_checkpoint(&dir, 10).unwrap();
+        assert_eq!(*resumed.unwrap().executions(), 30);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,5 +1,10 @@
 //! The fuzzer, and state are the core pieces of every good fuzzer
 
+#[cfg(feature = "std")]
+pub mod checkpoint;
+#[cfg(feature = "std")]
+pub use checkpoint::CheckpointManager;
+
 use alloc::vec::Vec;
 use core::{
     cell::{Ref, RefMut},
@@ -26,7 +31,7 @@ use crate::monitors::ClientPerfMonitor;
 #[cfg(feature = "scalability_introspection")]
 use crate::monitors::ScalabilityMonitor;
 use crate::{
-    corpus::{Corpus, CorpusId, HasCurrentCorpusIdx, HasTestcase, Testcase},
+    corpus::{is_refused_add, Corpus, CorpusId, HasCurrentCorpusIdx, HasTestcase, Testcase},
     events::{Event, EventFirer, LogSeverity},
     feedbacks::Feedback,
     fuzzer::{Evaluator, ExecuteInputResult},
@@ -107,6 +112,29 @@ pub trait HasRand {
     fn rand_mut(&mut self) -> &mut Self::Rand;
 }
 
+/// Trait for the seed of the [`Rand`], to replay the random decisions of a fuzzer deterministically
+pub trait HasRngSeed {
+    /// The seed the [`Rand`] was last seeded with through [`HasRngSeed::set_rng_seed`].
+    /// The seed of a [`Rand`] passed in already seeded is not known, this is `0` until it is reseeded.
+    fn rng_seed(&self) -> u64;
+
+    /// Reseeds the [`Rand`], so that it (re)starts the sequence of `seed`
+    fn set_rng_seed(&mut self, seed: u64);
+}
+
+/// The seed of the [`Rand`] of a state, kept as metadata by [`HasRngSeed::set_rng_seed`]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct RngSeedMetadata {
+    /// The seed
+    pub seed: u64,
+}
+
+libafl_bolts::impl_serdeany!(RngSeedMetadata);
+
 #[cfg(feature = "introspection")]
 /// Trait for offering a [`ClientPerfMonitor`]
 pub trait HasClientPerfMonitor {
@@ -378,6 +406,23 @@ where
     }
 }
 
+impl<I, C, R, SC> HasRngSeed for StdState<I, C, R, SC>
+where
+    R: Rand,
+{
+    #[inline]
+    fn rng_seed(&self) -> u64 {
+        self.metadata_map()
+            .get::<RngSeedMetadata>()
+            .map_or(0, |meta| meta.seed)
+    }
+
+    fn set_rng_seed(&mut self, seed: u64) {
+        self.add_metadata(RngSeedMetadata { seed });
+        self.rand.set_seed(seed);
+    }
+}
+
 impl<I, C, R, SC> HasCorpus for StdState<I, C, R, SC>
 where
     I: Input,
@@ -719,7 +764,13 @@ where
         log::info!("Loading file {:?} ...", &path);
         let input = loader(fuzzer, self, path)?;
         if forced {
-            let _: CorpusId = fuzzer.add_input(self, executor, manager, input)?;
+            match fuzzer.add_input(self, executor, manager, input) {
+                Ok(_) => {}
+                Err(err) if is_refused_add(&err) => {
+                    log::warn!("File {:?} was refused by the corpus, skipped: {err}", &path);
+                }
+                Err(err) => return Err(err),
+            }
         } else {
             let (res, _) = fuzzer.evaluate_input(self, executor, manager, input)?;
             if res == ExecuteInputResult::None {
@@ -989,8 +1040,11 @@ where
         for _ in 0..num {
             let input = generator.generate(self)?;
             if forced {
-                let _: CorpusId = fuzzer.add_input(self, executor, manager, input)?;
-                added += 1;
+                match fuzzer.add_input(self, executor, manager, input) {
+                    Ok(_) => added += 1,
+                    Err(err) if is_refused_add(&err) => {}
+                    Err(err) => return Err(err),
+                }
             } else {
                 let (res, _) = fuzzer.evaluate_input(self, executor, manager, input)?;
                 if res != ExecuteInputResult::None {
@@ -1236,9 +1290,18 @@ impl<I> HasScalabilityMonitor for NopState<I> {
 
 #[cfg(test)]
 pub mod test {
-    use libafl_bolts::rands::StdRand;
+    use alloc::vec::Vec;
+
+    use libafl_bolts::rands::{Rand, StdRand};
+    #[cfg(feature = "std")]
+    use libafl_bolts::{
+        shmem::{ShMemProvider, StdShMemProvider},
+        staterestore::StateRestorer,
+    };
+    #[cfg(feature = "std")]
+    use serial_test::serial;
 
-    use super::StdState;
+    use super::{HasRand, HasRngSeed, StdState};
     use crate::{
         corpus::InMemoryCorpus,
         inputs::{Input, NopInput},
@@ -1258,6 +1321,65 @@ pub mod test {
         .expect("couldn't instantiate the test state")
     }
 
+    #[test]
+    fn test_new_keeps_the_rand() {
+        let mut state = test_std_state::<NopInput>();
+        assert_eq!(state.rng_seed(), 0);
+        assert_eq!(state.rand_mut().next(), StdRand::with_seed(0).next());
+    }
+
+    #[test]
+    fn test_rng_seed_restore() {
+        let mut rand = StdRand::with_seed(1337);
+        let expected: Vec<u64> = (0..2).map(|_| rand.next()).collect();
+
+        let mut state = test_std_state::<NopInput>();
+        state.set_rng_seed(1337);
+        assert_eq!(state.rand_mut().next(), expected[0]);
+
+        let serialized = postcard::to_allocvec(&state).unwrap();
+        let mut restored: StdState<
+            NopInput,
+            InMemoryCorpus<NopInput>,
+            StdRand,
+            InMemoryCorpus<NopInput>,
+        > = postcard::from_bytes(&serialized).unwrap();
+        assert_eq!(restored.rng_seed(), 1337);
+        assert_eq!(restored.rand_mut().next(), expected[1]);
+
+        let mut replayed = test_std_state::<NopInput>();
+        replayed.set_rng_seed(1337);
+        assert_eq!(replayed.rand_mut().next(), expected[0]);
+        assert_eq!(replayed.rand_mut().next(), expected[1]);
+    }
+
+    /// The restarting event managers hand the state to the next client through a [`StateRestorer`]
+    #[test]
+    #[serial]
+    #[cfg(feature = "std")]
+    #[cfg_attr(miri, ignore)]
+    fn test_rng_state_restorer() {
+        let mut rand = StdRand::with_seed(1337);
+        let expected: Vec<u64> = (0..2).map(|_| rand.next()).collect();
+
+        let mut state = test_std_state::<NopInput>();
+        state.set_rng_seed(1337);
+        assert_eq!(state.rand_mut().next(), expected[0]);
+
+        let mut shmem_provider = StdShMemProvider::new().unwrap();
+        let mut state_restorer =
+            StateRestorer::<StdShMemProvider>::new(shmem_provider.new_shmem(4096).unwrap());
+        state_restorer.save(&state).unwrap();
+        let mut restored: StdState<
+            NopInput,
+            InMemoryCorpus<NopInput>,
+            StdRand,
+            InMemoryCorpus<NopInput>,
+        > = state_restorer.restore().unwrap().unwrap();
+        assert_eq!(restored.rng_seed(), 1337);
+        assert_eq!(restored.rand_mut().next(), expected[1]);
+    }
+
     #[test]
     fn resume_simple() {
         let mut state = test_std_state::<NopInput>();
@@ -74,7 +74,7 @@ use clap::{Command, CommandFactory, Parser};
 use serde::{Deserialize, Serialize};
 
 use super::core_affinity::Cores;
-use crate::Error;
+use crate::{current_nanos, Error};
 
 /// helper function to go from a parsed cli string to a `Duration`
 fn parse_timeout(src: &str) -> Result<Duration, Error> {
@@ -226,6 +226,45 @@ pub struct FuzzerOptions {
     #[arg(long, help_heading = "ASan Options")]
     pub max_allocation_panics: bool,
 
+    /// The minimum alignment of the allocations of the `ASan` allocator, a power of two.
+    /// Only the address of each allocation is aligned, accesses past its size are still reported.
+    #[cfg(feature = "frida_cli")]
+    #[arg(long, default_value = "8", help_heading = "ASan Options")]
+    pub min_allocation_alignment: usize,
+
+    /// Instruct `ASan` to report reads of heap memory that has not been written to yet.
+    /// This doubles the writes to the shadow map.
+    #[cfg(feature = "frida_cli")]
+    #[arg(long, help_heading = "ASan Options")]
+    pub msan_mode: bool,
+
+    /// Append every memory access checked by `ASan` to a binary log file at this path, for offline analysis (x86_64 only)
+    #[cfg(feature = "frida_cli")]
+    #[arg(long, help_heading = "ASan Options")]
+    pub asan_access_log: Option<PathBuf>,
+
+    /// Track calls and returns of the instrumented code on a shadow stack, reporting returns to unexpected addresses,
+    /// as caused by ROP chains (x86_64 only)
+    #[cfg(feature = "frida_cli")]
+    #[arg(long, help_heading = "ASan Options")]
+    pub shadow_stack: bool,
+
+    /// Report output of `snprintf` and `vsnprintf` truncated to the size of their destination as an error.
+    /// Truncation is well-defined, and often intended, so it is only logged by default.
+    #[cfg(feature = "frida_cli")]
+    #[arg(long, help_heading = "ASan Options")]
+    pub report_snprintf_truncation: bool,
+
+    /// Write a color-coded HTML report of each `ASan` error into this directory
+    #[cfg(feature = "frida_cli")]
+    #[arg(long, help_heading = "ASan Options")]
+    pub asan_html_report_dir: Option<PathBuf>,
+
+    /// Write the `ASan` errors into a Valgrind `memcheck` compatible XML report at this path
+    #[cfg(feature = "frida_cli")]
+    #[arg(long, help_heading = "ASan Options")]
+    pub valgrind_xml_report: Option<PathBuf>,
+
     /// Disable coverage
     #[cfg(feature = "frida_cli")]
     #[arg(long, help_heading = "Frida Options")]
@@ -302,9 +341,35 @@ pub struct FuzzerOptions {
         requires = "replay"
     )]
     pub repeat: Option<usize>,
+
+    /// Seed the PRNG with this value, to replay the random decisions of a previous run
+    #[arg(long, value_name = "SEED", help_heading = "Replay Options")]
+    pub replay_seed: Option<u64>,
+
+    /// Base URL of an `InfluxDB` v2 instance to push the metrics to, e.g. `http://localhost:8086`
+    #[arg(long, value_name = "URL", help_heading = "Monitor Options")]
+    pub influxdb2_url: Option<String>,
+
+    /// API token for `InfluxDB`, better given as `INFLUXDB2_TOKEN` environment variable
+    #[arg(long, value_name = "TOKEN", help_heading = "Monitor Options")]
+    pub influxdb2_token: Option<String>,
+
+    /// `InfluxDB` bucket the metrics are written to
+    #[arg(long, value_name = "BUCKET", help_heading = "Monitor Options")]
+    pub influxdb2_bucket: Option<String>,
+
+    /// `InfluxDB` organization owning the bucket
+    #[arg(long, value_name = "ORG", help_heading = "Monitor Options")]
+    pub influxdb2_org: Option<String>,
 }
 
 impl FuzzerOptions {
+    /// The seed to initialize the PRNG with: the `--replay-seed`, if given, else a fresh one
+    #[must_use]
+    pub fn rng_seed(&self) -> u64 {
+        self.replay_seed.unwrap_or_else(current_nanos)
+    }
+
     /// Given an `App`, add it to `FuzzerOptions` as a subcommand and return the resulting `App`
     ///
     /// # Examples
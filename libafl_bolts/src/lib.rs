@@ -314,6 +314,10 @@ pub enum Error {
     IllegalArgument(String, ErrorBacktrace),
     /// The performed action is not supported on the current platform
     Unsupported(String, ErrorBacktrace),
+    /// The item to add already exists
+    Duplicate(String, ErrorBacktrace),
+    /// The item to add was refused, e.g. by a filter
+    Refused(String, ErrorBacktrace),
     /// Shutting down, not really an error.
     ShuttingDown,
     /// Something else happened
@@ -410,6 +414,22 @@ impl Error {
     {
         Error::Unsupported(arg.into(), ErrorBacktrace::new())
     }
+    /// The item to add already exists
+    #[must_use]
+    pub fn duplicate<S>(arg: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Error::Duplicate(arg.into(), ErrorBacktrace::new())
+    }
+    /// The item to add was refused, e.g. by a filter
+    #[must_use]
+    pub fn refused<S>(arg: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Error::Refused(arg.into(), ErrorBacktrace::new())
+    }
     /// Something else happened
     #[must_use]
     pub fn unknown<S>(arg: S) -> Self
@@ -473,6 +493,14 @@ impl Display for Error {
                 )?;
                 display_error_backtrace(f, b)
             }
+            Self::Duplicate(s, b) => {
+                write!(f, "Duplicate: {0}", &s)?;
+                display_error_backtrace(f, b)
+            }
+            Self::Refused(s, b) => {
+                write!(f, "Refused: {0}", &s)?;
+                display_error_backtrace(f, b)
+            }
             Self::ShuttingDown => write!(f, "Shutting down!"),
             Self::Unknown(s, b) => {
                 write!(f, "Unknown error: {0}", &s)?;
@@ -6,11 +6,18 @@
         target_os = "android"
     )
 ))]
-use std::{collections::BTreeMap, ffi::c_void};
-
+use std::{
+    alloc::Layout,
+    collections::BTreeMap,
+    ffi::c_void,
+    ptr::NonNull,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use allocator_api2::alloc::{AllocError, Allocator as AllocatorApi};
 use backtrace::Backtrace;
 use frida_gum::{PageProtection, RangeDetails};
-use hashbrown::HashMap;
+use hashbrown::{hash_map::DefaultHashBuilder, HashMap};
 use libafl_bolts::cli::FuzzerOptions;
 #[cfg(any(
     target_os = "linux",
@@ -21,12 +28,97 @@ use libafl_bolts::cli::FuzzerOptions;
     )
 ))]
 use mmap_rs::{MemoryAreas, MmapFlags, MmapMut, MmapOptions, ReservedMut};
-use nix::libc::memset;
+use nix::libc::{self, memset};
 use rangemap::RangeSet;
 use serde::{Deserialize, Serialize};
 
 use crate::asan::errors::{AsanError, AsanErrors};
 
+/// The size of the address space reserved for the allocation metadata
+const METADATA_REGION_SIZE: usize = 1 << 30;
+
+/// A memory region of its own, holding the allocation metadata out of reach of heap overflows of the target.
+///
+/// The region is read-only, except between [`Allocator::unlock_metadata`] and [`Allocator::lock_metadata`].
+/// Memory is handed out by bumping a pointer, and never reused: the metadata map only frees its old table
+/// when it grows, so at most half of the used space is wasted.
+#[derive(Debug)]
+struct MetadataRegion {
+    base: usize,
+    used: AtomicUsize,
+    /// The number of nested unlocks, the region is writable while it is not `0`
+    unlocked: AtomicUsize,
+}
+
+impl MetadataRegion {
+    fn new() -> Self {
+        let base = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                METADATA_REGION_SIZE,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_NORESERVE,
+                -1,
+                0,
+            )
+        };
+        assert!(
+            base != libc::MAP_FAILED,
+            "Failed to map the allocation metadata region: {}",
+            std::io::Error::last_os_error()
+        );
+        Self {
+            base: base as usize,
+            used: AtomicUsize::new(0),
+            unlocked: AtomicUsize::new(0),
+        }
+    }
+
+    fn protect(&self, prot: libc::c_int) {
+        let ret = unsafe { libc::mprotect(self.base as *mut c_void, METADATA_REGION_SIZE, prot) };
+        assert!(
+            ret == 0,
+            "Failed to change the protection of the allocation metadata region: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+}
+
+/// Allocates the [`AllocationMetadata`] map inside the [`MetadataRegion`]
+#[derive(Debug, Clone, Copy)]
+struct MetadataArena {
+    region: &'static MetadataRegion,
+}
+
+impl MetadataArena {
+    fn new() -> Self {
+        Self {
+            region: Box::leak(Box::new(MetadataRegion::new())),
+        }
+    }
+}
+
+unsafe impl AllocatorApi for MetadataArena {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let align_mask = layout.align() - 1;
+        let mut start = 0;
+        self.region
+            .used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                start = (used + align_mask) & !align_mask;
+                let end = start.checked_add(layout.size())?;
+                (end <= METADATA_REGION_SIZE).then_some(end)
+            })
+            .map_err(|_| AllocError)?;
+        let ptr = NonNull::new((self.region.base + start) as *mut u8).ok_or(AllocError)?;
+        Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+    }
+
+    unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {
+        // bump allocated, the memory is never reused
+    }
+}
+
 /// An allocator wrapper with binary-only address sanitization
 #[derive(Debug)]
 pub struct Allocator {
@@ -34,6 +126,8 @@ pub struct Allocator {
     max_total_allocation: usize,
     max_allocation_panics: bool,
     allocation_backtraces: bool,
+    /// The minimum alignment of the addresses of the allocations
+    min_alignment: usize,
     /// The page size
     page_size: usize,
     /// The shadow offsets
@@ -42,8 +136,8 @@ pub struct Allocator {
     shadow_bit: usize,
     /// The reserved (pre-allocated) shadow mapping
     pre_allocated_shadow_mappings: HashMap<(usize, usize), ReservedMut>,
-    /// All tracked allocations
-    allocations: HashMap<usize, AllocationMetadata>,
+    /// All tracked allocations, stored in a region that is read-only outside of the allocator hooks
+    allocations: HashMap<usize, AllocationMetadata, DefaultHashBuilder, MetadataArena>,
     /// All mappings
     mappings: HashMap<usize, MmapMut>,
     /// The shadow memory pages
@@ -58,6 +152,12 @@ pub struct Allocator {
     base_mapping_addr: usize,
     /// The current mapping address
     current_mapping_addr: usize,
+    /// Whether to track uninitialized heap memory
+    msan_mode: bool,
+    /// The heap ranges that have been allocated, but not written to yet
+    uninitialized: RangeSet<usize>,
+    /// The ranges of the live allocations, never adjacent thanks to the red zones, for [`Allocator::remaining_size`]
+    live_allocations: RangeSet<usize>,
 }
 
 macro_rules! map_to_shadow {
@@ -83,6 +183,8 @@ pub struct AllocationMetadata {
     pub freed: bool,
     /// If the allocation was done with a size of 0
     pub is_malloc_zero: bool,
+    /// The alignment of the address of the allocation
+    pub alignment: usize,
 }
 
 impl Allocator {
@@ -113,11 +215,18 @@ impl Allocator {
     ))]
     #[must_use]
     pub fn new(options: &FuzzerOptions) -> Self {
+        assert!(
+            options.min_allocation_alignment.is_power_of_two(),
+            "The minimum allocation alignment must be a power of two"
+        );
         Self {
             max_allocation: options.max_allocation,
             max_allocation_panics: options.max_allocation_panics,
             max_total_allocation: options.max_total_allocation,
             allocation_backtraces: options.allocation_backtraces,
+            min_alignment: options.min_allocation_alignment,
+            // uninitialized writes can only be told apart from invalid ones on x86_64 for now
+            msan_mode: options.msan_mode && cfg!(target_arch = "x86_64"),
             ..Self::default()
         }
     }
@@ -152,9 +261,16 @@ impl Allocator {
     }
 
     /// Allocate a new allocation of the given size.
+    /// The returned address is aligned to the larger of `alignment` and the minimum alignment.
+    /// Allocations are page-aligned, for larger alignments the mapping is larger by the difference,
+    /// and the allocation is moved up to the next multiple of the alignment within it.
+    /// Only the `size` bytes of the allocation are unpoisoned, accesses past them are reported, whatever the alignment.
     #[must_use]
     #[allow(clippy::missing_safety_doc)]
-    pub unsafe fn alloc(&mut self, size: usize, _alignment: usize) -> *mut c_void {
+    pub unsafe fn alloc(&mut self, size: usize, alignment: usize) -> *mut c_void {
+        let alignment = std::cmp::max(self.min_alignment, alignment.next_power_of_two());
+        // the room needed to move a page-aligned allocation up to the alignment
+        let alignment_slack = alignment.saturating_sub(self.page_size);
         let mut is_malloc_zero = false;
         let size = if size == 0 {
             // log::warn!("zero-sized allocation!");
@@ -171,17 +287,18 @@ impl Allocator {
 
             return std::ptr::null_mut();
         }
-        let rounded_up_size = self.round_up_to_page(size) + 2 * self.page_size;
+        let rounded_up_size = self.round_up_to_page(size) + 2 * self.page_size + alignment_slack;
 
         if self.total_allocation_size + rounded_up_size > self.max_total_allocation {
             return std::ptr::null_mut();
         }
         self.total_allocation_size += rounded_up_size;
 
-        let metadata = if let Some(mut metadata) = self.find_smallest_fit(rounded_up_size) {
+        let mut metadata = if let Some(mut metadata) = self.find_smallest_fit(rounded_up_size) {
             //log::trace!("reusing allocation at {:x}, (actual mapping starts at {:x}) size {:x}", metadata.address, metadata.address - self.page_size, size);
             metadata.is_malloc_zero = is_malloc_zero;
             metadata.size = size;
+            metadata.alignment = alignment;
             if self.allocation_backtraces {
                 metadata.allocation_site_backtrace = Some(Backtrace::new_unresolved());
             }
@@ -216,6 +333,7 @@ impl Allocator {
                 address,
                 size,
                 actual_size: rounded_up_size,
+                alignment,
                 ..AllocationMetadata::default()
             };
             if self.allocation_backtraces {
@@ -225,14 +343,30 @@ impl Allocator {
             metadata
         };
 
+        if alignment > self.page_size {
+            // the skipped pages stay poisoned, in front of the left red zone, and no longer belong to the allocation
+            let aligned = (metadata.address + self.page_size + alignment - 1) & !(alignment - 1);
+            let skipped = aligned - self.page_size - metadata.address;
+            metadata.address += skipped;
+            metadata.actual_size -= skipped;
+        }
+
         self.largest_allocation = std::cmp::max(self.largest_allocation, metadata.actual_size);
-        // unpoison the shadow memory for the allocation itself
-        Self::unpoison(
-            map_to_shadow!(self, metadata.address + self.page_size),
-            size,
-        );
         let address = (metadata.address + self.page_size) as *mut c_void;
+        if self.msan_mode {
+            // keep the allocation poisoned until it gets written to, so that reads trap
+            self.uninitialized
+                .insert(address as usize..address as usize + metadata.size);
+        } else {
+            // unpoison the shadow memory for the allocation itself
+            Self::unpoison(
+                map_to_shadow!(self, metadata.address + self.page_size),
+                metadata.size,
+            );
+        }
 
+        self.live_allocations
+            .insert(address as usize..address as usize + metadata.size);
         self.allocations.insert(address as usize, metadata);
         // log::trace!("serving address: {:?}, size: {:x}", address, size);
         address
@@ -265,37 +399,84 @@ impl Allocator {
         }
 
         // poison the shadow memory for the allocation
-        Self::poison(shadow_mapping_start, metadata.size);
+        let size = metadata.size;
+        Self::poison(shadow_mapping_start, size);
+        self.uninitialized.remove(ptr as usize..ptr as usize + size);
+        self.live_allocations
+            .remove(ptr as usize..ptr as usize + size);
     }
 
-    /// Finds the metadata for the allocation at the given address.
+    /// Finds the metadata for the allocation a faulting access at `ptr` belongs to.
+    ///
+    /// Each allocation is surrounded by a poisoned page on both sides, its left and right red zone.
+    /// An access to either red zone belongs to the allocation of the mapping containing it, so underflows
+    /// are attributed as reliably as overflows. Other addresses, e.g. far out of bounds, belong to the allocation
+    /// closest to them, in either direction, preferring the allocation `hint_base`, the base register of the access, points into.
+    ///
+    /// Unlike ASan, the left red zone is not marked with a shadow value of its own: the shadow is a bitmap,
+    /// so an access to the left red zone is only told apart by its address being before the allocation.
     pub fn find_metadata(
         &mut self,
         ptr: usize,
         hint_base: usize,
     ) -> Option<&mut AllocationMetadata> {
-        let mut metadatas: Vec<&mut AllocationMetadata> = self.allocations.values_mut().collect();
-        metadatas.sort_by(|a, b| a.address.cmp(&b.address));
-        let mut offset_to_closest = i64::max_value();
-        let mut closest = None;
-        let ptr: i64 = ptr.try_into().unwrap();
-        for metadata in metadatas {
-            let address: i64 = metadata.address.try_into().unwrap();
-            let new_offset = if hint_base == metadata.address {
-                (ptr - address).abs()
+        let page_size = self.page_size;
+        let mapping_contains = |metadata: &AllocationMetadata, addr: usize| {
+            metadata.address <= addr && addr < metadata.address + metadata.actual_size
+        };
+        // the distance of `addr` to the accessible bytes of the allocation, `0` if it is within them
+        let distance = |metadata: &AllocationMetadata, addr: usize| {
+            let start = metadata.address + page_size;
+            let end = start + metadata.size;
+            if addr < start {
+                start - addr
             } else {
-                std::cmp::min(offset_to_closest, (ptr - address).abs())
-            };
-            if new_offset < offset_to_closest {
-                offset_to_closest = new_offset;
-                closest = Some(metadata);
+                addr.saturating_sub(end - 1)
             }
+        };
+
+        let key = if let Some((key, _)) = self
+            .allocations
+            .iter()
+            .find(|(_, metadata)| mapping_contains(metadata, hint_base))
+        {
+            *key
+        } else if let Some((key, _)) = self
+            .allocations
+            .iter()
+            .find(|(_, metadata)| mapping_contains(metadata, ptr))
+        {
+            *key
+        } else {
+            *self
+                .allocations
+                .iter()
+                .min_by_key(|(_, metadata)| distance(metadata, ptr))?
+                .0
+        };
+        self.allocations.get_mut(&key)
+    }
+
+    /// Makes the allocation metadata writable, until the matching [`Allocator::lock_metadata`].
+    /// Calls may nest, e.g. if a hook runs within another one.
+    pub fn unlock_metadata(&self) {
+        let region = self.allocations.allocator().region;
+        if region.unlocked.fetch_add(1, Ordering::Relaxed) == 0 {
+            region.protect(libc::PROT_READ | libc::PROT_WRITE);
+        }
+    }
+
+    /// Makes the allocation metadata read-only again, after the outermost [`Allocator::unlock_metadata`]
+    pub fn lock_metadata(&self) {
+        let region = self.allocations.allocator().region;
+        if region.unlocked.fetch_sub(1, Ordering::Relaxed) == 1 {
+            region.protect(libc::PROT_READ);
         }
-        closest
     }
 
     /// Resets the allocator contents
     pub fn reset(&mut self) {
+        self.unlock_metadata();
         let mut tmp_allocations = Vec::new();
         for (address, mut allocation) in self.allocations.drain() {
             if !allocation.freed {
@@ -324,6 +505,7 @@ impl Allocator {
         }
 
         self.total_allocation_size = 0;
+        self.lock_metadata();
     }
 
     /// Gets the usable size of the allocation, by allocated pointer
@@ -469,6 +651,104 @@ impl Allocator {
         map_to_shadow!(self, start)
     }
 
+    /// Checks if the region has shadow memory of its own: it is mapped by [`Allocator::map_shadow_for_region`],
+    /// and below the addresses whose shadow wraps around onto that of lower addresses
+    #[must_use]
+    pub fn has_shadow_for_region(&self, start: usize, end: usize) -> bool {
+        if start >= end {
+            return true;
+        }
+        if (end - 1) >> 3 >= 1 << (self.shadow_bit + 1) {
+            return false;
+        }
+        let shadow_start = self.round_down_to_page(map_to_shadow!(self, start));
+        let shadow_end = map_to_shadow!(self, end - 1) + 1;
+        self.shadow_pages
+            .gaps(&(shadow_start..shadow_end))
+            .next()
+            .is_none()
+    }
+
+    /// Whether uninitialized heap memory is tracked (the `msan_mode` option)
+    #[inline]
+    #[must_use]
+    pub fn msan_mode(&self) -> bool {
+        self.msan_mode
+    }
+
+    /// Checks if any byte in the given range has been allocated, but not written to yet
+    #[inline]
+    #[must_use]
+    pub fn is_uninitialized(&self, start: usize, size: usize) -> bool {
+        size > 0 && self.uninitialized.overlaps(&(start..start + size))
+    }
+
+    /// Marks the given range as initialized, unpoisoning all of its uninitialized bytes.
+    /// Bytes that were not uninitialized heap memory before keep their shadow as-is.
+    pub fn mark_initialized(&mut self, start: usize, size: usize) {
+        if size == 0 || self.uninitialized.is_empty() {
+            return;
+        }
+        let range = start..start + size;
+        let overlapping: Vec<_> = self.uninitialized.overlapping(&range).cloned().collect();
+        for uninit in overlapping {
+            self.set_shadow_bits(uninit.start.max(range.start), uninit.end.min(range.end));
+        }
+        self.uninitialized.remove(range);
+    }
+
+    /// Sets the shadow bits of all bytes in `start..end`, keeping the bits of the bytes around it.
+    /// The shadow is one bit per byte, most significant bit first: whole shadow bytes are set at once,
+    /// only the partial shadow bytes at either end are masked.
+    fn set_shadow_bits(&self, start: usize, end: usize) {
+        // the bits of the bytes `from..to` within the shadow byte of `from`
+        let set_bits = |from: usize, to: usize| {
+            let mask = (0xffu16 >> (from & 7)) & (0xff00u16 >> (to - (from & !7)));
+            let shadow = map_to_shadow!(self, from) as *mut u8;
+            unsafe {
+                *shadow |= mask as u8;
+            }
+        };
+
+        if start >= end {
+            return;
+        }
+        let aligned_start = (start + 7) & !7;
+        let aligned_end = end & !7;
+        if aligned_start > aligned_end {
+            // start and end share a single shadow byte
+            set_bits(start, end);
+            return;
+        }
+        if start < aligned_start {
+            set_bits(start, aligned_start);
+        }
+        if aligned_start < aligned_end {
+            unsafe {
+                memset(
+                    map_to_shadow!(self, aligned_start) as *mut c_void,
+                    0xff,
+                    (aligned_end - aligned_start) / 8,
+                );
+            }
+        }
+        if aligned_end < end {
+            set_bits(aligned_end, end);
+        }
+    }
+
+    /// The number of bytes from `ptr` to the end of the live allocation it points into,
+    /// or `None` if it does not point into one of our allocations
+    #[must_use]
+    pub fn remaining_size(&self, ptr: usize) -> Option<usize> {
+        if !self.is_managed(ptr as *mut c_void) {
+            return None;
+        }
+        self.live_allocations
+            .get(&ptr)
+            .map(|allocation| allocation.end - ptr)
+    }
+
     /// Checks if the currennt address is one of ours
     #[inline]
     pub fn is_managed(&self, ptr: *mut c_void) -> bool {
@@ -476,14 +756,18 @@ impl Allocator {
         self.base_mapping_addr <= ptr as usize && (ptr as usize) < self.current_mapping_addr
     }
 
-    /// Checks if any of the allocations has not been freed
-    pub fn check_for_leaks(&self) {
+    /// Checks if any of the allocations has not been freed, reporting each of them as a leak.
+    /// Returns the total leaked bytes.
+    pub fn check_for_leaks(&self) -> usize {
+        let mut leaked_bytes = 0;
         for metadata in self.allocations.values() {
             if !metadata.freed {
+                leaked_bytes += metadata.size;
                 AsanErrors::get_mut()
                     .report_error(AsanError::Leak((metadata.address, metadata.clone())));
             }
         }
+        leaked_bytes
     }
 
     /// Unpoison all the memory that is currently mapped with read/write permissions.
@@ -642,18 +926,78 @@ impl Default for Allocator {
             max_allocation_panics: false,
             max_total_allocation: 1 << 32,
             allocation_backtraces: false,
+            min_alignment: 8,
             page_size,
             pre_allocated_shadow_mappings: HashMap::new(),
             mappings: HashMap::new(),
             shadow_offset: 0,
             shadow_bit: 0,
-            allocations: HashMap::new(),
+            allocations: HashMap::with_hasher_in(
+                DefaultHashBuilder::default(),
+                MetadataArena::new(),
+            ),
             shadow_pages: RangeSet::new(),
             allocation_queue: BTreeMap::new(),
             largest_allocation: 0,
             total_allocation_size: 0,
             base_mapping_addr: 0,
             current_mapping_addr: 0,
+            msan_mode: false,
+            uninitialized: RangeSet::new(),
+            live_allocations: RangeSet::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::c_void;
+
+    use super::Allocator;
+
+    #[test]
+    fn test_alloc_alignment_above_page_size() {
+        let mut allocator = Allocator::default();
+        allocator.init();
+        allocator.unlock_metadata();
+        let page_size = allocator.page_size;
+        let alignment = 4 * page_size;
+        let is_unpoisoned = |allocator: &Allocator, address: usize| {
+            let shadow = unsafe { *(allocator.map_to_shadow(address) as *const u8) };
+            shadow & (0x80 >> (address & 7)) != 0
+        };
+
+        let mut allocations = vec![];
+        for size in [1, 0x1234, alignment + 1] {
+            let ptr = unsafe { allocator.alloc(size, alignment) } as usize;
+            allocations.push(ptr);
+            assert_eq!(ptr % alignment, 0);
+            assert_eq!(allocator.get_usable_size(ptr as *mut c_void), size);
+            assert_eq!(allocator.remaining_size(ptr), Some(size));
+
+            // exactly the allocation is accessible, with the red zones around it
+            assert!(is_unpoisoned(&allocator, ptr));
+            assert!(is_unpoisoned(&allocator, ptr + size - 1));
+            assert!(!is_unpoisoned(&allocator, ptr + size));
+            assert!(!is_unpoisoned(&allocator, ptr - 1));
+
+            let metadata = allocator.find_metadata(ptr, ptr).unwrap();
+            assert_eq!(metadata.address + page_size, ptr);
+            assert!(metadata.actual_size >= size + 2 * page_size);
+            assert_eq!(metadata.alignment, alignment);
+        }
+
+        for ptr in allocations {
+            unsafe { allocator.release(ptr as *mut c_void) };
         }
+        allocator.lock_metadata();
+
+        // the released allocations are queued for reuse, and moved up to the alignment again
+        allocator.reset();
+        allocator.unlock_metadata();
+        let ptr = unsafe { allocator.alloc(1, alignment) } as usize;
+        assert_eq!(ptr % alignment, 0);
+        assert_eq!(allocator.remaining_size(ptr), Some(1));
+        allocator.lock_metadata();
     }
 }
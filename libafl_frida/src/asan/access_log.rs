@@ -0,0 +1,238 @@
+//! A structured log of the memory accesses checked by the [`AsanRuntime`](crate::asan::asan_rt::AsanRuntime),
+//! for offline analysis tools.
+//!
+//! Each access is stored as an [`AccessRecord`] into a fixed size ring buffer while the target runs,
+//! and the buffer is appended to the log file after each execution.
+//! The `libafl_frida_tools` crate decodes and renders the resulting files.
+use core::{
+    cell::UnsafeCell,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+#[cfg(all(target_arch = "x86_64", unix))]
+use frida_gum::{instruction_writer::X86Register, stalker::Instruction, CpuContext};
+use libafl::Error;
+#[cfg(all(target_arch = "x86_64", unix))]
+use yaxpeax_x86::amd64::InstDecoder;
+
+#[cfg(all(target_arch = "x86_64", unix))]
+use crate::utils::frida_to_cs;
+
+/// The default number of records the [`AccessLog`] holds between two flushes
+pub const DEFAULT_ACCESS_LOG_CAPACITY: usize = 1 << 20;
+
+/// The size of an encoded [`AccessRecord`]
+pub const ACCESS_RECORD_SIZE: usize = 18;
+
+/// A memory access checked by `ASan`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AccessRecord {
+    /// The address of the accessing instruction
+    pub pc: u64,
+    /// The accessed address
+    pub addr: u64,
+    /// The number of bytes accessed
+    pub size: u8,
+    /// If the access was a write
+    pub is_write: bool,
+}
+
+impl AccessRecord {
+    /// Encodes the record as `pc`, `addr` (little endian `u64`s), `size` and `is_write` (one byte each)
+    #[must_use]
+    pub fn to_bytes(&self) -> [u8; ACCESS_RECORD_SIZE] {
+        let mut bytes = [0; ACCESS_RECORD_SIZE];
+        bytes[0..8].copy_from_slice(&self.pc.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.addr.to_le_bytes());
+        bytes[16] = self.size;
+        bytes[17] = u8::from(self.is_write);
+        bytes
+    }
+
+    /// Decodes a record encoded by [`AccessRecord::to_bytes`]
+    #[must_use]
+    pub fn from_bytes(bytes: &[u8; ACCESS_RECORD_SIZE]) -> Self {
+        Self {
+            pc: u64::from_le_bytes(bytes[0..8].try_into().unwrap()),
+            addr: u64::from_le_bytes(bytes[8..16].try_into().unwrap()),
+            size: bytes[16],
+            is_write: bytes[17] != 0,
+        }
+    }
+}
+
+/// A ring buffer of [`AccessRecord`]s, flushed to a file.
+///
+/// Pushing a record does not take a lock. If more records than the capacity get pushed between two flushes,
+/// the oldest ones are overwritten, and the number of lost records is logged on the next flush.
+#[derive(Debug)]
+pub struct AccessLog {
+    records: Box<[UnsafeCell<AccessRecord>]>,
+    head: AtomicUsize,
+    file: BufWriter<File>,
+}
+
+// The records are only written through `push`, each slot by the thread that reserved it.
+unsafe impl Sync for AccessLog {}
+
+impl AccessLog {
+    /// Creates a new [`AccessLog`], appending to the file at `path`
+    pub fn new<P: AsRef<Path>>(path: P, capacity: usize) -> Result<Self, Error> {
+        if capacity == 0 {
+            return Err(Error::illegal_argument(
+                "The capacity of an AccessLog has to be positive",
+            ));
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            records: (0..capacity)
+                .map(|_| UnsafeCell::new(AccessRecord::default()))
+                .collect(),
+            head: AtomicUsize::new(0),
+            file: BufWriter::new(file),
+        })
+    }
+
+    /// Records an access
+    #[inline]
+    pub fn push(&self, record: AccessRecord) {
+        let idx = self.head.fetch_add(1, Ordering::Relaxed) % self.records.len();
+        unsafe {
+            *self.records[idx].get() = record;
+        }
+    }
+
+    /// The number of records pushed since the last flush, including overwritten ones
+    #[must_use]
+    pub fn pushed(&self) -> usize {
+        self.head.load(Ordering::Relaxed)
+    }
+
+    /// Appends the buffered records, oldest first, to the file and empties the buffer
+    pub fn flush(&mut self) -> Result<(), Error> {
+        let pushed = self.head.swap(0, Ordering::Acquire);
+        let capacity = self.records.len();
+        let (start, count) = if pushed > capacity {
+            log::warn!(
+                "ASan access log overflowed, {} records were lost",
+                pushed - capacity
+            );
+            (pushed % capacity, capacity)
+        } else {
+            (0, pushed)
+        };
+
+        for i in 0..count {
+            let record = unsafe { *self.records[(start + i) % capacity].get() };
+            self.file.write_all(&record.to_bytes())?;
+        }
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+/// The access log of the `ASan` runtime, if enabled
+pub static mut ASAN_ACCESS_LOG: Option<AccessLog> = None;
+
+/// Puts a callout before `instruction`, recording its memory access into the [`ASAN_ACCESS_LOG`].
+/// `details` are the width, base register, index register, scale and displacement of the access,
+/// as found by [`AsanRuntime::asan_is_interesting_instruction`](crate::asan::asan_rt::AsanRuntime::asan_is_interesting_instruction).
+#[cfg(all(target_arch = "x86_64", unix))]
+pub(crate) fn put_access_log_callout(
+    instruction: &Instruction,
+    decoder: InstDecoder,
+    details: (u8, X86Register, X86Register, u8, i32),
+) {
+    let instr = instruction.instr();
+    let pc = instr.address();
+    let next_pc = pc + instr.bytes().len() as u64;
+    // The order is like in Intel, so the first operand is the destination
+    let cs_instr = frida_to_cs(decoder, instr);
+    let is_write = cs_instr.operand_count() > 0 && cs_instr.operand(0).is_memory();
+
+    let (size, basereg, indexreg, scale, disp) = details;
+    instruction.put_callout(move |context| {
+        let reg = |reg| {
+            if reg == X86Register::Rip {
+                // the stalked code is elsewhere, but the access is relative to the original instruction
+                next_pc
+            } else {
+                register_value(&context, reg)
+            }
+        };
+        let addr = reg(basereg)
+            .wrapping_add(reg(indexreg).wrapping_mul(u64::from(scale.max(1))))
+            .wrapping_add_signed(i64::from(disp));
+        if let Some(access_log) = unsafe { ASAN_ACCESS_LOG.as_ref() } {
+            access_log.push(AccessRecord {
+                pc,
+                addr,
+                size,
+                is_write,
+            });
+        }
+    });
+}
+
+/// The value of a register in the given context, as used in memory operands
+#[cfg(all(target_arch = "x86_64", unix))]
+#[must_use]
+fn register_value(context: &CpuContext, reg: X86Register) -> u64 {
+    match reg {
+        X86Register::Rax => context.rax(),
+        X86Register::Rbx => context.rbx(),
+        X86Register::Rcx => context.rcx(),
+        X86Register::Rdx => context.rdx(),
+        X86Register::Rbp => context.rbp(),
+        X86Register::Rsp => context.rsp(),
+        X86Register::Rsi => context.rsi(),
+        X86Register::Rdi => context.rdi(),
+        X86Register::R8 => context.r8(),
+        X86Register::R9 => context.r9(),
+        X86Register::R10 => context.r10(),
+        X86Register::R11 => context.r11(),
+        X86Register::R12 => context.r12(),
+        X86Register::R13 => context.r13(),
+        X86Register::R14 => context.r14(),
+        X86Register::R15 => context.r15(),
+        X86Register::Rip => context.rip(),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AccessLog, AccessRecord, ACCESS_RECORD_SIZE};
+
+    #[test]
+    fn test_access_log_ring() {
+        let path = std::env::temp_dir().join(format!("asan_access_log_{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut log = AccessLog::new(&path, 2).unwrap();
+        for addr in 0..3 {
+            log.push(AccessRecord {
+                pc: 0x1000,
+                addr,
+                size: 4,
+                is_write: addr % 2 == 0,
+            });
+        }
+        log.flush().unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        // the oldest record got overwritten
+        let addrs: Vec<u64> = bytes
+            .chunks_exact(ACCESS_RECORD_SIZE)
+            .map(|chunk| AccessRecord::from_bytes(chunk.try_into().unwrap()).addr)
+            .collect();
+        assert_eq!(addrs, [1, 2]);
+        assert_eq!(log.pushed(), 0);
+    }
+}
@@ -7,15 +7,11 @@ this helps finding mem errors early.
 */
 
 use core::{
+    cell::RefCell,
     fmt::{self, Debug, Formatter},
     ptr::addr_of_mut,
 };
-use std::{
-    ffi::c_void,
-    num::NonZeroUsize,
-    ptr::{addr_of, write_volatile},
-    rc::Rc,
-};
+use std::{ffi::c_void, num::NonZeroUsize, path::PathBuf, ptr::write_volatile, rc::Rc};
 
 use backtrace::Backtrace;
 use dynasmrt::{dynasm, DynasmApi, DynasmLabelApi};
@@ -24,21 +20,23 @@ use frida_gum::instruction_writer::X86Register;
 #[cfg(target_arch = "aarch64")]
 use frida_gum::instruction_writer::{Aarch64Register, IndexMode};
 use frida_gum::{
-    instruction_writer::InstructionWriter, interceptor::Interceptor, stalker::StalkerOutput, Gum,
-    Module, ModuleDetails, ModuleMap, NativePointer, PageProtection, RangeDetails,
+    instruction_writer::InstructionWriter,
+    interceptor::{Interceptor, InvocationContext, InvocationListener, ProbeListener},
+    stalker::StalkerOutput,
+    Gum, Module, ModuleDetails, ModuleMap, NativePointer, PageProtection, RangeDetails,
 };
 use frida_gum_sys::Insn;
 use hashbrown::HashMap;
 use libafl_bolts::{cli::FuzzerOptions, AsSlice};
 // #[cfg(target_vendor = "apple")]
 // use libc::RLIMIT_STACK;
-use libc::{c_char, wchar_t};
+use libc::{c_char, c_int, c_long, c_longlong, c_ulong, c_ulonglong, wchar_t};
 // #[cfg(target_vendor = "apple")]
 // use libc::{getrlimit, rlimit};
 // #[cfg(all(unix, not(target_vendor = "apple")))]
 // use libc::{getrlimit64, rlimit64};
 use nix::sys::mman::{mmap, MapFlags, ProtFlags};
-use rangemap::RangeMap;
+use rangemap::{RangeMap, RangeSet};
 #[cfg(target_arch = "aarch64")]
 use yaxpeax_arch::Arch;
 #[cfg(target_arch = "aarch64")]
@@ -50,14 +48,23 @@ use yaxpeax_x86::amd64::{InstDecoder, Instruction, Opcode};
 use crate::utils::frida_to_cs;
 #[cfg(target_arch = "aarch64")]
 use crate::utils::{instruction_width, writer_register};
-#[cfg(target_arch = "x86_64")]
-use crate::utils::{operand_details, AccessType};
 use crate::{
     alloc::Allocator,
-    asan::errors::{AsanError, AsanErrors, AsanReadWriteError, ASAN_ERRORS},
+    asan::{
+        access_log::{AccessLog, ASAN_ACCESS_LOG, DEFAULT_ACCESS_LOG_CAPACITY},
+        errors::{AsanError, AsanErrors, AsanReadWriteError},
+        leaks,
+        report::AsanHtmlReporter,
+        valgrind::ValgrindXmlReporter,
+    },
     helper::{FridaRuntime, SkipRange},
     utils::disas_count,
 };
+#[cfg(target_arch = "x86_64")]
+use crate::{
+    asan::shadow_stack,
+    utils::{operand_details, AccessType},
+};
 
 extern "C" {
     fn __register_frame(begin: *mut c_void);
@@ -142,6 +149,14 @@ pub struct AsanRuntime {
     skip_ranges: Vec<SkipRange>,
     continue_on_error: bool,
     shadow_check_func: Option<extern "C" fn(*const c_void, usize) -> bool>,
+    access_log_path: Option<PathBuf>,
+    html_report_dir: Option<PathBuf>,
+    valgrind_xml_report: Option<PathBuf>,
+    shadow_stack: bool,
+    /// Whether truncated `snprintf` output is reported, or only logged
+    report_snprintf_truncation: bool,
+    /// Always readable memory without shadow, whose accesses are not checked
+    safe_read_ranges: RangeSet<usize>,
 
     #[cfg(target_arch = "aarch64")]
     eh_frame: [u32; ASAN_EH_FRAME_DWORD_COUNT],
@@ -154,6 +169,7 @@ impl Debug for AsanRuntime {
             .field("continue_on_error", &self.continue_on_error)
             .field("module_map", &"<ModuleMap>")
             .field("skip_ranges", &self.skip_ranges)
+            .field("safe_read_ranges", &self.safe_read_ranges)
             .field("suppressed_addresses", &self.suppressed_addresses)
             .finish_non_exhaustive()
     }
@@ -166,19 +182,48 @@ impl FridaRuntime for AsanRuntime {
     fn init(
         &mut self,
         gum: &Gum,
-        _ranges: &RangeMap<usize, (u16, String)>,
+        ranges: &RangeMap<usize, (u16, String)>,
         module_map: &Rc<ModuleMap>,
     ) {
         self.allocator.init();
+        #[cfg(not(target_arch = "x86_64"))]
+        let _ = ranges;
+
+        let mut errors = AsanErrors::new(self.continue_on_error);
+        if let Some(dir) = &self.html_report_dir {
+            let html_reporter =
+                AsanHtmlReporter::new(dir).expect("Failed to create the ASan HTML report dir");
+            errors.set_html_reporter(Some(html_reporter));
+        }
+        if let Some(path) = &self.valgrind_xml_report {
+            let valgrind_reporter =
+                ValgrindXmlReporter::new(path).expect("Failed to create the Valgrind XML report");
+            errors.set_valgrind_reporter(Some(valgrind_reporter));
+        }
+        AsanErrors::init_thread_local(errors);
 
-        unsafe {
-            ASAN_ERRORS = Some(AsanErrors::new(self.continue_on_error));
+        if let Some(path) = &self.access_log_path {
+            let access_log = AccessLog::new(path, DEFAULT_ACCESS_LOG_CAPACITY)
+                .expect("Failed to open the ASan access log");
+            unsafe {
+                ASAN_ACCESS_LOG = Some(access_log);
+            }
+        }
+
+        #[cfg(target_arch = "x86_64")]
+        if self.shadow_stack {
+            shadow_stack::set_instrumented_ranges(
+                ranges
+                    .iter()
+                    .map(|(range, _)| range.start as u64..range.end as u64),
+            );
         }
 
         self.generate_instrumentation_blobs();
 
         self.generate_shadow_check_function();
         self.unpoison_all_existing_memory();
+        self.add_unshadowed_safe_read_ranges();
 
         self.module_map = Some(module_map.clone());
         self.suppressed_addresses
@@ -270,6 +315,10 @@ impl FridaRuntime for AsanRuntime {
         let slice = target_bytes.as_slice();
 
         self.unpoison(slice.as_ptr() as usize, slice.len());
+        #[cfg(target_arch = "x86_64")]
+        if self.shadow_stack {
+            shadow_stack::clear_shadow_stack();
+        }
         Ok(())
     }
 
@@ -286,6 +335,10 @@ impl FridaRuntime for AsanRuntime {
         self.poison(slice.as_ptr() as usize, slice.len());
         self.reset_allocations();
 
+        if let Some(access_log) = unsafe { (*addr_of_mut!(ASAN_ACCESS_LOG)).as_mut() } {
+            access_log.flush()?;
+        }
+
         Ok(())
     }
 }
@@ -308,6 +361,11 @@ impl AsanRuntime {
             allocator: Allocator::new(options),
             skip_ranges,
             continue_on_error,
+            access_log_path: options.asan_access_log.clone(),
+            html_report_dir: options.asan_html_report_dir.clone(),
+            valgrind_xml_report: options.valgrind_xml_report.clone(),
+            shadow_stack: options.shadow_stack && cfg!(target_arch = "x86_64"),
+            report_snprintf_truncation: options.report_snprintf_truncation,
             ..Self::default()
         }
     }
@@ -335,15 +393,63 @@ impl AsanRuntime {
         &self.shadow_check_func
     }
 
+    /// If memory accesses are logged to a file, see [`crate::asan::access_log`]
+    #[must_use]
+    pub fn access_log_enabled(&self) -> bool {
+        self.access_log_path.is_some()
+    }
+
+    /// If calls and returns are tracked on a shadow stack, see [`crate::asan::shadow_stack`]
+    #[must_use]
+    pub fn shadow_stack_enabled(&self) -> bool {
+        self.shadow_stack
+    }
+
+    /// Adds a range of memory that is always safe to read, e.g. the VDSO, whose accesses bypass the shadow check.
+    ///
+    /// Only code instrumented after the call skips the check. The ranges are compared inline before each
+    /// shadow lookup, so only a few should be added. Only supported on `x86_64`, other architectures check all accesses.
+    pub fn add_safe_read_range(&mut self, start: usize, end: usize) {
+        if start < end {
+            self.safe_read_ranges.insert(start..end);
+        }
+    }
+
+    /// The ranges of memory that bypass the shadow check, see [`AsanRuntime::add_safe_read_range`]
+    #[must_use]
+    pub fn safe_read_ranges(&self) -> &RangeSet<usize> {
+        &self.safe_read_ranges
+    }
+
+    /// Adds the readable ranges without a shadow mapping of their own as safe read ranges.
+    /// Their shadow either does not exist or is that of other memory, e.g. for the VDSO or `vsyscall` pages
+    /// mapped above the addresses the shadow covers, so checking them only produces false reports.
+    fn add_unshadowed_safe_read_ranges(&mut self) {
+        let mut unshadowed = Vec::new();
+        RangeDetails::enumerate_with_prot(PageProtection::Read, &mut |range: &RangeDetails| {
+            let start = range.memory_range().base_address().0 as usize;
+            let end = start + range.memory_range().size();
+            if !self.allocator.has_shadow_for_region(start, end) {
+                unshadowed.push((start, end));
+            }
+            true
+        });
+        for (start, end) in unshadowed {
+            log::info!("treating the unshadowed range {start:#x}-{end:#x} as safe to read");
+            self.add_safe_read_range(start, end);
+        }
+    }
+
     /// Check if the test leaked any memory and report it if so.
+    /// The total leaked bytes are recorded for the [`crate::asan::leaks::MemoryLeakObserver`].
     pub fn check_for_leaks(&mut self) {
-        self.allocator.check_for_leaks();
+        leaks::set_leaked_bytes(self.allocator.check_for_leaks());
     }
 
-    /// Returns the `AsanErrors` from the recent run
+    /// Returns the `AsanErrors` of the current thread from the recent run
     #[allow(clippy::unused_self)]
     pub fn errors(&mut self) -> &Option<AsanErrors> {
-        unsafe { &*addr_of!(ASAN_ERRORS) }
+        unsafe { &*AsanErrors::thread_local_ptr() }
     }
 
     /// Make sure the specified memory is unpoisoned
@@ -505,7 +611,7 @@ impl AsanRuntime {
     /// Determine the tls start, end for the currently running thread
     #[must_use]
     #[cfg(not(target_os = "ios"))]
-    fn current_tls() -> (usize, usize) {
+    pub fn current_tls() -> (usize, usize) {
         let tls_address = unsafe { tls_ptr() } as usize;
 
         #[cfg(target_os = "android")]
@@ -537,6 +643,22 @@ impl AsanRuntime {
         Interceptor::current_invocation().cpu_context().rip() as usize
     }
 
+    /// Gets the stack pointer of the caller of the current hook
+    #[cfg(target_arch = "aarch64")]
+    #[must_use]
+    #[inline]
+    pub fn current_stack_pointer() -> usize {
+        Interceptor::current_invocation().cpu_context().sp() as usize
+    }
+
+    /// Gets the stack pointer of the caller of the current hook
+    #[cfg(target_arch = "x86_64")]
+    #[must_use]
+    #[inline]
+    pub fn current_stack_pointer() -> usize {
+        Interceptor::current_invocation().cpu_context().rsp() as usize
+    }
+
     /// Hook all functions required for ASAN to function, replacing them with our own
     /// implementations.
     #[allow(clippy::items_after_statements)]
@@ -557,7 +679,10 @@ impl AsanRuntime {
                         let this = &mut *(invocation.replacement_data().unwrap().0 as *mut AsanRuntime);
                         let real_address = this.real_address_for_stalked(invocation.return_addr());
                         if !this.suppressed_addresses.contains(&real_address) && this.module_map.as_ref().unwrap().find(real_address as u64).is_some() {
-                            this.[<hook_ $name>]($($param),*)
+                            this.allocator().unlock_metadata();
+                            let ret = this.[<hook_ $name>]($($param),*);
+                            this.allocator().lock_metadata();
+                            ret
                         } else {
                             $name($($param),*)
                         }
@@ -582,7 +707,10 @@ impl AsanRuntime {
                         let mut invocation = Interceptor::current_invocation();
                         let this = &mut *(invocation.replacement_data().unwrap().0 as *mut AsanRuntime);
                         if this.[<hook_check_ $name>]($($param),*) {
-                            this.[<hook_ $name>]($($param),*)
+                            this.allocator().unlock_metadata();
+                            let ret = this.[<hook_ $name>]($($param),*);
+                            this.allocator().lock_metadata();
+                            ret
                         } else {
                             $name($($param),*)
                         }
@@ -765,6 +893,29 @@ impl AsanRuntime {
             *mut c_void
         );
         hook_func!(None, munmap, (addr: *const c_void, length: usize), i32);
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        hook_func!(
+            None,
+            mremap,
+            (
+                old_address: *mut c_void,
+                old_size: usize,
+                new_size: usize,
+                flags: i32,
+                new_address: *mut c_void
+            ),
+            *mut c_void
+        );
+        // Hide the environment of the fuzzer and the runtime from the target
+        hook_func!(None, getenv, (name: *const c_char), *mut c_char);
+        hook_func!(
+            None,
+            setenv,
+            (name: *const c_char, value: *const c_char, overwrite: c_int),
+            c_int
+        );
+        hook_func!(None, unsetenv, (name: *const c_char), c_int);
+        hook_func!(None, putenv, (string: *mut c_char), c_int);
 
         // Hook libc functions which may access allocated memory
         hook_func!(
@@ -866,6 +1017,12 @@ impl AsanRuntime {
             (dest: *mut c_char, src: *const c_char),
             *mut c_char
         );
+        hook_func!(
+            None,
+            strncat,
+            (dest: *mut c_char, src: *const c_char, n: usize),
+            *mut c_char
+        );
         hook_func!(None, strcmp, (s1: *const c_char, s2: *const c_char), i32);
         hook_func!(
             None,
@@ -894,6 +1051,30 @@ impl AsanRuntime {
         hook_func!(None, strdup, (s: *const c_char), *mut c_char);
         hook_func!(None, strlen, (s: *const c_char), usize);
         hook_func!(None, strnlen, (s: *const c_char, n: usize), usize);
+        hook_func!(
+            None,
+            strtol,
+            (nptr: *const c_char, endptr: *mut *mut c_char, base: c_int),
+            c_long
+        );
+        hook_func!(
+            None,
+            strtoll,
+            (nptr: *const c_char, endptr: *mut *mut c_char, base: c_int),
+            c_longlong
+        );
+        hook_func!(
+            None,
+            strtoul,
+            (nptr: *const c_char, endptr: *mut *mut c_char, base: c_int),
+            c_ulong
+        );
+        hook_func!(
+            None,
+            strtoull,
+            (nptr: *const c_char, endptr: *mut *mut c_char, base: c_int),
+            c_ulonglong
+        );
         hook_func!(
             None,
             strstr,
@@ -938,6 +1119,43 @@ impl AsanRuntime {
             (s: *mut c_void, c: *const c_void, n: usize),
             ()
         );
+
+        // The `sprintf` family is variadic, or takes a `va_list`, so it can't be replaced, only observed
+        for (name, size_arg) in [
+            ("snprintf", Some(1)),
+            ("vsnprintf", Some(1)),
+            ("sprintf", None),
+        ] {
+            let Some(function) = frida_gum::Module::find_export_by_name(None, name) else {
+                continue;
+            };
+            let listener = Box::leak(Box::new(PrintfDestListener {
+                runtime: core::ptr::from_mut(self),
+                name,
+                size_arg,
+            }));
+            interceptor.attach(function, listener).ok();
+        }
+
+        // `setjmp` returns twice, and `longjmp` never returns, so both are only probed on entry, and not replaced
+        for (name, is_setjmp) in [
+            ("setjmp", true),
+            ("_setjmp", true),
+            ("sigsetjmp", true),
+            ("__sigsetjmp", true),
+            ("longjmp", false),
+            ("_longjmp", false),
+            ("siglongjmp", false),
+        ] {
+            let Some(function) = frida_gum::Module::find_export_by_name(None, name) else {
+                continue;
+            };
+            let listener = Box::leak(Box::new(JmpListener {
+                runtime: core::ptr::from_mut(self),
+                is_setjmp,
+            }));
+            interceptor.attach_instruction(function, listener).ok();
+        }
     }
 
     #[cfg(target_arch = "x86_64")]
@@ -981,6 +1199,25 @@ impl AsanRuntime {
             }
         }
 
+        // in msan mode, freshly allocated heap memory stays poisoned until it's written to
+        let access_size = insn
+            .mem_size()
+            .and_then(|size| size.bytes_size())
+            .map_or(1, usize::from);
+        let uninitialized = self.allocator.is_uninitialized(fault_address, access_size);
+        // the first write initializes the memory, this is not an error, as long as the whole access
+        // is within a live allocation. Writes reaching past it, or into freed memory, are reported below.
+        if uninitialized
+            && matches!(access_type, Some(AccessType::Write))
+            && self
+                .allocator
+                .remaining_size(fault_address)
+                .is_some_and(|remaining| remaining >= access_size)
+        {
+            self.allocator.mark_initialized(fault_address, access_size);
+            return;
+        }
+
         let backtrace = Backtrace::new();
         let (stack_start, stack_end) = Self::current_stack();
 
@@ -1049,6 +1286,8 @@ impl AsanRuntime {
                                 AccessType::Read => {
                                     if metadata.freed {
                                         AsanError::ReadAfterFree(asan_readwrite_error)
+                                    } else if uninitialized {
+                                        AsanError::UninitializedMemoryRead(asan_readwrite_error)
                                     } else {
                                         AsanError::OobRead(asan_readwrite_error)
                                     }
@@ -1792,6 +2031,37 @@ impl AsanRuntime {
         ops_vec[..ops_vec.len() - 10].to_vec().into_boxed_slice() //????
     }
 
+    /// Generates the comparisons of the accessed address in Rdi with the safe read ranges, put before a shadow check blob
+    /// of `blob_len` bytes. An address in one of the ranges jumps to where the `done` of the blob lands, skipping the
+    /// check and the jump to the report. Clobbers Rsi and the flags, both saved or reloaded in `emit_shadow_check`.
+    #[cfg(target_arch = "x86_64")]
+    #[allow(clippy::cast_possible_wrap, clippy::cast_possible_truncation)]
+    fn generate_safe_read_ranges_check(&self, blob_len: usize) -> Box<[u8]> {
+        let mut ops = dynasmrt::VecAssembler::<dynasmrt::x64::X64Relocation>::new(0);
+        for range in self.safe_read_ranges.iter() {
+            dynasm!(ops
+                ;   .arch x64
+                ;   mov     rsi, QWORD range.start as i64
+                ;   cmp     rdi, rsi
+                ;   jb      >next
+                ;   mov     rsi, QWORD range.end as i64
+                ;   cmp     rdi, rsi
+                ;   jb      >safe
+                ;next:
+            );
+        }
+        dynasm!(ops
+            ;   .arch x64
+            ;   jmp     >check
+            ;safe:
+            // jmp rel32, past the blob and into the nops after the jump to the report
+            ;   .byte   0xe9u8 as i8
+            ;   .dword  (blob_len + 10) as i32
+            ;check:
+        );
+        ops.finalize().unwrap().into_boxed_slice()
+    }
+
     #[cfg(target_arch = "aarch64")]
     #[allow(clippy::unused_self)]
     fn generate_shadow_check_blob(&mut self, bit: u32) -> Box<[u8]> {
@@ -2469,13 +2739,23 @@ impl AsanRuntime {
         writer.put_push_reg(X86Register::Rdi); // save accessed_address
 
         #[cfg(unix)]
-        let checked: bool = match width {
-            1 => writer.put_bytes(self.blob_check_mem_byte()),
-            2 => writer.put_bytes(self.blob_check_mem_halfword()),
-            4 => writer.put_bytes(self.blob_check_mem_dword()),
-            8 => writer.put_bytes(self.blob_check_mem_qword()),
-            16 => writer.put_bytes(self.blob_check_mem_16bytes()),
-            _ => false,
+        let blob = match width {
+            1 => Some(self.blob_check_mem_byte()),
+            2 => Some(self.blob_check_mem_halfword()),
+            4 => Some(self.blob_check_mem_dword()),
+            8 => Some(self.blob_check_mem_qword()),
+            16 => Some(self.blob_check_mem_16bytes()),
+            _ => None,
+        };
+        #[cfg(unix)]
+        let checked: bool = match blob {
+            Some(blob) => {
+                if !self.safe_read_ranges.is_empty() {
+                    writer.put_bytes(&self.generate_safe_read_ranges_check(blob.len()));
+                }
+                writer.put_bytes(blob)
+            }
+            None => false,
         };
 
         if checked {
@@ -2750,8 +3030,156 @@ impl Default for AsanRuntime {
             skip_ranges: Vec::new(),
             continue_on_error: false,
             shadow_check_func: None,
+            access_log_path: None,
+            html_report_dir: None,
+            valgrind_xml_report: None,
+            shadow_stack: false,
+            report_snprintf_truncation: false,
+            safe_read_ranges: RangeSet::new(),
             #[cfg(target_arch = "aarch64")]
             eh_frame: [0; ASAN_EH_FRAME_DWORD_COUNT],
         }
     }
 }
+
+/// Checks the destination buffer of calls to the `sprintf` family.
+///
+/// With a size argument (`snprintf`, `vsnprintf`), the destination is checked to hold that size before the call,
+/// and the output is checked not to be truncated after it, which is only reported with `report_snprintf_truncation`.
+/// Otherwise, the destination is checked to hold the number of bytes actually written after the call.
+struct PrintfDestListener {
+    runtime: *mut AsanRuntime,
+    name: &'static str,
+    /// The index of the argument holding the size of the destination buffer, if any
+    size_arg: Option<u32>,
+}
+
+thread_local! {
+    /// The destination, pc, and size argument of each running call of the `sprintf` family, innermost last,
+    /// still to be checked after it returns. `None` for calls that are not checked.
+    static PRINTF_DESTS: RefCell<Vec<Option<(usize, usize, Option<usize>)>>> = const { RefCell::new(Vec::new()) };
+}
+
+impl PrintfDestListener {
+    fn check_dest(&self, runtime: &AsanRuntime, pc: usize, dest: usize, n: usize) {
+        if !runtime.hook_check_strncpy_dest(dest as *mut c_char, n) {
+            AsanErrors::get_mut().report_error(AsanError::StrncpyDestOverflow((
+                self.name.to_string(),
+                pc,
+                dest,
+                n,
+                Backtrace::new(),
+            )));
+        }
+    }
+}
+
+impl InvocationListener for PrintfDestListener {
+    fn on_enter(&mut self, context: InvocationContext) {
+        let runtime = unsafe { &*self.runtime };
+        let real_address = runtime.real_address_for_stalked(context.return_addr());
+        if runtime.suppressed_addresses.contains(&real_address)
+            || runtime
+                .module_map
+                .as_ref()
+                .unwrap()
+                .find(real_address as u64)
+                .is_none()
+        {
+            PRINTF_DESTS.with(|dests| dests.borrow_mut().push(None));
+            return;
+        }
+
+        let dest = context.arg(0);
+        let n = self.size_arg.map(|size_arg| context.arg(size_arg));
+        if let Some(n) = n {
+            if !runtime.hook_check_strncpy_dest(dest as *mut c_char, n) {
+                AsanErrors::get_mut().report_error(AsanError::SnprintfDestTooSmall((
+                    self.name.to_string(),
+                    real_address,
+                    dest,
+                    n,
+                    Backtrace::new(),
+                )));
+            }
+        }
+        PRINTF_DESTS.with(|dests| dests.borrow_mut().push(Some((dest, real_address, n))));
+    }
+
+    fn on_leave(&mut self, context: InvocationContext) {
+        let Some((dest, pc, n)) = PRINTF_DESTS.with(|dests| dests.borrow_mut().pop().flatten())
+        else {
+            return;
+        };
+        // the number of bytes written, or that would have been written, without the terminating NUL, or negative on error
+        #[allow(clippy::cast_possible_truncation)]
+        let Ok(written) = usize::try_from(context.return_value() as i32) else {
+            return;
+        };
+        let runtime = unsafe { &*self.runtime };
+        match n {
+            // a size of 0 only queries the length of the output
+            Some(n) if n > 0 && written > n - 1 && !runtime.report_snprintf_truncation => {
+                log::debug!(
+                    "{} at {pc:#x} truncated its output of {} bytes to {n} bytes",
+                    self.name,
+                    written + 1
+                );
+            }
+            Some(n) if n > 0 && written > n - 1 => {
+                AsanErrors::get_mut().report_error(AsanError::SnprintfTruncated((
+                    self.name.to_string(),
+                    pc,
+                    dest,
+                    written + 1,
+                    Backtrace::new(),
+                )));
+            }
+            Some(_) => {}
+            None => self.check_dest(runtime, pc, dest, written + 1),
+        }
+    }
+}
+
+thread_local! {
+    /// The stack pointer of the caller of `setjmp`, by the address of its `jmp_buf`
+    static JMP_BUF_STACK_POINTERS: RefCell<HashMap<usize, usize>> = RefCell::new(HashMap::new());
+}
+
+/// Unpoisons the frames discarded by a non-local jump.
+///
+/// `longjmp` discards the frames between its caller and the frame of the matching `setjmp`,
+/// so poison left in them must not trigger on the next use of that stack memory.
+/// The stack pointer of the `setjmp` caller is recorded on entry to `setjmp`, keyed by the `jmp_buf`,
+/// as glibc mangles the one stored in the `jmp_buf`. On entry to `longjmp`, the stack between the
+/// current stack pointer and the recorded one is unpoisoned. The jump itself is left untouched.
+struct JmpListener {
+    runtime: *mut AsanRuntime,
+    is_setjmp: bool,
+}
+
+impl ProbeListener for JmpListener {
+    fn call(&mut self, context: InvocationContext) {
+        let env = context.arg(0);
+        // the stack pointer on entry, i.e. that of the caller, without the return address pushed by the `call`
+        #[cfg(target_arch = "x86_64")]
+        let sp = context.cpu_context().rsp() as usize + 8;
+        #[cfg(target_arch = "aarch64")]
+        let sp = context.cpu_context().sp() as usize;
+
+        if self.is_setjmp {
+            JMP_BUF_STACK_POINTERS.with(|sps| sps.borrow_mut().insert(env, sp));
+            return;
+        }
+
+        let Some(target_sp) = JMP_BUF_STACK_POINTERS.with(|sps| sps.borrow().get(&env).copied())
+        else {
+            log::trace!("longjmp to the unknown jmp_buf {env:#x}, not unpoisoning the stack");
+            return;
+        };
+        if sp < target_sp {
+            let runtime = unsafe { &mut *self.runtime };
+            runtime.unpoison(sp, target_sp - sp);
+        }
+    }
+}
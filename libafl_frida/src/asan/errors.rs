@@ -1,5 +1,5 @@
 //! Errors that can be caught by the `libafl_frida` address sanitizer.
-use std::{fmt::Debug, io::Write, marker::PhantomData};
+use std::{cell::UnsafeCell, fmt::Debug, io::Write, marker::PhantomData, sync::Mutex};
 
 use backtrace::Backtrace;
 use color_backtrace::{default_output_stream, BacktracePrinter, Verbosity};
@@ -30,27 +30,45 @@ use yaxpeax_x86::amd64::InstDecoder;
 #[cfg(target_arch = "x86_64")]
 use crate::asan::asan_rt::ASAN_SAVE_REGISTER_NAMES;
 use crate::{
-    alloc::AllocationMetadata, asan::asan_rt::ASAN_SAVE_REGISTER_COUNT, utils::disas_count,
+    alloc::AllocationMetadata,
+    asan::{
+        asan_rt::ASAN_SAVE_REGISTER_COUNT, report::AsanHtmlReporter, valgrind::ValgrindXmlReporter,
+    },
+    utils::disas_count,
 };
 
+/// A faulting memory access, found by the instrumentation of the [`crate::asan::asan_rt::AsanRuntime`]
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub(crate) struct AsanReadWriteError {
+pub struct AsanReadWriteError {
+    /// The registers at the time of the access
     pub registers: [usize; ASAN_SAVE_REGISTER_COUNT],
+    /// The address of the faulting instruction
     pub pc: usize,
+    /// The base register, index register, displacement, and address of the access
     pub fault: (Option<u16>, Option<u16>, usize, usize),
+    /// The allocation closest to the accessed address
     pub metadata: AllocationMetadata,
+    /// The backtrace of the access
     pub backtrace: Backtrace,
 }
 
+/// An error found by the `libafl_frida` address sanitizer
 #[allow(clippy::type_complexity)]
 #[derive(Debug, Clone, Serialize, Deserialize, SerdeAny)]
-pub(crate) enum AsanError {
+pub enum AsanError {
+    /// A read out of the bounds of a heap allocation
     OobRead(AsanReadWriteError),
+    /// A write out of the bounds of a heap allocation
     OobWrite(AsanReadWriteError),
+    /// A read of freed memory
     ReadAfterFree(AsanReadWriteError),
+    /// A write to freed memory
     WriteAfterFree(AsanReadWriteError),
+    /// The address, allocation, and backtrace of a second `free`
     DoubleFree((usize, AllocationMetadata, Backtrace)),
+    /// The address and backtrace of a `free` of memory that was never allocated
     UnallocatedFree((usize, Backtrace)),
+    /// A faulting access that could not be attributed, with the registers, pc, fault, and backtrace
     Unknown(
         (
             [usize; ASAN_SAVE_REGISTER_COUNT],
@@ -59,7 +77,9 @@ pub(crate) enum AsanError {
             Backtrace,
         ),
     ),
+    /// The address and allocation of leaked memory
     Leak((usize, AllocationMetadata)),
+    /// A read out of the bounds of a stack frame, with the registers, pc, fault, and backtrace
     StackOobRead(
         (
             [usize; ASAN_SAVE_REGISTER_COUNT],
@@ -68,6 +88,7 @@ pub(crate) enum AsanError {
             Backtrace,
         ),
     ),
+    /// A write out of the bounds of a stack frame, with the registers, pc, fault, and backtrace
     StackOobWrite(
         (
             [usize; ASAN_SAVE_REGISTER_COUNT],
@@ -76,12 +97,26 @@ pub(crate) enum AsanError {
             Backtrace,
         ),
     ),
+    /// A hooked function reading out of bounds, with its name, pc, address, size, and backtrace
     BadFuncArgRead((String, usize, usize, usize, Backtrace)),
+    /// A hooked function writing out of bounds, with its name, pc, address, size, and backtrace
     BadFuncArgWrite((String, usize, usize, usize, Backtrace)),
+    /// A string copy overflowing its destination, with the function name, pc, address, size, and backtrace
+    StrncpyDestOverflow((String, usize, usize, usize, Backtrace)),
+    /// A `snprintf` size argument larger than the destination, with the function name, pc, address, size, and backtrace
+    SnprintfDestTooSmall((String, usize, usize, usize, Backtrace)),
+    /// A `snprintf` output truncated to the size argument, with the function name, pc, address,
+    /// the size the output would have needed, and backtrace
+    SnprintfTruncated((String, usize, usize, usize, Backtrace)),
+    /// A read of allocated memory never written to
+    UninitializedMemoryRead(AsanReadWriteError),
+    /// A return to an address no tracked call pushed, with the pc of the `ret`,
+    /// the expected and the actual return address, and the backtrace
+    ShadowStackCorruption((usize, usize, usize, Backtrace)),
 }
 
 impl AsanError {
-    fn description(&self) -> &str {
+    pub(crate) fn description(&self) -> &str {
         match self {
             AsanError::OobRead(_) => "heap out-of-bounds read",
             AsanError::OobWrite(_) => "heap out-of-bounds write",
@@ -95,6 +130,11 @@ impl AsanError {
             AsanError::StackOobWrite(_) => "stack out-of-bounds write",
             AsanError::BadFuncArgRead(_) => "function arg resulting in bad read",
             AsanError::BadFuncArgWrite(_) => "function arg resulting in bad write",
+            AsanError::StrncpyDestOverflow(_) => "destination buffer overflow",
+            AsanError::SnprintfDestTooSmall(_) => "destination buffer smaller than its size",
+            AsanError::SnprintfTruncated(_) => "formatted output truncated",
+            AsanError::UninitializedMemoryRead(_) => "heap use-of-uninitialized-value read",
+            AsanError::ShadowStackCorruption(_) => "shadow stack corruption",
         }
     }
 }
@@ -105,6 +145,10 @@ impl AsanError {
 pub struct AsanErrors {
     continue_on_error: bool,
     errors: Vec<AsanError>,
+    #[serde(skip)]
+    html_reporter: Option<AsanHtmlReporter>,
+    #[serde(skip)]
+    valgrind_reporter: Option<ValgrindXmlReporter>,
 }
 
 impl AsanErrors {
@@ -114,9 +158,21 @@ impl AsanErrors {
         Self {
             errors: Vec::new(),
             continue_on_error,
+            html_reporter: None,
+            valgrind_reporter: None,
         }
     }
 
+    /// Sets the [`AsanHtmlReporter`] writing an HTML report of each error, in addition to the console output
+    pub fn set_html_reporter(&mut self, html_reporter: Option<AsanHtmlReporter>) {
+        self.html_reporter = html_reporter;
+    }
+
+    /// Sets the [`ValgrindXmlReporter`] appending each error to a Valgrind compatible XML report
+    pub fn set_valgrind_reporter(&mut self, valgrind_reporter: Option<ValgrindXmlReporter>) {
+        self.valgrind_reporter = valgrind_reporter;
+    }
+
     /// Clears this `AsanErrors` struct
     pub fn clear(&mut self) {
         self.errors.clear();
@@ -134,10 +190,87 @@ impl AsanErrors {
         self.errors.is_empty()
     }
 
-    /// Get a mutable reference to the global [`struct@AsanErrors`] object
+    /// Get a mutable reference to the [`struct@AsanErrors`] object of the current thread.
+    /// Threads that did not initialize the [`crate::asan::asan_rt::AsanRuntime`], e.g. ones spawned by the target,
+    /// get a copy of the configuration set by [`AsanErrors::init_thread_local`], without any errors.
+    /// As the Valgrind XML report is a single file, tracking its end, only the initializing thread appends to it.
     #[must_use]
     pub fn get_mut<'a>() -> &'a mut Self {
-        unsafe { ASAN_ERRORS.as_mut().unwrap() }
+        let ptr = Self::thread_local_ptr();
+        unsafe {
+            if (*ptr).is_none() {
+                let config = ASAN_ERRORS_CONFIG
+                    .lock()
+                    .unwrap()
+                    .clone()
+                    .unwrap_or_else(|| AsanErrors::new(false));
+                *ptr = Some(config);
+                Self::register_thread_local(ptr);
+            }
+            (*ptr).as_mut().unwrap()
+        }
+    }
+
+    /// A pointer to the [`struct@AsanErrors`] of the current thread, valid as long as the thread lives.
+    /// It is `None` until the first error of the thread, or until the [`crate::asan::asan_rt::AsanRuntime`]
+    /// was initialized on this thread.
+    #[must_use]
+    pub fn thread_local_ptr() -> *mut Option<AsanErrors> {
+        ASAN_ERRORS.with(|errors| errors.0.get())
+    }
+
+    /// Sets the [`struct@AsanErrors`] of the current thread, and the configuration of the threads initialized lazily by [`AsanErrors::get_mut`]
+    pub(crate) fn init_thread_local(errors: AsanErrors) {
+        let mut config = errors.clone();
+        config.set_valgrind_reporter(None);
+        *ASAN_ERRORS_CONFIG.lock().unwrap() = Some(config);
+        let ptr = Self::thread_local_ptr();
+        unsafe {
+            *ptr = Some(errors);
+        }
+        Self::register_thread_local(ptr);
+    }
+
+    /// Registers the [`struct@AsanErrors`] of the current thread for [`AsanErrors::collect_from_all_threads`]
+    fn register_thread_local(ptr: *mut Option<AsanErrors>) {
+        let mut all = ALL_ASAN_ERRORS.lock().unwrap();
+        if !all.iter().any(|registered| registered.0 == ptr) {
+            all.push(AsanErrorsPtr(ptr));
+        }
+    }
+
+    /// Drains the errors of all live threads, including the current one, for reporting.
+    ///
+    /// As the errors of the threads are not synchronized,
+    /// call this while the other threads are not running the target, e.g. between executions.
+    #[must_use]
+    pub fn collect_from_all_threads() -> Vec<AsanError> {
+        let all = ALL_ASAN_ERRORS.lock().unwrap();
+        let mut errors = Vec::new();
+        for registered in all.iter() {
+            if let Some(thread_errors) = unsafe { (*registered.0).as_mut() } {
+                errors.append(&mut thread_errors.errors);
+            }
+        }
+        errors
+    }
+
+    /// Moves the errors of all live threads into the [`struct@AsanErrors`] of the current thread,
+    /// where the [`AsanErrorsObserver`] and the executor look for them, see [`AsanErrors::collect_from_all_threads`]
+    pub(crate) fn join_into_thread_local() {
+        let errors = Self::collect_from_all_threads();
+        if !errors.is_empty() {
+            Self::get_mut().errors = errors;
+        }
+    }
+
+    /// Clears the errors of the current thread, if initialized
+    pub fn clear_thread_local() {
+        unsafe {
+            if let Some(errors) = (*Self::thread_local_ptr()).as_mut() {
+                errors.clear();
+            }
+        }
     }
 
     /// Report an error
@@ -145,6 +278,19 @@ impl AsanErrors {
     pub(crate) fn report_error(&mut self, error: AsanError) {
         self.errors.push(error.clone());
 
+        if let Some(html_reporter) = &self.html_reporter {
+            match html_reporter.report(&error) {
+                Ok(path) => log::info!("ASan HTML report written to {}", path.display()),
+                Err(err) => log::error!("Failed to write the ASan HTML report: {err}"),
+            }
+        }
+
+        if let Some(valgrind_reporter) = &mut self.valgrind_reporter {
+            if let Err(err) = valgrind_reporter.report(&error) {
+                log::error!("Failed to write the Valgrind XML report: {err}");
+            }
+        }
+
         let mut out_stream = default_output_stream();
         let output = out_stream.as_mut();
 
@@ -168,7 +314,8 @@ impl AsanErrors {
             AsanError::OobRead(mut error)
             | AsanError::OobWrite(mut error)
             | AsanError::ReadAfterFree(mut error)
-            | AsanError::WriteAfterFree(mut error) => {
+            | AsanError::WriteAfterFree(mut error)
+            | AsanError::UninitializedMemoryRead(mut error) => {
                 let (basereg, indexreg, _displacement, fault_address) = error.fault;
 
                 if let Some(module_details) = ModuleDetails::with_address(error.pc as u64) {
@@ -318,7 +465,10 @@ impl AsanErrors {
                 }
             }
             AsanError::BadFuncArgRead((name, _pc, address, size, backtrace))
-            | AsanError::BadFuncArgWrite((name, _pc, address, size, backtrace)) => {
+            | AsanError::BadFuncArgWrite((name, _pc, address, size, backtrace))
+            | AsanError::StrncpyDestOverflow((name, _pc, address, size, backtrace))
+            | AsanError::SnprintfDestTooSmall((name, _pc, address, size, backtrace))
+            | AsanError::SnprintfTruncated((name, _pc, address, size, backtrace)) => {
                 writeln!(
                     output,
                     " in call to {name}, argument {address:#016x}, size: {size:#x}"
@@ -401,6 +551,15 @@ impl AsanErrors {
                 output.reset().unwrap();
                 backtrace_printer.print_trace(&backtrace, output).unwrap();
             }
+            AsanError::ShadowStackCorruption((pc, expected, actual, backtrace)) => {
+                writeln!(
+                    output,
+                    " at {pc:#016x}, returning to {actual:#016x} instead of {expected:#016x}"
+                )
+                .unwrap();
+                output.reset().unwrap();
+                backtrace_printer.print_trace(&backtrace, output).unwrap();
+            }
             AsanError::Leak((ptr, mut metadata)) => {
                 writeln!(output, " of {ptr:#016x}").unwrap();
                 output.reset().unwrap();
@@ -543,8 +702,35 @@ impl AsanErrors {
     }
 }
 
-/// static field for `AsanErrors` for a run
-pub static mut ASAN_ERRORS: Option<AsanErrors> = None;
+/// The `AsanErrors` of a thread, unregistered from [`AsanErrors::collect_from_all_threads`] when the thread exits
+struct ThreadAsanErrors(UnsafeCell<Option<AsanErrors>>);
+
+impl Drop for ThreadAsanErrors {
+    fn drop(&mut self) {
+        let ptr = self.0.get();
+        if let Ok(mut all) = ALL_ASAN_ERRORS.lock() {
+            all.retain(|registered| registered.0 != ptr);
+        }
+    }
+}
+
+thread_local! {
+    /// The `AsanErrors` of the current thread, for a run
+    static ASAN_ERRORS: ThreadAsanErrors = const { ThreadAsanErrors(UnsafeCell::new(None)) };
+}
+
+/// A pointer to the `AsanErrors` of a live thread
+struct AsanErrorsPtr(*mut Option<AsanErrors>);
+
+// # Safety
+// The pointers are only dereferenced while holding the lock of `ALL_ASAN_ERRORS`, and unregistered, under the lock, when their thread exits.
+unsafe impl Send for AsanErrorsPtr {}
+
+/// The `AsanErrors` of all live threads that initialized one
+static ALL_ASAN_ERRORS: Mutex<Vec<AsanErrorsPtr>> = Mutex::new(Vec::new());
+
+/// The configuration of the `AsanErrors` of threads other than the one initializing the runtime
+static ASAN_ERRORS_CONFIG: Mutex<Option<AsanErrors>> = Mutex::new(None);
 
 /// An observer for frida address sanitizer `AsanError`s for a frida executor run
 #[derive(Debug, Serialize, Deserialize)]
@@ -558,12 +744,18 @@ where
     S: UsesInput,
 {
     fn pre_exec(&mut self, _state: &mut S, _input: &S::Input) -> Result<(), Error> {
-        unsafe {
-            if ASAN_ERRORS.is_some() {
-                ASAN_ERRORS.as_mut().unwrap().clear();
-            }
-        }
+        // also drops the errors other threads left after a crash of the last run
+        drop(AsanErrors::collect_from_all_threads());
+        Ok(())
+    }
 
+    fn post_exec(
+        &mut self,
+        _state: &mut S,
+        _input: &S::Input,
+        _exit_kind: &ExitKind,
+    ) -> Result<(), Error> {
+        AsanErrors::join_into_thread_local();
         Ok(())
     }
 }
@@ -576,6 +768,13 @@ impl Named for AsanErrorsObserver {
 }
 
 impl AsanErrorsObserver {
+    /// Creates a new `AsanErrorsObserver`, observing the `AsanErrors` of the current thread.
+    /// The observer has to be used on this thread.
+    #[must_use]
+    pub fn thread_local() -> Self {
+        Self::new(AsanErrors::thread_local_ptr())
+    }
+
     /// Creates a new `AsanErrorsObserver`, pointing to a constant `AsanErrors` field
     #[must_use]
     pub fn new(errors: *const Option<AsanErrors>) -> Self {
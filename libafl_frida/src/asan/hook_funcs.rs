@@ -1,18 +1,47 @@
 //! The allocator hooks for address sanitizer.
-use std::ffi::c_void;
+use std::ffi::{c_void, CStr};
 
 use backtrace::Backtrace;
-use libc::{c_char, wchar_t};
+use libc::{c_char, c_int, c_long, c_longlong, c_ulong, c_ulonglong, wchar_t};
 use nix::libc::memset;
 
 use crate::{
     alloc::Allocator,
     asan::{
-        asan_rt::AsanRuntime,
-        errors::{AsanError, AsanErrors},
+        asan_rt::{AsanRuntime, ASAN_SAVE_REGISTER_COUNT},
+        errors::{AsanError, AsanErrors, AsanReadWriteError},
     },
 };
 
+/// The environment variables of the fuzzer and the ASan runtime, hidden from the target by the `getenv` hook,
+/// and protected from changes by the `setenv`, `unsetenv` and `putenv` hooks
+pub const HIDDEN_ENV_VARS: &[&str] = &[
+    "__AFL_SHM_ID",
+    "__AFL_SHM_FUZZ_ID",
+    "LD_PRELOAD",
+    "DYLD_INSERT_LIBRARIES",
+    "LIBAFL_FRIDA_OPTIONS",
+];
+
+/// Returns `true` if `name` is one of the [`HIDDEN_ENV_VARS`]
+fn is_hidden_env_var(name: &[u8]) -> bool {
+    HIDDEN_ENV_VARS.iter().any(|var| var.as_bytes() == name)
+}
+
+/// Returns `true` if the variable named by the C string `name` is one of the [`HIDDEN_ENV_VARS`],
+/// `name_end` cuts off the name, e.g. at the `=` of a `putenv` string
+unsafe fn is_hidden_env_var_ptr(name: *const c_char, name_end: Option<u8>) -> bool {
+    if name.is_null() {
+        return false;
+    }
+    let name = CStr::from_ptr(name).to_bytes();
+    let name = match name_end.and_then(|end| name.iter().position(|c| *c == end)) {
+        Some(len) => &name[..len],
+        None => name,
+    };
+    is_hidden_env_var(name)
+}
+
 #[allow(clippy::not_unsafe_ptr_arg_deref)]
 impl AsanRuntime {
     #[inline]
@@ -114,6 +143,8 @@ impl AsanRuntime {
         unsafe {
             memset(ret, 0, size * nmemb);
         }
+        self.allocator_mut()
+            .mark_initialized(ret as usize, size * nmemb);
         ret
     }
 
@@ -122,12 +153,22 @@ impl AsanRuntime {
     pub fn hook_realloc(&mut self, ptr: *mut c_void, size: usize) -> *mut c_void {
         unsafe {
             let ret = self.allocator_mut().alloc(size, 0x8);
-            if ptr != std::ptr::null_mut() && ret != std::ptr::null_mut() {
+            if ret == std::ptr::null_mut() {
+                // as for the libc realloc, the old allocation stays valid on failure
+                return ret;
+            }
+            if ptr != std::ptr::null_mut() {
                 let old_size = self.allocator_mut().get_usable_size(ptr);
                 let copy_size = if size < old_size { size } else { old_size };
                 (ptr as *mut u8).copy_to(ret as *mut u8, copy_size);
+                self.allocator_mut()
+                    .mark_initialized(ret as usize, copy_size);
+            }
+            if ret != ptr {
+                // the allocation moved: releasing the old one poisons it, and marks it as freed,
+                // so that accesses through the old pointer are reported as use-after-free
+                self.allocator_mut().release(ptr);
             }
-            self.allocator_mut().release(ptr);
             ret
         }
     }
@@ -320,8 +361,93 @@ impl AsanRuntime {
         }
         let res = unsafe { mmap(addr, length, prot, flags, fd, offset) };
         if res != (-1_isize as *mut c_void) {
-            self.allocator_mut()
-                .map_shadow_for_region(res as usize, res as usize + length, true);
+            // file-backed mappings are usually not `mprotect`ed later, so their shadow follows `prot`:
+            // readable ones are accessible, while a `PROT_NONE` mapping of a file stays poisoned
+            let accessible = fd == -1 || prot & libc::PROT_READ != 0;
+            let allocator = self.allocator_mut();
+            allocator.map_shadow_for_region(res as usize, res as usize + length, accessible);
+            if !accessible {
+                // the shadow may remain from a previous mapping replaced with `MAP_FIXED`
+                Allocator::poison(allocator.map_to_shadow(res as usize), length);
+            }
+        }
+        res
+    }
+
+    /// Moves the shadow of a mapping resized or moved by `mremap`.
+    ///
+    /// `mremap` is variadic, `new_address` is only read by it with `MREMAP_FIXED`.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[inline]
+    pub fn hook_mremap(
+        &mut self,
+        old_address: *mut c_void,
+        old_size: usize,
+        new_size: usize,
+        flags: i32,
+        new_address: *mut c_void,
+    ) -> *mut c_void {
+        extern "C" {
+            fn mremap(
+                old_address: *mut c_void,
+                old_size: usize,
+                new_size: usize,
+                flags: i32,
+                new_address: *mut c_void,
+            ) -> *mut c_void;
+        }
+        let old_start = old_address as usize;
+        // the accessibility of the kept bytes moves along with them. The mappings are page aligned,
+        // so their shadow starts at a whole shadow byte. Regions without shadow, e.g. mapped before
+        // the runtime, were never poisoned, and are accessible as a whole.
+        let kept_size = old_size.min(new_size);
+        let old_shadow = {
+            let allocator = self.allocator();
+            allocator
+                .has_shadow_for_region(old_start, old_start + old_size)
+                .then(|| unsafe {
+                    core::slice::from_raw_parts(
+                        allocator.map_to_shadow(old_start) as *const u8,
+                        kept_size / 8,
+                    )
+                    .to_vec()
+                })
+        };
+
+        let res = unsafe { mremap(old_address, old_size, new_size, flags, new_address) };
+        if res != (-1_isize as *mut c_void) {
+            let new_start = res as usize;
+            let allocator = self.allocator_mut();
+            if old_shadow.is_some() {
+                if new_start == old_start {
+                    if new_size < old_size {
+                        Allocator::poison(
+                            allocator.map_to_shadow(old_start + new_size),
+                            old_size - new_size,
+                        );
+                    }
+                } else {
+                    Allocator::poison(allocator.map_to_shadow(old_start), old_size);
+                }
+            }
+
+            allocator.map_shadow_for_region(new_start, new_start + new_size, false);
+            // the grown part is fresh memory, accessible
+            let copied = if let Some(old_shadow) = old_shadow {
+                unsafe {
+                    core::ptr::copy_nonoverlapping(
+                        old_shadow.as_ptr(),
+                        allocator.map_to_shadow(new_start) as *mut u8,
+                        old_shadow.len(),
+                    );
+                }
+                old_shadow.len() * 8
+            } else {
+                0
+            };
+            if new_size > copied {
+                self.unpoison(new_start + copied, new_size - copied);
+            }
         }
         res
     }
@@ -338,6 +464,56 @@ impl AsanRuntime {
         res
     }
 
+    #[inline]
+    pub fn hook_getenv(&mut self, name: *const c_char) -> *mut c_char {
+        extern "C" {
+            fn getenv(name: *const c_char) -> *mut c_char;
+        }
+        if unsafe { is_hidden_env_var_ptr(name, None) } {
+            return std::ptr::null_mut();
+        }
+        unsafe { getenv(name) }
+    }
+
+    #[inline]
+    pub fn hook_setenv(
+        &mut self,
+        name: *const c_char,
+        value: *const c_char,
+        overwrite: c_int,
+    ) -> c_int {
+        extern "C" {
+            fn setenv(name: *const c_char, value: *const c_char, overwrite: c_int) -> c_int;
+        }
+        // pretend success, the target must not notice the variable exists
+        if unsafe { is_hidden_env_var_ptr(name, None) } {
+            return 0;
+        }
+        unsafe { setenv(name, value, overwrite) }
+    }
+
+    #[inline]
+    pub fn hook_unsetenv(&mut self, name: *const c_char) -> c_int {
+        extern "C" {
+            fn unsetenv(name: *const c_char) -> c_int;
+        }
+        if unsafe { is_hidden_env_var_ptr(name, None) } {
+            return 0;
+        }
+        unsafe { unsetenv(name) }
+    }
+
+    #[inline]
+    pub fn hook_putenv(&mut self, string: *mut c_char) -> c_int {
+        extern "C" {
+            fn putenv(string: *mut c_char) -> c_int;
+        }
+        if unsafe { is_hidden_env_var_ptr(string, Some(b'=')) } {
+            return 0;
+        }
+        unsafe { putenv(string) }
+    }
+
     #[inline]
     pub fn hook_write(&mut self, fd: i32, buf: *const c_void, count: usize) -> usize {
         extern "C" {
@@ -360,33 +536,33 @@ impl AsanRuntime {
         extern "C" {
             fn read(fd: i32, buf: *mut c_void, count: usize) -> usize;
         }
-        if !(self.shadow_check_func().unwrap())(buf, count) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "read".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                buf as usize,
-                count,
-                Backtrace::new(),
-            )));
+        if !self.check_func_arg_dest(buf, count) {
+            self.report_bad_func_arg_read("read", buf as usize, count);
+        }
+        let ret = unsafe { read(fd, buf, count) };
+        // only the bytes actually read are initialized, `-1` on errors
+        if (ret as isize) > 0 {
+            self.allocator_mut().mark_initialized(buf as usize, ret);
         }
-        unsafe { read(fd, buf, count) }
+        ret
     }
 
     #[inline]
     pub fn hook_fgets(&mut self, s: *mut c_void, size: u32, stream: *mut c_void) -> *mut c_void {
         extern "C" {
             fn fgets(s: *mut c_void, size: u32, stream: *mut c_void) -> *mut c_void;
+            fn strlen(s: *const c_char) -> usize;
         }
-        if !(self.shadow_check_func().unwrap())(s, size as usize) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "fgets".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                s as usize,
-                size as usize,
-                Backtrace::new(),
-            )));
+        if !self.check_func_arg_dest(s, size as usize) {
+            self.report_bad_func_arg_read("fgets", s as usize, size as usize);
+        }
+        let ret = unsafe { fgets(s, size, stream) };
+        if !ret.is_null() {
+            // the line read, including its terminating nul byte
+            let len = unsafe { strlen(s as *const c_char) } + 1;
+            self.allocator_mut().mark_initialized(s as usize, len);
         }
-        unsafe { fgets(s, size, stream) }
+        ret
     }
 
     #[inline]
@@ -395,22 +571,10 @@ impl AsanRuntime {
             fn memcmp(s1: *const c_void, s2: *const c_void, n: usize) -> i32;
         }
         if !(self.shadow_check_func().unwrap())(s1, n) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "memcmp".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                s1 as usize,
-                n,
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("memcmp", s1 as usize, n);
         }
         if !(self.shadow_check_func().unwrap())(s2, n) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "memcmp".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                s2 as usize,
-                n,
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("memcmp", s2 as usize, n);
         }
         unsafe { memcmp(s1, s2, n) }
     }
@@ -420,6 +584,7 @@ impl AsanRuntime {
         extern "C" {
             fn memcpy(dest: *mut c_void, src: *const c_void, n: usize) -> *mut c_void;
         }
+        self.allocator_mut().mark_initialized(dest as usize, n);
         if !(self.shadow_check_func().unwrap())(dest, n) {
             AsanErrors::get_mut().report_error(AsanError::BadFuncArgWrite((
                 "memcpy".to_string(),
@@ -430,13 +595,7 @@ impl AsanRuntime {
             )));
         }
         if !(self.shadow_check_func().unwrap())(src, n) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "memcpy".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                src as usize,
-                n,
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("memcpy", src as usize, n);
         }
         unsafe { memcpy(dest, src, n) }
     }
@@ -447,6 +606,7 @@ impl AsanRuntime {
         extern "C" {
             fn mempcpy(dest: *mut c_void, src: *const c_void, n: usize) -> *mut c_void;
         }
+        self.allocator_mut().mark_initialized(dest as usize, n);
         if !(self.shadow_check_func().unwrap())(dest, n) {
             AsanErrors::get_mut().report_error(AsanError::BadFuncArgWrite((
                 "mempcpy".to_string(),
@@ -457,13 +617,7 @@ impl AsanRuntime {
             )));
         }
         if !(self.shadow_check_func().unwrap())(src, n) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "mempcpy".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                src as usize,
-                n,
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("mempcpy", src as usize, n);
         }
         unsafe { mempcpy(dest, src, n) }
     }
@@ -473,6 +627,7 @@ impl AsanRuntime {
         extern "C" {
             fn memmove(dest: *mut c_void, src: *const c_void, n: usize) -> *mut c_void;
         }
+        self.allocator_mut().mark_initialized(dest as usize, n);
         if !(self.shadow_check_func().unwrap())(dest, n) {
             AsanErrors::get_mut().report_error(AsanError::BadFuncArgWrite((
                 "memmove".to_string(),
@@ -483,13 +638,7 @@ impl AsanRuntime {
             )));
         }
         if !(self.shadow_check_func().unwrap())(src, n) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "memmove".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                src as usize,
-                n,
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("memmove", src as usize, n);
         }
         unsafe { memmove(dest, src, n) }
     }
@@ -499,6 +648,7 @@ impl AsanRuntime {
         extern "C" {
             fn memset(dest: *mut c_void, c: i32, n: usize) -> *mut c_void;
         }
+        self.allocator_mut().mark_initialized(dest as usize, n);
         if !(self.shadow_check_func().unwrap())(dest, n) {
             AsanErrors::get_mut().report_error(AsanError::BadFuncArgWrite((
                 "memset".to_string(),
@@ -517,13 +667,7 @@ impl AsanRuntime {
             fn memchr(s: *mut c_void, c: i32, n: usize) -> *mut c_void;
         }
         if !(self.shadow_check_func().unwrap())(s, n) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "memchr".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                s as usize,
-                n,
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("memchr", s as usize, n);
         }
         unsafe { memchr(s, c, n) }
     }
@@ -535,13 +679,7 @@ impl AsanRuntime {
             fn memrchr(s: *mut c_void, c: i32, n: usize) -> *mut c_void;
         }
         if !(self.shadow_check_func().unwrap())(s, n) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "memrchr".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                s as usize,
-                n,
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("memrchr", s as usize, n);
         }
         unsafe { memrchr(s, c, n) }
     }
@@ -563,22 +701,10 @@ impl AsanRuntime {
             ) -> *mut c_void;
         }
         if !(self.shadow_check_func().unwrap())(haystack, haystacklen) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "memmem".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                haystack as usize,
-                haystacklen,
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("memmem", haystack as usize, haystacklen);
         }
         if !(self.shadow_check_func().unwrap())(needle, needlelen) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "memmem".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                needle as usize,
-                needlelen,
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("memmem", needle as usize, needlelen);
         }
         unsafe { memmem(haystack, haystacklen, needle, needlelen) }
     }
@@ -589,6 +715,7 @@ impl AsanRuntime {
         extern "C" {
             fn bzero(s: *mut c_void, n: usize);
         }
+        self.allocator_mut().mark_initialized(s as usize, n);
         if !(self.shadow_check_func().unwrap())(s, n) {
             AsanErrors::get_mut().report_error(AsanError::BadFuncArgWrite((
                 "bzero".to_string(),
@@ -607,6 +734,7 @@ impl AsanRuntime {
         extern "C" {
             fn explicit_bzero(s: *mut c_void, n: usize);
         }
+        self.allocator_mut().mark_initialized(s as usize, n);
         if !(self.shadow_check_func().unwrap())(s, n) {
             AsanErrors::get_mut().report_error(AsanError::BadFuncArgWrite((
                 "explicit_bzero".to_string(),
@@ -626,22 +754,10 @@ impl AsanRuntime {
             fn bcmp(s1: *const c_void, s2: *const c_void, n: usize) -> i32;
         }
         if !(self.shadow_check_func().unwrap())(s1, n) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "bcmp".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                s1 as usize,
-                n,
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("bcmp", s1 as usize, n);
         }
         if !(self.shadow_check_func().unwrap())(s2, n) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "bcmp".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                s2 as usize,
-                n,
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("bcmp", s2 as usize, n);
         }
         unsafe { bcmp(s1, s2, n) }
     }
@@ -653,13 +769,7 @@ impl AsanRuntime {
             fn strlen(s: *const c_char) -> usize;
         }
         if !(self.shadow_check_func().unwrap())(s as *const c_void, unsafe { strlen(s) }) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "strchr".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                s as usize,
-                unsafe { strlen(s) },
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("strchr", s as usize, unsafe { strlen(s) });
         }
         unsafe { strchr(s, c) }
     }
@@ -671,13 +781,7 @@ impl AsanRuntime {
             fn strlen(s: *const c_char) -> usize;
         }
         if !(self.shadow_check_func().unwrap())(s as *const c_void, unsafe { strlen(s) }) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "strrchr".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                s as usize,
-                unsafe { strlen(s) },
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("strrchr", s as usize, unsafe { strlen(s) });
         }
         unsafe { strrchr(s, c) }
     }
@@ -689,22 +793,10 @@ impl AsanRuntime {
             fn strlen(s: *const c_char) -> usize;
         }
         if !(self.shadow_check_func().unwrap())(s1 as *const c_void, unsafe { strlen(s1) }) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "strcasecmp".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                s1 as usize,
-                unsafe { strlen(s1) },
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("strcasecmp", s1 as usize, unsafe { strlen(s1) });
         }
         if !(self.shadow_check_func().unwrap())(s2 as *const c_void, unsafe { strlen(s2) }) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "strcasecmp".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                s2 as usize,
-                unsafe { strlen(s2) },
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("strcasecmp", s2 as usize, unsafe { strlen(s2) });
         }
         unsafe { strcasecmp(s1, s2) }
     }
@@ -715,22 +807,10 @@ impl AsanRuntime {
             fn strncasecmp(s1: *const c_char, s2: *const c_char, n: usize) -> i32;
         }
         if !(self.shadow_check_func().unwrap())(s1 as *const c_void, n) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "strncasecmp".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                s1 as usize,
-                n,
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("strncasecmp", s1 as usize, n);
         }
         if !(self.shadow_check_func().unwrap())(s2 as *const c_void, n) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "strncasecmp".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                s2 as usize,
-                n,
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("strncasecmp", s2 as usize, n);
         }
         unsafe { strncasecmp(s1, s2, n) }
     }
@@ -742,24 +822,42 @@ impl AsanRuntime {
             fn strlen(s: *const c_char) -> usize;
         }
         if !(self.shadow_check_func().unwrap())(s1 as *const c_void, unsafe { strlen(s1) }) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "strcat".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                s1 as usize,
-                unsafe { strlen(s1) },
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("strcat", s1 as usize, unsafe { strlen(s1) });
         }
         if !(self.shadow_check_func().unwrap())(s2 as *const c_void, unsafe { strlen(s2) }) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "strcat".to_string(),
+            self.report_bad_func_arg_read("strcat", s2 as usize, unsafe { strlen(s2) });
+        }
+        unsafe { strcat(s1, s2) }
+    }
+
+    #[inline]
+    pub fn hook_strncat(&mut self, dest: *mut c_char, src: *const c_char, n: usize) -> *mut c_char {
+        extern "C" {
+            fn strncat(dest: *mut c_char, src: *const c_char, n: usize) -> *mut c_char;
+            fn strlen(s: *const c_char) -> usize;
+            fn strnlen(s: *const c_char, n: usize) -> usize;
+        }
+        let dest_len = unsafe { strlen(dest) };
+        let src_len = unsafe { strnlen(src, n) };
+        if !(self.shadow_check_func().unwrap())(dest as *const c_void, dest_len) {
+            self.report_bad_func_arg_read("strncat", dest as usize, dest_len);
+        }
+        if !(self.shadow_check_func().unwrap())(src as *const c_void, src_len) {
+            self.report_bad_func_arg_read("strncat", src as usize, src_len);
+        }
+        // strncat always appends a terminating NUL
+        if !self.hook_check_strncpy_dest(dest, dest_len + src_len + 1) {
+            AsanErrors::get_mut().report_error(AsanError::StrncpyDestOverflow((
+                "strncat".to_string(),
                 self.real_address_for_stalked(AsanRuntime::pc()),
-                s2 as usize,
-                unsafe { strlen(s2) },
+                dest as usize,
+                dest_len + src_len + 1,
                 Backtrace::new(),
             )));
         }
-        unsafe { strcat(s1, s2) }
+        self.allocator_mut()
+            .mark_initialized(dest as usize + dest_len, src_len + 1);
+        unsafe { strncat(dest, src, n) }
     }
 
     #[inline]
@@ -769,22 +867,10 @@ impl AsanRuntime {
             fn strlen(s: *const c_char) -> usize;
         }
         if !(self.shadow_check_func().unwrap())(s1 as *const c_void, unsafe { strlen(s1) }) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "strcmp".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                s1 as usize,
-                unsafe { strlen(s1) },
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("strcmp", s1 as usize, unsafe { strlen(s1) });
         }
         if !(self.shadow_check_func().unwrap())(s2 as *const c_void, unsafe { strlen(s2) }) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "strcmp".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                s2 as usize,
-                unsafe { strlen(s2) },
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("strcmp", s2 as usize, unsafe { strlen(s2) });
         }
         unsafe { strcmp(s1, s2) }
     }
@@ -796,22 +882,10 @@ impl AsanRuntime {
             fn strnlen(s: *const c_char, n: usize) -> usize;
         }
         if !(self.shadow_check_func().unwrap())(s1 as *const c_void, unsafe { strnlen(s1, n) }) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "strncmp".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                s1 as usize,
-                n,
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("strncmp", s1 as usize, n);
         }
         if !(self.shadow_check_func().unwrap())(s2 as *const c_void, unsafe { strnlen(s2, n) }) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "strncmp".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                s2 as usize,
-                n,
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("strncmp", s2 as usize, n);
         }
         unsafe { strncmp(s1, s2, n) }
     }
@@ -822,6 +896,8 @@ impl AsanRuntime {
             fn strcpy(dest: *mut c_char, src: *const c_char) -> *mut c_char;
             fn strlen(s: *const c_char) -> usize;
         }
+        self.allocator_mut()
+            .mark_initialized(dest as usize, unsafe { strlen(src) } + 1);
         if !(self.shadow_check_func().unwrap())(dest as *const c_void, unsafe { strlen(src) }) {
             AsanErrors::get_mut().report_error(AsanError::BadFuncArgWrite((
                 "strcpy".to_string(),
@@ -832,40 +908,48 @@ impl AsanRuntime {
             )));
         }
         if !(self.shadow_check_func().unwrap())(src as *const c_void, unsafe { strlen(src) }) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "strcpy".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                src as usize,
-                unsafe { strlen(src) },
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("strcpy", src as usize, unsafe { strlen(src) });
         }
         unsafe { strcpy(dest, src) }
     }
 
+    /// Checks that the allocation `dest` points into can hold `n` bytes.
+    /// Pointers outside of our allocations can't be checked, and always pass.
+    #[inline]
+    #[must_use]
+    pub fn hook_check_strncpy_dest(&self, dest: *mut c_char, n: usize) -> bool {
+        self.allocator()
+            .remaining_size(dest as usize)
+            .map_or(true, |remaining| remaining >= n)
+    }
+
     #[inline]
     pub fn hook_strncpy(&mut self, dest: *mut c_char, src: *const c_char, n: usize) -> *mut c_char {
         extern "C" {
             fn strncpy(dest: *mut c_char, src: *const c_char, n: usize) -> *mut c_char;
         }
-        if !(self.shadow_check_func().unwrap())(dest as *const c_void, n) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgWrite((
+        self.allocator_mut().mark_initialized(dest as usize, n);
+        // the destination is checked only once, an overflow of the allocation fails the shadow check as well
+        if !self.hook_check_strncpy_dest(dest, n) {
+            AsanErrors::get_mut().report_error(AsanError::StrncpyDestOverflow((
                 "strncpy".to_string(),
                 self.real_address_for_stalked(AsanRuntime::pc()),
                 dest as usize,
                 n,
                 Backtrace::new(),
             )));
-        }
-        if !(self.shadow_check_func().unwrap())(src as *const c_void, n) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
+        } else if !(self.shadow_check_func().unwrap())(dest as *const c_void, n) {
+            AsanErrors::get_mut().report_error(AsanError::BadFuncArgWrite((
                 "strncpy".to_string(),
                 self.real_address_for_stalked(AsanRuntime::pc()),
-                src as usize,
+                dest as usize,
                 n,
                 Backtrace::new(),
             )));
         }
+        if !(self.shadow_check_func().unwrap())(src as *const c_void, n) {
+            self.report_bad_func_arg_read("strncpy", src as usize, n);
+        }
         unsafe { strncpy(dest, src, n) }
     }
 
@@ -875,6 +959,8 @@ impl AsanRuntime {
             fn stpcpy(dest: *mut c_char, src: *const c_char) -> *mut c_char;
             fn strlen(s: *const c_char) -> usize;
         }
+        self.allocator_mut()
+            .mark_initialized(dest as usize, unsafe { strlen(src) } + 1);
         if !(self.shadow_check_func().unwrap())(dest as *const c_void, unsafe { strlen(src) }) {
             AsanErrors::get_mut().report_error(AsanError::BadFuncArgWrite((
                 "stpcpy".to_string(),
@@ -885,13 +971,7 @@ impl AsanRuntime {
             )));
         }
         if !(self.shadow_check_func().unwrap())(src as *const c_void, unsafe { strlen(src) }) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "stpcpy".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                src as usize,
-                unsafe { strlen(src) },
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("stpcpy", src as usize, unsafe { strlen(src) });
         }
         unsafe { stpcpy(dest, src) }
     }
@@ -904,13 +984,7 @@ impl AsanRuntime {
         }
         let size = unsafe { strlen(s) };
         if !(self.shadow_check_func().unwrap())(s as *const c_void, size) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "strdup".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                s as usize,
-                unsafe { strlen(s) },
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("strdup", s as usize, unsafe { strlen(s) });
         }
 
         unsafe {
@@ -927,13 +1001,7 @@ impl AsanRuntime {
         }
         let size = unsafe { strlen(s) };
         if !(self.shadow_check_func().unwrap())(s as *const c_void, size) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "strlen".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                s as usize,
-                size,
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("strlen", s as usize, size);
         }
         size
     }
@@ -945,17 +1013,151 @@ impl AsanRuntime {
         }
         let size = unsafe { strnlen(s, n) };
         if !(self.shadow_check_func().unwrap())(s as *const c_void, size) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "strnlen".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                s as usize,
-                size,
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("strnlen", s as usize, size);
         }
         size
     }
 
+    /// Checks the bytes of `nptr` actually read by one of the `strto*` functions: up to `end`,
+    /// where parsing stopped, including the byte it stopped at.
+    /// If nothing was parsed, `end` is `nptr`, and only its first byte is known to be read.
+    /// Checks the destination buffer a hooked function writes its output to.
+    /// In msan mode, a buffer within a live allocation is valid even if it was never written to yet,
+    /// the bytes the function actually writes are marked as initialized after it returns.
+    fn check_func_arg_dest(&self, dest: *mut c_void, size: usize) -> bool {
+        (self.shadow_check_func().unwrap())(dest, size)
+            || (self.allocator().msan_mode()
+                && self
+                    .allocator()
+                    .remaining_size(dest as usize)
+                    .is_some_and(|remaining| remaining >= size))
+    }
+
+    /// Reports a hooked function reading `size` bytes at `address` that failed the shadow check.
+    /// A read of allocated memory that was never written to, within a live allocation, is reported as
+    /// [`AsanError::UninitializedMemoryRead`], anything else as [`AsanError::BadFuncArgRead`].
+    fn report_bad_func_arg_read(&mut self, name: &str, address: usize, size: usize) {
+        let pc = self.real_address_for_stalked(AsanRuntime::pc());
+        let uninitialized = self.allocator().is_uninitialized(address, size)
+            && self
+                .allocator()
+                .remaining_size(address)
+                .is_some_and(|remaining| remaining >= size);
+        if uninitialized {
+            if let Some(metadata) = self.allocator_mut().find_metadata(address, address) {
+                let metadata = metadata.clone();
+                AsanErrors::get_mut().report_error(AsanError::UninitializedMemoryRead(
+                    AsanReadWriteError {
+                        registers: [0; ASAN_SAVE_REGISTER_COUNT],
+                        pc,
+                        fault: (None, None, 0, address),
+                        metadata,
+                        backtrace: Backtrace::new(),
+                    },
+                ));
+                return;
+            }
+        }
+        AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
+            name.to_string(),
+            pc,
+            address,
+            size,
+            Backtrace::new(),
+        )));
+    }
+
+    fn check_strto_nptr(&mut self, name: &str, nptr: *const c_char, end: *const c_char) {
+        let size = end as usize - nptr as usize + 1;
+        if !(self.shadow_check_func().unwrap())(nptr as *const c_void, size) {
+            self.report_bad_func_arg_read(name, nptr as usize, size);
+        }
+    }
+
+    #[inline]
+    pub fn hook_strtol(
+        &mut self,
+        nptr: *const c_char,
+        endptr: *mut *mut c_char,
+        base: c_int,
+    ) -> c_long {
+        extern "C" {
+            fn strtol(nptr: *const c_char, endptr: *mut *mut c_char, base: c_int) -> c_long;
+        }
+        let mut end = core::ptr::null_mut();
+        let res = unsafe { strtol(nptr, &mut end, base) };
+        self.check_strto_nptr("strtol", nptr, end);
+        if !endptr.is_null() {
+            unsafe {
+                *endptr = end;
+            }
+        }
+        res
+    }
+
+    #[inline]
+    pub fn hook_strtoll(
+        &mut self,
+        nptr: *const c_char,
+        endptr: *mut *mut c_char,
+        base: c_int,
+    ) -> c_longlong {
+        extern "C" {
+            fn strtoll(nptr: *const c_char, endptr: *mut *mut c_char, base: c_int) -> c_longlong;
+        }
+        let mut end = core::ptr::null_mut();
+        let res = unsafe { strtoll(nptr, &mut end, base) };
+        self.check_strto_nptr("strtoll", nptr, end);
+        if !endptr.is_null() {
+            unsafe {
+                *endptr = end;
+            }
+        }
+        res
+    }
+
+    #[inline]
+    pub fn hook_strtoul(
+        &mut self,
+        nptr: *const c_char,
+        endptr: *mut *mut c_char,
+        base: c_int,
+    ) -> c_ulong {
+        extern "C" {
+            fn strtoul(nptr: *const c_char, endptr: *mut *mut c_char, base: c_int) -> c_ulong;
+        }
+        let mut end = core::ptr::null_mut();
+        let res = unsafe { strtoul(nptr, &mut end, base) };
+        self.check_strto_nptr("strtoul", nptr, end);
+        if !endptr.is_null() {
+            unsafe {
+                *endptr = end;
+            }
+        }
+        res
+    }
+
+    #[inline]
+    pub fn hook_strtoull(
+        &mut self,
+        nptr: *const c_char,
+        endptr: *mut *mut c_char,
+        base: c_int,
+    ) -> c_ulonglong {
+        extern "C" {
+            fn strtoull(nptr: *const c_char, endptr: *mut *mut c_char, base: c_int) -> c_ulonglong;
+        }
+        let mut end = core::ptr::null_mut();
+        let res = unsafe { strtoull(nptr, &mut end, base) };
+        self.check_strto_nptr("strtoull", nptr, end);
+        if !endptr.is_null() {
+            unsafe {
+                *endptr = end;
+            }
+        }
+        res
+    }
+
     #[inline]
     pub fn hook_strstr(&mut self, haystack: *const c_char, needle: *const c_char) -> *mut c_char {
         extern "C" {
@@ -965,23 +1167,11 @@ impl AsanRuntime {
         if !(self.shadow_check_func().unwrap())(haystack as *const c_void, unsafe {
             strlen(haystack)
         }) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "strstr".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                haystack as usize,
-                unsafe { strlen(haystack) },
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("strstr", haystack as usize, unsafe { strlen(haystack) });
         }
         if !(self.shadow_check_func().unwrap())(needle as *const c_void, unsafe { strlen(needle) })
         {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "strstr".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                needle as usize,
-                unsafe { strlen(needle) },
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("strstr", needle as usize, unsafe { strlen(needle) });
         }
         unsafe { strstr(haystack, needle) }
     }
@@ -999,23 +1189,13 @@ impl AsanRuntime {
         if !(self.shadow_check_func().unwrap())(haystack as *const c_void, unsafe {
             strlen(haystack)
         }) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "strcasestr".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                haystack as usize,
-                unsafe { strlen(haystack) },
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("strcasestr", haystack as usize, unsafe {
+                strlen(haystack)
+            });
         }
         if !(self.shadow_check_func().unwrap())(needle as *const c_void, unsafe { strlen(needle) })
         {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "strcasestr".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                needle as usize,
-                unsafe { strlen(needle) },
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("strcasestr", needle as usize, unsafe { strlen(needle) });
         }
         unsafe { strcasestr(haystack, needle) }
     }
@@ -1027,13 +1207,7 @@ impl AsanRuntime {
             fn strlen(s: *const c_char) -> usize;
         }
         if !(self.shadow_check_func().unwrap())(s as *const c_void, unsafe { strlen(s) }) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "atoi".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                s as usize,
-                unsafe { strlen(s) },
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("atoi", s as usize, unsafe { strlen(s) });
         }
         unsafe { atoi(s) }
     }
@@ -1046,13 +1220,7 @@ impl AsanRuntime {
             fn strlen(s: *const c_char) -> usize;
         }
         if !(self.shadow_check_func().unwrap())(s as *const c_void, unsafe { strlen(s) }) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "atol".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                s as usize,
-                unsafe { strlen(s) },
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("atol", s as usize, unsafe { strlen(s) });
         }
         unsafe { atol(s) }
     }
@@ -1065,13 +1233,7 @@ impl AsanRuntime {
             fn strlen(s: *const c_char) -> usize;
         }
         if !(self.shadow_check_func().unwrap())(s as *const c_void, unsafe { strlen(s) }) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "atoll".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                s as usize,
-                unsafe { strlen(s) },
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("atoll", s as usize, unsafe { strlen(s) });
         }
         unsafe { atoll(s) }
     }
@@ -1084,13 +1246,7 @@ impl AsanRuntime {
         }
         let size = unsafe { wcslen(s) };
         if !(self.shadow_check_func().unwrap())(s as *const c_void, (size + 1) * 2) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "wcslen".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                s as usize,
-                (size + 1) * 2,
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("wcslen", s as usize, (size + 1) * 2);
         }
         size
     }
@@ -1116,13 +1272,7 @@ impl AsanRuntime {
         if !(self.shadow_check_func().unwrap())(src as *const c_void, unsafe {
             (wcslen(src) + 1) * 2
         }) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "wcscpy".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                src as usize,
-                (unsafe { wcslen(src) } + 1) * 2,
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("wcscpy", src as usize, (unsafe { wcslen(src) } + 1) * 2);
         }
         unsafe { wcscpy(dest, src) }
     }
@@ -1137,24 +1287,12 @@ impl AsanRuntime {
         if !(self.shadow_check_func().unwrap())(s1 as *const c_void, unsafe {
             (wcslen(s1) + 1) * 2
         }) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "wcscmp".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                s1 as usize,
-                (unsafe { wcslen(s1) } + 1) * 2,
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("wcscmp", s1 as usize, (unsafe { wcslen(s1) } + 1) * 2);
         }
         if !(self.shadow_check_func().unwrap())(s2 as *const c_void, unsafe {
             (wcslen(s2) + 1) * 2
         }) {
-            AsanErrors::get_mut().report_error(AsanError::BadFuncArgRead((
-                "wcscmp".to_string(),
-                self.real_address_for_stalked(AsanRuntime::pc()),
-                s2 as usize,
-                (unsafe { wcslen(s2) } + 1) * 2,
-                Backtrace::new(),
-            )));
+            self.report_bad_func_arg_read("wcscmp", s2 as usize, (unsafe { wcslen(s2) } + 1) * 2);
         }
         unsafe { wcscmp(s1, s2) }
     }
@@ -1165,6 +1303,7 @@ impl AsanRuntime {
         extern "C" {
             fn memset_pattern4(s: *mut c_void, p4: *const c_void, n: usize);
         }
+        self.allocator_mut().mark_initialized(s as usize, n);
         if !(self.shadow_check_func().unwrap())(s, n) {
             AsanErrors::get_mut().report_error(AsanError::BadFuncArgWrite((
                 "memset_pattern4".to_string(),
@@ -1192,6 +1331,7 @@ impl AsanRuntime {
         extern "C" {
             fn memset_pattern8(s: *mut c_void, p8: *const c_void, n: usize);
         }
+        self.allocator_mut().mark_initialized(s as usize, n);
         if !(self.shadow_check_func().unwrap())(s, n) {
             AsanErrors::get_mut().report_error(AsanError::BadFuncArgWrite((
                 "memset_pattern8".to_string(),
@@ -1219,6 +1359,7 @@ impl AsanRuntime {
         extern "C" {
             fn memset_pattern16(s: *mut c_void, p16: *const c_void, n: usize);
         }
+        self.allocator_mut().mark_initialized(s as usize, n);
         if !(self.shadow_check_func().unwrap())(s, n) {
             AsanErrors::get_mut().report_error(AsanError::BadFuncArgWrite((
                 "memset_pattern16".to_string(),
@@ -0,0 +1,326 @@
+//! Feedback on the memory leaked by an execution, as found by the leak check of the [`crate::asan::asan_rt::AsanRuntime`].
+//!
+//! With `detect_leaks` enabled, the runtime sums up the sizes of the allocations not freed by the end of each run.
+//! The [`MemoryLeakObserver`] records this sum, the [`MemoryLeakFeedback`] keeps the inputs leaking more than
+//! any input before, and the [`LeakSizeBucketFeedback`] the inputs whose leak falls into a new power-of-two size bucket.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use libafl::{
+    events::EventFirer,
+    executors::ExitKind,
+    feedbacks::{Feedback, HasObserverName},
+    inputs::UsesInput,
+    observers::{Observer, ObserversTuple},
+    state::{HasNamedMetadata, State},
+    Error,
+};
+use libafl_bolts::Named;
+use serde::{Deserialize, Serialize};
+
+/// The bytes leaked by the last execution, set by the leak check of the runtime
+static LEAKED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// Sets the number of bytes leaked by the current execution
+pub(crate) fn set_leaked_bytes(leaked_bytes: usize) {
+    LEAKED_BYTES.store(leaked_bytes, Ordering::Relaxed);
+}
+
+/// The size bucket of a leak of `leaked_bytes`: `0` for no leak, else the number of bits of the size
+#[must_use]
+pub fn leak_size_bucket(leaked_bytes: usize) -> u32 {
+    usize::BITS - leaked_bytes.leading_zeros()
+}
+
+/// Holds the number of bytes leaked by the last execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryLeakObserver {
+    name: String,
+    leaked_bytes: usize,
+}
+
+impl MemoryLeakObserver {
+    /// Creates a new [`MemoryLeakObserver`]
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            leaked_bytes: 0,
+        }
+    }
+
+    /// The number of bytes leaked by the last execution
+    #[must_use]
+    pub fn leaked_bytes(&self) -> usize {
+        self.leaked_bytes
+    }
+}
+
+impl<S> Observer<S> for MemoryLeakObserver
+where
+    S: UsesInput,
+{
+    fn pre_exec(&mut self, _state: &mut S, _input: &S::Input) -> Result<(), Error> {
+        self.leaked_bytes = 0;
+        set_leaked_bytes(0);
+        Ok(())
+    }
+
+    fn post_exec(
+        &mut self,
+        _state: &mut S,
+        _input: &S::Input,
+        _exit_kind: &ExitKind,
+    ) -> Result<(), Error> {
+        self.leaked_bytes = LEAKED_BYTES.load(Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+impl Named for MemoryLeakObserver {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// The prefix of the name of the metadata of a [`MemoryLeakFeedback`], followed by the name of its observer
+pub const MEMORYLEAKFEEDBACK_PREFIX: &str = "memoryleakfeedback_metadata_";
+
+/// The largest leak seen by a [`MemoryLeakFeedback`]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[allow(clippy::unsafe_derive_deserialize)]
+pub struct MemoryLeakMetadata {
+    /// The number of bytes leaked by the leakiest input so far
+    pub max_leaked_bytes: usize,
+}
+
+libafl_bolts::impl_serdeany!(MemoryLeakMetadata);
+
+/// Considers an input interesting if it leaked more bytes than any input before
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryLeakFeedback {
+    name: String,
+    observer_name: String,
+}
+
+impl MemoryLeakFeedback {
+    /// Creates a new [`MemoryLeakFeedback`], reading the leaked bytes from the given observer
+    #[must_use]
+    pub fn new(observer: &MemoryLeakObserver) -> Self {
+        Self {
+            name: MEMORYLEAKFEEDBACK_PREFIX.to_string() + observer.name(),
+            observer_name: observer.name().to_string(),
+        }
+    }
+}
+
+impl<S> Feedback<S> for MemoryLeakFeedback
+where
+    S: State + HasNamedMetadata,
+{
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error> {
+        state.add_named_metadata(MemoryLeakMetadata::default(), &self.name);
+        Ok(())
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    fn is_interesting<EM, OT>(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _input: &S::Input,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .match_name::<MemoryLeakObserver>(&self.observer_name)
+            .expect("A MemoryLeakFeedback needs a MemoryLeakObserver");
+
+        let meta = state
+            .named_metadata_map_mut()
+            .get_mut::<MemoryLeakMetadata>(&self.name)
+            .unwrap();
+        if observer.leaked_bytes() > meta.max_leaked_bytes {
+            meta.max_leaked_bytes = observer.leaked_bytes();
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+}
+
+impl Named for MemoryLeakFeedback {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl HasObserverName for MemoryLeakFeedback {
+    fn observer_name(&self) -> &str {
+        &self.observer_name
+    }
+}
+
+/// The prefix of the name of the metadata of a [`LeakSizeBucketFeedback`], followed by the name of its observer
+pub const LEAKSIZEBUCKETFEEDBACK_PREFIX: &str = "leaksizebucketfeedback_metadata_";
+
+/// The leak size buckets seen by a [`LeakSizeBucketFeedback`]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[allow(clippy::unsafe_derive_deserialize)]
+pub struct LeakSizeBucketMetadata {
+    /// Bit `n` is set if a leak of bucket `n` was seen, see [`leak_size_bucket`]
+    pub buckets: u128,
+}
+
+libafl_bolts::impl_serdeany!(LeakSizeBucketMetadata);
+
+/// Considers an input interesting if its leak falls into a size bucket never seen before,
+/// the buckets being the powers of two, e.g. the first leak of more than 1MB
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeakSizeBucketFeedback {
+    name: String,
+    observer_name: String,
+}
+
+impl LeakSizeBucketFeedback {
+    /// Creates a new [`LeakSizeBucketFeedback`], reading the leaked bytes from the given observer
+    #[must_use]
+    pub fn new(observer: &MemoryLeakObserver) -> Self {
+        Self {
+            name: LEAKSIZEBUCKETFEEDBACK_PREFIX.to_string() + observer.name(),
+            observer_name: observer.name().to_string(),
+        }
+    }
+}
+
+impl<S> Feedback<S> for LeakSizeBucketFeedback
+where
+    S: State + HasNamedMetadata,
+{
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error> {
+        state.add_named_metadata(LeakSizeBucketMetadata::default(), &self.name);
+        Ok(())
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    fn is_interesting<EM, OT>(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _input: &S::Input,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .match_name::<MemoryLeakObserver>(&self.observer_name)
+            .expect("A LeakSizeBucketFeedback needs a MemoryLeakObserver");
+        if observer.leaked_bytes() == 0 {
+            return Ok(false);
+        }
+
+        let meta = state
+            .named_metadata_map_mut()
+            .get_mut::<LeakSizeBucketMetadata>(&self.name)
+            .unwrap();
+        let bit = 1 << leak_size_bucket(observer.leaked_bytes());
+        let is_new = meta.buckets & bit == 0;
+        meta.buckets |= bit;
+        Ok(is_new)
+    }
+}
+
+impl Named for LeakSizeBucketFeedback {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl HasObserverName for LeakSizeBucketFeedback {
+    fn observer_name(&self) -> &str {
+        &self.observer_name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl::{
+        corpus::InMemoryCorpus,
+        events::NopEventManager,
+        executors::ExitKind,
+        feedbacks::{ConstFeedback, Feedback},
+        inputs::BytesInput,
+        observers::Observer,
+        state::StdState,
+    };
+    use libafl_bolts::{rands::StdRand, tuples::tuple_list};
+
+    use super::{
+        leak_size_bucket, set_leaked_bytes, LeakSizeBucketFeedback, MemoryLeakFeedback,
+        MemoryLeakObserver,
+    };
+
+    #[test]
+    fn test_leak_feedbacks() {
+        assert_eq!(leak_size_bucket(0), 0);
+        assert_eq!(leak_size_bucket(1), 1);
+        assert_eq!(leak_size_bucket(0x1000), 13);
+
+        let mut observer = MemoryLeakObserver::new("leaks");
+        let mut max_feedback = MemoryLeakFeedback::new(&observer);
+        let mut bucket_feedback = LeakSizeBucketFeedback::new(&observer);
+
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        max_feedback.init_state(&mut state).unwrap();
+        bucket_feedback.init_state(&mut state).unwrap();
+        let mut mgr = NopEventManager::new();
+        let input = BytesInput::new(vec![]);
+
+        // (leaked bytes, interesting to the max feedback, interesting to the bucket feedback)
+        for (leaked_bytes, new_max, new_bucket) in [
+            (0, false, false),
+            (0x10, true, true),
+            (0x11, true, false),
+            (0x8, false, true),
+            (0x11, false, false),
+        ] {
+            observer.pre_exec(&mut state, &input).unwrap();
+            set_leaked_bytes(leaked_bytes);
+            observer
+                .post_exec(&mut state, &input, &ExitKind::Ok)
+                .unwrap();
+            assert_eq!(observer.leaked_bytes(), leaked_bytes);
+
+            let observers = tuple_list!(observer);
+            assert_eq!(
+                max_feedback
+                    .is_interesting(&mut state, &mut mgr, &input, &observers, &ExitKind::Ok)
+                    .unwrap(),
+                new_max
+            );
+            assert_eq!(
+                bucket_feedback
+                    .is_interesting(&mut state, &mut mgr, &input, &observers, &ExitKind::Ok)
+                    .unwrap(),
+                new_bucket
+            );
+            (observer, ()) = observers;
+        }
+    }
+}
@@ -1,5 +1,15 @@
 //! Address sanitization using [`frida`](https://frida.re/)
+//!
+//! The runtime is only available on unix, where it replaces the libc allocator.
+//! The Windows heap functions, such as `RtlAllocateHeap` and `RtlFreeHeap`, are not intercepted,
+//! so allocations on any heap handle, the process heap or private ones, are not tracked.
+pub mod access_log;
 pub mod asan_rt;
 pub mod errors;
 #[allow(missing_docs)]
 pub mod hook_funcs;
+pub mod leaks;
+pub mod report;
+#[cfg(target_arch = "x86_64")]
+pub mod shadow_stack;
+pub mod valgrind;
@@ -0,0 +1,238 @@
+//! HTML reports of the errors found by the [`AsanRuntime`](crate::asan::asan_rt::AsanRuntime), for triaging many findings.
+//!
+//! Each error reported to [`AsanErrors`](crate::asan::errors::AsanErrors) is rendered into its own file,
+//! color-coded by the kind of the error, with the registers and the backtrace in expandable sections.
+use core::{
+    fmt::Write,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use backtrace::Backtrace;
+use libafl::Error;
+use libafl_bolts::current_time;
+
+#[cfg(target_arch = "x86_64")]
+use crate::asan::asan_rt::ASAN_SAVE_REGISTER_NAMES;
+use crate::asan::{asan_rt::ASAN_SAVE_REGISTER_COUNT, errors::AsanError};
+
+/// The template of a report, with `{{placeholders}}` for the finding
+const REPORT_TEMPLATE: &str = include_str!("report.html");
+
+/// The number of reports written by this process, keeping the names of reports within the same millisecond apart
+static REPORT_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// How bad an [`AsanError`] is, deciding the color of its report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AsanErrorSeverity {
+    /// Use-after-free and double-free, usually exploitable (red)
+    UseAfterFree,
+    /// Out-of-bounds accesses (orange)
+    OutOfBounds,
+    /// Reads of uninitialized memory (yellow)
+    Uninitialized,
+    /// Anything else, like leaks (gray)
+    Other,
+}
+
+impl AsanErrorSeverity {
+    /// The color of reports of this severity, as CSS color
+    #[must_use]
+    pub fn color(self) -> &'static str {
+        match self {
+            AsanErrorSeverity::UseAfterFree => "#d32f2f",
+            AsanErrorSeverity::OutOfBounds => "#f57c00",
+            AsanErrorSeverity::Uninitialized => "#fbc02d",
+            AsanErrorSeverity::Other => "#757575",
+        }
+    }
+
+    fn of(error: &AsanError) -> Self {
+        match error {
+            AsanError::ReadAfterFree(_)
+            | AsanError::WriteAfterFree(_)
+            | AsanError::DoubleFree(_) => AsanErrorSeverity::UseAfterFree,
+            AsanError::OobRead(_)
+            | AsanError::OobWrite(_)
+            | AsanError::StackOobRead(_)
+            | AsanError::StackOobWrite(_)
+            | AsanError::BadFuncArgRead(_)
+            | AsanError::BadFuncArgWrite(_)
+            | AsanError::StrncpyDestOverflow(_)
+            | AsanError::SnprintfDestTooSmall(_) => AsanErrorSeverity::OutOfBounds,
+            AsanError::UninitializedMemoryRead(_) => AsanErrorSeverity::Uninitialized,
+            AsanError::UnallocatedFree(_)
+            | AsanError::Unknown(_)
+            | AsanError::Leak(_)
+            | AsanError::SnprintfTruncated(_)
+            | AsanError::ShadowStackCorruption(_) => AsanErrorSeverity::Other,
+        }
+    }
+}
+
+/// Writes an HTML report for each `ASan` error into a directory,
+/// named `<timestamp>_<pid>_<counter>_<error type>.html`, so that the reports of several threads or fuzzer clients never clash
+#[derive(Debug, Clone)]
+pub struct AsanHtmlReporter {
+    dir: PathBuf,
+}
+
+impl AsanHtmlReporter {
+    /// Creates a new [`AsanHtmlReporter`], writing into `dir`, which is created if needed
+    pub fn new<P: AsRef<Path>>(dir: P) -> Result<Self, Error> {
+        fs::create_dir_all(dir.as_ref())?;
+        Ok(Self {
+            dir: dir.as_ref().to_path_buf(),
+        })
+    }
+
+    /// The directory the reports are written to
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Writes the report of `error`, returning its path
+    pub(crate) fn report(&self, error: &AsanError) -> Result<PathBuf, Error> {
+        let description = error.description();
+        let path = self.dir.join(format!(
+            "{}_{}_{}_{}.html",
+            current_time().as_millis(),
+            std::process::id(),
+            REPORT_COUNT.fetch_add(1, Ordering::Relaxed),
+            description.replace(|c: char| !c.is_ascii_alphanumeric(), "_")
+        ));
+        fs::write(&path, render(error))?;
+        Ok(path)
+    }
+}
+
+/// Renders the report of `error` into the template
+fn render(error: &AsanError) -> String {
+    let (summary, registers, backtrace) = match error {
+        AsanError::OobRead(error)
+        | AsanError::OobWrite(error)
+        | AsanError::ReadAfterFree(error)
+        | AsanError::WriteAfterFree(error)
+        | AsanError::UninitializedMemoryRead(error) => (
+            format!(
+                "at {:#x}, faulting address {:#x}, in the {:#x} byte allocation at {:#x}",
+                error.pc,
+                error.fault.3,
+                error.metadata.size,
+                error.metadata.address + 0x1000
+            ),
+            Some(render_registers(&error.registers, error.pc)),
+            Some(&error.backtrace),
+        ),
+        AsanError::Unknown((registers, pc, fault, backtrace))
+        | AsanError::StackOobRead((registers, pc, fault, backtrace))
+        | AsanError::StackOobWrite((registers, pc, fault, backtrace)) => (
+            format!("at {pc:#x}, faulting address {:#x}", fault.3),
+            Some(render_registers(registers, *pc)),
+            Some(backtrace),
+        ),
+        AsanError::BadFuncArgRead((name, pc, address, size, backtrace))
+        | AsanError::BadFuncArgWrite((name, pc, address, size, backtrace))
+        | AsanError::StrncpyDestOverflow((name, pc, address, size, backtrace))
+        | AsanError::SnprintfDestTooSmall((name, pc, address, size, backtrace))
+        | AsanError::SnprintfTruncated((name, pc, address, size, backtrace)) => (
+            format!("in call to {name} at {pc:#x}, argument {address:#x}, size {size:#x}"),
+            None,
+            Some(backtrace),
+        ),
+        AsanError::DoubleFree((ptr, metadata, backtrace)) => (
+            format!(
+                "of {ptr:#x}, in the {:#x} byte allocation at {:#x}",
+                metadata.size,
+                metadata.address + 0x1000
+            ),
+            None,
+            Some(backtrace),
+        ),
+        AsanError::UnallocatedFree((ptr, backtrace)) => {
+            (format!("of {ptr:#x}"), None, Some(backtrace))
+        }
+        AsanError::ShadowStackCorruption((pc, expected, actual, backtrace)) => (
+            format!("at {pc:#x}, returning to {actual:#x} instead of {expected:#x}"),
+            None,
+            Some(backtrace),
+        ),
+        AsanError::Leak((ptr, metadata)) => (
+            format!("of {ptr:#x}, with size {:#x}", metadata.size),
+            None,
+            metadata.allocation_site_backtrace.as_ref(),
+        ),
+    };
+
+    let backtrace = backtrace.map_or_else(
+        || "no backtrace".to_string(),
+        |backtrace| {
+            let mut backtrace = backtrace.clone();
+            backtrace.resolve();
+            format!("{backtrace:?}")
+        },
+    );
+    REPORT_TEMPLATE
+        .replace("{{title}}", &escape(error.description()))
+        .replace("{{color}}", AsanErrorSeverity::of(error).color())
+        .replace("{{summary}}", &escape(&summary))
+        .replace(
+            "{{registers}}",
+            &escape(registers.as_deref().unwrap_or("no registers")),
+        )
+        .replace("{{backtrace}}", &escape(&backtrace))
+}
+
+/// One line per four registers, like the console report
+fn render_registers(registers: &[usize; ASAN_SAVE_REGISTER_COUNT], pc: usize) -> String {
+    let mut out = String::new();
+    for (reg, val) in registers.iter().enumerate() {
+        #[cfg(target_arch = "x86_64")]
+        let name = ASAN_SAVE_REGISTER_NAMES[reg].to_string();
+        #[cfg(not(target_arch = "x86_64"))]
+        let name = format!("x{reg:02}");
+        write!(out, "{name}: {val:#018x} ").unwrap();
+        if reg % 4 == 3 {
+            out.push('\n');
+        }
+    }
+    writeln!(out, "\npc: {pc:#018x}").unwrap();
+    out
+}
+
+/// Escapes `text` for the HTML body, or any XML
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use backtrace::Backtrace;
+
+    use super::{render, AsanErrorSeverity};
+    use crate::asan::errors::AsanError;
+
+    #[test]
+    fn test_html_report() {
+        let error = AsanError::UnallocatedFree((0x1337, Backtrace::new_unresolved()));
+        let html = render(&error);
+        assert!(html.contains("<title>unallocated-free</title>"));
+        assert!(html.contains(AsanErrorSeverity::Other.color()));
+        assert!(html.contains("of 0x1337"));
+        assert!(!html.contains("{{"));
+    }
+}
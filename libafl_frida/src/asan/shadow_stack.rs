@@ -0,0 +1,97 @@
+//! A software shadow stack, detecting returns to addresses no instrumented `call` pushed, as done by ROP chains.
+//!
+//! A callout before each `call` of the instrumented code pushes its return address onto a per-thread shadow stack,
+//! and a callout before each `ret` compares the return address on the real stack with the top of the shadow stack.
+//! All calls are tracked, not only the indirect ones: the returns of the direct calls have to match as well.
+//!
+//! Not every mismatch is an attack, so some are tolerated:
+//! - a return to an address deeper in the shadow stack unwinds to it, like a `longjmp` or an exception does,
+//! - returns to code that is not instrumented, e.g. of callbacks invoked by libc, are not checked,
+//!   as the matching `call` was not tracked. A ROP chain returning only into gadgets of such code is not detected.
+//!
+//! Other mismatches are reported as [`AsanError::ShadowStackCorruption`]. Only `x86_64` is supported.
+use core::cell::RefCell;
+use std::sync::OnceLock;
+
+use backtrace::Backtrace;
+use frida_gum::stalker::Instruction;
+use rangemap::RangeSet;
+use yaxpeax_x86::amd64::{InstDecoder, Opcode};
+
+use crate::{
+    asan::errors::{AsanError, AsanErrors},
+    utils::frida_to_cs,
+};
+
+thread_local! {
+    /// The return addresses of the tracked calls of this thread, innermost last
+    static SHADOW_STACK: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The instrumented code, the returns into it are checked
+static INSTRUMENTED_RANGES: OnceLock<RangeSet<u64>> = OnceLock::new();
+
+/// Sets the ranges of the instrumented code, only the first call has an effect
+pub(crate) fn set_instrumented_ranges<I>(ranges: I)
+where
+    I: IntoIterator<Item = core::ops::Range<u64>>,
+{
+    let _ = INSTRUMENTED_RANGES.set(ranges.into_iter().collect());
+}
+
+/// Empties the shadow stack of this thread, e.g. before each execution
+pub(crate) fn clear_shadow_stack() {
+    SHADOW_STACK.with(|stack| stack.borrow_mut().clear());
+}
+
+/// Pushes the return address of a call
+fn on_call(return_address: u64) {
+    SHADOW_STACK.with(|stack| stack.borrow_mut().push(return_address));
+}
+
+/// Checks the return address of the `ret` at `pc` against the shadow stack, and pops it
+#[allow(clippy::cast_possible_truncation)]
+fn on_ret(pc: u64, return_address: u64) {
+    let expected = SHADOW_STACK.with(|stack| {
+        let mut stack = stack.borrow_mut();
+        match stack.iter().rposition(|addr| *addr == return_address) {
+            Some(depth) => {
+                // unwinds the frames skipped by a `longjmp` or an exception
+                stack.truncate(depth);
+                None
+            }
+            None => stack.last().copied(),
+        }
+    });
+    let Some(expected) = expected else {
+        // matched, or nothing tracked yet, e.g. when returning from the harness
+        return;
+    };
+    let instrumented = INSTRUMENTED_RANGES
+        .get()
+        .map_or(false, |ranges| ranges.contains(&return_address));
+    if instrumented {
+        AsanErrors::get_mut().report_error(AsanError::ShadowStackCorruption((
+            pc as usize,
+            expected as usize,
+            return_address as usize,
+            Backtrace::new(),
+        )));
+    }
+}
+
+/// Puts a callout before `instruction` if it is a `call` or a `ret`, maintaining the shadow stack
+pub(crate) fn put_shadow_stack_callout(instruction: &Instruction, decoder: InstDecoder) {
+    let instr = instruction.instr();
+    let pc = instr.address();
+    let next_pc = pc + instr.bytes().len() as u64;
+    match frida_to_cs(decoder, instr).opcode() {
+        Opcode::CALL => instruction.put_callout(move |_context| on_call(next_pc)),
+        Opcode::RETURN => instruction.put_callout(move |context| {
+            // the callout runs before the `ret`, so the return address is still on top of the stack
+            let return_address = unsafe { (context.rsp() as *const u64).read_unaligned() };
+            on_ret(pc, return_address);
+        }),
+        _ => (),
+    }
+}
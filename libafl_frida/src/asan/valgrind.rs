@@ -0,0 +1,323 @@
+//! Valgrind `memcheck` compatible XML reports of the errors found by the [`AsanRuntime`](crate::asan::asan_rt::AsanRuntime).
+//!
+//! Tools parsing the output of `valgrind --xml=yes` can import the findings without changes.
+//! The report is kept a complete XML document after each error, so it stays readable if the target crashes.
+use core::fmt::Write as _;
+use std::{
+    fs::{File, OpenOptions},
+    io::{Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    process,
+};
+
+use backtrace::Backtrace;
+use frida_gum::ModuleDetails;
+use libafl::Error;
+
+use crate::asan::{errors::AsanError, report::escape};
+
+/// The end of the report, overwritten by each new error
+const FOOTER: &str = "</valgrindoutput>\n";
+
+/// The Valgrind error kind of an [`AsanError`], the `<kind>` of its report
+#[must_use]
+pub fn valgrind_kind(error: &AsanError) -> &'static str {
+    match error {
+        AsanError::OobRead(_)
+        | AsanError::ReadAfterFree(_)
+        | AsanError::StackOobRead(_)
+        | AsanError::BadFuncArgRead(_)
+        // memcheck has no kind for unattributed faults, these are mostly reads
+        | AsanError::Unknown(_) => "InvalidRead",
+        AsanError::OobWrite(_)
+        | AsanError::WriteAfterFree(_)
+        | AsanError::StackOobWrite(_)
+        | AsanError::BadFuncArgWrite(_)
+        | AsanError::StrncpyDestOverflow(_)
+        | AsanError::SnprintfDestTooSmall(_) => "InvalidWrite",
+        // not memory errors, reported like failed client checks
+        AsanError::SnprintfTruncated(_) | AsanError::ShadowStackCorruption(_) => "ClientCheck",
+        AsanError::DoubleFree(_) | AsanError::UnallocatedFree(_) => "InvalidFree",
+        AsanError::UninitializedMemoryRead(_) => "UninitValue",
+        AsanError::Leak(_) => "Leak_DefinitelyLost",
+    }
+}
+
+/// Writes all `ASan` errors into a single XML file, in the format of `valgrind --tool=memcheck --xml=yes`
+#[derive(Debug, Clone)]
+pub struct ValgrindXmlReporter {
+    path: PathBuf,
+    /// The offset of the [`FOOTER`] in the file
+    end: u64,
+    /// The number of errors reported, for their `<unique>` ids
+    count: usize,
+}
+
+impl ValgrindXmlReporter {
+    /// Creates a new [`ValgrindXmlReporter`], truncating the file at `path` and writing the preamble into it
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let mut header = String::new();
+        writeln!(header, "<?xml version=\"1.0\"?>\n").unwrap();
+        writeln!(header, "<valgrindoutput>\n").unwrap();
+        writeln!(header, "<protocolversion>4</protocolversion>").unwrap();
+        writeln!(header, "<protocoltool>memcheck</protocoltool>\n").unwrap();
+        writeln!(header, "<preamble>").unwrap();
+        writeln!(
+            header,
+            "  <line>Memcheck compatible report of the libafl_frida address sanitizer</line>"
+        )
+        .unwrap();
+        writeln!(header, "</preamble>\n").unwrap();
+        writeln!(header, "<pid>{}</pid>", process::id()).unwrap();
+        writeln!(header, "<tool>memcheck</tool>\n").unwrap();
+
+        let mut file = File::create(path.as_ref())?;
+        file.write_all(header.as_bytes())?;
+        file.write_all(FOOTER.as_bytes())?;
+        Ok(Self {
+            path: path.as_ref().to_path_buf(),
+            end: header.len() as u64,
+            count: 0,
+        })
+    }
+
+    /// The path of the report
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends `error` to the report
+    pub(crate) fn report(&mut self, error: &AsanError) -> Result<(), Error> {
+        let xml = render(error, self.count);
+        let mut file = OpenOptions::new().write(true).open(&self.path)?;
+        file.seek(SeekFrom::Start(self.end))?;
+        file.write_all(xml.as_bytes())?;
+        file.write_all(FOOTER.as_bytes())?;
+        self.end += xml.len() as u64;
+        self.count += 1;
+        Ok(())
+    }
+}
+
+/// Renders `error` into an `<error>` element
+fn render(error: &AsanError, unique: usize) -> String {
+    let mut out = String::new();
+    writeln!(out, "<error>").unwrap();
+    writeln!(out, "  <unique>{unique:#x}</unique>").unwrap();
+    writeln!(out, "  <tid>1</tid>").unwrap();
+    writeln!(out, "  <kind>{}</kind>", valgrind_kind(error)).unwrap();
+
+    match error {
+        AsanError::OobRead(rw_error)
+        | AsanError::OobWrite(rw_error)
+        | AsanError::ReadAfterFree(rw_error)
+        | AsanError::WriteAfterFree(rw_error)
+        | AsanError::UninitializedMemoryRead(rw_error) => {
+            let fault_address = rw_error.fault.3;
+            let block = rw_error.metadata.address + 0x1000;
+            let size = rw_error.metadata.size;
+            write_what(
+                &mut out,
+                &format!(
+                    "{} at {:#x}, address {fault_address:#x}",
+                    error.description(),
+                    rw_error.pc
+                ),
+            );
+            write_stack(&mut out, Some(&rw_error.backtrace));
+            let state = if rw_error.metadata.freed {
+                "free'd"
+            } else {
+                "alloc'd"
+            };
+            let aux = if fault_address < block {
+                format!(
+                    "Address {fault_address:#x} is {} bytes before a block of size {size} {state}",
+                    block - fault_address
+                )
+            } else if fault_address >= block + size {
+                format!(
+                    "Address {fault_address:#x} is {} bytes after a block of size {size} {state}",
+                    fault_address - (block + size)
+                )
+            } else {
+                format!(
+                    "Address {fault_address:#x} is {} bytes inside a block of size {size} {state}",
+                    fault_address - block
+                )
+            };
+            write_aux(
+                &mut out,
+                &aux,
+                rw_error.metadata.allocation_site_backtrace.as_ref(),
+            );
+        }
+        AsanError::Unknown((_, pc, fault, backtrace))
+        | AsanError::StackOobRead((_, pc, fault, backtrace))
+        | AsanError::StackOobWrite((_, pc, fault, backtrace)) => {
+            write_what(
+                &mut out,
+                &format!("{} at {pc:#x}, address {:#x}", error.description(), fault.3),
+            );
+            write_stack(&mut out, Some(backtrace));
+        }
+        AsanError::BadFuncArgRead((name, pc, address, size, backtrace))
+        | AsanError::BadFuncArgWrite((name, pc, address, size, backtrace))
+        | AsanError::StrncpyDestOverflow((name, pc, address, size, backtrace))
+        | AsanError::SnprintfDestTooSmall((name, pc, address, size, backtrace))
+        | AsanError::SnprintfTruncated((name, pc, address, size, backtrace)) => {
+            write_what(
+                &mut out,
+                &format!(
+                    "{} of size {size} in call to {name} at {pc:#x}, address {address:#x}",
+                    error.description()
+                ),
+            );
+            write_stack(&mut out, Some(backtrace));
+        }
+        AsanError::DoubleFree((ptr, metadata, backtrace)) => {
+            write_what(&mut out, &format!("Invalid free() of {ptr:#x}"));
+            write_stack(&mut out, Some(backtrace));
+            write_aux(
+                &mut out,
+                &format!(
+                    "Address {ptr:#x} is 0 bytes inside a block of size {} free'd",
+                    metadata.size
+                ),
+                metadata.release_site_backtrace.as_ref(),
+            );
+        }
+        AsanError::UnallocatedFree((ptr, backtrace)) => {
+            write_what(&mut out, &format!("Invalid free() of {ptr:#x}"));
+            write_stack(&mut out, Some(backtrace));
+        }
+        AsanError::ShadowStackCorruption((pc, expected, actual, backtrace)) => {
+            write_what(
+                &mut out,
+                &format!(
+                    "{} at {pc:#x}, returning to {actual:#x} instead of {expected:#x}",
+                    error.description()
+                ),
+            );
+            write_stack(&mut out, Some(backtrace));
+        }
+        AsanError::Leak((ptr, metadata)) => {
+            writeln!(out, "  <xwhat>").unwrap();
+            writeln!(
+                out,
+                "    <text>{} bytes in 1 blocks are definitely lost, at {ptr:#x}</text>",
+                metadata.size
+            )
+            .unwrap();
+            writeln!(out, "    <leakedbytes>{}</leakedbytes>", metadata.size).unwrap();
+            writeln!(out, "    <leakedblocks>1</leakedblocks>").unwrap();
+            writeln!(out, "  </xwhat>").unwrap();
+            write_stack(&mut out, metadata.allocation_site_backtrace.as_ref());
+        }
+    }
+
+    writeln!(out, "</error>\n").unwrap();
+    out
+}
+
+/// Writes the `<what>` of an error
+fn write_what(out: &mut String, what: &str) {
+    writeln!(out, "  <what>{}</what>", escape(what)).unwrap();
+}
+
+/// Writes an `<auxwhat>` and the stack it refers to
+fn write_aux(out: &mut String, aux: &str, backtrace: Option<&Backtrace>) {
+    writeln!(out, "  <auxwhat>{}</auxwhat>", escape(aux)).unwrap();
+    if backtrace.is_some() {
+        write_stack(out, backtrace);
+    }
+}
+
+/// Writes the `<stack>` of `backtrace`, a `<frame>` for each symbol, with function names and source locations if known
+fn write_stack(out: &mut String, backtrace: Option<&Backtrace>) {
+    writeln!(out, "  <stack>").unwrap();
+    if let Some(backtrace) = backtrace {
+        let mut backtrace = backtrace.clone();
+        backtrace.resolve();
+        for frame in backtrace.frames() {
+            let ip = frame.ip() as usize;
+            let obj = ModuleDetails::with_address(ip as u64).map(|module| module.path());
+            let symbols = frame.symbols();
+            if symbols.is_empty() {
+                write_frame(out, ip, obj.as_deref(), None, None, None);
+                continue;
+            }
+            for symbol in symbols {
+                let name = symbol.name().map(|name| name.to_string());
+                if matches!(&name, Some(name) if name.starts_with("libafl_frida::asan")) {
+                    continue;
+                }
+                write_frame(
+                    out,
+                    ip,
+                    obj.as_deref(),
+                    name.as_deref(),
+                    symbol.filename(),
+                    symbol.lineno(),
+                );
+            }
+        }
+    }
+    writeln!(out, "  </stack>").unwrap();
+}
+
+/// Writes a single `<frame>`
+fn write_frame(
+    out: &mut String,
+    ip: usize,
+    obj: Option<&str>,
+    function: Option<&str>,
+    file: Option<&Path>,
+    line: Option<u32>,
+) {
+    writeln!(out, "    <frame>").unwrap();
+    writeln!(out, "      <ip>0x{ip:X}</ip>").unwrap();
+    if let Some(obj) = obj {
+        writeln!(out, "      <obj>{}</obj>", escape(obj)).unwrap();
+    }
+    if let Some(function) = function {
+        writeln!(out, "      <fn>{}</fn>", escape(function)).unwrap();
+    }
+    if let Some(file) = file {
+        if let Some(dir) = file.parent() {
+            writeln!(out, "      <dir>{}</dir>", escape(&dir.to_string_lossy())).unwrap();
+        }
+        if let Some(name) = file.file_name() {
+            writeln!(
+                out,
+                "      <file>{}</file>",
+                escape(&name.to_string_lossy())
+            )
+            .unwrap();
+        }
+    }
+    if let Some(line) = line {
+        writeln!(out, "      <line>{line}</line>").unwrap();
+    }
+    writeln!(out, "    </frame>").unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use backtrace::Backtrace;
+
+    use super::render;
+    use crate::asan::errors::AsanError;
+
+    #[test]
+    fn test_valgrind_xml_report() {
+        let error = AsanError::UnallocatedFree((0x1337, Backtrace::from(vec![])));
+        let xml = render(&error, 3);
+        assert!(xml.starts_with("<error>"));
+        assert!(xml.contains("<unique>0x3</unique>"));
+        assert!(xml.contains("<kind>InvalidFree</kind>"));
+        assert!(xml.contains("<what>Invalid free() of 0x1337</what>"));
+        assert!(xml.contains("<stack>"));
+    }
+}
@@ -0,0 +1,191 @@
+//! Coverage of the single bytes compared by SIMD equality instructions.
+//!
+//! A `pcmpeqb` comparing 16 bytes at once is a single edge for the [`crate::coverage_rt::CoverageRuntime`],
+//! so SIMD-optimized parsers (`memcmp`, `strlen`, JSON scanners, ...) give no feedback for partially matching inputs.
+//! The [`CmpCoverageRuntime`] splits each comparison into its byte lanes, with a map slot per lane:
+//! each input matching more bytes at the same instruction covers more slots.
+use std::{cell::RefCell, marker::PhantomPinned, pin::Pin, rc::Rc};
+
+use dynasmrt::{dynasm, DynasmApi, DynasmLabelApi};
+use frida_gum::{instruction_writer::InstructionWriter, stalker::StalkerOutput, ModuleMap};
+use frida_gum_sys::Insn;
+use libafl_bolts::hash_std;
+use rangemap::RangeMap;
+use yaxpeax_x86::amd64::{register_class, InstDecoder, Opcode, Operand};
+
+use crate::{helper::FridaRuntime, utils::frida_to_cs};
+
+/// (Default) map size for SIMD comparison coverage
+pub const CMP_COVERAGE_MAP_SIZE: usize = 64 * 1024;
+
+/// The most byte lanes of a supported comparison (`ymm` registers)
+const MAX_LANES: usize = 32;
+
+/// A SIMD equality comparison, found by [`CmpCoverageRuntime::simd_cmp`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimdCmp {
+    /// The number of the destination register, holding the result of the comparison
+    pub reg: u8,
+    /// If the destination is a `ymm` register (32 lanes) instead of an `xmm` register (16 lanes)
+    pub ymm: bool,
+}
+
+#[derive(Debug)]
+struct CmpCoverageRuntimeInner {
+    map: [u8; CMP_COVERAGE_MAP_SIZE],
+    _pinned: PhantomPinned,
+}
+
+/// Frida runtime for the coverage of SIMD comparisons, splitting them into byte comparisons
+///
+/// Supports the SSE2 and AVX2 equality comparisons (`pcmpeq*`, `vpcmpeq*`) into `xmm` and `ymm` registers.
+/// The AVX-512 forms comparing into mask registers are not instrumented.
+/// Its map is separate from the one of the [`crate::coverage_rt::CoverageRuntime`],
+/// observe it with its own map observer, see [`CmpCoverageRuntime::map_mut_ptr`].
+#[derive(Debug)]
+pub struct CmpCoverageRuntime(Pin<Rc<RefCell<CmpCoverageRuntimeInner>>>);
+
+impl Default for CmpCoverageRuntime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FridaRuntime for CmpCoverageRuntime {
+    /// Initialize the SIMD comparison coverage runtime
+    /// The struct MUST NOT be moved after this function is called, as the generated assembly references it
+    fn init(
+        &mut self,
+        _gum: &frida_gum::Gum,
+        _ranges: &RangeMap<usize, (u16, String)>,
+        _module_map: &Rc<ModuleMap>,
+    ) {
+    }
+
+    fn pre_exec<I: libafl::inputs::Input + libafl::inputs::HasTargetBytes>(
+        &mut self,
+        _input: &I,
+    ) -> Result<(), libafl::Error> {
+        self.0.borrow_mut().map.fill(0);
+        Ok(())
+    }
+
+    fn post_exec<I: libafl::inputs::Input + libafl::inputs::HasTargetBytes>(
+        &mut self,
+        _input: &I,
+    ) -> Result<(), libafl::Error> {
+        Ok(())
+    }
+}
+
+impl CmpCoverageRuntime {
+    /// Create a new SIMD comparison coverage runtime
+    #[must_use]
+    pub fn new() -> Self {
+        Self(Rc::pin(RefCell::new(CmpCoverageRuntimeInner {
+            map: [0_u8; CMP_COVERAGE_MAP_SIZE],
+            _pinned: PhantomPinned,
+        })))
+    }
+
+    /// Retrieve the coverage map pointer
+    pub fn map_mut_ptr(&mut self) -> *mut u8 {
+        self.0.borrow_mut().map.as_mut_ptr()
+    }
+
+    /// Check if the instruction is a supported SIMD equality comparison
+    #[must_use]
+    pub fn simd_cmp(decoder: InstDecoder, instr: &Insn) -> Option<SimdCmp> {
+        let cs_instr = frida_to_cs(decoder, instr);
+        match cs_instr.opcode() {
+            Opcode::PCMPEQB
+            | Opcode::PCMPEQW
+            | Opcode::PCMPEQD
+            | Opcode::PCMPEQQ
+            | Opcode::VPCMPEQB
+            | Opcode::VPCMPEQW
+            | Opcode::VPCMPEQD
+            | Opcode::VPCMPEQQ => (),
+            _ => return None,
+        }
+
+        // The result is a mask of the equal lanes in the destination
+        match cs_instr.operand(0) {
+            Operand::Register(reg) if reg.class() == register_class::X => Some(SimdCmp {
+                reg: reg.num(),
+                ymm: false,
+            }),
+            Operand::Register(reg) if reg.class() == register_class::Y => Some(SimdCmp {
+                reg: reg.num(),
+                ymm: true,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Write inline instrumentation, bumping the slot of each equal byte lane.
+    /// It has to run right after the comparison, while the destination still holds the result.
+    #[allow(clippy::cast_possible_wrap)]
+    fn generate_inline_code(slots: *mut u8, cmp: SimdCmp) -> Box<[u8]> {
+        let mut ops = dynasmrt::VecAssembler::<dynasmrt::x64::X64Relocation>::new(0);
+        dynasm!(ops
+            ;   .arch x64
+            // Store the context, below the red zone, including all flags, as the `add`/`sbb` clobber OF as well
+            ; lea    rsp, [rsp-0x80]
+            ; pushfq
+            ; push   rax
+            ; push   rcx
+            ; push   rdx
+        );
+
+        // One bit per byte lane, set if the lane was equal
+        if cmp.ymm {
+            dynasm!(ops
+                ;   .arch x64
+                ; vpmovmskb eax, Ry(cmp.reg)
+            );
+        } else {
+            dynasm!(ops
+                ;   .arch x64
+                ; pmovmskb eax, Rx(cmp.reg)
+            );
+        }
+
+        dynasm!(ops
+            ;   .arch x64
+            ; mov rdx, QWORD slots as _
+            ; test eax, eax
+            ; jz >done
+            ; next:
+            // Take the lowest equal lane, and saturate its slot at 255
+            ; bsf ecx, eax
+            ; btr eax, ecx
+            ; add BYTE [rdx + rcx], 1
+            ; sbb BYTE [rdx + rcx], 0
+            ; test eax, eax
+            ; jnz <next
+            ; done:
+
+            // Restore the context
+            ; pop    rdx
+            ; pop    rcx
+            ; pop    rax
+            ; popfq
+            ; lea    rsp, [rsp+0x80]
+        );
+        let ops_vec = ops.finalize().unwrap();
+
+        ops_vec[..ops_vec.len()].to_vec().into_boxed_slice()
+    }
+
+    /// Emits the instrumentation of the comparison at `address`, after it was kept in the current basic block.
+    #[inline]
+    pub fn emit_simd_cmp_coverage(&mut self, address: u64, cmp: SimdCmp, output: &StalkerOutput) {
+        // The lanes of the comparison get consecutive slots, starting at the hash of its address
+        let offset =
+            (hash_std(&address.to_le_bytes()) as usize) % (CMP_COVERAGE_MAP_SIZE - MAX_LANES);
+        let slots = unsafe { self.0.borrow_mut().map.as_mut_ptr().add(offset) };
+        let code = Self::generate_inline_code(slots, cmp);
+        output.writer().put_bytes(&code);
+    }
+}
@@ -6,6 +6,7 @@ use std::{cell::RefCell, marker::PhantomPinned, pin::Pin, rc::Rc};
 use dynasmrt::DynasmLabelApi;
 use dynasmrt::{dynasm, DynasmApi};
 use frida_gum::{instruction_writer::InstructionWriter, stalker::StalkerOutput, ModuleMap};
+use libafl::observers::StdMapObserver;
 use libafl_bolts::hash_std;
 use rangemap::RangeMap;
 
@@ -18,6 +19,7 @@ pub const MAP_SIZE: usize = 64 * 1024;
 struct CoverageRuntimeInner {
     map: [u8; MAP_SIZE],
     previous_pc: u64,
+    accumulate: bool,
     _pinned: PhantomPinned,
 }
 
@@ -46,6 +48,9 @@ impl FridaRuntime for CoverageRuntime {
         &mut self,
         _input: &I,
     ) -> Result<(), libafl::Error> {
+        if !self.accumulate() {
+            self.reset_coverage_map();
+        }
         Ok(())
     }
 
@@ -64,6 +69,7 @@ impl CoverageRuntime {
         Self(Rc::pin(RefCell::new(CoverageRuntimeInner {
             map: [0_u8; MAP_SIZE],
             previous_pc: 0,
+            accumulate: false,
             _pinned: PhantomPinned,
         })))
     }
@@ -73,6 +79,43 @@ impl CoverageRuntime {
         self.0.borrow_mut().map.as_mut_ptr()
     }
 
+    /// Creates a [`StdMapObserver`] over the coverage map.
+    ///
+    /// The observer is differential, i.e. it never resets the map itself: the runtime resets it in
+    /// [`FridaRuntime::pre_exec`], unless [`CoverageRuntime::accumulate`] is set.
+    /// A plain [`StdMapObserver`] over [`CoverageRuntime::map_mut_ptr`] would reset the map anyway.
+    ///
+    /// # Safety
+    /// The observer keeps a pointer to the map, so this runtime must outlive it.
+    #[must_use]
+    pub unsafe fn map_observer<S>(&mut self, name: S) -> StdMapObserver<'static, u8, true>
+    where
+        S: Into<String>,
+    {
+        StdMapObserver::differential_from_mut_ptr(name, self.map_mut_ptr(), MAP_SIZE)
+    }
+
+    /// Zeroes the coverage map and resets the previous location used for edge hashing.
+    /// Called from [`FridaRuntime::pre_exec`] unless [`CoverageRuntime::accumulate`] is set.
+    pub fn reset_coverage_map(&mut self) {
+        let mut borrow = self.0.borrow_mut();
+        borrow.map.fill(0);
+        borrow.previous_pc = 0;
+    }
+
+    /// Returns `true` if coverage is kept across executions instead of being reset in `pre_exec`
+    #[must_use]
+    pub fn accumulate(&self) -> bool {
+        self.0.borrow().accumulate
+    }
+
+    /// Sets whether coverage should accumulate across executions (`false` by default).
+    /// Useful to get a summary of the whole campaign, but the feedback can no longer tell
+    /// which execution produced new coverage.
+    pub fn set_accumulate(&mut self, accumulate: bool) {
+        self.0.borrow_mut().accumulate = accumulate;
+    }
+
     /// A minimal `maybe_log` implementation. We insert this into the transformed instruction stream
     /// every time we need a copy that is within a direct branch of the start of the transformed basic
     /// block.
@@ -213,3 +256,52 @@ impl CoverageRuntime {
         writer.put_bytes(&code);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use libafl::{
+        executors::ExitKind,
+        inputs::BytesInput,
+        observers::{MapObserver, Observer},
+        state::NopState,
+    };
+
+    use super::CoverageRuntime;
+    use crate::helper::FridaRuntime;
+
+    /// Runs the runtime and the observer hooks around an execution hitting the `edge`
+    fn run(
+        runtime: &mut CoverageRuntime,
+        observer: &mut impl Observer<NopState<BytesInput>>,
+        edge: usize,
+    ) {
+        let mut state = NopState::<BytesInput>::new();
+        let input = BytesInput::new(vec![]);
+        runtime.pre_exec(&input).unwrap();
+        observer.pre_exec(&mut state, &input).unwrap();
+        // what the instrumented code would do
+        unsafe { *runtime.map_mut_ptr().add(edge) += 1 };
+        observer
+            .post_exec(&mut state, &input, &ExitKind::Ok)
+            .unwrap();
+        runtime.post_exec(&input).unwrap();
+    }
+
+    #[test]
+    fn test_coverage_accumulate() {
+        let mut runtime = CoverageRuntime::new();
+        runtime.set_accumulate(true);
+        let mut observer = unsafe { runtime.map_observer("edges") };
+
+        run(&mut runtime, &mut observer, 1);
+        run(&mut runtime, &mut observer, 2);
+        assert_eq!(*observer.get(1), 1);
+        assert_eq!(*observer.get(2), 1);
+        assert_eq!(observer.count_bytes(), 2);
+
+        runtime.set_accumulate(false);
+        run(&mut runtime, &mut observer, 3);
+        assert_eq!(observer.count_bytes(), 1);
+        assert_eq!(*observer.get(3), 1);
+    }
+}
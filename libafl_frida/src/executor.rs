@@ -20,7 +20,7 @@ use libafl::{
 
 #[cfg(not(test))]
 #[cfg(unix)]
-use crate::asan::errors::ASAN_ERRORS;
+use crate::asan::errors::AsanErrors;
 use crate::helper::{FridaInstrumentationHelper, FridaRuntimeTuple};
 #[cfg(windows)]
 use crate::windows_hooks::initialize;
@@ -107,9 +107,13 @@ where
         #[cfg(not(test))]
         #[cfg(unix)]
         unsafe {
-            if ASAN_ERRORS.is_some() && !ASAN_ERRORS.as_ref().unwrap().is_empty() {
-                log::error!("Crashing target as it had ASAN errors");
-                libc::raise(libc::SIGABRT);
+            // the target may have reported errors on threads it spawned
+            AsanErrors::join_into_thread_local();
+            if let Some(errors) = (*AsanErrors::thread_local_ptr()).as_ref() {
+                if !errors.is_empty() {
+                    log::error!("Crashing target as it had ASAN errors");
+                    libc::raise(libc::SIGABRT);
+                }
             }
         }
         self.helper.post_exec(input)?;
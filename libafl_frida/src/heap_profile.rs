@@ -0,0 +1,295 @@
+//! Tracking the shape of the heap, i.e. how many chunks of each size are live, for heap shape feedback.
+//!
+//! Some bugs (heap sprays, size-class confusion) only show up with a specific distribution of allocation sizes.
+//! The [`HeapProfileObserver`] records it for each execution, and the [`NewHeapShapeFeedback`]
+//! considers inputs leading to a new distribution interesting.
+
+use core::{
+    hash::{BuildHasher, Hasher},
+    ptr::addr_of_mut,
+};
+use std::{collections::BTreeMap, sync::Once};
+
+use frida_gum::{
+    interceptor::{Interceptor, InvocationContext, InvocationListener},
+    Gum, Module,
+};
+use hashbrown::{HashMap, HashSet};
+use libafl::{
+    events::EventFirer,
+    executors::ExitKind,
+    feedbacks::{Feedback, HasObserverName},
+    inputs::UsesInput,
+    observers::{Observer, ObserversTuple},
+    state::{HasNamedMetadata, State},
+    Error,
+};
+use libafl_bolts::Named;
+use serde::{Deserialize, Serialize};
+
+/// The maximum number of distinct sizes in a heap profile; chunks of further sizes are ignored
+pub const MAX_HEAP_PROFILE_SIZES: usize = 256;
+
+/// The chunks allocated during the current execution
+#[derive(Debug, Default)]
+struct HeapProfile {
+    /// If an execution is running, only then allocations are recorded
+    active: bool,
+    /// size -> number of live chunks
+    sizes: BTreeMap<usize, usize>,
+    /// address -> size of the live chunks
+    live: HashMap<usize, usize>,
+}
+
+impl HeapProfile {
+    fn reset(&mut self, active: bool) {
+        self.active = active;
+        self.sizes.clear();
+        self.live.clear();
+    }
+
+    fn record_malloc(&mut self, ptr: usize, size: usize) {
+        if !self.active || ptr == 0 {
+            return;
+        }
+        if let Some(count) = self.sizes.get_mut(&size) {
+            *count += 1;
+        } else if self.sizes.len() < MAX_HEAP_PROFILE_SIZES {
+            self.sizes.insert(size, 1);
+        } else {
+            return;
+        }
+        self.live.insert(ptr, size);
+    }
+
+    fn record_free(&mut self, ptr: usize) {
+        let Some(size) = self.live.remove(&ptr) else {
+            return;
+        };
+        if let Some(count) = self.sizes.get_mut(&size) {
+            *count -= 1;
+            if *count == 0 {
+                self.sizes.remove(&size);
+            }
+        }
+    }
+}
+
+/// The heap profile of the current execution, filled by the `malloc` and `free` hooks
+static mut HEAP_PROFILE: Option<HeapProfile> = None;
+
+/// Ensures `malloc` and `free` only get hooked once
+static HEAP_PROFILE_HOOKS: Once = Once::new();
+
+fn heap_profile() -> &'static mut HeapProfile {
+    unsafe { (*addr_of_mut!(HEAP_PROFILE)).get_or_insert_with(HeapProfile::default) }
+}
+
+/// Remembers the size passed to `malloc`, to record it along with the returned chunk
+struct MallocListener {
+    size: usize,
+}
+
+impl InvocationListener for MallocListener {
+    fn on_enter(&mut self, context: InvocationContext) {
+        self.size = context.arg(0);
+    }
+
+    fn on_leave(&mut self, context: InvocationContext) {
+        heap_profile().record_malloc(context.return_value(), self.size);
+    }
+}
+
+struct FreeListener;
+
+impl InvocationListener for FreeListener {
+    fn on_enter(&mut self, context: InvocationContext) {
+        heap_profile().record_free(context.arg(0));
+    }
+
+    fn on_leave(&mut self, _context: InvocationContext) {}
+}
+
+/// An observer recording the shape of the heap at the end of each execution:
+/// the number of chunks of each size, allocated during the execution with `malloc` and not freed since.
+///
+/// At most [`MAX_HEAP_PROFILE_SIZES`] distinct sizes are recorded.
+/// The hooks are process-wide, so this assumes the target runs single-threaded, in-process.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeapProfileObserver {
+    name: String,
+    fingerprint: Vec<(usize, usize)>,
+}
+
+impl HeapProfileObserver {
+    /// Creates a new [`HeapProfileObserver`], hooking `malloc` and `free` if that did not happen yet
+    #[must_use]
+    pub fn new(name: &str, gum: &Gum) -> Self {
+        HEAP_PROFILE_HOOKS.call_once(|| {
+            let mut interceptor = Interceptor::obtain(gum);
+            if let Some(malloc) = Module::find_export_by_name(None, "malloc") {
+                let listener = Box::leak(Box::new(MallocListener { size: 0 }));
+                interceptor.attach(malloc, listener).ok();
+            }
+            if let Some(free) = Module::find_export_by_name(None, "free") {
+                let listener = Box::leak(Box::new(FreeListener));
+                interceptor.attach(free, listener).ok();
+            }
+        });
+        Self {
+            name: name.to_string(),
+            fingerprint: vec![],
+        }
+    }
+
+    /// The heap shape at the end of the last execution, as (size, number of chunks) sorted by size
+    #[must_use]
+    pub fn fingerprint(&self) -> &[(usize, usize)] {
+        &self.fingerprint
+    }
+}
+
+impl<S> Observer<S> for HeapProfileObserver
+where
+    S: UsesInput,
+{
+    fn pre_exec(&mut self, _state: &mut S, _input: &S::Input) -> Result<(), Error> {
+        self.fingerprint.clear();
+        heap_profile().reset(true);
+        Ok(())
+    }
+
+    fn post_exec(
+        &mut self,
+        _state: &mut S,
+        _input: &S::Input,
+        _exit_kind: &ExitKind,
+    ) -> Result<(), Error> {
+        let profile = heap_profile();
+        profile.active = false;
+        self.fingerprint = profile
+            .sizes
+            .iter()
+            .map(|(size, count)| (*size, *count))
+            .collect();
+        profile.reset(false);
+        Ok(())
+    }
+}
+
+impl Named for HeapProfileObserver {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// The prefix of the [`NewHeapShapeMetadata`] names
+pub const NEWHEAPSHAPEFEEDBACK_PREFIX: &str = "newheapshapefeedback_metadata_";
+
+/// The hashes of all heap shapes seen by a [`NewHeapShapeFeedback`]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[allow(clippy::unsafe_derive_deserialize)]
+pub struct NewHeapShapeMetadata {
+    /// The hashes of the fingerprints
+    pub shapes: HashSet<u64>,
+}
+
+libafl_bolts::impl_serdeany!(NewHeapShapeMetadata);
+
+/// Considers an input interesting if the heap shape at the end of its execution was never seen before
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewHeapShapeFeedback {
+    name: String,
+    observer_name: String,
+}
+
+impl NewHeapShapeFeedback {
+    /// Creates a new [`NewHeapShapeFeedback`], using the fingerprints of the given [`HeapProfileObserver`]
+    #[must_use]
+    pub fn new(observer: &HeapProfileObserver) -> Self {
+        Self {
+            name: NEWHEAPSHAPEFEEDBACK_PREFIX.to_string() + observer.name(),
+            observer_name: observer.name().to_string(),
+        }
+    }
+}
+
+impl<S> Feedback<S> for NewHeapShapeFeedback
+where
+    S: State + HasNamedMetadata,
+{
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error> {
+        state.add_named_metadata(NewHeapShapeMetadata::default(), &self.name);
+        Ok(())
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    fn is_interesting<EM, OT>(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _input: &S::Input,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .match_name::<HeapProfileObserver>(&self.observer_name)
+            .expect("A NewHeapShapeFeedback needs a HeapProfileObserver");
+
+        let mut hasher = ahash::RandomState::with_seeds(0, 0, 0, 0).build_hasher();
+        for (size, count) in observer.fingerprint() {
+            hasher.write_usize(*size);
+            hasher.write_usize(*count);
+        }
+        let hash = hasher.finish();
+
+        let meta = state
+            .named_metadata_map_mut()
+            .get_mut::<NewHeapShapeMetadata>(&self.name)
+            .unwrap();
+        Ok(meta.shapes.insert(hash))
+    }
+}
+
+impl Named for NewHeapShapeFeedback {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl HasObserverName for NewHeapShapeFeedback {
+    fn observer_name(&self) -> &str {
+        &self.observer_name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HeapProfile, MAX_HEAP_PROFILE_SIZES};
+
+    #[test]
+    fn test_heap_profile() {
+        let mut profile = HeapProfile::default();
+        profile.record_malloc(0x1000, 16);
+        assert!(profile.sizes.is_empty());
+
+        profile.reset(true);
+        profile.record_malloc(0x1000, 16);
+        profile.record_malloc(0x2000, 16);
+        profile.record_malloc(0x3000, 32);
+        profile.record_free(0x1000);
+        profile.record_free(0x3000);
+        // not ours
+        profile.record_free(0x4000);
+        assert_eq!(profile.sizes.iter().collect::<Vec<_>>(), [(&16, &1)]);
+
+        for size in 0..2 * MAX_HEAP_PROFILE_SIZES {
+            profile.record_malloc(0x10000 + size, size);
+        }
+        assert_eq!(profile.sizes.len(), MAX_HEAP_PROFILE_SIZES);
+    }
+}
@@ -14,6 +14,7 @@ use frida_gum::{
 };
 use libafl::{
     inputs::{HasTargetBytes, Input},
+    observers::StdMapObserver,
     Error,
 };
 use libafl_bolts::{cli::FuzzerOptions, tuples::MatchFirstType};
@@ -30,6 +31,10 @@ use yaxpeax_x86::amd64::InstDecoder;
 
 #[cfg(unix)]
 use crate::asan::asan_rt::AsanRuntime;
+#[cfg(all(target_arch = "x86_64", unix))]
+use crate::asan::{access_log::put_access_log_callout, shadow_stack::put_shadow_stack_callout};
+#[cfg(all(target_arch = "x86_64", unix))]
+use crate::cmp_coverage_rt::CmpCoverageRuntime;
 #[cfg(feature = "cmplog")]
 use crate::cmplog_rt::CmpLogRuntime;
 use crate::{coverage_rt::CoverageRuntime, drcov_rt::DrCovRuntime};
@@ -470,6 +475,8 @@ where
             let instr_size = instr.bytes().len();
             let address = instr.address();
             // log::trace!("block @ {:x} transformed to {:x}", address, output.writer().pc());
+            #[cfg(all(target_arch = "x86_64", unix))]
+            let mut simd_cmp = None;
 
             if ranges.borrow().contains_key(&(address as usize)) {
                 let mut runtimes = (*runtimes).borrow_mut();
@@ -499,12 +506,22 @@ where
                 #[cfg(all(target_arch = "x86_64", unix))]
                 if let Some(details) = res {
                     if let Some(rt) = runtimes.match_first_type_mut::<AsanRuntime>() {
+                        if rt.access_log_enabled() {
+                            put_access_log_callout(&instruction, decoder, details);
+                        }
                         rt.emit_shadow_check(
                             address, output, details.0, details.1, details.2, details.3, details.4,
                         );
                     }
                 }
 
+                #[cfg(all(target_arch = "x86_64", unix))]
+                if let Some(rt) = runtimes.match_first_type_mut::<AsanRuntime>() {
+                    if rt.shadow_stack_enabled() {
+                        put_shadow_stack_callout(&instruction, decoder);
+                    }
+                }
+
                 #[cfg(target_arch = "aarch64")]
                 if let Some((basereg, indexreg, displacement, width, shift)) = res {
                     if let Some(rt) = runtimes.match_first_type_mut::<AsanRuntime>() {
@@ -541,6 +558,14 @@ where
                     }
                 }
 
+                #[cfg(all(target_arch = "x86_64", unix))]
+                if runtimes
+                    .match_first_type_mut::<CmpCoverageRuntime>()
+                    .is_some()
+                {
+                    simd_cmp = CmpCoverageRuntime::simd_cmp(decoder, instr);
+                }
+
                 #[cfg(unix)]
                 if let Some(rt) = runtimes.match_first_type_mut::<AsanRuntime>() {
                     rt.add_stalked_address(
@@ -554,6 +579,17 @@ where
                 }
             }
             instruction.keep();
+
+            // the result of the comparison is only there after it ran
+            #[cfg(all(target_arch = "x86_64", unix))]
+            if let Some(simd_cmp) = simd_cmp {
+                if let Some(rt) = runtimes
+                    .borrow_mut()
+                    .match_first_type_mut::<CmpCoverageRuntime>()
+                {
+                    rt.emit_simd_cmp_coverage(address, simd_cmp, output);
+                }
+            }
         }
         if basic_block_size != 0 {
             if let Some(rt) = runtimes.borrow_mut().match_first_type_mut::<DrCovRuntime>() {
@@ -653,6 +689,20 @@ where
             .map(CoverageRuntime::map_mut_ptr)
     }
 
+    /// A map observer over the coverage map, see [`CoverageRuntime::map_observer`]
+    ///
+    /// # Safety
+    /// The observer keeps a pointer to the map, so this helper must outlive it.
+    pub unsafe fn map_observer<S>(&mut self, name: S) -> Option<StdMapObserver<'static, u8, true>>
+    where
+        S: Into<String>,
+    {
+        (*self.runtimes)
+            .borrow_mut()
+            .match_first_type_mut::<CoverageRuntime>()
+            .map(|runtime| runtime.map_observer(name))
+    }
+
     /// Ranges
     pub fn ranges(&self) -> Ref<RangeMap<usize, (u16, String)>> {
         self.ranges.borrow()
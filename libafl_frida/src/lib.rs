@@ -77,6 +77,9 @@ pub mod windows_hooks;
 
 pub mod coverage_rt;
 
+#[cfg(all(target_arch = "x86_64", unix))]
+pub mod cmp_coverage_rt;
+
 /// Hooking thread lifecycle events. Seems like this is apple-only for now.
 #[cfg(target_vendor = "apple")]
 pub mod pthread_hook;
@@ -90,6 +93,13 @@ pub mod helper;
 
 pub mod drcov_rt;
 
+/// Heap shape feedback
+pub mod heap_profile;
+
+/// TLS leak detection
+#[cfg(all(unix, not(target_os = "ios")))]
+pub mod tls;
+
 /// The frida executor
 pub mod executor;
 
@@ -346,7 +356,7 @@ impl Default for FridaOptions {
 
 #[cfg(test)]
 mod tests {
-    use std::{ptr::addr_of, sync::OnceLock};
+    use std::sync::OnceLock;
 
     use clap::Parser;
     use frida_gum::Gum;
@@ -370,7 +380,7 @@ mod tests {
     use crate::{
         asan::{
             asan_rt::AsanRuntime,
-            errors::{AsanErrorsFeedback, AsanErrorsObserver, ASAN_ERRORS},
+            errors::{AsanErrorsFeedback, AsanErrorsObserver},
         },
         coverage_rt::CoverageRuntime,
         executor::FridaInProcessExecutor,
@@ -391,6 +401,8 @@ mod tests {
             ("malloc_heap_oob_write", 1),
             ("malloc_heap_uaf_write", 1),
             ("malloc_heap_uaf_read", 1),
+            ("malloc_heap_realloc_uaf_read", 1),
+            ("malloc_heap_snprintf_dest_too_small", 1),
         ];
 
         let lib = libloading::Library::new(options.clone().harness.unwrap()).unwrap();
@@ -438,7 +450,7 @@ mod tests {
             let mut fuzzer = StdFuzzer::new(StdScheduler::new(), feedback, objective);
 
             let observers = tuple_list!(
-                AsanErrorsObserver::new(addr_of!(ASAN_ERRORS)) //,
+                AsanErrorsObserver::thread_local() //,
             );
 
             {
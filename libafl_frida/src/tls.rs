@@ -0,0 +1,285 @@
+//! Detecting state leaking between executions through thread-local storage.
+//!
+//! Targets may keep state in TLS, like the `OpenSSL` error queue or `errno`. If an execution leaves it changed,
+//! the next executions start from a different state, making their coverage non-deterministic.
+//! The [`TlsObserver`] snapshots the TLS block of the target module on the fuzzing thread before and after each execution,
+//! and the [`TlsLeakFeedback`] considers inputs interesting if they changed TLS where no input did before.
+//! Only the block of the target module is compared, not the TLS of the fuzzer or other libraries, so it is small.
+//! Finding the TLS block of a module is only supported on Linux.
+
+use core::ops::Range;
+
+use hashbrown::HashSet;
+use libafl::{
+    events::EventFirer,
+    executors::ExitKind,
+    feedbacks::{Feedback, HasObserverName},
+    inputs::UsesInput,
+    observers::{Observer, ObserversTuple},
+    state::{HasNamedMetadata, State},
+    Error,
+};
+use libafl_bolts::Named;
+use serde::{Deserialize, Serialize};
+
+/// Finds the TLS block of the module whose path ends in `module` on the current thread, as address and size.
+/// The main executable has an empty path. Returns `None` if the module has no TLS,
+/// or its block has not been allocated on this thread yet.
+#[cfg(target_os = "linux")]
+fn module_tls_block(module: &str) -> Option<(usize, usize)> {
+    use core::ffi::{c_int, c_void, CStr};
+
+    struct Search<'a> {
+        module: &'a str,
+        block: Option<(usize, usize)>,
+    }
+
+    unsafe extern "C" fn callback(
+        info: *mut libc::dl_phdr_info,
+        _size: usize,
+        data: *mut c_void,
+    ) -> c_int {
+        let info = &*info;
+        let search = &mut *data.cast::<Search>();
+        let name = if info.dlpi_name.is_null() {
+            ""
+        } else {
+            CStr::from_ptr(info.dlpi_name).to_str().unwrap_or_default()
+        };
+        let is_module = if search.module.is_empty() {
+            name.is_empty()
+        } else {
+            name.ends_with(search.module)
+        };
+        if !is_module {
+            return 0;
+        }
+        let phdrs = core::slice::from_raw_parts(info.dlpi_phdr, info.dlpi_phnum.into());
+        if let Some(tls) = phdrs.iter().find(|phdr| phdr.p_type == libc::PT_TLS) {
+            if !info.dlpi_tls_data.is_null() {
+                #[allow(clippy::cast_possible_truncation)]
+                let size = tls.p_memsz as usize;
+                search.block = Some((info.dlpi_tls_data as usize, size));
+            }
+        }
+        1
+    }
+
+    let mut search = Search {
+        module,
+        block: None,
+    };
+    unsafe {
+        libc::dl_iterate_phdr(Some(callback), core::ptr::addr_of_mut!(search).cast());
+    }
+    search.block
+}
+
+#[cfg(not(target_os = "linux"))]
+fn module_tls_block(_module: &str) -> Option<(usize, usize)> {
+    None
+}
+
+/// Compares the TLS block of the target module on the current thread before and after each execution.
+///
+/// The changed bytes are reported as ranges of offsets from the start of the TLS block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsObserver {
+    name: String,
+    /// The name of the target module, or a suffix of its path
+    module: String,
+    continue_on_error: bool,
+    /// The TLS block of the target module on the fuzzing thread, looked up until it is allocated
+    #[serde(skip)]
+    tls: Option<(usize, usize)>,
+    #[serde(skip)]
+    snapshot: Vec<u8>,
+    diff: Vec<Range<usize>>,
+}
+
+impl TlsObserver {
+    /// Creates a new [`TlsObserver`], comparing the TLS block of `module`, the file name of the target library,
+    /// or an empty string for the main executable.
+    /// With `continue_on_error`, as the `ASan` option, the TLS changes of each execution are logged as warnings.
+    #[must_use]
+    pub fn new(name: &str, module: &str, continue_on_error: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            module: module.to_string(),
+            continue_on_error,
+            tls: None,
+            snapshot: Vec::new(),
+            diff: Vec::new(),
+        }
+    }
+
+    /// The ranges of TLS offsets changed by the last execution
+    #[must_use]
+    pub fn diff(&self) -> &[Range<usize>] {
+        &self.diff
+    }
+
+    /// The TLS block of the target module on the current thread, empty if not found (yet)
+    fn tls(&mut self) -> &[u8] {
+        if self.tls.is_none() {
+            self.tls = module_tls_block(&self.module);
+        }
+        let Some((start, len)) = self.tls else {
+            return &[];
+        };
+        // # Safety
+        // The range is the TLS block of the module on this thread, which lives as long as the thread
+        unsafe { core::slice::from_raw_parts(start as *const u8, len) }
+    }
+}
+
+/// The ranges of offsets at which `before` and `after` differ
+fn diff_ranges(before: &[u8], after: &[u8]) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for (offset, (old, new)) in before.iter().zip(after).enumerate() {
+        if old == new {
+            continue;
+        }
+        match ranges.last_mut() {
+            Some(range) if range.end == offset => range.end += 1,
+            _ => ranges.push(offset..offset + 1),
+        }
+    }
+    ranges
+}
+
+impl<S> Observer<S> for TlsObserver
+where
+    S: UsesInput,
+{
+    fn pre_exec(&mut self, _state: &mut S, _input: &S::Input) -> Result<(), Error> {
+        self.diff.clear();
+        let mut snapshot = core::mem::take(&mut self.snapshot);
+        snapshot.clear();
+        snapshot.extend_from_slice(self.tls());
+        self.snapshot = snapshot;
+        Ok(())
+    }
+
+    fn post_exec(
+        &mut self,
+        _state: &mut S,
+        _input: &S::Input,
+        _exit_kind: &ExitKind,
+    ) -> Result<(), Error> {
+        let snapshot = core::mem::take(&mut self.snapshot);
+        self.diff = diff_ranges(&snapshot, self.tls());
+        self.snapshot = snapshot;
+        if self.continue_on_error && !self.diff.is_empty() {
+            log::warn!(
+                "The execution left the TLS changed at offsets {:x?}",
+                self.diff
+            );
+        }
+        Ok(())
+    }
+}
+
+impl Named for TlsObserver {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// The prefix of the [`TlsLeakMetadata`] names
+pub const TLSLEAKFEEDBACK_PREFIX: &str = "tlsleakfeedback_metadata_";
+
+/// The TLS offsets changed by any execution seen by a [`TlsLeakFeedback`]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[allow(clippy::unsafe_derive_deserialize)]
+pub struct TlsLeakMetadata {
+    /// The changed offsets
+    pub offsets: HashSet<usize>,
+}
+
+libafl_bolts::impl_serdeany!(TlsLeakMetadata);
+
+/// Considers an input interesting if its execution left the TLS changed at an offset no earlier execution changed.
+///
+/// Only new offsets count, so that state changed by every execution, like `errno`, is reported once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsLeakFeedback {
+    name: String,
+    observer_name: String,
+}
+
+impl TlsLeakFeedback {
+    /// Creates a new [`TlsLeakFeedback`], using the changes reported by the given [`TlsObserver`]
+    #[must_use]
+    pub fn new(observer: &TlsObserver) -> Self {
+        Self {
+            name: TLSLEAKFEEDBACK_PREFIX.to_string() + observer.name(),
+            observer_name: observer.name().to_string(),
+        }
+    }
+}
+
+impl<S> Feedback<S> for TlsLeakFeedback
+where
+    S: State + HasNamedMetadata,
+{
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error> {
+        state.add_named_metadata(TlsLeakMetadata::default(), &self.name);
+        Ok(())
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    fn is_interesting<EM, OT>(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _input: &S::Input,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .match_name::<TlsObserver>(&self.observer_name)
+            .expect("A TlsLeakFeedback needs a TlsObserver");
+
+        let meta = state
+            .named_metadata_map_mut()
+            .get_mut::<TlsLeakMetadata>(&self.name)
+            .unwrap();
+        let mut interesting = false;
+        for range in observer.diff() {
+            for offset in range.clone() {
+                interesting |= meta.offsets.insert(offset);
+            }
+        }
+        Ok(interesting)
+    }
+}
+
+impl Named for TlsLeakFeedback {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl HasObserverName for TlsLeakFeedback {
+    fn observer_name(&self) -> &str {
+        &self.observer_name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::diff_ranges;
+
+    #[test]
+    fn test_tls_diff() {
+        let before = [0, 1, 2, 3, 4, 5, 6, 7];
+        let after = [0, 9, 9, 3, 4, 9, 6, 9];
+        assert_eq!(diff_ranges(&before, &after), [1..3, 5..6, 7..8]);
+        assert!(diff_ranges(&before, &before).is_empty());
+    }
+}
@@ -57,6 +57,23 @@ extern "C" int malloc_heap_oob_write(const uint8_t *_data, size_t _size) {
   return 0;
 }
 
+extern "C" int malloc_heap_realloc_uaf_read(const uint8_t *_data,
+                                            size_t _size) {
+  int *array = static_cast<int *>(malloc(100 * sizeof(int)));
+  int *moved = static_cast<int *>(realloc(array, 200 * sizeof(int)));
+  fprintf(stdout, "%d\n", array[5]);
+  free(moved);
+  return 0;
+}
+
+extern "C" int malloc_heap_snprintf_dest_too_small(const uint8_t *_data,
+                                                   size_t _size) {
+  char *buf = static_cast<char *>(malloc(8));
+  snprintf(buf, 16, "%d", 1);
+  free(buf);
+  return 0;
+}
+
 extern "C" int LLVMFuzzerTestOneInput(const uint8_t *data, size_t size) {
   // abort();
   return 0;
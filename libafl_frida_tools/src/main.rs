@@ -0,0 +1,160 @@
+//! Decodes and renders the memory access logs written by the `libafl_frida` `ASan` runtime
+//! (`--asan-access-log`), for offline analysis.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use clap::{Parser, ValueEnum};
+
+/// The size of an encoded record.
+/// This has to match `ACCESS_RECORD_SIZE` in `libafl_frida::asan::access_log`.
+const ACCESS_RECORD_SIZE: usize = 18;
+
+/// A memory access, see `libafl_frida::asan::access_log::AccessRecord`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct AccessRecord {
+    pc: u64,
+    addr: u64,
+    size: u8,
+    is_write: bool,
+}
+
+impl AccessRecord {
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            pc: u64::from_le_bytes(bytes[0..8].try_into().unwrap()),
+            addr: u64::from_le_bytes(bytes[8..16].try_into().unwrap()),
+            size: bytes[16],
+            is_write: bytes[17] != 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// One access per line, human readable
+    Text,
+    /// Comma separated values, with a header
+    Csv,
+    /// The number of reads and writes per instruction
+    Summary,
+}
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "asan_access_log",
+    about = "Decode and render a libafl_frida ASan access log"
+)]
+struct Opt {
+    /// The access log to decode
+    log: PathBuf,
+
+    /// How to render the accesses
+    #[arg(short, long, value_enum, default_value = "text")]
+    format: Format,
+
+    /// Only show accesses of the instruction at this address (hex)
+    #[arg(long, value_parser = parse_hex)]
+    pc: Option<u64>,
+
+    /// Only show writes
+    #[arg(long, conflicts_with = "reads_only")]
+    writes_only: bool,
+
+    /// Only show reads
+    #[arg(long)]
+    reads_only: bool,
+}
+
+fn parse_hex(src: &str) -> Result<u64, String> {
+    u64::from_str_radix(src.trim_start_matches("0x"), 16).map_err(|err| err.to_string())
+}
+
+fn render<W: Write>(opt: &Opt, records: &[AccessRecord], out: &mut W) -> io::Result<()> {
+    let records = records.iter().filter(|record| {
+        opt.pc.map_or(true, |pc| record.pc == pc)
+            && !(opt.writes_only && !record.is_write)
+            && !(opt.reads_only && record.is_write)
+    });
+
+    match opt.format {
+        Format::Text => {
+            for record in records {
+                writeln!(
+                    out,
+                    "{:#018x}: {} {:#018x} ({} bytes)",
+                    record.pc,
+                    if record.is_write { "W" } else { "R" },
+                    record.addr,
+                    record.size
+                )?;
+            }
+        }
+        Format::Csv => {
+            writeln!(out, "pc,addr,size,is_write")?;
+            for record in records {
+                writeln!(
+                    out,
+                    "{:#x},{:#x},{},{}",
+                    record.pc, record.addr, record.size, record.is_write
+                )?;
+            }
+        }
+        Format::Summary => {
+            // pc -> (reads, writes)
+            let mut summary: BTreeMap<u64, (u64, u64)> = BTreeMap::new();
+            for record in records {
+                let counts = summary.entry(record.pc).or_default();
+                if record.is_write {
+                    counts.1 += 1;
+                } else {
+                    counts.0 += 1;
+                }
+            }
+            writeln!(out, "{:<18} {:>12} {:>12}", "pc", "reads", "writes")?;
+            for (pc, (reads, writes)) in summary {
+                writeln!(out, "{pc:#018x} {reads:>12} {writes:>12}")?;
+            }
+        }
+    }
+    out.flush()
+}
+
+fn main() -> ExitCode {
+    let opt = Opt::parse();
+
+    let bytes = match fs::read(&opt.log) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            eprintln!("Failed to read {}: {err}", opt.log.display());
+            return ExitCode::FAILURE;
+        }
+    };
+    if bytes.len() % ACCESS_RECORD_SIZE != 0 {
+        eprintln!(
+            "{} is truncated, ignoring the last {} bytes",
+            opt.log.display(),
+            bytes.len() % ACCESS_RECORD_SIZE
+        );
+    }
+    let records: Vec<AccessRecord> = bytes
+        .chunks_exact(ACCESS_RECORD_SIZE)
+        .map(AccessRecord::from_bytes)
+        .collect();
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    match render(&opt, &records, &mut out) {
+        Ok(()) => ExitCode::SUCCESS,
+        // e.g. piped into `head`
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Failed to write the output: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
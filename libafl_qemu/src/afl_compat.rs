@@ -0,0 +1,157 @@
+//! Coverage in the format of AFL++, for hybrid setups running AFL++ tools next to a `LibAFL` fuzzer.
+//!
+//! The [`AflCoverageHelper`] writes the edge hitcounts into the 64KB bitmap of AFL, in the shared memory
+//! segment given by `__AFL_SHM_ID`. The edge ids are computed from the addresses of the basic blocks
+//! like the QEMU mode of AFL++ does, so that the maps of both fuzzers agree on the edges of a testcase,
+//! and corpora can be imported from one to the other, e.g. with `afl-cmin`.
+
+use std::{cell::UnsafeCell, env, ptr};
+
+use libafl::inputs::UsesInput;
+use libafl_bolts::{
+    shmem::{ShMem, ShMemId, ShMemProvider, UnixShMem, UnixShMemProvider},
+    AsMutSlice, AsSlice, Error,
+};
+
+use crate::{
+    emu::{Emulator, GuestAddr},
+    helper::{
+        HasInstrumentationFilter, IsFilter, QemuHelper, QemuHelperTuple,
+        QemuInstrumentationAddressRangeFilter,
+    },
+    hooks::{Hook, QemuHooks},
+};
+
+/// The size of the coverage bitmap of AFL
+pub const AFL_MAP_SIZE: usize = 1 << 16;
+/// The env var holding the id of the shared memory segment of the coverage bitmap
+pub const AFL_SHM_ENV_VAR: &str = "__AFL_SHM_ID";
+
+/// The bitmap written by the hooks, the equivalent of `__afl_area_ptr`
+static mut AFL_AREA_PTR: *mut u8 = ptr::null_mut();
+
+thread_local!(static AFL_PREV_LOC: UnsafeCell<u64> = const { UnsafeCell::new(0) });
+
+/// The location of the block at `pc` in the bitmap, as computed by the QEMU mode of AFL++
+#[must_use]
+// GuestAddress is u32 for 32 bit guests
+#[allow(clippy::unnecessary_cast)]
+pub fn afl_block_location(pc: GuestAddr) -> u64 {
+    let pc = pc as u64;
+    ((pc >> 4) ^ (pc << 8)) & (AFL_MAP_SIZE as u64 - 1)
+}
+
+/// Collects the edge coverage into an AFL-compatible bitmap in shared memory.
+///
+/// If `__AFL_SHM_ID` is set, e.g. by `afl-showmap`, the helper attaches to that segment.
+/// Otherwise, it creates a new segment and exports its id in `__AFL_SHM_ID`, for the AFL++ tools to attach to.
+/// Wrap [`AflCoverageHelper::map_mut_ptr`] into a map observer to use the same coverage for the feedbacks.
+#[derive(Debug)]
+pub struct AflCoverageHelper {
+    shmem: UnixShMem,
+    address_filter: QemuInstrumentationAddressRangeFilter,
+}
+
+impl AflCoverageHelper {
+    /// Creates a new [`AflCoverageHelper`], attaching to the segment in `__AFL_SHM_ID`, or creating a new one
+    pub fn new(address_filter: QemuInstrumentationAddressRangeFilter) -> Result<Self, Error> {
+        let mut provider = UnixShMemProvider::new()?;
+        let mut shmem = if let Ok(id) = env::var(AFL_SHM_ENV_VAR) {
+            provider.shmem_from_id_and_size(ShMemId::from_string(&id), AFL_MAP_SIZE)?
+        } else {
+            let shmem = provider.new_shmem(AFL_MAP_SIZE)?;
+            shmem.write_to_env(AFL_SHM_ENV_VAR)?;
+            shmem
+        };
+        // the mapping stays at the same address, even if the helper is moved
+        unsafe {
+            AFL_AREA_PTR = shmem.as_mut_slice().as_mut_ptr();
+        }
+        Ok(Self {
+            shmem,
+            address_filter,
+        })
+    }
+
+    /// The id of the shared memory segment, as written to `__AFL_SHM_ID`
+    #[must_use]
+    pub fn shm_id(&self) -> ShMemId {
+        self.shmem.id()
+    }
+
+    /// The coverage bitmap of the last execution
+    #[must_use]
+    pub fn map(&self) -> &[u8] {
+        self.shmem.as_slice()
+    }
+
+    /// A pointer to the coverage bitmap of [`AFL_MAP_SIZE`] bytes, e.g. for a `StdMapObserver`
+    pub fn map_mut_ptr(&mut self) -> *mut u8 {
+        self.shmem.as_mut_slice().as_mut_ptr()
+    }
+
+    #[must_use]
+    pub fn must_instrument(&self, addr: GuestAddr) -> bool {
+        self.address_filter.allowed(addr)
+    }
+}
+
+impl HasInstrumentationFilter<QemuInstrumentationAddressRangeFilter> for AflCoverageHelper {
+    fn filter(&self) -> &QemuInstrumentationAddressRangeFilter {
+        &self.address_filter
+    }
+
+    fn filter_mut(&mut self) -> &mut QemuInstrumentationAddressRangeFilter {
+        &mut self.address_filter
+    }
+}
+
+impl<S> QemuHelper<S> for AflCoverageHelper
+where
+    S: UsesInput,
+{
+    const HOOKS_DO_SIDE_EFFECTS: bool = false;
+
+    fn first_exec<QT>(&self, hooks: &QemuHooks<QT, S>)
+    where
+        QT: QemuHelperTuple<S>,
+    {
+        hooks.blocks(
+            Hook::Function(gen_afl_block_ids::<QT, S>),
+            Hook::Empty,
+            Hook::Raw(trace_afl_block_transition),
+        );
+    }
+
+    fn pre_exec(&mut self, _emulator: &Emulator, _input: &S::Input) {
+        self.shmem.as_mut_slice().fill(0);
+        AFL_PREV_LOC.with(|prev_loc| unsafe { *prev_loc.get() = 0 });
+    }
+}
+
+pub fn gen_afl_block_ids<QT, S>(
+    hooks: &mut QemuHooks<QT, S>,
+    _state: Option<&mut S>,
+    pc: GuestAddr,
+) -> Option<u64>
+where
+    S: UsesInput,
+    QT: QemuHelperTuple<S>,
+{
+    if let Some(h) = hooks.helpers().match_first_type::<AflCoverageHelper>() {
+        if !h.must_instrument(pc) {
+            return None;
+        }
+    }
+    Some(afl_block_location(pc))
+}
+
+pub extern "C" fn trace_afl_block_transition(_: *const (), id: u64) {
+    unsafe {
+        AFL_PREV_LOC.with(|prev_loc| {
+            let entry = AFL_AREA_PTR.add((*prev_loc.get() ^ id) as usize);
+            *entry = (*entry).wrapping_add(1);
+            *prev_loc.get() = id >> 1;
+        });
+    }
+}
@@ -0,0 +1,289 @@
+//! Copy-on-write snapshots of the guest RAM in system mode.
+//!
+//! Restoring the full RAM after each run costs time proportional to its size, even though a run only writes a few pages.
+//! The [`CowSnapshotHelper`] hooks the memory writes of the guest, and saves the original content of a physical page
+//! the first time it is written to. On restore, only the pages written since the last restore are copied back.
+//!
+//! Only the writes of the emulated CPU are tracked: devices writing to RAM through DMA bypass the hooks,
+//! and the CPU and device state are not part of the snapshot.
+
+use std::time::Instant;
+
+use hashbrown::{HashMap, HashSet};
+use libafl::{
+    executors::ExitKind,
+    inputs::UsesInput,
+    observers::{Observer, ObserversTuple},
+};
+use libafl_bolts::Named;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    emu::Emulator,
+    helper::{QemuHelper, QemuHelperTuple},
+    hooks::{Hook, QemuHooks},
+    GuestAddr, GuestPhysAddr,
+};
+
+/// The size of the pages tracked by the [`CowSnapshotHelper`]
+pub const COW_SNAPSHOT_PAGE_SIZE: usize = 4096;
+const COW_SNAPSHOT_PAGE_MASK: GuestPhysAddr = !(COW_SNAPSHOT_PAGE_SIZE as GuestPhysAddr - 1);
+
+/// The cost of the last restore of a [`CowSnapshotHelper`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotStats {
+    /// The number of pages saved so far, the first time each of them was written to
+    pub pages_saved: usize,
+    /// The number of pages copied back by the last restore
+    pub pages_restored: usize,
+    /// The duration of the last restore, in microseconds
+    pub restore_time_us: u64,
+}
+
+/// Restores the guest RAM before each run, copying back only the pages written to since the last restore.
+///
+/// The snapshot is taken before the first run. Writes are hooked from then on:
+/// the first write to a page saves its original content, and marks it as dirty until the next restore.
+#[derive(Debug)]
+pub struct CowSnapshotHelper {
+    /// The content of the pages at the time of the snapshot, by physical page address
+    saved: HashMap<GuestPhysAddr, Box<[u8; COW_SNAPSHOT_PAGE_SIZE]>>,
+    /// The pages written to since the last restore
+    dirty: HashSet<GuestPhysAddr>,
+    snapshotted: bool,
+    stats: SnapshotStats,
+    observer_name: Option<String>,
+}
+
+impl CowSnapshotHelper {
+    /// Creates a new [`CowSnapshotHelper`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            saved: HashMap::new(),
+            dirty: HashSet::new(),
+            snapshotted: false,
+            stats: SnapshotStats::default(),
+            observer_name: None,
+        }
+    }
+
+    /// Creates a new [`CowSnapshotHelper`], reporting its [`SnapshotStats`] to the given [`SnapshotStatsObserver`]
+    #[must_use]
+    pub fn with_observer(observer: &SnapshotStatsObserver) -> Self {
+        Self {
+            observer_name: Some(observer.name().to_string()),
+            ..Self::new()
+        }
+    }
+
+    /// The cost of the last restore
+    #[must_use]
+    pub fn stats(&self) -> &SnapshotStats {
+        &self.stats
+    }
+
+    /// Saves the pages touched by a write of `size` bytes at the virtual address `addr`, if they are not dirty yet.
+    /// Called before the write happens.
+    pub fn access(&mut self, emulator: &Emulator, addr: GuestAddr, size: usize) {
+        if !self.snapshotted || size == 0 {
+            return;
+        }
+        let Some(cpu) = emulator.current_cpu() else {
+            return;
+        };
+        let last = addr.wrapping_add(size as GuestAddr - 1);
+        for vaddr in [addr, last] {
+            // unmapped addresses fault in the guest, without writing anything
+            let Some(paddr) = cpu.get_phys_addr(vaddr) else {
+                continue;
+            };
+            let page = paddr & COW_SNAPSHOT_PAGE_MASK;
+            if !self.dirty.insert(page) {
+                continue;
+            }
+            self.saved.entry(page).or_insert_with(|| {
+                let mut data = Box::new([0; COW_SNAPSHOT_PAGE_SIZE]);
+                unsafe {
+                    emulator.read_phys_mem(page, &mut data[..]);
+                }
+                data
+            });
+        }
+        self.stats.pages_saved = self.saved.len();
+    }
+
+    /// Starts tracking the writes, the current content of RAM is the snapshot
+    pub fn snapshot(&mut self) {
+        self.saved.clear();
+        self.dirty.clear();
+        self.snapshotted = true;
+        self.stats = SnapshotStats::default();
+    }
+
+    /// Copies the original content back to the pages written to since the last restore
+    pub fn restore(&mut self, emulator: &Emulator) {
+        let start = Instant::now();
+        self.stats.pages_restored = self.dirty.len();
+        for page in self.dirty.drain() {
+            let data = &self.saved[&page];
+            unsafe {
+                emulator.write_phys_mem(page, &data[..]);
+            }
+        }
+        // writing through the physical memory API also invalidates the translated code of the pages
+        self.stats.restore_time_us = u64::try_from(start.elapsed().as_micros()).unwrap_or(u64::MAX);
+    }
+}
+
+impl Default for CowSnapshotHelper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> QemuHelper<S> for CowSnapshotHelper
+where
+    S: UsesInput,
+{
+    fn first_exec<QT>(&self, hooks: &QemuHooks<QT, S>)
+    where
+        QT: QemuHelperTuple<S>,
+    {
+        hooks.writes(
+            Hook::Empty,
+            Hook::Function(trace_write1_cow_snapshot::<QT, S>),
+            Hook::Function(trace_write2_cow_snapshot::<QT, S>),
+            Hook::Function(trace_write4_cow_snapshot::<QT, S>),
+            Hook::Function(trace_write8_cow_snapshot::<QT, S>),
+            Hook::Function(trace_write_n_cow_snapshot::<QT, S>),
+        );
+    }
+
+    fn pre_exec(&mut self, emulator: &Emulator, _input: &S::Input) {
+        if self.snapshotted {
+            self.restore(emulator);
+        } else {
+            self.snapshot();
+        }
+    }
+
+    fn post_exec<OT>(
+        &mut self,
+        _emulator: &Emulator,
+        _input: &S::Input,
+        observers: &mut OT,
+        _exit_kind: &mut ExitKind,
+    ) where
+        OT: ObserversTuple<S>,
+    {
+        if let Some(observer_name) = &self.observer_name {
+            let observer = observers
+                .match_name_mut::<SnapshotStatsObserver>(observer_name)
+                .expect("The SnapshotStatsObserver of the CowSnapshotHelper is missing");
+            observer.stats = self.stats;
+        }
+    }
+}
+
+fn cow_snapshot_access<QT, S>(hooks: &mut QemuHooks<QT, S>, addr: GuestAddr, size: usize)
+where
+    S: UsesInput,
+    QT: QemuHelperTuple<S>,
+{
+    let emulator = hooks.emulator().clone();
+    let h = hooks.match_helper_mut::<CowSnapshotHelper>().unwrap();
+    h.access(&emulator, addr, size);
+}
+
+pub fn trace_write1_cow_snapshot<QT, S>(
+    hooks: &mut QemuHooks<QT, S>,
+    _state: Option<&mut S>,
+    _id: u64,
+    addr: GuestAddr,
+) where
+    S: UsesInput,
+    QT: QemuHelperTuple<S>,
+{
+    cow_snapshot_access(hooks, addr, 1);
+}
+
+pub fn trace_write2_cow_snapshot<QT, S>(
+    hooks: &mut QemuHooks<QT, S>,
+    _state: Option<&mut S>,
+    _id: u64,
+    addr: GuestAddr,
+) where
+    S: UsesInput,
+    QT: QemuHelperTuple<S>,
+{
+    cow_snapshot_access(hooks, addr, 2);
+}
+
+pub fn trace_write4_cow_snapshot<QT, S>(
+    hooks: &mut QemuHooks<QT, S>,
+    _state: Option<&mut S>,
+    _id: u64,
+    addr: GuestAddr,
+) where
+    S: UsesInput,
+    QT: QemuHelperTuple<S>,
+{
+    cow_snapshot_access(hooks, addr, 4);
+}
+
+pub fn trace_write8_cow_snapshot<QT, S>(
+    hooks: &mut QemuHooks<QT, S>,
+    _state: Option<&mut S>,
+    _id: u64,
+    addr: GuestAddr,
+) where
+    S: UsesInput,
+    QT: QemuHelperTuple<S>,
+{
+    cow_snapshot_access(hooks, addr, 8);
+}
+
+pub fn trace_write_n_cow_snapshot<QT, S>(
+    hooks: &mut QemuHooks<QT, S>,
+    _state: Option<&mut S>,
+    _id: u64,
+    addr: GuestAddr,
+    size: usize,
+) where
+    S: UsesInput,
+    QT: QemuHelperTuple<S>,
+{
+    cow_snapshot_access(hooks, addr, size);
+}
+
+/// Holds the [`SnapshotStats`] of the restore before the last execution, set by a [`CowSnapshotHelper`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotStatsObserver {
+    name: String,
+    stats: SnapshotStats,
+}
+
+impl SnapshotStatsObserver {
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            stats: SnapshotStats::default(),
+        }
+    }
+
+    /// The cost of the restore before the last execution
+    #[must_use]
+    pub fn stats(&self) -> &SnapshotStats {
+        &self.stats
+    }
+}
+
+impl<S> Observer<S> for SnapshotStatsObserver where S: UsesInput {}
+
+impl Named for SnapshotStatsObserver {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
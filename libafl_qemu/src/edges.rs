@@ -8,6 +8,8 @@ pub use libafl_targets::{
 };
 use serde::{Deserialize, Serialize};
 
+#[cfg(emulation_mode = "usermode")]
+use crate::helpers::filter::LibraryFilterHelper;
 use crate::{
     emu::GuestAddr,
     helper::{
@@ -465,6 +467,21 @@ where
 
 thread_local!(static PREV_LOC : UnsafeCell<u64> = const { UnsafeCell::new(0) });
 
+/// Returns `false` if a [`LibraryFilterHelper`] is in the helpers and excludes `pc` from the coverage
+#[cfg(emulation_mode = "usermode")]
+// GuestAddress is u32 for 32 bit guests
+#[allow(clippy::unnecessary_cast)]
+fn library_filter_allows<QT, S>(hooks: &QemuHooks<QT, S>, pc: GuestAddr) -> bool
+where
+    S: UsesInput,
+    QT: QemuHelperTuple<S>,
+{
+    hooks
+        .helpers()
+        .match_first_type::<LibraryFilterHelper>()
+        .map_or(true, |filter| filter.is_covered_pc(pc as u64))
+}
+
 pub fn gen_unique_edge_ids<QT, S>(
     hooks: &mut QemuHooks<QT, S>,
     state: Option<&mut S>,
@@ -476,6 +493,10 @@ where
     S: UsesInput,
     QT: QemuHelperTuple<S>,
 {
+    #[cfg(emulation_mode = "usermode")]
+    if !library_filter_allows(hooks, src) && !library_filter_allows(hooks, dest) {
+        return None;
+    }
     if let Some(h) = hooks.helpers().match_first_type::<QemuEdgeCoverageHelper>() {
         #[cfg(emulation_mode = "usermode")]
         {
@@ -551,6 +572,10 @@ where
     S: UsesInput,
     QT: QemuHelperTuple<S>,
 {
+    #[cfg(emulation_mode = "usermode")]
+    if !library_filter_allows(hooks, src) && !library_filter_allows(hooks, dest) {
+        return None;
+    }
     if let Some(h) = hooks
         .helpers()
         .match_first_type::<QemuEdgeCoverageChildHelper>()
@@ -617,6 +642,10 @@ where
     S: UsesInput,
     QT: QemuHelperTuple<S>,
 {
+    #[cfg(emulation_mode = "usermode")]
+    if !library_filter_allows(hooks, pc) {
+        return None;
+    }
     if let Some(h) = hooks
         .helpers()
         .match_first_type::<QemuEdgeCoverageClassicHelper>()
@@ -75,6 +75,29 @@ impl<'a> EasyElf<'a> {
         None
     }
 
+    /// The names and addresses of all defined function symbols
+    #[must_use]
+    pub fn function_symbols(&self, load_addr: GuestAddr) -> Vec<(&'a str, GuestAddr)> {
+        let mut functions = Vec::new();
+        for sym in &self.elf.syms {
+            if !sym.is_function() || sym.st_value == 0 {
+                continue;
+            }
+            if let Some(sym_name) = self.elf.strtab.get_at(sym.st_name) {
+                let addr = if self.is_pic() {
+                    sym.st_value as GuestAddr + load_addr
+                } else {
+                    sym.st_value as GuestAddr
+                };
+                #[cfg(cpu_target = "arm")]
+                // Required because of arm interworking addresses aka bit(0) for thumb mode
+                let addr = addr & !(0x1 as GuestAddr);
+                functions.push((sym_name, addr));
+            }
+        }
+        functions
+    }
+
     #[must_use]
     pub fn get_section(&self, name: &str, load_addr: GuestAddr) -> Option<Range<GuestAddr>> {
         for section in &self.elf.section_headers {
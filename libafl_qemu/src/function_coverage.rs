@@ -0,0 +1,223 @@
+//! Coverage of the functions of the target, by name, for source-level coverage reports.
+//!
+//! The [`FunctionCoverageHelper`] hooks the entry of each function symbol of the target ELF,
+//! and hands the functions called during an execution to a [`FunctionCoverageObserver`].
+
+use std::{fmt::Write, path::PathBuf};
+
+use hashbrown::{HashMap, HashSet};
+use libafl::{
+    executors::ExitKind,
+    inputs::UsesInput,
+    observers::{Observer, ObserversTuple},
+    Error,
+};
+use libafl_bolts::Named;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    elf::EasyElf,
+    emu::{Emulator, GuestAddr},
+    helper::{QemuHelper, QemuHelperTuple},
+    hooks::{Hook, QemuHooks},
+};
+
+/// Records which functions of the target get called, by hooking the entry of each function symbol
+#[derive(Debug)]
+pub struct FunctionCoverageHelper {
+    binary: PathBuf,
+    /// The names of the functions starting at each hooked address (symbols may alias)
+    entries: HashMap<GuestAddr, Vec<String>>,
+    num_functions: usize,
+    /// The hooked addresses reached in the current execution
+    hit: HashSet<GuestAddr>,
+    observer_name: String,
+}
+
+impl FunctionCoverageHelper {
+    /// Creates a new [`FunctionCoverageHelper`] for the functions of the ELF at `binary`, usually the target itself.
+    /// If it is position independent, it is expected at the load address of the target in `emulator`.
+    pub fn new<P: Into<PathBuf>>(
+        emulator: &Emulator,
+        binary: P,
+        observer: &FunctionCoverageObserver,
+    ) -> Result<Self, Error> {
+        let binary = binary.into();
+        let mut elf_buffer = Vec::new();
+        let elf = EasyElf::from_file(&binary, &mut elf_buffer)?;
+
+        let mut entries: HashMap<GuestAddr, Vec<String>> = HashMap::new();
+        for (name, addr) in elf.function_symbols(emulator.load_addr()) {
+            entries.entry(addr).or_default().push(name.to_string());
+        }
+        let num_functions = entries.values().flatten().collect::<HashSet<_>>().len();
+
+        Ok(Self {
+            binary,
+            entries,
+            num_functions,
+            hit: HashSet::new(),
+            observer_name: observer.name().to_string(),
+        })
+    }
+
+    /// The number of hooked functions
+    #[must_use]
+    pub fn num_functions(&self) -> usize {
+        self.num_functions
+    }
+
+    fn mark_called(&mut self, pc: GuestAddr) {
+        self.hit.insert(pc);
+    }
+}
+
+impl<S> QemuHelper<S> for FunctionCoverageHelper
+where
+    S: UsesInput,
+{
+    fn first_exec<QT>(&self, hooks: &QemuHooks<QT, S>)
+    where
+        QT: QemuHelperTuple<S>,
+    {
+        log::info!(
+            "FunctionCoverageHelper: hooking {} functions of {}",
+            self.entries.len(),
+            self.binary.display()
+        );
+        for pc in self.entries.keys() {
+            hooks.instruction(*pc, Hook::Function(on_function_entry::<QT, S>), true);
+        }
+    }
+
+    fn pre_exec(&mut self, _emulator: &Emulator, _input: &S::Input) {
+        self.hit.clear();
+    }
+
+    fn post_exec<OT>(
+        &mut self,
+        _emulator: &Emulator,
+        _input: &S::Input,
+        observers: &mut OT,
+        _exit_kind: &mut ExitKind,
+    ) where
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .match_name_mut::<FunctionCoverageObserver>(&self.observer_name)
+            .expect("A FunctionCoverageHelper needs a FunctionCoverageObserver");
+        // the names are copied once, afterwards only the flags of the called functions are set
+        if observer.functions.is_empty() {
+            observer.functions = self
+                .entries
+                .values()
+                .flatten()
+                .map(|name| (name.clone(), false))
+                .collect();
+        }
+        for pc in self.hit.drain() {
+            for name in &self.entries[&pc] {
+                if let Some(called) = observer.functions.get_mut(name) {
+                    *called = true;
+                }
+            }
+        }
+    }
+}
+
+/// The instruction hook of the [`FunctionCoverageHelper`], marking the function at `pc` as called
+fn on_function_entry<QT, S>(hooks: &mut QemuHooks<QT, S>, _state: Option<&mut S>, pc: GuestAddr)
+where
+    QT: QemuHelperTuple<S>,
+    S: UsesInput,
+{
+    if let Some(helper) = hooks
+        .helpers_mut()
+        .match_first_type_mut::<FunctionCoverageHelper>()
+    {
+        helper.mark_called(pc);
+    }
+}
+
+/// Holds, for each function of the target, if it was called in the last execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionCoverageObserver {
+    name: String,
+    functions: HashMap<String, bool>,
+}
+
+impl FunctionCoverageObserver {
+    /// Creates a new [`FunctionCoverageObserver`] with the given name, to be passed to the [`FunctionCoverageHelper`]
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            functions: HashMap::new(),
+        }
+    }
+
+    /// If each function was called in the last execution
+    #[must_use]
+    pub fn functions(&self) -> &HashMap<String, bool> {
+        &self.functions
+    }
+
+    /// The names of the functions called in the last execution
+    pub fn called(&self) -> impl Iterator<Item = &str> {
+        self.functions
+            .iter()
+            .filter(|(_, called)| **called)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Renders the coverage of the last execution as HTML table, sorted by function name
+    #[must_use]
+    pub fn html_report(&self) -> String {
+        let mut functions: Vec<(&String, &bool)> = self.functions.iter().collect();
+        functions.sort_unstable();
+        let called = functions.iter().filter(|(_, called)| **called).count();
+
+        let mut html = String::from("<table>\n<tr><th>function</th><th>called</th></tr>\n");
+        for (name, called) in functions {
+            let (color, mark) = if *called {
+                ("#c8e6c9", "yes")
+            } else {
+                ("#ffcdd2", "no")
+            };
+            let name = name
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;");
+            writeln!(
+                html,
+                "<tr style=\"background: {color}\"><td><code>{name}</code></td><td>{mark}</td></tr>"
+            )
+            .unwrap();
+        }
+        writeln!(
+            html,
+            "</table>\n<p>{called} of {} functions called</p>",
+            self.functions.len()
+        )
+        .unwrap();
+        html
+    }
+}
+
+impl<S> Observer<S> for FunctionCoverageObserver
+where
+    S: UsesInput,
+{
+    fn pre_exec(&mut self, _state: &mut S, _input: &S::Input) -> Result<(), Error> {
+        self.functions
+            .values_mut()
+            .for_each(|called| *called = false);
+        Ok(())
+    }
+}
+
+impl Named for FunctionCoverageObserver {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
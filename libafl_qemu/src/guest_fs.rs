@@ -0,0 +1,427 @@
+//! Redirect the filesystem operations of the guest to a per-execution temporary directory
+//!
+//! The [`GuestFileSystemHelper`] hooks the path-based syscalls of the guest (`open`, `unlink`, `mkdir`, `rename`
+//! and their `*at` variants), and executes them on the host itself, after rewriting the paths so that they point
+//! inside a fresh directory created for each execution. That directory is removed again after the run,
+//! so targets cannot pollute the host filesystem nor leak files from one execution into the next.
+//!
+//! Paths are first normalized lexically, resolving `.` and `..`, so that `/allowed/../etc` is not mistaken for
+//! an allowlisted path. Paths starting with one of the allowlisted prefixes are left untouched, all other paths
+//! are forced inside the temporary directory. Paths trying to escape it (through `..`) are denied with `EACCES`.
+//! Open flags and modes are passed to the host unchanged, so guest and host need to agree on their values.
+
+use std::{
+    env,
+    ffi::{CStr, CString, OsStr},
+    fs,
+    os::{raw::c_char, unix::ffi::OsStrExt},
+    path::{Component, Path, PathBuf},
+    process,
+};
+
+use hashbrown::HashMap;
+use libafl::{executors::ExitKind, inputs::UsesInput, observers::ObserversTuple};
+
+use crate::{
+    emu::{Emulator, SyscallHookResult},
+    helper::{QemuHelper, QemuHelperTuple},
+    hooks::{Hook, QemuHooks},
+    GuestAddr, SYS_close, SYS_mkdirat, SYS_openat, SYS_renameat2, SYS_unlinkat,
+};
+#[cfg(not(cpu_target = "aarch64"))]
+use crate::{SYS_mkdir, SYS_open, SYS_rename, SYS_renameat, SYS_unlink};
+
+/// Redirects the guest's filesystem accesses to a per-execution temporary directory.
+#[derive(Debug)]
+pub struct GuestFileSystemHelper {
+    /// The base under which the per-execution directories are created
+    base_dir: PathBuf,
+    /// Path prefixes the guest may access on the host filesystem directly
+    allowlist: Vec<PathBuf>,
+    /// The directory of the current execution, if any
+    current_dir: Option<PathBuf>,
+    /// The fds opened on behalf of the guest, with the redirected path they refer to
+    redirected_fds: HashMap<i32, PathBuf>,
+    executions: u64,
+}
+
+impl Default for GuestFileSystemHelper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GuestFileSystemHelper {
+    /// Creates a new [`GuestFileSystemHelper`], creating the per-execution directories in [`env::temp_dir`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_base_dir(env::temp_dir())
+    }
+
+    /// Creates a new [`GuestFileSystemHelper`], creating the per-execution directories in `base_dir`
+    #[must_use]
+    pub fn with_base_dir<P: AsRef<Path>>(base_dir: P) -> Self {
+        Self {
+            base_dir: base_dir.as_ref().to_path_buf(),
+            allowlist: Vec::new(),
+            current_dir: None,
+            redirected_fds: HashMap::new(),
+            executions: 0,
+        }
+    }
+
+    /// Allows the guest to access paths starting with `prefix` on the host, without redirection
+    #[must_use]
+    pub fn allow<P: AsRef<Path>>(mut self, prefix: P) -> Self {
+        self.allowlist.push(prefix.as_ref().to_path_buf());
+        self
+    }
+
+    /// The temporary directory of the current execution
+    #[must_use]
+    pub fn current_dir(&self) -> Option<&Path> {
+        self.current_dir.as_deref()
+    }
+
+    /// The fds opened on behalf of the guest during the current execution, with their redirected paths
+    #[must_use]
+    pub fn redirected_fds(&self) -> &HashMap<i32, PathBuf> {
+        &self.redirected_fds
+    }
+
+    /// Returns `true` if the guest may access `path` on the host directly.
+    /// The path is normalized first, paths with a `..` above their start are never allowed.
+    #[must_use]
+    pub fn is_allowed(&self, path: &Path) -> bool {
+        normalize_path(path)
+            .is_some_and(|path| self.allowlist.iter().any(|prefix| path.starts_with(prefix)))
+    }
+
+    /// Rewrites a guest path to its location in the current temporary directory.
+    /// Returns `None` if the path tries to escape the temporary directory.
+    #[must_use]
+    pub fn redirect(&self, path: &Path) -> Option<PathBuf> {
+        let path = normalize_path(path)?;
+        if self.allowlist.iter().any(|prefix| path.starts_with(prefix)) {
+            return Some(path);
+        }
+        let mut redirected = self.current_dir.clone()?;
+        // normalized, so only the root and normal components are left
+        redirected.extend(
+            path.components()
+                .filter(|component| matches!(component, Component::Normal(_))),
+        );
+        if let Some(parent) = redirected.parent() {
+            drop(fs::create_dir_all(parent));
+        }
+        Some(redirected)
+    }
+}
+
+impl<S> QemuHelper<S> for GuestFileSystemHelper
+where
+    S: UsesInput,
+{
+    fn init_hooks<QT>(&self, hooks: &QemuHooks<QT, S>)
+    where
+        QT: QemuHelperTuple<S>,
+    {
+        hooks.syscalls(Hook::Function(guest_fs_syscall_hook::<QT, S>));
+    }
+
+    fn pre_exec(&mut self, _emulator: &Emulator, _input: &S::Input) {
+        let dir = self.base_dir.join(format!(
+            "libafl_qemu_fs_{}_{}",
+            process::id(),
+            self.executions
+        ));
+        self.executions += 1;
+        fs::create_dir_all(&dir).expect("Failed to create the guest filesystem directory");
+        self.current_dir = Some(dir);
+    }
+
+    fn post_exec<OT>(
+        &mut self,
+        _emulator: &Emulator,
+        _input: &S::Input,
+        _observers: &mut OT,
+        _exit_kind: &mut ExitKind,
+    ) where
+        OT: ObserversTuple<S>,
+    {
+        for fd in self.redirected_fds.keys() {
+            unsafe {
+                libc::close(*fd);
+            }
+        }
+        self.redirected_fds.clear();
+        if let Some(dir) = self.current_dir.take() {
+            if let Err(err) = fs::remove_dir_all(&dir) {
+                log::warn!("Failed to remove the guest filesystem directory {dir:?}: {err}");
+            }
+        }
+    }
+}
+
+/// Resolves the `.` and `..` components of `path` lexically, without looking at the filesystem.
+/// Returns `None` if a `..` goes above the start of the path.
+fn normalize_path(path: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    let mut depth = 0_usize;
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => {
+                normalized.push(part);
+                depth += 1;
+            }
+            Component::ParentDir => {
+                depth = depth.checked_sub(1)?;
+                normalized.pop();
+            }
+            Component::RootDir | Component::Prefix(_) => normalized.push(component),
+            Component::CurDir => {}
+        }
+    }
+    Some(normalized)
+}
+
+/// Converts the return value of a libc call to what the guest expects from the syscall
+#[allow(clippy::cast_sign_loss)]
+fn syscall_ret(res: libc::c_int) -> GuestAddr {
+    let res = if res < 0 {
+        -std::io::Error::last_os_error()
+            .raw_os_error()
+            .unwrap_or(libc::EIO)
+    } else {
+        res
+    };
+    res as GuestAddr
+}
+
+/// The return value for denied accesses, `-EACCES`
+#[allow(clippy::cast_sign_loss)]
+fn denied() -> SyscallHookResult {
+    SyscallHookResult::new(Some(-libc::EACCES as GuestAddr))
+}
+
+/// Reads the nul-terminated guest path at `addr`
+fn read_guest_path(emu: &Emulator, addr: GuestAddr) -> PathBuf {
+    let path = unsafe { CStr::from_ptr(emu.g2h::<c_char>(addr)) };
+    PathBuf::from(OsStr::from_bytes(path.to_bytes()))
+}
+
+/// Redirects the guest path at `addr`, returning the host path to use instead
+fn redirect_guest_path<QT, S>(
+    hooks: &mut QemuHooks<QT, S>,
+    emu: &Emulator,
+    addr: GuestAddr,
+) -> Option<CString>
+where
+    S: UsesInput,
+    QT: QemuHelperTuple<S>,
+{
+    let path = read_guest_path(emu, addr);
+    let h = hooks.match_helper_mut::<GuestFileSystemHelper>().unwrap();
+    let redirected = h.redirect(&path)?;
+    CString::new(redirected.as_os_str().as_bytes()).ok()
+}
+
+/// Opens `path` on the host on behalf of the guest, remembering the redirected fd
+fn open_redirected<QT, S>(
+    hooks: &mut QemuHooks<QT, S>,
+    path: &CString,
+    flags: GuestAddr,
+    mode: GuestAddr,
+) -> SyscallHookResult
+where
+    S: UsesInput,
+    QT: QemuHelperTuple<S>,
+{
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    let fd = unsafe {
+        libc::openat(
+            libc::AT_FDCWD,
+            path.as_ptr(),
+            flags as libc::c_int,
+            mode as libc::c_uint,
+        )
+    };
+    if fd >= 0 {
+        let h = hooks.match_helper_mut::<GuestFileSystemHelper>().unwrap();
+        let path = PathBuf::from(OsStr::from_bytes(path.as_bytes()));
+        // allowlisted files are not ours to close
+        if h.current_dir().is_some_and(|dir| path.starts_with(dir)) {
+            h.redirected_fds.insert(fd, path);
+        }
+    }
+    SyscallHookResult::new(Some(syscall_ret(fd)))
+}
+
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+#[allow(non_upper_case_globals)]
+pub fn guest_fs_syscall_hook<QT, S>(
+    hooks: &mut QemuHooks<QT, S>,
+    _state: Option<&mut S>,
+    sys_num: i32,
+    a0: GuestAddr,
+    a1: GuestAddr,
+    a2: GuestAddr,
+    a3: GuestAddr,
+    a4: GuestAddr,
+    _a5: GuestAddr,
+    _a6: GuestAddr,
+    _a7: GuestAddr,
+) -> SyscallHookResult
+where
+    S: UsesInput,
+    QT: QemuHelperTuple<S>,
+{
+    // Outside of executions (e.g., while the target is loaded), the guest uses the host filesystem
+    if hooks
+        .match_helper_mut::<GuestFileSystemHelper>()
+        .unwrap()
+        .current_dir()
+        .is_none()
+    {
+        return SyscallHookResult::new(None);
+    }
+    let emu = hooks.emulator().clone();
+
+    // Relative paths to an fd other than `AT_FDCWD` are already confined to that directory
+    let dirfd_is_cwd = |dirfd: GuestAddr| dirfd as libc::c_int == libc::AT_FDCWD;
+
+    match i64::from(sys_num) {
+        SYS_close => {
+            let h = hooks.match_helper_mut::<GuestFileSystemHelper>().unwrap();
+            h.redirected_fds.remove(&(a0 as i32));
+            SyscallHookResult::new(None)
+        }
+        #[cfg(not(cpu_target = "aarch64"))]
+        SYS_open => {
+            let Some(path) = redirect_guest_path(hooks, &emu, a0) else {
+                return denied();
+            };
+            open_redirected(hooks, &path, a1, a2)
+        }
+        SYS_openat if dirfd_is_cwd(a0) => {
+            let Some(path) = redirect_guest_path(hooks, &emu, a1) else {
+                return denied();
+            };
+            open_redirected(hooks, &path, a2, a3)
+        }
+        #[cfg(not(cpu_target = "aarch64"))]
+        SYS_mkdir => {
+            let Some(path) = redirect_guest_path(hooks, &emu, a0) else {
+                return denied();
+            };
+            let res = unsafe { libc::mkdir(path.as_ptr(), a1 as libc::mode_t) };
+            SyscallHookResult::new(Some(syscall_ret(res)))
+        }
+        SYS_mkdirat if dirfd_is_cwd(a0) => {
+            let Some(path) = redirect_guest_path(hooks, &emu, a1) else {
+                return denied();
+            };
+            let res = unsafe { libc::mkdir(path.as_ptr(), a2 as libc::mode_t) };
+            SyscallHookResult::new(Some(syscall_ret(res)))
+        }
+        #[cfg(not(cpu_target = "aarch64"))]
+        SYS_unlink => {
+            let Some(path) = redirect_guest_path(hooks, &emu, a0) else {
+                return denied();
+            };
+            let res = unsafe { libc::unlink(path.as_ptr()) };
+            SyscallHookResult::new(Some(syscall_ret(res)))
+        }
+        SYS_unlinkat if dirfd_is_cwd(a0) => {
+            let Some(path) = redirect_guest_path(hooks, &emu, a1) else {
+                return denied();
+            };
+            let res = unsafe { libc::unlinkat(libc::AT_FDCWD, path.as_ptr(), a2 as libc::c_int) };
+            SyscallHookResult::new(Some(syscall_ret(res)))
+        }
+        #[cfg(not(cpu_target = "aarch64"))]
+        SYS_rename => {
+            let (Some(old), Some(new)) = (
+                redirect_guest_path(hooks, &emu, a0),
+                redirect_guest_path(hooks, &emu, a1),
+            ) else {
+                return denied();
+            };
+            let res = unsafe { libc::rename(old.as_ptr(), new.as_ptr()) };
+            SyscallHookResult::new(Some(syscall_ret(res)))
+        }
+        #[cfg(not(cpu_target = "aarch64"))]
+        SYS_renameat if dirfd_is_cwd(a0) && dirfd_is_cwd(a2) => {
+            let (Some(old), Some(new)) = (
+                redirect_guest_path(hooks, &emu, a1),
+                redirect_guest_path(hooks, &emu, a3),
+            ) else {
+                return denied();
+            };
+            let res = unsafe { libc::rename(old.as_ptr(), new.as_ptr()) };
+            SyscallHookResult::new(Some(syscall_ret(res)))
+        }
+        SYS_renameat2 if dirfd_is_cwd(a0) && dirfd_is_cwd(a2) => {
+            let (Some(old), Some(new)) = (
+                redirect_guest_path(hooks, &emu, a1),
+                redirect_guest_path(hooks, &emu, a3),
+            ) else {
+                return denied();
+            };
+            let res = unsafe {
+                libc::syscall(
+                    libc::SYS_renameat2,
+                    libc::AT_FDCWD,
+                    old.as_ptr(),
+                    libc::AT_FDCWD,
+                    new.as_ptr(),
+                    a4 as libc::c_uint,
+                )
+            };
+            SyscallHookResult::new(Some(syscall_ret(res as libc::c_int)))
+        }
+        _ => SyscallHookResult::new(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        path::{Path, PathBuf},
+        process,
+    };
+
+    use super::{normalize_path, GuestFileSystemHelper};
+
+    #[test]
+    fn test_guest_fs_allowlist() {
+        assert_eq!(
+            normalize_path(Path::new("/a/./b/../c")),
+            Some(PathBuf::from("/a/c"))
+        );
+        assert_eq!(normalize_path(Path::new("a/../..")), None);
+
+        let mut helper = GuestFileSystemHelper::new().allow("/allowed");
+        assert!(helper.is_allowed(Path::new("/allowed/file")));
+        assert!(!helper.is_allowed(Path::new("/allowed/../../etc/passwd")));
+        assert!(!helper.is_allowed(Path::new("/allowed/../etc/passwd")));
+
+        let dir = env::temp_dir().join(format!("libafl_qemu_fs_test_{}", process::id()));
+        helper.current_dir = Some(dir.clone());
+        assert_eq!(
+            helper.redirect(Path::new("/allowed/./file")),
+            Some(PathBuf::from("/allowed/file"))
+        );
+        assert_eq!(
+            helper.redirect(Path::new("/allowed/../etc/passwd")),
+            Some(dir.join("etc/passwd"))
+        );
+        assert_eq!(
+            helper.redirect(Path::new("/allowed/../../etc/passwd")),
+            None
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
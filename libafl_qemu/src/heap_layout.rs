@@ -0,0 +1,254 @@
+//! Pre-conditioning the heap of the target, for all executions to start from the same layout.
+//!
+//! Many heap bugs are only exploitable, or only crash, with a specific heap layout, e.g. with a freed chunk
+//! of the right size class next to the overflowing buffer. Reaching such a layout by chance takes long.
+//! The [`HeapLayoutHelper`] calls `malloc` and `free` of the target in the sequence given by a [`HeapSprayConfig`]
+//! once, when the hooks are initialized, before the snapshot of the target is taken.
+//! Restoring the snapshot, e.g. with the [`crate::QemuSnapshotHelper`], then starts each execution from the prepared layout.
+//!
+//! The config is a TOML file of allocation batches, processed in order:
+//!
+//! ```toml
+//! # 32 chunks of 0x40 bytes, every second one freed again, leaving holes in the size class
+//! [[batches]]
+//! size = 0x40
+//! count = 32
+//! free_every = 2
+//!
+//! # 4 chunks of 0x400 bytes, kept allocated
+//! [[batches]]
+//! size = 0x400
+//! count = 4
+//! ```
+//!
+//! The spray runs before the helpers install their hooks for the executions, most of them in
+//! [`QemuHelper::first_exec`], so it does not show up as coverage, comparisons, or other feedback of the first input.
+//! The helper has to come before the helpers whose init hooks should not see the spray in the helper tuple,
+//! and after those that should, e.g. the [`crate::QemuAsanHelper`], to track the sprayed chunks.
+//! As the layout is part of the snapshot, changes to the config only take effect when the fuzzer restarts.
+
+use std::{fs, path::Path};
+
+use libafl::{inputs::UsesInput, Error};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    emu::{ArchExtras, EmuExitReason, Emulator, GuestAddr, GuestReg, MmapPerms},
+    helper::{QemuHelper, QemuHelperTuple},
+    hooks::QemuHooks,
+    return_values::RETURN_VALUE_REG,
+    CallingConvention, Regs,
+};
+
+/// A batch of allocations of a [`HeapSprayConfig`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeapSprayBatch {
+    /// The size of each chunk
+    pub size: GuestReg,
+    /// The number of chunks to allocate
+    pub count: usize,
+    /// If not `0`, every `free_every`-th chunk of this batch is freed right after the batch was allocated
+    #[serde(default)]
+    pub free_every: usize,
+}
+
+/// The sequence of allocations done by a [`HeapLayoutHelper`], see the [module documentation](self)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeapSprayConfig {
+    /// The batches, allocated in order
+    #[serde(default)]
+    pub batches: Vec<HeapSprayBatch>,
+}
+
+impl HeapSprayConfig {
+    /// Parses the TOML config file at `path`
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        toml::from_str(&fs::read_to_string(path)?).map_err(|e| {
+            Error::serialize(format!(
+                "Failed to deserialize toml at {}: {e}",
+                path.display()
+            ))
+        })
+    }
+}
+
+/// Calls `malloc` and `free` of the target once, before the snapshot, following a [`HeapSprayConfig`]
+#[derive(Debug)]
+pub struct HeapLayoutHelper {
+    config: HeapSprayConfig,
+    malloc: GuestAddr,
+    free: GuestAddr,
+}
+
+impl HeapLayoutHelper {
+    /// Creates a new [`HeapLayoutHelper`], reading the [`HeapSprayConfig`] at `config_path`
+    /// and calling the `malloc` and `free` found at the given guest addresses.
+    pub fn new<P: AsRef<Path>>(
+        config_path: P,
+        malloc: GuestAddr,
+        free: GuestAddr,
+    ) -> Result<Self, Error> {
+        Ok(Self::with_config(
+            HeapSprayConfig::from_file(config_path)?,
+            malloc,
+            free,
+        ))
+    }
+
+    /// Creates a new [`HeapLayoutHelper`], spraying the given [`HeapSprayConfig`]
+    /// with the `malloc` and `free` found at the given guest addresses.
+    #[must_use]
+    pub fn with_config(config: HeapSprayConfig, malloc: GuestAddr, free: GuestAddr) -> Self {
+        Self {
+            config,
+            malloc,
+            free,
+        }
+    }
+
+    /// The config sprayed
+    #[must_use]
+    pub fn config(&self) -> &HeapSprayConfig {
+        &self.config
+    }
+
+    /// Calls the function at `func` with a single argument, returning to the breakpoint at `ret_addr`,
+    /// and returns its return value
+    #[allow(clippy::useless_conversion)] // the breakpoint address is wider on 32-bit targets
+    fn call(
+        emulator: &Emulator,
+        ret_addr: GuestAddr,
+        func: GuestAddr,
+        arg: GuestReg,
+    ) -> Result<GuestReg, String> {
+        // leave a gap below the stack of the target, aligned for the call
+        let stack_ptr: GuestAddr = emulator.read_reg(Regs::Sp)?;
+        let stack_ptr = (stack_ptr - 0x1000) & !0xf;
+        // on x86, the return address is pushed by the call
+        #[cfg(any(cpu_target = "x86_64", cpu_target = "i386"))]
+        let stack_ptr = stack_ptr - core::mem::size_of::<GuestAddr>() as GuestAddr;
+
+        emulator.write_reg(Regs::Sp, stack_ptr)?;
+        emulator.write_reg(Regs::Pc, func)?;
+        emulator.write_return_address(ret_addr)?;
+        emulator.write_function_argument(CallingConvention::Cdecl, 0, arg)?;
+
+        match unsafe { emulator.run() } {
+            Ok(EmuExitReason::Breakpoint(addr)) if addr == ret_addr.into() => {
+                emulator.read_reg(RETURN_VALUE_REG)
+            }
+            Ok(reason) => Err(format!("Unexpected exit during the heap spray: {reason}")),
+            Err(err) => Err(format!("Unexpected exit during the heap spray: {err:?}")),
+        }
+    }
+
+    /// Runs all batches of the config, the sprayed calls return to a breakpoint on the page at `ret_addr`
+    fn spray(&self, emulator: &Emulator, ret_addr: GuestAddr) -> Result<(), String> {
+        for batch in &self.config.batches {
+            let mut chunks = Vec::with_capacity(batch.count);
+            for _ in 0..batch.count {
+                chunks.push(Self::call(emulator, ret_addr, self.malloc, batch.size)?);
+            }
+            for chunk in batch.freed(chunks) {
+                if chunk != 0 {
+                    Self::call(emulator, ret_addr, self.free, chunk)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl HeapSprayBatch {
+    /// The chunks of `chunks`, allocated by this batch in order, that are freed again
+    fn freed(&self, chunks: Vec<GuestReg>) -> Vec<GuestReg> {
+        if self.free_every == 0 {
+            return vec![];
+        }
+        chunks
+            .into_iter()
+            .skip(self.free_every - 1)
+            .step_by(self.free_every)
+            .collect()
+    }
+}
+
+impl<S> QemuHelper<S> for HeapLayoutHelper
+where
+    S: UsesInput,
+{
+    fn init_hooks<QT>(&self, hooks: &QemuHooks<QT, S>)
+    where
+        QT: QemuHelperTuple<S>,
+    {
+        if self.config.batches.is_empty() {
+            return;
+        }
+
+        let emulator = hooks.emulator();
+        let cpu = emulator
+            .current_cpu()
+            .unwrap_or_else(|| emulator.cpu_from_index(0));
+        let page_size = cpu.page_size();
+        let saved = cpu.save_state();
+
+        // the sprayed calls return to a breakpoint on a page of its own, removed again afterwards
+        match emulator.map_private(0, page_size, MmapPerms::ReadExecute) {
+            Ok(ret_addr) => {
+                emulator.set_breakpoint(ret_addr);
+                if let Err(err) = self.spray(emulator, ret_addr) {
+                    log::warn!("The heap spray failed: {err}");
+                }
+                emulator.remove_breakpoint(ret_addr);
+                if let Err(err) = emulator.unmap(ret_addr, page_size) {
+                    log::warn!("Failed to unmap the return page of the heap spray: {err}");
+                }
+            }
+            Err(err) => log::warn!("The heap spray failed: {err}"),
+        }
+        cpu.restore_state(&saved);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HeapSprayBatch, HeapSprayConfig};
+
+    #[test]
+    fn test_heap_spray_config() {
+        let config: HeapSprayConfig = toml::from_str(
+            r#"
+            [[batches]]
+            size = 0x40
+            count = 6
+            free_every = 2
+
+            [[batches]]
+            size = 0x400
+            count = 4
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.batches,
+            [
+                HeapSprayBatch {
+                    size: 0x40,
+                    count: 6,
+                    free_every: 2,
+                },
+                HeapSprayBatch {
+                    size: 0x400,
+                    count: 4,
+                    free_every: 0,
+                },
+            ]
+        );
+
+        // every second chunk of the first batch is freed, none of the second one
+        let chunks = vec![0x10, 0x20, 0x30, 0x40, 0x50, 0x60];
+        assert_eq!(config.batches[0].freed(chunks.clone()), [0x20, 0x40, 0x60]);
+        assert!(config.batches[1].freed(chunks).is_empty());
+    }
+}
@@ -0,0 +1,179 @@
+//! Restricting the coverage collection to some of the modules mapped into the target.
+//!
+//! By default, the edge coverage helpers instrument all the code, libc included, filling the map with edges
+//! that are rarely of interest. With a [`LibraryFilterHelper`] in the helpers tuple, they skip the blocks
+//! outside of the selected modules.
+
+use core::ops::Range;
+
+use libafl::inputs::UsesInput;
+
+use crate::{
+    emu::Emulator,
+    helper::{QemuHelper, QemuInstrumentationAddressRangeFilter},
+};
+
+/// Selects the modules to collect coverage for, by name.
+///
+/// Each pattern is matched against the path of a mapping and against its file name,
+/// and may contain the wildcards `*` and `?`, e.g. `libpng*.so*`.
+/// The address ranges of the modules are resolved when the helper is created,
+/// call [`LibraryFilterHelper::update_ranges`] after the target loaded more libraries.
+#[derive(Debug)]
+pub struct LibraryFilterHelper {
+    patterns: Vec<String>,
+    ranges: Vec<Range<u64>>,
+}
+
+impl LibraryFilterHelper {
+    /// Creates a new [`LibraryFilterHelper`] for the modules matching `patterns`, currently mapped in `emulator`
+    #[must_use]
+    pub fn new(emulator: &Emulator, patterns: Vec<String>) -> Self {
+        let mut helper = Self {
+            patterns,
+            ranges: Vec::new(),
+        };
+        helper.update_ranges(emulator);
+        helper
+    }
+
+    /// Resolves the address ranges of the selected modules again, from the current mappings of `emulator`.
+    /// The JIT cache is flushed, so that the blocks are instrumented anew.
+    pub fn update_ranges(&mut self, emulator: &Emulator) {
+        self.ranges.clear();
+        for map in emulator.mappings() {
+            let Some(path) = map.path() else {
+                continue;
+            };
+            if module_matches(&self.patterns, path) {
+                // GuestAddress is u32 for 32 bit guests
+                #[allow(clippy::unnecessary_cast)]
+                self.ranges.push(map.start() as u64..map.end() as u64);
+            }
+        }
+        log::info!(
+            "Collecting coverage in {} mappings matching {:?}",
+            self.ranges.len(),
+            self.patterns
+        );
+        emulator.flush_jit();
+    }
+
+    /// The patterns of the selected modules
+    #[must_use]
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    /// The address ranges of the selected modules
+    #[must_use]
+    pub fn ranges(&self) -> &[Range<u64>] {
+        &self.ranges
+    }
+
+    /// Returns `true` if coverage is collected for the block at `pc`
+    #[must_use]
+    pub fn is_covered_pc(&self, pc: u64) -> bool {
+        self.ranges.iter().any(|range| range.contains(&pc))
+    }
+
+    /// An allow list of the address ranges of the selected modules, for the helpers taking an address filter
+    #[must_use]
+    #[allow(clippy::unnecessary_cast)]
+    pub fn address_filter(&self) -> QemuInstrumentationAddressRangeFilter {
+        QemuInstrumentationAddressRangeFilter::AllowList(
+            self.ranges
+                .iter()
+                .map(|range| range.start as _..range.end as _)
+                .collect(),
+        )
+    }
+}
+
+impl<S> QemuHelper<S> for LibraryFilterHelper
+where
+    S: UsesInput,
+{
+    const HOOKS_DO_SIDE_EFFECTS: bool = false;
+}
+
+/// Returns `true` if any of the `patterns` matches the whole `path` of a mapping, or its file name
+fn module_matches(patterns: &[String], path: &str) -> bool {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    patterns
+        .iter()
+        .any(|pattern| glob_match(pattern, path) || glob_match(pattern, file_name))
+}
+
+/// Matches `text` against `pattern`, where `*` matches any sequence of characters and `?` any single one
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.as_bytes();
+    let text = text.as_bytes();
+    let (mut p, mut t) = (0, 0);
+    // the position after the last `*`, and the text position it was tried at
+    let mut backtrack = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == b'?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == b'*' {
+            p += 1;
+            backtrack = Some((p, t));
+        } else if let Some((star_p, star_t)) = backtrack {
+            // let the last `*` match one more character
+            p = star_p;
+            t = star_t + 1;
+            backtrack = Some((star_p, t));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{glob_match, module_matches};
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("libc.so.6", "libc.so.6"));
+        assert!(!glob_match("libc.so", "libc.so.6"));
+
+        // `*` matches any sequence, including an empty one
+        assert!(glob_match("libpng*.so*", "libpng16.so.16"));
+        assert!(glob_match("libpng*.so*", "libpng.so"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a*b*c", "axxbyybc"));
+        assert!(!glob_match("a*b*c", "axxbyyb"));
+
+        // `?` matches exactly one character
+        assert!(glob_match("libz.so.?", "libz.so.1"));
+        assert!(!glob_match("libz.so.?", "libz.so."));
+        assert!(!glob_match("libz.so.?", "libz.so.12"));
+    }
+
+    #[test]
+    fn test_module_matches() {
+        let path = "/usr/lib/x86_64-linux-gnu/libpng16.so.16.37.0";
+
+        // patterns without a directory match the file name
+        assert!(module_matches(&["libpng*".to_string()], path));
+        assert!(!module_matches(&["png*".to_string()], path));
+
+        // patterns with a directory are anchored at both ends of the path
+        assert!(module_matches(&["/usr/lib/*/libpng*".to_string()], path));
+        assert!(!module_matches(&["/lib/*/libpng*".to_string()], path));
+        assert!(!module_matches(
+            &["/usr/lib/*/libpng16.so".to_string()],
+            path
+        ));
+        assert!(module_matches(&["*/libpng16.so.*".to_string()], path));
+
+        assert!(module_matches(
+            &["libc.so*".to_string(), "libpng16.so.16.37.0".to_string()],
+            path
+        ));
+        assert!(!module_matches(&[], path));
+    }
+}
@@ -0,0 +1,6 @@
+//! Helpers selecting what the other helpers instrument
+
+#[cfg(emulation_mode = "usermode")]
+pub mod filter;
+#[cfg(emulation_mode = "usermode")]
+pub use filter::LibraryFilterHelper;
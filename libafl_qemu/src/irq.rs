@@ -0,0 +1,314 @@
+//! Fuzzing the interrupt handling of system-mode targets.
+//!
+//! Interrupts arriving at unexpected points of the kernel are a common source of races and reentrancy bugs,
+//! but the emulated devices only raise them at the points the input happens to trigger.
+//! The [`IrqFuzzingHelper`] raises random interrupt lines of an interrupt controller while the target runs:
+//! before each executed basic block, it pulses one of the configured lines with a given probability.
+//! The random choices are seeded with a hash of the input, so an input always fires the same interrupts
+//! before the same blocks, and crashes found with an interrupt replay.
+//! The pairs of block and interrupt fired during a run end up in an [`IrqObserver`], and the
+//! [`IrqCoverageFeedback`] keeps the inputs firing an interrupt in a block it was never fired in before.
+//!
+//! The lines are those of a device in the QEMU object model, usually the interrupt controller,
+//! given by its QOM path, e.g. `/machine/ioapic` on `x86_64` (see `info qom-tree` in the QEMU monitor).
+//! The interrupts are raised from the vCPU thread, so this needs single-threaded TCG (`-accel tcg,thread=single`).
+
+use std::{
+    ffi::{c_char, c_int, c_void, CString},
+    ptr,
+};
+
+use hashbrown::HashSet;
+use libafl::{
+    events::EventFirer,
+    executors::ExitKind,
+    feedbacks::{Feedback, HasObserverName},
+    inputs::{HasTargetBytes, UsesInput},
+    observers::{Observer, ObserversTuple},
+    state::{HasNamedMetadata, State},
+    Error,
+};
+use libafl_bolts::{
+    hash_std,
+    rands::{Rand, StdRand},
+    AsSlice, Named,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    emu::{Emulator, GuestAddr},
+    helper::{QemuHelper, QemuHelperTuple},
+    hooks::{Hook, QemuHooks},
+};
+
+/// The prefix of the metadata names
+pub const IRQCOVERAGEFEEDBACK_PREFIX: &str = "irqcoveragefeedback_metadata_";
+
+/// The resolution of the probability to fire an interrupt
+const PROBABILITY_SCALE: u64 = 1 << 20;
+
+/// A `qemu_irq` of QEMU, an opaque handle of an interrupt line
+type QemuIrq = *mut c_void;
+
+extern "C" {
+    fn object_resolve_path(path: *const c_char, ambiguous: *mut bool) -> *mut c_void;
+    fn qdev_get_gpio_in(dev: *mut c_void, n: c_int) -> QemuIrq;
+    fn qemu_set_irq(irq: QemuIrq, level: c_int);
+}
+
+/// Pulses random interrupt lines of a device between basic blocks, see the [module documentation](self)
+#[derive(Debug)]
+pub struct IrqFuzzingHelper {
+    device_path: String,
+    irqs: Vec<u32>,
+    /// The interrupt lines of `irqs`, resolved on the first interrupt
+    lines: Option<Vec<QemuIrq>>,
+    probability: f64,
+    /// Seeded from the input before each run
+    rand: StdRand,
+    /// The pairs of block and interrupt fired in the current run
+    fired: Vec<(GuestAddr, u32)>,
+    observer_name: String,
+}
+
+impl IrqFuzzingHelper {
+    /// Creates a new [`IrqFuzzingHelper`], pulsing the input lines `irqs` of the device at the QOM path `device_path`
+    /// before each basic block with the given `probability`, and reporting them to the given [`IrqObserver`].
+    #[must_use]
+    pub fn new(
+        device_path: &str,
+        irqs: Vec<u32>,
+        probability: f64,
+        observer: &IrqObserver,
+    ) -> Self {
+        Self {
+            device_path: device_path.to_string(),
+            irqs,
+            lines: None,
+            probability: probability.clamp(0.0, 1.0),
+            rand: StdRand::with_seed(0),
+            fired: Vec::new(),
+            observer_name: observer.name().to_string(),
+        }
+    }
+
+    /// The interrupt numbers fired by this helper
+    #[must_use]
+    pub fn irqs(&self) -> &[u32] {
+        &self.irqs
+    }
+
+    /// Looks up the interrupt lines in the QEMU object model
+    #[allow(clippy::cast_possible_wrap)]
+    fn resolve_lines(&self) -> Vec<QemuIrq> {
+        let path = CString::new(self.device_path.as_str()).unwrap();
+        let device = unsafe { object_resolve_path(path.as_ptr(), ptr::null_mut()) };
+        assert!(
+            !device.is_null(),
+            "IrqFuzzingHelper: there is no device at {}",
+            self.device_path
+        );
+        self.irqs
+            .iter()
+            .map(|irq| unsafe { qdev_get_gpio_in(device, *irq as c_int) })
+            .collect()
+    }
+
+    /// With the configured probability, pulses a random interrupt line before the block at `pc`
+    #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+    pub fn maybe_fire(&mut self, pc: GuestAddr) {
+        if self.irqs.is_empty()
+            || self.rand.below(PROBABILITY_SCALE) as f64
+                >= self.probability * PROBABILITY_SCALE as f64
+        {
+            return;
+        }
+        if self.lines.is_none() {
+            self.lines = Some(self.resolve_lines());
+        }
+        let idx = self.rand.below(self.irqs.len() as u64) as usize;
+        let line = self.lines.as_ref().unwrap()[idx];
+        // a pulse, so a level-triggered line does not stay asserted, and fire again and again
+        unsafe {
+            qemu_set_irq(line, 1);
+            qemu_set_irq(line, 0);
+        }
+        self.fired.push((pc, self.irqs[idx]));
+    }
+}
+
+impl<S> QemuHelper<S> for IrqFuzzingHelper
+where
+    S: UsesInput,
+    S::Input: HasTargetBytes,
+{
+    fn first_exec<QT>(&self, hooks: &QemuHooks<QT, S>)
+    where
+        QT: QemuHelperTuple<S>,
+    {
+        hooks.blocks(
+            Hook::Function(gen_irq_block_ids::<QT, S>),
+            Hook::Empty,
+            Hook::Function(exec_irq_block::<QT, S>),
+        );
+    }
+
+    fn pre_exec(&mut self, _emulator: &Emulator, input: &S::Input) {
+        // the interrupts are part of the execution of the input, so they have to replay with it
+        self.rand
+            .set_seed(hash_std(input.target_bytes().as_slice()));
+        self.fired.clear();
+    }
+
+    fn post_exec<OT>(
+        &mut self,
+        _emulator: &Emulator,
+        _input: &S::Input,
+        observers: &mut OT,
+        _exit_kind: &mut ExitKind,
+    ) where
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .match_name_mut::<IrqObserver>(&self.observer_name)
+            .expect("An IrqFuzzingHelper needs an IrqObserver");
+        observer.fired.clone_from(&self.fired);
+    }
+}
+
+/// The block generation hook of the [`IrqFuzzingHelper`], identifying each block by its address
+pub fn gen_irq_block_ids<QT, S>(
+    _hooks: &mut QemuHooks<QT, S>,
+    _state: Option<&mut S>,
+    pc: GuestAddr,
+) -> Option<u64>
+where
+    S: UsesInput,
+    QT: QemuHelperTuple<S>,
+{
+    Some(pc as u64)
+}
+
+/// The block execution hook of the [`IrqFuzzingHelper`], possibly firing an interrupt
+#[allow(clippy::cast_possible_truncation)]
+pub fn exec_irq_block<QT, S>(hooks: &mut QemuHooks<QT, S>, _state: Option<&mut S>, id: u64)
+where
+    S: UsesInput,
+    QT: QemuHelperTuple<S>,
+{
+    if let Some(helper) = hooks
+        .helpers_mut()
+        .match_first_type_mut::<IrqFuzzingHelper>()
+    {
+        helper.maybe_fire(id as GuestAddr);
+    }
+}
+
+/// Holds the pairs of basic block and interrupt fired in the last execution, set by an [`IrqFuzzingHelper`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IrqObserver {
+    name: String,
+    fired: Vec<(GuestAddr, u32)>,
+}
+
+impl IrqObserver {
+    /// Creates a new [`IrqObserver`] with the given name, to be passed to the [`IrqFuzzingHelper`]
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            fired: Vec::new(),
+        }
+    }
+
+    /// The blocks and the interrupts fired before them in the last execution, in order
+    #[must_use]
+    pub fn fired(&self) -> &[(GuestAddr, u32)] {
+        &self.fired
+    }
+}
+
+impl<S> Observer<S> for IrqObserver where S: UsesInput {}
+
+impl Named for IrqObserver {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// The pairs of basic block and interrupt seen by an [`IrqCoverageFeedback`]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[allow(clippy::unsafe_derive_deserialize)]
+pub struct IrqCoverageMetadata {
+    /// The pairs of block address and interrupt fired so far
+    pub tried: HashSet<(u64, u32)>,
+}
+
+libafl_bolts::impl_serdeany!(IrqCoverageMetadata);
+
+/// Considers an input interesting if it fired an interrupt before a basic block it was never fired before
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IrqCoverageFeedback {
+    name: String,
+    observer_name: String,
+}
+
+impl IrqCoverageFeedback {
+    /// Creates a new [`IrqCoverageFeedback`] for the pairs reported by the given [`IrqObserver`]
+    #[must_use]
+    pub fn new(observer: &IrqObserver) -> Self {
+        Self {
+            name: IRQCOVERAGEFEEDBACK_PREFIX.to_string() + observer.name(),
+            observer_name: observer.name().to_string(),
+        }
+    }
+}
+
+impl<S> Feedback<S> for IrqCoverageFeedback
+where
+    S: State + HasNamedMetadata,
+{
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error> {
+        state.add_named_metadata(IrqCoverageMetadata::default(), &self.name);
+        Ok(())
+    }
+
+    fn is_interesting<EM, OT>(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _input: &S::Input,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .match_name::<IrqObserver>(&self.observer_name)
+            .expect("An IrqCoverageFeedback needs an IrqObserver");
+
+        let meta = state
+            .named_metadata_map_mut()
+            .get_mut::<IrqCoverageMetadata>(&self.name)
+            .unwrap();
+        let mut interesting = false;
+        for (pc, irq) in observer.fired() {
+            interesting |= meta.tried.insert((*pc as u64, *irq));
+        }
+        Ok(interesting)
+    }
+}
+
+impl Named for IrqCoverageFeedback {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl HasObserverName for IrqCoverageFeedback {
+    fn observer_name(&self) -> &str {
+        &self.observer_name
+    }
+}
@@ -92,6 +92,84 @@ pub mod snapshot;
 #[cfg(all(emulation_mode = "usermode", not(cpu_target = "hexagon")))]
 pub use snapshot::QemuSnapshotHelper;
 
+#[cfg(all(emulation_mode = "usermode", not(cpu_target = "hexagon")))]
+pub mod guest_fs;
+#[cfg(all(emulation_mode = "usermode", not(cpu_target = "hexagon")))]
+pub use guest_fs::GuestFileSystemHelper;
+
+#[cfg(all(emulation_mode = "usermode", not(cpu_target = "hexagon")))]
+pub mod syscall_replay;
+#[cfg(all(emulation_mode = "usermode", not(cpu_target = "hexagon")))]
+pub use syscall_replay::{RecordedSyscall, SyscallReplayHelper, SyscallReplayMode};
+
+#[cfg(emulation_mode = "systemmode")]
+pub mod network;
+#[cfg(emulation_mode = "systemmode")]
+pub use network::{NetworkResponseObserver, NetworkStubHelper, NewNetworkBehaviorFeedback};
+
+#[cfg(emulation_mode = "systemmode")]
+pub mod tap_network;
+#[cfg(emulation_mode = "systemmode")]
+pub use tap_network::TapNetworkHelper;
+
+#[cfg(emulation_mode = "systemmode")]
+pub mod cow_snapshot;
+#[cfg(emulation_mode = "systemmode")]
+pub use cow_snapshot::{CowSnapshotHelper, SnapshotStats, SnapshotStatsObserver};
+
+#[cfg(emulation_mode = "systemmode")]
+pub mod irq;
+#[cfg(emulation_mode = "systemmode")]
+pub use irq::{IrqCoverageFeedback, IrqCoverageMetadata, IrqFuzzingHelper, IrqObserver};
+
+#[cfg(emulation_mode = "systemmode")]
+pub mod virtio_block;
+#[cfg(emulation_mode = "systemmode")]
+pub use virtio_block::{PartitionType, VirtioBlockHelper, VirtioBlockHelperBuilder};
+
+#[cfg(emulation_mode = "usermode")]
+pub mod function_coverage;
+#[cfg(emulation_mode = "usermode")]
+pub use function_coverage::{FunctionCoverageHelper, FunctionCoverageObserver};
+
+#[cfg(all(emulation_mode = "usermode", feature = "afl_compat"))]
+pub mod afl_compat;
+#[cfg(all(emulation_mode = "usermode", feature = "afl_compat"))]
+pub use afl_compat::AflCoverageHelper;
+
+pub mod helpers;
+#[cfg(emulation_mode = "usermode")]
+pub use helpers::LibraryFilterHelper;
+
+#[cfg(emulation_mode = "usermode")]
+pub mod memory_frequency;
+#[cfg(emulation_mode = "usermode")]
+pub use memory_frequency::{
+    ColdAddressFeedback, MemoryAccessFrequencyHelper, MemoryAccessFrequencyObserver,
+};
+
+#[cfg(emulation_mode = "usermode")]
+pub mod register_values;
+#[cfg(emulation_mode = "usermode")]
+pub use register_values::{
+    RegisterSnapshot, RegisterValueFeedback, RegisterValueHelper, RegisterValueObserver,
+};
+
+#[cfg(emulation_mode = "usermode")]
+pub mod return_values;
+#[cfg(emulation_mode = "usermode")]
+pub use return_values::{ReturnValueDiffFeedback, ReturnValueHelper, ReturnValueObserver};
+
+#[cfg(emulation_mode = "usermode")]
+pub mod stack_depth;
+#[cfg(emulation_mode = "usermode")]
+pub use stack_depth::StackDepthLimitHelper;
+
+#[cfg(all(emulation_mode = "usermode", feature = "heap_layout"))]
+pub mod heap_layout;
+#[cfg(all(emulation_mode = "usermode", feature = "heap_layout"))]
+pub use heap_layout::{HeapLayoutHelper, HeapSprayBatch, HeapSprayConfig};
+
 #[cfg(all(emulation_mode = "usermode", not(cpu_target = "hexagon")))]
 pub mod asan;
 #[cfg(all(emulation_mode = "usermode", not(cpu_target = "hexagon")))]
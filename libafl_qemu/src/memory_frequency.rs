@@ -0,0 +1,342 @@
+//! Counting the memory accesses of the target per address, to find the rarely accessed ones.
+//!
+//! The [`MemoryAccessFrequencyHelper`] hooks all memory reads and writes, and hands the counts of an execution
+//! to a [`MemoryAccessFrequencyObserver`]. The [`ColdAddressFeedback`] considers inputs interesting
+//! if they access new addresses outside of the hot set, the addresses accessed the most over all executions.
+//!
+//! The accesses are counted per bucket of neighbouring addresses, by default a cache line,
+//! and the feedback tracks a bounded number of buckets, so its metadata does not grow without bound.
+
+use hashbrown::{HashMap, HashSet};
+use libafl::{
+    events::EventFirer,
+    executors::ExitKind,
+    feedbacks::{Feedback, HasObserverName},
+    inputs::UsesInput,
+    observers::{Observer, ObserversTuple},
+    state::{HasNamedMetadata, State},
+    Error,
+};
+use libafl_bolts::Named;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    emu::{Emulator, GuestAddr},
+    helper::{QemuHelper, QemuHelperTuple},
+    hooks::{Hook, QemuHooks},
+};
+
+/// The count of accesses to an address saturates at this value
+pub const MEMORY_ACCESS_COUNT_MAX: u32 = 65535;
+
+/// The default number of addresses in the hot set
+pub const DEFAULT_HOT_SET_SIZE: usize = 1000;
+
+/// The default number of low address bits dropped to get the bucket of an access, one bucket per cache line
+pub const DEFAULT_ADDRESS_BUCKET_BITS: u32 = 6;
+
+/// The default number of buckets tracked by a [`ColdAddressFeedback`]
+pub const DEFAULT_MAX_TRACKED_ADDRESSES: usize = 1 << 16;
+
+/// Counts the memory accesses per bucket of addresses, and stores them into a [`MemoryAccessFrequencyObserver`]
+/// after the execution.
+#[derive(Debug)]
+pub struct MemoryAccessFrequencyHelper {
+    counts: HashMap<u64, u32>,
+    bucket_bits: u32,
+    observer_name: String,
+}
+
+impl MemoryAccessFrequencyHelper {
+    /// Creates a new [`MemoryAccessFrequencyHelper`] reporting to the given observer,
+    /// counting the accesses per cache line
+    #[must_use]
+    pub fn new(observer: &MemoryAccessFrequencyObserver) -> Self {
+        Self::with_bucket_bits(observer, DEFAULT_ADDRESS_BUCKET_BITS)
+    }
+
+    /// Creates a new [`MemoryAccessFrequencyHelper`] reporting to the given observer,
+    /// counting the accesses per bucket of `1 << bucket_bits` addresses
+    #[must_use]
+    pub fn with_bucket_bits(observer: &MemoryAccessFrequencyObserver, bucket_bits: u32) -> Self {
+        Self {
+            counts: HashMap::new(),
+            bucket_bits: bucket_bits.min(63),
+            observer_name: observer.name().to_string(),
+        }
+    }
+
+    fn access(&mut self, addr: GuestAddr) {
+        // the observer reports the first address of the bucket
+        let bucket = (u64::from(addr) >> self.bucket_bits) << self.bucket_bits;
+        let count = self.counts.entry(bucket).or_default();
+        if *count < MEMORY_ACCESS_COUNT_MAX {
+            *count += 1;
+        }
+    }
+}
+
+impl<S> QemuHelper<S> for MemoryAccessFrequencyHelper
+where
+    S: UsesInput,
+{
+    fn first_exec<QT>(&self, hooks: &QemuHooks<QT, S>)
+    where
+        QT: QemuHelperTuple<S>,
+    {
+        hooks.reads(
+            Hook::Empty,
+            Hook::Function(trace_access::<QT, S>),
+            Hook::Function(trace_access::<QT, S>),
+            Hook::Function(trace_access::<QT, S>),
+            Hook::Function(trace_access::<QT, S>),
+            Hook::Function(trace_access_n::<QT, S>),
+        );
+        hooks.writes(
+            Hook::Empty,
+            Hook::Function(trace_access::<QT, S>),
+            Hook::Function(trace_access::<QT, S>),
+            Hook::Function(trace_access::<QT, S>),
+            Hook::Function(trace_access::<QT, S>),
+            Hook::Function(trace_access_n::<QT, S>),
+        );
+    }
+
+    fn pre_exec(&mut self, _emulator: &Emulator, _input: &S::Input) {
+        self.counts.clear();
+    }
+
+    fn post_exec<OT>(
+        &mut self,
+        _emulator: &Emulator,
+        _input: &S::Input,
+        observers: &mut OT,
+        _exit_kind: &mut ExitKind,
+    ) where
+        OT: ObserversTuple<S>,
+    {
+        let Some(observer) =
+            observers.match_name_mut::<MemoryAccessFrequencyObserver>(&self.observer_name)
+        else {
+            log::error!(
+                "MemoryAccessFrequencyHelper: there is no MemoryAccessFrequencyObserver named {}",
+                self.observer_name
+            );
+            return;
+        };
+        core::mem::swap(&mut observer.counts, &mut self.counts);
+    }
+}
+
+/// The memory access hook of the [`MemoryAccessFrequencyHelper`], for accesses of 1 to 8 bytes.
+/// Only the first byte of an access is counted.
+fn trace_access<QT, S>(
+    hooks: &mut QemuHooks<QT, S>,
+    _state: Option<&mut S>,
+    _id: u64,
+    addr: GuestAddr,
+) where
+    QT: QemuHelperTuple<S>,
+    S: UsesInput,
+{
+    if let Some(helper) = hooks
+        .helpers_mut()
+        .match_first_type_mut::<MemoryAccessFrequencyHelper>()
+    {
+        helper.access(addr);
+    }
+}
+
+/// The memory access hook of the [`MemoryAccessFrequencyHelper`], for accesses of other sizes
+fn trace_access_n<QT, S>(
+    hooks: &mut QemuHooks<QT, S>,
+    state: Option<&mut S>,
+    id: u64,
+    addr: GuestAddr,
+    _size: usize,
+) where
+    QT: QemuHelperTuple<S>,
+    S: UsesInput,
+{
+    trace_access(hooks, state, id, addr);
+}
+
+/// Holds the number of accesses to each bucket of addresses in the last execution, by the first address of the bucket
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryAccessFrequencyObserver {
+    name: String,
+    counts: HashMap<u64, u32>,
+}
+
+impl MemoryAccessFrequencyObserver {
+    /// Creates a new [`MemoryAccessFrequencyObserver`] with the given name
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            counts: HashMap::new(),
+        }
+    }
+
+    /// The number of accesses to each address in the last execution, saturating at [`MEMORY_ACCESS_COUNT_MAX`]
+    #[must_use]
+    pub fn counts(&self) -> &HashMap<u64, u32> {
+        &self.counts
+    }
+
+    /// The `size` addresses accessed the most in the last execution
+    #[must_use]
+    pub fn hot_set(&self, size: usize) -> HashSet<u64> {
+        hottest(&self.counts, size)
+    }
+}
+
+/// The `size` addresses with the highest counts
+fn hottest(counts: &HashMap<u64, u32>, size: usize) -> HashSet<u64> {
+    let mut by_count: Vec<(&u64, &u32)> = counts.iter().collect();
+    if by_count.len() > size {
+        by_count.select_nth_unstable_by(size, |a, b| b.1.cmp(a.1));
+        by_count.truncate(size);
+    }
+    by_count.into_iter().map(|(addr, _)| *addr).collect()
+}
+
+impl<S> Observer<S> for MemoryAccessFrequencyObserver
+where
+    S: UsesInput,
+{
+    fn pre_exec(&mut self, _state: &mut S, _input: &S::Input) -> Result<(), Error> {
+        self.counts.clear();
+        Ok(())
+    }
+}
+
+impl Named for MemoryAccessFrequencyObserver {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// The prefix of the [`ColdAddressMetadata`] names
+pub const COLDADDRESSFEEDBACK_PREFIX: &str = "coldaddressfeedback_metadata_";
+
+/// The access counts over all executions seen by a [`ColdAddressFeedback`], and the resulting hot set
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[allow(clippy::unsafe_derive_deserialize)]
+pub struct ColdAddressMetadata {
+    /// The number of accesses to each address over all executions, saturating at [`MEMORY_ACCESS_COUNT_MAX`]
+    pub counts: HashMap<u64, u32>,
+    /// The addresses accessed the most
+    pub hot: HashSet<u64>,
+    /// The addresses outside of the hot set accessed in an earlier execution
+    pub cold: HashSet<u64>,
+}
+
+libafl_bolts::impl_serdeany!(ColdAddressMetadata);
+
+/// Considers an input interesting if it accessed an address outside of the hot set that no earlier execution accessed
+///
+/// The hot set is recomputed from the counts over all executions each time an input is interesting.
+/// Once `max_addresses` addresses are tracked, new addresses are neither counted nor interesting.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColdAddressFeedback {
+    name: String,
+    observer_name: String,
+    hot_set_size: usize,
+    max_addresses: usize,
+}
+
+impl ColdAddressFeedback {
+    /// Creates a new [`ColdAddressFeedback`] for the given observer, with [`DEFAULT_HOT_SET_SIZE`] addresses
+    /// in the hot set
+    #[must_use]
+    pub fn new(observer: &MemoryAccessFrequencyObserver) -> Self {
+        Self::with_hot_set_size(observer, DEFAULT_HOT_SET_SIZE)
+    }
+
+    /// Creates a new [`ColdAddressFeedback`] with `hot_set_size` addresses in the hot set
+    #[must_use]
+    pub fn with_hot_set_size(
+        observer: &MemoryAccessFrequencyObserver,
+        hot_set_size: usize,
+    ) -> Self {
+        Self {
+            name: COLDADDRESSFEEDBACK_PREFIX.to_string() + observer.name(),
+            observer_name: observer.name().to_string(),
+            hot_set_size,
+            max_addresses: DEFAULT_MAX_TRACKED_ADDRESSES,
+        }
+    }
+
+    /// Tracks at most `max_addresses` addresses, [`DEFAULT_MAX_TRACKED_ADDRESSES`] by default
+    #[must_use]
+    pub fn with_max_addresses(mut self, max_addresses: usize) -> Self {
+        self.max_addresses = max_addresses;
+        self
+    }
+}
+
+impl<S> Feedback<S> for ColdAddressFeedback
+where
+    S: State + HasNamedMetadata,
+{
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error> {
+        state.add_named_metadata(ColdAddressMetadata::default(), &self.name);
+        Ok(())
+    }
+
+    fn is_interesting<EM, OT>(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _input: &S::Input,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .match_name::<MemoryAccessFrequencyObserver>(&self.observer_name)
+            .ok_or_else(|| {
+                Error::key_not_found(format!(
+                    "MemoryAccessFrequencyObserver {} not found",
+                    self.observer_name
+                ))
+            })?;
+
+        let meta = state
+            .named_metadata_map_mut()
+            .get_mut::<ColdAddressMetadata>(&self.name)
+            .ok_or_else(|| Error::illegal_state("ColdAddressMetadata not in the state"))?;
+        let mut interesting = false;
+        for (addr, count) in observer.counts() {
+            if meta.counts.len() >= self.max_addresses && !meta.counts.contains_key(addr) {
+                continue;
+            }
+            let total = meta.counts.entry(*addr).or_default();
+            *total = total.saturating_add(*count).min(MEMORY_ACCESS_COUNT_MAX);
+            if !meta.hot.contains(addr) {
+                interesting |= meta.cold.insert(*addr);
+            }
+        }
+
+        if interesting {
+            meta.hot = hottest(&meta.counts, self.hot_set_size);
+        }
+        Ok(interesting)
+    }
+}
+
+impl Named for ColdAddressFeedback {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl HasObserverName for ColdAddressFeedback {
+    fn observer_name(&self) -> &str {
+        &self.observer_name
+    }
+}
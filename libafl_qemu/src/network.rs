@@ -0,0 +1,292 @@
+//! Packet-level fuzzing of system-mode targets through their emulated network device.
+//!
+//! The [`NetworkStubHelper`] takes the place of the network backend of an emulated NIC, e.g. `virtio-net`
+//! or `e1000`: QEMU's `socket` netdev is connected to one end of a socket pair, and the helper holds the other.
+//! The guest keeps its regular network driver. Before each run, the input is queued as a frame received by the NIC,
+//! and after the run, the frames the NIC transmitted are collected into a [`NetworkResponseObserver`].
+//!
+//! QEMU writes the transmitted frames to the socket as soon as the guest hands them to the NIC,
+//! so all of them are there when the run ends. Pass the arguments of [`NetworkStubHelper::qemu_args`]
+//! to the [`Emulator`], together with a NIC using the `netdev`, e.g. `-device virtio-net-pci,netdev=stub0`.
+
+use std::{
+    io,
+    os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, OwnedFd, RawFd},
+};
+
+use hashbrown::HashSet;
+use libafl::{
+    events::EventFirer,
+    executors::ExitKind,
+    feedbacks::{Feedback, HasObserverName},
+    inputs::{HasTargetBytes, UsesInput},
+    observers::{Observer, ObserversTuple},
+    state::{HasNamedMetadata, State},
+    Error,
+};
+use libafl_bolts::{hash_std, AsSlice, Named};
+use serde::{Deserialize, Serialize};
+
+use crate::{emu::Emulator, helper::QemuHelper};
+
+/// The maximum size of a frame, longer inputs are truncated
+pub const MAX_STUB_FRAME_SIZE: usize = 65536;
+
+/// The size of the length prefix of the frames on the socket of QEMU's `socket` netdev
+const FRAME_LEN_PREFIX: usize = 4;
+
+/// Feeds each input to the emulated NIC as a received frame, and records the frames it transmits
+/// into a [`NetworkResponseObserver`], see the [module documentation](self).
+///
+/// The frames transmitted during a run are concatenated, in the order they were transmitted.
+#[derive(Debug)]
+pub struct NetworkStubHelper {
+    /// The end of the socket pair of the helper
+    sock: OwnedFd,
+    /// The end of the socket pair of QEMU, handed over to it and never closed by the helper
+    qemu_fd: RawFd,
+    /// The bytes read from the socket that do not form a whole frame yet
+    pending: Vec<u8>,
+    observer_name: String,
+}
+
+impl NetworkStubHelper {
+    /// Creates a new [`NetworkStubHelper`], reporting the transmitted frames to the given observer
+    pub fn new(observer: &NetworkResponseObserver) -> Result<Self, Error> {
+        let mut fds = [0; 2];
+        if unsafe { libc::socketpair(libc::AF_UNIX, libc::SOCK_STREAM, 0, fds.as_mut_ptr()) } < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        let sock = unsafe { OwnedFd::from_raw_fd(fds[0]) };
+        let qemu_fd = unsafe { OwnedFd::from_raw_fd(fds[1]) }.into_raw_fd();
+        if unsafe { libc::fcntl(sock.as_raw_fd(), libc::F_SETFL, libc::O_NONBLOCK) } < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        Ok(Self {
+            sock,
+            qemu_fd,
+            pending: Vec::new(),
+            observer_name: observer.name().to_string(),
+        })
+    }
+
+    /// The QEMU arguments connecting the `netdev` named `netdev_id` to the helper,
+    /// QEMU has to run in this process
+    #[must_use]
+    pub fn qemu_args(&self, netdev_id: &str) -> Vec<String> {
+        vec![
+            "-netdev".to_string(),
+            format!("socket,id={netdev_id},fd={}", self.qemu_fd),
+        ]
+    }
+
+    /// Reads the frames QEMU wrote to the socket so far, with their payloads concatenated
+    fn read_frames(&mut self) -> Vec<u8> {
+        let mut buf = [0; 4096];
+        loop {
+            let len =
+                unsafe { libc::recv(self.sock.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len(), 0) };
+            // EAGAIN, nothing left to read
+            let Ok(len) = usize::try_from(len) else {
+                break;
+            };
+            if len == 0 {
+                break;
+            }
+            self.pending.extend_from_slice(&buf[..len]);
+        }
+
+        let mut frames = Vec::new();
+        let mut pos = 0;
+        while let Some(prefix) = self.pending.get(pos..pos + FRAME_LEN_PREFIX) {
+            let len = u32::from_be_bytes(prefix.try_into().unwrap()) as usize;
+            let Some(frame) = self
+                .pending
+                .get(pos + FRAME_LEN_PREFIX..pos + FRAME_LEN_PREFIX + len)
+            else {
+                break;
+            };
+            frames.extend_from_slice(frame);
+            pos += FRAME_LEN_PREFIX + len;
+        }
+        self.pending.drain(..pos);
+        frames
+    }
+}
+
+impl<S> QemuHelper<S> for NetworkStubHelper
+where
+    S: UsesInput,
+    S::Input: HasTargetBytes,
+{
+    fn pre_exec(&mut self, _emulator: &Emulator, input: &S::Input) {
+        // drop what the guest sent between the runs, a frame still being written is kept
+        // in `pending` so that the stream stays in sync with the length prefixes
+        let dropped = self.read_frames();
+        if !dropped.is_empty() {
+            log::debug!(
+                "Dropped {} bytes sent by the NIC between runs",
+                dropped.len()
+            );
+        }
+
+        let target_bytes = input.target_bytes();
+        let frame =
+            &target_bytes.as_slice()[..target_bytes.as_slice().len().min(MAX_STUB_FRAME_SIZE)];
+        let mut message = Vec::with_capacity(FRAME_LEN_PREFIX + frame.len());
+        message.extend_from_slice(&u32::try_from(frame.len()).unwrap().to_be_bytes());
+        message.extend_from_slice(frame);
+        let written = unsafe {
+            libc::send(
+                self.sock.as_raw_fd(),
+                message.as_ptr().cast(),
+                message.len(),
+                0,
+            )
+        };
+        if usize::try_from(written).ok() != Some(message.len()) {
+            log::warn!(
+                "Failed to queue a frame of {} bytes for the NIC: {}",
+                frame.len(),
+                io::Error::last_os_error()
+            );
+        }
+    }
+
+    fn post_exec<OT>(
+        &mut self,
+        _emulator: &Emulator,
+        _input: &S::Input,
+        observers: &mut OT,
+        _exit_kind: &mut ExitKind,
+    ) where
+        OT: ObserversTuple<S>,
+    {
+        let response = self.read_frames();
+        let observer = observers
+            .match_name_mut::<NetworkResponseObserver>(&self.observer_name)
+            .expect("A NetworkStubHelper needs a NetworkResponseObserver");
+        observer.set_response(response);
+    }
+}
+
+/// Holds the frames the target transmitted during the last execution, concatenated, if any
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkResponseObserver {
+    name: String,
+    response: Vec<u8>,
+}
+
+impl NetworkResponseObserver {
+    /// Creates a new [`NetworkResponseObserver`] with the given name, to be passed to the helper
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            response: vec![],
+        }
+    }
+
+    /// The transmitted packet, empty if there was none
+    #[must_use]
+    pub fn response(&self) -> &[u8] {
+        &self.response
+    }
+
+    /// Sets the packet transmitted during the last execution
+    pub(crate) fn set_response(&mut self, response: Vec<u8>) {
+        self.response = response;
+    }
+}
+
+impl<S> Observer<S> for NetworkResponseObserver
+where
+    S: UsesInput,
+{
+    fn pre_exec(&mut self, _state: &mut S, _input: &S::Input) -> Result<(), Error> {
+        self.response.clear();
+        Ok(())
+    }
+}
+
+impl Named for NetworkResponseObserver {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// The prefix of the [`NewNetworkBehaviorMetadata`] names
+pub const NEWNETWORKBEHAVIORFEEDBACK_PREFIX: &str = "newnetworkbehaviorfeedback_metadata_";
+
+/// The hashes of all responses seen by a [`NewNetworkBehaviorFeedback`]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[allow(clippy::unsafe_derive_deserialize)]
+pub struct NewNetworkBehaviorMetadata {
+    /// The hashes of the responses
+    pub responses: HashSet<u64>,
+}
+
+libafl_bolts::impl_serdeany!(NewNetworkBehaviorMetadata);
+
+/// Considers an input interesting if the target responded with a packet that was never seen before
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewNetworkBehaviorFeedback {
+    name: String,
+    observer_name: String,
+}
+
+impl NewNetworkBehaviorFeedback {
+    /// Creates a new [`NewNetworkBehaviorFeedback`] for the responses of the given [`NetworkResponseObserver`]
+    #[must_use]
+    pub fn new(observer: &NetworkResponseObserver) -> Self {
+        Self {
+            name: NEWNETWORKBEHAVIORFEEDBACK_PREFIX.to_string() + observer.name(),
+            observer_name: observer.name().to_string(),
+        }
+    }
+}
+
+impl<S> Feedback<S> for NewNetworkBehaviorFeedback
+where
+    S: State + HasNamedMetadata,
+{
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error> {
+        state.add_named_metadata(NewNetworkBehaviorMetadata::default(), &self.name);
+        Ok(())
+    }
+
+    fn is_interesting<EM, OT>(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _input: &S::Input,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .match_name::<NetworkResponseObserver>(&self.observer_name)
+            .expect("A NewNetworkBehaviorFeedback needs a NetworkResponseObserver");
+
+        let meta = state
+            .named_metadata_map_mut()
+            .get_mut::<NewNetworkBehaviorMetadata>(&self.name)
+            .unwrap();
+        Ok(meta.responses.insert(hash_std(observer.response())))
+    }
+}
+
+impl Named for NewNetworkBehaviorFeedback {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl HasObserverName for NewNetworkBehaviorFeedback {
+    fn observer_name(&self) -> &str {
+        &self.observer_name
+    }
+}
@@ -0,0 +1,276 @@
+//! Capturing the CPU registers whenever the target reaches one of a set of program counters.
+//!
+//! The [`RegisterValueHelper`] hooks the given addresses, and hands the snapshots taken during an execution
+//! to a [`RegisterValueObserver`]. The [`RegisterValueFeedback`] considers inputs interesting
+//! if they reach one of the addresses with a register state never seen there before.
+//!
+//! By default, all registers but the stack pointer are captured: its value depends on the depth of the stack
+//! and its layout, so it would make almost every snapshot new. [`RegisterValueHelper::with_registers`]
+//! restricts the snapshots to the registers that matter for the target.
+//!
+//! QEMU breakpoints ([`Emulator::set_breakpoint`]) stop the emulation, so instruction hooks are used instead.
+
+use hashbrown::HashSet;
+use libafl::{
+    events::EventFirer,
+    executors::ExitKind,
+    feedbacks::{Feedback, HasObserverName},
+    inputs::UsesInput,
+    observers::{Observer, ObserversTuple},
+    state::{HasNamedMetadata, State},
+    Error,
+};
+use libafl_bolts::{hash_std, Named};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    emu::{Emulator, GuestAddr},
+    helper::{QemuHelper, QemuHelperTuple},
+    hooks::{Hook, QemuHooks},
+    GuestReg, Regs,
+};
+
+/// The registers of the CPU when it reached `pc`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct RegisterSnapshot {
+    /// The program counter the snapshot was taken at
+    pub pc: GuestAddr,
+    /// The values of the captured registers, in the order of their QEMU register numbers
+    pub regs: Vec<GuestReg>,
+}
+
+impl RegisterSnapshot {
+    /// A hash of the register values
+    #[must_use]
+    pub fn regs_hash(&self) -> u64 {
+        let bytes: Vec<u8> = self.regs.iter().flat_map(|reg| reg.to_le_bytes()).collect();
+        hash_std(&bytes)
+    }
+}
+
+/// Takes a [`RegisterSnapshot`] each time the target reaches one of the breakpoints,
+/// and stores them into a [`RegisterValueObserver`] after the execution.
+#[derive(Debug)]
+pub struct RegisterValueHelper {
+    breakpoints: Vec<GuestAddr>,
+    /// The QEMU numbers of the captured registers, all but the stack pointer if `None`
+    registers: Option<Vec<i32>>,
+    snapshots: Vec<RegisterSnapshot>,
+    observer_name: String,
+}
+
+impl RegisterValueHelper {
+    /// Creates a new [`RegisterValueHelper`], capturing all registers but the stack pointer
+    /// at the given `breakpoints`, and reporting them to the given [`RegisterValueObserver`]
+    #[must_use]
+    pub fn new(breakpoints: Vec<GuestAddr>, observer: &RegisterValueObserver) -> Self {
+        Self {
+            breakpoints,
+            registers: None,
+            snapshots: vec![],
+            observer_name: observer.name().to_string(),
+        }
+    }
+
+    /// Captures only the given registers
+    #[must_use]
+    pub fn with_registers<R, I>(mut self, registers: I) -> Self
+    where
+        R: Into<i32>,
+        I: IntoIterator<Item = R>,
+    {
+        let mut registers: Vec<i32> = registers.into_iter().map(Into::into).collect();
+        registers.sort_unstable();
+        registers.dedup();
+        self.registers = Some(registers);
+        self
+    }
+
+    /// The program counters the registers are captured at
+    #[must_use]
+    pub fn breakpoints(&self) -> &[GuestAddr] {
+        &self.breakpoints
+    }
+}
+
+impl<S> QemuHelper<S> for RegisterValueHelper
+where
+    S: UsesInput,
+{
+    fn first_exec<QT>(&self, hooks: &QemuHooks<QT, S>)
+    where
+        QT: QemuHelperTuple<S>,
+    {
+        for pc in &self.breakpoints {
+            hooks.instruction(*pc, Hook::Function(snapshot_registers::<QT, S>), true);
+        }
+    }
+
+    fn pre_exec(&mut self, _emulator: &Emulator, _input: &S::Input) {
+        self.snapshots.clear();
+    }
+
+    fn post_exec<OT>(
+        &mut self,
+        _emulator: &Emulator,
+        _input: &S::Input,
+        observers: &mut OT,
+        _exit_kind: &mut ExitKind,
+    ) where
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .match_name_mut::<RegisterValueObserver>(&self.observer_name)
+            .expect("A RegisterValueHelper needs a RegisterValueObserver");
+        observer.snapshots.append(&mut self.snapshots);
+    }
+}
+
+/// The instruction hook of the [`RegisterValueHelper`], copying all registers of the current CPU
+fn snapshot_registers<QT, S>(hooks: &mut QemuHooks<QT, S>, _state: Option<&mut S>, pc: GuestAddr)
+where
+    QT: QemuHelperTuple<S>,
+    S: UsesInput,
+{
+    let Some(cpu) = hooks.emulator().current_cpu() else {
+        return;
+    };
+    let Some(helper) = hooks
+        .helpers_mut()
+        .match_first_type_mut::<RegisterValueHelper>()
+    else {
+        return;
+    };
+    let read = |reg: i32| -> GuestReg { cpu.read_reg(reg).unwrap_or_default() };
+    let regs = match &helper.registers {
+        Some(registers) => registers.iter().copied().map(read).collect(),
+        None => {
+            let sp: i32 = Regs::Sp.into();
+            (0..cpu.num_regs())
+                .filter(|reg| *reg != sp)
+                .map(read)
+                .collect()
+        }
+    };
+    helper.snapshots.push(RegisterSnapshot { pc, regs });
+}
+
+/// Holds the [`RegisterSnapshot`]s taken during the last execution, in the order they were taken
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegisterValueObserver {
+    name: String,
+    snapshots: Vec<RegisterSnapshot>,
+}
+
+impl RegisterValueObserver {
+    /// Creates a new [`RegisterValueObserver`] with the given name, to be passed to the [`RegisterValueHelper`]
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            snapshots: vec![],
+        }
+    }
+
+    /// The snapshots of the last execution
+    #[must_use]
+    pub fn snapshots(&self) -> &[RegisterSnapshot] {
+        &self.snapshots
+    }
+}
+
+impl<S> Observer<S> for RegisterValueObserver
+where
+    S: UsesInput,
+{
+    fn pre_exec(&mut self, _state: &mut S, _input: &S::Input) -> Result<(), Error> {
+        self.snapshots.clear();
+        Ok(())
+    }
+}
+
+impl Named for RegisterValueObserver {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// The prefix of the [`RegisterValueMetadata`] names
+pub const REGISTERVALUEFEEDBACK_PREFIX: &str = "registervaluefeedback_metadata_";
+
+/// The `(pc, register state hash)` pairs seen by a [`RegisterValueFeedback`]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[allow(clippy::unsafe_derive_deserialize)]
+pub struct RegisterValueMetadata {
+    /// The program counters and hashes of the register states seen there
+    pub states: HashSet<(GuestAddr, u64)>,
+}
+
+libafl_bolts::impl_serdeany!(RegisterValueMetadata);
+
+/// Considers an input interesting if it reached a breakpoint with a new register state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegisterValueFeedback {
+    name: String,
+    observer_name: String,
+}
+
+impl RegisterValueFeedback {
+    /// Creates a new [`RegisterValueFeedback`] for the snapshots of the given [`RegisterValueObserver`]
+    #[must_use]
+    pub fn new(observer: &RegisterValueObserver) -> Self {
+        Self {
+            name: REGISTERVALUEFEEDBACK_PREFIX.to_string() + observer.name(),
+            observer_name: observer.name().to_string(),
+        }
+    }
+}
+
+impl<S> Feedback<S> for RegisterValueFeedback
+where
+    S: State + HasNamedMetadata,
+{
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error> {
+        state.add_named_metadata(RegisterValueMetadata::default(), &self.name);
+        Ok(())
+    }
+
+    fn is_interesting<EM, OT>(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _input: &S::Input,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .match_name::<RegisterValueObserver>(&self.observer_name)
+            .expect("A RegisterValueFeedback needs a RegisterValueObserver");
+
+        let meta = state
+            .named_metadata_map_mut()
+            .get_mut::<RegisterValueMetadata>(&self.name)
+            .unwrap();
+        let mut interesting = false;
+        for snapshot in observer.snapshots() {
+            interesting |= meta.states.insert((snapshot.pc, snapshot.regs_hash()));
+        }
+        Ok(interesting)
+    }
+}
+
+impl Named for RegisterValueFeedback {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl HasObserverName for RegisterValueFeedback {
+    fn observer_name(&self) -> &str {
+        &self.observer_name
+    }
+}
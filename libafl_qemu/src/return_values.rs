@@ -0,0 +1,299 @@
+//! Capturing the return values of functions, for ABI-level differential testing.
+//!
+//! When two implementations of the same function are compared, e.g. the string functions of glibc and musl,
+//! their return values tell more than their coverage. The [`ReturnValueHelper`] hooks the entry of the given
+//! functions, hooks the return address found there, and reads the return register once the function returns.
+//! The values of an execution end up in a [`ReturnValueObserver`], and the [`ReturnValueDiffFeedback`]
+//! considers inputs interesting if two implementations, both called by the harness with the same arguments,
+//! returned different values in the same execution.
+
+use std::path::Path;
+
+use hashbrown::{HashMap, HashSet};
+use libafl::{
+    events::EventFirer,
+    executors::ExitKind,
+    feedbacks::{Feedback, HasObserverName},
+    inputs::UsesInput,
+    observers::{Observer, ObserversTuple},
+    state::State,
+    Error,
+};
+use libafl_bolts::Named;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    elf::EasyElf,
+    emu::{ArchExtras, Emulator, GuestAddr},
+    helper::{QemuHelper, QemuHelperTuple},
+    hooks::{Hook, QemuHooks},
+    Regs,
+};
+
+/// The register holding the return value of a function in the calling convention of the target
+#[cfg(cpu_target = "x86_64")]
+pub(crate) const RETURN_VALUE_REG: Regs = Regs::Rax;
+#[cfg(cpu_target = "i386")]
+pub(crate) const RETURN_VALUE_REG: Regs = Regs::Eax;
+#[cfg(cpu_target = "aarch64")]
+pub(crate) const RETURN_VALUE_REG: Regs = Regs::X0;
+#[cfg(any(cpu_target = "arm", cpu_target = "hexagon"))]
+pub(crate) const RETURN_VALUE_REG: Regs = Regs::R0;
+#[cfg(cpu_target = "mips")]
+pub(crate) const RETURN_VALUE_REG: Regs = Regs::V0;
+#[cfg(cpu_target = "ppc")]
+pub(crate) const RETURN_VALUE_REG: Regs = Regs::R3;
+
+/// Hooks the entries of a set of functions, and records their return values into a [`ReturnValueObserver`]
+#[derive(Debug)]
+pub struct ReturnValueHelper {
+    /// The name of the function starting at each hooked address
+    functions: HashMap<GuestAddr, String>,
+    /// The return sites hooked so far, shared by all calls returning there
+    return_sites: HashSet<GuestAddr>,
+    /// The return addresses and names of the calls that did not return yet, innermost last
+    pending: Vec<(GuestAddr, String)>,
+    values: Vec<(String, u64)>,
+    observer_name: String,
+}
+
+impl ReturnValueHelper {
+    /// Creates a new [`ReturnValueHelper`] for the functions starting at the given addresses
+    #[must_use]
+    pub fn new(functions: Vec<(String, GuestAddr)>, observer: &ReturnValueObserver) -> Self {
+        Self {
+            functions: functions
+                .into_iter()
+                .map(|(name, addr)| (addr, name))
+                .collect(),
+            return_sites: HashSet::new(),
+            pending: vec![],
+            values: vec![],
+            observer_name: observer.name().to_string(),
+        }
+    }
+
+    /// Creates a new [`ReturnValueHelper`] for the functions named `names` in the ELF at `binary`.
+    /// If it is position independent, it is expected at the load address of the target in `emulator`.
+    pub fn from_elf<P: AsRef<Path>>(
+        emulator: &Emulator,
+        binary: P,
+        names: &[&str],
+        observer: &ReturnValueObserver,
+    ) -> Result<Self, Error> {
+        let mut elf_buffer = Vec::new();
+        let elf = EasyElf::from_file(binary, &mut elf_buffer)?;
+        let functions = elf
+            .function_symbols(emulator.load_addr())
+            .into_iter()
+            .filter(|(name, _)| names.contains(name))
+            .map(|(name, addr)| (name.to_string(), addr))
+            .collect();
+        Ok(Self::new(functions, observer))
+    }
+
+    /// The names of the hooked functions
+    pub fn functions(&self) -> impl Iterator<Item = &str> {
+        self.functions.values().map(String::as_str)
+    }
+}
+
+impl<S> QemuHelper<S> for ReturnValueHelper
+where
+    S: UsesInput,
+{
+    fn first_exec<QT>(&self, hooks: &QemuHooks<QT, S>)
+    where
+        QT: QemuHelperTuple<S>,
+    {
+        for pc in self.functions.keys() {
+            hooks.instruction(*pc, Hook::Function(on_function_entry::<QT, S>), true);
+        }
+    }
+
+    fn pre_exec(&mut self, _emulator: &Emulator, _input: &S::Input) {
+        self.pending.clear();
+        self.values.clear();
+    }
+
+    fn post_exec<OT>(
+        &mut self,
+        _emulator: &Emulator,
+        _input: &S::Input,
+        observers: &mut OT,
+        _exit_kind: &mut ExitKind,
+    ) where
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .match_name_mut::<ReturnValueObserver>(&self.observer_name)
+            .expect("A ReturnValueHelper needs a ReturnValueObserver");
+        observer.values.append(&mut self.values);
+    }
+}
+
+/// The instruction hook at the entry of a function, hooking the return address of the call
+fn on_function_entry<QT, S>(hooks: &mut QemuHooks<QT, S>, _state: Option<&mut S>, pc: GuestAddr)
+where
+    QT: QemuHelperTuple<S>,
+    S: UsesInput,
+{
+    let Ok(ret_addr) = hooks.emulator().read_return_address::<GuestAddr>() else {
+        return;
+    };
+    let Some(helper) = hooks
+        .helpers_mut()
+        .match_first_type_mut::<ReturnValueHelper>()
+    else {
+        return;
+    };
+    let Some(name) = helper.functions.get(&pc).cloned() else {
+        return;
+    };
+    helper.pending.push((ret_addr, name));
+    if helper.return_sites.insert(ret_addr) {
+        hooks.instruction(ret_addr, Hook::Function(on_function_return::<QT, S>), true);
+    }
+}
+
+/// The instruction hook at a return site, reading the return value of the innermost call returning there
+fn on_function_return<QT, S>(hooks: &mut QemuHooks<QT, S>, _state: Option<&mut S>, pc: GuestAddr)
+where
+    QT: QemuHelperTuple<S>,
+    S: UsesInput,
+{
+    let Ok(value) = hooks.emulator().read_reg::<_, u64>(RETURN_VALUE_REG) else {
+        return;
+    };
+    let Some(helper) = hooks
+        .helpers_mut()
+        .match_first_type_mut::<ReturnValueHelper>()
+    else {
+        return;
+    };
+    // the site is also reached without returning from a hooked call
+    let Some(idx) = helper.pending.iter().rposition(|(ret, _)| *ret == pc) else {
+        return;
+    };
+    // calls above it were left without returning, e.g. by a longjmp
+    let (_, name) = helper.pending.drain(idx..).next().unwrap();
+    helper.values.push((name, value));
+}
+
+/// Holds the return values of the hooked functions during the last execution, in the order they returned
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReturnValueObserver {
+    name: String,
+    values: Vec<(String, u64)>,
+}
+
+impl ReturnValueObserver {
+    /// Creates a new [`ReturnValueObserver`] with the given name, to be passed to the [`ReturnValueHelper`]
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            values: vec![],
+        }
+    }
+
+    /// The function names and return values of the last execution
+    #[must_use]
+    pub fn values(&self) -> &[(String, u64)] {
+        &self.values
+    }
+
+    /// The values returned by the function named `function` in the last execution
+    pub fn values_of<'a>(&'a self, function: &'a str) -> impl Iterator<Item = u64> + 'a {
+        self.values
+            .iter()
+            .filter(move |(name, _)| name == function)
+            .map(|(_, value)| *value)
+    }
+}
+
+impl<S> Observer<S> for ReturnValueObserver
+where
+    S: UsesInput,
+{
+    fn pre_exec(&mut self, _state: &mut S, _input: &S::Input) -> Result<(), Error> {
+        self.values.clear();
+        Ok(())
+    }
+}
+
+impl Named for ReturnValueObserver {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// The prefix of the [`ReturnValueDiffFeedback`] names
+pub const RETURNVALUEDIFFFEEDBACK_PREFIX: &str = "returnvaluedifffeedback_";
+
+/// Considers an input interesting if two implementations of a function returned different values for it.
+///
+/// The harness calls both implementations, e.g. the `strlen` of glibc and the one of musl, with the same arguments,
+/// and both are hooked by the [`ReturnValueHelper`]. The feedback compares the sequences of values
+/// each of them returned during the execution, so it is typically used as an objective.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReturnValueDiffFeedback {
+    name: String,
+    observer_name: String,
+    function: String,
+    other_function: String,
+}
+
+impl ReturnValueDiffFeedback {
+    /// Creates a new [`ReturnValueDiffFeedback`], comparing the values returned by `function`
+    /// to those returned by `other_function` in the same execution
+    #[must_use]
+    pub fn new(observer: &ReturnValueObserver, function: &str, other_function: &str) -> Self {
+        Self {
+            name: format!(
+                "{RETURNVALUEDIFFFEEDBACK_PREFIX}{}_{function}_{other_function}",
+                observer.name()
+            ),
+            observer_name: observer.name().to_string(),
+            function: function.to_string(),
+            other_function: other_function.to_string(),
+        }
+    }
+}
+
+impl<S> Feedback<S> for ReturnValueDiffFeedback
+where
+    S: State,
+{
+    fn is_interesting<EM, OT>(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _input: &S::Input,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .match_name::<ReturnValueObserver>(&self.observer_name)
+            .expect("A ReturnValueDiffFeedback needs a ReturnValueObserver");
+        Ok(!observer
+            .values_of(&self.function)
+            .eq(observer.values_of(&self.other_function)))
+    }
+}
+
+impl Named for ReturnValueDiffFeedback {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl HasObserverName for ReturnValueDiffFeedback {
+    fn observer_name(&self) -> &str {
+        &self.observer_name
+    }
+}
@@ -0,0 +1,152 @@
+//! Stopping deeply recursing targets before their stack overflows.
+//!
+//! In usermode, the guest stack lives in the address space of the fuzzer, and an unbounded recursion
+//! of the target can take the fuzzer down with it instead of ending in a clean crash of the target.
+//! The [`StackDepthLimitHelper`] takes the stack pointer at the first basic block of each execution,
+//! i.e. the one the harness set up, as the stack base, unless a fixed base is configured.
+//! It checks the depth of the stack before each basic block, and stops the emulation as soon as it exceeds
+//! the limit. The execution is then reported as [`ExitKind::Crash`].
+
+use libafl::{executors::ExitKind, inputs::UsesInput, observers::ObserversTuple};
+
+use crate::{
+    emu::{Emulator, GuestAddr, GuestUsize},
+    helper::{QemuHelper, QemuHelperTuple},
+    hooks::{Hook, QemuHooks},
+    Regs,
+};
+
+/// The default limit of the [`StackDepthLimitHelper`], the usual stack size on Linux
+pub const DEFAULT_STACK_DEPTH_LIMIT: GuestUsize = 8 * 1024 * 1024;
+
+/// Reports executions whose stack grows deeper than a limit as crashes, see the [module documentation](self).
+///
+/// Exceeding the limit triggers a breakpoint on the current CPU, so [`Emulator::run`] returns
+/// to the harness as for any other breakpoint, and the harness should end the execution then.
+/// The stack is assumed to grow downwards, as it does on all supported architectures.
+#[derive(Debug)]
+pub struct StackDepthLimitHelper {
+    stack_depth_limit: GuestUsize,
+    /// The stack base set with [`StackDepthLimitHelper::stack_base`], used for all executions
+    configured_stack_base: Option<GuestAddr>,
+    /// The stack base of the current execution, `None` until its first block
+    stack_base: Option<GuestAddr>,
+    /// If the current execution exceeded the limit
+    exceeded: bool,
+}
+
+impl Default for StackDepthLimitHelper {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StackDepthLimitHelper {
+    /// Creates a new [`StackDepthLimitHelper`] with the [`DEFAULT_STACK_DEPTH_LIMIT`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            stack_depth_limit: DEFAULT_STACK_DEPTH_LIMIT,
+            configured_stack_base: None,
+            stack_base: None,
+            exceeded: false,
+        }
+    }
+
+    /// Sets the maximum depth of the stack in bytes
+    #[must_use]
+    pub fn stack_depth_limit(mut self, stack_depth_limit: GuestUsize) -> Self {
+        self.stack_depth_limit = stack_depth_limit;
+        self
+    }
+
+    /// Measures the depth from `stack_base` in all executions, instead of from the stack pointer
+    /// at the first block of each execution.
+    /// Use it if the harness does not reset the stack pointer before each run.
+    #[must_use]
+    pub fn stack_base(mut self, stack_base: GuestAddr) -> Self {
+        self.configured_stack_base = Some(stack_base);
+        self
+    }
+
+    /// The depth of the stack at the stack pointer `sp`, taking `sp` as the base in the first block of a run
+    fn depth(&mut self, sp: GuestAddr) -> GuestUsize {
+        self.stack_base.get_or_insert(sp).saturating_sub(sp)
+    }
+}
+
+impl<S> QemuHelper<S> for StackDepthLimitHelper
+where
+    S: UsesInput,
+{
+    fn first_exec<QT>(&self, hooks: &QemuHooks<QT, S>)
+    where
+        QT: QemuHelperTuple<S>,
+    {
+        hooks.blocks(
+            Hook::Function(gen_stack_depth_block_ids::<QT, S>),
+            Hook::Empty,
+            Hook::Function(exec_stack_depth_block::<QT, S>),
+        );
+    }
+
+    fn pre_exec(&mut self, _emulator: &Emulator, _input: &S::Input) {
+        // the harness sets the stack pointer after this, so the base is taken at the first block
+        self.stack_base = self.configured_stack_base;
+        self.exceeded = false;
+    }
+
+    fn post_exec<OT>(
+        &mut self,
+        _emulator: &Emulator,
+        _input: &S::Input,
+        _observers: &mut OT,
+        exit_kind: &mut ExitKind,
+    ) where
+        OT: ObserversTuple<S>,
+    {
+        if self.exceeded {
+            *exit_kind = ExitKind::Crash;
+        }
+    }
+}
+
+/// The block generation hook of the [`StackDepthLimitHelper`], identifying each block by its address
+pub fn gen_stack_depth_block_ids<QT, S>(
+    _hooks: &mut QemuHooks<QT, S>,
+    _state: Option<&mut S>,
+    pc: GuestAddr,
+) -> Option<u64>
+where
+    S: UsesInput,
+    QT: QemuHelperTuple<S>,
+{
+    Some(pc as u64)
+}
+
+/// The block execution hook of the [`StackDepthLimitHelper`], stopping the emulation if the stack is too deep
+pub fn exec_stack_depth_block<QT, S>(hooks: &mut QemuHooks<QT, S>, _state: Option<&mut S>, _id: u64)
+where
+    S: UsesInput,
+    QT: QemuHelperTuple<S>,
+{
+    let Some(cpu) = hooks.emulator().current_cpu() else {
+        return;
+    };
+    let Ok(sp) = cpu.read_reg::<_, GuestAddr>(Regs::Sp) else {
+        return;
+    };
+    if let Some(helper) = hooks
+        .helpers_mut()
+        .match_first_type_mut::<StackDepthLimitHelper>()
+    {
+        let depth = helper.depth(sp);
+        if !helper.exceeded && depth > helper.stack_depth_limit {
+            log::info!(
+                "StackDepthLimitHelper: the stack is {depth:#x} bytes deep, stopping the execution"
+            );
+            helper.exceeded = true;
+            cpu.trigger_breakpoint();
+        }
+    }
+}
@@ -0,0 +1,247 @@
+//! Deterministic fuzzing of network clients and parsers, by recording and replaying their `recv` syscalls.
+//!
+//! In [`SyscallReplayMode::Record`], the [`SyscallReplayHelper`] lets the guest talk to the real network,
+//! and logs the data returned by each `recv`/`recvfrom` of an execution.
+//! In [`SyscallReplayMode::Replay`], the network is no longer touched: each `recv` is answered with the next
+//! slice of the current input, as long as the recorded data of the same call, so that the input is split
+//! into the messages the target saw during the recording. Calls beyond the recording get as many bytes as
+//! they asked for, and the end of the input is reported as a closed connection.
+
+use libafl::{
+    executors::ExitKind,
+    inputs::{HasTargetBytes, UsesInput},
+    observers::ObserversTuple,
+};
+use libafl_bolts::AsSlice;
+
+#[cfg(cpu_target = "arm")]
+use crate::SYS_recv;
+use crate::{
+    emu::{Emulator, SyscallHookResult},
+    helper::{QemuHelper, QemuHelperTuple},
+    hooks::{Hook, QemuHooks},
+    GuestAddr, SYS_recvfrom,
+};
+
+/// Whether the [`SyscallReplayHelper`] records the `recv` syscalls, or replays them from the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyscallReplayMode {
+    /// Let the syscalls through, logging the data they returned
+    Record,
+    /// Answer the syscalls with slices of the input
+    Replay,
+}
+
+/// A `recv` syscall of the guest, as seen in [`SyscallReplayMode::Record`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedSyscall {
+    /// The number of the syscall
+    pub sys_num: i32,
+    /// The socket the data was received from
+    pub fd: i32,
+    /// The data returned to the guest
+    pub data: Vec<u8>,
+}
+
+/// Records the data the guest receives from the network, or replays it from the fuzzer input.
+#[derive(Debug)]
+pub struct SyscallReplayHelper {
+    mode: SyscallReplayMode,
+    /// The syscalls recorded during the last execution in [`SyscallReplayMode::Record`]
+    log: Vec<RecordedSyscall>,
+    /// The bytes of the current input
+    input: Vec<u8>,
+    /// The offset of the bytes to return by the next replayed syscall
+    cursor: usize,
+    /// The number of syscalls replayed in the current execution
+    replayed: usize,
+    /// `true` during executions, the syscalls of the startup code are left alone
+    running: bool,
+}
+
+impl SyscallReplayHelper {
+    /// Creates a new [`SyscallReplayHelper`] in the given mode, with an empty log
+    #[must_use]
+    pub fn new(mode: SyscallReplayMode) -> Self {
+        Self::with_log(mode, Vec::new())
+    }
+
+    /// Creates a new [`SyscallReplayHelper`], replaying the message boundaries of a previous recording
+    #[must_use]
+    pub fn with_log(mode: SyscallReplayMode, log: Vec<RecordedSyscall>) -> Self {
+        Self {
+            mode,
+            log,
+            input: Vec::new(),
+            cursor: 0,
+            replayed: 0,
+            running: false,
+        }
+    }
+
+    #[must_use]
+    pub fn mode(&self) -> SyscallReplayMode {
+        self.mode
+    }
+
+    /// Switches the mode, e.g. to replay after recording a first execution
+    pub fn set_mode(&mut self, mode: SyscallReplayMode) {
+        self.mode = mode;
+    }
+
+    /// The syscalls recorded during the last execution in [`SyscallReplayMode::Record`]
+    #[must_use]
+    pub fn log(&self) -> &[RecordedSyscall] {
+        &self.log
+    }
+
+    /// The concatenated data of the recorded syscalls, a first input replaying the recorded session
+    #[must_use]
+    pub fn recorded_input(&self) -> Vec<u8> {
+        self.log
+            .iter()
+            .flat_map(|syscall| syscall.data.iter().copied())
+            .collect()
+    }
+
+    /// Takes the next slice of the input for a replayed syscall asking for at most `len` bytes
+    fn next_slice(&mut self, len: usize) -> &[u8] {
+        let len = self
+            .log
+            .get(self.replayed)
+            .map_or(len, |syscall| syscall.data.len().min(len));
+        let start = self.cursor;
+        let end = (start + len).min(self.input.len());
+        self.cursor = end;
+        self.replayed += 1;
+        &self.input[start..end]
+    }
+}
+
+impl<S> QemuHelper<S> for SyscallReplayHelper
+where
+    S: UsesInput,
+    S::Input: HasTargetBytes,
+{
+    fn init_hooks<QT>(&self, hooks: &QemuHooks<QT, S>)
+    where
+        QT: QemuHelperTuple<S>,
+    {
+        hooks.syscalls(Hook::Function(syscall_replay_hook::<QT, S>));
+        hooks.after_syscalls(Hook::Function(syscall_record_hook::<QT, S>));
+    }
+
+    fn pre_exec(&mut self, _emulator: &Emulator, input: &S::Input) {
+        match self.mode {
+            SyscallReplayMode::Record => self.log.clear(),
+            SyscallReplayMode::Replay => {
+                self.input.clear();
+                self.input
+                    .extend_from_slice(input.target_bytes().as_slice());
+                self.cursor = 0;
+                self.replayed = 0;
+            }
+        }
+        self.running = true;
+    }
+
+    fn post_exec<OT>(
+        &mut self,
+        _emulator: &Emulator,
+        _input: &S::Input,
+        _observers: &mut OT,
+        _exit_kind: &mut ExitKind,
+    ) where
+        OT: ObserversTuple<S>,
+    {
+        self.running = false;
+    }
+}
+
+/// Returns `true` for the syscalls receiving data from a socket into the buffer `a1` of size `a2`
+fn is_recv(sys_num: i32) -> bool {
+    match i64::from(sys_num) {
+        SYS_recvfrom => true,
+        #[cfg(cpu_target = "arm")]
+        SYS_recv => true,
+        _ => false,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::cast_possible_truncation)]
+pub fn syscall_replay_hook<QT, S>(
+    hooks: &mut QemuHooks<QT, S>,
+    _state: Option<&mut S>,
+    sys_num: i32,
+    _a0: GuestAddr,
+    a1: GuestAddr,
+    a2: GuestAddr,
+    _a3: GuestAddr,
+    _a4: GuestAddr,
+    _a5: GuestAddr,
+    _a6: GuestAddr,
+    _a7: GuestAddr,
+) -> SyscallHookResult
+where
+    S: UsesInput,
+    QT: QemuHelperTuple<S>,
+{
+    if !is_recv(sys_num) {
+        return SyscallHookResult::new(None);
+    }
+    let emu = hooks.emulator().clone();
+    let h = hooks.match_helper_mut::<SyscallReplayHelper>().unwrap();
+    if !h.running || h.mode != SyscallReplayMode::Replay {
+        return SyscallHookResult::new(None);
+    }
+
+    let data = h.next_slice(a2 as usize);
+    unsafe {
+        emu.write_mem(a1, data);
+    }
+    SyscallHookResult::new(Some(data.len() as GuestAddr))
+}
+
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+pub fn syscall_record_hook<QT, S>(
+    hooks: &mut QemuHooks<QT, S>,
+    _state: Option<&mut S>,
+    result: GuestAddr,
+    sys_num: i32,
+    a0: GuestAddr,
+    a1: GuestAddr,
+    _a2: GuestAddr,
+    _a3: GuestAddr,
+    _a4: GuestAddr,
+    _a5: GuestAddr,
+    _a6: GuestAddr,
+    _a7: GuestAddr,
+) -> GuestAddr
+where
+    S: UsesInput,
+    QT: QemuHelperTuple<S>,
+{
+    if !is_recv(sys_num) {
+        return result;
+    }
+    let emu = hooks.emulator().clone();
+    let h = hooks.match_helper_mut::<SyscallReplayHelper>().unwrap();
+    // errors are negative, and not worth replaying
+    let len = result as isize;
+    if !h.running || h.mode != SyscallReplayMode::Record || len < 0 {
+        return result;
+    }
+
+    let mut data = vec![0; len as usize];
+    unsafe {
+        emu.read_mem(a1, &mut data);
+    }
+    h.log.push(RecordedSyscall {
+        sys_num,
+        fd: a0 as i32,
+        data,
+    });
+    result
+}
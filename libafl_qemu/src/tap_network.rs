@@ -0,0 +1,352 @@
+//! Packet-level fuzzing of system-mode targets through a host TAP interface.
+//!
+//! Unlike the [`crate::NetworkStubHelper`], which talks to the emulated NIC directly through a socket,
+//! the [`TapNetworkHelper`] puts the host network stack in between: QEMU connects the emulated NIC
+//! to a TAP interface on the host, so the guest can also be reached by host tools through that interface.
+//! The helper creates the interface, injects each input as an Ethernet frame into it before the run,
+//! and collects the frames the guest transmitted during the run into a [`NetworkResponseObserver`].
+//!
+//! To keep the runs reproducible, the frame bypasses the queueing discipline of the interface, so that it is
+//! queued for QEMU by the time the run starts, and IPv6 is disabled on the interface, so that the host
+//! does not send neighbor discovery or multicast listener traffic to the guest. Frames sent by the host are
+//! never recorded, and after the run the helper waits for all frames the guest transmitted to be delivered.
+//!
+//! The interface has to exist before QEMU starts, so create the helper first, and pass the arguments
+//! of [`TapNetworkHelper::qemu_args`] to the [`Emulator`], together with a NIC using the `netdev`,
+//! e.g. `-device e1000,netdev=libafl0`. Creating a TAP interface needs `CAP_NET_ADMIN`.
+
+use std::{
+    ffi::CString,
+    fs::{self, File, OpenOptions},
+    io, mem,
+    os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, OwnedFd, RawFd},
+    time::Duration,
+};
+
+use libafl::{
+    executors::ExitKind,
+    inputs::{HasTargetBytes, UsesInput},
+    observers::ObserversTuple,
+    Error,
+};
+use libafl_bolts::{AsSlice, Named};
+
+use crate::{emu::Emulator, helper::QemuHelper, network::NetworkResponseObserver};
+
+const TUNSETIFF: libc::c_ulong = 0x4004_54ca;
+const TUNSETPERSIST: libc::c_ulong = 0x4004_54cb;
+const IFF_TAP: libc::c_short = 0x0002;
+const IFF_NO_PI: libc::c_short = 0x1000;
+const PACKET_QDISC_BYPASS: libc::c_int = 20;
+const PACKET_OUTGOING: u8 = 4;
+
+/// The length of the Ethernet header prepended by [`TapNetworkHelper::with_ethernet_header`]
+pub const ETHERNET_HEADER_LEN: usize = 14;
+/// The maximum size of a frame read back from the TAP interface
+const MAX_FRAME_SIZE: usize = 65536;
+/// The default time to wait for the frames transmitted by the guest during a run to be delivered
+pub const DEFAULT_RESPONSE_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// The `struct ifreq` of the interface ioctls, with the flags member of its union
+#[repr(C)]
+struct IfReq {
+    name: [libc::c_char; libc::IFNAMSIZ],
+    flags: libc::c_short,
+    _pad: [u8; 22],
+}
+
+impl IfReq {
+    fn new(ifname: &str) -> Result<Self, Error> {
+        if ifname.len() >= libc::IFNAMSIZ {
+            return Err(Error::illegal_argument(format!(
+                "The interface name {ifname} is too long"
+            )));
+        }
+        let mut req: Self = unsafe { mem::zeroed() };
+        for (dst, src) in req.name.iter_mut().zip(ifname.bytes()) {
+            *dst = src as libc::c_char;
+        }
+        Ok(req)
+    }
+}
+
+/// Creates the TAP interface named `ifname`, which is removed again once the returned file is closed.
+/// A leftover persistent interface of the same name, e.g. of an older version of this helper, is removed first.
+fn create_tap(ifname: &str) -> Result<File, Error> {
+    // attaching to an existing persistent interface, and clearing the flag, removes it
+    if let Ok(tun) = open_tap(ifname) {
+        unsafe {
+            libc::ioctl(tun.as_raw_fd(), TUNSETPERSIST, 0);
+        }
+    }
+    open_tap(ifname)
+}
+
+/// Opens `/dev/net/tun`, and attaches to the TAP interface `ifname`, creating it if needed
+fn open_tap(ifname: &str) -> Result<File, Error> {
+    let tun = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/net/tun")?;
+    let mut req = IfReq::new(ifname)?;
+    req.flags = IFF_TAP | IFF_NO_PI;
+    if unsafe { libc::ioctl(tun.as_raw_fd(), TUNSETIFF, &mut req) } < 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    Ok(tun)
+}
+
+/// Sets the interface `ifname` up, using `sock` for the ioctls
+fn set_up(sock: RawFd, ifname: &str) -> Result<(), Error> {
+    let mut req = IfReq::new(ifname)?;
+    unsafe {
+        if libc::ioctl(sock, libc::SIOCGIFFLAGS, &mut req) < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        req.flags |= libc::IFF_UP as libc::c_short;
+        if libc::ioctl(sock, libc::SIOCSIFFLAGS, &mut req) < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+    }
+    Ok(())
+}
+
+/// Disables IPv6 on the interface `ifname`, so the host does not talk to the guest on its own
+fn disable_ipv6(ifname: &str) {
+    let path = format!("/proc/sys/net/ipv6/conf/{ifname}/disable_ipv6");
+    if let Err(err) = fs::write(&path, "1") {
+        log::warn!(
+            "Failed to disable IPv6 on {ifname}, the host may send frames to the guest: {err}"
+        );
+    }
+}
+
+/// Opens a non-blocking raw packet socket, bound to the interface `ifname`.
+/// Frames sent on it bypass the queueing discipline, so they are handed to the interface before `send` returns.
+fn open_packet_socket(ifname: &str) -> Result<OwnedFd, Error> {
+    let protocol = (libc::ETH_P_ALL as u16).to_be();
+    let fd = unsafe {
+        libc::socket(
+            libc::AF_PACKET,
+            libc::SOCK_RAW | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC,
+            libc::c_int::from(protocol),
+        )
+    };
+    if fd < 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    let sock = unsafe { OwnedFd::from_raw_fd(fd) };
+    let bypass: libc::c_int = 1;
+    let ret = unsafe {
+        libc::setsockopt(
+            sock.as_raw_fd(),
+            libc::SOL_PACKET,
+            PACKET_QDISC_BYPASS,
+            (&bypass as *const libc::c_int).cast(),
+            mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    set_up(sock.as_raw_fd(), ifname)?;
+
+    let c_ifname = CString::new(ifname).unwrap();
+    let ifindex = unsafe { libc::if_nametoindex(c_ifname.as_ptr()) };
+    if ifindex == 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    let mut addr: libc::sockaddr_ll = unsafe { mem::zeroed() };
+    addr.sll_family = libc::AF_PACKET as u16;
+    addr.sll_protocol = protocol;
+    addr.sll_ifindex = ifindex as i32;
+    let ret = unsafe {
+        libc::bind(
+            sock.as_raw_fd(),
+            (&addr as *const libc::sockaddr_ll).cast(),
+            mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t,
+        )
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error().into());
+    }
+    Ok(sock)
+}
+
+/// Injects each input as an Ethernet frame into a TAP interface connected to the guest NIC,
+/// and records the frames the guest transmits during the run into a [`NetworkResponseObserver`].
+///
+/// The frames received from the guest are concatenated, in the order they were transmitted.
+/// QEMU uses the file descriptor of the interface created by the helper, so the interface is removed
+/// when the fuzzer process exits, even if QEMU is still attached to it.
+#[derive(Debug)]
+pub struct TapNetworkHelper {
+    ifname: String,
+    /// The file descriptor of the interface, handed over to QEMU and never closed by the helper
+    tap_fd: RawFd,
+    sock: OwnedFd,
+    header: Option<[u8; ETHERNET_HEADER_LEN]>,
+    response_timeout: Duration,
+    /// The number of frames the guest had transmitted before the current run
+    rx_packets: u64,
+    observer_name: String,
+}
+
+impl TapNetworkHelper {
+    /// Creates the TAP interface `ifname`, recreating it if it already exists
+    pub fn new(ifname: &str, observer: &NetworkResponseObserver) -> Result<Self, Error> {
+        let tap_fd = create_tap(ifname)?.into_raw_fd();
+        disable_ipv6(ifname);
+        let sock = open_packet_socket(ifname)?;
+        Ok(Self {
+            ifname: ifname.to_string(),
+            tap_fd,
+            sock,
+            header: None,
+            response_timeout: DEFAULT_RESPONSE_TIMEOUT,
+            rx_packets: 0,
+            observer_name: observer.name().to_string(),
+        })
+    }
+
+    /// Prepends an Ethernet header to each input, so that the inputs are the payloads of the frames
+    #[must_use]
+    pub fn with_ethernet_header(
+        mut self,
+        src_mac: [u8; 6],
+        dst_mac: [u8; 6],
+        ethertype: u16,
+    ) -> Self {
+        let mut header = [0; ETHERNET_HEADER_LEN];
+        header[..6].copy_from_slice(&dst_mac);
+        header[6..12].copy_from_slice(&src_mac);
+        header[12..].copy_from_slice(&ethertype.to_be_bytes());
+        self.header = Some(header);
+        self
+    }
+
+    /// Waits at most `timeout` after a run for the frames the guest transmitted to be delivered,
+    /// [`DEFAULT_RESPONSE_TIMEOUT`] by default
+    #[must_use]
+    pub fn with_response_timeout(mut self, timeout: Duration) -> Self {
+        self.response_timeout = timeout;
+        self
+    }
+
+    /// The name of the TAP interface
+    #[must_use]
+    pub fn ifname(&self) -> &str {
+        &self.ifname
+    }
+
+    /// The QEMU arguments connecting the `netdev` named `netdev_id` to the TAP interface
+    #[must_use]
+    pub fn qemu_args(&self, netdev_id: &str) -> Vec<String> {
+        vec![
+            "-netdev".to_string(),
+            format!("tap,id={netdev_id},fd={}", self.tap_fd),
+        ]
+    }
+
+    /// The number of frames the guest transmitted so far.
+    /// The kernel counts them as soon as QEMU writes them, before they are delivered to the packet socket.
+    fn guest_frames(&self) -> u64 {
+        let path = format!("/sys/class/net/{}/statistics/rx_packets", self.ifname);
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|count| count.trim().parse().ok())
+            .unwrap_or(0)
+    }
+
+    /// Reads the frames transmitted by the guest, concatenated, waiting for at least `expected` of them
+    /// for up to the response timeout. Frames sent by the host are skipped.
+    fn read_frames(&self, expected: u64) -> Vec<u8> {
+        let mut frames = Vec::new();
+        let mut frame = vec![0; MAX_FRAME_SIZE];
+        let mut count = 0;
+        loop {
+            let mut addr: libc::sockaddr_ll = unsafe { mem::zeroed() };
+            let mut addr_len = mem::size_of::<libc::sockaddr_ll>() as libc::socklen_t;
+            let len = unsafe {
+                libc::recvfrom(
+                    self.sock.as_raw_fd(),
+                    frame.as_mut_ptr().cast(),
+                    frame.len(),
+                    0,
+                    (&mut addr as *mut libc::sockaddr_ll).cast(),
+                    &mut addr_len,
+                )
+            };
+            if let Ok(len) = usize::try_from(len) {
+                if addr.sll_pkttype != PACKET_OUTGOING {
+                    frames.extend_from_slice(&frame[..len]);
+                    count += 1;
+                }
+                continue;
+            }
+            // EAGAIN, wait for the frames still in flight
+            if count >= expected || !self.wait_readable() {
+                break;
+            }
+        }
+        frames
+    }
+
+    /// Waits for the packet socket to become readable, returns `false` on timeout
+    fn wait_readable(&self) -> bool {
+        let mut pollfd = libc::pollfd {
+            fd: self.sock.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let timeout =
+            libc::c_int::try_from(self.response_timeout.as_millis()).unwrap_or(libc::c_int::MAX);
+        unsafe { libc::poll(&mut pollfd, 1, timeout) > 0 }
+    }
+}
+
+impl<S> QemuHelper<S> for TapNetworkHelper
+where
+    S: UsesInput,
+    S::Input: HasTargetBytes,
+{
+    fn pre_exec(&mut self, _emulator: &Emulator, input: &S::Input) {
+        // drop what the guest sent between the runs
+        self.read_frames(0);
+        self.rx_packets = self.guest_frames();
+
+        let target_bytes = input.target_bytes();
+        let mut frame = Vec::with_capacity(ETHERNET_HEADER_LEN + target_bytes.as_slice().len());
+        if let Some(header) = &self.header {
+            frame.extend_from_slice(header);
+        }
+        frame.extend_from_slice(target_bytes.as_slice());
+        let written =
+            unsafe { libc::send(self.sock.as_raw_fd(), frame.as_ptr().cast(), frame.len(), 0) };
+        if written < 0 {
+            log::warn!(
+                "Failed to inject a frame of {} bytes into {}: {}",
+                frame.len(),
+                self.ifname,
+                io::Error::last_os_error()
+            );
+        }
+    }
+
+    fn post_exec<OT>(
+        &mut self,
+        _emulator: &Emulator,
+        _input: &S::Input,
+        observers: &mut OT,
+        _exit_kind: &mut ExitKind,
+    ) where
+        OT: ObserversTuple<S>,
+    {
+        let expected = self.guest_frames().saturating_sub(self.rx_packets);
+        let response = self.read_frames(expected);
+        let observer = observers
+            .match_name_mut::<NetworkResponseObserver>(&self.observer_name)
+            .expect("A TapNetworkHelper needs a NetworkResponseObserver");
+        observer.set_response(response);
+    }
+}
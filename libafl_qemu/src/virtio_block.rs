@@ -0,0 +1,425 @@
+//! Fuzzing filesystems and disk drivers of system-mode targets through a virtio block device.
+//!
+//! The [`VirtioBlockHelper`] backs a QEMU `virtio-blk` device with a raw image in memory,
+//! and copies the current input into that image before each run.
+//! The image is an anonymous memory file (`memfd`) mapped into the fuzzer, which QEMU's block layer opens
+//! through `/proc/self/fd`, so the guest sees the new content without any disk I/O,
+//! as long as its own caches are reset between runs, e.g. by restoring a snapshot.
+//!
+//! Optionally, the image gets a partition table (see [`PartitionType`]) with a single partition holding the input,
+//! so the guest finds a well-formed disk, even if the filesystem on it is not.
+
+use std::{
+    ffi::CString,
+    io,
+    os::unix::io::{AsRawFd, FromRawFd, OwnedFd},
+    ptr, slice,
+};
+
+use libafl::{
+    inputs::{HasTargetBytes, UsesInput},
+    Error,
+};
+use libafl_bolts::AsSlice;
+
+use crate::{emu::Emulator, helper::QemuHelper};
+
+/// The size of a sector of the block device
+pub const SECTOR_SIZE: usize = 512;
+
+/// The default size of the block device
+pub const DEFAULT_IMAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// The number of sectors holding a GPT partition entry array
+const GPT_ENTRIES_SECTORS: usize = 32;
+/// The number of entries in a GPT partition entry array
+const GPT_ENTRIES_COUNT: usize = 128;
+/// The size of a GPT partition entry
+const GPT_ENTRY_SIZE: usize = 128;
+/// The size of a GPT header
+const GPT_HEADER_SIZE: usize = 92;
+/// The "Linux filesystem data" partition type, in the mixed-endian GUID encoding of GPT
+const GPT_LINUX_FS_GUID: [u8; 16] = [
+    0xaf, 0x3d, 0xc6, 0x0f, 0x83, 0x84, 0x72, 0x47, 0x8e, 0x79, 0x3d, 0x69, 0xd8, 0x47, 0x7d, 0xe4,
+];
+/// The GUID of the disk, fixed so the images are reproducible
+const GPT_DISK_GUID: [u8; 16] = *b"LibAFL-disk-GUID";
+/// The GUID of the partition, fixed so the images are reproducible
+const GPT_PARTITION_GUID: [u8; 16] = *b"LibAFL-part-GUID";
+
+/// The MBR partition type for Linux filesystems
+const MBR_LINUX_TYPE: u8 = 0x83;
+/// The MBR partition type of the protective MBR in front of a GPT
+const MBR_GPT_PROTECTIVE_TYPE: u8 = 0xee;
+
+/// The partition table put in front of the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionType {
+    /// A GUID partition table, with a protective MBR, a primary and a backup header
+    Gpt,
+    /// A classic MBR partition table
+    Mbr,
+}
+
+/// CRC-32 (IEEE), as used by GPT
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0_u32;
+    for byte in bytes {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 0 {
+                crc >> 1
+            } else {
+                (crc >> 1) ^ 0xedb8_8320
+            };
+        }
+    }
+    !crc
+}
+
+/// Writes an MBR with a single partition entry into the first sector of `image`
+fn write_mbr(image: &mut [u8], partition_type: u8, first_lba: u32, sectors: u32) {
+    let entry = &mut image[446..462];
+    entry[0] = 0; // not bootable
+    entry[1..4].copy_from_slice(&[0xff, 0xff, 0xff]); // CHS addressing is not used
+    entry[4] = partition_type;
+    entry[5..8].copy_from_slice(&[0xff, 0xff, 0xff]);
+    entry[8..12].copy_from_slice(&first_lba.to_le_bytes());
+    entry[12..16].copy_from_slice(&sectors.to_le_bytes());
+    image[510] = 0x55;
+    image[511] = 0xaa;
+}
+
+/// Writes a GPT header at `lba`, describing the entry array at `entries_lba`
+fn write_gpt_header(
+    image: &mut [u8],
+    lba: u64,
+    backup_lba: u64,
+    entries_lba: u64,
+    layout: &Layout,
+) {
+    let sectors = (image.len() / SECTOR_SIZE) as u64;
+    let entries_start = entries_lba as usize * SECTOR_SIZE;
+    let entries_crc =
+        crc32(&image[entries_start..entries_start + GPT_ENTRIES_COUNT * GPT_ENTRY_SIZE]);
+
+    let header = &mut image[lba as usize * SECTOR_SIZE..][..GPT_HEADER_SIZE];
+    header[0..8].copy_from_slice(b"EFI PART");
+    header[8..12].copy_from_slice(&0x0001_0000_u32.to_le_bytes());
+    header[12..16].copy_from_slice(&(GPT_HEADER_SIZE as u32).to_le_bytes());
+    header[16..20].fill(0); // the CRC, computed over the header with this field zeroed
+    header[20..24].fill(0);
+    header[24..32].copy_from_slice(&lba.to_le_bytes());
+    header[32..40].copy_from_slice(&backup_lba.to_le_bytes());
+    header[40..48].copy_from_slice(&layout.first_lba().to_le_bytes());
+    header[48..56].copy_from_slice(&(layout.first_lba() + layout.sectors() - 1).to_le_bytes());
+    header[56..72].copy_from_slice(&GPT_DISK_GUID);
+    header[72..80].copy_from_slice(&entries_lba.to_le_bytes());
+    header[80..84].copy_from_slice(&(GPT_ENTRIES_COUNT as u32).to_le_bytes());
+    header[84..88].copy_from_slice(&(GPT_ENTRY_SIZE as u32).to_le_bytes());
+    header[88..92].copy_from_slice(&entries_crc.to_le_bytes());
+    let header_crc = crc32(header);
+    header[16..20].copy_from_slice(&header_crc.to_le_bytes());
+
+    debug_assert!(backup_lba < sectors);
+}
+
+/// Writes a GPT partition entry array at `lba`, with a single partition
+fn write_gpt_entries(image: &mut [u8], lba: u64, layout: &Layout) {
+    let entry = &mut image[lba as usize * SECTOR_SIZE..][..GPT_ENTRY_SIZE];
+    entry[0..16].copy_from_slice(&GPT_LINUX_FS_GUID);
+    entry[16..32].copy_from_slice(&GPT_PARTITION_GUID);
+    entry[32..40].copy_from_slice(&layout.first_lba().to_le_bytes());
+    entry[40..48].copy_from_slice(&(layout.first_lba() + layout.sectors() - 1).to_le_bytes());
+    // the name, in UTF-16LE
+    for (i, c) in "fuzz".encode_utf16().enumerate() {
+        entry[56 + 2 * i..58 + 2 * i].copy_from_slice(&c.to_le_bytes());
+    }
+}
+
+/// Where the input goes in the image
+#[derive(Debug, Clone, Copy)]
+struct Layout {
+    /// The offset of the input in the image
+    data_offset: usize,
+    /// The maximum size of the input, longer inputs are truncated
+    data_capacity: usize,
+}
+
+impl Layout {
+    fn new(image_size: usize, partition_type: Option<PartitionType>) -> Result<Self, Error> {
+        let sectors = image_size / SECTOR_SIZE;
+        // the sectors in front of, and behind the partition
+        let (front, back) = match partition_type {
+            None => (0, 0),
+            Some(PartitionType::Mbr) => (1, 0),
+            // protective MBR, primary header and entries in front, backup entries and header behind
+            Some(PartitionType::Gpt) => (2 + GPT_ENTRIES_SECTORS, GPT_ENTRIES_SECTORS + 1),
+        };
+        if image_size % SECTOR_SIZE != 0 || sectors <= front + back {
+            return Err(Error::illegal_argument(format!(
+                "The image size {image_size} has to be a multiple of {SECTOR_SIZE}, and larger than the partition table"
+            )));
+        }
+        Ok(Self {
+            data_offset: front * SECTOR_SIZE,
+            data_capacity: (sectors - front - back) * SECTOR_SIZE,
+        })
+    }
+
+    fn first_lba(&self) -> u64 {
+        (self.data_offset / SECTOR_SIZE) as u64
+    }
+
+    fn sectors(&self) -> u64 {
+        (self.data_capacity / SECTOR_SIZE) as u64
+    }
+
+    /// An empty image of `image_size`, with the partition table
+    fn image(&self, image_size: usize, partition_type: Option<PartitionType>) -> Vec<u8> {
+        let mut image = vec![0; image_size];
+        let sectors = (image_size / SECTOR_SIZE) as u64;
+        match partition_type {
+            None => (),
+            Some(PartitionType::Mbr) => {
+                let first_lba = u32::try_from(self.first_lba()).unwrap();
+                let len = u32::try_from(self.sectors()).unwrap_or(u32::MAX);
+                write_mbr(&mut image, MBR_LINUX_TYPE, first_lba, len);
+            }
+            Some(PartitionType::Gpt) => {
+                let len = u32::try_from(sectors - 1).unwrap_or(u32::MAX);
+                write_mbr(&mut image, MBR_GPT_PROTECTIVE_TYPE, 1, len);
+
+                let backup_entries_lba = sectors - 1 - GPT_ENTRIES_SECTORS as u64;
+                write_gpt_entries(&mut image, 2, self);
+                write_gpt_entries(&mut image, backup_entries_lba, self);
+                write_gpt_header(&mut image, 1, sectors - 1, 2, self);
+                write_gpt_header(&mut image, sectors - 1, 1, backup_entries_lba, self);
+            }
+        }
+        image
+    }
+}
+
+/// A raw image in a `memfd`, mapped into the fuzzer and shared with QEMU
+#[derive(Debug)]
+struct MemoryImage {
+    fd: OwnedFd,
+    ptr: *mut u8,
+    len: usize,
+}
+
+impl MemoryImage {
+    /// Creates a new image holding `content`
+    fn new(content: &[u8]) -> Result<Self, Error> {
+        let name = CString::new("libafl_virtio_blk").unwrap();
+        let fd = unsafe { libc::memfd_create(name.as_ptr(), libc::MFD_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        let len = content.len();
+        let size = libc::off_t::try_from(len)
+            .map_err(|_| Error::illegal_argument(format!("The image size {len} is too large")))?;
+        if unsafe { libc::ftruncate(fd.as_raw_fd(), size) } < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                fd.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error().into());
+        }
+        let mut image = Self {
+            fd,
+            ptr: ptr.cast(),
+            len,
+        };
+        image.as_mut_slice().copy_from_slice(content);
+        Ok(image)
+    }
+
+    /// The path QEMU opens the image at, valid in this process only
+    fn path(&self) -> String {
+        format!("/proc/self/fd/{}", self.fd.as_raw_fd())
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.ptr, self.len) }
+    }
+}
+
+impl Drop for MemoryImage {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr.cast(), self.len);
+        }
+    }
+}
+
+/// Builds a [`VirtioBlockHelper`]
+#[derive(Debug, Clone)]
+pub struct VirtioBlockHelperBuilder {
+    image_size: usize,
+    partition_type: Option<PartitionType>,
+}
+
+impl VirtioBlockHelperBuilder {
+    /// The size of the block device, a multiple of [`SECTOR_SIZE`]. Defaults to [`DEFAULT_IMAGE_SIZE`].
+    #[must_use]
+    pub fn with_image_size(mut self, image_size: usize) -> Self {
+        self.image_size = image_size;
+        self
+    }
+
+    /// Puts a partition table in front of the input, with a single partition holding it
+    #[must_use]
+    pub fn with_partition_type(mut self, partition_type: PartitionType) -> Self {
+        self.partition_type = Some(partition_type);
+        self
+    }
+
+    /// Creates the image in memory, and the [`VirtioBlockHelper`] copying the inputs into it
+    pub fn build(self) -> Result<VirtioBlockHelper, Error> {
+        let layout = Layout::new(self.image_size, self.partition_type)?;
+        let memory = MemoryImage::new(&layout.image(self.image_size, self.partition_type))?;
+        Ok(VirtioBlockHelper {
+            memory,
+            layout,
+            len: 0,
+        })
+    }
+}
+
+/// Presents each input to the guest as the content of a `virtio-blk` device.
+///
+/// Pass [`VirtioBlockHelper::qemu_args`] to QEMU, to add the device backed by the image in memory.
+#[derive(Debug)]
+pub struct VirtioBlockHelper {
+    memory: MemoryImage,
+    layout: Layout,
+    /// The length of the input currently in the image
+    len: usize,
+}
+
+impl VirtioBlockHelper {
+    /// Starts building a [`VirtioBlockHelper`]
+    #[must_use]
+    pub fn builder() -> VirtioBlockHelperBuilder {
+        VirtioBlockHelperBuilder {
+            image_size: DEFAULT_IMAGE_SIZE,
+            partition_type: None,
+        }
+    }
+
+    /// The arguments adding the block device to QEMU, which has to run in this process
+    #[must_use]
+    pub fn qemu_args(&self) -> Vec<String> {
+        vec![
+            "-drive".to_string(),
+            format!("file={},if=virtio,format=raw", self.memory.path()),
+        ]
+    }
+
+    /// The input currently in the image, possibly truncated
+    #[must_use]
+    pub fn image(&self) -> &[u8] {
+        &self.memory.as_slice()[self.layout.data_offset..][..self.len]
+    }
+
+    /// The maximum size of an input, longer inputs are truncated
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.layout.data_capacity
+    }
+
+    /// Replaces the input in the image
+    pub fn set_image(&mut self, data: &[u8]) {
+        let data = &data[..data.len().min(self.layout.data_capacity)];
+        let region = &mut self.memory.as_mut_slice()[self.layout.data_offset..];
+        region[..data.len()].copy_from_slice(data);
+        // zero what is left of the previous input
+        if self.len > data.len() {
+            region[data.len()..self.len].fill(0);
+        }
+        self.len = data.len();
+    }
+}
+
+impl<S> QemuHelper<S> for VirtioBlockHelper
+where
+    S: UsesInput,
+    S::Input: HasTargetBytes,
+{
+    fn pre_exec(&mut self, _emulator: &Emulator, input: &S::Input) {
+        self.set_image(input.target_bytes().as_slice());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{crc32, Layout, PartitionType, VirtioBlockHelper, SECTOR_SIZE};
+
+    #[test]
+    fn test_virtio_block_layout() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+
+        let size = 128 * SECTOR_SIZE;
+        assert!(Layout::new(size + 1, None).is_err());
+        assert!(Layout::new(SECTOR_SIZE, Some(PartitionType::Mbr)).is_err());
+
+        let mbr = Layout::new(size, Some(PartitionType::Mbr)).unwrap();
+        let image = mbr.image(size, Some(PartitionType::Mbr));
+        assert_eq!(mbr.data_offset, SECTOR_SIZE);
+        assert_eq!(&image[510..512], &[0x55, 0xaa]);
+        assert_eq!(image[446 + 4], 0x83);
+
+        let gpt = Layout::new(size, Some(PartitionType::Gpt)).unwrap();
+        let image = gpt.image(size, Some(PartitionType::Gpt));
+        assert_eq!(gpt.data_offset, 34 * SECTOR_SIZE);
+        assert_eq!(gpt.data_capacity, (128 - 34 - 33) * SECTOR_SIZE);
+        assert_eq!(&image[SECTOR_SIZE..SECTOR_SIZE + 8], b"EFI PART");
+        assert_eq!(
+            &image[size - SECTOR_SIZE..size - SECTOR_SIZE + 8],
+            b"EFI PART"
+        );
+    }
+
+    #[test]
+    fn test_virtio_block_set_image() {
+        let mut helper = VirtioBlockHelper::builder()
+            .with_image_size(64 * SECTOR_SIZE)
+            .with_partition_type(PartitionType::Mbr)
+            .build()
+            .unwrap();
+        helper.set_image(b"abc");
+        helper.set_image(b"x");
+        assert_eq!(helper.image(), b"x");
+
+        // what QEMU reads from the image
+        let path = helper.qemu_args()[1]
+            .strip_prefix("file=")
+            .unwrap()
+            .split(',')
+            .next()
+            .unwrap()
+            .to_string();
+        let image = fs::read(path).unwrap();
+        assert_eq!(image.len(), 64 * SECTOR_SIZE);
+        assert_eq!(&image[510..512], &[0x55, 0xaa]);
+        assert_eq!(&image[SECTOR_SIZE..SECTOR_SIZE + 3], b"x\0\0");
+    }
+}
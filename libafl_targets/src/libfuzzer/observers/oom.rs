@@ -3,7 +3,7 @@ use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
 use libafl::{
     events::EventFirer,
-    executors::ExitKind,
+    executors::{hooks::inprocess::inprocess_report_oom, ExitKind},
     feedbacks::Feedback,
     inputs::UsesInput,
     observers::{Observer, ObserversTuple},
@@ -43,6 +43,11 @@ pub unsafe extern "C" fn __sanitizer_malloc_hook(ptr: *const c_void, size: usize
         if (size > MALLOC_MAX.load(Ordering::Relaxed) || total > RSS_MAX.load(Ordering::Relaxed))
             && !OOMED.swap(true, Ordering::Relaxed)
         {
+            // the oom handler of the executor may decide to ignore it
+            if inprocess_report_oom(size) == ExitKind::Ok {
+                OOMED.store(false, Ordering::Relaxed);
+                return;
+            }
             unsafe {
                 // we need to kill the process in a way that immediately triggers the crash handler
                 libc::raise(SIGABRT);
@@ -0,0 +1,51 @@
+//! Replays the events logged by a `FileEventManager`, reconstructing the campaign from scratch:
+//! its corpus from the reported testcases, its executions, and its stats in a printing monitor.
+//! The corpus is optionally written to a directory afterwards.
+//! The inputs are assumed to be `BytesInput`s.
+
+use std::{env, fs, path::PathBuf, process};
+
+use libafl::{
+    corpus::{Corpus, InMemoryCorpus},
+    events::{replay_event_log, SimpleEventManager},
+    inputs::{BytesInput, Input},
+    state::{HasCorpus, HasExecutions, StdState},
+    Error,
+};
+use libafl_bolts::rands::StdRand;
+
+fn main() -> Result<(), Error> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 || args.len() > 3 {
+        eprintln!("Usage: {} <events dir> [<corpus output dir>]", args[0]);
+        process::exit(1);
+    }
+    let events_dir = PathBuf::from(&args[1]);
+    let corpus_dir = args.get(2).map(PathBuf::from);
+
+    let mut state = StdState::new(
+        StdRand::with_seed(0),
+        InMemoryCorpus::<BytesInput>::new(),
+        InMemoryCorpus::new(),
+        &mut (),
+        &mut (),
+    )?;
+    let mut mgr = SimpleEventManager::printing();
+    let events = replay_event_log(&events_dir, &mut mgr, &mut state)?;
+
+    if let Some(corpus_dir) = &corpus_dir {
+        fs::create_dir_all(corpus_dir)?;
+        for id in state.corpus().ids() {
+            let input = state.corpus().cloned_input_for_id(id)?;
+            input.to_file(corpus_dir.join(input.generate_name(id.into())))?;
+        }
+    }
+
+    println!(
+        "Replayed {events} events from {}: {} testcases, {} executions",
+        events_dir.display(),
+        state.corpus().count(),
+        state.executions()
+    );
+    Ok(())
+}